@@ -6,7 +6,7 @@ use forge_domain::*;
 use forge_infra::ForgeInfra;
 use forge_services::{CommandExecutorService, ForgeServices, Infrastructure};
 use forge_stream::MpscStream;
-use tracing::error;
+use tracing::{error, info_span, Instrument};
 
 pub struct ForgeAPI<F> {
     app: Arc<F>,
@@ -19,10 +19,10 @@ impl<F: Services + Infrastructure> ForgeAPI<F> {
 }
 
 impl ForgeAPI<ForgeServices<ForgeInfra>> {
-    pub fn init(restricted: bool) -> Self {
+    pub fn init(restricted: bool) -> Result<Self> {
         let infra = Arc::new(ForgeInfra::new(restricted));
-        let app = Arc::new(ForgeServices::new(infra));
-        ForgeAPI::new(app)
+        let app = Arc::new(ForgeServices::new(infra)?);
+        Ok(ForgeAPI::new(app))
     }
 }
 
@@ -52,17 +52,32 @@ impl<F: Services + Infrastructure> API for ForgeAPI<F> {
             .unwrap_or_default()
             .expect("conversation for the request should've been created at this point.");
 
-        Ok(MpscStream::spawn(move |tx| async move {
-            let tx = Arc::new(tx);
-
-            let orch = Orchestrator::new(app, conversation, Some(tx.clone()));
-
-            if let Err(err) = orch.dispatch(chat.event).await {
-                if let Err(e) = tx.send(Err(err)).await {
-                    error!("Failed to send error to stream: {:#?}", e);
+        // Everything logged for the turn, by the orchestrator, the tools and the
+        // provider, carries its id
+        let span = info_span!(
+            "turn",
+            turn_id = %chat.turn_id,
+            conversation_id = %chat.conversation_id
+        );
+
+        Ok(MpscStream::spawn_with_cancellation(
+            move |tx, cancellation| {
+                async move {
+                    let tx = Arc::new(tx);
+
+                    let orch = Orchestrator::new(app, conversation, Some(tx.clone()))
+                        .cancellation(cancellation)
+                        .turn_id(chat.turn_id);
+
+                    if let Err(err) = orch.dispatch(chat.event).await {
+                        if let Err(e) = tx.send(Err(err)).await {
+                            error!("Failed to send error to stream: {:#?}", e);
+                        }
+                    }
                 }
-            }
-        }))
+                .instrument(span)
+            },
+        ))
     }
 
     async fn init_conversation<W: Into<Workflow> + Send + Sync>(
@@ -2,3 +2,5 @@ mod forge_api;
 
 pub use forge_api::*;
 pub use forge_domain::*;
+pub use forge_provider::{Cassette, Interaction, Recorder, Replayer};
+pub use forge_stream::MpscStream;
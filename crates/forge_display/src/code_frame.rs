@@ -0,0 +1,139 @@
+use std::fmt;
+
+use console::style;
+use derive_setters::Setters;
+
+use crate::theme::palette;
+
+/// Renders the lines around a location in a file with a caret pointing at the
+/// column, in the style of compiler diagnostics:
+///
+/// ```text
+///  --> src/main.rs:2:21
+///   |
+/// 1 | fn main() {
+/// 2 |     println!("World"
+///   |                     ^
+/// 3 | }
+/// ```
+#[derive(Clone, Setters)]
+#[setters(into, strip_option)]
+pub struct CodeFrame {
+    content: String,
+    /// 1-based line number of the location
+    line: usize,
+    /// 1-based column, in characters, of the location
+    column: usize,
+    path: Option<String>,
+    /// Number of lines shown before and after the location
+    context: usize,
+}
+
+impl CodeFrame {
+    pub fn new(content: impl Into<String>, line: usize, column: usize) -> Self {
+        Self {
+            content: content.into(),
+            line,
+            column,
+            path: None,
+            context: 2,
+        }
+    }
+}
+
+impl fmt::Display for CodeFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines = self.content.lines().collect::<Vec<_>>();
+        let line = self.line.clamp(1, lines.len().max(1));
+        let first = line.saturating_sub(self.context).max(1);
+        let last = (line + self.context).min(lines.len());
+        let width = last.max(line).to_string().len();
+        let gutter = " ".repeat(width);
+        let palette = palette();
+
+        if let Some(path) = &self.path {
+            writeln!(
+                f,
+                "{}{} {}:{}:{}",
+                gutter,
+                style("-->").fg(palette.gutter).bold(),
+                path,
+                line,
+                self.column
+            )?;
+        }
+        writeln!(f, "{} {}", gutter, style("|").fg(palette.gutter).bold())?;
+
+        for number in first..=last {
+            let text = lines.get(number - 1).copied().unwrap_or_default();
+            writeln!(
+                f,
+                "{} {} {}",
+                style(format!("{number:>width$}")).fg(palette.gutter).bold(),
+                style("|").fg(palette.gutter).bold(),
+                text
+            )?;
+
+            if number == line {
+                writeln!(
+                    f,
+                    "{} {} {}{}",
+                    gutter,
+                    style("|").fg(palette.gutter).bold(),
+                    " ".repeat(self.column.saturating_sub(1)),
+                    style("^").fg(palette.error).bold()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use console::strip_ansi_codes;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_code_frame() {
+        let fixture = CodeFrame::new("fn main() {\n    let x = ;\n}\n", 2, 13).path("src/main.rs");
+
+        let actual = strip_ansi_codes(&fixture.to_string()).to_string();
+
+        let expected = [
+            " --> src/main.rs:2:13",
+            "  |",
+            "1 | fn main() {",
+            "2 |     let x = ;",
+            "  |             ^",
+            "3 | }",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_code_frame_context() {
+        let content = (1..=20)
+            .map(|i| format!("line {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let fixture = CodeFrame::new(content, 10, 1).context(1usize);
+
+        let actual = strip_ansi_codes(&fixture.to_string()).to_string();
+
+        let expected = [
+            "   |",
+            " 9 | line 9",
+            "10 | line 10",
+            "   | ^",
+            "11 | line 11",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(actual, expected);
+    }
+}
@@ -1,7 +1,17 @@
 use std::fmt;
+use std::ops::Range;
 
 use console::{style, Style};
-use similar::{ChangeTag, TextDiff};
+use derive_setters::Setters;
+use similar::{ChangeTag, DiffOp, DiffTag, TextDiff};
+
+use crate::theme::palette;
+
+/// Minimum terminal width at which the side-by-side layout is used
+const SIDE_BY_SIDE_MIN_WIDTH: usize = 160;
+
+/// Width taken by the line number and separator columns of each side
+const GUTTER_WIDTH: usize = 7;
 
 struct Line(Option<usize>);
 
@@ -14,12 +24,70 @@ impl fmt::Display for Line {
     }
 }
 
-pub struct DiffFormat;
+/// Formats the difference between two texts. Changed lines are highlighted
+/// with the words that changed emphasized, so single character edits are
+/// easy to spot.
+#[derive(Debug, Clone, Setters)]
+#[setters(strip_option)]
+pub struct DiffFormat {
+    /// Number of unchanged lines shown around each change
+    context: usize,
+    /// Width of the terminal. The old and new texts are shown side by side
+    /// when the terminal is wide enough.
+    width: Option<usize>,
+    /// Titles shown above the hunks, in order, eg: the function that each
+    /// one changes
+    titles: Vec<Option<String>>,
+}
+
+impl Default for DiffFormat {
+    fn default() -> Self {
+        Self { context: 3, width: None, titles: Vec::new() }
+    }
+}
+
+/// A group of changes of a diff, with the unchanged lines shown around it.
+/// Lines are 0-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffHunk {
+    /// Lines of the old text
+    pub old: Range<usize>,
+    /// Lines of the new text
+    pub new: Range<usize>,
+    /// First line of the new text that was changed, or before which lines
+    /// were removed
+    pub changed: usize,
+}
 
 impl DiffFormat {
+    /// Formats the diff with the default settings
     pub fn format(old: &str, new: &str) -> String {
+        Self::default().render(old, new)
+    }
+
+    /// The hunks of the diff, as they are rendered
+    pub fn hunks(&self, old: &str, new: &str) -> Vec<DiffHunk> {
+        TextDiff::from_lines(old, new)
+            .grouped_ops(self.context)
+            .iter()
+            .filter_map(|group| {
+                let (first, last) = (group.first()?, group.last()?);
+                let changed = group
+                    .iter()
+                    .find(|op| op.tag() != DiffTag::Equal)
+                    .unwrap_or(first);
+                Some(DiffHunk {
+                    old: first.old_range().start..last.old_range().end,
+                    new: first.new_range().start..last.new_range().end,
+                    changed: changed.new_range().start,
+                })
+            })
+            .collect()
+    }
+
+    pub fn render(&self, old: &str, new: &str) -> String {
         let diff = TextDiff::from_lines(old, new);
-        let ops = diff.grouped_ops(3);
+        let ops = diff.grouped_ops(self.context);
         let mut output = String::new();
 
         if ops.is_empty() {
@@ -27,42 +95,148 @@ impl DiffFormat {
             return output;
         }
 
+        let side_by_side = self.width.filter(|width| *width >= SIDE_BY_SIDE_MIN_WIDTH);
+
         for (idx, group) in ops.iter().enumerate() {
             if idx > 0 {
                 output.push_str(&format!("{}\n", style("...").dim()));
             }
+            if let Some(Some(title)) = self.titles.get(idx) {
+                output.push_str(&format!("{}\n", style(format!("@@ {title}")).dim()));
+            }
             for op in group {
-                for change in diff.iter_inline_changes(op) {
-                    let (sign, s) = match change.tag() {
-                        ChangeTag::Delete => ("-", Style::new().blue()),
-                        ChangeTag::Insert => ("+", Style::new().yellow()),
-                        ChangeTag::Equal => (" ", Style::new().dim()),
-                    };
-
-                    output.push_str(&format!(
-                        "{}{} |{}",
-                        style(Line(change.old_index())).dim(),
-                        style(Line(change.new_index())).dim(),
-                        s.apply_to(sign),
-                    ));
-
-                    for (_, value) in change.iter_strings_lossy() {
-                        output.push_str(&format!("{}", s.apply_to(value)));
-                    }
-                    if change.missing_newline() {
-                        output.push('\n');
-                    }
+                match side_by_side {
+                    Some(width) => Self::side_by_side(&diff, op, width, &mut output),
+                    None => Self::unified(&diff, op, &mut output),
                 }
             }
         }
         output
     }
+
+    fn style(tag: ChangeTag) -> (&'static str, Style) {
+        match tag {
+            ChangeTag::Delete => ("-", Style::new().fg(palette().removed)),
+            ChangeTag::Insert => ("+", Style::new().fg(palette().added)),
+            ChangeTag::Equal => (" ", Style::new().dim()),
+        }
+    }
+
+    fn unified(diff: &TextDiff<'_, '_, '_, str>, op: &DiffOp, output: &mut String) {
+        for change in diff.iter_inline_changes(op) {
+            let (sign, s) = Self::style(change.tag());
+
+            output.push_str(&format!(
+                "{}{} |{}",
+                style(Line(change.old_index())).dim(),
+                style(Line(change.new_index())).dim(),
+                s.apply_to(sign),
+            ));
+
+            for (emphasized, value) in change.iter_strings_lossy() {
+                let s = if emphasized {
+                    s.clone().bold().underlined()
+                } else {
+                    s.clone()
+                };
+                output.push_str(&format!("{}", s.apply_to(value)));
+            }
+            if change.missing_newline() {
+                output.push('\n');
+            }
+        }
+    }
+
+    fn side_by_side(
+        diff: &TextDiff<'_, '_, '_, str>,
+        op: &DiffOp,
+        width: usize,
+        output: &mut String,
+    ) {
+        let column = width.saturating_sub(2 * GUTTER_WIDTH + 1) / 2;
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+
+        for change in diff.iter_inline_changes(op) {
+            let (_, s) = Self::style(change.tag());
+            let segments = change
+                .iter_strings_lossy()
+                .map(|(emphasized, value)| (emphasized, value.trim_end_matches('\n').to_string()))
+                .collect::<Vec<_>>();
+            let cell = Cell::new(&segments, &s, column);
+
+            match change.tag() {
+                ChangeTag::Delete => left.push((change.old_index(), cell)),
+                ChangeTag::Insert => right.push((change.new_index(), cell)),
+                ChangeTag::Equal => {
+                    // Keep the columns aligned before adding unchanged lines
+                    Self::flush(&mut left, &mut right, column, output);
+                    left.push((change.old_index(), cell.clone()));
+                    right.push((change.new_index(), cell));
+                }
+            }
+        }
+        Self::flush(&mut left, &mut right, column, output);
+    }
+
+    /// Writes the pending rows of both sides next to each other
+    fn flush(
+        left: &mut Vec<(Option<usize>, Cell)>,
+        right: &mut Vec<(Option<usize>, Cell)>,
+        column: usize,
+        output: &mut String,
+    ) {
+        let rows = left.len().max(right.len());
+        let mut left = left.drain(..);
+        let mut right = right.drain(..);
+        for _ in 0..rows {
+            let (old_index, old) = left.next().unwrap_or((None, Cell::default()));
+            let (new_index, new) = right.next().unwrap_or((None, Cell::default()));
+            output.push_str(&format!(
+                "{} | {}{} {} | {}\n",
+                style(Line(old_index)).dim(),
+                old.content,
+                " ".repeat(column.saturating_sub(old.width)),
+                style(Line(new_index)).dim(),
+                new.content,
+            ));
+        }
+    }
+}
+
+/// A styled column of the side-by-side layout, truncated to fit its width
+#[derive(Debug, Clone, Default)]
+struct Cell {
+    content: String,
+    /// Number of visible characters in the content
+    width: usize,
+}
+
+impl Cell {
+    fn new(segments: &[(bool, String)], s: &Style, max_width: usize) -> Self {
+        let mut cell = Cell::default();
+        for (emphasized, value) in segments {
+            let value = value
+                .chars()
+                .take(max_width.saturating_sub(cell.width))
+                .collect::<String>();
+            let s = if *emphasized {
+                s.clone().bold().underlined()
+            } else {
+                s.clone()
+            };
+            cell.width += value.chars().count();
+            cell.content.push_str(&format!("{}", s.apply_to(value)));
+        }
+        cell
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use console::strip_ansi_codes;
     use insta::assert_snapshot;
+    use pretty_assertions::assert_eq;
 
     use super::*;
 
@@ -90,6 +264,75 @@ mod tests {
         assert_snapshot!(clean_diff);
     }
 
+    #[test]
+    fn test_diff_emphasizes_changed_words() {
+        console::set_colors_enabled(true);
+        let old = "let value = 1;\n";
+        let new = "let value = 2;\n";
+        let diff = DiffFormat::format(old, new);
+        let emphasized = Style::new()
+            .yellow()
+            .bold()
+            .underlined()
+            .force_styling(true)
+            .apply_to("2")
+            .to_string();
+        assert!(diff.contains(&emphasized));
+    }
+
+    #[test]
+    fn test_diff_context_lines() {
+        let old = "1\n2\n3\n4\n5\n";
+        let new = "1\n2\nthree\n4\n5\n";
+        let diff = DiffFormat::default().context(0).render(old, new);
+        let actual = strip_ansi_codes(&diff).to_string();
+        let expected = "3        |-3\n    3    |+three\n";
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_diff_hunks() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n";
+        let new = "1\ntwo\n3\n4\n5\n7\n";
+        let fixture = DiffFormat::default().context(1);
+
+        let actual = fixture.hunks(old, new);
+        let expected = vec![
+            DiffHunk { old: 0..3, new: 0..3, changed: 1 },
+            DiffHunk { old: 4..7, new: 4..6, changed: 5 },
+        ];
+        assert_eq!(actual, expected);
+
+        let diff = fixture
+            .titles(vec![None, Some("fn seven".to_string())])
+            .render(old, new);
+        let actual = strip_ansi_codes(&diff).to_string();
+        assert!(actual.contains("...\n@@ fn seven\n"), "{actual}");
+        assert_eq!(actual.matches("@@").count(), 1);
+    }
+
+    #[test]
+    fn test_diff_side_by_side() {
+        let old = "same\nold line\n";
+        let new = "same\nnew line\n";
+        let diff = DiffFormat::default().width(160).render(old, new);
+        let actual = strip_ansi_codes(&diff)
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>();
+        let expected = vec!["1 | same 1 | same", "2 | old line 2 | new line"];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_diff_narrow_terminal_uses_unified_layout() {
+        let old = "old\n";
+        let new = "new\n";
+        let actual = DiffFormat::default().width(80).render(old, new);
+        let expected = DiffFormat::format(old, new);
+        assert_eq!(actual, expected);
+    }
+
     #[test]
     fn test_diff_printer_simple_diff() {
         let old = "line 1\nline 2\nline 3\nline 5\nline 6\nline 7\nline 8\nline 9";
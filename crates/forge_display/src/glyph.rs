@@ -0,0 +1,115 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether decorative glyphs are replaced with plain ASCII markers
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+/// Decorative symbols used in the output, each with an ASCII fallback for
+/// terminals whose font doesn't include them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Glyph {
+    /// Marker in front of titles
+    Bullet,
+    /// Separates the source and target of a change
+    Arrow,
+    /// Input prompt indicator
+    Chevron,
+    /// Marks custom commands
+    Gear,
+    /// Marks the highlighted option of a selection
+    Pointer,
+    ScrollUp,
+    ScrollDown,
+    /// Separates the parts of the status line
+    Dot,
+}
+
+impl Glyph {
+    pub fn as_str(self) -> &'static str {
+        if is_ascii() {
+            self.ascii()
+        } else {
+            self.unicode()
+        }
+    }
+
+    fn unicode(self) -> &'static str {
+        match self {
+            Glyph::Bullet => "⏺",
+            Glyph::Arrow => "→",
+            Glyph::Chevron => "❯",
+            Glyph::Gear => "⚙",
+            Glyph::Pointer => "➤",
+            Glyph::ScrollUp => "⇡",
+            Glyph::ScrollDown => "⇣",
+            Glyph::Dot => "·",
+        }
+    }
+
+    fn ascii(self) -> &'static str {
+        match self {
+            Glyph::Bullet => "*",
+            Glyph::Arrow => "->",
+            Glyph::Chevron => ">",
+            Glyph::Gear => "#",
+            Glyph::Pointer => ">",
+            Glyph::ScrollUp => "^",
+            Glyph::ScrollDown => "v",
+            Glyph::Dot => "-",
+        }
+    }
+}
+
+impl fmt::Display for Glyph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Frames of the progress spinner
+pub fn spinner_frames() -> &'static [&'static str] {
+    if is_ascii() {
+        &["|", "/", "-", "\\"]
+    } else {
+        &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]
+    }
+}
+
+/// Returns true if decorative glyphs are replaced with ASCII markers
+pub fn is_ascii() -> bool {
+    ASCII.load(Ordering::Relaxed)
+}
+
+/// Switches all subsequent output between decorative glyphs and ASCII markers
+pub fn set_ascii(ascii: bool) {
+    ASCII.store(ascii, Ordering::Relaxed);
+}
+
+/// Returns true if the terminal is known to lack the glyphs, such as the
+/// Linux virtual console
+pub fn detect_ascii() -> bool {
+    std::env::var("TERM").is_ok_and(|term| term == "linux" || term == "dumb")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii_glyphs_are_ascii() {
+        let glyphs = [
+            Glyph::Bullet,
+            Glyph::Arrow,
+            Glyph::Chevron,
+            Glyph::Gear,
+            Glyph::Pointer,
+            Glyph::ScrollUp,
+            Glyph::ScrollDown,
+            Glyph::Dot,
+        ];
+
+        let actual = glyphs.iter().all(|glyph| glyph.ascii().is_ascii());
+
+        assert!(actual);
+    }
+}
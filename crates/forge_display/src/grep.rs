@@ -4,6 +4,8 @@ use console::style;
 use derive_setters::Setters;
 use regex::Regex;
 
+use crate::theme::palette;
+
 /// RipGrepFormatter formats search results in ripgrep-like style.
 #[derive(Clone, Setters)]
 #[setters(into, strip_option)]
@@ -91,7 +93,9 @@ impl GrepFormat {
                     format!(
                         "{}{}{}",
                         &content[..mat.start()],
-                        style(&content[mat.start()..mat.end()]).yellow().bold(),
+                        style(&content[mat.start()..mat.end()])
+                            .fg(palette().accent)
+                            .bold(),
                         &content[mat.end()..]
                     )
                 },
@@ -109,7 +113,7 @@ impl GrepFormat {
         group: Vec<(&str, &str)>,
         max_num_width: usize,
     ) -> String {
-        let file_header = style(path).cyan();
+        let file_header = style(path).fg(palette().info);
         let formatted_lines = group
             .into_iter()
             .map(|(num, content)| self.format_line(num, content, max_num_width))
@@ -123,7 +127,7 @@ impl GrepFormat {
         let formatted_paths: Vec<_> = self
             .lines
             .iter()
-            .map(|line| format!("{}", style(line).cyan()))
+            .map(|line| format!("{}", style(line).fg(palette().info)))
             .collect();
 
         // Join with newlines
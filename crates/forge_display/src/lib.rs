@@ -1,9 +1,19 @@
+pub mod code_frame;
 pub mod diff;
+pub mod glyph;
 pub mod grep;
 pub mod markdown;
+pub mod renderer;
+pub mod table;
+pub mod theme;
 pub mod title;
 
-pub use diff::DiffFormat;
+pub use code_frame::CodeFrame;
+pub use diff::{DiffFormat, DiffHunk};
+pub use glyph::Glyph;
 pub use grep::GrepFormat;
 pub use markdown::MarkdownFormat;
+pub use renderer::{ProgressFormat, RendererRegistry, ToolOutput, ToolRenderer, TreeFormat};
+pub use table::{Align, TableFormat};
+pub use theme::{palette, set_palette, Palette};
 pub use title::*;
@@ -3,6 +3,8 @@ use regex::Regex;
 use termimad::crossterm::style::{Attribute, Color};
 use termimad::{CompoundStyle, LineStyle, MadSkin};
 
+use crate::theme::{ansi256, palette};
+
 /// MarkdownFormat provides functionality for formatting markdown text for
 /// terminal display.
 #[derive(Clone, Setters, Default)]
@@ -13,10 +15,17 @@ pub struct MarkdownFormat {
 }
 
 impl MarkdownFormat {
-    /// Create a new MarkdownFormat with the default skin
+    /// Create a new MarkdownFormat with the default skin, colored with the
+    /// current palette
     pub fn new() -> Self {
+        let palette = palette();
         let mut skin = MadSkin::default();
-        let compound_style = CompoundStyle::new(Some(Color::Cyan), None, Attribute::Bold.into());
+        skin.set_headers_fg(Color::AnsiValue(ansi256(palette.accent)));
+        let compound_style = CompoundStyle::new(
+            Some(Color::AnsiValue(ansi256(palette.info))),
+            None,
+            Attribute::Bold.into(),
+        );
         skin.inline_code = compound_style.clone();
 
         let mut codeblock_style = CompoundStyle::new(None, None, Default::default());
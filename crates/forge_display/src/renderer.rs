@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+
+use console::style;
+
+use crate::theme::palette;
+
+/// Output of a tool, split into the fields of its front matter and its body
+///
+/// ```text
+/// ---
+/// path: /src/main.rs
+/// ---
+/// fn main() {}
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolOutput<'a> {
+    pub fields: Vec<(&'a str, &'a str)>,
+    pub body: &'a str,
+}
+
+impl<'a> ToolOutput<'a> {
+    pub fn parse(content: &'a str) -> Self {
+        let front_matter = content
+            .strip_prefix("---\n")
+            .and_then(|rest| rest.split_once("\n---\n").or(rest.split_once("---\n")));
+
+        match front_matter {
+            Some((header, body)) => Self {
+                fields: header
+                    .lines()
+                    .filter_map(|line| line.split_once(':'))
+                    .map(|(key, value)| (key.trim(), value.trim()))
+                    .collect(),
+                body,
+            },
+            None => Self { fields: Vec::new(), body: content },
+        }
+    }
+
+    /// Value of a field of the front matter
+    pub fn field(&self, name: &str) -> Option<&'a str> {
+        self.fields
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+impl Display for ToolOutput<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let muted = palette().muted;
+        for (key, value) in &self.fields {
+            writeln!(f, "{} {}", style(format!("{key}:")).fg(muted), value)?;
+        }
+        write!(f, "{}", self.body.trim_end())
+    }
+}
+
+/// Renders the output of a tool for the terminal. Returning `None` falls back
+/// to the default rendering.
+pub trait ToolRenderer: Send + Sync {
+    fn render(&self, output: &ToolOutput) -> Option<String>;
+}
+
+impl<F> ToolRenderer for F
+where
+    F: Fn(&ToolOutput) -> Option<String> + Send + Sync,
+{
+    fn render(&self, output: &ToolOutput) -> Option<String> {
+        self(output)
+    }
+}
+
+/// Renderers for the output of tools by the name of the tool, so that
+/// embedders and plugins can display their tools' output as tables (see
+/// [`TableFormat`](crate::TableFormat)), trees or progress instead of raw front
+/// matter
+#[derive(Clone, Default)]
+pub struct RendererRegistry {
+    renderers: HashMap<String, Arc<dyn ToolRenderer>>,
+}
+
+impl RendererRegistry {
+    /// Registers the renderer of a tool, replacing any previous one
+    pub fn register(&mut self, tool: impl Into<String>, renderer: impl ToolRenderer + 'static) {
+        self.renderers.insert(tool.into(), Arc::new(renderer));
+    }
+
+    pub fn contains(&self, tool: &str) -> bool {
+        self.renderers.contains_key(tool)
+    }
+
+    /// Renders the output of a tool with its renderer, or with the default
+    /// rendering of its front matter and body
+    pub fn render(&self, tool: &str, content: &str) -> String {
+        let output = ToolOutput::parse(content);
+        self.renderers
+            .get(tool)
+            .and_then(|renderer| renderer.render(&output))
+            .unwrap_or_else(|| output.to_string())
+    }
+}
+
+/// Tree of paths, eg: the files a tool found
+#[derive(Debug, Clone, Default)]
+pub struct TreeFormat {
+    paths: Vec<String>,
+}
+
+#[derive(Default)]
+struct Node {
+    children: Vec<(String, Node)>,
+}
+
+impl Node {
+    fn insert<'a>(&mut self, mut parts: impl Iterator<Item = &'a str>) {
+        if let Some(part) = parts.next() {
+            let index = match self.children.iter().position(|(name, _)| name == part) {
+                Some(index) => index,
+                None => {
+                    self.children.push((part.to_string(), Node::default()));
+                    self.children.len() - 1
+                }
+            };
+            self.children[index].1.insert(parts);
+        }
+    }
+
+    fn write(&self, f: &mut Formatter<'_>, prefix: &str) -> fmt::Result {
+        let gutter = palette().gutter;
+        for (index, (name, node)) in self.children.iter().enumerate() {
+            let last = index == self.children.len() - 1;
+            let branch = if last { "└── " } else { "├── " };
+            write!(
+                f,
+                "\n{}{}{}",
+                style(prefix).fg(gutter),
+                style(branch).fg(gutter),
+                name
+            )?;
+            let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            node.write(f, &prefix)?;
+        }
+        Ok(())
+    }
+}
+
+impl TreeFormat {
+    pub fn new<I: IntoIterator<Item = S>, S: ToString>(paths: I) -> Self {
+        Self {
+            paths: paths.into_iter().map(|path| path.to_string()).collect(),
+        }
+    }
+}
+
+impl Display for TreeFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut root = Node::default();
+        for path in &self.paths {
+            root.insert(path.split('/').filter(|part| !part.is_empty()));
+        }
+        write!(f, ".")?;
+        root.write(f, "")
+    }
+}
+
+/// Progress bar, eg: `[#####-----] 5/10`
+#[derive(Debug, Clone)]
+pub struct ProgressFormat {
+    done: u64,
+    total: u64,
+    width: usize,
+    percent: bool,
+}
+
+impl ProgressFormat {
+    pub fn new(done: u64, total: u64) -> Self {
+        Self { done: done.min(total), total, width: 20, percent: false }
+    }
+
+    /// Progress bar of a fraction between 0 and 1, eg: `[##--------] 20%`
+    pub fn fraction(fraction: f64) -> Self {
+        let done = (fraction.clamp(0.0, 1.0) * 100.0).round() as u64;
+        Self { percent: true, ..Self::new(done, 100) }
+    }
+
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+}
+
+impl Display for ProgressFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let filled = (self.done * self.width as u64)
+            .checked_div(self.total)
+            .unwrap_or(self.width as u64) as usize;
+        write!(
+            f,
+            "[{}{}] ",
+            style("#".repeat(filled)).fg(palette().accent),
+            style("-".repeat(self.width - filled)).fg(palette().muted),
+        )?;
+        if self.percent {
+            write!(f, "{}%", self.done)
+        } else {
+            write!(f, "{}/{}", self.done, self.total)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn strip(content: impl ToString) -> String {
+        strip_ansi_escapes::strip_str(content.to_string())
+    }
+
+    #[test]
+    fn test_parse_front_matter() {
+        let fixture = "---\npath: /src/main.rs\ntotal_chars: 12\n---\nfn main() {}\n";
+
+        let actual = ToolOutput::parse(fixture);
+
+        let expected = ToolOutput {
+            fields: vec![("path", "/src/main.rs"), ("total_chars", "12")],
+            body: "fn main() {}\n",
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_parse_without_front_matter() {
+        let actual = ToolOutput::parse("Hello");
+        assert_eq!(actual, ToolOutput { fields: vec![], body: "Hello" });
+    }
+
+    #[test]
+    fn test_registry_uses_registered_renderer() {
+        let mut registry = RendererRegistry::default();
+        registry.register("deploy", |output: &ToolOutput| {
+            output
+                .field("status")
+                .map(|status| format!("Deploy {status}"))
+        });
+
+        let actual = [
+            registry.render("deploy", "---\nstatus: done\n---\n"),
+            strip(registry.render("deploy", "---\nregion: eu\n---\nlog")),
+            strip(registry.render("other", "---\nstatus: done\n---\nlog")),
+        ];
+
+        let expected = [
+            "Deploy done".to_string(),
+            "region: eu\nlog".to_string(),
+            "status: done\nlog".to_string(),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_tree() {
+        let fixture = TreeFormat::new(["src/main.rs", "src/ui/mod.rs", "README.md"]);
+
+        let actual = strip(fixture);
+
+        let expected = ".\n├── src\n│   ├── main.rs\n│   └── ui\n│       └── mod.rs\n└── README.md";
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_progress() {
+        let actual = [
+            strip(ProgressFormat::new(5, 10).width(10)),
+            strip(ProgressFormat::new(0, 0).width(4)),
+            strip(ProgressFormat::fraction(0.42).width(10)),
+        ];
+
+        let expected = [
+            "[#####-----] 5/10".to_string(),
+            "[####] 0/0".to_string(),
+            "[####------] 42%".to_string(),
+        ];
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,203 @@
+use std::fmt::{self, Display, Formatter};
+
+use console::style;
+
+/// Alignment of the cells of a column
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Align {
+    #[default]
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Column {
+    header: String,
+    align: Align,
+    max_width: Option<usize>,
+}
+
+/// Table with columns sized to their widest cell, for tabular output such as
+/// search results, test summaries and usage reports
+#[derive(Debug, Clone, Default)]
+pub struct TableFormat {
+    columns: Vec<Column>,
+    rows: Vec<Vec<String>>,
+}
+
+impl TableFormat {
+    pub fn new<I: IntoIterator<Item = S>, S: ToString>(headers: I) -> Self {
+        Self {
+            columns: headers
+                .into_iter()
+                .map(|header| Column { header: header.to_string(), ..Default::default() })
+                .collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn row<I: IntoIterator<Item = S>, S: ToString>(mut self, row: I) -> Self {
+        self.push(row);
+        self
+    }
+
+    pub fn push<I: IntoIterator<Item = S>, S: ToString>(&mut self, row: I) {
+        self.rows
+            .push(row.into_iter().map(|cell| cell.to_string()).collect());
+    }
+
+    /// Sets the alignment of a column, eg: right for numbers
+    pub fn align(mut self, column: usize, align: Align) -> Self {
+        if let Some(column) = self.columns.get_mut(column) {
+            column.align = align;
+        }
+        self
+    }
+
+    /// Truncates the cells of a column that are longer than `max_width`
+    /// characters when displayed. CSV exports aren't truncated.
+    pub fn max_width(mut self, column: usize, max_width: usize) -> Self {
+        if let Some(column) = self.columns.get_mut(column) {
+            column.max_width = Some(max_width.max(1));
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Exports the table as CSV, quoting the cells that need it
+    pub fn to_csv(&self) -> String {
+        let line = |cells: &mut dyn Iterator<Item = &str>| {
+            cells
+                .map(|cell| {
+                    if cell.contains([',', '"', '\n', '\r']) {
+                        format!("\"{}\"", cell.replace('"', "\"\""))
+                    } else {
+                        cell.to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        let mut csv = line(&mut self.columns.iter().map(|column| column.header.as_str()));
+        csv.push('\n');
+        for row in &self.rows {
+            csv.push_str(&line(&mut row.iter().map(String::as_str)));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    fn cell(&self, column: usize, content: &str) -> String {
+        match self.columns.get(column).and_then(|column| column.max_width) {
+            Some(max_width) if content.chars().count() > max_width => {
+                let mut cell = content.chars().take(max_width - 1).collect::<String>();
+                cell.push('…');
+                cell
+            }
+            _ => content.to_string(),
+        }
+    }
+}
+
+impl Display for TableFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let headers = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| self.cell(index, &column.header))
+            .collect::<Vec<_>>();
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .map(|(index, cell)| self.cell(index, cell))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut widths = Vec::new();
+        for cells in std::iter::once(&headers).chain(&rows) {
+            for (index, cell) in cells.iter().enumerate() {
+                let width = cell.chars().count();
+                match widths.get_mut(index) {
+                    Some(current) => *current = width.max(*current),
+                    None => widths.push(width),
+                }
+            }
+        }
+
+        let line = |cells: &[String]| {
+            cells
+                .iter()
+                .enumerate()
+                .map(|(index, cell)| {
+                    let width = widths[index];
+                    match self.columns.get(index).map(|column| column.align) {
+                        Some(Align::Right) => format!("{cell:>width$}"),
+                        _ => format!("{cell:<width$}"),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        };
+
+        write!(f, "{}", style(line(&headers)).bold())?;
+        for row in &rows {
+            write!(f, "\n{}", line(row))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn fixture() -> TableFormat {
+        TableFormat::new(["Name", "Status", "Tokens"])
+            .row(["api", "running", "1200"])
+            .row(["database-primary", "stopped, \"manually\"", "35"])
+            .align(2, Align::Right)
+    }
+
+    #[test]
+    fn test_table() {
+        let actual = strip_ansi_escapes::strip_str(fixture().to_string());
+
+        let expected = [
+            "Name              Status               Tokens",
+            "api               running                1200",
+            "database-primary  stopped, \"manually\"      35",
+        ]
+        .join("\n");
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_table_truncates_cells() {
+        let fixture = fixture().max_width(0, 8);
+
+        let actual = strip_ansi_escapes::strip_str(fixture.to_string());
+
+        assert!(actual.contains("\ndatabas…  stopped"));
+    }
+
+    #[test]
+    fn test_table_to_csv() {
+        let actual = fixture().max_width(0, 8).to_csv();
+
+        let expected = "Name,Status,Tokens\napi,running,1200\ndatabase-primary,\"stopped, \"\"manually\"\"\",35\n";
+        assert_eq!(actual, expected);
+    }
+}
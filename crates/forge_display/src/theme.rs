@@ -0,0 +1,143 @@
+use std::sync::RwLock;
+
+use console::Color;
+
+/// Palette used by every formatter in this crate. It's global because the
+/// formatted values are created all over the codebase and would otherwise
+/// need the palette threaded through each of them.
+static PALETTE: RwLock<Palette> = RwLock::new(Palette::DARK);
+
+/// Colors used for each kind of output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    /// Regular text, such as titles
+    pub text: Color,
+    /// Actions, completions and search matches
+    pub accent: Color,
+    /// Debug messages, paths and inline code
+    pub info: Color,
+    /// Git branch and prompt indicator
+    pub success: Color,
+    pub error: Color,
+    /// Lines added in diffs
+    pub added: Color,
+    /// Lines removed in diffs
+    pub removed: Color,
+    /// Line numbers and separators of code frames
+    pub gutter: Color,
+    /// Secondary information such as the status line
+    pub muted: Color,
+}
+
+impl Palette {
+    pub const DARK: Self = Self {
+        text: Color::White,
+        accent: Color::Yellow,
+        info: Color::Cyan,
+        success: Color::Color256(10),
+        error: Color::Red,
+        added: Color::Yellow,
+        removed: Color::Blue,
+        gutter: Color::Blue,
+        muted: Color::Color256(8),
+    };
+
+    pub const LIGHT: Self = Self {
+        text: Color::Black,
+        accent: Color::Color256(130),
+        info: Color::Color256(25),
+        success: Color::Color256(28),
+        error: Color::Color256(124),
+        added: Color::Color256(28),
+        removed: Color::Color256(124),
+        gutter: Color::Color256(25),
+        muted: Color::Color256(245),
+    };
+
+    pub const SOLARIZED: Self = Self {
+        text: Color::Color256(244),
+        accent: Color::Color256(136),
+        info: Color::Color256(37),
+        success: Color::Color256(64),
+        error: Color::Color256(160),
+        added: Color::Color256(64),
+        removed: Color::Color256(160),
+        gutter: Color::Color256(33),
+        muted: Color::Color256(240),
+    };
+
+    /// Picks the light or dark palette based on the background of the
+    /// terminal, falling back to dark when it can't be detected
+    pub fn detect() -> Self {
+        let light = std::env::var("COLORFGBG")
+            .ok()
+            .and_then(|value| is_light_background(&value))
+            .unwrap_or(false);
+        if light {
+            Self::LIGHT
+        } else {
+            Self::DARK
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::DARK
+    }
+}
+
+/// Returns the palette currently used for output
+pub fn palette() -> Palette {
+    *PALETTE.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Changes the palette used for all subsequent output
+pub fn set_palette(palette: Palette) {
+    *PALETTE.write().unwrap_or_else(|e| e.into_inner()) = palette;
+}
+
+/// Converts a color to its ANSI 256 color number, for use with other terminal
+/// styling libraries
+pub fn ansi256(color: Color) -> u8 {
+    match color {
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::White => 7,
+        Color::Color256(value) => value,
+    }
+}
+
+/// Parses the `COLORFGBG` variable set by some terminals, which has the form
+/// `fg;bg` or `fg;default;bg`
+fn is_light_background(colorfgbg: &str) -> Option<bool> {
+    let background = colorfgbg.rsplit(';').next()?.parse::<u8>().ok()?;
+    Some(matches!(background, 7 | 9..=15))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_is_light_background() {
+        let fixture = ["15;0", "0;15", "0;default;7", "default", ""];
+        let actual = fixture.map(is_light_background);
+        let expected = [Some(false), Some(true), Some(true), None, None];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_ansi256() {
+        let actual = [Color::Cyan, Color::Color256(130)].map(ansi256);
+        let expected = [6, 130];
+        assert_eq!(actual, expected);
+    }
+}
@@ -1,8 +1,11 @@
 use std::fmt::{self, Display, Formatter};
 
-use colored::Colorize;
+use console::style;
 use derive_setters::Setters;
 
+use crate::theme::palette;
+use crate::Glyph;
+
 #[derive(Clone)]
 pub enum Category {
     Action,
@@ -77,14 +80,15 @@ impl TitleFormat {
     }
 
     fn format(&self) -> String {
+        let palette = palette();
         let mut buf = String::new();
 
         let icon = match self.category {
-            Category::Action => "⏺".yellow(),
-            Category::Info => "⏺".white(),
-            Category::Debug => "⏺".cyan(),
-            Category::Error => "⏺".red(),
-            Category::Completion => "⏺".yellow(),
+            Category::Action => style(Glyph::Bullet).fg(palette.accent),
+            Category::Info => style(Glyph::Bullet).fg(palette.text),
+            Category::Debug => style(Glyph::Bullet).fg(palette.info),
+            Category::Error => style(Glyph::Bullet).fg(palette.error),
+            Category::Completion => style(Glyph::Bullet).fg(palette.accent),
         };
 
         buf.push_str(format!("{icon} ").as_str());
@@ -95,25 +99,30 @@ impl TitleFormat {
             use chrono::Local;
 
             buf.push_str(
-                format!("[{}] ", Local::now().format("%H:%M:%S.%3f"))
-                    .dimmed()
+                style(format!("[{}] ", Local::now().format("%H:%M:%S.%3f")))
+                    .dim()
                     .to_string()
                     .as_str(),
             );
         }
 
         let title = match self.category {
-            Category::Action => self.title.white(),
-            Category::Info => self.title.white(),
-            Category::Debug => self.title.dimmed(),
-            Category::Error => format!("{} {}", "ERROR:".bold(), self.title).red(),
-            Category::Completion => self.title.white().bold(),
+            Category::Action => style(self.title.clone()).fg(palette.text),
+            Category::Info => style(self.title.clone()).fg(palette.text),
+            Category::Debug => style(self.title.clone()).dim(),
+            Category::Error => style(format!(
+                "{} {}",
+                style("ERROR:").fg(palette.error).bold(),
+                self.title
+            ))
+            .fg(palette.error),
+            Category::Completion => style(self.title.clone()).fg(palette.text).bold(),
         };
 
         buf.push_str(title.to_string().as_str());
 
         if let Some(ref sub_title) = self.sub_title {
-            buf.push_str(&format!(" {}", sub_title.dimmed()).to_string());
+            buf.push_str(&format!(" {}", style(sub_title).dim()).to_string());
         }
 
         buf
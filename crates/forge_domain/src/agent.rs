@@ -11,8 +11,9 @@ use crate::merge::Key;
 use crate::temperature::Temperature;
 use crate::template::Template;
 use crate::{
-    Context, Error, Event, EventContext, ModelId, Result, Role, SystemContext, ToolDefinition,
-    ToolName,
+    Consensus, Context, Error, Event, EventContext, GitPolicy, ModelId, Moderation, NamedTool,
+    Parameters, ResponseSchema, Result, Role, SystemContext, ToolDefinition, ToolName,
+    ToolOverride, ToolSlimming,
 };
 
 // Unique identifier for an agent
@@ -79,6 +80,13 @@ pub struct Compact {
     #[merge(strategy = crate::merge::std::overwrite)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub summary_tag: Option<SummaryTag>,
+
+    /// Number of the most salient turns of the compacted messages that are
+    /// kept verbatim instead of being summarized, eg: the ones that worked on
+    /// files that are still being changed or hit errors that weren't resolved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub retained_turns: Option<usize>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -110,6 +118,7 @@ impl Compact {
             summary_tag: None,
             model,
             retention_window: 0,
+            retained_turns: None,
         }
     }
 
@@ -232,11 +241,59 @@ pub struct Agent {
     #[merge(strategy = crate::merge::option)]
     pub compact: Option<Compact>,
 
+    /// Configuration for consensus mode, where risky edits need to be
+    /// independently proposed by a second model before being applied
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub consensus: Option<Consensus>,
+
+    /// JSON schema of the agent's final response. When set, the agent is asked
+    /// for a response conforming to the schema after completing its task, and
+    /// the validated response is stored in the `<agent-id>_output` variable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub response_schema: Option<ResponseSchema>,
+
     /// A set of custom rules that the agent should follow
     #[serde(skip_serializing_if = "Option::is_none")]
     #[merge(strategy = crate::merge::option)]
     pub custom_rules: Option<String>,
 
+    /// Naming policy of the commits and branches that the agent creates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub git_policy: Option<GitPolicy>,
+
+    /// Reduced tool set for models with a small context or without native
+    /// tool calling
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub tool_slimming: Option<ToolSlimming>,
+
+    /// Descriptions of tools and their parameters that replace the built-in
+    /// ones
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub tool_overrides: Option<Vec<ToolOverride>>,
+
+    /// Moderation of the content that the agent writes to files or executes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub moderation: Option<Moderation>,
+
+    /// Simulates the tools that change files or run commands: their diffs and
+    /// commands are reported, but nothing is written or executed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub dry_run: Option<bool>,
+
+    /// Queues the patches whose search text doesn't match for the user to
+    /// resolve at the end of the task, instead of failing the tool call, so
+    /// that long batches of edits keep moving
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub defer_patch_conflicts: Option<bool>,
+
     /// Temperature used for agent
     ///
     /// Temperature controls the randomness in the model's output.
@@ -280,7 +337,15 @@ impl Agent {
             max_turns: None,
             max_walker_depth: None,
             compact: None,
+            consensus: None,
+            response_schema: None,
             custom_rules: None,
+            git_policy: None,
+            tool_slimming: None,
+            tool_overrides: None,
+            moderation: None,
+            dry_run: None,
+            defer_patch_conflicts: None,
             hide_content: None,
             temperature: None,
         }
@@ -303,6 +368,75 @@ impl Agent {
         }
     }
 
+    /// Adapts the agent to the capabilities reported by the model's server and
+    /// returns a warning for every configured behaviour that had to be
+    /// degraded
+    pub fn adapt(&mut self, parameters: &Parameters) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let model = self.model.as_ref().map(ModelId::as_str).unwrap_or_default();
+
+        match self.tool_supported {
+            // Tool calls are described in the system prompt when the server
+            // can't handle them natively
+            Some(true) if !parameters.tool_supported => {
+                self.tool_supported = Some(false);
+                warnings.push(format!(
+                    "The server for {model} doesn't support native tool calling, tools will be described in the prompt instead"
+                ));
+            }
+            None => self.tool_supported = Some(parameters.tool_supported),
+            _ => {}
+        }
+
+        if let (Some(context_length), Some(compact)) =
+            (parameters.context_length, &mut self.compact)
+        {
+            // Leave room for the response and the tokens that were estimated short
+            let limit = context_length * 3 / 4;
+            if compact
+                .token_threshold
+                .is_none_or(|threshold| threshold > limit)
+            {
+                compact.token_threshold = Some(limit);
+                warnings.push(format!(
+                    "The server for {model} accepts at most {context_length} tokens, compacting the context above {limit} tokens"
+                ));
+            }
+        }
+
+        warnings
+    }
+
+    /// Decides whether the tools are slimmed for the model's server, returning
+    /// a warning when they are. The decision is kept, so that the tool set
+    /// doesn't change between turns.
+    pub fn adapt_tools(&mut self, parameters: &Parameters) -> Option<String> {
+        let model = self.model.as_ref().map(ModelId::as_str).unwrap_or_default();
+        let slimming = self.tool_slimming.get_or_insert_with(Default::default);
+        if slimming.enabled.is_some() {
+            return None;
+        }
+
+        let slim = slimming.should_slim(parameters);
+        slimming.enabled = Some(slim);
+        slim.then(|| format!(
+            "The server for {model} has limited tool support or a small context, only the core tools are sent and the others are listed on demand"
+        ))
+    }
+
+    /// Slimming of the tools, when it applies to the agent
+    pub fn active_tool_slimming(&self) -> Option<&ToolSlimming> {
+        self.tool_slimming
+            .as_ref()
+            .filter(|slimming| slimming.enabled.unwrap_or_default())
+    }
+
+    /// Whether the agent may call the tool, ie: it was given the tool, or the
+    /// tool lists the tools that slimming left out
+    pub fn allows_tool(&self, name: &ToolName) -> bool {
+        *name == ToolSlimming::tool_name() || self.tools.iter().flatten().any(|tool| tool == name)
+    }
+
     pub async fn init_context(&self, mut forge_tools: Vec<ToolDefinition>) -> Result<Context> {
         let allowed = self.tools.iter().flatten().collect::<HashSet<_>>();
 
@@ -311,7 +445,7 @@ impl Agent {
 
         let tool_defs = forge_tools
             .into_iter()
-            .filter(|tool| allowed.contains(&tool.name))
+            .filter(|tool| allowed.contains(&tool.name) || tool.name == ToolSlimming::tool_name())
             .collect::<Vec<_>>();
 
         // Use the agent's tool_supported flag directly instead of querying the provider
@@ -379,6 +513,53 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_adapt_falls_back_to_prompted_tool_calls() {
+        let mut fixture = Agent::new("agent")
+            .model(ModelId::new("qwen"))
+            .tool_supported(true);
+
+        let warnings = fixture.adapt(&Parameters::new(false));
+
+        assert_eq!(fixture.tool_supported, Some(false));
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_adapt_uses_native_tool_calls_when_not_configured() {
+        let mut fixture = Agent::new("agent").model(ModelId::new("qwen"));
+
+        let warnings = fixture.adapt(&Parameters::new(true));
+
+        assert_eq!(fixture.tool_supported, Some(true));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_adapt_lowers_compaction_threshold() {
+        let mut fixture = Agent::new("agent")
+            .model(ModelId::new("qwen"))
+            .compact(Compact::new(ModelId::new("qwen")).token_threshold(100_000u64));
+
+        let warnings = fixture.adapt(&Parameters::new(true).context_length(8192u64));
+
+        let actual = fixture.compact.and_then(|compact| compact.token_threshold);
+        assert_eq!(actual, Some(6144));
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_adapt_tools_slims_once() {
+        let mut fixture = Agent::new("agent").model(ModelId::new("qwen"));
+
+        let first = fixture.adapt_tools(&Parameters::new(true).context_length(8192u64));
+        let second = fixture.adapt_tools(&Parameters::new(true).context_length(8192u64));
+
+        assert!(first.is_some());
+        assert_eq!(second, None);
+        assert!(fixture.active_tool_slimming().is_some());
+    }
+
     #[test]
     fn test_merge_model() {
         // Base has a value, should not be overwritten
@@ -433,6 +614,20 @@ mod tests {
         assert_eq!(base.ephemeral, Some(false));
     }
 
+    #[test]
+    fn test_allows_tool() {
+        let fixture = Agent::new("forge").tools(vec![ToolName::new("forge_tool_fs_read")]);
+
+        let actual = [
+            fixture.allows_tool(&ToolName::new("forge_tool_fs_read")),
+            fixture.allows_tool(&ToolName::new("forge_tool_process_shell")),
+            fixture.allows_tool(&ToolSlimming::tool_name()),
+        ];
+
+        let expected = [true, false, true];
+        assert_eq!(actual, expected);
+    }
+
     #[test]
     fn test_merge_tools() {
         // Base has no value, should take other's values
@@ -0,0 +1,262 @@
+use std::fmt;
+
+use derive_setters::Setters;
+use serde::{Deserialize, Serialize};
+
+/// Classification of a single change in the release notes. The variants are
+/// ordered by how prominently they should appear in the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Breaking,
+    Feature,
+    Fix,
+    Other,
+}
+
+impl ChangeKind {
+    /// Human readable heading used for the section in the release notes
+    pub fn title(&self) -> &'static str {
+        match self {
+            ChangeKind::Breaking => "Breaking Changes",
+            ChangeKind::Feature => "Features",
+            ChangeKind::Fix => "Bug Fixes",
+            ChangeKind::Other => "Other Changes",
+        }
+    }
+
+    fn from_type(commit_type: &str) -> Self {
+        match commit_type.to_lowercase().as_str() {
+            "feat" | "feature" => ChangeKind::Feature,
+            "fix" | "bugfix" | "hotfix" => ChangeKind::Fix,
+            _ => ChangeKind::Other,
+        }
+    }
+}
+
+/// A single commit included in the changelog
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Setters)]
+#[setters(strip_option, into)]
+pub struct ChangelogEntry {
+    /// Abbreviated commit hash
+    pub hash: String,
+    pub kind: ChangeKind,
+    /// Optional conventional-commit scope, eg: `fix(ui): ...` has scope `ui`
+    pub scope: Option<String>,
+    pub summary: String,
+    /// Pull request number referenced in the subject, eg: `(#123)`
+    pub pr: Option<u64>,
+}
+
+impl ChangelogEntry {
+    /// Parses a commit into an entry following the conventional commit
+    /// specification. Subjects that don't follow the specification are
+    /// classified as [`ChangeKind::Other`] and kept verbatim.
+    pub fn parse(hash: impl ToString, subject: &str, body: &str) -> Self {
+        let hash = hash.to_string().chars().take(7).collect::<String>();
+        let (subject, pr) = extract_pr(subject.trim());
+        let breaking_body = body
+            .lines()
+            .any(|line| line.starts_with("BREAKING CHANGE") || line.starts_with("BREAKING-CHANGE"));
+
+        let Some((header, summary)) = subject.split_once(": ") else {
+            return Self {
+                hash,
+                kind: if breaking_body {
+                    ChangeKind::Breaking
+                } else {
+                    ChangeKind::Other
+                },
+                scope: None,
+                summary: subject.to_string(),
+                pr,
+            };
+        };
+
+        let (header, breaking_header) = match header.strip_suffix('!') {
+            Some(header) => (header, true),
+            None => (header, false),
+        };
+
+        let (commit_type, scope) = match header.split_once('(') {
+            Some((commit_type, scope)) => (
+                commit_type,
+                scope.strip_suffix(')').map(|scope| scope.to_string()),
+            ),
+            None => (header, None),
+        };
+
+        // A header containing whitespace isn't a conventional commit type
+        if commit_type.is_empty() || commit_type.contains(char::is_whitespace) {
+            return Self {
+                hash,
+                kind: ChangeKind::Other,
+                scope: None,
+                summary: subject.to_string(),
+                pr,
+            };
+        }
+
+        let kind = if breaking_header || breaking_body {
+            ChangeKind::Breaking
+        } else {
+            ChangeKind::from_type(commit_type)
+        };
+
+        Self { hash, kind, scope, summary: summary.trim().to_string(), pr }
+    }
+}
+
+/// Extracts a trailing pull request reference such as `(#123)` from the
+/// subject
+fn extract_pr(subject: &str) -> (&str, Option<u64>) {
+    if let Some(rest) = subject.strip_suffix(')') {
+        if let Some(index) = rest.rfind("(#") {
+            if let Ok(pr) = rest[index + 2..].parse::<u64>() {
+                return (rest[..index].trim_end(), Some(pr));
+            }
+        }
+    }
+    (subject, None)
+}
+
+/// A group of entries of the same kind
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangelogSection {
+    pub kind: ChangeKind,
+    pub title: String,
+    pub entries: Vec<ChangelogEntry>,
+}
+
+/// Release notes for a range of commits
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Changelog {
+    /// The git revision range the changelog was generated from
+    pub range: String,
+    pub sections: Vec<ChangelogSection>,
+}
+
+impl Changelog {
+    /// Groups the entries by their kind, preserving the commit order within
+    /// each section and skipping empty sections.
+    pub fn new(range: impl ToString, entries: Vec<ChangelogEntry>) -> Self {
+        let mut sections: Vec<ChangelogSection> = Vec::new();
+        for kind in [
+            ChangeKind::Breaking,
+            ChangeKind::Feature,
+            ChangeKind::Fix,
+            ChangeKind::Other,
+        ] {
+            let entries = entries
+                .iter()
+                .filter(|entry| entry.kind == kind)
+                .cloned()
+                .collect::<Vec<_>>();
+            if !entries.is_empty() {
+                sections.push(ChangelogSection { kind, title: kind.title().to_string(), entries });
+            }
+        }
+
+        Self { range: range.to_string(), sections }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+}
+
+/// Renders the changelog as Markdown. This is the default template used when
+/// no custom template is configured.
+impl fmt::Display for Changelog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "## {}", self.range)?;
+        for section in &self.sections {
+            writeln!(f)?;
+            writeln!(f, "### {}", section.title)?;
+            writeln!(f)?;
+            for entry in &section.entries {
+                write!(f, "- ")?;
+                if let Some(scope) = &entry.scope {
+                    write!(f, "**{scope}:** ")?;
+                }
+                write!(f, "{}", entry.summary)?;
+                if let Some(pr) = entry.pr {
+                    write!(f, " (#{pr})")?;
+                }
+                writeln!(f, " ({})", entry.hash)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_feature_with_scope_and_pr() {
+        let actual = ChangelogEntry::parse("abcdef1234", "feat(ui): add dark theme (#42)", "");
+        let expected = ChangelogEntry {
+            hash: "abcdef1".to_string(),
+            kind: ChangeKind::Feature,
+            scope: Some("ui".to_string()),
+            summary: "add dark theme".to_string(),
+            pr: Some(42),
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_parse_breaking_marker() {
+        let actual = ChangelogEntry::parse("abc", "fix!: drop legacy flag", "");
+        assert_eq!(actual.kind, ChangeKind::Breaking);
+        assert_eq!(actual.summary, "drop legacy flag");
+    }
+
+    #[test]
+    fn test_parse_breaking_footer() {
+        let actual = ChangelogEntry::parse("abc", "feat: new config", "BREAKING CHANGE: renamed");
+        assert_eq!(actual.kind, ChangeKind::Breaking);
+    }
+
+    #[test]
+    fn test_parse_non_conventional_subject() {
+        let actual = ChangelogEntry::parse("abc", "Update README: typo", "");
+        let expected = ChangelogEntry {
+            hash: "abc".to_string(),
+            kind: ChangeKind::Other,
+            scope: None,
+            summary: "Update README: typo".to_string(),
+            pr: None,
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_changelog_markdown() {
+        let fixture = vec![
+            ChangelogEntry::parse("1111111", "fix: handle empty input (#7)", ""),
+            ChangelogEntry::parse("2222222", "feat(cli): add changelog", ""),
+            ChangelogEntry::parse("3333333", "chore: bump deps", ""),
+        ];
+        let actual = Changelog::new("v0.1.0..v0.2.0", fixture).to_string();
+        let expected = r#"## v0.1.0..v0.2.0
+
+### Features
+
+- **cli:** add changelog (2222222)
+
+### Bug Fixes
+
+- handle empty input (#7) (1111111)
+
+### Other Changes
+
+- bump deps (3333333)
+"#;
+        assert_eq!(actual, expected);
+    }
+}
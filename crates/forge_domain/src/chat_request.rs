@@ -1,17 +1,37 @@
+use derive_more::derive::Display;
 use derive_setters::Setters;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{ConversationId, Event};
 
+/// Identifies a turn, ie: a request of the user and everything that answers
+/// it, across the logs of the UI, the orchestrator, the tools and the provider
+#[derive(Debug, Display, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct TurnId(Uuid);
+
+impl TurnId {
+    pub fn generate() -> Self {
+        Self(Uuid::new_v4())
+    }
+
+    pub fn into_string(&self) -> String {
+        self.0.to_string()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Setters)]
 #[setters(into, strip_option)]
 pub struct ChatRequest {
     pub event: Event,
     pub conversation_id: ConversationId,
+    #[serde(default = "TurnId::generate")]
+    pub turn_id: TurnId,
 }
 
 impl ChatRequest {
     pub fn new(content: Event, conversation_id: ConversationId) -> Self {
-        Self { event: content, conversation_id }
+        Self { event: content, conversation_id, turn_id: TurnId::generate() }
     }
 }
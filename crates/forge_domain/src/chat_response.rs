@@ -1,6 +1,7 @@
 use serde::Serialize;
+use serde_json::Value;
 
-use crate::{ToolCallFull, ToolResult, Usage};
+use crate::{PatchConflict, Progress, ToolCallFull, ToolResult, Usage};
 
 /// Events that are emitted by the agent for external consumption. This includes
 /// events for all internal state changes.
@@ -13,7 +14,17 @@ pub enum ChatResponse {
         is_md: bool,
         is_summary: bool,
     },
+    /// Diff of the changes a tool made to a file
+    Diff(String),
     ToolCallStart(ToolCallFull),
+    /// Progress of the tool that is running
+    ToolCallProgress(Progress),
     ToolCallEnd(ToolResult),
     Usage(Usage),
+    /// Validated response of an agent that has a response schema
+    Output(Value),
+    /// Patch that didn't match and was queued for the user to resolve
+    PatchConflict(PatchConflict),
+    /// Behaviour that was degraded to fit what the model's server supports
+    Warning(String),
 }
@@ -0,0 +1,56 @@
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use derive_setters::Setters;
+
+/// Format of the time shown to agents in prompts
+const PROMPT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S %:z";
+
+/// Source of the current time. Time can be frozen, and its timezone fixed, so
+/// that prompts, which include it, are the same from one run to the next, eg:
+/// when replaying a cassette or running evals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Setters)]
+#[setters(strip_option)]
+pub struct Clock {
+    /// Time returned instead of the current one
+    pub frozen: Option<DateTime<FixedOffset>>,
+    /// Timezone of the returned times, the local one by default
+    pub utc_offset: Option<FixedOffset>,
+}
+
+impl Clock {
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        let now = self
+            .frozen
+            .map_or_else(Utc::now, |frozen| frozen.with_timezone(&Utc));
+        match self.utc_offset {
+            Some(offset) => now.with_timezone(&offset),
+            None => now.with_timezone(&Local).fixed_offset(),
+        }
+    }
+
+    /// Current time as shown in prompts, eg: `2025-01-01 10:00:00 +02:00`
+    pub fn prompt_time(&self) -> String {
+        self.now().format(PROMPT_TIME_FORMAT).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_prompt_time() {
+        let fixture = Clock::default()
+            .frozen(DateTime::parse_from_rfc3339("2025-01-01T10:00:00Z").unwrap())
+            .utc_offset(FixedOffset::east_opt(2 * 3600).unwrap());
+
+        let actual = [fixture.prompt_time(), fixture.prompt_time()];
+
+        let expected = [
+            "2025-01-01 12:00:00 +02:00".to_string(),
+            "2025-01-01 12:00:00 +02:00".to_string(),
+        ];
+        assert_eq!(actual, expected);
+    }
+}
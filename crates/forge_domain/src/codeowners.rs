@@ -0,0 +1,157 @@
+use std::path::{Path, PathBuf};
+
+use glob::{MatchOptions, Pattern};
+
+/// Locations of the CODEOWNERS file, relative to the root of a repository, in
+/// the order they are looked up
+const LOCATIONS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+#[derive(Debug, Clone)]
+struct Rule {
+    patterns: Vec<Pattern>,
+    owners: Vec<String>,
+}
+
+/// Owners of the files of a repository, from its CODEOWNERS file
+#[derive(Debug, Clone)]
+pub struct CodeOwners {
+    root: PathBuf,
+    rules: Vec<Rule>,
+}
+
+impl CodeOwners {
+    /// Finds the CODEOWNERS file of the repository that contains `cwd`
+    pub fn find(cwd: &Path) -> Option<Self> {
+        cwd.ancestors().find_map(|root| {
+            LOCATIONS.iter().find_map(|location| {
+                let content = std::fs::read_to_string(root.join(location)).ok()?;
+                Some(Self::parse(root, &content))
+            })
+        })
+    }
+
+    pub fn parse(root: impl Into<PathBuf>, content: &str) -> Self {
+        let rules = content
+            .lines()
+            .map(|line| line.split_once(" #").map_or(line, |(rule, _)| rule).trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let patterns = patterns(parts.next()?);
+                let owners = parts.map(str::to_string).collect();
+                Some(Rule { patterns, owners })
+            })
+            .collect();
+        Self { root: root.into(), rules }
+    }
+
+    /// Owners of a file, absolute or relative to the root of the repository.
+    /// The last rule that matches the file wins, as on GitHub, and a rule
+    /// without owners means that the file has none.
+    pub fn owners(&self, path: &Path) -> &[String] {
+        let path = path.strip_prefix(&self.root).unwrap_or(path);
+        let path = path.to_string_lossy().replace('\\', "/");
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                rule.patterns
+                    .iter()
+                    .any(|pattern| pattern.matches_with(&path, MATCH_OPTIONS))
+            })
+            .map(|rule| rule.owners.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+/// Globs equivalent to a CODEOWNERS pattern, which follows the rules of
+/// gitignore: patterns without a slash match at any depth, and patterns that
+/// name a directory match everything inside it
+fn patterns(pattern: &str) -> Vec<Pattern> {
+    let anchored = pattern.starts_with('/') || pattern.trim_end_matches('/').contains('/');
+    let mut glob = pattern.trim_start_matches('/').to_string();
+    if glob.ends_with('/') {
+        glob.push_str("**");
+    }
+    if !anchored && !glob.starts_with("**") {
+        glob = format!("**/{glob}");
+    }
+
+    let mut globs = vec![glob.clone()];
+    if !glob.ends_with('*') {
+        globs.push(format!("{glob}/**"));
+    }
+    globs
+        .iter()
+        .filter_map(|glob| Pattern::new(glob).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn fixture() -> CodeOwners {
+        CodeOwners::parse(
+            "/repo",
+            r#"
+# Default owners
+*                   @org/core
+*.md                @org/docs   # documentation
+/crates/payments/   @org/payments @alice
+docs/*              @org/docs
+/vendor/
+"#,
+        )
+    }
+
+    #[test]
+    fn test_owners() {
+        let fixture = fixture();
+
+        let actual = [
+            "src/main.rs",
+            "README.md",
+            "/repo/crates/payments/src/lib.rs",
+            "crates/other/payments/lib.rs",
+            "docs/guide.txt",
+            "docs/api/index.txt",
+            "vendor/lib.rs",
+        ]
+        .map(|path| fixture.owners(Path::new(path)).join(" "));
+
+        let expected = [
+            "@org/core",
+            "@org/docs",
+            "@org/payments @alice",
+            "@org/core",
+            "@org/docs",
+            "@org/core",
+            "",
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_find() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join(".github")).unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join(".github/CODEOWNERS"), "*.rs @rustaceans\n").unwrap();
+
+        let actual = CodeOwners::find(&dir.path().join("src")).unwrap();
+
+        assert_eq!(
+            actual.owners(&dir.path().join("src/lib.rs")),
+            ["@rustaceans".to_string()]
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use derive_setters::Setters;
+use merge::Merge;
+use serde::{Deserialize, Serialize};
+
+use crate::ModelId;
+
+/// Number of deleted lines above which an edit is considered risky when not
+/// configured
+const DEFAULT_MAX_DELETED_LINES: usize = 50;
+
+/// A change to the content of a file, computed without applying it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileChange {
+    pub path: PathBuf,
+    pub old: String,
+    pub new: String,
+}
+
+impl FileChange {
+    pub fn new(path: impl Into<PathBuf>, old: impl Into<String>, new: impl Into<String>) -> Self {
+        Self { path: path.into(), old: old.into(), new: new.into() }
+    }
+
+    /// Number of lines of the old content that are missing from the new one
+    pub fn deleted_lines(&self) -> usize {
+        let mut remaining = HashMap::<&str, usize>::new();
+        for line in self.new.lines() {
+            *remaining.entry(line).or_default() += 1;
+        }
+
+        self.old
+            .lines()
+            .filter(|line| match remaining.get_mut(line) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            })
+            .count()
+    }
+
+    /// Returns true if both changes produce the same content for the same
+    /// file, ignoring differences in trailing whitespace
+    pub fn agrees_with(&self, other: &FileChange) -> bool {
+        fn normalize(content: &str) -> Vec<&str> {
+            content.trim_end().lines().map(str::trim_end).collect()
+        }
+        self.path == other.path && normalize(&self.new) == normalize(&other.new)
+    }
+}
+
+/// Configuration for consensus mode, where risky edits are only applied when
+/// a second model, given the same conversation, independently proposes the
+/// same change
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Merge, Setters)]
+#[setters(strip_option, into)]
+pub struct Consensus {
+    /// Model that independently proposes risky edits
+    #[merge(strategy = crate::merge::std::overwrite)]
+    pub model: ModelId,
+
+    /// Number of deleted lines above which an edit is considered risky.
+    /// Defaults to 50.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub max_deleted_lines: Option<usize>,
+
+    /// Edits to files whose path contains any of these patterns are always
+    /// considered risky, eg: `migrations/`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[merge(strategy = crate::merge::vec::append)]
+    pub paths: Vec<String>,
+}
+
+impl Consensus {
+    pub fn new(model: ModelId) -> Self {
+        Self { model, max_deleted_lines: None, paths: Vec::new() }
+    }
+
+    /// Determines if the change needs to be agreed upon before being applied
+    pub fn is_risky(&self, change: &FileChange) -> bool {
+        let path = change.path.to_string_lossy();
+        self.paths
+            .iter()
+            .any(|pattern| path.contains(pattern.as_str()))
+            || change.deleted_lines() > self.max_deleted_lines.unwrap_or(DEFAULT_MAX_DELETED_LINES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_deleted_lines() {
+        let fixture = FileChange::new("/a.rs", "a\nb\nb\nc\n", "a\nb\nd\n");
+        let actual = fixture.deleted_lines();
+        assert_eq!(actual, 2);
+    }
+
+    #[test]
+    fn test_is_risky_by_deleted_lines() {
+        let consensus = Consensus::new(ModelId::new("reviewer")).max_deleted_lines(1usize);
+
+        let actual = [
+            FileChange::new("/a.rs", "a\nb\n", "a\n"),
+            FileChange::new("/a.rs", "a\nb\nc\n", "a\n"),
+        ]
+        .map(|change| consensus.is_risky(&change));
+
+        assert_eq!(actual, [false, true]);
+    }
+
+    #[test]
+    fn test_is_risky_by_path() {
+        let consensus =
+            Consensus::new(ModelId::new("reviewer")).paths(vec!["migrations/".to_string()]);
+        let fixture = FileChange::new("/db/migrations/001_init.sql", "", "CREATE TABLE a;");
+
+        let actual = consensus.is_risky(&fixture);
+
+        assert!(actual);
+    }
+
+    #[test]
+    fn test_agrees_with_ignores_trailing_whitespace() {
+        let fixture = FileChange::new("/a.rs", "old", "fn main() {}  \n\n");
+        let other = FileChange::new("/a.rs", "old", "fn main() {}");
+
+        let actual = fixture.agrees_with(&other);
+
+        assert!(actual);
+    }
+}
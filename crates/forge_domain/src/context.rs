@@ -5,7 +5,7 @@ use tracing::debug;
 
 use super::{ToolCallFull, ToolResult};
 use crate::temperature::Temperature;
-use crate::{ToolCallRecord, ToolChoice, ToolDefinition};
+use crate::{ResponseSchema, ToolCallRecord, ToolChoice, ToolDefinition, TurnId};
 
 /// Represents a message being sent to the LLM provider
 /// NOTE: ToolResults message are part of the larger Request object and not part
@@ -108,6 +108,13 @@ pub struct Context {
     pub max_tokens: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<Temperature>,
+    /// Schema the response must conform to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_schema: Option<ResponseSchema>,
+    /// Turn that the request is made for, sent to the provider so that its
+    /// logs can be correlated with ours
+    #[serde(skip)]
+    pub turn_id: Option<TurnId>,
 }
 
 impl Context {
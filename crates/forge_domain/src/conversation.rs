@@ -96,6 +96,30 @@ impl Conversation {
                 agent.custom_rules = Some(custom_rules);
             }
 
+            if let Some(git_policy) = workflow.git_policy.clone() {
+                agent.git_policy = Some(git_policy);
+            }
+
+            if let Some(tool_slimming) = workflow.tool_slimming.clone() {
+                agent.tool_slimming = Some(tool_slimming);
+            }
+
+            // The agent's own overrides take precedence over the workflow's
+            if let Some(tool_overrides) = workflow.tool_overrides.clone() {
+                agent
+                    .tool_overrides
+                    .get_or_insert_with(Vec::new)
+                    .extend(tool_overrides);
+            }
+
+            if let Some(moderation) = workflow.moderation.clone() {
+                agent.moderation = Some(moderation);
+            }
+
+            if let Some(defer_patch_conflicts) = workflow.defer_patch_conflicts {
+                agent.defer_patch_conflicts = Some(defer_patch_conflicts);
+            }
+
             if let Some(max_walker_depth) = workflow.max_walker_depth {
                 agent.max_walker_depth = Some(max_walker_depth);
             }
@@ -346,7 +370,8 @@ mod tests {
             .max_walker_depth(5)
             .custom_rules("Be helpful".to_string())
             .temperature(Temperature::new(0.7).unwrap())
-            .tool_supported(true);
+            .tool_supported(true)
+            .defer_patch_conflicts(true);
 
         // Act
         let conversation = super::Conversation::new_inner(id.clone(), workflow);
@@ -361,6 +386,7 @@ mod tests {
             assert_eq!(agent.custom_rules, Some("Be helpful".to_string()));
             assert_eq!(agent.temperature, Some(Temperature::new(0.7).unwrap()));
             assert_eq!(agent.tool_supported, Some(true));
+            assert_eq!(agent.defer_patch_conflicts, Some(true));
         }
     }
 
@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use derive_setters::Setters;
 use serde::{Deserialize, Serialize};
 
-use crate::{Provider, RetryConfig};
+use crate::{Clock, Provider, ResourceLimits, RetentionPolicy, RetryConfig};
 
 #[derive(Debug, Setters, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -26,6 +26,39 @@ pub struct Environment {
     pub provider: Provider,
     /// Configuration for the retry mechanism
     pub retry_config: RetryConfig,
+    /// Limits on the resources of the commands that tools run
+    #[serde(default)]
+    pub resource_limits: ResourceLimits,
+    /// How long the stored sessions, snapshots, logs and spill files are kept
+    #[serde(default)]
+    pub retention: RetentionPolicy,
+    /// CODEOWNERS teams or users that the user belongs to, eg: `@org/core`.
+    /// Changes to files that are owned only by others need a confirmation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub code_owner_teams: Vec<String>,
+    /// Records provider interactions to, or replays them from, a cassette
+    /// file. Used to run integration tests without network access.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cassette: Option<CassetteMode>,
+    /// Number of the last provider interactions kept to be inspected with
+    /// `/debug last-request`, none when 0
+    #[serde(default)]
+    pub inspected_requests: usize,
+    /// Source of the time shown to agents
+    #[serde(skip)]
+    pub clock: Clock,
+}
+
+/// Controls how the provider client interacts with a cassette file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CassetteMode {
+    /// Forward requests to the provider and append every interaction to the
+    /// cassette at the given path
+    Record(PathBuf),
+    /// Serve responses from the cassette at the given path without contacting
+    /// the provider
+    Replay(PathBuf),
 }
 
 impl Environment {
@@ -43,4 +76,13 @@ impl Environment {
     pub fn snapshot_path(&self) -> PathBuf {
         self.base_path.join("snapshots")
     }
+
+    /// Path of the last provider interactions, see `inspected_requests`
+    pub fn inspection_path(&self) -> PathBuf {
+        self.base_path.join("last_requests.json")
+    }
+
+    pub fn file_usage_path(&self) -> PathBuf {
+        self.base_path.join("file_usage.json")
+    }
 }
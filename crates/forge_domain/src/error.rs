@@ -45,6 +45,24 @@ pub enum Error {
 
     #[error("No model defined for agent: {0}")]
     NoModelDefined(AgentId),
+
+    #[error("Invalid {0} pattern in the git policy: {1}")]
+    GitPolicyPattern(&'static str, regex::Error),
+
+    #[error("Commit message '{0}' doesn't follow the git policy. {1}")]
+    CommitMessagePolicy(String, String),
+
+    #[error("Branch name '{0}' doesn't follow the git policy. {1}")]
+    BranchNamePolicy(String, String),
+
+    #[error("Invalid pattern of the moderation rule '{0}': {1}")]
+    ModerationPattern(String, regex::Error),
+
+    #[error("Content refused by the moderation rule '{0}'")]
+    ModerationRule(String),
+
+    #[error("Content flagged by the moderation endpoint: {0}")]
+    ModerationFlagged(String),
 }
 
 pub type Result<A> = std::result::Result<A, Error>;
@@ -5,7 +5,7 @@ use schemars::{schema_for, JsonSchema};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::{NamedTool, ToolCallFull, ToolDefinition, ToolName};
+use crate::{Clock, NamedTool, ToolCallFull, ToolDefinition, ToolName};
 
 // We'll use simple strings for JSON schema compatibility
 #[derive(Debug, Deserialize, Serialize, Clone, Setters)]
@@ -37,14 +37,12 @@ pub struct EventContext {
 }
 
 impl EventContext {
-    pub fn new(event: Event) -> Self {
+    pub fn new(event: Event, clock: &Clock) -> Self {
         Self {
             event,
             suggestions: Default::default(),
             variables: Default::default(),
-            current_time: chrono::Local::now()
-                .format("%Y-%m-%d %H:%M:%S %:z")
-                .to_string(),
+            current_time: clock.prompt_time(),
         }
     }
 }
@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Tools that read the file at their `path` argument
+const READ_TOOLS: &[&str] = &["forge_tool_fs_read"];
+
+/// Tools that edit the file at their `path` argument
+const EDIT_TOOLS: &[&str] = &[
+    "forge_tool_fs_create",
+    "forge_tool_fs_patch",
+    "forge_tool_fs_remove",
+];
+
+/// Weight of an edit compared to a read when ranking files, since a file that
+/// the agent changes is more likely to be relevant again
+const EDIT_WEIGHT: u64 = 3;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileStats {
+    pub reads: u64,
+    pub edits: u64,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+impl FileStats {
+    pub fn score(&self) -> u64 {
+        self.reads + self.edits * EDIT_WEIGHT
+    }
+}
+
+/// How often the agent read and edited each file, across sessions. The files
+/// used the most are preferred when preloading the context of a session.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileUsage {
+    files: BTreeMap<PathBuf, FileStats>,
+}
+
+impl FileUsage {
+    /// Loads the usage from `path`, starting over when it is missing or
+    /// unreadable
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Records a call of a file tool. Returns false when the tool doesn't
+    /// read or edit a file.
+    pub fn record(&mut self, tool: &str, path: &Path, time: DateTime<Utc>) -> bool {
+        let is_read = READ_TOOLS.contains(&tool);
+        if !is_read && !EDIT_TOOLS.contains(&tool) {
+            return false;
+        }
+
+        let stats = self.files.entry(path.to_path_buf()).or_default();
+        if is_read {
+            stats.reads += 1;
+        } else {
+            stats.edits += 1;
+        }
+        stats.last_used = Some(time);
+        true
+    }
+
+    /// Files under `cwd` that were used the most, relative to `cwd`. Ties are
+    /// broken by the most recently used.
+    pub fn hot_files(&self, cwd: &Path, limit: usize) -> Vec<(PathBuf, FileStats)> {
+        let mut files = self
+            .files
+            .iter()
+            .filter_map(|(path, stats)| {
+                let relative = path.strip_prefix(cwd).ok()?;
+                Some((relative.to_path_buf(), *stats))
+            })
+            .collect::<Vec<_>>();
+        files.sort_by(|(_, a), (_, b)| {
+            b.score()
+                .cmp(&a.score())
+                .then_with(|| b.last_used.cmp(&a.last_used))
+        });
+        files.truncate(limit);
+        files
+    }
+
+    /// Orders the files of a workspace, relative to `cwd`, so that the hot
+    /// ones come first. Hot files that are missing from `files`, eg: because
+    /// they are deeper than the walker goes, are added when they still exist.
+    pub fn rank(&self, cwd: &Path, files: Vec<String>, limit: usize) -> Vec<String> {
+        let hot = self
+            .hot_files(cwd, limit)
+            .into_iter()
+            .filter(|(path, _)| cwd.join(path).is_file())
+            .map(|(path, _)| path.to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        let rest = files.into_iter().filter(|file| !hot.contains(file));
+        hot.iter().cloned().chain(rest).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn time(seconds: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(seconds, 0).unwrap()
+    }
+
+    #[test]
+    fn test_hot_files() {
+        let cwd = Path::new("/repo");
+        let mut fixture = FileUsage::default();
+        fixture.record("forge_tool_fs_read", &cwd.join("src/a.rs"), time(1));
+        fixture.record("forge_tool_fs_read", &cwd.join("src/a.rs"), time(2));
+        fixture.record("forge_tool_fs_patch", &cwd.join("src/b.rs"), time(3));
+        fixture.record("forge_tool_fs_read", &cwd.join("README.md"), time(4));
+        fixture.record("forge_tool_fs_read", Path::new("/other/c.rs"), time(5));
+        assert!(!fixture.record("forge_tool_process_shell", &cwd.join("x"), time(6)));
+
+        let actual = fixture
+            .hot_files(cwd, 2)
+            .into_iter()
+            .map(|(path, stats)| (path, stats.score()))
+            .collect::<Vec<_>>();
+
+        let expected = vec![
+            (PathBuf::from("src/b.rs"), 3),
+            (PathBuf::from("src/a.rs"), 2),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_rank() {
+        let fixture = tempfile::tempdir().unwrap();
+        let cwd = fixture.path();
+        std::fs::create_dir_all(cwd.join("src/deep")).unwrap();
+        std::fs::write(cwd.join("src/deep/hot.rs"), "").unwrap();
+        let mut usage = FileUsage::default();
+        usage.record("forge_tool_fs_patch", &cwd.join("src/deep/hot.rs"), time(1));
+        usage.record("forge_tool_fs_read", &cwd.join("README.md"), time(2));
+        usage.record("forge_tool_fs_read", &cwd.join("deleted.rs"), time(3));
+
+        let actual = usage.rank(
+            cwd,
+            vec!["Cargo.toml".to_string(), "README.md".to_string()],
+            10,
+        );
+
+        let expected = vec!["src/deep/hot.rs", "Cargo.toml", "README.md"];
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,290 @@
+use derive_setters::Setters;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+/// Naming conventions of commits and branches, eg: to pass the commit-lint
+/// hooks of an organization. Git commands that agents run are checked against
+/// the policy and conformed to it when a simple fix is enough.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Setters)]
+#[setters(strip_option, into)]
+pub struct GitPolicy {
+    /// Regex that the first line of commit messages must match, eg:
+    /// `^(feat|fix|docs|chore)(\(.+\))?: .+$`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_pattern: Option<String>,
+
+    /// Maximum number of characters in the first line of commit messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_subject_length: Option<usize>,
+
+    /// Regex that the names of new branches must match, eg:
+    /// `^(feature|fix)/[a-z0-9-]+$`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_pattern: Option<String>,
+}
+
+impl GitPolicy {
+    /// Describes the policy, so that agents can follow it from the start
+    pub fn rules(&self) -> String {
+        let mut rules = vec!["Follow the git naming policy of the project:".to_string()];
+        if let Some(pattern) = &self.commit_pattern {
+            rules.push(format!(
+                "- The first line of commit messages must match the regex `{pattern}`"
+            ));
+        }
+        if let Some(length) = self.max_subject_length {
+            rules.push(format!(
+                "- The first line of commit messages must be at most {length} characters long"
+            ));
+        }
+        if let Some(pattern) = &self.branch_pattern {
+            rules.push(format!("- Branch names must match the regex `{pattern}`"));
+        }
+        rules.join("\n")
+    }
+
+    /// Conforms the first line of a commit message to the policy, failing
+    /// when it can't be fixed without changing its meaning
+    pub fn conform_commit(&self, message: &str) -> Result<String> {
+        let (subject, body) = match message.split_once('\n') {
+            Some((subject, body)) => (subject, Some(body)),
+            None => (message, None),
+        };
+        let pattern = compile("commit", self.commit_pattern.as_deref())?;
+        let matches = |subject: &str| {
+            pattern
+                .as_ref()
+                .is_none_or(|pattern| pattern.is_match(subject))
+                && self
+                    .max_subject_length
+                    .is_none_or(|length| subject.chars().count() <= length)
+        };
+
+        let subject = [subject.to_string(), normalize_subject(subject)]
+            .into_iter()
+            .find(|subject| matches(subject))
+            .ok_or_else(|| Error::CommitMessagePolicy(subject.to_string(), self.rules()))?;
+        Ok(match body {
+            Some(body) => format!("{subject}\n{body}"),
+            None => subject,
+        })
+    }
+
+    /// Conforms a branch name to the policy, failing when it can't be fixed
+    /// without changing its meaning
+    pub fn conform_branch(&self, name: &str) -> Result<String> {
+        let Some(pattern) = compile("branch", self.branch_pattern.as_deref())? else {
+            return Ok(name.to_string());
+        };
+        [name.to_string(), slugify(name)]
+            .into_iter()
+            .find(|name| pattern.is_match(name))
+            .ok_or_else(|| Error::BranchNamePolicy(name.to_string(), self.rules()))
+    }
+
+    /// Conforms the commit messages and new branch names in a shell command
+    /// to the policy, eg: `git commit -m "..."` or `git checkout -b ...`
+    pub fn conform_command(&self, command: &str) -> Result<String> {
+        if !command.contains("git ") {
+            return Ok(command.to_string());
+        }
+
+        let mut error = None;
+        let command = commit_regex().replace_all(command, |captures: &Captures| {
+            let (quote, message) = match captures.name("double") {
+                Some(message) => ('"', message.as_str()),
+                None => ('\'', &captures["single"]),
+            };
+            match self.conform_commit(message) {
+                Ok(message) => format!("{}{quote}{message}{quote}", &captures["prefix"]),
+                Err(err) => {
+                    error.get_or_insert(err);
+                    captures[0].to_string()
+                }
+            }
+        });
+        let command = branch_regex().replace_all(&command, |captures: &Captures| {
+            match self.conform_branch(&captures["name"]) {
+                Ok(name) => format!("{}{name}", &captures["prefix"]),
+                Err(err) => {
+                    error.get_or_insert(err);
+                    captures[0].to_string()
+                }
+            }
+        });
+
+        match error {
+            Some(error) => Err(error),
+            None => Ok(command.into_owned()),
+        }
+    }
+}
+
+fn compile(kind: &'static str, pattern: Option<&str>) -> Result<Option<Regex>> {
+    pattern
+        .map(|pattern| Regex::new(pattern).map_err(|err| Error::GitPolicyPattern(kind, err)))
+        .transpose()
+}
+
+/// First message of a `git commit` command, in single or double quotes. Only
+/// the first message is the subject, the others are paragraphs of the body.
+fn commit_regex() -> Regex {
+    Regex::new(
+        r#"(?P<prefix>\bgit\s+commit\b[^;&|]*?(?:-[a-zA-Z]*m\s*|--message[=\s]\s*))(?:"(?P<double>(?:\\.|[^"\\])*)"|'(?P<single>[^']*)')"#,
+    )
+    .unwrap()
+}
+
+/// Name of a branch created by `git checkout -b`, `git switch -c` or
+/// `git branch`. Quoted names are left alone.
+fn branch_regex() -> Regex {
+    Regex::new(
+        r#"(?P<prefix>\bgit\s+(?:checkout\s+-[bB]|switch\s+(?:-[cC]|--create)|branch)\s+)(?P<name>[^\s;&|'"-][^\s;&|'"]*)"#,
+    )
+    .unwrap()
+}
+
+/// Fixes the common mistakes in the first line of a commit message: extra
+/// whitespace, a trailing period, and capitalized types or descriptions
+fn normalize_subject(subject: &str) -> String {
+    let subject = subject.split_whitespace().collect::<Vec<_>>().join(" ");
+    let subject = subject.trim_end_matches('.');
+    match subject.split_once(": ") {
+        Some((kind, description)) => {
+            let mut chars = description.chars();
+            let description = match (chars.next(), chars.next()) {
+                // Acronyms such as `API` keep their case
+                (Some(first), Some(second)) if !second.is_uppercase() => {
+                    format!(
+                        "{}{}",
+                        first.to_lowercase(),
+                        &description[first.len_utf8()..]
+                    )
+                }
+                _ => description.to_string(),
+            };
+            format!("{}: {description}", kind.to_lowercase())
+        }
+        None => subject.to_string(),
+    }
+}
+
+/// Lowercases a branch name and replaces the characters that naming policies
+/// usually reject with dashes
+fn slugify(name: &str) -> String {
+    let name = name
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "/._-".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    name.split('/')
+        .map(|segment| {
+            segment
+                .split('-')
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn fixture() -> GitPolicy {
+        GitPolicy::default()
+            .commit_pattern(r"^(feat|fix|docs|chore)(\(.+\))?: [a-z].+$")
+            .max_subject_length(50usize)
+            .branch_pattern(r"^(feature|fix)/[a-z0-9-]+$")
+    }
+
+    #[test]
+    fn test_conform_commit() {
+        let fixture = fixture();
+
+        let actual = [
+            "fix: handle empty input",
+            "Feat(api):  Add the search endpoint.\n\nDetails",
+            "docs: API reference",
+        ]
+        .map(|message| fixture.conform_commit(message).ok());
+
+        let expected = [
+            Some("fix: handle empty input".to_string()),
+            Some("feat(api): add the search endpoint\n\nDetails".to_string()),
+            None,
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_conform_commit_too_long() {
+        let fixture = fixture();
+
+        let actual = fixture.conform_commit(&format!("fix: {}", "a".repeat(50)));
+
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn test_conform_branch() {
+        let fixture = fixture();
+
+        let actual = ["feature/search", "Feature/Add Search", "search"]
+            .map(|name| fixture.conform_branch(name).ok());
+
+        let expected = [
+            Some("feature/search".to_string()),
+            Some("feature/add-search".to_string()),
+            None,
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_conform_command() {
+        let fixture = fixture();
+
+        let actual = fixture
+            .conform_command(
+                r#"git switch -c Fix/Empty-Input && git add . && git commit -m "Fix: Handle empty input." -m "Body""#,
+            )
+            .unwrap();
+
+        let expected = r#"git switch -c fix/empty-input && git add . && git commit -m "fix: handle empty input" -m "Body""#;
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_conform_command_violation() {
+        let fixture = fixture();
+
+        let actual = fixture.conform_command("git commit -am 'update stuff'");
+
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn test_conform_command_without_policy() {
+        let fixture = GitPolicy::default();
+
+        let actual = fixture
+            .conform_command("git commit -m 'WIP' && git branch Temp")
+            .unwrap();
+
+        let expected = "git commit -m 'WIP' && git branch Temp";
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,130 @@
+use serde_json::Value;
+
+/// Parses JSON written by a model, repairing the mistakes commonly made by
+/// models that weren't trained for function calling: a surrounding code
+/// fence, trailing commas, and output that stops before every array and
+/// object is closed. Output that stops inside a string isn't repaired, since
+/// the rest of the string, eg: the content of a file, can't be guessed. The
+/// error of the original input is returned when it can't be repaired.
+pub fn repair_json(input: &str) -> Result<Value, serde_json::Error> {
+    match serde_json::from_str(input) {
+        Ok(value) => Ok(value),
+        Err(error) => repair(input)
+            .and_then(|repaired| serde_json::from_str(&repaired).ok())
+            .ok_or(error),
+    }
+}
+
+fn repair(input: &str) -> Option<String> {
+    let input = strip_code_fence(input.trim());
+    let mut output = String::with_capacity(input.len());
+    let mut closers = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in input.chars() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            output.push(c);
+            continue;
+        }
+
+        match c {
+            '"' => in_string = true,
+            '{' => closers.push('}'),
+            '[' => closers.push(']'),
+            '}' | ']' => {
+                remove_trailing_comma(&mut output);
+                closers.pop();
+            }
+            _ => {}
+        }
+        output.push(c);
+    }
+
+    if in_string {
+        return None;
+    }
+    while let Some(closer) = closers.pop() {
+        remove_trailing_comma(&mut output);
+        output.push(closer);
+    }
+    Some(output)
+}
+
+fn remove_trailing_comma(output: &mut String) {
+    let end = output.trim_end().len();
+    if output[..end].ends_with(',') {
+        output.truncate(end - 1);
+    }
+}
+
+/// Removes a markdown code fence, eg: ```json ... ```
+fn strip_code_fence(input: &str) -> &str {
+    match input.strip_prefix("```") {
+        Some(rest) => {
+            let rest = rest.trim_start_matches(char::is_alphanumeric);
+            rest.trim_end().strip_suffix("```").unwrap_or(rest).trim()
+        }
+        None => input,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_valid_json() {
+        let actual = repair_json(r#"{"path": "/a.rs"}"#).unwrap();
+        let expected = json!({"path": "/a.rs"});
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_code_fence_and_trailing_commas() {
+        let fixture = "```json\n{\"paths\": [\"/a.rs\", \"/b.rs\",], \"recursive\": true,}\n```";
+
+        let actual = repair_json(fixture).unwrap();
+
+        let expected = json!({"paths": ["/a.rs", "/b.rs"], "recursive": true});
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_truncated_output() {
+        let fixture = r#"{"name": "forge_tool_fs_read", "arguments": {"path": "/a.rs","#;
+
+        let actual = repair_json(fixture).unwrap();
+
+        let expected = json!({
+            "name": "forge_tool_fs_read",
+            "arguments": {"path": "/a.rs"}
+        });
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_truncated_string() {
+        let fixture = r#"{"name": "forge_tool_fs_write", "arguments": {"content": "fn main() {"#;
+
+        let actual = repair_json(fixture);
+
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn test_unrepairable() {
+        let actual = repair_json("not json");
+        assert!(actual.is_err());
+    }
+}
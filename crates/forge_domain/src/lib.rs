@@ -1,9 +1,13 @@
 mod agent;
 mod api;
 mod attachment;
+mod changelog;
 mod chat_request;
 mod chat_response;
+mod clock;
+mod codeowners;
 mod compaction_result;
+mod consensus;
 mod conversation_html;
 
 mod context;
@@ -12,13 +16,22 @@ mod env;
 mod error;
 mod event;
 mod file;
+mod file_usage;
+mod git_policy;
+mod json_repair;
 mod merge;
 mod message;
 mod model;
+mod moderation;
 mod orch;
+mod patch_conflict;
 mod point;
+mod progress;
 mod provider;
+mod response_schema;
+mod retention;
 mod retry_config;
+mod salience;
 mod services;
 mod shell;
 mod suggestion;
@@ -26,6 +39,7 @@ mod system_context;
 mod temperature;
 mod template;
 mod text_utils;
+mod theme;
 mod tool;
 mod tool_call;
 mod tool_call_context;
@@ -34,16 +48,22 @@ mod tool_call_record;
 mod tool_choice;
 mod tool_definition;
 mod tool_name;
+mod tool_override;
 mod tool_result;
+mod tool_slimming;
 mod tool_usage;
 mod workflow;
 
 pub use agent::*;
 pub use api::*;
 pub use attachment::*;
+pub use changelog::*;
 pub use chat_request::*;
 pub use chat_response::*;
+pub use clock::*;
+pub use codeowners::*;
 pub use compaction_result::*;
+pub use consensus::*;
 pub use context::*;
 pub use conversation::*;
 pub use conversation_html::*;
@@ -51,12 +71,21 @@ pub use env::*;
 pub use error::*;
 pub use event::*;
 pub use file::*;
+pub use file_usage::*;
+pub use git_policy::*;
+pub use json_repair::*;
 pub use message::*;
 pub use model::*;
+pub use moderation::*;
 pub use orch::*;
+pub use patch_conflict::*;
 pub use point::*;
+pub use progress::*;
 pub use provider::*;
+pub use response_schema::*;
+pub use retention::*;
 pub use retry_config::*;
+pub use salience::*;
 pub use services::*;
 pub use shell::*;
 pub use suggestion::*;
@@ -64,6 +93,7 @@ pub use system_context::*;
 pub use temperature::*;
 pub use template::*;
 pub use text_utils::*;
+pub use theme::*;
 pub use tool::*;
 pub use tool_call::*;
 pub use tool_call_context::*;
@@ -72,6 +102,8 @@ pub use tool_call_record::*;
 pub use tool_choice::*;
 pub use tool_definition::*;
 pub use tool_name::*;
+pub use tool_override::*;
 pub use tool_result::*;
+pub use tool_slimming::*;
 pub use tool_usage::*;
 pub use workflow::*;
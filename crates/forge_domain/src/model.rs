@@ -8,17 +8,42 @@ pub struct Model {
     pub name: Option<String>,
     pub description: Option<String>,
     pub context_length: Option<u64>,
+    /// Price of the tokens, when the provider reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<Pricing>,
     // TODO: add provider information to the model
 }
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+/// Price of a model's tokens, in USD per token
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Setters)]
+#[setters(strip_option)]
+pub struct Pricing {
+    pub prompt: Option<f64>,
+    pub completion: Option<f64>,
+}
+
+impl Pricing {
+    /// Cost of a request, in USD. Tokens without a price are free.
+    pub fn cost(&self, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+        self.prompt.unwrap_or_default() * prompt_tokens as f64
+            + self.completion.unwrap_or_default() * completion_tokens as f64
+    }
+}
+
+/// Capabilities of a model as reported by the server that hosts it
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, Setters)]
+#[setters(strip_option, into)]
 pub struct Parameters {
+    /// Whether the server accepts tool definitions and returns native tool
+    /// calls
     pub tool_supported: bool,
+    /// Maximum number of tokens the server accepts for a request
+    pub context_length: Option<u64>,
 }
 
 impl Parameters {
     pub fn new(tool_supported: bool) -> Self {
-        Self { tool_supported }
+        Self { tool_supported, context_length: None }
     }
 }
 
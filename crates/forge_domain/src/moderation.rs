@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+
+use derive_setters::Setters;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{Error, Result};
+
+/// Moderation of the content that agents write to files or execute, for
+/// organizations with content compliance requirements. Content that a rule
+/// matches, or that the moderation endpoint flags, is refused.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Setters)]
+#[setters(strip_option, into)]
+pub struct Moderation {
+    /// Regexes of the content that must not be written or executed, by the
+    /// name reported when they match
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rules: BTreeMap<String, String>,
+
+    /// OpenAI compatible moderation endpoint, eg:
+    /// `https://api.openai.com/v1/moderations`. Its key is read from
+    /// `FORGE_MODERATION_API_KEY`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+}
+
+impl Moderation {
+    /// Checks the content against the rules
+    pub fn check(&self, content: &str) -> Result<()> {
+        for (name, pattern) in &self.rules {
+            let regex =
+                Regex::new(pattern).map_err(|err| Error::ModerationPattern(name.clone(), err))?;
+            if regex.is_match(content) {
+                return Err(Error::ModerationRule(name.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks a response of the moderation endpoint, failing with the
+    /// categories that it flagged
+    pub fn check_response(response: &Value) -> Result<()> {
+        let results = response["results"].as_array().into_iter().flatten();
+        let flagged = results
+            .filter(|result| result["flagged"].as_bool().unwrap_or_default())
+            .collect::<Vec<_>>();
+        if flagged.is_empty() {
+            return Ok(());
+        }
+
+        let mut categories = flagged
+            .iter()
+            .filter_map(|result| result["categories"].as_object())
+            .flatten()
+            .filter(|(_, flagged)| flagged.as_bool().unwrap_or_default())
+            .map(|(category, _)| category.clone())
+            .collect::<Vec<_>>();
+        categories.sort();
+        categories.dedup();
+        if categories.is_empty() {
+            categories.push("unspecified".to_string());
+        }
+        Err(Error::ModerationFlagged(categories.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let fixture = Moderation::default().rules(BTreeMap::from([(
+            "internal hosts".to_string(),
+            r"\.corp\.example\.com".to_string(),
+        )]));
+
+        let actual = [
+            fixture.check("curl https://api.corp.example.com/users"),
+            fixture.check("curl https://example.com"),
+        ]
+        .map(|result| result.map_err(|err| err.to_string()));
+
+        let expected = [
+            Err("Content refused by the moderation rule 'internal hosts'".to_string()),
+            Ok(()),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_check_response() {
+        let fixture = [
+            json!({"results": [{"flagged": false, "categories": {"violence": false}}]}),
+            json!({"results": [{"flagged": true, "categories": {"violence": true, "hate": true, "sexual": false}}]}),
+            json!({"results": [{"flagged": true}]}),
+        ];
+
+        let actual = fixture
+            .iter()
+            .map(|response| Moderation::check_response(response).map_err(|err| err.to_string()))
+            .collect::<Vec<_>>();
+
+        let expected = vec![
+            Ok(()),
+            Err("Content flagged by the moderation endpoint: hate, violence".to_string()),
+            Err("Content flagged by the moderation endpoint: unspecified".to_string()),
+        ];
+        assert_eq!(actual, expected);
+    }
+}
@@ -3,7 +3,6 @@ use std::sync::Arc;
 
 use anyhow::{bail, Context as AnyhowContext};
 use async_recursion::async_recursion;
-use chrono::Local;
 use forge_walker::Walker;
 use futures::future::join_all;
 use futures::{Stream, StreamExt};
@@ -11,6 +10,7 @@ use serde_json::Value;
 use tokio::sync::RwLock;
 use tokio_retry::strategy::{jitter, ExponentialBackoff};
 use tokio_retry::RetryIf;
+use tokio_util::sync::CancellationToken;
 use tracing::debug;
 
 // Use retry_config default values directly in this file
@@ -19,6 +19,13 @@ use crate::*;
 
 type ArcSender = Arc<tokio::sync::mpsc::Sender<anyhow::Result<AgentMessage<ChatResponse>>>>;
 
+/// Number of times an agent is asked for a response that conforms to its
+/// response schema
+const MAX_OUTPUT_ATTEMPTS: usize = 3;
+
+/// Number of the most used files that are listed first in the system prompt
+const HOT_FILES: usize = 20;
+
 #[derive(Debug, Clone)]
 pub struct AgentMessage<T> {
     pub agent: AgentId,
@@ -37,6 +44,8 @@ pub struct Orchestrator<Services> {
     sender: Option<ArcSender>,
     conversation: Arc<RwLock<Conversation>>,
     retry_strategy: std::iter::Take<tokio_retry::strategy::ExponentialBackoff>,
+    cancellation: CancellationToken,
+    turn_id: TurnId,
 }
 
 struct ChatCompletionResult {
@@ -66,14 +75,30 @@ impl<A: Services> Orchestrator<A> {
             sender,
             retry_strategy,
             conversation: Arc::new(RwLock::new(conversation)),
+            cancellation: CancellationToken::new(),
+            turn_id: TurnId::generate(),
         }
     }
 
+    /// Sets the token that stops the orchestrator and the tools it calls when
+    /// cancelled
+    pub fn cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Sets the turn that the requests to the provider are made for
+    pub fn turn_id(mut self, turn_id: TurnId) -> Self {
+        self.turn_id = turn_id;
+        self
+    }
+
     // Helper function to get all tool results from a vector of tool calls
     #[async_recursion]
     async fn get_all_tool_results(
         &self,
         agent: &Agent,
+        context: &Context,
         tool_calls: &[ToolCallFull],
         tool_context: ToolCallContext,
     ) -> anyhow::Result<Vec<ToolCallRecord>> {
@@ -85,12 +110,29 @@ impl<A: Services> Orchestrator<A> {
             self.send(agent, ChatResponse::ToolCallStart(tool_call.clone()))
                 .await?;
 
-            // Execute the tool
-            let tool_result = self
-                .services
-                .tool_service()
-                .call(tool_context.clone(), tool_call.clone())
-                .await;
+            // Execute the tool, unless the agent wasn't given it, eg: in an untrusted
+            // workspace, or it's a risky edit that the consensus model doesn't agree
+            // with
+            let tool_result = if !agent.allows_tool(&tool_call.name) {
+                ToolResult::from(tool_call.clone()).failure(anyhow::anyhow!(
+                    "The tool {} isn't available to the agent {}",
+                    tool_call.name,
+                    agent.id
+                ))
+            } else {
+                match self.check_consensus(agent, context, tool_call).await? {
+                    Some(rejected) => rejected,
+                    None if tool_call.name == ToolSlimming::tool_name() => {
+                        self.list_more_tools(agent, tool_call)
+                    }
+                    None => {
+                        self.services
+                            .tool_service()
+                            .call(tool_context.clone(), tool_call.clone())
+                            .await
+                    }
+                }
+            };
 
             // Send the end notification
             self.send(agent, ChatResponse::ToolCallEnd(tool_result.clone()))
@@ -103,6 +145,83 @@ impl<A: Services> Orchestrator<A> {
         Ok(tool_call_records)
     }
 
+    /// Asks the consensus model to independently propose the change when the
+    /// tool call is a risky edit. Returns a failed result, without making the
+    /// change, if the proposals don't agree.
+    async fn check_consensus(
+        &self,
+        agent: &Agent,
+        context: &Context,
+        tool_call: &ToolCallFull,
+    ) -> anyhow::Result<Option<ToolResult>> {
+        let Some(consensus) = &agent.consensus else {
+            return Ok(None);
+        };
+        let tool_service = self.services.tool_service();
+        // A call whose change can't be previewed, eg: a patch that doesn't match,
+        // is left to report its error when it's executed
+        let Some(change) = tool_service.preview(tool_call).await.ok().flatten() else {
+            return Ok(None);
+        };
+        if !consensus.is_risky(&change) {
+            return Ok(None);
+        }
+
+        debug!(agent_id = %agent.id, path = %change.path.display(), "Requesting consensus");
+        let response = self
+            .services
+            .provider_service()
+            .chat(
+                &consensus.model,
+                context.clone().turn_id(self.turn_id.clone()),
+            )
+            .await?;
+        let messages = response
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut alternative = None;
+        for call in collect_tool_calls(&messages)? {
+            if let Some(proposal) = tool_service.preview(&call).await.ok().flatten() {
+                if proposal.path == change.path {
+                    alternative = Some(proposal);
+                    break;
+                }
+            }
+        }
+
+        if alternative
+            .as_ref()
+            .is_some_and(|alternative| change.agrees_with(alternative))
+        {
+            debug!(agent_id = %agent.id, path = %change.path.display(), "Consensus reached");
+            return Ok(None);
+        }
+
+        let mut reason = format!(
+            "The change to {} was not applied. It is considered risky ({} lines deleted) and the consensus model '{}' ",
+            change.path.display(),
+            change.deleted_lines(),
+            consensus.model
+        );
+        match alternative {
+            Some(alternative) => reason.push_str(&format!(
+                "proposed different content for the file:\n{}\n",
+                alternative.new
+            )),
+            None => reason.push_str("did not propose a change to this file.\n"),
+        }
+        reason.push_str(
+            "Reconsider the change, prefer smaller targeted edits, or ask the user to confirm it.",
+        );
+
+        Ok(Some(
+            ToolResult::from(tool_call.clone()).failure(anyhow::anyhow!(reason)),
+        ))
+    }
+
     async fn send(&self, agent: &Agent, message: ChatResponse) -> anyhow::Result<()> {
         if let Some(sender) = &self.sender {
             // Send message if it's a Custom type or if hide_content is false
@@ -117,17 +236,55 @@ impl<A: Services> Orchestrator<A> {
         Ok(())
     }
 
-    /// Get the allowed tools for an agent
+    /// Get the allowed tools for an agent, slimmed when the agent's model
+    /// can't handle them all
     fn get_allowed_tools(&self, agent: &Agent) -> Vec<ToolDefinition> {
+        let tools = self.get_all_allowed_tools(agent);
+        match agent.active_tool_slimming() {
+            Some(slimming) => slimming.slim(tools),
+            None => tools,
+        }
+    }
+
+    fn get_all_allowed_tools(&self, agent: &Agent) -> Vec<ToolDefinition> {
         let allowed = agent.tools.iter().flatten().collect::<HashSet<_>>();
+        let overrides = agent.tool_overrides.as_deref().unwrap_or_default();
         self.services
             .tool_service()
             .list()
             .into_iter()
             .filter(|tool| allowed.contains(&tool.name))
+            .map(
+                |tool| match ToolOverride::find(overrides, &tool.name, agent.model.as_ref()) {
+                    Some(tool_override) => tool_override.apply(tool),
+                    None => tool,
+                },
+            )
             .collect()
     }
 
+    /// Tools of the agent that were left out by slimming
+    fn get_more_tools(&self, agent: &Agent) -> Vec<ToolDefinition> {
+        agent
+            .active_tool_slimming()
+            .map(|slimming| slimming.more_tools(self.get_all_allowed_tools(agent)))
+            .unwrap_or_default()
+    }
+
+    /// Lists the tools left out by slimming, with their full descriptions
+    fn list_more_tools(&self, agent: &Agent, tool_call: &ToolCallFull) -> ToolResult {
+        let more = self.get_more_tools(agent);
+        let result = ToolResult::from(tool_call.clone());
+        if more.is_empty() {
+            result.success("All the available tools are already listed.")
+        } else {
+            result.success(format!(
+                "The following tools are now available:\n{}",
+                ToolUsagePrompt::from(&more)
+            ))
+        }
+    }
+
     async fn set_system_prompt(
         &self,
         context: Context,
@@ -145,8 +302,10 @@ impl<A: Services> Orchestrator<A> {
                 .map(|f| f.path)
                 .collect::<Vec<_>>();
             files.sort();
+            // Files that the agent used the most in previous sessions come first
+            let files = FileUsage::load(&env.file_usage_path()).rank(&env.cwd, files, HOT_FILES);
 
-            let current_time = Local::now().format("%Y-%m-%d %H:%M:%S %:z").to_string();
+            let current_time = env.clock.prompt_time();
 
             let tool_information = match agent.tool_supported.unwrap_or_default() {
                 true => None,
@@ -159,7 +318,13 @@ impl<A: Services> Orchestrator<A> {
                 tool_information,
                 tool_supported: agent.tool_supported.unwrap_or_default(),
                 files,
-                custom_rules: agent.custom_rules.as_ref().cloned().unwrap_or_default(),
+                custom_rules: agent
+                    .custom_rules
+                    .iter()
+                    .cloned()
+                    .chain(agent.git_policy.as_ref().map(GitPolicy::rules))
+                    .collect::<Vec<_>>()
+                    .join("\n\n"),
                 variables: variables.clone(),
             };
 
@@ -373,12 +538,43 @@ impl<A: Services> Orchestrator<A> {
         Ok(())
     }
 
+    /// Adapts the agent to the capabilities of its model's server, keeping the
+    /// adapted agent in the conversation so that warnings are only sent once
+    async fn adapt_agent(&self, agent: &Agent) -> anyhow::Result<Agent> {
+        let mut agent = agent.clone();
+        let Some(model) = &agent.model else {
+            return Ok(agent);
+        };
+        let Some(parameters) = self.services.provider_service().parameters(model).await? else {
+            return Ok(agent);
+        };
+
+        let mut warnings = agent.adapt(&parameters);
+        warnings.extend(agent.adapt_tools(&parameters));
+        {
+            let mut conversation = self.conversation.write().await;
+            if let Some(existing) = conversation.agents.iter_mut().find(|a| a.id == agent.id) {
+                *existing = agent.clone();
+            }
+        }
+        for warning in warnings {
+            self.send(&agent, ChatResponse::Warning(warning)).await?;
+        }
+        Ok(agent)
+    }
+
     // Get the ToolCallContext for an agent
-    fn get_tool_call_context(&self, agent_id: &AgentId) -> ToolCallContext {
+    fn get_tool_call_context(&self, agent: &Agent) -> ToolCallContext {
         // Create a new ToolCallContext with the agent ID
-        ToolCallContext::default()
-            .agent_id(agent_id.clone())
+        let mut context = ToolCallContext::default()
+            .agent_id(agent.id.clone())
+            .moderation(agent.moderation.clone())
+            .dry_run(agent.dry_run.unwrap_or_default())
+            .defer_patch_conflicts(agent.defer_patch_conflicts.unwrap_or_default())
             .sender(self.sender.clone())
+            .cancellation(self.cancellation.child_token());
+        context.git_policy = agent.git_policy.clone();
+        context
     }
 
     // Create a helper method with the core functionality
@@ -391,7 +587,7 @@ impl<A: Services> Orchestrator<A> {
             event = ?event,
             "Initializing agent"
         );
-        let agent = conversation.get_agent(agent_id)?;
+        let agent = &self.adapt_agent(conversation.get_agent(agent_id)?).await?;
 
         let mut context = if agent.ephemeral.unwrap_or_default() {
             agent.init_context(self.get_allowed_tools(agent)).await?
@@ -433,11 +629,15 @@ impl<A: Services> Orchestrator<A> {
 
         self.set_context(&agent.id, context.clone()).await?;
 
-        let tool_context = self.get_tool_call_context(&agent.id);
+        let tool_context = self.get_tool_call_context(agent);
 
         let mut empty_tool_call_count = 0;
 
         while !tool_context.get_complete().await {
+            if tool_context.is_cancelled() {
+                bail!("Cancelled by the user");
+            }
+
             // Set context for the current loop iteration
             self.set_context(&agent.id, context.clone()).await?;
 
@@ -451,7 +651,7 @@ impl<A: Services> Orchestrator<A> {
             let response = self
                 .services
                 .provider_service()
-                .chat(model_id, context.clone())
+                .chat(model_id, context.clone().turn_id(self.turn_id.clone()))
                 .await?;
 
             let ChatCompletionResult { tool_calls, content, usage } =
@@ -479,12 +679,26 @@ impl<A: Services> Orchestrator<A> {
             );
 
             // Process tool calls and update context
+            let tool_call_records = self
+                .get_all_tool_results(agent, &context, &tool_calls, tool_context.clone())
+                .await?;
+            // Tools that were listed on demand can be called natively from now on
+            let list_more = tool_call_records
+                .iter()
+                .any(|record| record.tool_call.name == ToolSlimming::tool_name());
             context = context.append_message(
                 content,
-                self.get_all_tool_results(agent, &tool_calls, tool_context.clone())
-                    .await?,
+                tool_call_records,
                 agent.tool_supported.unwrap_or_default(),
             );
+            if list_more && agent.tool_supported.unwrap_or_default() {
+                let more = self
+                    .get_more_tools(agent)
+                    .into_iter()
+                    .filter(|tool| !context.tools.iter().any(|t| t.name == tool.name))
+                    .collect::<Vec<_>>();
+                context = context.extend_tools(more);
+            }
 
             if empty_tool_calls {
                 // No tool calls present, which doesn't mean task is complete so reprompt the
@@ -507,12 +721,76 @@ impl<A: Services> Orchestrator<A> {
             self.sync_conversation().await?;
         }
 
+        if let Some(schema) = &agent.response_schema {
+            let output = self.get_output(agent, &context, schema).await?;
+            self.conversation
+                .write()
+                .await
+                .variables
+                .insert(format!("{}_output", agent.id), output.clone());
+            self.send(agent, ChatResponse::Output(output)).await?;
+        }
+
         self.complete_turn(&agent.id).await?;
         self.sync_conversation().await?;
 
         Ok(())
     }
 
+    /// Asks the agent for its final response in the shape of the schema,
+    /// feeding the validation errors back until the response conforms
+    async fn get_output(
+        &self,
+        agent: &Agent,
+        context: &Context,
+        schema: &ResponseSchema,
+    ) -> anyhow::Result<Value> {
+        let model_id = agent
+            .model
+            .as_ref()
+            .ok_or(Error::MissingModel(agent.id.clone()))?;
+        let mut context = context
+            .clone()
+            .tool_choice(ToolChoice::None)
+            .response_schema(schema.clone())
+            .add_message(ContextMessage::user(schema.instructions()));
+
+        for attempt in 1..=MAX_OUTPUT_ATTEMPTS {
+            let response = self
+                .services
+                .provider_service()
+                .chat(model_id, context.clone().turn_id(self.turn_id.clone()))
+                .await?;
+            let content = response
+                .collect::<Vec<_>>()
+                .await
+                .into_iter()
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .iter()
+                .flat_map(|message| message.content.iter())
+                .map(|content| content.as_str())
+                .collect::<String>();
+
+            match schema.parse(&content) {
+                Ok(output) => return Ok(output),
+                Err(errors) => {
+                    debug!(agent_id = %agent.id, attempt, errors = ?errors, "Response doesn't conform to the schema");
+                    context = context
+                        .add_message(ContextMessage::assistant(content, None))
+                        .add_message(ContextMessage::user(format!(
+                            "The response doesn't conform to the schema:\n{}\nRespond again with only the corrected JSON.",
+                            errors.join("\n")
+                        )));
+                }
+            }
+        }
+
+        bail!(
+            "Agent {} didn't respond in the shape of its response schema after {MAX_OUTPUT_ATTEMPTS} attempts",
+            agent.id
+        )
+    }
+
     async fn set_user_prompt(
         &self,
         mut context: Context,
@@ -521,7 +799,9 @@ impl<A: Services> Orchestrator<A> {
         event: &Event,
     ) -> anyhow::Result<Context> {
         let content = if let Some(user_prompt) = &agent.user_prompt {
-            let event_context = EventContext::new(event.clone()).variables(variables.clone());
+            let clock = self.services.environment_service().get_environment().clock;
+            let event_context =
+                EventContext::new(event.clone(), &clock).variables(variables.clone());
             debug!(event_context = ?event_context, "Event context");
             self.services
                 .template_service()
@@ -555,6 +835,29 @@ impl<A: Services> Orchestrator<A> {
     }
 }
 
+/// Extracts the complete tool calls from a response, whether they were sent
+/// whole, in parts, or as XML in the content
+fn collect_tool_calls(messages: &[ChatCompletionMessage]) -> anyhow::Result<Vec<ToolCallFull>> {
+    let parts = messages
+        .iter()
+        .flat_map(|message| &message.tool_calls)
+        .filter_map(|tool_call| tool_call.as_partial().cloned())
+        .collect::<Vec<_>>();
+    let content = messages
+        .iter()
+        .flat_map(|message| message.content.iter())
+        .map(|content| content.as_str())
+        .collect::<String>();
+
+    Ok(messages
+        .iter()
+        .flat_map(|message| &message.tool_calls)
+        .filter_map(|tool_call| tool_call.as_full().cloned())
+        .chain(ToolCallFull::try_from_parts(&parts)?)
+        .chain(ToolCallFull::try_from_xml(&content).unwrap_or_default())
+        .collect())
+}
+
 fn is_parse_error(error: &anyhow::Error) -> bool {
     let check = error
         .downcast_ref::<Error>()
@@ -0,0 +1,15 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+/// A patch whose search text didn't match the file, queued for the user to
+/// resolve at the end of the task instead of failing the tool call
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchConflict {
+    pub path: PathBuf,
+    /// Operation of the patch, eg: `replace`
+    pub operation: String,
+    pub search: String,
+    pub content: String,
+}
@@ -0,0 +1,58 @@
+use derive_setters::Setters;
+use serde::Serialize;
+
+/// Progress of a long-running tool, eg: a search over many files
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Setters)]
+#[setters(strip_option, into)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Percentage of the work that is done, between 0 and 100
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percentage: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes_processed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_bytes: Option<u64>,
+}
+
+impl Progress {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self { message: Some(message.into()), ..Default::default() }
+    }
+
+    /// Fraction of the work that is done, from the percentage or else from the
+    /// bytes processed
+    pub fn fraction(&self) -> Option<f64> {
+        let fraction = match (self.percentage, self.bytes_processed, self.total_bytes) {
+            (Some(percentage), _, _) => percentage / 100.0,
+            (None, Some(processed), Some(total)) if total > 0 => processed as f64 / total as f64,
+            _ => return None,
+        };
+        Some(fraction.clamp(0.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_fraction() {
+        let actual = [
+            Progress::new("Searching").percentage(42.0).fraction(),
+            Progress::new("Extracting")
+                .bytes_processed(256u64)
+                .total_bytes(1024u64)
+                .fraction(),
+            Progress::new("Indexing").bytes_processed(256u64).fraction(),
+            Progress::default().percentage(150.0).fraction(),
+        ];
+
+        let expected = [Some(0.42), Some(0.25), None, Some(1.0)];
+        assert_eq!(actual, expected);
+    }
+}
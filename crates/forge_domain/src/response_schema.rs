@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::repair_json;
+
+/// JSON schema that the final response of an agent must conform to. Only the
+/// `type`, `enum`, `const`, `properties`, `required`, `additionalProperties`
+/// and `items` keywords are validated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ResponseSchema(Value);
+
+impl ResponseSchema {
+    pub fn new(schema: Value) -> Self {
+        Self(schema)
+    }
+
+    pub fn as_value(&self) -> &Value {
+        &self.0
+    }
+
+    /// Instructions for models that can't be constrained to the schema
+    /// natively
+    pub fn instructions(&self) -> String {
+        format!(
+            "Respond only with a JSON value that conforms to the following JSON schema, without any surrounding text:\n{}",
+            self.0
+        )
+    }
+
+    /// Parses the response of a model and validates it against the schema,
+    /// returning every violation when it doesn't conform
+    pub fn parse(&self, content: &str) -> Result<Value, Vec<String>> {
+        let value = repair_json(content).map_err(|error| vec![format!("Invalid JSON: {error}")])?;
+        let mut errors = Vec::new();
+        validate(&self.0, &value, "$", &mut errors);
+        if errors.is_empty() {
+            Ok(value)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn validate(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    if let Some(expected) = schema.get("type") {
+        let types = match expected {
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            Value::String(name) => vec![name.as_str()],
+            _ => vec![],
+        };
+        if !types.is_empty() && !types.iter().any(|name| is_type(value, name)) {
+            errors.push(format!("{path}: expected {}", types.join(" or ")));
+            return;
+        }
+    }
+
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            errors.push(format!(
+                "{path}: expected one of {}",
+                Value::Array(allowed.clone())
+            ));
+        }
+    }
+
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            errors.push(format!("{path}: expected {expected}"));
+        }
+    }
+
+    if let Value::Object(object) = value {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    errors.push(format!("{path}: missing required property '{key}'"));
+                }
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (key, item) in object {
+            match properties.and_then(|properties| properties.get(key)) {
+                Some(property) => validate(property, item, &format!("{path}.{key}"), errors),
+                None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                    errors.push(format!("{path}: unexpected property '{key}'"))
+                }
+                None => {}
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (index, item) in items.iter().enumerate() {
+            validate(item_schema, item, &format!("{path}[{index}]"), errors);
+        }
+    }
+}
+
+fn is_type(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    fn fixture() -> ResponseSchema {
+        ResponseSchema::new(json!({
+            "type": "object",
+            "properties": {
+                "severity": { "enum": ["low", "high"] },
+                "files": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["severity", "files"],
+            "additionalProperties": false
+        }))
+    }
+
+    #[test]
+    fn test_parse_valid_response() {
+        let actual = fixture()
+            .parse("```json\n{\"severity\": \"high\", \"files\": [\"src/main.rs\"]}\n```")
+            .unwrap();
+
+        let expected = json!({"severity": "high", "files": ["src/main.rs"]});
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_parse_reports_violations() {
+        let mut actual = fixture()
+            .parse(r#"{"severity": "medium", "files": [1], "notes": ""}"#)
+            .unwrap_err();
+        actual.sort();
+
+        let expected = vec![
+            "$.files[0]: expected string".to_string(),
+            r#"$.severity: expected one of ["low","high"]"#.to_string(),
+            "$: unexpected property 'notes'".to_string(),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_parse_invalid_json() {
+        let actual = fixture().parse("The severity is high");
+        assert!(actual.is_err());
+    }
+}
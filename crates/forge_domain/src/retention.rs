@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Days after which stored files are pruned by default
+const MAX_AGE_DAYS: u64 = 30;
+
+/// Total size of the stored files above which the oldest are pruned by default
+const MAX_TOTAL_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// How long forge keeps the files it stores, ie: session checkpoints,
+/// snapshots, logs and spill files, so that they don't grow without bound
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionPolicy {
+    /// Files older than this many days are pruned. Defaults to 30 days, and 0
+    /// keeps files regardless of their age.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_days: Option<u64>,
+    /// Total size of the stored files, in bytes, above which the oldest are
+    /// pruned. Defaults to 2 GB, and 0 disables the limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_total_bytes: Option<u64>,
+}
+
+impl RetentionPolicy {
+    /// Age after which files are pruned, if any
+    pub fn max_age(&self) -> Option<Duration> {
+        match self.max_age_days.unwrap_or(MAX_AGE_DAYS) {
+            0 => None,
+            days => Some(Duration::from_secs(days * 24 * 60 * 60)),
+        }
+    }
+
+    /// Total size above which the oldest files are pruned, if any
+    pub fn max_total_bytes(&self) -> Option<u64> {
+        match self.max_total_bytes.unwrap_or(MAX_TOTAL_BYTES) {
+            0 => None,
+            bytes => Some(bytes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_limits() {
+        let fixture = [
+            RetentionPolicy::default(),
+            RetentionPolicy { max_age_days: Some(7), max_total_bytes: Some(1024) },
+            RetentionPolicy { max_age_days: Some(0), max_total_bytes: Some(0) },
+        ];
+
+        let actual = fixture
+            .iter()
+            .map(|policy| (policy.max_age(), policy.max_total_bytes()))
+            .collect::<Vec<_>>();
+
+        let expected = vec![
+            (
+                Some(Duration::from_secs(30 * 86400)),
+                Some(2 * 1024 * 1024 * 1024),
+            ),
+            (Some(Duration::from_secs(7 * 86400)), Some(1024)),
+            (None, None),
+        ];
+        assert_eq!(actual, expected);
+    }
+}
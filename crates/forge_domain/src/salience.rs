@@ -0,0 +1,216 @@
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::{ContextMessage, Role};
+
+/// Words of assistant messages that announce a decision
+const DECISION_MARKERS: &[&str] = &[
+    "decided",
+    "decision",
+    "we will",
+    "i will",
+    "going with",
+    "instead of",
+    "chose",
+];
+
+/// Scores how much a turn of the conversation is worth keeping verbatim when
+/// the context is compacted, instead of being summarized. Implementations can
+/// be swapped to experiment with what agents need to remember.
+pub trait SalienceScorer: Send + Sync {
+    /// Scores the messages of a turn, given the messages that follow it. Turns
+    /// scoring 0 or less are always summarized.
+    fn score(&self, turn: &[ContextMessage], following: &[ContextMessage]) -> f64;
+}
+
+/// Scores turns that work on files that are still being worked on, that hit
+/// errors that weren't resolved since, or that announce decisions
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicScorer;
+
+impl HeuristicScorer {
+    const OPEN_FILE: f64 = 2.0;
+    const UNRESOLVED_ERROR: f64 = 3.0;
+    const DECISION: f64 = 1.0;
+}
+
+impl SalienceScorer for HeuristicScorer {
+    fn score(&self, turn: &[ContextMessage], following: &[ContextMessage]) -> f64 {
+        let mut score = 0.0;
+
+        let later_paths = paths(following);
+        if paths(turn).iter().any(|path| later_paths.contains(path)) {
+            score += Self::OPEN_FILE;
+        }
+
+        let unresolved = failed_tools(turn).into_iter().any(|tool| {
+            !following.iter().any(|message| match message {
+                ContextMessage::ToolMessage(result) => {
+                    !result.is_error && result.name.as_str() == tool
+                }
+                _ => false,
+            })
+        });
+        if unresolved {
+            score += Self::UNRESOLVED_ERROR;
+        }
+
+        let decides = turn.iter().any(|message| match message {
+            ContextMessage::ContentMessage(message) if message.role == Role::Assistant => {
+                let content = message.content.to_lowercase();
+                DECISION_MARKERS
+                    .iter()
+                    .any(|marker| content.contains(marker))
+            }
+            _ => false,
+        });
+        if decides {
+            score += Self::DECISION;
+        }
+
+        score
+    }
+}
+
+/// Paths that the tool calls of the messages operate on
+fn paths(messages: &[ContextMessage]) -> HashSet<&str> {
+    messages
+        .iter()
+        .filter_map(|message| match message {
+            ContextMessage::ContentMessage(message) => message.tool_calls.as_ref(),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|call| call.arguments.get("path")?.as_str())
+        .collect()
+}
+
+/// Names of the tools whose calls failed
+fn failed_tools(messages: &[ContextMessage]) -> Vec<&str> {
+    messages
+        .iter()
+        .filter_map(|message| match message {
+            ContextMessage::ToolMessage(result) if result.is_error => Some(result.name.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Splits messages into turns, ie: a message followed by the results of its
+/// tool calls, so that a call is never kept without its result
+pub fn turns(messages: &[ContextMessage]) -> Vec<Range<usize>> {
+    let mut turns: Vec<Range<usize>> = Vec::new();
+    for (index, message) in messages.iter().enumerate() {
+        match (message, turns.last_mut()) {
+            (ContextMessage::ToolMessage(_), Some(turn)) => turn.end = index + 1,
+            _ => turns.push(index..index + 1),
+        }
+    }
+    turns
+}
+
+/// Chooses up to `limit` turns of `messages` to keep verbatim, the ones with
+/// the highest positive scores, in the order of the conversation. Ties are
+/// broken in favor of the most recent turns.
+pub fn salient_turns(
+    scorer: &dyn SalienceScorer,
+    messages: &[ContextMessage],
+    following: &[ContextMessage],
+    limit: usize,
+) -> Vec<Range<usize>> {
+    let mut scored = turns(messages)
+        .into_iter()
+        .map(|turn| {
+            let after = [&messages[turn.end..], following].concat();
+            (scorer.score(&messages[turn.clone()], &after), turn)
+        })
+        .filter(|(score, _)| *score > 0.0)
+        .collect::<Vec<_>>();
+
+    scored.sort_by(|(a, a_turn), (b, b_turn)| {
+        b.total_cmp(a).then_with(|| b_turn.start.cmp(&a_turn.start))
+    });
+    let mut salient = scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, turn)| turn)
+        .collect::<Vec<_>>();
+    salient.sort_by_key(|turn| turn.start);
+    salient
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{ToolCallFull, ToolCallId, ToolName, ToolResult};
+
+    fn call(tool: &str, path: &str) -> ContextMessage {
+        ContextMessage::assistant(
+            "",
+            Some(vec![ToolCallFull {
+                name: ToolName::new(tool),
+                call_id: Some(ToolCallId::new(path)),
+                arguments: json!({ "path": path }),
+            }]),
+        )
+    }
+
+    fn result(tool: &str, is_error: bool) -> ContextMessage {
+        let result = ToolResult::new(ToolName::new(tool)).success("done");
+        if is_error {
+            ContextMessage::tool_result(result.failure(anyhow::anyhow!("failed")))
+        } else {
+            ContextMessage::tool_result(result)
+        }
+    }
+
+    #[test]
+    fn test_turns() {
+        let fixture = vec![
+            ContextMessage::user("Fix the build"),
+            call("forge_tool_fs_read", "/a.rs"),
+            result("forge_tool_fs_read", false),
+            ContextMessage::assistant("Done", None),
+        ];
+
+        let actual = turns(&fixture);
+
+        let expected = vec![0..1, 1..3, 3..4];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_salient_turns() {
+        let fixture = vec![
+            // A file that isn't used later
+            call("forge_tool_fs_read", "/old.rs"),
+            result("forge_tool_fs_read", false),
+            // An error that is resolved later
+            call("forge_tool_process_shell", "/build"),
+            result("forge_tool_process_shell", true),
+            // A file that is still being changed
+            call("forge_tool_fs_read", "/lib.rs"),
+            result("forge_tool_fs_read", false),
+            // An error that is never resolved
+            call("forge_tool_task_run", "/tests"),
+            result("forge_tool_task_run", true),
+            // A decision
+            ContextMessage::assistant("I decided to keep the API", None),
+        ];
+        let following = vec![
+            call("forge_tool_fs_patch", "/lib.rs"),
+            result("forge_tool_process_shell", false),
+        ];
+
+        let actual = [
+            salient_turns(&HeuristicScorer, &fixture, &following, 2),
+            salient_turns(&HeuristicScorer, &fixture, &following, 10),
+        ];
+
+        let expected = [vec![4..6, 6..8], vec![4..6, 6..8, 8..9]];
+        assert_eq!(actual, expected);
+    }
+}
@@ -2,8 +2,8 @@ use std::path::Path;
 
 use crate::{
     Agent, Attachment, ChatCompletionMessage, CompactionResult, Context, Conversation,
-    ConversationId, Environment, File, Model, ModelId, ResultStream, ToolCallContext, ToolCallFull,
-    ToolDefinition, ToolResult, Workflow,
+    ConversationId, Environment, File, FileChange, Model, ModelId, Parameters, ResultStream,
+    ToolCallContext, ToolCallFull, ToolDefinition, ToolResult, Workflow,
 };
 
 #[async_trait::async_trait]
@@ -14,6 +14,12 @@ pub trait ProviderService: Send + Sync + 'static {
         context: Context,
     ) -> ResultStream<ChatCompletionMessage, anyhow::Error>;
     async fn models(&self) -> anyhow::Result<Vec<Model>>;
+
+    /// Probes the capabilities of the model on its server. Returns None when
+    /// they can't be determined.
+    async fn parameters(&self, _model: &ModelId) -> anyhow::Result<Option<Parameters>> {
+        Ok(None)
+    }
 }
 
 #[async_trait::async_trait]
@@ -21,6 +27,11 @@ pub trait ToolService: Send + Sync {
     // TODO: should take `call` by reference
     async fn call(&self, context: ToolCallContext, call: ToolCallFull) -> ToolResult;
     fn list(&self) -> Vec<ToolDefinition>;
+
+    /// Computes the change to a file the call would make, without making it
+    async fn preview(&self, _call: &ToolCallFull) -> anyhow::Result<Option<FileChange>> {
+        Ok(None)
+    }
 }
 
 #[async_trait::async_trait]
@@ -1,13 +1,123 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
 /// Output from a command execution
+#[derive(Debug, Clone)]
 pub struct CommandOutput {
     pub command: String,
     pub stdout: String,
     pub stderr: String,
     pub exit_code: Option<i32>,
+    /// Peak resources used by the command, when they were monitored
+    pub usage: Option<ResourceUsage>,
 }
 
 impl CommandOutput {
     pub fn success(&self) -> bool {
-        self.exit_code.is_none_or(|code| code >= 0)
+        let killed = self
+            .usage
+            .as_ref()
+            .is_some_and(|usage| usage.killed.is_some());
+        !killed && self.exit_code.is_none_or(|code| code >= 0)
+    }
+}
+
+/// Environment variables of a command, on top of the ones it inherits from
+/// forge
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandEnv {
+    /// Variables set for the command, overriding the inherited ones
+    pub vars: BTreeMap<String, String>,
+    /// Whether the command only inherits the variables that a shell needs,
+    /// eg: PATH and HOME
+    pub clean: bool,
+}
+
+impl CommandEnv {
+    /// Whether the command runs with the environment of forge as is
+    pub fn is_inherited(&self) -> bool {
+        self.vars.is_empty() && !self.clean
     }
 }
+
+/// Size of the terminal that a command runs in, in characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalSize {
+    pub rows: u16,
+    pub cols: u16,
+}
+
+impl Default for TerminalSize {
+    fn default() -> Self {
+        Self { rows: 24, cols: 80 }
+    }
+}
+
+/// Output that a command running in the background wrote since it was last
+/// polled
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JobOutput {
+    pub id: u64,
+    pub command: String,
+    pub stdout: String,
+    pub stderr: String,
+    /// Whether the command is still running
+    pub running: bool,
+    /// Exit code of the command once it exited, `None` while it runs or when
+    /// it was killed
+    pub exit_code: Option<i32>,
+}
+
+/// Peak resources used by a command and the processes it spawned
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResourceUsage {
+    pub peak_memory_bytes: u64,
+    pub peak_cpu_percent: f32,
+    /// Why the command was killed, when it exceeded a resource limit or its
+    /// timeout
+    pub killed: Option<String>,
+    /// Whether the command was killed because it ran longer than its timeout
+    pub timed_out: bool,
+}
+
+impl ResourceUsage {
+    /// Usage of a command that was killed because it ran longer than
+    /// `timeout`
+    pub fn timed_out(timeout: Duration) -> Self {
+        Self {
+            killed: Some(format!(
+                "ran longer than the timeout of {} seconds",
+                timeout.as_secs()
+            )),
+            timed_out: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Limits on the resources that tools use, so that a runaway command is killed
+/// before it freezes the machine and runaway writes don't fill the disk
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceLimits {
+    /// Maximum resident memory of a command and the processes it spawned, in
+    /// bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_memory_bytes: Option<u64>,
+    /// Memory that must remain available on the system while a command runs,
+    /// in bytes. Defaults to 5% of the system's memory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_available_memory_bytes: Option<u64>,
+    /// Bytes that tools may write in a session, counting snapshots and spill
+    /// files, before the user is asked whether to continue. Defaults to 1 GB,
+    /// and 0 disables the quota.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_write_bytes: Option<u64>,
+    /// Size of a file, in bytes, above which a patch that only replaces text
+    /// is streamed through the file instead of applied in memory. Defaults to
+    /// 8 MB.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub streaming_patch_bytes: Option<u64>,
+}
@@ -0,0 +1,45 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Color theme used for terminal output
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    /// Picks the dark or light theme based on the background of the terminal
+    #[default]
+    Auto,
+    Dark,
+    Light,
+    Solarized,
+    /// Overrides individual colors of the automatically picked theme. Keys
+    /// are the color roles (eg: `added`, `removed`) and values are color
+    /// names or ANSI 256 color numbers.
+    Custom(BTreeMap<String, String>),
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_theme_from_name() {
+        let actual: Theme = serde_yml::from_str("solarized").unwrap();
+        assert_eq!(actual, Theme::Solarized);
+    }
+
+    #[test]
+    fn test_theme_custom_colors() {
+        let fixture = "custom:\n  added: green\n  removed: '160'\n";
+
+        let actual: Theme = serde_yml::from_str(fixture).unwrap();
+
+        let expected = Theme::Custom(BTreeMap::from([
+            ("added".to_string(), "green".to_string()),
+            ("removed".to_string(), "160".to_string()),
+        ]));
+        assert_eq!(actual, expected);
+    }
+}
@@ -1,7 +1,9 @@
 use schemars::JsonSchema;
 use serde_json::Value;
 
-use crate::{ExecutableTool, NamedTool, ToolCallContext, ToolDefinition, ToolDescription};
+use crate::{
+    ExecutableTool, FileChange, NamedTool, ToolCallContext, ToolDefinition, ToolDescription,
+};
 
 struct JsonTool<T>(T);
 
@@ -22,6 +24,11 @@ where
         let input: T::Input = serde_json::from_value(input)?;
         self.0.call(context, input).await
     }
+
+    async fn preview(&self, input: Self::Input) -> anyhow::Result<Option<FileChange>> {
+        let input: T::Input = serde_json::from_value(input)?;
+        self.0.preview(input).await
+    }
 }
 
 pub struct Tool {
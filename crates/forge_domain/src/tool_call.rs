@@ -3,7 +3,7 @@ use derive_setters::Setters;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::{extract_tag_content, Error, Result, ToolName};
+use crate::{extract_tag_content, parse, repair_json, Error, Result, ToolName};
 
 /// Unique identifier for a using a tool
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -125,14 +125,26 @@ impl ToolCallFull {
         }
     }
 
-    /// Parse multiple tool calls from XML format.
+    /// Parse the tool call that a model without native tool calling wrote in
+    /// a `forge_tool_call` block. The block holds either a JSON object with
+    /// the name and arguments of the tool, or a tag named after the tool with
+    /// a nested tag per argument.
     pub fn try_from_xml(input: &str) -> std::result::Result<Vec<Self>, Error> {
-        match extract_tag_content(input, "forge_tool_call") {
-            None => Ok(Default::default()),
-            Some(content) => Ok(vec![
-                serde_json::from_str(content).map_err(Error::ToolCallArgument)?
-            ]),
+        let Some(content) = extract_tag_content(input, "forge_tool_call") else {
+            return Ok(Default::default());
+        };
+        if content.starts_with('<') {
+            return parse(&format!("<forge_tool_call>{content}</forge_tool_call>"));
         }
+
+        let value = repair_json(content).map_err(Error::ToolCallArgument)?;
+        let mut tool_call: Self = serde_json::from_value(value).map_err(Error::ToolCallArgument)?;
+
+        // Some models encode the arguments as a JSON string
+        if let Value::String(arguments) = &tool_call.arguments {
+            tool_call.arguments = repair_json(arguments).map_err(Error::ToolCallArgument)?;
+        }
+        Ok(vec![tool_call])
     }
 }
 
@@ -203,6 +215,19 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_truncated_tool_call() {
+        let input = [ToolCallPart {
+            call_id: Some(ToolCallId("call_1".to_string())),
+            name: Some(ToolName::new("forge_tool_fs_write")),
+            arguments_part: "{\"path\": \"/a.rs\", \"content\": \"fn main() {".to_string(),
+        }];
+
+        let actual = ToolCallFull::try_from_parts(&input);
+
+        assert!(matches!(actual, Err(Error::ToolCallArgument(_))));
+    }
+
     #[test]
     fn test_empty_call_parts() {
         let actual = ToolCallFull::try_from_parts(&[]).unwrap();
@@ -211,6 +236,48 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_xml_with_repaired_json() {
+        let message = "<forge_tool_call>\n```json\n{\"name\": \"forge_tool_fs_read\", \"arguments\": {\"path\": \"/a/b.txt\",},}\n```\n</forge_tool_call>";
+
+        let actual = ToolCallFull::try_from_xml(message).unwrap();
+
+        let expected = vec![ToolCallFull {
+            name: ToolName::new("forge_tool_fs_read"),
+            call_id: None,
+            arguments: serde_json::json!({"path": "/a/b.txt"}),
+        }];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_xml_with_encoded_arguments() {
+        let message = r#"<forge_tool_call>{"name": "forge_tool_fs_read", "arguments": "{\"path\": \"/a/b.txt\"}"}</forge_tool_call>"#;
+
+        let actual = ToolCallFull::try_from_xml(message).unwrap();
+
+        let expected = vec![ToolCallFull {
+            name: ToolName::new("forge_tool_fs_read"),
+            call_id: None,
+            arguments: serde_json::json!({"path": "/a/b.txt"}),
+        }];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_xml_with_argument_tags() {
+        let message = "<forge_tool_call>\n<forge_tool_fs_read>\n<path>/a/b.txt</path>\n</forge_tool_fs_read>\n</forge_tool_call>";
+
+        let actual = ToolCallFull::try_from_xml(message).unwrap();
+
+        let expected = vec![ToolCallFull {
+            name: ToolName::new("forge_tool_fs_read"),
+            call_id: None,
+            arguments: serde_json::json!({"path": "/a/b.txt"}),
+        }];
+        assert_eq!(actual, expected);
+    }
+
     #[test]
     fn test_real_example() {
         let message = include_str!("./fixtures/tool_call_01.md");
@@ -1,10 +1,12 @@
+use std::future::Future;
 use std::sync::Arc;
 
 use derive_setters::Setters;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 
-use crate::{AgentId, AgentMessage, ChatResponse};
+use crate::{AgentId, AgentMessage, ChatResponse, GitPolicy, Moderation, PatchConflict, Progress};
 
 /// Type alias for Arc<Sender<Result<AgentMessage<ChatResponse>>>>
 type ArcSender = Arc<Sender<anyhow::Result<AgentMessage<ChatResponse>>>>;
@@ -19,6 +21,20 @@ pub struct ToolCallContext {
     /// This is wrapped in an RWLock for thread-safety
     #[setters(skip)]
     pub is_complete: Arc<RwLock<bool>>,
+    /// Cancelled when the user stops the conversation, so that tools can stop
+    /// their work without leaving processes or half-written files behind
+    pub cancellation: CancellationToken,
+    /// Naming policy that git commands run by tools are conformed to
+    #[setters(strip_option)]
+    pub git_policy: Option<GitPolicy>,
+    /// Moderation of the content that tools write to files or execute
+    pub moderation: Option<Moderation>,
+    /// Whether the tools that change files or run commands are only
+    /// simulated, eg: for demos
+    pub dry_run: bool,
+    /// Whether patches that don't match are queued for the user instead of
+    /// failing
+    pub defer_patch_conflicts: bool,
 }
 
 impl ToolCallContext {
@@ -28,6 +44,25 @@ impl ToolCallContext {
             agent_id: None,
             sender: None,
             is_complete: Arc::new(RwLock::new(false)),
+            cancellation: CancellationToken::new(),
+            git_policy: None,
+            moderation: None,
+            dry_run: false,
+            defer_patch_conflicts: false,
+        }
+    }
+
+    /// Checks whether the user has cancelled the tool call
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    /// Runs a future until it completes, or fails as soon as the user cancels
+    /// the tool call, eg: a request that is still waiting for its response
+    pub async fn until_cancelled<F: Future>(&self, future: F) -> anyhow::Result<F::Output> {
+        tokio::select! {
+            output = future => Ok(output),
+            _ = self.cancellation.cancelled() => anyhow::bail!("Cancelled by the user"),
         }
     }
 
@@ -67,6 +102,45 @@ impl ToolCallContext {
         }
     }
 
+    /// Sends the diff of a file change so that it can be shown in a pager
+    pub async fn send_diff(&self, diff: impl ToString) -> anyhow::Result<()> {
+        if let Some(agent_id) = &self.agent_id {
+            self.send(AgentMessage::new(
+                agent_id.clone(),
+                ChatResponse::Diff(diff.to_string()),
+            ))
+            .await
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Queues a patch that didn't match for the user to resolve
+    pub async fn send_patch_conflict(&self, conflict: PatchConflict) -> anyhow::Result<()> {
+        if let Some(agent_id) = &self.agent_id {
+            self.send(AgentMessage::new(
+                agent_id.clone(),
+                ChatResponse::PatchConflict(conflict),
+            ))
+            .await
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Reports the progress of a long-running tool
+    pub async fn send_progress(&self, progress: Progress) -> anyhow::Result<()> {
+        if let Some(agent_id) = &self.agent_id {
+            self.send(AgentMessage::new(
+                agent_id.clone(),
+                ChatResponse::ToolCallProgress(progress),
+            ))
+            .await
+        } else {
+            Ok(())
+        }
+    }
+
     pub async fn send_text(&self, content: impl ToString) -> anyhow::Result<()> {
         if let Some(agent_id) = &self.agent_id {
             self.send(AgentMessage::new(
@@ -108,6 +182,16 @@ mod tests {
         assert!(context.get_complete().await);
     }
 
+    #[test]
+    fn test_cancellation() {
+        let token = CancellationToken::new();
+        let context = ToolCallContext::default().cancellation(token.child_token());
+        assert!(!context.is_cancelled());
+
+        token.cancel();
+        assert!(context.is_cancelled());
+    }
+
     #[test]
     fn test_with_sender() {
         // This is just a type check test - we don't actually create a sender
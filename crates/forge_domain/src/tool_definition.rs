@@ -4,7 +4,7 @@ use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
-use crate::{NamedTool, ToolCallContext, ToolName};
+use crate::{FileChange, NamedTool, ToolCallContext, ToolName};
 
 ///
 /// Refer to the specification over here:
@@ -57,4 +57,10 @@ pub trait ExecutableTool {
     type Input: DeserializeOwned;
 
     async fn call(&self, context: ToolCallContext, input: Self::Input) -> anyhow::Result<String>;
+
+    /// Computes the change the tool would make to a file, without applying
+    /// it. Tools that don't edit files return `None`.
+    async fn preview(&self, _input: Self::Input) -> anyhow::Result<Option<FileChange>> {
+        Ok(None)
+    }
 }
@@ -1,5 +1,11 @@
+use std::fmt::Display;
+
 use serde::{Deserialize, Serialize};
 
+/// Separator between the namespace of a tool and its name, eg:
+/// `mcp:github:create_issue`
+const NAMESPACE_SEPARATOR: char = ':';
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct ToolName(String);
@@ -8,6 +14,16 @@ impl ToolName {
     pub fn new(value: impl ToString) -> Self {
         ToolName(value.to_string())
     }
+
+    /// Name of a tool within a namespace, eg: `plugin:lint:check`. Built-in
+    /// tools keep their plain names, which agents and workflows refer to.
+    pub fn namespaced(namespace: &ToolNamespace, name: impl Display) -> Self {
+        match namespace {
+            ToolNamespace::Builtin => ToolName::new(name),
+            ToolNamespace::Mcp(server) => ToolName::new(format!("mcp:{server}:{name}")),
+            ToolNamespace::Plugin(plugin) => ToolName::new(format!("plugin:{plugin}:{name}")),
+        }
+    }
 }
 
 impl ToolName {
@@ -18,8 +34,81 @@ impl ToolName {
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// Namespace that the tool was registered in. Names without a known
+    /// namespace are those of built-in tools.
+    pub fn namespace(&self) -> ToolNamespace {
+        let mut parts = self.0.splitn(3, NAMESPACE_SEPARATOR);
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some("mcp"), Some(server), Some(_)) => ToolNamespace::Mcp(server.to_string()),
+            (Some("plugin"), Some(plugin), Some(_)) => ToolNamespace::Plugin(plugin.to_string()),
+            _ => ToolNamespace::Builtin,
+        }
+    }
+
+    /// Name of a built-in tool without its explicit `builtin:` namespace, so
+    /// that `builtin:forge_tool_fs_read` refers to `forge_tool_fs_read`
+    pub fn without_builtin_namespace(&self) -> Option<ToolName> {
+        self.0.strip_prefix("builtin:").map(ToolName::new)
+    }
+}
+
+/// Source that a tool was registered from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ToolNamespace {
+    Builtin,
+    /// Tool of an MCP server, by the name of the server
+    Mcp(String),
+    /// Tool of a plugin, by the name of the plugin
+    Plugin(String),
+}
+
+impl Display for ToolNamespace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToolNamespace::Builtin => write!(f, "builtin"),
+            ToolNamespace::Mcp(server) => write!(f, "mcp:{server}"),
+            ToolNamespace::Plugin(plugin) => write!(f, "plugin:{plugin}"),
+        }
+    }
 }
 
 pub trait NamedTool {
     fn tool_name() -> ToolName;
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_namespace() {
+        let fixture = [
+            ToolName::new("forge_tool_fs_read"),
+            ToolName::namespaced(&ToolNamespace::Mcp("github".to_string()), "create_issue"),
+            ToolName::namespaced(&ToolNamespace::Plugin("lint".to_string()), "check"),
+            ToolName::new("mcp:incomplete"),
+        ];
+
+        let actual = fixture
+            .iter()
+            .map(|name| (name.as_str().to_string(), name.namespace()))
+            .collect::<Vec<_>>();
+
+        let expected = vec![
+            ("forge_tool_fs_read".to_string(), ToolNamespace::Builtin),
+            (
+                "mcp:github:create_issue".to_string(),
+                ToolNamespace::Mcp("github".to_string()),
+            ),
+            (
+                "plugin:lint:check".to_string(),
+                ToolNamespace::Plugin("lint".to_string()),
+            ),
+            ("mcp:incomplete".to_string(), ToolNamespace::Builtin),
+        ];
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+
+use schemars::schema::Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::{ModelId, ToolDefinition, ToolName};
+
+/// Replaces the documentation of a tool from the configuration, eg: to tune
+/// the description of a tool for a model without recompiling. Overrides are
+/// named after a variant, so that the success of the tool calls can be
+/// compared between variants.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolOverride {
+    /// Name of the tool, eg: `forge_tool_fs_read`
+    pub tool: ToolName,
+
+    /// Model that the override applies to. When unset, it applies to every
+    /// model without an override of its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<ModelId>,
+
+    /// Name of the variant, reported with the tool calls
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+
+    /// Description that replaces the tool's own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Descriptions that replace the ones of the tool's parameters, by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, String>,
+}
+
+impl ToolOverride {
+    /// Override of `tool` for `model`, preferring the ones specific to the
+    /// model
+    pub fn find<'a>(
+        overrides: &'a [ToolOverride],
+        tool: &ToolName,
+        model: Option<&ModelId>,
+    ) -> Option<&'a ToolOverride> {
+        let mut matching = overrides.iter().filter(|o| &o.tool == tool);
+        matching
+            .clone()
+            .find(|o| o.model.is_some() && o.model.as_ref() == model)
+            .or_else(|| matching.find(|o| o.model.is_none()))
+    }
+
+    /// Applies the override to the definition of its tool. Parameters that
+    /// the tool doesn't have are ignored.
+    pub fn apply(&self, mut tool: ToolDefinition) -> ToolDefinition {
+        if let Some(description) = &self.description {
+            tool.description = description.clone();
+        }
+        if let Some(object) = tool.input_schema.schema.object.as_mut() {
+            for (name, description) in &self.parameters {
+                if let Some(Schema::Object(property)) = object.properties.get_mut(name) {
+                    property.metadata().description = Some(description.clone());
+                }
+            }
+        }
+        tool
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use schemars::JsonSchema;
+
+    use super::*;
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Input {
+        /// The path of the file
+        path: String,
+    }
+
+    fn fixture() -> Vec<ToolOverride> {
+        vec![
+            ToolOverride {
+                tool: ToolName::new("forge_tool_fs_read"),
+                model: None,
+                variant: Some("default".to_string()),
+                description: Some("Reads a file".to_string()),
+                parameters: BTreeMap::from([
+                    ("path".to_string(), "Absolute path".to_string()),
+                    ("missing".to_string(), "Ignored".to_string()),
+                ]),
+            },
+            ToolOverride {
+                tool: ToolName::new("forge_tool_fs_read"),
+                model: Some(ModelId::new("qwen")),
+                variant: Some("short".to_string()),
+                description: Some("Read".to_string()),
+                parameters: BTreeMap::new(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_find() {
+        let fixture = fixture();
+        let tool = ToolName::new("forge_tool_fs_read");
+
+        let actual = [
+            ToolOverride::find(&fixture, &tool, Some(&ModelId::new("qwen"))),
+            ToolOverride::find(&fixture, &tool, Some(&ModelId::new("gpt-4o"))),
+            ToolOverride::find(&fixture, &ToolName::new("forge_tool_fs_create"), None),
+        ]
+        .map(|o| o.and_then(|o| o.variant.clone()));
+
+        let expected = [Some("short".to_string()), Some("default".to_string()), None];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_apply() {
+        let tool = ToolDefinition::new("forge_tool_fs_read")
+            .description("Original")
+            .input_schema(schemars::schema_for!(Input));
+
+        let actual = fixture()[0].apply(tool);
+
+        let path = match &actual
+            .input_schema
+            .schema
+            .object
+            .as_ref()
+            .unwrap()
+            .properties["path"]
+        {
+            Schema::Object(property) => property.metadata.as_ref().unwrap().description.clone(),
+            Schema::Bool(_) => None,
+        };
+        assert_eq!(actual.description, "Reads a file");
+        assert_eq!(path, Some("Absolute path".to_string()));
+    }
+}
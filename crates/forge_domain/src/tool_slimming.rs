@@ -0,0 +1,192 @@
+use derive_setters::Setters;
+use serde::{Deserialize, Serialize};
+
+use crate::{NamedTool, Parameters, ToolDefinition, ToolName};
+
+/// Context length at or below which the tools are slimmed by default
+const MAX_CONTEXT_LENGTH: u64 = 32_768;
+
+/// Maximum number of characters of a slimmed tool description by default
+const MAX_DESCRIPTION_LENGTH: usize = 200;
+
+/// Tools that are always sent when slimming, unless configured otherwise
+const CORE_TOOLS: &[&str] = &[
+    "forge_tool_fs_read",
+    "forge_tool_fs_create",
+    "forge_tool_fs_patch",
+    "forge_tool_fs_search",
+    "forge_tool_process_shell",
+    "forge_tool_attempt_completion",
+    "forge_tool_followup",
+];
+
+/// Reduces the tools sent to models with a small context or without native
+/// tool calling, since dozens of verbose schemas overwhelm small models. Only
+/// the core tools are sent, with shortened descriptions, and the model can
+/// list the others on demand.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Setters)]
+#[setters(strip_option, into)]
+pub struct ToolSlimming {
+    /// Forces slimming on or off. When unset, the tools are slimmed if the
+    /// server doesn't support native tool calling or has a small context.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+
+    /// Context length, in tokens, at or below which the tools are slimmed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_context_length: Option<u64>,
+
+    /// Tools that are always sent when slimming
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub core_tools: Option<Vec<ToolName>>,
+
+    /// Maximum number of characters of the description of a core tool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_description_length: Option<usize>,
+}
+
+impl NamedTool for ToolSlimming {
+    fn tool_name() -> ToolName {
+        ToolName::new("forge_tool_tools_list_more")
+    }
+}
+
+impl ToolSlimming {
+    /// Definition of the meta-tool that lists the tools left out
+    pub fn tool_definition() -> ToolDefinition {
+        ToolDefinition::new(Self::tool_name()).description(
+            "Lists the tools that were left out to keep the request small, with their full descriptions and parameters. Use it when none of the available tools fits the task.",
+        )
+    }
+
+    /// Whether the tools should be slimmed for a server with the given
+    /// capabilities
+    pub fn should_slim(&self, parameters: &Parameters) -> bool {
+        self.enabled.unwrap_or_else(|| {
+            let max = self.max_context_length.unwrap_or(MAX_CONTEXT_LENGTH);
+            !parameters.tool_supported
+                || parameters
+                    .context_length
+                    .is_some_and(|length| length <= max)
+        })
+    }
+
+    fn is_core(&self, tool: &ToolDefinition) -> bool {
+        match &self.core_tools {
+            Some(core_tools) => core_tools.contains(&tool.name),
+            None => CORE_TOOLS.contains(&tool.name.as_str()),
+        }
+    }
+
+    /// Keeps the core tools, with shortened descriptions, and adds the
+    /// meta-tool when others were left out
+    pub fn slim(&self, tools: Vec<ToolDefinition>) -> Vec<ToolDefinition> {
+        let limit = self
+            .max_description_length
+            .unwrap_or(MAX_DESCRIPTION_LENGTH);
+        let total = tools.len();
+        let mut slimmed = tools
+            .into_iter()
+            .filter(|tool| self.is_core(tool))
+            .map(|tool| {
+                let description = shorten(&tool.description, limit);
+                tool.description(description)
+            })
+            .collect::<Vec<_>>();
+        if slimmed.len() < total {
+            slimmed.push(Self::tool_definition());
+        }
+        slimmed
+    }
+
+    /// Tools that are left out when slimming
+    pub fn more_tools(&self, tools: Vec<ToolDefinition>) -> Vec<ToolDefinition> {
+        tools
+            .into_iter()
+            .filter(|tool| !self.is_core(tool))
+            .collect()
+    }
+}
+
+/// First sentence of a description, cut at a word boundary to `limit`
+/// characters
+fn shorten(description: &str, limit: usize) -> String {
+    let line = description.trim().lines().next().unwrap_or_default();
+    let sentence = match line.find(". ") {
+        Some(end) => &line[..=end],
+        None => line,
+    };
+    if sentence.chars().count() <= limit {
+        return sentence.to_string();
+    }
+    let cut = sentence.chars().take(limit).collect::<String>();
+    let cut = cut
+        .rsplit_once(' ')
+        .map_or(cut.as_str(), |(words, _)| words);
+    format!("{}...", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_should_slim() {
+        let fixture = ToolSlimming::default();
+
+        let actual = [
+            fixture.should_slim(&Parameters::new(false)),
+            fixture.should_slim(&Parameters::new(true)),
+            fixture.should_slim(&Parameters { tool_supported: true, context_length: Some(8192) }),
+            fixture
+                .should_slim(&Parameters { tool_supported: true, context_length: Some(200_000) }),
+            fixture
+                .clone()
+                .enabled(false)
+                .should_slim(&Parameters::new(false)),
+        ];
+
+        let expected = [true, false, true, false, false];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_slim() {
+        let fixture = vec![
+            ToolDefinition::new("forge_tool_fs_read")
+                .description("Reads a file. Supports text and PDF files.\nMore details."),
+            ToolDefinition::new("forge_tool_net_fetch").description("Fetches a URL."),
+            ToolDefinition::new("forge_tool_process_shell")
+                .description("Executes a shell command in the current working directory"),
+        ];
+        let slimming = ToolSlimming::default().max_description_length(20usize);
+
+        let actual = slimming
+            .slim(fixture.clone())
+            .into_iter()
+            .map(|tool| (tool.name.into_string(), tool.description))
+            .collect::<Vec<_>>();
+
+        let expected = vec![
+            (
+                "forge_tool_fs_read".to_string(),
+                "Reads a file.".to_string(),
+            ),
+            (
+                "forge_tool_process_shell".to_string(),
+                "Executes a shell...".to_string(),
+            ),
+            (
+                "forge_tool_tools_list_more".to_string(),
+                ToolSlimming::tool_definition().description,
+            ),
+        ];
+        assert_eq!(actual, expected);
+
+        let more = slimming.more_tools(fixture);
+        assert_eq!(more.len(), 1);
+        assert_eq!(more[0].name.as_str(), "forge_tool_net_fetch");
+    }
+}
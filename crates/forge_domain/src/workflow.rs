@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::temperature::Temperature;
-use crate::{Agent, AgentId, ModelId};
+use crate::{Agent, AgentId, GitPolicy, ModelId, Moderation, Theme, ToolOverride, ToolSlimming};
 
 /// Configuration for a workflow that contains all settings
 /// required to initialize a workflow.
@@ -45,6 +45,35 @@ pub struct Workflow {
     #[merge(strategy = crate::merge::option)]
     pub custom_rules: Option<String>,
 
+    /// Naming policy of the commits and branches that agents create
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub git_policy: Option<GitPolicy>,
+
+    /// Reduced tool set for models with a small context or without native
+    /// tool calling, applied to all agents
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub tool_slimming: Option<ToolSlimming>,
+
+    /// Descriptions of tools and their parameters that replace the built-in
+    /// ones, for all agents
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub tool_overrides: Option<Vec<ToolOverride>>,
+
+    /// Moderation of the content that agents write to files or execute, for
+    /// all agents
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub moderation: Option<Moderation>,
+
+    /// Queues the patches whose search text doesn't match for the user to
+    /// resolve at the end of the task, for all agents
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub defer_patch_conflicts: Option<bool>,
+
     /// Temperature used for all agents
     ///
     /// Temperature controls the randomness in the model's output.
@@ -67,6 +96,34 @@ pub struct Workflow {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[merge(strategy = crate::merge::option)]
     pub tool_supported: Option<bool>,
+
+    /// Number of lines above which diffs of file changes are shown in a pager
+    /// instead of being printed. Set to 0 to always print diffs.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub diff_pager_threshold: Option<usize>,
+
+    /// Color theme used for terminal output. Defaults to picking a dark or
+    /// light theme based on the background of the terminal.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub theme: Option<Theme>,
+
+    /// Flag to replace emoji and other decorative glyphs in the output with
+    /// plain ASCII markers, for terminals whose font doesn't include them.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub ascii: Option<bool>,
+
+    /// Estimated cost, in USD, above which switching from plan to act mode
+    /// asks for approval. The estimate is shown regardless.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[merge(strategy = crate::merge::option)]
+    pub plan_budget: Option<f64>,
 }
 
 impl Default for Workflow {
@@ -100,8 +157,17 @@ impl Workflow {
             model: None,
             max_walker_depth: None,
             custom_rules: None,
+            git_policy: None,
+            tool_slimming: None,
+            tool_overrides: None,
+            moderation: None,
+            defer_patch_conflicts: None,
             temperature: None,
             tool_supported: None,
+            diff_pager_threshold: None,
+            theme: None,
+            ascii: None,
+            plan_budget: None,
         }
     }
 
@@ -137,6 +203,10 @@ mod tests {
         assert_eq!(actual.custom_rules, None);
         assert_eq!(actual.temperature, None);
         assert_eq!(actual.tool_supported, None);
+        assert_eq!(actual.diff_pager_threshold, None);
+        assert_eq!(actual.theme, None);
+        assert_eq!(actual.ascii, None);
+        assert_eq!(actual.plan_budget, None);
     }
 
     #[test]
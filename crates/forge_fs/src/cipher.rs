@@ -0,0 +1,241 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// Header of the files encrypted by forge, followed by the nonce and the
+/// ciphertext
+const MAGIC: &[u8] = b"FORGE-AES256GCM\n";
+
+/// Service and account of the key in the OS keychain
+const KEYCHAIN_SERVICE: &str = "forge";
+const KEYCHAIN_ACCOUNT: &str = "encryption-key";
+
+static CIPHER: OnceLock<Cipher> = OnceLock::new();
+
+/// Encrypts the files that forge persists, eg: session checkpoints and
+/// snapshots, with AES-256-GCM. The key comes from `FORGE_ENCRYPTION_KEY` or
+/// the OS keychain.
+pub struct Cipher {
+    key: LessSafeKey,
+}
+
+impl std::fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Cipher")
+    }
+}
+
+impl Cipher {
+    /// Creates a cipher from a 256-bit key
+    pub fn new(key: &[u8]) -> Result<Self> {
+        let key = UnboundKey::new(&AES_256_GCM, key)
+            .map_err(|_| anyhow!("The encryption key must be 32 bytes long"))?;
+        Ok(Self { key: LessSafeKey::new(key) })
+    }
+
+    /// Cipher enabled by `FORGE_ENCRYPT`, with the base64 key from
+    /// `FORGE_ENCRYPTION_KEY` or the OS keychain. A key is generated and
+    /// stored in the keychain the first time.
+    pub fn from_env() -> Result<Option<Self>> {
+        let enabled =
+            std::env::var("FORGE_ENCRYPT").is_ok_and(|value| matches!(value.trim(), "1" | "true"));
+        if !enabled {
+            return Ok(None);
+        }
+
+        let key = match std::env::var("FORGE_ENCRYPTION_KEY") {
+            Ok(key) => key,
+            Err(_) => keychain_key()?,
+        };
+        let key = STANDARD
+            .decode(key.trim())
+            .context("The encryption key must be base64 encoded")?;
+        Self::new(&key).map(Some)
+    }
+
+    /// Whether the content was encrypted by a cipher
+    pub fn is_encrypted(content: &[u8]) -> bool {
+        content.starts_with(MAGIC)
+    }
+
+    pub fn encrypt(&self, content: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("Failed to generate a nonce"))?;
+
+        let mut sealed = content.to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow!("Failed to encrypt the content"))?;
+
+        let mut output = Vec::with_capacity(MAGIC.len() + NONCE_LEN + sealed.len());
+        output.extend_from_slice(MAGIC);
+        output.extend_from_slice(&nonce);
+        output.extend_from_slice(&sealed);
+        Ok(output)
+    }
+
+    pub fn decrypt(&self, content: &[u8]) -> Result<Vec<u8>> {
+        let sealed = content
+            .strip_prefix(MAGIC)
+            .filter(|sealed| sealed.len() >= NONCE_LEN)
+            .context("The content is not encrypted")?;
+        let (nonce, sealed) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| anyhow!("Invalid nonce in the encrypted content"))?;
+
+        let mut sealed = sealed.to_vec();
+        let plain = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut sealed)
+            .map_err(|_| {
+                anyhow!("Failed to decrypt the content, the encryption key may have changed")
+            })?;
+        Ok(plain.to_vec())
+    }
+}
+
+/// Key from the OS keychain, generating one the first time
+fn keychain_key() -> Result<String> {
+    if let Some(key) = keychain_get() {
+        return Ok(key);
+    }
+
+    let mut key = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut key)
+        .map_err(|_| anyhow!("Failed to generate the encryption key"))?;
+    let key = STANDARD.encode(key);
+    keychain_set(&key)?;
+    Ok(key)
+}
+
+fn keychain_get() -> Option<String> {
+    let output = if cfg!(target_os = "macos") {
+        Command::new("security")
+            .args(["find-generic-password", "-w"])
+            .args(["-s", KEYCHAIN_SERVICE, "-a", KEYCHAIN_ACCOUNT])
+            .output()
+    } else {
+        Command::new("secret-tool")
+            .args(["lookup", "service", KEYCHAIN_SERVICE])
+            .args(["account", KEYCHAIN_ACCOUNT])
+            .output()
+    }
+    .ok()?;
+    let key = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !key.is_empty()).then_some(key)
+}
+
+fn keychain_set(key: &str) -> Result<()> {
+    // The secret is written to stdin, so that it doesn't show in the process
+    // list. `security` reads it as part of a command in interactive mode.
+    let status = if cfg!(target_os = "macos") {
+        let command = format!(
+            "add-generic-password -U -s {KEYCHAIN_SERVICE} -a {KEYCHAIN_ACCOUNT} -w {key}\n"
+        );
+        run_with_stdin(
+            Command::new("security").arg("-i").stdout(Stdio::null()),
+            &command,
+        )
+    } else {
+        run_with_stdin(
+            Command::new("secret-tool")
+                .args(["store", "--label", "Forge encryption key"])
+                .args(["service", KEYCHAIN_SERVICE, "account", KEYCHAIN_ACCOUNT]),
+            key,
+        )
+    };
+    // `security` succeeds in interactive mode even when its command fails, so
+    // the key is read back
+    match status {
+        Ok(status) if status.success() && keychain_get().as_deref() == Some(key) => Ok(()),
+        _ => bail!(
+            "Failed to store the encryption key in the OS keychain, set FORGE_ENCRYPTION_KEY to a base64 encoded 32 byte key instead"
+        ),
+    }
+}
+
+/// Runs the command with `input` written to its stdin
+fn run_with_stdin(command: &mut Command, input: &str) -> std::io::Result<ExitStatus> {
+    let mut child = command.stdin(Stdio::piped()).spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+    }
+    child.wait()
+}
+
+impl crate::ForgeFS {
+    /// Sets the cipher that encrypts the files written with `write_sealed`.
+    /// Returns false when a cipher is already set.
+    pub fn set_cipher(cipher: Cipher) -> bool {
+        CIPHER.set(cipher).is_ok()
+    }
+
+    /// Encrypts the content when a cipher is set
+    pub fn seal(content: &[u8]) -> Result<Vec<u8>> {
+        match CIPHER.get() {
+            Some(cipher) => cipher.encrypt(content),
+            None => Ok(content.to_vec()),
+        }
+    }
+
+    /// Decrypts the content when it is encrypted, so that files written
+    /// before encryption was enabled can still be read
+    pub fn unseal(content: Vec<u8>) -> Result<Vec<u8>> {
+        if !Cipher::is_encrypted(&content) {
+            return Ok(content);
+        }
+        match CIPHER.get() {
+            Some(cipher) => cipher.decrypt(&content),
+            None => bail!("The file is encrypted, set FORGE_ENCRYPT=true to read it"),
+        }
+    }
+
+    /// Writes a file that forge persists, encrypted when a cipher is set
+    pub async fn write_sealed<T: AsRef<Path>, U: AsRef<[u8]>>(path: T, contents: U) -> Result<()> {
+        let contents = Self::seal(contents.as_ref())?;
+        Self::write_atomic(path, contents).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let fixture = Cipher::new(&[7u8; 32]).unwrap();
+
+        let encrypted = fixture.encrypt(b"fn main() {}").unwrap();
+        let actual = fixture.decrypt(&encrypted).unwrap();
+
+        assert!(Cipher::is_encrypted(&encrypted));
+        assert!(!encrypted.windows(4).any(|window| window == b"main"));
+        assert_eq!(actual, b"fn main() {}".to_vec());
+    }
+
+    #[test]
+    fn test_decrypt_with_another_key() {
+        let encrypted = Cipher::new(&[1u8; 32]).unwrap().encrypt(b"secret").unwrap();
+
+        let actual = Cipher::new(&[2u8; 32]).unwrap().decrypt(&encrypted);
+
+        assert!(actual.is_err());
+        assert!(Cipher::new(&[0u8; 16]).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt::{self, Display};
+
+/// Byte order marks, by the encoding they identify
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+pub(crate) const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+pub(crate) const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Encoding of a text file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// UTF-8 starting with a byte order mark
+    Utf8Bom,
+    /// UTF-16, little endian, starting with a byte order mark
+    Utf16Le,
+    /// UTF-16, big endian, starting with a byte order mark
+    Utf16Be,
+    /// Any content that isn't valid UTF-8, read byte by byte
+    Latin1,
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf8Bom => "utf-8-bom",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Latin1 => "latin-1",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Line endings of a text file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineEnding::Lf => write!(f, "lf"),
+            LineEnding::Crlf => write!(f, "crlf"),
+        }
+    }
+}
+
+/// Content of a text file, decoded to UTF-8 with `\n` line endings, along with
+/// the encoding and line endings that it is written back with
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextFile {
+    pub content: String,
+    pub encoding: Encoding,
+    pub line_ending: LineEnding,
+}
+
+impl TextFile {
+    /// Decodes the content of a file. Line endings are only converted when
+    /// every line ends with `\r\n`, so that files with mixed line endings are
+    /// written back unchanged.
+    pub fn decode(bytes: &[u8]) -> Self {
+        let (content, encoding) = if let Some(bytes) = bytes.strip_prefix(UTF8_BOM) {
+            (
+                String::from_utf8_lossy(bytes).into_owned(),
+                Encoding::Utf8Bom,
+            )
+        } else if let Some(bytes) = bytes.strip_prefix(UTF16LE_BOM) {
+            (decode_utf16(bytes, u16::from_le_bytes), Encoding::Utf16Le)
+        } else if let Some(bytes) = bytes.strip_prefix(UTF16BE_BOM) {
+            (decode_utf16(bytes, u16::from_be_bytes), Encoding::Utf16Be)
+        } else {
+            match std::str::from_utf8(bytes) {
+                Ok(content) => (content.to_string(), Encoding::Utf8),
+                Err(_) => (
+                    bytes.iter().map(|&byte| byte as char).collect(),
+                    Encoding::Latin1,
+                ),
+            }
+        };
+
+        let lines = content.matches('\n').count();
+        if lines > 0 && content.matches("\r\n").count() == lines {
+            Self {
+                content: content.replace("\r\n", "\n"),
+                encoding,
+                line_ending: LineEnding::Crlf,
+            }
+        } else {
+            Self { content, encoding, line_ending: LineEnding::Lf }
+        }
+    }
+
+    /// Encodes content with the encoding and line endings of the file.
+    /// Characters that Latin-1 can't represent are replaced with `?`.
+    pub fn encode(&self, content: &str) -> Vec<u8> {
+        let content = match self.line_ending {
+            LineEnding::Lf => content.to_string(),
+            LineEnding::Crlf => content.replace("\r\n", "\n").replace('\n', "\r\n"),
+        };
+
+        match self.encoding {
+            Encoding::Utf8 => content.into_bytes(),
+            Encoding::Utf8Bom => [UTF8_BOM, content.as_bytes()].concat(),
+            Encoding::Utf16Le => encode_utf16(&content, UTF16LE_BOM, u16::to_le_bytes),
+            Encoding::Utf16Be => encode_utf16(&content, UTF16BE_BOM, u16::to_be_bytes),
+            Encoding::Latin1 => content
+                .chars()
+                .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+                .collect(),
+        }
+    }
+
+    /// Whether the file is written back as it was read, ie: in UTF-8 with `\n`
+    /// line endings
+    pub fn is_plain(&self) -> bool {
+        self.encoding == Encoding::Utf8 && self.line_ending == LineEnding::Lf
+    }
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+    let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+fn encode_utf16(content: &str, bom: &[u8], bytes: fn(u16) -> [u8; 2]) -> Vec<u8> {
+    let mut encoded = bom.to_vec();
+    encoded.extend(content.encode_utf16().flat_map(bytes));
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let utf16le = [
+            UTF16LE_BOM,
+            "a\r\nb"
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes)
+                .collect::<Vec<_>>()
+                .as_slice(),
+        ]
+        .concat();
+        let fixture: Vec<&[u8]> = vec![
+            b"a\nb",
+            b"\xEF\xBB\xBFa\nb",
+            &utf16le,
+            b"caf\xE9\r\n",
+            b"a\r\nb\nc",
+        ];
+
+        let actual = fixture
+            .into_iter()
+            .map(TextFile::decode)
+            .map(|file| (file.content, file.encoding, file.line_ending))
+            .collect::<Vec<_>>();
+
+        let expected = vec![
+            ("a\nb".to_string(), Encoding::Utf8, LineEnding::Lf),
+            ("a\nb".to_string(), Encoding::Utf8Bom, LineEnding::Lf),
+            ("a\nb".to_string(), Encoding::Utf16Le, LineEnding::Crlf),
+            ("café\n".to_string(), Encoding::Latin1, LineEnding::Crlf),
+            ("a\r\nb\nc".to_string(), Encoding::Utf8, LineEnding::Lf),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_encode_roundtrip() {
+        let utf16be = [
+            UTF16BE_BOM,
+            "x\r\n"
+                .encode_utf16()
+                .flat_map(u16::to_be_bytes)
+                .collect::<Vec<_>>()
+                .as_slice(),
+        ]
+        .concat();
+        let fixture: Vec<&[u8]> = vec![b"a\nb", b"\xEF\xBB\xBFa\r\nb", &utf16be, b"caf\xE9\n"];
+
+        let actual = fixture
+            .iter()
+            .map(|bytes| {
+                let file = TextFile::decode(bytes);
+                file.encode(&file.content)
+            })
+            .collect::<Vec<_>>();
+
+        let expected = fixture
+            .iter()
+            .map(|bytes| bytes.to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+}
@@ -2,6 +2,11 @@ use anyhow::Result;
 use tokio::fs::File;
 use tokio::io::AsyncReadExt;
 
+use crate::encoding::{UTF16BE_BOM, UTF16LE_BOM};
+
+/// Number of bytes at the start of a file that tell whether it's binary
+const SAMPLE_SIZE: usize = 8192;
+
 impl crate::ForgeFS {
     /// Checks if a file is binary by examining its content.
     /// This version takes a path and opens the file itself.
@@ -23,7 +28,7 @@ impl crate::ForgeFS {
     /// This is a crate-private implementation detail.
     pub(crate) async fn is_binary(file: &mut File) -> Result<(bool, String)> {
         // Read sample data
-        let mut sample = vec![0; 8192];
+        let mut sample = vec![0; SAMPLE_SIZE];
         let bytes_read = file.read(&mut sample).await?;
         sample.truncate(bytes_read);
 
@@ -32,20 +37,38 @@ impl crate::ForgeFS {
             return Ok((true, "Empty file".into()));
         }
 
-        // Get file type info
-        let is_text = match infer::get(&sample) {
-            Some(info) => matches!(
-                info.matcher_type(),
-                infer::MatcherType::Text | infer::MatcherType::Doc
-            ),
-            None => true, // Assume text if type can't be determined
-        };
+        if let Some(mime_type) = Self::binary_type(&sample) {
+            return Ok((false, mime_type));
+        }
 
         let description = infer::get(&sample)
             .map(|info| info.mime_type().to_string())
             .unwrap_or_else(|| "Text file (no specific format detected)".into());
 
-        Ok((is_text, description))
+        Ok((true, description))
+    }
+
+    /// The mime type of binary content, eg: `image/png`, or `None` for text.
+    /// Content of an unknown type is binary when it holds a null byte, which
+    /// text never does outside of UTF-16.
+    pub fn binary_type(bytes: &[u8]) -> Option<String> {
+        if bytes.starts_with(UTF16LE_BOM) || bytes.starts_with(UTF16BE_BOM) {
+            return None;
+        }
+        let sample = &bytes[..bytes.len().min(SAMPLE_SIZE)];
+        match infer::get(sample) {
+            Some(info)
+                if matches!(
+                    info.matcher_type(),
+                    infer::MatcherType::Text | infer::MatcherType::Doc
+                ) =>
+            {
+                None
+            }
+            Some(info) => Some(info.mime_type().to_string()),
+            None if sample.contains(&0) => Some("application/octet-stream".to_string()),
+            None => None,
+        }
     }
 }
 
@@ -101,4 +124,19 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_binary_type() {
+        let png = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+        assert_eq!(
+            crate::ForgeFS::binary_type(&png),
+            Some("image/png".to_string())
+        );
+        assert_eq!(
+            crate::ForgeFS::binary_type(b"text\0with a null byte"),
+            Some("application/octet-stream".to_string())
+        );
+        assert_eq!(crate::ForgeFS::binary_type(b"fn main() {}"), None);
+        assert_eq!(crate::ForgeFS::binary_type(&[0xFF, 0xFE, b'a', 0]), None);
+    }
 }
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+
+use tokio::sync::OwnedMutexGuard;
+
+/// Locks of the files that are being changed, by path. A lock is removed once
+/// nobody holds or waits for it.
+static LOCKS: LazyLock<Mutex<HashMap<PathBuf, Weak<tokio::sync::Mutex<()>>>>> =
+    LazyLock::new(Default::default);
+
+/// Exclusive access to a file within the process, released when dropped
+#[derive(Debug)]
+pub struct FileLock {
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl crate::ForgeFS {
+    /// Waits until no other task of the process holds the lock of the file,
+    /// and takes it. Tools hold it while they read, change and write a file,
+    /// so that tools running in parallel don't overwrite each other's changes.
+    /// The lock is advisory: it doesn't stop writes that don't take it.
+    pub async fn lock<T: AsRef<Path>>(path: T) -> FileLock {
+        let path = std::path::absolute(path.as_ref()).unwrap_or_else(|_| path.as_ref().into());
+        let mutex = {
+            let mut locks = LOCKS.lock().unwrap();
+            locks.retain(|_, lock| lock.strong_count() > 0);
+            match locks.get(&path).and_then(Weak::upgrade) {
+                Some(mutex) => mutex,
+                None => {
+                    let mutex = Arc::new(tokio::sync::Mutex::new(()));
+                    locks.insert(path, Arc::downgrade(&mutex));
+                    mutex
+                }
+            }
+        };
+        FileLock { _guard: mutex.lock_owned().await }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::ForgeFS;
+
+    #[tokio::test]
+    async fn test_lock_is_exclusive_per_file() {
+        let lock = ForgeFS::lock("/project/a.rs").await;
+
+        let other = tokio::time::timeout(Duration::from_millis(50), ForgeFS::lock("/project/b.rs"))
+            .await
+            .is_ok();
+        let same = tokio::time::timeout(Duration::from_millis(50), ForgeFS::lock("/project/a.rs"))
+            .await
+            .is_ok();
+        drop(lock);
+        let released =
+            tokio::time::timeout(Duration::from_millis(50), ForgeFS::lock("/project/a.rs"))
+                .await
+                .is_ok();
+
+        assert_eq!((other, same, released), (true, false, true));
+    }
+}
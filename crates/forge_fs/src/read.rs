@@ -15,9 +15,20 @@ impl crate::ForgeFS {
             .with_context(|| format!("Failed to read file {}", path.as_ref().display()))
     }
 
+    /// Reads a file that forge persists, decrypting it when it was written
+    /// with `write_sealed`
+    pub async fn read_sealed<T: AsRef<Path>>(path: T) -> Result<Vec<u8>> {
+        tokio::fs::read(path.as_ref())
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(Self::unseal)
+            .with_context(|| format!("Failed to read file {}", path.as_ref().display()))
+    }
+
     pub async fn read_to_string<T: AsRef<Path>>(path: T) -> Result<String> {
-        tokio::fs::read_to_string(path.as_ref())
+        Self::read(path.as_ref())
             .await
+            .and_then(|bytes| Ok(String::from_utf8(bytes)?))
             .with_context(|| format!("Failed to read file as string {}", path.as_ref().display()))
     }
 }
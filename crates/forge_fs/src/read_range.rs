@@ -3,6 +3,7 @@ use std::path::Path;
 
 use anyhow::{Context, Result};
 
+use crate::encoding::{Encoding, TextFile};
 use crate::error::Error;
 use crate::file_info::FileInfo;
 
@@ -25,17 +26,38 @@ impl crate::ForgeFS {
             .await
             .with_context(|| format!("Failed to open file {}", path_ref.display()))?;
 
-        // Check if the file is binary
-        let (is_text, file_type) = Self::is_binary(&mut file).await?;
-        if !is_text {
-            return Err(Error::BinaryFileNotSupported(file_type).into());
-        }
-
         // Read the file content
-        let content = tokio::fs::read_to_string(path_ref)
+        let content = tokio::fs::read(path_ref)
             .await
             .with_context(|| format!("Failed to read file content from {}", path_ref.display()))?;
 
+        // Text in other encodings is transcoded, like the patch tool does, so
+        // that what is read can be searched for. Files in UTF-16 look binary,
+        // so they are only recognized by their byte order mark.
+        let text = TextFile::decode(&content);
+        if !matches!(text.encoding, Encoding::Utf16Le | Encoding::Utf16Be) {
+            let (is_text, file_type) = Self::is_binary(&mut file).await?;
+            if !is_text {
+                return Err(Error::BinaryFileNotSupported(file_type).into());
+            }
+        }
+
+        Self::char_range(text.content, start_char, end_char)
+    }
+
+    /// Reads a specific range of characters from a file that forge persists,
+    /// decrypting it when it was written with `write_sealed`
+    pub async fn read_sealed_range_utf8<T: AsRef<Path>>(
+        path: T,
+        start_char: u64,
+        end_char: u64,
+    ) -> Result<(String, FileInfo)> {
+        let content = String::from_utf8(Self::read_sealed(path).await?).map_err(Error::from)?;
+        Self::char_range(content, start_char, end_char)
+    }
+
+    /// Extracts a range of characters from the content of a file
+    fn char_range(content: String, start_char: u64, end_char: u64) -> Result<(String, FileInfo)> {
         let total_chars = content.chars().count() as u64;
 
         // Validate and normalize the character range
@@ -0,0 +1,191 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, BufWriter};
+
+/// Size of the chunks that files are streamed in
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// An occurrence of a text found in a file by
+/// [`crate::ForgeFS::find_streaming`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamMatch {
+    /// Position of the occurrence, in bytes from the start of the file
+    pub offset: u64,
+    /// Line of the occurrence, starting at 1
+    pub line: usize,
+}
+
+impl crate::ForgeFS {
+    /// Whether a file can be patched as a stream of bytes, ie: it isn't
+    /// encrypted, and is in UTF-8 without a byte order mark and with `\n` line
+    /// endings. The encoding is detected from the start of the file.
+    pub async fn is_streamable<R: AsyncRead + Unpin>(reader: R) -> Result<bool> {
+        let mut head = Vec::with_capacity(CHUNK_SIZE);
+        reader
+            .take(CHUNK_SIZE as u64)
+            .read_to_end(&mut head)
+            .await
+            .context("Failed to read file")?;
+
+        // A character may be cut at the end of the chunk
+        let is_utf8 = match std::str::from_utf8(&head) {
+            Ok(_) => true,
+            Err(err) => err.error_len().is_none(),
+        };
+        Ok(is_utf8
+            && !head.starts_with(b"\xEF\xBB\xBF")
+            && !head.contains(&b'\r')
+            && !crate::Cipher::is_encrypted(&head))
+    }
+
+    /// Finds the occurrences of a text in a file, reading it in chunks so that
+    /// it is never held in memory at once. Occurrences don't overlap.
+    pub async fn find_streaming<R: AsyncRead + Unpin>(
+        mut reader: R,
+        search: &str,
+    ) -> Result<Vec<StreamMatch>> {
+        let search = search.as_bytes();
+        anyhow::ensure!(!search.is_empty(), "The search text is empty");
+
+        let mut chunk = vec![0; CHUNK_SIZE];
+        // The end of the previous chunk is kept, so that occurrences across
+        // chunks are found
+        let mut buffer = Vec::with_capacity(CHUNK_SIZE + search.len());
+        // Position of the buffer in the file
+        let mut base = 0u64;
+        // Line of the first byte of the buffer whose line breaks weren't
+        // counted yet
+        let mut line = 1;
+        let mut counted = 0;
+        let mut matches = Vec::new();
+
+        loop {
+            let read = reader
+                .read(&mut chunk)
+                .await
+                .context("Failed to read file")?;
+            buffer.extend_from_slice(&chunk[..read]);
+
+            let mut from = 0;
+            while let Some(index) = find(&buffer[from..], search) {
+                let start = from + index;
+                line += count_lines(&buffer[counted..start]);
+                counted = start;
+                matches.push(StreamMatch { offset: base + start as u64, line });
+                from = start + search.len();
+            }
+            if read == 0 {
+                return Ok(matches);
+            }
+
+            // Only the bytes that may start an occurrence are kept
+            let keep = from.max(buffer.len().saturating_sub(search.len() - 1));
+            line += count_lines(&buffer[counted..keep]);
+            counted = 0;
+            buffer.drain(..keep);
+            base += keep as u64;
+        }
+    }
+
+    /// Replaces the `length` bytes at each of the given offsets of a file,
+    /// which must be sorted and not overlap. The file is streamed into a
+    /// temporary file that is renamed over it, like [`Self::write_atomic`]
+    /// does, and synced like [`Self::write_durable`] does when `durable` is
+    /// set.
+    pub async fn replace_streaming<T: AsRef<Path>>(
+        path: T,
+        offsets: &[u64],
+        length: usize,
+        replacement: &[u8],
+        durable: bool,
+    ) -> Result<()> {
+        let path = path.as_ref();
+        Self::write_atomic_with(path, durable, |temp| async move {
+            let mut reader = tokio::fs::File::open(path).await?;
+            let mut writer = BufWriter::new(tokio::fs::File::create(temp).await?);
+            let mut skipped = vec![0; length];
+            let mut position = 0;
+            for &offset in offsets {
+                tokio::io::copy(&mut (&mut reader).take(offset - position), &mut writer).await?;
+                reader.read_exact(&mut skipped).await?;
+                writer.write_all(replacement).await?;
+                position = offset + length as u64;
+            }
+            tokio::io::copy(&mut reader, &mut writer).await?;
+            writer.flush().await
+        })
+        .await
+    }
+}
+
+/// Position of the first occurrence of `needle` in `haystack`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn count_lines(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&byte| byte == b'\n').count()
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::ForgeFS;
+
+    #[tokio::test]
+    async fn test_find_and_replace_streaming() -> Result<()> {
+        // The second occurrence spans the first two chunks
+        let content = format!(
+            "fn needle() {{}}\n{}\nneedle\n{}needle",
+            "a".repeat(CHUNK_SIZE - 18),
+            "b\n".repeat(10)
+        );
+        let fixture = tempfile::NamedTempFile::new()?;
+        tokio::fs::write(fixture.path(), &content).await?;
+
+        let file = tokio::fs::File::open(fixture.path()).await?;
+        let matches = ForgeFS::find_streaming(file, "needle").await?;
+        let actual = matches.iter().map(|m| m.line).collect::<Vec<_>>();
+        let expected = vec![1, 3, 14];
+        assert_eq!(actual, expected);
+
+        let offsets = matches.iter().map(|m| m.offset).collect::<Vec<_>>();
+        ForgeFS::replace_streaming(fixture.path(), &offsets[1..], "needle".len(), b"pin", true)
+            .await?;
+
+        let actual = tokio::fs::read_to_string(fixture.path()).await?;
+        let expected = content
+            .replace("needle", "pin")
+            .replacen("pin", "needle", 1);
+        assert_eq!(actual, expected);
+        let file = tokio::fs::File::open(fixture.path()).await?;
+        assert!(ForgeFS::is_streamable(file).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_is_streamable() -> Result<()> {
+        let fixture = tempfile::NamedTempFile::new()?;
+        let mut actual = Vec::new();
+        for content in [
+            &b"a\nb\n"[..],
+            b"a\r\nb\r\n",
+            b"\xEF\xBB\xBFa\n",
+            b"caf\xE9\n",
+        ] {
+            tokio::fs::write(fixture.path(), content).await?;
+            let file = tokio::fs::File::open(fixture.path()).await?;
+            actual.push(ForgeFS::is_streamable(file).await?);
+        }
+
+        let expected = vec![true, false, false, false];
+        assert_eq!(actual, expected);
+        Ok(())
+    }
+}
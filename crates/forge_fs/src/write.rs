@@ -1,7 +1,13 @@
-use std::path::Path;
+use std::fs::Metadata;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use anyhow::{Context, Result};
 
+/// Number of temporary files created by the process, to name the next one
+static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
+
 impl crate::ForgeFS {
     pub async fn create_dir_all<T: AsRef<Path>>(path: T) -> Result<()> {
         tokio::fs::create_dir_all(path.as_ref())
@@ -15,9 +21,194 @@ impl crate::ForgeFS {
             .with_context(|| format!("Failed to write file {}", path.as_ref().display()))
     }
 
+    /// Writes the file through a temporary file in the same directory that is
+    /// renamed over it, so that the file is never left half-written, eg: when
+    /// the write is cancelled. The permissions and owner of an existing file
+    /// are kept.
+    pub async fn write_atomic<T: AsRef<Path>, U: AsRef<[u8]>>(path: T, contents: U) -> Result<()> {
+        Self::write_atomic_with(path.as_ref(), false, |temp| async move {
+            tokio::fs::write(temp, contents).await
+        })
+        .await
+    }
+
+    /// Like [`Self::write_atomic`], and flushes the file and its directory to
+    /// the disk before returning, so that the write survives a crash
+    pub async fn write_durable<T: AsRef<Path>, U: AsRef<[u8]>>(path: T, contents: U) -> Result<()> {
+        Self::write_atomic_with(path.as_ref(), true, |temp| async move {
+            tokio::fs::write(temp, contents).await
+        })
+        .await
+    }
+
+    /// Like [`Self::write_atomic`], with the temporary file written by `write`,
+    /// eg: to stream the content instead of holding it in memory. When
+    /// `durable` is set, the file is synced before it is renamed, and its
+    /// directory after.
+    pub(crate) async fn write_atomic_with<F, Fut>(
+        path: &Path,
+        durable: bool,
+        write: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(PathBuf) -> Fut,
+        Fut: Future<Output = std::io::Result<()>>,
+    {
+        let name = path
+            .file_name()
+            .with_context(|| format!("Failed to write file {}", path.display()))?;
+        // Every write has its own temporary file, so that concurrent writes of
+        // the same file never interleave
+        let temp = path.with_file_name(format!(
+            ".{}.{}.{}.tmp",
+            name.to_string_lossy(),
+            std::process::id(),
+            TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let result = async {
+            write(temp.clone()).await?;
+            if let Ok(metadata) = tokio::fs::metadata(path).await {
+                tokio::fs::set_permissions(&temp, metadata.permissions()).await?;
+                preserve_owner(&temp, &metadata);
+            }
+            if durable {
+                tokio::fs::File::open(&temp).await?.sync_all().await?;
+            }
+            tokio::fs::rename(&temp, path).await?;
+            if durable {
+                sync_dir(path).await?;
+            }
+            Ok(())
+        }
+        .await;
+
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&temp).await;
+        }
+        result.with_context(|| format!("Failed to write file {}", path.display()))
+    }
+
     pub async fn remove_file<T: AsRef<Path>>(path: T) -> Result<()> {
         tokio::fs::remove_file(path.as_ref())
             .await
             .with_context(|| format!("Failed to remove file {}", path.as_ref().display()))
     }
 }
+
+/// Gives the file the owner of the file it replaces. Only privileged users can
+/// give files away, so this is best effort.
+#[cfg(unix)]
+fn preserve_owner(path: &Path, metadata: &Metadata) {
+    use std::os::unix::fs::MetadataExt;
+
+    let _ = std::os::unix::fs::chown(path, Some(metadata.uid()), Some(metadata.gid()));
+}
+
+#[cfg(not(unix))]
+fn preserve_owner(_: &Path, _: &Metadata) {}
+
+/// Syncs the directory of a file, so that a rename into it survives a crash
+#[cfg(unix)]
+async fn sync_dir(path: &Path) -> std::io::Result<()> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    tokio::fs::File::open(dir).await?.sync_all().await
+}
+
+/// Directories can't be opened to be synced on other platforms, where renames
+/// are made durable by the file system
+#[cfg(not(unix))]
+async fn sync_dir(_: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::ForgeFS;
+
+    #[tokio::test]
+    async fn test_write_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = dir.path().join("file.txt");
+        ForgeFS::write(&fixture, "old").await.unwrap();
+
+        ForgeFS::write_atomic(&fixture, "new").await.unwrap();
+
+        let actual = std::fs::read_to_string(&fixture).unwrap();
+        assert_eq!(actual, "new");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_writes_dont_interleave() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = dir.path().join("file.txt");
+        let contents = (0..20)
+            .map(|i| i.to_string().repeat(10_000))
+            .collect::<Vec<_>>();
+
+        let mut writes = tokio::task::JoinSet::new();
+        for content in contents.clone() {
+            let path = fixture.clone();
+            writes.spawn(async move { ForgeFS::write_atomic(path, content).await });
+        }
+        while let Some(result) = writes.join_next().await {
+            result.unwrap().unwrap();
+        }
+
+        let actual = std::fs::read_to_string(&fixture).unwrap();
+        assert!(contents.contains(&actual));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_durable() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = dir.path().join("file.txt");
+
+        ForgeFS::write_durable(&fixture, "new").await.unwrap();
+
+        let actual = std::fs::read_to_string(&fixture).unwrap();
+        assert_eq!(actual, "new");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_keeps_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = dir.path().join("file.txt");
+        ForgeFS::write(&fixture, "old").await.unwrap();
+
+        let result = ForgeFS::write_atomic_with(&fixture, true, |temp| async move {
+            tokio::fs::write(temp, "trunc").await?;
+            Err(std::io::Error::other("interrupted"))
+        })
+        .await;
+
+        let actual = std::fs::read_to_string(&fixture).unwrap();
+        assert_eq!(actual, "old");
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_write_atomic_keeps_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = dir.path().join("script.sh");
+        ForgeFS::write(&fixture, "old").await.unwrap();
+        std::fs::set_permissions(&fixture, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        ForgeFS::write_atomic(&fixture, "new").await.unwrap();
+
+        let actual = std::fs::metadata(&fixture).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(actual, 0o755);
+    }
+}
@@ -1,6 +1,9 @@
 use std::path::PathBuf;
 
-use forge_domain::{Environment, Provider, RetryConfig};
+use chrono::{DateTime, FixedOffset};
+use forge_domain::{
+    CassetteMode, Clock, Environment, Provider, ResourceLimits, RetentionPolicy, RetryConfig,
+};
 
 pub struct ForgeEnvironmentService {
     restricted: bool,
@@ -108,6 +111,92 @@ impl ForgeEnvironmentService {
         }
     }
 
+    /// Resolves the cassette used to record or replay provider interactions
+    /// from the `FORGE_CASSETTE_RECORD` or `FORGE_CASSETTE_REPLAY` environment
+    /// variables. Replay takes precedence when both are set.
+    fn resolve_cassette(&self) -> Option<CassetteMode> {
+        if let Ok(path) = std::env::var("FORGE_CASSETTE_REPLAY") {
+            return Some(CassetteMode::Replay(PathBuf::from(path)));
+        }
+
+        std::env::var("FORGE_CASSETTE_RECORD")
+            .ok()
+            .map(|path| CassetteMode::Record(PathBuf::from(path)))
+    }
+
+    /// Resolves the clock from the `FORGE_FROZEN_TIME` environment variable, an
+    /// RFC 3339 time that the clock stays at, and `FORGE_UTC_OFFSET`, eg:
+    /// `+02:00`, the timezone of the times instead of the local one
+    fn resolve_clock(&self) -> Clock {
+        Clock {
+            frozen: std::env::var("FORGE_FROZEN_TIME")
+                .ok()
+                .and_then(|val| DateTime::parse_from_rfc3339(val.trim()).ok()),
+            utc_offset: std::env::var("FORGE_UTC_OFFSET")
+                .ok()
+                .and_then(|val| val.trim().parse::<FixedOffset>().ok()),
+        }
+    }
+
+    /// Resolves the number of provider interactions kept for inspection from
+    /// the `FORGE_INSPECTED_REQUESTS` environment variable, 5 by default
+    fn resolve_inspected_requests(&self) -> usize {
+        std::env::var("FORGE_INSPECTED_REQUESTS")
+            .ok()
+            .and_then(|val| val.parse::<usize>().ok())
+            .unwrap_or(5)
+    }
+
+    /// Resolves the limits on the resources of tools from the
+    /// `FORGE_MAX_PROCESS_MEMORY_MB`, `FORGE_MIN_AVAILABLE_MEMORY_MB`,
+    /// `FORGE_WRITE_QUOTA_MB` and `FORGE_STREAMING_PATCH_MB` environment
+    /// variables
+    fn resolve_resource_limits(&self) -> ResourceLimits {
+        let megabytes = |key: &str| {
+            std::env::var(key)
+                .ok()
+                .and_then(|val| val.parse::<u64>().ok())
+                .map(|val| val * 1024 * 1024)
+        };
+
+        ResourceLimits {
+            max_memory_bytes: megabytes("FORGE_MAX_PROCESS_MEMORY_MB"),
+            min_available_memory_bytes: megabytes("FORGE_MIN_AVAILABLE_MEMORY_MB"),
+            max_write_bytes: megabytes("FORGE_WRITE_QUOTA_MB"),
+            streaming_patch_bytes: megabytes("FORGE_STREAMING_PATCH_MB"),
+        }
+    }
+
+    /// Resolves the retention of the stored files from `FORGE_RETENTION_DAYS`
+    /// and `FORGE_RETENTION_MB`
+    fn resolve_retention(&self) -> RetentionPolicy {
+        let number = |key: &str| {
+            std::env::var(key)
+                .ok()
+                .and_then(|val| val.parse::<u64>().ok())
+        };
+
+        RetentionPolicy {
+            max_age_days: number("FORGE_RETENTION_DAYS"),
+            max_total_bytes: number("FORGE_RETENTION_MB").map(|val| val * 1024 * 1024),
+        }
+    }
+
+    /// Resolves the CODEOWNERS teams of the user from the comma separated
+    /// `FORGE_CODEOWNERS_TEAMS` environment variable
+    fn resolve_code_owner_teams(&self) -> Vec<String> {
+        std::env::var("FORGE_CODEOWNERS_TEAMS")
+            .map(|teams| {
+                teams
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|team| !team.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     fn get(&self) -> Environment {
         dotenv::dotenv().ok();
         let cwd = std::env::current_dir().unwrap_or(PathBuf::from("."));
@@ -125,6 +214,12 @@ impl ForgeEnvironmentService {
             home: dirs::home_dir(),
             provider,
             retry_config,
+            resource_limits: self.resolve_resource_limits(),
+            retention: self.resolve_retention(),
+            code_owner_teams: self.resolve_code_owner_teams(),
+            cassette: self.resolve_cassette(),
+            inspected_requests: self.resolve_inspected_requests(),
+            clock: self.resolve_clock(),
         }
     }
 }
@@ -1,12 +1,72 @@
-use std::io::{self, Write};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use forge_domain::{CommandOutput, Environment};
+use anyhow::anyhow;
+use forge_domain::{
+    CommandEnv, CommandOutput, Environment, JobOutput, ResourceUsage, TerminalSize,
+};
 use forge_services::CommandExecutorService;
-use tokio::io::AsyncReadExt;
-use tokio::process::Command;
+use portable_pty::{native_pty_system, CommandBuilder, PtySize};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::{Child, Command};
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::resource_monitor::{kill_tree, ResourceMonitor};
+
+/// Interval between samples of the resources used by a command
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Output kept for a background command between two polls, in bytes. Older
+/// output is dropped, eg: the logs of a dev server that nobody polled.
+const MAX_JOB_OUTPUT: usize = 1024 * 1024;
+
+/// Time to wait for the rest of the output of a background command that
+/// exited, in case the processes that it spawned keep writing to its pipes
+const JOB_OUTPUT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Variables that a command inherits when it runs in a clean environment,
+/// without which shells and common tools don't work
+const CLEAN_ENV_VARS: [&str; 12] = [
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "TERM",
+    "LANG",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "USERPROFILE",
+    "SYSTEMROOT",
+];
+
+/// The variables of forge that a command inherits in a clean environment
+fn clean_env_vars() -> impl Iterator<Item = (&'static str, String)> {
+    CLEAN_ENV_VARS
+        .into_iter()
+        .filter_map(|name| Some((name, std::env::var(name).ok()?)))
+}
+
+/// Kills the processes of a command when it's dropped before the command
+/// finished, eg: when the tool call that runs it is aborted. Killing only the
+/// shell would leave the processes that it started running.
+struct TreeGuard(Option<u32>);
+
+impl Drop for TreeGuard {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0 {
+            kill_tree(pid);
+        }
+    }
+}
 
 /// Service for executing shell commands
 #[derive(Clone, Debug)]
@@ -16,22 +76,110 @@ pub struct ForgeCommandExecutorService {
 
     // Mutex to ensure that only one command is executed at a time
     ready: Arc<Mutex<()>>,
+
+    /// Commands running in the background, by job id
+    jobs: Arc<Mutex<HashMap<u64, Job>>>,
+    last_job: Arc<AtomicU64>,
+}
+
+/// A command running in the background, with the output that it wrote since
+/// it was last polled
+#[derive(Debug)]
+struct Job {
+    command: String,
+    child: Child,
+    stdout: Arc<std::sync::Mutex<Vec<u8>>>,
+    stderr: Arc<std::sync::Mutex<Vec<u8>>>,
+    readers: Vec<JoinHandle<()>>,
+}
+
+/// A job that is dropped while its command runs, eg: with the service when
+/// forge exits, kills the processes of the command, which would otherwise
+/// keep running without forge
+impl Drop for Job {
+    fn drop(&mut self) {
+        if let (Ok(None), Some(pid)) = (self.child.try_wait(), self.child.id()) {
+            kill_tree(pid);
+        }
+    }
+}
+
+impl Job {
+    /// Waits for the output that the command wrote before it exited
+    async fn finish(&mut self) {
+        for reader in self.readers.drain(..) {
+            let _ = tokio::time::timeout(JOB_OUTPUT_TIMEOUT, reader).await;
+        }
+    }
+
+    /// Takes the output written since the last call
+    fn output(&self, id: u64, running: bool, exit_code: Option<i32>) -> JobOutput {
+        let take = |buffer: &std::sync::Mutex<Vec<u8>>| {
+            let bytes = std::mem::take(&mut *buffer.lock().unwrap());
+            String::from_utf8_lossy(&bytes).into_owned()
+        };
+        JobOutput {
+            id,
+            command: self.command.clone(),
+            stdout: take(&self.stdout),
+            stderr: take(&self.stderr),
+            running,
+            exit_code,
+        }
+    }
+}
+
+/// Collects the output of a background command as it is written, keeping the
+/// last [`MAX_JOB_OUTPUT`] bytes
+fn collect<A: AsyncRead + Unpin + Send + 'static>(
+    io: Option<A>,
+    buffer: Arc<std::sync::Mutex<Vec<u8>>>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let Some(mut io) = io else {
+            return;
+        };
+        let mut buff = [0; 1024];
+        while let Ok(n) = io.read(&mut buff).await {
+            if n == 0 {
+                break;
+            }
+            let mut output = buffer.lock().unwrap();
+            output.extend_from_slice(&buff[..n]);
+            let excess = output.len().saturating_sub(MAX_JOB_OUTPUT);
+            output.drain(..excess);
+        }
+    })
 }
 
 impl ForgeCommandExecutorService {
     pub fn new(restricted: bool, env: Environment) -> Self {
-        Self { restricted, env, ready: Arc::new(Mutex::new(())) }
+        Self {
+            restricted,
+            env,
+            ready: Arc::new(Mutex::new(())),
+            jobs: Default::default(),
+            last_job: Default::default(),
+        }
     }
 
-    fn prepare_command(&self, command_str: &str, working_dir: &Path) -> Command {
-        // Create a basic command
-        let is_windows = cfg!(target_os = "windows");
-        let shell = if self.restricted && !is_windows {
+    /// The shell that runs the commands, restricted unless forge runs
+    /// unrestricted
+    fn shell(&self) -> &str {
+        if self.restricted && !cfg!(target_os = "windows") {
             "rbash"
         } else {
             self.env.shell.as_str()
-        };
-        let mut command = Command::new(shell);
+        }
+    }
+
+    fn prepare_command(&self, command_str: &str, working_dir: &Path, env: &CommandEnv) -> Command {
+        // Create a basic command
+        let is_windows = cfg!(target_os = "windows");
+        let mut command = Command::new(self.shell());
+        if env.clean {
+            command.env_clear().envs(clean_env_vars());
+        }
 
         // Core color settings for general commands
         command
@@ -50,6 +198,9 @@ impl ForgeCommandExecutorService {
         // Other common tools
         command.env("GREP_OPTIONS", "--color=always"); // GNU grep
 
+        // The variables of the call override the ones above
+        command.envs(&env.vars);
+
         let parameter = if is_windows { "/C" } else { "-c" };
 
         command.arg(parameter).arg(command_str);
@@ -68,28 +219,38 @@ impl ForgeCommandExecutorService {
         command
     }
 
-    /// Internal method to execute commands with streaming to console
+    /// Internal method to execute commands with streaming to console. The
+    /// command and the processes it spawned are killed once it runs longer
+    /// than `timeout` or `cancellation` is cancelled.
     async fn execute_command_internal(
         &self,
         command: String,
         working_dir: &Path,
+        env: &CommandEnv,
+        timeout: Option<Duration>,
+        cancellation: &CancellationToken,
     ) -> anyhow::Result<CommandOutput> {
         let ready = self.ready.lock().await;
 
-        let mut prepared_command = self.prepare_command(&command, working_dir);
+        let mut prepared_command = self.prepare_command(&command, working_dir, env);
 
         // Spawn the command
         let mut child = prepared_command.spawn()?;
 
         let mut stdout_pipe = child.stdout.take();
         let mut stderr_pipe = child.stderr.take();
+        let pid = child.id();
 
         // Stream the output of the command to stdout and stderr concurrently
-        let (status, stdout_buffer, stderr_buffer) = tokio::try_join!(
-            child.wait(),
-            stream(&mut stdout_pipe, io::stdout()),
-            stream(&mut stderr_pipe, io::stderr())
-        )?;
+        let output = async {
+            tokio::try_join!(
+                child.wait(),
+                stream(&mut stdout_pipe, io::stdout()),
+                stream(&mut stderr_pipe, io::stderr())
+            )
+        };
+        let ((status, stdout_buffer, stderr_buffer), usage) =
+            self.supervise(pid, timeout, cancellation, output).await?;
 
         // Drop happens after `try_join` due to <https://github.com/tokio-rs/tokio/issues/4309>
         drop(stdout_pipe);
@@ -101,8 +262,133 @@ impl ForgeCommandExecutorService {
             stderr: String::from_utf8_lossy(&stderr_buffer).into_owned(),
             exit_code: status.code(),
             command,
+            usage: Some(usage),
+        })
+    }
+
+    /// Executes a command attached to a pseudo-terminal, streaming its output
+    /// to the console like [`Self::execute_command_internal`]
+    async fn execute_command_in_pty_internal(
+        &self,
+        command: String,
+        working_dir: &Path,
+        env: &CommandEnv,
+        size: TerminalSize,
+        timeout: Option<Duration>,
+        cancellation: &CancellationToken,
+    ) -> anyhow::Result<CommandOutput> {
+        let ready = self.ready.lock().await;
+
+        let pair = native_pty_system().openpty(PtySize {
+            rows: size.rows,
+            cols: size.cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        })?;
+        let mut prepared_command = CommandBuilder::new(self.shell());
+        prepared_command.arg(if cfg!(target_os = "windows") {
+            "/C"
+        } else {
+            "-c"
+        });
+        prepared_command.arg(&command);
+        prepared_command.cwd(working_dir);
+        if env.clean {
+            prepared_command.env_clear();
+            for (name, value) in clean_env_vars() {
+                prepared_command.env(name, value);
+            }
+        }
+        for (name, value) in &env.vars {
+            prepared_command.env(name, value);
+        }
+        let mut child = pair.slave.spawn_command(prepared_command)?;
+        let pid = child.process_id();
+
+        // The output only ends once every process holding the terminal closed
+        // it, so the slave isn't kept here
+        drop(pair.slave);
+        // Commands that prompt for input read the end of it instead of waiting
+        // for an answer
+        drop(pair.master.take_writer()?);
+
+        let mut reader = pair.master.try_clone_reader()?;
+        let output = tokio::task::spawn_blocking(move || -> io::Result<_> {
+            let mut output = Vec::new();
+            let mut stdout = io::stdout();
+            let mut buff = [0; 1024];
+            // Reading fails instead of returning 0 on some platforms once the
+            // terminal is closed
+            while let Ok(n) = reader.read(&mut buff) {
+                if n == 0 {
+                    break;
+                }
+                stdout.write_all(&buff[..n])?;
+                stdout.flush()?;
+                output.extend_from_slice(&buff[..n]);
+            }
+            Ok((child.wait()?, output))
+        });
+        let ((status, output), usage) = self
+            .supervise(pid, timeout, cancellation, async { output.await? })
+            .await?;
+
+        drop(pair.master);
+        drop(ready);
+
+        // The output keeps the colors for the console, and the terminal's line
+        // endings are converted for the model
+        Ok(CommandOutput {
+            stdout: String::from_utf8_lossy(&output).replace("\r\n", "\n"),
+            stderr: String::new(),
+            exit_code: Some(status.exit_code() as i32),
+            command,
+            usage: Some(usage),
         })
     }
+
+    /// Waits for the output of a command while sampling the resources that it
+    /// uses. When it exceeds the limits, runs longer than `timeout` or is
+    /// cancelled its processes are killed, and the output is still collected.
+    async fn supervise<T>(
+        &self,
+        pid: Option<u32>,
+        timeout: Option<Duration>,
+        cancellation: &CancellationToken,
+        output: impl Future<Output = io::Result<T>>,
+    ) -> anyhow::Result<(T, ResourceUsage)> {
+        let mut guard = TreeGuard(pid);
+        tokio::pin!(output);
+        let mut monitor = ResourceMonitor::new(self.env.resource_limits.clone());
+        let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+        let mut killed = false;
+        let deadline = tokio::time::sleep(timeout.unwrap_or(Duration::MAX));
+        tokio::pin!(deadline);
+        let output = loop {
+            tokio::select! {
+                output = &mut output => break output?,
+                _ = interval.tick(), if !killed => {
+                    if let Some(pid) = pid {
+                        killed = monitor.sample(pid);
+                    }
+                }
+                _ = &mut deadline, if !killed && timeout.is_some() => {
+                    if let (Some(pid), Some(timeout)) = (pid, timeout) {
+                        monitor.time_out(pid, timeout);
+                    }
+                    killed = true;
+                }
+                _ = cancellation.cancelled(), if !killed => {
+                    if let Some(pid) = pid {
+                        monitor.cancel(pid);
+                    }
+                    killed = true;
+                }
+            }
+        };
+        guard.0 = None;
+        Ok((output, monitor.usage()))
+    }
 }
 
 /// reads the output from A and writes it to W
@@ -135,7 +421,102 @@ impl CommandExecutorService for ForgeCommandExecutorService {
         command: String,
         working_dir: PathBuf,
     ) -> anyhow::Result<CommandOutput> {
-        self.execute_command_internal(command, &working_dir).await
+        self.execute_command_internal(
+            command,
+            &working_dir,
+            &CommandEnv::default(),
+            None,
+            &CancellationToken::new(),
+        )
+        .await
+    }
+
+    async fn execute_command_with(
+        &self,
+        command: String,
+        working_dir: PathBuf,
+        env: CommandEnv,
+        timeout: Option<Duration>,
+        cancellation: CancellationToken,
+    ) -> anyhow::Result<CommandOutput> {
+        self.execute_command_internal(command, &working_dir, &env, timeout, &cancellation)
+            .await
+    }
+
+    async fn execute_command_in_pty(
+        &self,
+        command: String,
+        working_dir: PathBuf,
+        env: CommandEnv,
+        size: TerminalSize,
+        timeout: Option<Duration>,
+        cancellation: CancellationToken,
+    ) -> anyhow::Result<CommandOutput> {
+        self.execute_command_in_pty_internal(
+            command,
+            &working_dir,
+            &env,
+            size,
+            timeout,
+            &cancellation,
+        )
+        .await
+    }
+
+    async fn spawn_command(&self, command: String, working_dir: PathBuf) -> anyhow::Result<u64> {
+        let mut prepared_command =
+            self.prepare_command(&command, &working_dir, &CommandEnv::default());
+        // A background command can't read the terminal while forge uses it
+        prepared_command.stdin(std::process::Stdio::null());
+        let mut child = prepared_command.spawn()?;
+
+        let stdout = Arc::<std::sync::Mutex<Vec<u8>>>::default();
+        let stderr = Arc::<std::sync::Mutex<Vec<u8>>>::default();
+        let readers = vec![
+            collect(child.stdout.take(), stdout.clone()),
+            collect(child.stderr.take(), stderr.clone()),
+        ];
+
+        let id = self.last_job.fetch_add(1, Ordering::Relaxed) + 1;
+        self.jobs
+            .lock()
+            .await
+            .insert(id, Job { command, child, stdout, stderr, readers });
+        Ok(id)
+    }
+
+    async fn poll_command(&self, id: u64) -> anyhow::Result<JobOutput> {
+        let mut jobs = self.jobs.lock().await;
+        let job = jobs
+            .get_mut(&id)
+            .ok_or_else(|| anyhow!("No background command with job id {id}"))?;
+
+        let Some(status) = job.child.try_wait()? else {
+            return Ok(job.output(id, true, None));
+        };
+        job.finish().await;
+        let output = job.output(id, false, status.code());
+        jobs.remove(&id);
+        Ok(output)
+    }
+
+    async fn kill_command(&self, id: u64) -> anyhow::Result<JobOutput> {
+        let mut job = self
+            .jobs
+            .lock()
+            .await
+            .remove(&id)
+            .ok_or_else(|| anyhow!("No background command with job id {id}"))?;
+
+        // The processes that the command spawned are killed first, so that
+        // they aren't left running without their parent
+        if let Some(pid) = job.child.id() {
+            kill_tree(pid);
+        }
+        let _ = job.child.start_kill();
+        let status = job.child.wait().await?;
+        job.finish().await;
+        Ok(job.output(id, false, status.code()))
     }
 }
 
@@ -156,6 +537,12 @@ mod tests {
             base_path: PathBuf::from("/base"),
             provider: Provider::open_router("test-key"),
             retry_config: Default::default(),
+            resource_limits: Default::default(),
+            retention: Default::default(),
+            code_owner_teams: vec![],
+            cassette: None,
+            inspected_requests: 0,
+            clock: Default::default(),
         }
     }
 
@@ -175,10 +562,232 @@ mod tests {
             stderr: "".to_string(),
             command: "echo \"hello world\"".into(),
             exit_code: Some(0),
+            usage: None,
         };
 
         assert_eq!(actual.stdout.trim(), expected.stdout.trim());
         assert_eq!(actual.stderr, expected.stderr);
         assert_eq!(actual.success(), expected.success());
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_timeout() {
+        let fixture = ForgeCommandExecutorService::new(false, test_env());
+
+        let actual = fixture
+            .execute_command_with(
+                "echo started; sleep 30".to_string(),
+                PathBuf::from("."),
+                CommandEnv::default(),
+                Some(Duration::from_secs(1)),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(actual.stdout, "started\n");
+        assert!(actual.usage.unwrap().timed_out);
+        assert!(!actual.success());
+    }
+
+    /// Whether a process is running, as a killed process stays a zombie until
+    /// its parent reaps it
+    #[cfg(unix)]
+    fn is_running(pid: u32) -> bool {
+        let pid = sysinfo::Pid::from_u32(pid);
+        let mut system = sysinfo::System::new();
+        system.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[pid]), true);
+        system
+            .process(pid)
+            .is_some_and(|process| process.status() != sysinfo::ProcessStatus::Zombie)
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancelled_command_leaves_no_process() {
+        let fixture = ForgeCommandExecutorService::new(false, test_env());
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+        let cancellation = CancellationToken::new();
+
+        let command = fixture.execute_command_with(
+            format!("sleep 30 & echo $! > {}; wait", pid_file.display()),
+            dir.path().to_path_buf(),
+            CommandEnv::default(),
+            None,
+            cancellation.clone(),
+        );
+        let cancel = async {
+            while !std::fs::read_to_string(&pid_file).is_ok_and(|pid| !pid.trim().is_empty()) {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            cancellation.cancel();
+        };
+        let (actual, _) = tokio::join!(command, cancel);
+        let child = std::fs::read_to_string(&pid_file)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        // The killed processes may take a moment to exit
+        let mut running = true;
+        for _ in 0..20 {
+            running = is_running(child);
+            if !running {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        let actual = actual.unwrap();
+        assert_eq!(
+            actual.usage.and_then(|usage| usage.killed),
+            Some("was cancelled by the user".to_string())
+        );
+        assert!(!running);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dropped_service_kills_jobs() {
+        let fixture = ForgeCommandExecutorService::new(false, test_env());
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+
+        fixture
+            .spawn_command(
+                format!("sleep 30 & echo $! > {}; wait", pid_file.display()),
+                dir.path().to_path_buf(),
+            )
+            .await
+            .unwrap();
+        while !std::fs::read_to_string(&pid_file).is_ok_and(|pid| !pid.trim().is_empty()) {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let child = std::fs::read_to_string(&pid_file)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        drop(fixture);
+
+        // The killed processes may take a moment to exit
+        let mut running = true;
+        for _ in 0..20 {
+            running = is_running(child);
+            if !running {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(!running);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_env() {
+        let fixture = ForgeCommandExecutorService::new(false, test_env());
+        std::env::set_var("FORGE_TEST_INHERITED", "inherited");
+        let env = CommandEnv {
+            vars: [("FORGE_TEST_VAR".to_string(), "set".to_string())].into(),
+            clean: false,
+        };
+        let command = "echo $FORGE_TEST_VAR $FORGE_TEST_INHERITED".to_string();
+
+        let inherited = fixture
+            .execute_command_with(
+                command.clone(),
+                PathBuf::from("."),
+                env.clone(),
+                None,
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        let clean = fixture
+            .execute_command_with(
+                command,
+                PathBuf::from("."),
+                CommandEnv { clean: true, ..env },
+                None,
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(inherited.stdout, "set inherited\n");
+        assert_eq!(clean.stdout, "set\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pty_command() {
+        let fixture = ForgeCommandExecutorService::new(false, test_env());
+
+        let actual = fixture
+            .execute_command_in_pty(
+                "[ -t 1 ] && echo terminal; stty size; echo warning >&2".to_string(),
+                PathBuf::from("."),
+                CommandEnv::default(),
+                TerminalSize { rows: 40, cols: 120 },
+                None,
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(actual.stdout, "terminal\n40 120\nwarning\n");
+        assert_eq!(actual.stderr, "");
+        assert_eq!(actual.exit_code, Some(0));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_background_command() {
+        let fixture = ForgeCommandExecutorService::new(false, test_env());
+        let id = fixture
+            .spawn_command(
+                "echo ready; echo warning >&2".to_string(),
+                PathBuf::from("."),
+            )
+            .await
+            .unwrap();
+
+        let mut stdout = String::new();
+        let mut stderr = String::new();
+        let actual = loop {
+            let output = fixture.poll_command(id).await.unwrap();
+            stdout.push_str(&output.stdout);
+            stderr.push_str(&output.stderr);
+            if !output.running {
+                break output;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        };
+
+        assert_eq!(stdout, "ready\n");
+        assert_eq!(stderr, "warning\n");
+        assert_eq!(actual.exit_code, Some(0));
+        // The job is forgotten once it exited and was polled
+        assert!(fixture.poll_command(id).await.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_kill_background_command() {
+        let fixture = ForgeCommandExecutorService::new(false, test_env());
+        let id = fixture
+            .spawn_command("sleep 30".to_string(), PathBuf::from("."))
+            .await
+            .unwrap();
+
+        let running = fixture.poll_command(id).await.unwrap();
+        let actual = fixture.kill_command(id).await.unwrap();
+
+        assert!(running.running);
+        assert!(!actual.running);
+        assert_eq!(actual.exit_code, None);
+        assert!(fixture.kill_command(id).await.is_err());
+    }
 }
@@ -12,6 +12,7 @@ use crate::fs_remove::ForgeFileRemoveService;
 use crate::fs_snap::ForgeFileSnapshotService;
 use crate::fs_write::ForgeFileWriteService;
 use crate::inquire::ForgeInquire;
+use crate::write_quota::{WriteQuota, DEFAULT_WRITE_QUOTA};
 
 #[derive(Clone)]
 pub struct ForgeInfra {
@@ -31,12 +32,23 @@ impl ForgeInfra {
         let environment_service = Arc::new(ForgeEnvironmentService::new(restricted));
         let env = environment_service.get_environment();
         let file_snapshot_service = Arc::new(ForgeFileSnapshotService::new(env.clone()));
+        let inquire_service = Arc::new(ForgeInquire::new());
+        let quota = Arc::new(WriteQuota::new(
+            env.resource_limits
+                .max_write_bytes
+                .unwrap_or(DEFAULT_WRITE_QUOTA),
+            inquire_service.clone(),
+        ));
         Self {
             file_read_service: Arc::new(ForgeFileReadService::new()),
-            file_write_service: Arc::new(ForgeFileWriteService::new(file_snapshot_service.clone())),
+            file_write_service: Arc::new(ForgeFileWriteService::new(
+                file_snapshot_service.clone(),
+                quota.clone(),
+            )),
             file_meta_service: Arc::new(ForgeFileMetaService),
             file_remove_service: Arc::new(ForgeFileRemoveService::new(
                 file_snapshot_service.clone(),
+                quota,
             )),
             environment_service,
             file_snapshot_service,
@@ -45,7 +57,7 @@ impl ForgeInfra {
                 restricted,
                 env.clone(),
             )),
-            inquire_service: Arc::new(ForgeInquire::new()),
+            inquire_service,
         }
     }
 }
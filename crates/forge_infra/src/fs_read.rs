@@ -2,6 +2,7 @@ use std::path::Path;
 
 use anyhow::Result;
 use forge_services::FsReadService;
+use tokio::io::AsyncRead;
 
 pub struct ForgeFileReadService;
 
@@ -17,14 +18,28 @@ impl ForgeFileReadService {
     }
 }
 
+/// Whether a file is one that a long output was spilled to by `write_temp`,
+/// which seals it. Forge names them with a `forge_` prefix, eg:
+/// `forge_shell_`, in the temp dir.
+fn is_spill_file(path: &Path) -> bool {
+    path.parent() == Some(std::env::temp_dir().as_path())
+        && path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with("forge_"))
+}
+
 #[async_trait::async_trait]
 impl FsReadService for ForgeFileReadService {
     async fn read_utf8(&self, path: &Path) -> Result<String> {
-        forge_fs::ForgeFS::read_utf8(path).await
+        Ok(String::from_utf8_lossy(&self.read(path).await?).to_string())
     }
 
     async fn read(&self, path: &Path) -> Result<Vec<u8>> {
-        forge_fs::ForgeFS::read(path).await
+        if is_spill_file(path) {
+            forge_fs::ForgeFS::read_sealed(path).await
+        } else {
+            forge_fs::ForgeFS::read(path).await
+        }
     }
 
     async fn range_read_utf8(
@@ -33,6 +48,45 @@ impl FsReadService for ForgeFileReadService {
         start_char: u64,
         end_char: u64,
     ) -> Result<(String, forge_fs::FileInfo)> {
-        forge_fs::ForgeFS::read_range_utf8(path, start_char, end_char).await
+        if is_spill_file(path) {
+            forge_fs::ForgeFS::read_sealed_range_utf8(path, start_char, end_char).await
+        } else {
+            forge_fs::ForgeFS::read_range_utf8(path, start_char, end_char).await
+        }
+    }
+
+    async fn size(&self, path: &Path) -> Result<u64> {
+        Ok(tokio::fs::metadata(path).await?.len())
+    }
+
+    async fn open(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+        Ok(Box::new(tokio::fs::File::open(path).await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_is_spill_file() {
+        let temp_dir = std::env::temp_dir();
+        let fixture = [
+            temp_dir.join("forge_shell_abc.md"),
+            temp_dir.join("notes.md"),
+            PathBuf::from("/project/forge_shell_abc.md"),
+        ];
+
+        let actual = fixture
+            .iter()
+            .map(|path| is_spill_file(path))
+            .collect::<Vec<_>>();
+
+        let expected = vec![true, false, false];
+        assert_eq!(actual, expected);
     }
 }
@@ -3,20 +3,23 @@ use std::sync::Arc;
 
 use forge_services::{FileRemoveService, FsSnapshotService};
 
-#[derive(Default)]
+use crate::write_quota::WriteQuota;
+
 pub struct ForgeFileRemoveService<S> {
     snaps: Arc<S>,
+    quota: Arc<WriteQuota>,
 }
 
 impl<S> ForgeFileRemoveService<S> {
-    pub fn new(snaps: Arc<S>) -> Self {
-        Self { snaps }
+    pub fn new(snaps: Arc<S>, quota: Arc<WriteQuota>) -> Self {
+        Self { snaps, quota }
     }
 }
 
 #[async_trait::async_trait]
 impl<S: FsSnapshotService> FileRemoveService for ForgeFileRemoveService<S> {
     async fn remove(&self, path: &Path) -> anyhow::Result<()> {
+        self.quota.reserve_snapshot(path).await?;
         let _ = self.snaps.create_snapshot(path).await?;
         Ok(forge_fs::ForgeFS::remove_file(path).await?)
     }
@@ -1,5 +1,6 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::Result;
 use forge_domain::Environment;
@@ -8,12 +9,17 @@ use forge_snaps::Snapshot;
 
 pub struct ForgeFileSnapshotService {
     inner: Arc<forge_snaps::SnapshotService>,
+    /// When the session started, since snapshots are shared by every session
+    session_start: Duration,
 }
 
 impl ForgeFileSnapshotService {
     pub fn new(env: Environment) -> Self {
         Self {
             inner: Arc::new(forge_snaps::SnapshotService::new(env.snapshot_path())),
+            session_start: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default(),
         }
     }
 }
@@ -25,8 +31,17 @@ impl FsSnapshotService for ForgeFileSnapshotService {
         self.inner.create_snapshot(file_path.to_path_buf()).await
     }
 
+    async fn record_creation(&self, file_path: &Path) -> Result<Snapshot> {
+        self.inner.record_creation(file_path.to_path_buf()).await
+    }
+
     // Undo
     async fn undo_snapshot(&self, file_path: &Path) -> Result<()> {
         self.inner.undo_snapshot(file_path.to_path_buf()).await
     }
+
+    async fn restore_session(&self) -> Result<Vec<PathBuf>> {
+        let paths = self.inner.restore_since(self.session_start).await?;
+        Ok(paths.into_iter().map(PathBuf::from).collect())
+    }
 }
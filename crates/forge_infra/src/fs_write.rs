@@ -5,24 +5,85 @@ use anyhow::Result;
 use bytes::Bytes;
 use forge_services::{FsSnapshotService, FsWriteService};
 
+use crate::write_quota::WriteQuota;
+
 pub struct ForgeFileWriteService<S> {
     snaps: Arc<S>,
+    quota: Arc<WriteQuota>,
+    durable: bool,
 }
 
 impl<S> ForgeFileWriteService<S> {
-    pub fn new(snaps: Arc<S>) -> Self {
-        Self { snaps }
+    pub fn new(snaps: Arc<S>, quota: Arc<WriteQuota>) -> Self {
+        Self { snaps, quota, durable: true }
+    }
+
+    /// Sets whether writes are synced to the disk, which tests can turn off
+    /// to run faster
+    pub fn with_durable(mut self, durable: bool) -> Self {
+        self.durable = durable;
+        self
+    }
+
+    async fn write_file(&self, path: &Path, contents: Bytes) -> Result<()> {
+        // The write runs in its own task, so that it completes even when the
+        // tool call that started it is dropped, eg: because it was cancelled
+        let path = path.to_path_buf();
+        let durable = self.durable;
+        tokio::spawn(async move {
+            if durable {
+                forge_fs::ForgeFS::write_durable(path, contents).await
+            } else {
+                forge_fs::ForgeFS::write_atomic(path, contents).await
+            }
+        })
+        .await?
     }
 }
 
 #[async_trait::async_trait]
 impl<S: FsSnapshotService> FsWriteService for ForgeFileWriteService<S> {
     async fn write(&self, path: &Path, contents: Bytes) -> Result<()> {
+        self.quota.reserve(contents.len() as u64).await?;
         if forge_fs::ForgeFS::exists(path) {
+            self.quota.reserve_snapshot(path).await?;
             let _ = self.snaps.create_snapshot(path).await?;
+        } else {
+            let _ = self.snaps.record_creation(path).await?;
         }
 
-        Ok(forge_fs::ForgeFS::write(path, contents.to_vec()).await?)
+        self.write_file(path, contents).await
+    }
+
+    fn durable(&self) -> bool {
+        self.durable
+    }
+
+    async fn replace(
+        &self,
+        path: &Path,
+        offsets: &[u64],
+        length: usize,
+        replacement: &str,
+    ) -> Result<()> {
+        let count = offsets.len() as u64;
+        let size = tokio::fs::metadata(path).await?.len();
+        self.quota
+            .reserve(
+                (size + count * replacement.len() as u64).saturating_sub(count * length as u64),
+            )
+            .await?;
+        self.quota.reserve_snapshot(path).await?;
+        let _ = self.snaps.create_snapshot(path).await?;
+
+        forge_fs::ForgeFS::replace_streaming(
+            path,
+            offsets,
+            length,
+            replacement.as_bytes(),
+            self.durable,
+        )
+        .await
     }
 
     async fn write_temp(&self, prefix: &str, ext: &str, content: &str) -> anyhow::Result<PathBuf> {
@@ -34,7 +95,13 @@ impl<S: FsSnapshotService> FsWriteService for ForgeFileWriteService<S> {
             .into_temp_path()
             .to_path_buf();
 
-        self.write(&path, content.to_string().into()).await?;
+        // Long outputs may contain secrets, so they are encrypted like the other
+        // files that forge persists, and decrypted by the file tools
+        let content = forge_fs::ForgeFS::seal(content.as_bytes())?;
+        self.quota.reserve(content.len() as u64).await?;
+
+        // Temporary files aren't snapshotted, so restoring the session keeps them
+        self.write_file(&path, content.into()).await?;
 
         Ok(path)
     }
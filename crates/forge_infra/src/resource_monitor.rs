@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use forge_domain::{ResourceLimits, ResourceUsage};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+/// Share of the system's memory that must remain available while a command
+/// runs, unless configured otherwise
+const MIN_AVAILABLE_MEMORY_RATIO: u64 = 20;
+
+/// Samples the memory and CPU used by a command and the processes it spawned,
+/// and kills them all when they exceed the limits
+pub struct ResourceMonitor {
+    limits: ResourceLimits,
+    system: System,
+    usage: ResourceUsage,
+}
+
+impl ResourceMonitor {
+    pub fn new(limits: ResourceLimits) -> Self {
+        Self {
+            limits,
+            system: System::new(),
+            usage: ResourceUsage::default(),
+        }
+    }
+
+    /// Samples the processes of the tree rooted at `root`, killing them when
+    /// they exceed a limit. Returns whether they were killed.
+    pub fn sample(&mut self, root: u32) -> bool {
+        self.system.refresh_memory();
+        self.system.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::nothing().with_memory().with_cpu(),
+        );
+
+        let tree = descendants(root, &parents(&self.system));
+
+        let (memory, cpu) = tree
+            .iter()
+            .filter_map(|pid| self.system.process(Pid::from_u32(*pid)))
+            .fold((0, 0.0), |(memory, cpu), process| {
+                (memory + process.memory(), cpu + process.cpu_usage())
+            });
+        self.usage.peak_memory_bytes = self.usage.peak_memory_bytes.max(memory);
+        self.usage.peak_cpu_percent = self.usage.peak_cpu_percent.max(cpu);
+
+        let reason = exceeded_limit(
+            &self.limits,
+            memory,
+            self.system.total_memory(),
+            self.system.available_memory(),
+        );
+        match reason {
+            Some(reason) => {
+                for pid in tree {
+                    if let Some(process) = self.system.process(Pid::from_u32(pid)) {
+                        process.kill();
+                    }
+                }
+                self.usage.killed = Some(reason);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Kills the processes of the tree rooted at `root` because they ran
+    /// longer than `timeout`
+    pub fn time_out(&mut self, root: u32, timeout: Duration) {
+        kill_tree(root);
+        self.usage = ResourceUsage {
+            peak_memory_bytes: self.usage.peak_memory_bytes,
+            peak_cpu_percent: self.usage.peak_cpu_percent,
+            ..ResourceUsage::timed_out(timeout)
+        };
+    }
+
+    /// Kills the processes of the tree rooted at `root` because the user
+    /// cancelled the command
+    pub fn cancel(&mut self, root: u32) {
+        kill_tree(root);
+        self.usage.killed = Some("was cancelled by the user".to_string());
+    }
+
+    pub fn usage(self) -> ResourceUsage {
+        self.usage
+    }
+}
+
+/// Kills a process and all the processes it spawned, eg: the server that a
+/// shell started
+pub fn kill_tree(root: u32) {
+    let mut system = System::new();
+    system.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::nothing());
+    for pid in descendants(root, &parents(&system)) {
+        if let Some(process) = system.process(Pid::from_u32(pid)) {
+            process.kill();
+        }
+    }
+}
+
+/// Pairs of the processes of the system and their parents
+fn parents(system: &System) -> Vec<(u32, Option<u32>)> {
+    system
+        .processes()
+        .iter()
+        // Threads are listed with the memory of their process
+        .filter(|(_, process)| process.thread_kind().is_none())
+        .map(|(pid, process)| (pid.as_u32(), process.parent().map(|pid| pid.as_u32())))
+        .collect()
+}
+
+/// Ids of the process and of all the processes it spawned, from the pairs of
+/// processes and their parents
+fn descendants(root: u32, parents: &[(u32, Option<u32>)]) -> Vec<u32> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (pid, parent) in parents {
+        if let Some(parent) = parent {
+            children.entry(*parent).or_default().push(*pid);
+        }
+    }
+
+    let mut tree = vec![root];
+    let mut index = 0;
+    while let Some(&pid) = tree.get(index) {
+        if let Some(children) = children.get(&pid) {
+            tree.extend(children.iter().filter(|child| **child != root));
+        }
+        index += 1;
+    }
+    tree
+}
+
+/// Describes the limit that the memory used by a command exceeds, if any
+fn exceeded_limit(
+    limits: &ResourceLimits,
+    memory: u64,
+    total_memory: u64,
+    available_memory: u64,
+) -> Option<String> {
+    let megabytes = |bytes: u64| bytes / 1024 / 1024;
+
+    if let Some(max) = limits.max_memory_bytes.filter(|max| memory > *max) {
+        return Some(format!(
+            "used {} MB of memory, over the limit of {} MB",
+            megabytes(memory),
+            megabytes(max)
+        ));
+    }
+
+    let min_available = limits
+        .min_available_memory_bytes
+        .unwrap_or(total_memory / MIN_AVAILABLE_MEMORY_RATIO);
+    if total_memory > 0 && available_memory < min_available {
+        return Some(format!(
+            "the system is running out of memory ({} MB available) while the command uses {} MB",
+            megabytes(available_memory),
+            megabytes(memory)
+        ));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    #[test]
+    fn test_descendants() {
+        let fixture = [
+            (1, None),
+            (10, Some(1)),
+            (11, Some(10)),
+            (12, Some(11)),
+            (13, Some(10)),
+            (20, Some(1)),
+        ];
+
+        let mut actual = descendants(10, &fixture);
+        actual.sort();
+
+        let expected = vec![10, 11, 12, 13];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_exceeded_limit() {
+        let limits = ResourceLimits { max_memory_bytes: Some(512 * MB), ..Default::default() };
+
+        let actual = [
+            exceeded_limit(&limits, 100 * MB, 8192 * MB, 4096 * MB),
+            exceeded_limit(&limits, 600 * MB, 8192 * MB, 4096 * MB),
+            exceeded_limit(&ResourceLimits::default(), 100 * MB, 8192 * MB, 300 * MB),
+        ];
+
+        let expected = [
+            None,
+            Some("used 600 MB of memory, over the limit of 512 MB".to_string()),
+            Some(
+                "the system is running out of memory (300 MB available) while the command uses 100 MB"
+                    .to_string(),
+            ),
+        ];
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,151 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use forge_services::InquireService;
+use tokio::sync::Mutex;
+
+/// Bytes that tools may write in a session when no quota is configured
+pub const DEFAULT_WRITE_QUOTA: u64 = 1024 * 1024 * 1024;
+
+const CONTINUE: &str = "Continue";
+const STOP: &str = "Stop";
+
+#[derive(Debug, Default)]
+struct Usage {
+    written: u64,
+    allowed: u64,
+}
+
+/// Counts the bytes that tools write in a session, including snapshots and
+/// spill files, and asks the user whether to continue every time they exceed
+/// the quota, eg: when an agent generates gigabytes of fixtures or logs
+pub struct WriteQuota {
+    quota: u64,
+    usage: Mutex<Usage>,
+    inquire: Arc<dyn InquireService>,
+}
+
+impl WriteQuota {
+    /// Creates a quota of `quota` bytes, where 0 disables the quota
+    pub fn new(quota: u64, inquire: Arc<dyn InquireService>) -> Self {
+        Self {
+            quota,
+            usage: Mutex::new(Usage { written: 0, allowed: quota }),
+            inquire,
+        }
+    }
+
+    /// Counts the bytes of a write before it happens, failing when the quota
+    /// is exceeded and the user doesn't want to continue
+    pub async fn reserve(&self, bytes: u64) -> Result<()> {
+        let mut usage = self.usage.lock().await;
+        let written = usage.written + bytes;
+        if self.quota > 0 && written > usage.allowed {
+            let message = format!(
+                "Forge has written {} MB in this session, over the quota of {} MB. Continue writing?",
+                written / 1024 / 1024,
+                usage.allowed / 1024 / 1024
+            );
+            let answer = self
+                .inquire
+                .select_one(&message, vec![CONTINUE.to_string(), STOP.to_string()])
+                .await?;
+            if answer.as_deref() != Some(CONTINUE) {
+                bail!(
+                    "Write quota of {} MB exceeded, the user stopped the write",
+                    usage.allowed / 1024 / 1024
+                );
+            }
+            usage.allowed = written + self.quota;
+        }
+        usage.written = written;
+        Ok(())
+    }
+
+    /// Counts the snapshot of a file that is about to be changed or removed
+    pub async fn reserve_snapshot(&self, path: &Path) -> Result<()> {
+        match tokio::fs::metadata(path).await {
+            Ok(metadata) => self.reserve(metadata.len()).await,
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    struct Answer {
+        answer: Option<&'static str>,
+        prompts: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl InquireService for Answer {
+        async fn prompt_question(&self, _: &str) -> Result<Option<String>> {
+            unimplemented!()
+        }
+
+        async fn select_one(&self, _: &str, _: Vec<String>) -> Result<Option<String>> {
+            self.prompts.fetch_add(1, Ordering::SeqCst);
+            Ok(self.answer.map(str::to_string))
+        }
+
+        async fn select_many(&self, _: &str, _: Vec<String>) -> Result<Option<Vec<String>>> {
+            unimplemented!()
+        }
+    }
+
+    fn fixture(answer: Option<&'static str>) -> (WriteQuota, Arc<Answer>) {
+        let inquire = Arc::new(Answer { answer, prompts: AtomicUsize::new(0) });
+        (WriteQuota::new(100, inquire.clone()), inquire)
+    }
+
+    #[tokio::test]
+    async fn test_reserve_within_quota() {
+        let (quota, inquire) = fixture(None);
+
+        quota.reserve(60).await.unwrap();
+        quota.reserve(40).await.unwrap();
+
+        assert_eq!(inquire.prompts.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_reserve_over_quota_continues() {
+        let (quota, inquire) = fixture(Some(CONTINUE));
+
+        quota.reserve(150).await.unwrap();
+        // The quota is extended by another 100 bytes after confirming
+        quota.reserve(100).await.unwrap();
+        quota.reserve(1).await.unwrap();
+
+        assert_eq!(inquire.prompts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reserve_over_quota_stops() {
+        let (quota, _) = fixture(Some(STOP));
+
+        quota.reserve(80).await.unwrap();
+        let actual = quota.reserve(30).await;
+
+        assert!(actual.is_err());
+        assert_eq!(quota.usage.lock().await.written, 80);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_quota() {
+        let inquire = Arc::new(Answer { answer: None, prompts: AtomicUsize::new(0) });
+        let quota = WriteQuota::new(0, inquire.clone());
+
+        quota.reserve(u32::MAX as u64).await.unwrap();
+
+        assert_eq!(inquire.prompts.load(Ordering::SeqCst), 0);
+    }
+}
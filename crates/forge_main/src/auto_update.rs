@@ -1,7 +1,7 @@
 use std::process::Stdio;
 
 use anyhow::Result;
-use forge_tracker::{EventKind, VERSION};
+use forge_tracker::{EventKind, UpdatePayload, VERSION};
 use tokio::process::Command;
 
 use crate::TRACKER;
@@ -15,10 +15,17 @@ pub async fn update_forge() {
     }
 
     // Spawn a new task that won't block the main application
-    if let Err(err) = perform_update().await {
-        // Send an event to the tracker on failure
-        // We don't need to handle this result since we're failing silently
-        let _ = send_update_failure_event(&format!("Auto update failed: {err}")).await;
+    match perform_update().await {
+        Ok(()) => {
+            let _ = TRACKER
+                .dispatch(EventKind::UpdatePerformed(UpdatePayload::new(VERSION)))
+                .await;
+        }
+        Err(err) => {
+            // Send an event to the tracker on failure
+            // We don't need to handle this result since we're failing silently
+            let _ = send_update_failure_event(&format!("Auto update failed: {err}")).await;
+        }
     }
 }
 
@@ -0,0 +1,112 @@
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use forge_api::{Changelog, ChangelogEntry};
+use handlebars::Handlebars;
+use tokio::process::Command;
+
+// Separators used to split the `git log` output into commits and fields. These
+// are ASCII control characters that don't appear in commit messages.
+const FIELD_SEPARATOR: char = '\u{1f}';
+const RECORD_SEPARATOR: char = '\u{1e}';
+
+/// Generates the release notes for the given git revision range. When a
+/// template is provided it is rendered with handlebars, otherwise the default
+/// Markdown layout is used.
+pub async fn generate(range: &str, template: Option<&Path>) -> Result<String> {
+    let log = git_log(range).await?;
+    let changelog = Changelog::new(range, parse_log(&log));
+
+    match template {
+        Some(path) => {
+            let template = tokio::fs::read_to_string(path)
+                .await
+                .with_context(|| format!("Failed to read template: {}", path.display()))?;
+            render(&template, &changelog)
+        }
+        None => Ok(changelog.to_string()),
+    }
+}
+
+/// Renders the changelog using a handlebars template
+fn render(template: &str, changelog: &Changelog) -> Result<String> {
+    let mut hb = Handlebars::new();
+    hb.set_strict_mode(true);
+    hb.register_escape_fn(handlebars::no_escape);
+    hb.render_template(template, changelog)
+        .context("Failed to render changelog template")
+}
+
+async fn git_log(range: &str) -> Result<String> {
+    let output = Command::new("git")
+        .args([
+            "log",
+            "--no-merges",
+            &format!("--format=%H{FIELD_SEPARATOR}%s{FIELD_SEPARATOR}%b{RECORD_SEPARATOR}"),
+            range,
+        ])
+        .output()
+        .await
+        .context("Failed to execute git")?;
+
+    if !output.status.success() {
+        bail!(
+            "git log failed for range '{}': {}",
+            range,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Parses the output of `git log` formatted with the field and record
+/// separators into changelog entries
+fn parse_log(log: &str) -> Vec<ChangelogEntry> {
+    log.split(RECORD_SEPARATOR)
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(3, FIELD_SEPARATOR);
+            let hash = fields.next().filter(|hash| !hash.is_empty())?;
+            let subject = fields.next().unwrap_or_default();
+            let body = fields.next().unwrap_or_default();
+            Some(ChangelogEntry::parse(hash, subject, body))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use forge_api::ChangeKind;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_log() {
+        let fixture = "aaaaaaaaaa\u{1f}feat: one\u{1f}\u{1e}\nbbbbbbbbbb\u{1f}fix(x): two (#3)\u{1f}details\u{1e}\n";
+        let actual = parse_log(fixture)
+            .into_iter()
+            .map(|entry| (entry.hash, entry.kind, entry.pr))
+            .collect::<Vec<_>>();
+        let expected = vec![
+            ("aaaaaaa".to_string(), ChangeKind::Feature, None),
+            ("bbbbbbb".to_string(), ChangeKind::Fix, Some(3)),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_render_custom_template() {
+        let fixture = Changelog::new(
+            "v1..v2",
+            vec![ChangelogEntry::parse("1234567", "feat: add thing", "")],
+        );
+        let actual = render(
+            "{{range}}{{#each sections}}|{{title}}{{#each entries}}:{{summary}}{{/each}}{{/each}}",
+            &fixture,
+        )
+        .unwrap();
+        let expected = "v1..v2|Features:add thing";
+        assert_eq!(actual, expected);
+    }
+}
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
 #[command(version = env!("CARGO_PKG_VERSION"))]
@@ -41,6 +41,28 @@ pub struct Cli {
     #[arg(long, default_value_t = false, short = 'r')]
     pub restricted: bool,
 
+    /// Trust the workspace for this session without asking, eg: in CI.
+    /// Untrusted workspaces only get read-only tools and can't run commands.
+    #[arg(long, default_value_t = false)]
+    pub trust_workspace: bool,
+
+    /// Simulate the tools that change files or run commands, eg: for demos.
+    /// Their diffs and commands are shown and logged, but nothing is written
+    /// or executed.
+    #[arg(long, default_value_t = false)]
+    pub dry_run: bool,
+
+    /// Work in a copy of the workspace, whose changes are only promoted to
+    /// the workspace with `/promote` once they pass validation.
+    #[arg(long, default_value_t = false)]
+    pub shadow: bool,
+
+    /// Command that validates the changes of a shadow workspace before they
+    /// are promoted, eg: `cargo test`. Defaults to the build command of the
+    /// detected stack.
+    #[arg(long, requires = "shadow")]
+    pub validate: Option<String>,
+
     /// Path to a file containing the workflow to execute.
     #[arg(long, short = 'w')]
     pub workflow: Option<PathBuf>,
@@ -54,4 +76,338 @@ pub struct Cli {
     /// This file should be in JSON format.
     #[arg(long)]
     pub conversation: Option<PathBuf>,
+
+    /// Write the validated response of agents that have a response schema to
+    /// a file, as JSON.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Use plain ASCII markers instead of emoji and other decorative glyphs,
+    /// for terminals whose font doesn't include them.
+    #[arg(long, default_value_t = false)]
+    pub ascii: bool,
+
+    /// Save the conversation to a checkpoint after being idle at the prompt
+    /// for this many seconds. Use 0 to disable.
+    ///
+    /// The checkpoint is written to `sessions/<conversation id>.json` in the
+    /// forge directory and can be resumed with `--conversation`.
+    #[arg(long, default_value_t = 1800)]
+    pub idle_timeout: u64,
+
+    /// Scope the session to a package of a monorepo, eg: a Cargo workspace
+    /// member, a pnpm, yarn or npm workspace, or a Bazel package.
+    ///
+    /// The package is matched by name or by its path from the root of the
+    /// monorepo. Search, indexing and commands run from the package's
+    /// directory.
+    #[arg(long)]
+    pub package: Option<String>,
+
+    /// Top-level subcommands that run a single task and exit.
+    #[command(subcommand)]
+    pub subcommands: Option<TopLevelCommand>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum TopLevelCommand {
+    /// Generate release notes for a range of commits.
+    Changelog(ChangelogCommand),
+
+    /// Upgrade a dependency and fix the code until the project builds again.
+    Migrate(MigrateCommand),
+
+    /// Watch the workspace and offer fixes whenever the build or tests fail.
+    Watch(WatchCommand),
+
+    /// Run a directory of evaluation tasks and report the pass rate per model.
+    Eval(EvalCommand),
+
+    /// Show the changes made to a file since a snapshot was taken.
+    Diff(DiffCommand),
+
+    /// Run pipelines that chain agents together.
+    Pipeline(PipelineCommand),
+
+    /// Find the root cause of a crash from a log file or a core dump.
+    Triage(TriageCommand),
+
+    /// Check the API key, provider, tools, terminal and permissions, and
+    /// print fixes for the problems found.
+    Doctor(DoctorCommand),
+
+    /// Generate a starter forge.yaml and project rules for the detected stack.
+    Init(InitCommand),
+
+    /// Inspect the tools that the configuration grants to the agents.
+    Tools(ToolsCommand),
+
+    /// Show statistics of the agent's work across sessions.
+    Stats(StatsCommand),
+
+    /// Manage the sessions, snapshots, logs and spill files that forge keeps.
+    Sessions(SessionsCommand),
+
+    /// Show or change the categories of telemetry that are shared.
+    Telemetry(TelemetryCommand),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct PipelineCommand {
+    #[command(subcommand)]
+    pub command: PipelineSubcommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum PipelineSubcommand {
+    /// Run the steps of a pipeline file in order.
+    Run(PipelineRunCommand),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct PipelineRunCommand {
+    /// Path to the pipeline file.
+    ///
+    /// The file lists `steps`, each with an `id` and a handlebars `prompt`,
+    /// and optionally an `agent`, a `response_schema`, a `when` condition and
+    /// a `for_each` to run the step once per file or item.
+    pub path: PathBuf,
+
+    /// Number of items processed in parallel by steps that fan out, unless
+    /// the step sets its own `concurrency`.
+    #[arg(long, short = 'j', default_value_t = 1)]
+    pub concurrency: usize,
+
+    /// Directory to write a Markdown (`report.md`) and a JSON (`report.json`)
+    /// report of the run to, with the status, files changed, diffs, commands
+    /// and token usage of every step.
+    #[arg(long)]
+    pub report_dir: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct DiffCommand {
+    /// ID (or unique prefix of the ID) of the snapshot to diff. Lists all the
+    /// snapshots when omitted.
+    pub snapshot: Option<String>,
+
+    /// Compare against another snapshot instead of the working tree.
+    #[arg(long, short = 'a')]
+    pub against: Option<String>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ChangelogCommand {
+    /// Git revision range to generate the changelog for, eg: `v0.1.0..HEAD`.
+    pub range: String,
+
+    /// Path to a handlebars template used to render the release notes.
+    ///
+    /// The template receives the changelog with `range` and `sections`, where
+    /// each section has a `title`, `kind` and a list of `entries`.
+    #[arg(long, short = 't')]
+    pub template: Option<PathBuf>,
+
+    /// Write the release notes to a file instead of printing them.
+    #[arg(long, short = 'o')]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct MigrateCommand {
+    /// Name of the dependency to upgrade.
+    pub dependency: String,
+
+    /// Version to upgrade the dependency to.
+    pub version: String,
+
+    /// Command used to verify that the project builds after the upgrade.
+    ///
+    /// When not provided, it is detected from the project manifest (Cargo.toml,
+    /// package.json, go.mod, pyproject.toml or pom.xml).
+    #[arg(long, short = 'b')]
+    pub build_command: Option<String>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct StatsCommand {
+    #[command(subcommand)]
+    pub command: StatsSubcommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum StatsSubcommand {
+    /// List the files of the workspace that the agent reads and edits the
+    /// most. These files are listed first when a session preloads its context.
+    Files(StatsFilesCommand),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct StatsFilesCommand {
+    /// Number of files to list.
+    #[arg(long, short = 'n', default_value_t = 20)]
+    pub limit: usize,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct SessionsCommand {
+    #[command(subcommand)]
+    pub command: SessionsSubcommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum SessionsSubcommand {
+    /// Delete the stored files that are older than the retention period, then
+    /// the oldest ones until the total size fits the limit.
+    Prune(SessionsPruneCommand),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct SessionsPruneCommand {
+    /// List the files that would be deleted without deleting them.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Delete files older than this many days, instead of
+    /// FORGE_RETENTION_DAYS. 0 keeps files regardless of their age.
+    #[arg(long)]
+    pub max_age_days: Option<u64>,
+
+    /// Total size of the stored files in MB, instead of FORGE_RETENTION_MB.
+    /// 0 disables the limit.
+    #[arg(long)]
+    pub max_size_mb: Option<u64>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct TelemetryCommand {
+    #[command(subcommand)]
+    pub command: TelemetrySubcommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum TelemetrySubcommand {
+    /// Show which categories of telemetry are shared.
+    Status,
+    /// Share a category of telemetry.
+    Enable(TelemetryCategoryCommand),
+    /// Stop sharing a category of telemetry.
+    Disable(TelemetryCategoryCommand),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct TelemetryCategoryCommand {
+    /// Category of telemetry. Prompts are never collected.
+    #[arg(value_enum)]
+    pub category: TelemetryCategory,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum TelemetryCategory {
+    /// Errors and diagnostics
+    Errors,
+    /// Usage metrics, eg: sessions and the names of the tools called
+    Usage,
+}
+
+impl From<TelemetryCategory> for forge_tracker::Category {
+    fn from(category: TelemetryCategory) -> Self {
+        match category {
+            TelemetryCategory::Errors => forge_tracker::Category::Errors,
+            TelemetryCategory::Usage => forge_tracker::Category::Usage,
+        }
+    }
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ToolsCommand {
+    #[command(subcommand)]
+    pub command: ToolsSubcommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ToolsSubcommand {
+    /// Document every tool with its description, JSON schema and the agents
+    /// that can use it.
+    Docs(ToolsDocsCommand),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ToolsDocsCommand {
+    /// Render the documentation as JSON instead of Markdown.
+    #[arg(long, default_value_t = false)]
+    pub json: bool,
+
+    /// File to write the documentation to, instead of the standard output.
+    #[arg(long, short = 'o')]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct InitCommand {
+    /// Overwrite the existing forge.yaml and rule files.
+    #[arg(long, default_value_t = false)]
+    pub force: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct DoctorCommand {
+    /// Share the results of the checks with the Forge team, to help improve
+    /// the diagnostics. Nothing is sent unless the errors category of
+    /// telemetry is enabled.
+    #[arg(long, default_value_t = false)]
+    pub share: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct TriageCommand {
+    /// Log file containing the panic, exception or stack trace, or a core
+    /// dump.
+    ///
+    /// Core dumps are read with gdb, which must be installed.
+    pub path: PathBuf,
+
+    /// Binary that produced the core dump, used to symbolize its backtrace.
+    #[arg(long)]
+    pub binary: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct WatchCommand {
+    /// Command that is re-run whenever a file changes, eg: `cargo test`.
+    ///
+    /// When not provided, the build command is detected from the project
+    /// manifest.
+    #[arg(long, short = 'x')]
+    pub command: Option<String>,
+
+    /// Apply fixes automatically instead of asking for confirmation.
+    #[arg(long, default_value_t = false)]
+    pub auto: bool,
+
+    /// Interval in seconds between checks for file changes.
+    #[arg(long, default_value_t = 2)]
+    pub interval: u64,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct EvalCommand {
+    /// Directory containing the task fixtures.
+    ///
+    /// Each sub-directory is a task with a `task.yaml` file (`prompt` and
+    /// `check` command) and an optional `repo` directory that is copied into a
+    /// fresh workspace before the task runs.
+    pub fixtures: PathBuf,
+
+    /// Models to evaluate, defaults to the model configured in the workflow.
+    #[arg(long = "model", short = 'm')]
+    pub models: Vec<String>,
+
+    /// Maximum time in seconds the agent may spend on a single task.
+    #[arg(long, default_value_t = 600)]
+    pub timeout: u64,
+
+    /// Also write the results of every task as CSV to this file.
+    #[arg(long)]
+    pub csv: Option<PathBuf>,
 }
@@ -0,0 +1,130 @@
+use std::fmt::{self, Display};
+use std::path::Path;
+
+use forge_api::PatchConflict;
+
+/// How the user resolves a patch that was queued because it didn't match
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolution {
+    /// The agent retries the patch after reading the file again
+    Retry,
+    /// The user applies the patch in their editor
+    Manual,
+    Skip,
+}
+
+impl Resolution {
+    pub const ALL: [Resolution; 3] = [Resolution::Retry, Resolution::Manual, Resolution::Skip];
+}
+
+impl Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Resolution::Retry => write!(f, "Let forge retry with the current file"),
+            Resolution::Manual => write!(f, "Apply it manually"),
+            Resolution::Skip => write!(f, "Skip it"),
+        }
+    }
+}
+
+/// Shows what a queued patch was meant to change
+pub fn describe(conflict: &PatchConflict) -> String {
+    format!(
+        "{} of:\n{}\nwith:\n{}",
+        conflict.operation, conflict.search, conflict.content
+    )
+}
+
+/// Opens a file in the user's editor, from `$VISUAL` or `$EDITOR`. Returns
+/// false when neither is set.
+pub fn open_in_editor(path: &Path) -> anyhow::Result<bool> {
+    let Some(editor) = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+    else {
+        return Ok(false);
+    };
+    editor_command(&editor, path).status()?;
+    Ok(true)
+}
+
+/// Runs the editor through the shell, like git does, since it may come with
+/// arguments, eg: `code --wait`. The path is passed as an argument of the
+/// shell script rather than in it, so that it isn't interpreted.
+fn editor_command(editor: &str, path: &Path) -> std::process::Command {
+    if cfg!(target_os = "windows") {
+        let mut command = std::process::Command::new("cmd");
+        command
+            .arg("/C")
+            .arg(format!("{editor} \"{}\"", path.display()));
+        command
+    } else {
+        let mut command = std::process::Command::new("sh");
+        command
+            .arg("-c")
+            .arg(format!("{editor} \"$@\""))
+            .arg(editor)
+            .arg(path);
+        command
+    }
+}
+
+/// Builds the task sent to the agent to retry the patches that didn't match
+pub fn retry_task(conflicts: &[PatchConflict]) -> String {
+    let patches = conflicts
+        .iter()
+        .map(|conflict| {
+            format!(
+                "<patch path=\"{}\" operation=\"{}\">\n<search>\n{}\n</search>\n<content>\n{}\n</content>\n</patch>",
+                conflict.path.display(),
+                conflict.operation,
+                conflict.search,
+                conflict.content
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "These patches didn't match the files when you made them, so they were skipped. Read the files again, since they may have changed, and apply the changes that are still needed.\n\n{patches}"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_retry_task() {
+        let fixture = vec![PatchConflict {
+            path: PathBuf::from("/project/src/main.rs"),
+            operation: "replace".to_string(),
+            search: "fn old() {}".to_string(),
+            content: "fn new() {}".to_string(),
+        }];
+
+        let actual = retry_task(&fixture);
+
+        let expected = "These patches didn't match the files when you made them, so they were skipped. Read the files again, since they may have changed, and apply the changes that are still needed.\n\n\
+                        <patch path=\"/project/src/main.rs\" operation=\"replace\">\n\
+                        <search>\nfn old() {}\n</search>\n\
+                        <content>\nfn new() {}\n</content>\n\
+                        </patch>";
+        assert_eq!(actual, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_editor_command_with_arguments() {
+        let fixture = Path::new("/project/my file.rs");
+
+        let actual = editor_command("echo --wait", fixture).output().unwrap();
+
+        let expected = "--wait /project/my file.rs\n";
+        assert_eq!(String::from_utf8_lossy(&actual.stdout), expected);
+    }
+}
@@ -0,0 +1,101 @@
+use anyhow::Result;
+use forge_display::{DiffFormat, Glyph, TableFormat, TitleFormat};
+use forge_snaps::{Snapshot, SnapshotService};
+
+/// Number of characters of the snapshot ID shown in listings
+const SHORT_ID_LEN: usize = 8;
+
+fn short_id(snapshot: &Snapshot) -> String {
+    snapshot.id.to_string().chars().take(SHORT_ID_LEN).collect()
+}
+
+/// Lists all the snapshots, most recent first
+pub async fn list(service: &SnapshotService) -> Result<String> {
+    let snapshots = service.list().await?;
+    if snapshots.is_empty() {
+        return Ok("No snapshots found".to_string());
+    }
+
+    let mut table = TableFormat::new(["ID", "Time", "Path"]);
+    for snapshot in snapshots {
+        let time =
+            chrono::DateTime::<chrono::Utc>::from(std::time::UNIX_EPOCH + snapshot.timestamp)
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S");
+        table.push([short_id(&snapshot), time.to_string(), snapshot.path]);
+    }
+    Ok(table.to_string())
+}
+
+/// Diffs a snapshot against another snapshot, or against the current content
+/// of the file in the working tree when `against` is not provided
+pub async fn diff(service: &SnapshotService, id: &str, against: Option<&str>) -> Result<String> {
+    let snapshot = service.find(id).await?;
+    let old = String::from_utf8_lossy(&service.read(&snapshot).await?).to_string();
+
+    let (target, new) = match against {
+        Some(other) => {
+            let other = service.find(other).await?;
+            let content = String::from_utf8_lossy(&service.read(&other).await?).to_string();
+            (short_id(&other), content)
+        }
+        None => match tokio::fs::read(&snapshot.path).await {
+            Ok(content) => (
+                "working tree".to_string(),
+                String::from_utf8_lossy(&content).to_string(),
+            ),
+            Err(_) => ("deleted".to_string(), String::new()),
+        },
+    };
+
+    let title = TitleFormat::action("Diff").sub_title(format!(
+        "{} [{} {} {}]",
+        snapshot.path,
+        short_id(&snapshot),
+        Glyph::Arrow,
+        target
+    ));
+    let (_, width) = console::Term::stdout().size();
+    let diff = DiffFormat::default()
+        .width(width as usize)
+        .render(&old, &new);
+    Ok(format!("{title}\n{diff}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use console::strip_ansi_codes;
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_diff_against_working_tree() {
+        let fixture = TempDir::new().unwrap();
+        let file = fixture.path().join("a.txt");
+        let service = SnapshotService::new(fixture.path().join("snapshots"));
+        std::fs::write(&file, "hello\nworld\n").unwrap();
+        let snapshot = service.create_snapshot(file.clone()).await.unwrap();
+        std::fs::write(&file, "hello\nthere\n").unwrap();
+
+        let actual = diff(&service, &snapshot.id.to_string(), None)
+            .await
+            .unwrap();
+        let actual = strip_ansi_codes(&actual);
+
+        assert!(actual.contains("working tree"));
+        assert!(actual.contains("|-world"));
+        assert!(actual.contains("|+there"));
+    }
+
+    #[tokio::test]
+    async fn test_list_empty() {
+        let fixture = TempDir::new().unwrap();
+        let service = SnapshotService::new(fixture.path().join("snapshots"));
+
+        let actual = list(&service).await.unwrap();
+
+        assert_eq!(actual, "No snapshots found");
+    }
+}
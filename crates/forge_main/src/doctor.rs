@@ -0,0 +1,289 @@
+use std::io::IsTerminal;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use forge_api::{ForgeAPI, API};
+use forge_display::{glyph, Glyph, TitleFormat};
+use forge_tracker::EventKind;
+use serde::Serialize;
+use tokio::process::Command;
+
+use crate::cli::{Cli, DoctorCommand, TopLevelCommand};
+use crate::TRACKER;
+
+/// Environment variables that provide the API key, in order of precedence
+const API_KEY_VARS: &[&str] = &[
+    "FORGE_KEY",
+    "OPENROUTER_API_KEY",
+    "OPENAI_API_KEY",
+    "ANTHROPIC_API_KEY",
+];
+
+/// Programs used by forge, with what they are used for
+const PROGRAMS: &[(&str, &str)] = &[
+    (
+        "git",
+        "checkpoints, changelogs and the git branch in the prompt",
+    ),
+    ("node", "automatic updates"),
+    ("npm", "automatic updates"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// Result of a check of the environment, with a fix when it doesn't pass
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn new(name: impl ToString, status: Status, detail: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            detail: detail.to_string(),
+            fix: None,
+        }
+    }
+
+    fn fix(mut self, fix: impl ToString) -> Self {
+        self.fix = Some(fix.to_string());
+        self
+    }
+
+    fn title(&self) -> TitleFormat {
+        let title = match self.status {
+            Status::Pass => TitleFormat::info(&self.name),
+            Status::Warn => TitleFormat::action(&self.name),
+            Status::Fail => TitleFormat::error(&self.name),
+        };
+        title.sub_title(&self.detail)
+    }
+}
+
+/// Diagnoses the environment of forge: the API key and provider, the programs
+/// it relies on, the terminal and the permissions of the workspace. It runs
+/// before the API is initialized, since that fails without an API key.
+pub struct Doctor {
+    restricted: bool,
+    command: DoctorCommand,
+}
+
+impl Doctor {
+    pub fn new(restricted: bool, command: DoctorCommand) -> Self {
+        Self { restricted, command }
+    }
+
+    /// The doctor for `forge doctor`, if that is the command being run
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        match &cli.subcommands {
+            Some(TopLevelCommand::Doctor(command)) => {
+                Some(Self::new(cli.restricted, command.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    pub async fn run(self) -> Result<()> {
+        let mut checks = vec![api_key_check(|name| std::env::var(name).ok())];
+        for (program, usage) in PROGRAMS {
+            checks.push(program_check(program, usage, version(program).await));
+        }
+        checks.push(terminal_check());
+        checks.push(writable_check("Workspace", &std::env::current_dir()?));
+
+        if checks[0].status == Status::Pass {
+            let api = ForgeAPI::init(self.restricted)?;
+            let env = api.environment();
+            checks.push(writable_check("Forge directory", &env.base_path));
+            let url = env.provider.to_base_url();
+            checks.push(match api.models().await {
+                Ok(models) => Check::new(
+                    "Provider",
+                    Status::Pass,
+                    format!("{} models available from {url}", models.len()),
+                ),
+                Err(err) => provider_check(url.as_str(), &format!("{err:?}")),
+            });
+        }
+
+        for check in &checks {
+            println!("{}", check.title());
+            if let Some(fix) = &check.fix {
+                println!("  {} {fix}", Glyph::Arrow);
+            }
+        }
+
+        if self.command.share {
+            let report = serde_json::to_string(&checks)?;
+            TRACKER.dispatch(EventKind::Doctor(report)).await?;
+        }
+
+        let failed = checks
+            .iter()
+            .filter(|check| check.status == Status::Fail)
+            .count();
+        if failed > 0 {
+            bail!("{failed} of {} checks failed", checks.len());
+        }
+        Ok(())
+    }
+}
+
+fn api_key_check(var: impl Fn(&str) -> Option<String>) -> Check {
+    match API_KEY_VARS
+        .iter()
+        .find(|name| var(name).is_some_and(|key| !key.trim().is_empty()))
+    {
+        Some(name) => Check::new("API key", Status::Pass, format!("{name} is set")),
+        None => Check::new("API key", Status::Fail, "No API key found").fix(format!(
+            "Set one of {} in your shell or in a .env file",
+            API_KEY_VARS.join(", ")
+        )),
+    }
+}
+
+/// First line of the output of `<program> --version`
+async fn version(program: &str) -> Option<String> {
+    let output = Command::new(program).arg("--version").output().await.ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    output
+        .status
+        .success()
+        .then(|| stdout.lines().next().unwrap_or_default().trim().to_string())
+}
+
+fn program_check(program: &str, usage: &str, version: Option<String>) -> Check {
+    match version {
+        Some(version) => Check::new(program, Status::Pass, version),
+        None => Check::new(
+            program,
+            Status::Warn,
+            format!("Not found, it is used for {usage}"),
+        )
+        .fix(format!(
+            "Install {program} and make sure it is in your PATH"
+        )),
+    }
+}
+
+fn terminal_check() -> Check {
+    let term = std::env::var("TERM").unwrap_or_default();
+    if !std::io::stdout().is_terminal() {
+        Check::new(
+            "Terminal",
+            Status::Warn,
+            "Output is not a terminal, prompts and colors are disabled",
+        )
+        .fix("Run forge in an interactive terminal, or pass the task with --prompt")
+    } else if glyph::detect_ascii() {
+        Check::new(
+            "Terminal",
+            Status::Warn,
+            format!("TERM={term} may not display Unicode symbols"),
+        )
+        .fix("Run forge with --ascii, or use a terminal with Unicode support")
+    } else {
+        Check::new("Terminal", Status::Pass, format!("TERM={term}"))
+    }
+}
+
+fn writable_check(name: &str, dir: &Path) -> Check {
+    let writable = std::fs::create_dir_all(dir).and_then(|_| tempfile::tempfile_in(dir));
+    match writable {
+        Ok(_) => Check::new(name, Status::Pass, format!("{} is writable", dir.display())),
+        Err(err) => Check::new(
+            name,
+            Status::Fail,
+            format!("{} is not writable: {err}", dir.display()),
+        )
+        .fix(format!(
+            "Fix the permissions of {} or run forge as its owner",
+            dir.display()
+        )),
+    }
+}
+
+/// Tells an invalid API key apart from a provider that can't be reached
+fn provider_check(url: &str, error: &str) -> Check {
+    let lowercase = error.to_lowercase();
+    let rejected = ["401", "403", "unauthorized", "forbidden", "invalid api key"]
+        .iter()
+        .any(|pattern| lowercase.contains(pattern));
+    if rejected {
+        Check::new(
+            "Provider",
+            Status::Fail,
+            format!("{url} rejected the API key"),
+        )
+        .fix("Check that the API key is valid and has not expired, or create a new one")
+    } else {
+        let reason = error.lines().next().unwrap_or_default();
+        Check::new(
+            "Provider",
+            Status::Fail,
+            format!("Could not reach {url}: {reason}"),
+        )
+        .fix("Check the network connection and proxy settings (HTTPS_PROXY), and OPENAI_URL or ANTHROPIC_URL if set")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_api_key_check() {
+        let actual = [
+            api_key_check(|name| (name == "OPENAI_API_KEY").then(|| "sk-1".to_string())).status,
+            api_key_check(|name| (name == "FORGE_KEY").then(|| " ".to_string())).status,
+            api_key_check(|_| None).status,
+        ];
+
+        let expected = [Status::Pass, Status::Fail, Status::Fail];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_provider_check() {
+        let fixture = "https://api.openai.com/v1/";
+
+        let actual = [
+            provider_check(fixture, "HTTP status 401 Unauthorized"),
+            provider_check(fixture, "error sending request\ndns error"),
+        ]
+        .map(|check| check.detail);
+
+        let expected = [
+            "https://api.openai.com/v1/ rejected the API key".to_string(),
+            "Could not reach https://api.openai.com/v1/: error sending request".to_string(),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_program_check() {
+        let actual = program_check("npm", "automatic updates", None);
+
+        let expected = Check::new(
+            "npm",
+            Status::Warn,
+            "Not found, it is used for automatic updates",
+        )
+        .fix("Install npm and make sure it is in your PATH");
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use forge_api::Pricing;
+use serde::{Deserialize, Serialize};
+
+/// Number of the last turns kept to estimate the next ones
+const MAX_TURNS: usize = 100;
+
+/// Directory that plans are written to, relative to the workspace
+const PLANS_DIR: &str = "plans";
+
+/// Usage of a turn, ie: a message of the user and the answer of the agent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TurnRecord {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub duration_ms: u64,
+}
+
+/// Usage of the last turns, across sessions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TurnStats {
+    turns: VecDeque<TurnRecord>,
+}
+
+impl TurnStats {
+    /// Path of the turn statistics, shared by every workspace
+    pub fn path(base_path: &Path) -> PathBuf {
+        base_path.join("turn_stats.json")
+    }
+
+    /// Loads the statistics from `path`, starting afresh when it is missing or
+    /// unreadable
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Records a turn, forgetting the oldest one past the limit
+    pub fn record(&mut self, turn: TurnRecord) {
+        self.turns.push_back(turn);
+        while self.turns.len() > MAX_TURNS {
+            self.turns.pop_front();
+        }
+    }
+
+    /// Average usage of a turn, if any was recorded
+    fn average(&self) -> Option<TurnRecord> {
+        let count = self.turns.len() as u64;
+        if count == 0 {
+            return None;
+        }
+        let sum = |field: fn(&TurnRecord) -> u64| self.turns.iter().map(field).sum::<u64>() / count;
+        Some(TurnRecord {
+            prompt_tokens: sum(|turn| turn.prompt_tokens),
+            completion_tokens: sum(|turn| turn.completion_tokens),
+            duration_ms: sum(|turn| turn.duration_ms),
+        })
+    }
+}
+
+/// Predicted usage of a plan, assuming that each of its steps takes a turn
+#[derive(Debug, Clone, PartialEq)]
+pub struct Estimate {
+    pub steps: usize,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub duration: Duration,
+    /// Cost in USD, when the price of the model is known
+    pub cost: Option<f64>,
+}
+
+impl Estimate {
+    /// Estimates a plan of `steps` steps from the usage of the last turns, or
+    /// nothing when no turn was recorded yet
+    pub fn new(steps: usize, stats: &TurnStats, pricing: Option<&Pricing>) -> Option<Self> {
+        let turn = stats.average()?;
+        let steps_u64 = steps as u64;
+        let prompt_tokens = turn.prompt_tokens * steps_u64;
+        let completion_tokens = turn.completion_tokens * steps_u64;
+        Some(Self {
+            steps,
+            prompt_tokens,
+            completion_tokens,
+            duration: Duration::from_millis(turn.duration_ms * steps_u64),
+            cost: pricing.map(|pricing| pricing.cost(prompt_tokens, completion_tokens)),
+        })
+    }
+
+    /// Whether the estimated cost is over the budget. An unknown cost is never
+    /// over it.
+    pub fn exceeds(&self, budget: f64) -> bool {
+        self.cost.is_some_and(|cost| cost > budget)
+    }
+}
+
+/// Number of steps of a plan, ie: the numbered items or `###` headings of its
+/// implementation plan, or of the whole plan when it has no such section
+pub fn plan_steps(markdown: &str) -> usize {
+    let section = markdown
+        .split("\n## ")
+        .find(|section| section.to_lowercase().starts_with("implementation plan"))
+        .unwrap_or(markdown);
+
+    section
+        .lines()
+        .filter(|line| {
+            let line = line.strip_prefix("### ").unwrap_or(line);
+            line.split_once(". ").is_some_and(|(number, _)| {
+                !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+            })
+        })
+        .count()
+}
+
+/// Most recently changed plan of the workspace, if it was changed after `since`
+pub fn latest_plan(cwd: &Path, since: SystemTime) -> Option<PathBuf> {
+    std::fs::read_dir(cwd.join(PLANS_DIR))
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "md"))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .filter(|(modified, _)| *modified >= since)
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_plan_steps() {
+        let fixture = "# Retry\n\n## Objective\n\n1. Not a step\n\n## Implementation Plan\n\n1. **Add the config**\n  - Notes: 2. nested\n2. **Use it**\n\n### 3. Test it\n\n## Verification Criteria\n\n1. Not a step either\n";
+
+        let actual = plan_steps(fixture);
+
+        let expected = 3;
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_estimate() {
+        let mut stats = TurnStats::default();
+        stats.record(TurnRecord {
+            prompt_tokens: 1000,
+            completion_tokens: 100,
+            duration_ms: 2000,
+        });
+        stats.record(TurnRecord {
+            prompt_tokens: 3000,
+            completion_tokens: 300,
+            duration_ms: 4000,
+        });
+        let pricing = Pricing::default().prompt(0.000001).completion(0.00001);
+
+        let actual = Estimate::new(4, &stats, Some(&pricing)).unwrap();
+
+        let expected = Estimate {
+            steps: 4,
+            prompt_tokens: 8000,
+            completion_tokens: 800,
+            duration: Duration::from_secs(12),
+            cost: Some(pricing.cost(8000, 800)),
+        };
+        assert_eq!(actual, expected);
+        assert!(actual.exceeds(0.01));
+        assert_eq!(Estimate::new(4, &TurnStats::default(), None), None);
+    }
+}
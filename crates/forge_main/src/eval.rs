@@ -0,0 +1,297 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use forge_api::{AgentMessage, ChatResponse, ModelId, Usage};
+use forge_display::{Align, TableFormat};
+use serde::Deserialize;
+use tokio_stream::StreamExt;
+
+/// Name of the file describing a task inside a fixture directory
+const TASK_FILE: &str = "task.yaml";
+
+/// Name of the directory containing the repository snapshot of a task
+const REPO_DIR: &str = "repo";
+
+/// A single evaluation task loaded from `<fixtures>/<name>/task.yaml`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct EvalTask {
+    /// Name of the task, defaults to the name of the fixture directory
+    #[serde(default)]
+    pub name: String,
+    /// The prompt sent to the agent
+    pub prompt: String,
+    /// Shell command executed in the workspace after the agent has finished.
+    /// The task passes if the command exits successfully.
+    pub check: String,
+    /// Directory with the repository snapshot that the agent works on,
+    /// defaults to the `repo` directory next to the task file.
+    #[serde(default)]
+    pub repo: Option<PathBuf>,
+}
+
+impl EvalTask {
+    /// Loads all the tasks from the fixture directory, sorted by name
+    pub fn load_all(dir: &Path) -> Result<Vec<EvalTask>> {
+        let mut tasks = Vec::new();
+        for entry in std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read fixtures: {}", dir.display()))?
+        {
+            let path = entry?.path();
+            if path.join(TASK_FILE).is_file() {
+                tasks.push(Self::load(&path)?);
+            }
+        }
+        tasks.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(tasks)
+    }
+
+    fn load(dir: &Path) -> Result<EvalTask> {
+        let path = dir.join(TASK_FILE);
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read task: {}", path.display()))?;
+        let mut task: EvalTask = serde_yml::from_str(&content)
+            .with_context(|| format!("Failed to parse task: {}", path.display()))?;
+
+        if task.name.is_empty() {
+            task.name = dir
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+        }
+
+        task.repo = match task.repo {
+            Some(repo) => Some(dir.join(repo)),
+            None => Some(dir.join(REPO_DIR)).filter(|repo| repo.is_dir()),
+        };
+
+        Ok(task)
+    }
+
+    /// Creates a fresh workspace for the task by copying the repository
+    /// snapshot into a temporary directory
+    pub fn workspace(&self) -> Result<tempfile::TempDir> {
+        let workspace = tempfile::Builder::new().prefix("forge-eval-").tempdir()?;
+        if let Some(repo) = &self.repo {
+            copy_dir(repo, workspace.path())?;
+        }
+        Ok(workspace)
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Outcome of running a single task with a model
+#[derive(Debug, Clone)]
+pub struct EvalResult {
+    pub task: String,
+    pub model: ModelId,
+    pub passed: bool,
+    pub duration: Duration,
+    pub usage: Usage,
+    /// Error raised by the agent while working on the task
+    pub error: Option<String>,
+}
+
+/// Drains a chat stream, accumulating the token usage reported by the agent
+pub async fn collect_usage(
+    stream: &mut (impl StreamExt<Item = Result<AgentMessage<ChatResponse>>> + Unpin),
+) -> (Usage, Option<String>) {
+    let mut usage = Usage::default();
+    while let Some(message) = stream.next().await {
+        match message {
+            Ok(AgentMessage { message: ChatResponse::Usage(current), .. }) => {
+                usage.prompt_tokens += current.prompt_tokens;
+                usage.completion_tokens += current.completion_tokens;
+                usage.total_tokens += current.total_tokens;
+            }
+            Ok(_) => {}
+            Err(err) => return (usage, Some(format!("{err:?}"))),
+        }
+    }
+    (usage, None)
+}
+
+/// Aggregated results of an evaluation run
+#[derive(Debug, Clone, Default)]
+pub struct EvalReport {
+    pub results: Vec<EvalResult>,
+}
+
+impl EvalReport {
+    fn models(&self) -> Vec<&ModelId> {
+        let mut models = Vec::new();
+        for result in &self.results {
+            if !models.contains(&&result.model) {
+                models.push(&result.model);
+            }
+        }
+        models
+    }
+}
+
+impl EvalReport {
+    /// Results of every task for every model, eg: to export as CSV
+    pub fn table(&self) -> TableFormat {
+        let mut table = TableFormat::new(["Model", "Task", "Result", "Tokens", "Duration"])
+            .align(3, Align::Right)
+            .align(4, Align::Right);
+        for result in &self.results {
+            table.push(result.row(true));
+        }
+        table
+    }
+}
+
+impl EvalResult {
+    fn row(&self, with_model: bool) -> Vec<String> {
+        let mut row = vec![
+            self.task.clone(),
+            if self.passed { "PASS" } else { "FAIL" }.to_string(),
+            self.usage.total_tokens.to_string(),
+            format!("{:.1}s", self.duration.as_secs_f64()),
+        ];
+        if with_model {
+            row.insert(0, self.model.to_string());
+        }
+        row
+    }
+}
+
+impl fmt::Display for EvalReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for model in self.models() {
+            let results = self
+                .results
+                .iter()
+                .filter(|result| &result.model == model)
+                .collect::<Vec<_>>();
+            let passed = results.iter().filter(|result| result.passed).count();
+            let tokens: u64 = results.iter().map(|result| result.usage.total_tokens).sum();
+            let duration: Duration = results.iter().map(|result| result.duration).sum();
+
+            let mut table = TableFormat::new(["Task", "Result", "Tokens", "Duration"])
+                .align(2, Align::Right)
+                .align(3, Align::Right)
+                .max_width(0, 48);
+            for result in &results {
+                table.push(result.row(false));
+            }
+
+            writeln!(f, "{model}")?;
+            writeln!(f, "{table}")?;
+            writeln!(
+                f,
+                "pass rate: {}/{} ({:.1}%), tokens: {}, duration: {:.1}s",
+                passed,
+                results.len(),
+                passed as f64 * 100.0 / results.len().max(1) as f64,
+                tokens,
+                duration.as_secs_f64()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn result(task: &str, model: &str, passed: bool, tokens: u64) -> EvalResult {
+        EvalResult {
+            task: task.to_string(),
+            model: ModelId::new(model),
+            passed,
+            duration: Duration::from_secs(2),
+            usage: Usage { total_tokens: tokens, ..Default::default() },
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_load_all_tasks() {
+        let fixture = tempfile::TempDir::new().unwrap();
+        let task = fixture.path().join("add-fn");
+        std::fs::create_dir_all(task.join("repo")).unwrap();
+        std::fs::write(task.join("repo/lib.rs"), "").unwrap();
+        std::fs::write(
+            task.join("task.yaml"),
+            "prompt: add a function\ncheck: cargo test\n",
+        )
+        .unwrap();
+
+        let actual = EvalTask::load_all(fixture.path()).unwrap();
+        let expected = vec![EvalTask {
+            name: "add-fn".to_string(),
+            prompt: "add a function".to_string(),
+            check: "cargo test".to_string(),
+            repo: Some(task.join("repo")),
+        }];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_workspace_copies_repo() {
+        let fixture = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(fixture.path().join("src")).unwrap();
+        std::fs::write(fixture.path().join("src/main.rs"), "fn main() {}").unwrap();
+        let task = EvalTask {
+            name: "copy".to_string(),
+            prompt: String::new(),
+            check: String::new(),
+            repo: Some(fixture.path().to_path_buf()),
+        };
+
+        let workspace = task.workspace().unwrap();
+
+        let actual = std::fs::read_to_string(workspace.path().join("src/main.rs")).unwrap();
+        let expected = "fn main() {}";
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_report_summary() {
+        let fixture = EvalReport {
+            results: vec![
+                result("a", "model-1", true, 100),
+                result("b", "model-1", false, 50),
+            ],
+        };
+
+        let actual = fixture.to_string();
+
+        assert!(actual.contains("pass rate: 1/2 (50.0%), tokens: 150, duration: 4.0s"));
+    }
+
+    #[test]
+    fn test_report_csv() {
+        let fixture = EvalReport {
+            results: vec![
+                result("a", "model-1", true, 100),
+                result("a", "model-2", false, 50),
+            ],
+        };
+
+        let actual = fixture.table().to_csv();
+
+        let expected =
+            "Model,Task,Result,Tokens,Duration\nmodel-1,a,PASS,100,2.0s\nmodel-2,a,FAIL,50,2.0s\n";
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,92 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use forge_api::{Conversation, ConversationId, API};
+use tokio::task::JoinHandle;
+use tracing::{debug, error};
+
+/// Directory of the conversation checkpoints
+pub fn sessions_path(base_path: &Path) -> PathBuf {
+    base_path.join("sessions")
+}
+
+/// Path of the checkpoint of a conversation, which can be resumed with
+/// `--conversation`
+pub fn checkpoint_path(base_path: &Path, conversation_id: &ConversationId) -> PathBuf {
+    sessions_path(base_path).join(format!("{conversation_id}.json"))
+}
+
+/// Writes the conversation to its checkpoint, replacing the previous one
+pub async fn write_checkpoint(path: &Path, conversation: &Conversation) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        forge_fs::ForgeFS::create_dir_all(parent).await?;
+    }
+    let content = serde_json::to_string_pretty(conversation)
+        .context("Failed to serialize the conversation")?;
+    forge_fs::ForgeFS::write_sealed(path, content).await
+}
+
+/// Saves the conversation to its checkpoint once the user has been idle at the
+/// prompt for the timeout. The timer stops when the guard is dropped, ie: on
+/// the next input, so that the session carries on where it was.
+pub struct IdleGuard {
+    handle: Option<JoinHandle<()>>,
+}
+
+impl IdleGuard {
+    pub fn start<F: API + 'static>(
+        api: Arc<F>,
+        conversation_id: Option<ConversationId>,
+        timeout: Option<Duration>,
+    ) -> Self {
+        let (Some(conversation_id), Some(timeout)) = (conversation_id, timeout) else {
+            return Self { handle: None };
+        };
+
+        let handle = tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            let path = checkpoint_path(&api.environment().base_path, &conversation_id);
+            let result = match api.conversation(&conversation_id).await {
+                Ok(Some(conversation)) => write_checkpoint(&path, &conversation).await,
+                Ok(None) => return,
+                Err(error) => Err(error),
+            };
+            match result {
+                Ok(_) => debug!(path = %path.display(), "Idle, saved the session checkpoint"),
+                Err(error) => error!(error = ?error, "Failed to save the session checkpoint"),
+            }
+        });
+        Self { handle: Some(handle) }
+    }
+}
+
+impl Drop for IdleGuard {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use forge_api::Workflow;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_write_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = Conversation::new(ConversationId::generate(), Workflow::default());
+        let path = checkpoint_path(dir.path(), &fixture.id);
+
+        write_checkpoint(&path, &fixture).await.unwrap();
+
+        let actual: Conversation =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(actual.id, fixture.id);
+    }
+}
@@ -5,6 +5,7 @@ use colored::Colorize;
 use forge_api::Environment;
 use forge_tracker::VERSION;
 
+use crate::locale::LOCALE;
 use crate::model::ForgeCommandManager;
 use crate::state::UIState;
 
@@ -97,14 +98,14 @@ impl From<&UIState> for Info {
         info = info.add_title("Usage".to_string());
 
         if estimated > usage.prompt_tokens {
-            info = info.add_key_value("Prompt", format!("~{estimated}"));
+            info = info.add_key_value("Prompt", format!("~{}", LOCALE.number(estimated)));
         } else {
-            info = info.add_key_value("Prompt", usage.prompt_tokens)
+            info = info.add_key_value("Prompt", LOCALE.number(usage.prompt_tokens))
         }
 
         info = info
-            .add_key_value("Completion", usage.completion_tokens)
-            .add_key_value("Total", usage.total_tokens);
+            .add_key_value("Completion", LOCALE.number(usage.completion_tokens))
+            .add_key_value("Total", LOCALE.number(usage.total_tokens));
 
         info
     }
@@ -0,0 +1,220 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use forge_api::{Command, Workflow};
+
+/// Directory of the markdown files with the rules of the project. Every agent
+/// follows them, in addition to the custom rules of the workflow.
+pub const RULES_DIR: &str = ".forge/rules";
+
+/// Technology stacks that `forge init` tunes the configuration for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stack {
+    Rust,
+    Node,
+    Python,
+}
+
+impl Stack {
+    /// Stacks used in the project, based on the manifest files in `cwd`
+    pub fn detect(cwd: &Path) -> Vec<Stack> {
+        [
+            (Stack::Rust, &["Cargo.toml"][..]),
+            (Stack::Node, &["package.json"][..]),
+            (
+                Stack::Python,
+                &["pyproject.toml", "setup.py", "requirements.txt"][..],
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, manifests)| manifests.iter().any(|file| cwd.join(file).exists()))
+        .map(|(stack, _)| stack)
+        .collect()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stack::Rust => "rust",
+            Stack::Node => "node",
+            Stack::Python => "python",
+        }
+    }
+
+    /// Commands that verify a change, in the order they should run
+    fn checks(self) -> &'static [&'static str] {
+        match self {
+            Stack::Rust => &[
+                "cargo fmt --all",
+                "cargo clippy --all-targets -- -D warnings",
+                "cargo test",
+            ],
+            Stack::Node => &["npm run lint --if-present", "npm test"],
+            Stack::Python => &["ruff check .", "pytest"],
+        }
+    }
+
+    fn rules(self) -> &'static str {
+        match self {
+            Stack::Rust => {
+                "# Rust\n\n- Propagate errors with `?` and avoid `unwrap` outside of tests.\n- Keep unit tests next to the code in a `#[cfg(test)] mod tests` module.\n- Prefer borrowing over cloning, and iterators over index loops.\n"
+            }
+            Stack::Node => {
+                "# Node\n\n- Use the package manager of the lockfile to add dependencies.\n- Follow the lint and formatting configuration of the project.\n- Prefer `async`/`await` over callbacks and raw promise chains.\n"
+            }
+            Stack::Python => {
+                "# Python\n\n- Add type hints to new functions and methods.\n- Write tests with pytest, next to the existing tests.\n- Prefer the standard library over new dependencies.\n"
+            }
+        }
+    }
+}
+
+/// Starter workflow for a project using `stacks`. Agents and their tools come
+/// from the default workflow, so only the project specific parts are set.
+pub fn workflow(stacks: &[Stack]) -> Workflow {
+    let checks = stacks
+        .iter()
+        .flat_map(|stack| stack.checks())
+        .map(|command| format!("- `{command}`"))
+        .collect::<Vec<_>>();
+
+    let mut workflow = Workflow::new();
+    workflow.custom_rules = Some(format!(
+        "Follow the conventions of the surrounding code and keep changes focused on the task.\nThe rules of the project are in `{RULES_DIR}`, add a markdown file there for new ones."
+    ));
+    workflow.commands = vec![Command::default()
+        .name("review")
+        .description("Reviews the uncommitted changes")
+        .prompt("Review the uncommitted changes with `git diff`. Point out bugs, missing tests and deviations from the conventions of the project, most important first.")];
+
+    if !checks.is_empty() {
+        let checks = checks.join("\n");
+        workflow.custom_rules = workflow
+            .custom_rules
+            .map(|rules| format!("{rules}\n\nBefore finishing a task, run:\n{checks}"));
+        workflow.commands.insert(
+            0,
+            Command::default()
+                .name("check")
+                .description("Runs the checks of the project and fixes the failures")
+                .prompt(format!(
+                    "Run the following commands and fix the failures until they all pass:\n{checks}"
+                )),
+        );
+    }
+    workflow
+}
+
+/// Writes the starter `forge.yaml` and the rules of each stack to `cwd`.
+/// Existing files are only overwritten when `force` is set.
+pub fn scaffold(cwd: &Path, stacks: &[Stack], force: bool) -> Result<Vec<PathBuf>> {
+    let workflow_path = cwd.join("forge.yaml");
+    if workflow_path.exists() && !force {
+        bail!(
+            "{} already exists, use --force to overwrite it",
+            workflow_path.display()
+        );
+    }
+
+    let mut files = vec![(workflow_path, serde_yml::to_string(&workflow(stacks))?)];
+    let rules_dir = cwd.join(RULES_DIR);
+    files.extend(stacks.iter().map(|stack| {
+        (
+            rules_dir.join(format!("{}.md", stack.name())),
+            stack.rules().to_string(),
+        )
+    }));
+    if stacks.is_empty() {
+        files.push((
+            rules_dir.join("project.md"),
+            "# Project\n\n- Document the build, test and lint commands of the project here.\n"
+                .to_string(),
+        ));
+    }
+
+    std::fs::create_dir_all(&rules_dir)?;
+    let mut written = Vec::new();
+    for (path, content) in files {
+        if force || !path.exists() {
+            std::fs::write(&path, content)?;
+            written.push(path);
+        }
+    }
+    Ok(written)
+}
+
+/// Rules of the project from the markdown files in `.forge/rules`, sorted by
+/// file name
+pub fn project_rules(cwd: &Path) -> Option<String> {
+    let mut files = std::fs::read_dir(cwd.join(RULES_DIR))
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let rules = files
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .map(|content| content.trim().to_string())
+        .filter(|content| !content.is_empty())
+        .collect::<Vec<_>>();
+    (!rules.is_empty()).then(|| rules.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_detect_stacks() {
+        let fixture = TempDir::new().unwrap();
+        std::fs::write(fixture.path().join("Cargo.toml"), "").unwrap();
+        std::fs::write(fixture.path().join("requirements.txt"), "").unwrap();
+
+        let actual = Stack::detect(fixture.path());
+
+        let expected = vec![Stack::Rust, Stack::Python];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_workflow_commands() {
+        let fixture = [Stack::Node];
+
+        let actual = workflow(&fixture);
+
+        let names = actual
+            .commands
+            .iter()
+            .map(|command| command.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["check", "review"]);
+        assert!(actual.custom_rules.unwrap().ends_with(
+            "Before finishing a task, run:\n- `npm run lint --if-present`\n- `npm test`"
+        ));
+    }
+
+    #[test]
+    fn test_scaffold_and_project_rules() {
+        let fixture = TempDir::new().unwrap();
+        let cwd = fixture.path();
+
+        let actual = scaffold(cwd, &[Stack::Rust, Stack::Python], false).unwrap();
+
+        let expected = vec![
+            cwd.join("forge.yaml"),
+            cwd.join(".forge/rules/rust.md"),
+            cwd.join(".forge/rules/python.md"),
+        ];
+        assert_eq!(actual, expected);
+        assert!(scaffold(cwd, &[Stack::Rust], false).is_err());
+
+        let rules = project_rules(cwd).unwrap();
+        assert!(rules.starts_with("# Python"));
+        assert!(rules.contains("\n\n# Rust"));
+    }
+}
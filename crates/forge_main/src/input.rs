@@ -8,7 +8,6 @@ use tokio::fs;
 use crate::editor::{ForgeEditor, ReadResult};
 use crate::model::{Command, ForgeCommandManager};
 use crate::prompt::ForgePrompt;
-use crate::TRACKER;
 
 /// Console implementation for handling user input via command line.
 #[derive(Debug)]
@@ -43,15 +42,12 @@ impl Console {
                 ReadResult::Continue => continue,
                 ReadResult::Exit => return Ok(Command::Exit),
                 ReadResult::Empty => continue,
-                ReadResult::Success(text) => {
-                    tokio::spawn(TRACKER.dispatch(forge_tracker::EventKind::Prompt(text.clone())));
-                    match self.command.parse(&text) {
-                        Ok(command) => return Ok(command),
-                        Err(e) => {
-                            println!("{}", TitleFormat::error(e.to_string()));
-                        }
+                ReadResult::Success(text) => match self.command.parse(&text) {
+                    Ok(command) => return Ok(command),
+                    Err(e) => {
+                        println!("{}", TitleFormat::error(e.to_string()));
                     }
-                }
+                },
             }
         }
     }
@@ -0,0 +1,81 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use forge_api::{Cassette, Interaction};
+
+/// Formats the `nth` last interaction with the provider, 1 being the last one:
+/// the request that was sent, with its messages, tool schemas and parameters,
+/// followed by the response that was received. Secrets were already scrubbed
+/// when the interactions were written.
+pub fn last_request(path: &Path, nth: usize) -> Result<String> {
+    if !path.exists() {
+        bail!("No request was sent to the provider yet");
+    }
+    let cassette = Cassette::load(path)?;
+    let count = cassette.interactions.len();
+    let Some(interaction) = nth
+        .checked_sub(1)
+        .and_then(|index| count.checked_sub(index + 1))
+        .and_then(|index| cassette.interactions.get(index))
+    else {
+        bail!("Only the last {count} requests are kept, set FORGE_INSPECTED_REQUESTS to keep more");
+    };
+    format(interaction)
+}
+
+fn format(interaction: &Interaction) -> Result<String> {
+    let request = serde_json::to_string_pretty(&interaction.request)?;
+    let response = serde_json::to_string_pretty(&interaction.response)?;
+
+    let mut output = String::new();
+    writeln!(output, "model: {}", interaction.model)?;
+    writeln!(
+        output,
+        "request: {} messages, {} tools, {} bytes",
+        interaction.request.messages.len(),
+        interaction.request.tools.len(),
+        request.len()
+    )?;
+    writeln!(output, "{request}")?;
+    writeln!(output, "response: {} messages", interaction.response.len())?;
+    writeln!(output, "{response}")?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use forge_api::{ChatCompletionMessage, Content, Context, ContextMessage, ModelId};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_last_request() {
+        let fixture = tempfile::TempDir::new().unwrap();
+        let path = fixture.path().join("last_requests.json");
+        let interaction = |text: &str| Interaction {
+            model: ModelId::new("model"),
+            request: Context::default().add_message(ContextMessage::user(text)),
+            response: vec![ChatCompletionMessage::assistant(Content::full("done"))],
+        };
+        let cassette = Cassette::default()
+            .interaction(interaction("first"))
+            .interaction(interaction("second"));
+        std::fs::write(&path, serde_json::to_string(&cassette).unwrap()).unwrap();
+
+        let actual = [
+            last_request(&path, 1).unwrap(),
+            last_request(&path, 2).unwrap(),
+        ];
+
+        let expected = [
+            format(&interaction("second")).unwrap(),
+            format(&interaction("first")).unwrap(),
+        ];
+        assert_eq!(actual, expected);
+        assert!(actual[0].starts_with("model: model\nrequest: 1 messages, 0 tools, "));
+        assert!(last_request(&path, 3).is_err());
+        assert!(last_request(&path, 0).is_err());
+    }
+}
@@ -1,18 +1,43 @@
 mod auto_update;
 mod banner;
+mod changelog;
 mod cli;
 mod completer;
+mod conflicts;
+mod diff;
+mod doctor;
 mod editor;
+mod estimate;
+mod eval;
+mod idle;
 mod info;
+mod init;
 mod input;
+mod inspect;
+mod locale;
+mod marks;
+mod memory;
+mod migrate;
 mod model;
+mod packages;
+mod pager;
+mod pipeline;
 mod prompt;
+mod report;
+mod retention;
+mod search;
+mod shadow;
 mod state;
+mod theme;
 mod tools_display;
+mod triage;
+mod trust;
 mod ui;
+mod watch;
 
 pub use auto_update::update_forge;
 pub use cli::Cli;
+pub use doctor::Doctor;
 use lazy_static::lazy_static;
 pub use ui::UI;
 lazy_static! {
@@ -0,0 +1,260 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, FixedOffset, Local, TimeZone, Utc};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// Locale of the user, from `FORGE_LOCALE` or the standard locale
+    /// variables, and timezone from `FORGE_TIMEZONE`
+    pub static ref LOCALE: Locale = Locale::from_env();
+}
+
+/// Timezone that timestamps are displayed in
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Zone {
+    #[default]
+    Local,
+    Utc,
+    Fixed(FixedOffset),
+}
+
+impl Zone {
+    /// Parses `local`, `UTC` or an offset such as `+05:30`
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("local") {
+            return Ok(Zone::Local);
+        }
+        if value.eq_ignore_ascii_case("utc") || value == "Z" {
+            return Ok(Zone::Utc);
+        }
+        let offset = value.split_at_checked(1).and_then(|(sign, offset)| {
+            let (hours, minutes) = offset.split_once(':').unwrap_or((offset, "0"));
+            let seconds = hours.parse::<i32>().ok()? * 3600 + minutes.parse::<i32>().ok()? * 60;
+            match sign {
+                "+" => FixedOffset::east_opt(seconds),
+                "-" => FixedOffset::west_opt(seconds),
+                _ => None,
+            }
+        });
+        match offset {
+            Some(offset) => Ok(Zone::Fixed(offset)),
+            None => {
+                bail!("Invalid timezone '{value}', expected local, UTC or an offset such as +05:30")
+            }
+        }
+    }
+}
+
+/// Formats timestamps, durations, numbers and sizes in reports for people, eg:
+/// `1.234,5` in German. Machine readable outputs, such as JSON and CSV, keep
+/// the raw values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Locale {
+    thousands: &'static str,
+    decimal: char,
+    date: &'static str,
+    zone: Zone,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self {
+            thousands: ",",
+            decimal: '.',
+            date: "%Y-%m-%d %H:%M:%S",
+            zone: Zone::Local,
+        }
+    }
+}
+
+impl Locale {
+    /// Locale from a tag such as `de-DE`, `fr_FR.UTF-8` or `en`. Unknown
+    /// languages use the default formats.
+    pub fn new(tag: &str) -> Self {
+        let tag = tag.split(['.', '@']).next().unwrap_or_default();
+        let mut parts = tag.split(['-', '_']);
+        let language = parts.next().unwrap_or_default().to_lowercase();
+        let region = parts.next().unwrap_or_default().to_uppercase();
+
+        let (thousands, decimal) = match language.as_str() {
+            "de" | "es" | "it" | "nl" | "pt" | "da" | "id" | "tr" | "el" => (".", ','),
+            "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "no" | "uk" | "hu" => {
+                ("\u{a0}", ',')
+            }
+            _ => (",", '.'),
+        };
+        let date = match (language.as_str(), region.as_str()) {
+            ("en", "US") => "%m/%d/%Y %I:%M:%S %p",
+            ("en", "GB" | "AU" | "NZ" | "IE" | "IN") => "%d/%m/%Y %H:%M:%S",
+            ("de" | "ru" | "pl" | "cs" | "sk" | "fi" | "nb" | "no" | "tr" | "uk" | "da", _) => {
+                "%d.%m.%Y %H:%M:%S"
+            }
+            ("fr" | "es" | "it" | "pt" | "el" | "id", _) => "%d/%m/%Y %H:%M:%S",
+            ("nl", _) => "%d-%m-%Y %H:%M:%S",
+            ("ja" | "zh", _) => "%Y/%m/%d %H:%M:%S",
+            _ => "%Y-%m-%d %H:%M:%S",
+        };
+        Self { thousands, decimal, date, zone: Zone::Local }
+    }
+
+    /// Locale from `FORGE_LOCALE`, falling back to `LC_ALL` and `LANG`, with
+    /// the timezone from `FORGE_TIMEZONE`. An invalid timezone is ignored.
+    pub fn from_env() -> Self {
+        let tag = ["FORGE_LOCALE", "LC_ALL", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty());
+        let locale = match tag {
+            Some(tag) if tag != "C" && tag != "POSIX" => Self::new(&tag),
+            _ => Self::default(),
+        };
+        match std::env::var("FORGE_TIMEZONE").map(|zone| Zone::parse(&zone)) {
+            Ok(Ok(zone)) => locale.zone(zone),
+            _ => locale,
+        }
+    }
+
+    pub fn zone(mut self, zone: Zone) -> Self {
+        self.zone = zone;
+        self
+    }
+
+    /// Integer with the thousands separator of the locale, eg: `12,345`
+    pub fn number(&self, value: u64) -> String {
+        let digits = value.to_string();
+        let mut out = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                out.push_str(self.thousands);
+            }
+            out.push(digit);
+        }
+        out
+    }
+
+    /// Decimal number with `precision` digits after the decimal separator
+    pub fn decimal(&self, value: f64, precision: usize) -> String {
+        let formatted = format!("{value:.precision$}");
+        let (sign, unsigned) = match formatted.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", formatted.as_str()),
+        };
+        let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        let integer = self.number(integer.parse().unwrap_or_default());
+        if fraction.is_empty() {
+            format!("{sign}{integer}")
+        } else {
+            format!("{sign}{integer}{}{fraction}", self.decimal)
+        }
+    }
+
+    /// Duration in the largest units that fit, eg: `4.2s`, `3m 05s` or
+    /// `1h 02m`
+    pub fn duration(&self, duration: Duration) -> String {
+        let seconds = duration.as_secs();
+        if seconds < 60 {
+            format!("{}s", self.decimal(duration.as_secs_f64(), 1))
+        } else if seconds < 3600 {
+            format!("{}m {:02}s", seconds / 60, seconds % 60)
+        } else {
+            format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60)
+        }
+    }
+
+    /// Size in binary units, eg: `1.5 MB`
+    pub fn bytes(&self, bytes: u64) -> String {
+        const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+        if bytes < 1024 {
+            return format!("{} B", self.number(bytes));
+        }
+
+        let mut size = bytes as f64 / 1024.0;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < UNITS.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        format!("{} {}", self.decimal(size, 1), UNITS[unit])
+    }
+
+    /// Timestamp in the timezone and date format of the locale
+    pub fn datetime<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> String {
+        match self.zone {
+            Zone::Local => time.with_timezone(&Local).format(self.date).to_string(),
+            Zone::Utc => format!("{} UTC", time.with_timezone(&Utc).format(self.date)),
+            Zone::Fixed(offset) => time
+                .with_timezone(&offset)
+                .format(&format!("{} %:z", self.date))
+                .to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_numbers() {
+        let fixture = [
+            Locale::default(),
+            Locale::new("de_DE.UTF-8"),
+            Locale::new("fr-FR"),
+        ];
+
+        let actual = fixture
+            .iter()
+            .map(|locale| {
+                [
+                    locale.number(1234567),
+                    locale.decimal(-1234.56, 1),
+                    locale.bytes(1536 * 1024),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let expected = vec![
+            ["1,234,567", "-1,234.6", "1.5 MB"],
+            ["1.234.567", "-1.234,6", "1,5 MB"],
+            ["1\u{a0}234\u{a0}567", "-1\u{a0}234,6", "1,5 MB"],
+        ]
+        .into_iter()
+        .map(|row| row.map(str::to_string))
+        .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_duration() {
+        let fixture = Locale::new("de");
+
+        let actual = [2_500, 185_000, 3_720_000]
+            .map(|millis| fixture.duration(Duration::from_millis(millis)));
+
+        let expected = ["2,5s", "3m 05s", "1h 02m"].map(str::to_string);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_datetime() {
+        let fixture = Utc.with_ymd_and_hms(2025, 3, 9, 14, 5, 0).unwrap();
+
+        let actual = [
+            Locale::new("de-DE").zone(Zone::Utc).datetime(&fixture),
+            Locale::new("en_US")
+                .zone(Zone::parse("+05:30").unwrap())
+                .datetime(&fixture),
+        ];
+
+        let expected = [
+            "09.03.2025 14:05:00 UTC".to_string(),
+            "03/09/2025 07:35:00 PM +05:30".to_string(),
+        ];
+        assert_eq!(actual, expected);
+        assert!(Zone::parse("Europe/Berlin").is_err());
+    }
+}
@@ -2,15 +2,29 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use clap::Parser;
-use forge::{Cli, UI};
+use forge::{Cli, Doctor, UI};
 use forge_api::ForgeAPI;
+use forge_fs::{Cipher, ForgeFS};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize and run the UI
     let cli = Cli::parse();
 
-    let api = Arc::new(ForgeAPI::init(cli.restricted));
+    // The doctor runs before the API is initialized, which fails without an API
+    // key, the most common problem it diagnoses
+    if let Some(doctor) = Doctor::from_cli(&cli) {
+        return doctor.run().await;
+    }
+
+    let api = Arc::new(ForgeAPI::init(cli.restricted)?);
+
+    // The cipher is set up once the environment, including the .env file, is
+    // loaded
+    if let Some(cipher) = Cipher::from_env()? {
+        ForgeFS::set_cipher(cipher);
+    }
+
     let mut ui = UI::init(cli, api)?;
     ui.run().await;
 
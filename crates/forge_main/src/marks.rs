@@ -0,0 +1,415 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use console::style;
+use forge_api::{Context, ContextMessage, Role};
+use forge_display::{DiffFormat, Glyph, TitleFormat};
+use forge_snaps::{Snapshot, SnapshotService};
+use regex::Regex;
+
+/// Prefix used to reference a mark in a prompt, eg: `@mark:fix-attempt-1`
+const REFERENCE_PREFIX: &str = "@mark:";
+
+/// Prefix used to reference a turn of the conversation in a prompt, eg:
+/// `@turn:3`
+const TURN_PREFIX: &str = "@turn:";
+
+/// Maximum number of characters of a message kept in a mark
+const EXCERPT_LEN: usize = 80;
+
+/// Number of messages shown when jumping to a mark
+const JUMP_MESSAGES: usize = 5;
+
+/// A named point in the conversation that can be returned to later
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mark {
+    pub name: String,
+    /// Number of messages in the main agent's context when the mark was made
+    pub message: usize,
+    /// Time since the unix epoch at which the mark was made, used to find the
+    /// state of files through their snapshots
+    pub timestamp: Duration,
+    /// Beginning of the last message before the mark
+    pub excerpt: String,
+}
+
+impl Mark {
+    pub fn new(name: impl Into<String>, context: Option<&Context>) -> Result<Self> {
+        let messages = context
+            .map(|context| context.messages.as_slice())
+            .unwrap_or_default();
+        let excerpt = messages
+            .iter()
+            .rev()
+            .find_map(text)
+            .map(|(_, content)| excerpt(content))
+            .unwrap_or_default();
+
+        Ok(Self {
+            name: name.into(),
+            message: messages.len(),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?,
+            excerpt,
+        })
+    }
+}
+
+/// Marks made in the current conversation, by name
+#[derive(Debug, Clone, Default)]
+pub struct Marks(BTreeMap<String, Mark>);
+
+impl Marks {
+    /// Adds a mark, replacing any existing mark with the same name
+    pub fn add(&mut self, mark: Mark) {
+        self.0.insert(mark.name.clone(), mark);
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Mark> {
+        self.0
+            .get(name)
+            .ok_or_else(|| anyhow!("No mark named '{name}', use /mark to list them"))
+    }
+
+    /// Lists the marks in the order they were made
+    pub fn list(&self) -> String {
+        if self.0.is_empty() {
+            return TitleFormat::info("No marks")
+                .sub_title("use /mark <name> to add one")
+                .to_string();
+        }
+
+        let mut marks = self.0.values().collect::<Vec<_>>();
+        marks.sort_by_key(|mark| mark.timestamp);
+        marks
+            .into_iter()
+            .map(|mark| {
+                format!(
+                    "{} {} {}",
+                    style(&mark.name).bold(),
+                    style(format!("#{}", mark.message)).dim(),
+                    mark.excerpt
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Replaces every `@mark:<name>` in the text with a description of the
+    /// marked point that the model can relate to the conversation
+    pub fn expand(&self, text: &str) -> Result<String> {
+        let pattern = Regex::new(&format!(r"{}([\w.-]+)", regex::escape(REFERENCE_PREFIX)))?;
+        let mut output = String::with_capacity(text.len());
+        let mut last = 0;
+        for captures in pattern.captures_iter(text) {
+            let reference = captures.get(0).unwrap();
+            let mark = self.get(&captures[1])?;
+            output.push_str(&text[last..reference.start()]);
+            write!(
+                output,
+                "the point in this conversation marked '{}', right after: \"{}\"",
+                mark.name, mark.excerpt
+            )?;
+            last = reference.end();
+        }
+        output.push_str(&text[last..]);
+        Ok(output)
+    }
+}
+
+/// Returns the author and content of messages that carry text
+fn text(message: &ContextMessage) -> Option<(String, &str)> {
+    match message {
+        ContextMessage::ContentMessage(message) if message.role != Role::System => Some((
+            message.role.to_string().to_lowercase(),
+            message.content.as_str(),
+        )),
+        ContextMessage::ToolMessage(result) => {
+            Some((result.name.as_str().to_string(), result.content.as_str()))
+        }
+        _ => None,
+    }
+}
+
+fn excerpt(content: &str) -> String {
+    let line = content.trim().lines().next().unwrap_or_default();
+    let mut excerpt = line.chars().take(EXCERPT_LEN).collect::<String>();
+    if line.chars().count() > EXCERPT_LEN || content.trim().lines().count() > 1 {
+        excerpt.push_str("...");
+    }
+    excerpt
+}
+
+/// Shows the messages that follow the mark
+pub fn jump(context: Option<&Context>, mark: &Mark) -> String {
+    let messages = context
+        .map(|context| context.messages.as_slice())
+        .unwrap_or_default();
+    let mut output = TitleFormat::info(format!("Mark {}", mark.name))
+        .sub_title(format!("after message #{}", mark.message))
+        .to_string();
+
+    let following = messages
+        .iter()
+        .enumerate()
+        .skip(mark.message)
+        .filter_map(|(index, message)| {
+            text(message).map(|(source, content)| (index, source, content))
+        })
+        .take(JUMP_MESSAGES)
+        .collect::<Vec<_>>();
+    if following.is_empty() {
+        output.push_str(&format!(
+            "\n  {}",
+            style("No messages after this mark").dim()
+        ));
+    }
+    for (index, source, content) in following {
+        output.push_str(&format!(
+            "\n  {} {}",
+            style(format!("#{} {source}", index + 1)).dim(),
+            excerpt(content)
+        ));
+    }
+    output
+}
+
+/// Files changed after the `from` mark, with their content at that mark and
+/// at the `to` mark, or in the working tree when `to` is not provided
+async fn changes(
+    service: &SnapshotService,
+    from: &Mark,
+    to: Option<&Mark>,
+) -> Result<Vec<(String, String, String)>> {
+    // A snapshot holds the content of a file right before it was changed, so
+    // the state of a file at a given time is its first snapshot taken after
+    // that time, or the working tree when it hasn't changed since
+    let mut by_path = BTreeMap::<String, Vec<Snapshot>>::new();
+    for snapshot in service.list().await? {
+        if snapshot.timestamp >= from.timestamp {
+            by_path
+                .entry(snapshot.path.clone())
+                .or_default()
+                .push(snapshot);
+        }
+    }
+
+    let mut changes = Vec::new();
+    for (path, mut snapshots) in by_path {
+        snapshots.sort_by_key(|snapshot| snapshot.timestamp);
+        let old = content(service, &path, snapshots.first()).await?;
+        let later = to.and_then(|to| {
+            snapshots
+                .iter()
+                .find(|snapshot| snapshot.timestamp >= to.timestamp)
+        });
+        let new = content(service, &path, later).await?;
+        if old != new {
+            changes.push((path, old, new));
+        }
+    }
+    Ok(changes)
+}
+
+/// Diffs the files changed after the `from` mark between their state at that
+/// mark and their state at the `to` mark, or in the working tree when `to` is
+/// not provided
+pub async fn diff(service: &SnapshotService, from: &Mark, to: Option<&Mark>) -> Result<String> {
+    let target = to.map_or("working tree".to_string(), |mark| mark.name.clone());
+    let mut output = String::new();
+    for (path, old, new) in changes(service, from, to).await? {
+        let title = TitleFormat::action("Diff").sub_title(format!(
+            "{path} [{} {} {target}]",
+            from.name,
+            Glyph::Arrow
+        ));
+        writeln!(output, "{title}\n{}", DiffFormat::format(&old, &new))?;
+    }
+
+    if output.is_empty() {
+        return Ok(TitleFormat::info("No changes")
+            .sub_title(format!("between {} and {target}", from.name))
+            .to_string());
+    }
+    Ok(output)
+}
+
+/// Start of every turn of the conversation, ie: every message sent by the
+/// user, so that the files can be shown as they were changed in a turn
+#[derive(Debug, Clone, Default)]
+pub struct Turns(Vec<Mark>);
+
+impl Turns {
+    /// Marks the start of a new turn, returning its number
+    pub fn start(&mut self, context: Option<&Context>) -> Result<usize> {
+        let number = self.0.len() + 1;
+        self.0.push(Mark::new(format!("turn-{number}"), context)?);
+        Ok(number)
+    }
+
+    /// Replaces every `@turn:<number>` in the text with a reference to that
+    /// turn, and appends the diffs of the files changed during the turn as
+    /// recorded by their snapshots, so that the model can explain a change
+    /// from what actually happened instead of from its memory
+    pub async fn expand(&self, service: &SnapshotService, text: &str) -> Result<String> {
+        let pattern = Regex::new(&format!(r"{}(\d+)", regex::escape(TURN_PREFIX)))?;
+        let mut numbers = Vec::new();
+        let expanded = pattern.replace_all(text, |captures: &regex::Captures| {
+            let number = captures[1].parse::<usize>().unwrap_or_default();
+            if !numbers.contains(&number) {
+                numbers.push(number);
+            }
+            format!("turn {number} of this conversation")
+        });
+
+        let mut output = expanded.to_string();
+        for number in numbers {
+            let from = number
+                .checked_sub(1)
+                .and_then(|index| self.0.get(index))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "No turn {number}, this conversation has {} turns",
+                        self.0.len()
+                    )
+                })?;
+            let to = self.0.get(number);
+
+            write!(output, "\n\n<file_history turn=\"{number}\">")?;
+            for (path, old, new) in changes(service, from, to).await? {
+                let diff = console::strip_ansi_codes(&DiffFormat::format(&old, &new)).to_string();
+                write!(
+                    output,
+                    "\n<file path=\"{path}\">\n{}\n</file>",
+                    diff.trim_end()
+                )?;
+            }
+            write!(output, "\n</file_history>")?;
+        }
+        Ok(output)
+    }
+}
+
+/// Reads the content of a snapshot, or of the file in the working tree when
+/// there's no snapshot. Deleted files are treated as empty.
+async fn content(
+    service: &SnapshotService,
+    path: &str,
+    snapshot: Option<&Snapshot>,
+) -> Result<String> {
+    let content = match snapshot {
+        Some(snapshot) => service.read(snapshot).await?,
+        None => tokio::fs::read(path).await.unwrap_or_default(),
+    };
+    Ok(String::from_utf8_lossy(&content).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use console::strip_ansi_codes;
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn fixture_context() -> Context {
+        Context::default()
+            .add_message(ContextMessage::system("System prompt"))
+            .add_message(ContextMessage::user("Fix the failing test"))
+            .add_message(ContextMessage::assistant("Updated the assertion", None))
+    }
+
+    fn fixture_mark(name: &str, timestamp: Duration) -> Mark {
+        Mark {
+            name: name.to_string(),
+            message: 2,
+            timestamp,
+            excerpt: "Fix the failing test".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_mark_new() {
+        let actual = Mark::new("first", Some(&fixture_context())).unwrap();
+
+        assert_eq!(actual.message, 3);
+        assert_eq!(actual.excerpt, "Updated the assertion");
+    }
+
+    #[test]
+    fn test_expand_references() {
+        let mut fixture = Marks::default();
+        fixture.add(fixture_mark("fix-attempt-1", Duration::ZERO));
+
+        let actual = fixture
+            .expand("Go back to @mark:fix-attempt-1 and retry")
+            .unwrap();
+
+        let expected = "Go back to the point in this conversation marked 'fix-attempt-1', right after: \"Fix the failing test\" and retry";
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_expand_unknown_mark() {
+        let fixture = Marks::default();
+
+        let actual = fixture.expand("see @mark:missing");
+
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn test_jump() {
+        let fixture = fixture_context();
+
+        let actual = jump(Some(&fixture), &fixture_mark("first", Duration::ZERO));
+        let actual = strip_ansi_codes(&actual);
+
+        assert!(actual.contains("#3 assistant Updated the assertion"));
+        assert!(!actual.contains("Fix the failing test"));
+    }
+
+    #[tokio::test]
+    async fn test_diff_between_mark_and_working_tree() {
+        let fixture = TempDir::new().unwrap();
+        let file = fixture.path().join("a.txt");
+        let service = SnapshotService::new(fixture.path().join("snapshots"));
+        std::fs::write(&file, "before\n").unwrap();
+        let mark = fixture_mark(
+            "start",
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap(),
+        );
+        service.create_snapshot(file.clone()).await.unwrap();
+        std::fs::write(&file, "after\n").unwrap();
+
+        let actual = diff(&service, &mark, None).await.unwrap();
+        let actual = strip_ansi_codes(&actual);
+
+        assert!(actual.contains("|-before"));
+        assert!(actual.contains("|+after"));
+    }
+
+    #[tokio::test]
+    async fn test_expand_turns() {
+        let fixture = TempDir::new().unwrap();
+        let file = fixture.path().join("a.txt");
+        let service = SnapshotService::new(fixture.path().join("snapshots"));
+        std::fs::write(&file, "before\n").unwrap();
+        let mut turns = Turns::default();
+        turns.start(None).unwrap();
+        service.create_snapshot(file.clone()).await.unwrap();
+        std::fs::write(&file, "after\n").unwrap();
+
+        let actual = turns
+            .expand(&service, "Why did you change a.txt in @turn:1?")
+            .await
+            .unwrap();
+
+        assert!(actual.starts_with("Why did you change a.txt in turn 1 of this conversation?"));
+        assert!(actual.contains("a.txt\">"));
+        assert!(actual.contains("|-before"));
+        assert!(actual.contains("|+after"));
+        assert!(turns.expand(&service, "@turn:2").await.is_err());
+    }
+}
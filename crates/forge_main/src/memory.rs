@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+
+use forge_api::{Context, ContextMessage, Role};
+
+use crate::info::Info;
+use crate::locale::LOCALE;
+
+/// Maximum number of characters of a summary shown in the status
+const EXCERPT_LEN: usize = 120;
+
+/// Tags around the summaries that compaction puts in place of the messages
+/// it drops
+const SUMMARY_START: &str = "<summary>";
+const SUMMARY_END: &str = "</summary>";
+
+/// What the agent did last with a file whose tool call is still in its
+/// context
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileAccess {
+    Read,
+    Changed,
+    Removed,
+}
+
+impl FileAccess {
+    fn from_tool(name: &str) -> Option<Self> {
+        match name {
+            "forge_tool_fs_read" => Some(Self::Read),
+            "forge_tool_fs_create" | "forge_tool_fs_patch" | "forge_tool_fs_undo" => {
+                Some(Self::Changed)
+            }
+            "forge_tool_fs_remove" => Some(Self::Removed),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Changed => "changed",
+            Self::Removed => "removed",
+        }
+    }
+}
+
+/// A summary that replaced messages dropped by compaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    /// 1-based position of the summary in the context
+    pub message: usize,
+    pub excerpt: String,
+}
+
+/// What the model knows of the conversation, ie: what is left in the main
+/// agent's context after compaction
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryStatus {
+    pub messages: usize,
+    pub estimated_tokens: u64,
+    pub summaries: Vec<Summary>,
+    /// Files that the calls in the context operated on, by path
+    pub files: BTreeMap<String, FileAccess>,
+}
+
+impl MemoryStatus {
+    pub fn new(context: Option<&Context>) -> Self {
+        let Some(context) = context else {
+            return Self::default();
+        };
+
+        let mut status = Self {
+            messages: context.messages.len(),
+            estimated_tokens: context.estimate_token_count(),
+            ..Default::default()
+        };
+        for (index, message) in context.messages.iter().enumerate() {
+            let ContextMessage::ContentMessage(message) = message else {
+                continue;
+            };
+            if message.role != Role::Assistant {
+                continue;
+            }
+            if let Some(summary) = summary(&message.content) {
+                status
+                    .summaries
+                    .push(Summary { message: index + 1, excerpt: excerpt(summary) });
+            }
+            for call in message.tool_calls.iter().flatten() {
+                let access = FileAccess::from_tool(call.name.as_str());
+                let path = call.arguments.get("path").and_then(|path| path.as_str());
+                if let (Some(access), Some(path)) = (access, path) {
+                    status.files.insert(path.to_string(), access);
+                }
+            }
+        }
+        status
+    }
+}
+
+/// Text of a compaction summary
+fn summary(content: &str) -> Option<&str> {
+    let start = content.find(SUMMARY_START)? + SUMMARY_START.len();
+    let end = content.rfind(SUMMARY_END)?;
+    content.get(start..end).map(str::trim)
+}
+
+/// First line of a summary, shortened to `EXCERPT_LEN` characters
+fn excerpt(summary: &str) -> String {
+    let line = summary
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    if line.chars().count() > EXCERPT_LEN {
+        format!("{}...", line.chars().take(EXCERPT_LEN).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+impl From<&MemoryStatus> for Info {
+    fn from(status: &MemoryStatus) -> Self {
+        let mut info = Info::new()
+            .add_title("Context")
+            .add_key_value("Messages", LOCALE.number(status.messages as u64))
+            .add_key_value(
+                "Tokens",
+                format!("~{}", LOCALE.number(status.estimated_tokens)),
+            )
+            .add_title("Summaries");
+
+        if status.summaries.is_empty() {
+            info = info.add_key("No history was summarized");
+        }
+        for summary in &status.summaries {
+            info = info.add_key_value(format!("Message {}", summary.message), &summary.excerpt);
+        }
+
+        info = info.add_title("Files");
+        if status.files.is_empty() {
+            info = info.add_key("No files, mention one with @ to add it");
+        }
+        for (path, access) in &status.files {
+            info = info.add_key_value(path, access.as_str());
+        }
+
+        info.add_title("Correcting")
+            .add_key_value("/search <text>", "Check whether a detail is still known")
+            .add_key_value("/compact", "Summarize the history again")
+            .add_key_value("/new", "Start over with an empty context")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use forge_api::{ToolCallFull, ToolName};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    fn call(tool: &str, path: &str) -> ContextMessage {
+        ContextMessage::assistant(
+            "",
+            Some(vec![
+                ToolCallFull::new(ToolName::new(tool)).arguments(json!({ "path": path }))
+            ]),
+        )
+    }
+
+    #[test]
+    fn test_memory_status() {
+        let fixture = Context::default()
+            .add_message(ContextMessage::user("Fix the build"))
+            .add_message(ContextMessage::assistant(
+                "Continuing from a prior analysis: <summary>\nThe build fails on a missing import.\nMore</summary> Proceed.",
+                None,
+            ))
+            .add_message(call("forge_tool_fs_read", "/src/lib.rs"))
+            .add_message(call("forge_tool_fs_read", "/src/main.rs"))
+            .add_message(call("forge_tool_fs_patch", "/src/lib.rs"))
+            .add_message(call("forge_tool_process_shell", "/src"));
+
+        let actual = MemoryStatus::new(Some(&fixture));
+
+        let expected = MemoryStatus {
+            messages: 6,
+            estimated_tokens: fixture.estimate_token_count(),
+            summaries: vec![Summary {
+                message: 2,
+                excerpt: "The build fails on a missing import.".to_string(),
+            }],
+            files: BTreeMap::from([
+                ("/src/lib.rs".to_string(), FileAccess::Changed),
+                ("/src/main.rs".to_string(), FileAccess::Read),
+            ]),
+        };
+        assert_eq!(actual, expected);
+    }
+}
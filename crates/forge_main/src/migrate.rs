@@ -0,0 +1,110 @@
+use std::fmt;
+use std::path::Path;
+
+/// A guided dependency upgrade that is handed to the agent as a task. The
+/// agent is expected to research the changes between versions, find the usage
+/// sites and iterate on the build until it succeeds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationTask {
+    pub dependency: String,
+    pub version: String,
+    pub build_command: String,
+}
+
+impl MigrationTask {
+    pub fn new(
+        dependency: impl ToString,
+        version: impl ToString,
+        build_command: impl ToString,
+    ) -> Self {
+        Self {
+            dependency: dependency.to_string(),
+            version: version.to_string(),
+            build_command: build_command.to_string(),
+        }
+    }
+}
+
+/// Detects the command used to verify that the project compiles, based on the
+/// manifest files present in the working directory.
+pub fn detect_build_command(cwd: &Path) -> Option<&'static str> {
+    [
+        ("Cargo.toml", "cargo check --all-targets"),
+        ("package.json", "npm run build --if-present"),
+        ("go.mod", "go build ./..."),
+        ("pyproject.toml", "python -m compileall -q ."),
+        ("pom.xml", "mvn -q compile"),
+    ]
+    .into_iter()
+    .find(|(manifest, _)| cwd.join(manifest).exists())
+    .map(|(_, command)| command)
+}
+
+impl fmt::Display for MigrationTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let MigrationTask { dependency, version, build_command } = self;
+        writeln!(
+            f,
+            "Upgrade the dependency `{dependency}` to version `{version}` in this project."
+        )?;
+        writeln!(f)?;
+        writeln!(f, "Follow these steps:")?;
+        writeln!(
+            f,
+            "1. Find the currently used version of `{dependency}` in the project manifests."
+        )?;
+        writeln!(
+            f,
+            "2. Fetch the changelog, release notes or migration guide of `{dependency}` between the current version and `{version}` and list the breaking API changes."
+        )?;
+        writeln!(
+            f,
+            "3. Update the manifest to `{version}` and search the code base for every usage site affected by those changes."
+        )?;
+        writeln!(
+            f,
+            "4. Run `{build_command}`, fix the reported errors and repeat until the command succeeds."
+        )?;
+        writeln!(
+            f,
+            "5. Finish with a summary of the API changes that were handled and any follow-up work that remains."
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_detect_build_command_cargo() {
+        let fixture = TempDir::new().unwrap();
+        std::fs::write(fixture.path().join("Cargo.toml"), "").unwrap();
+
+        let actual = detect_build_command(fixture.path());
+        let expected = Some("cargo check --all-targets");
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_detect_build_command_unknown() {
+        let fixture = TempDir::new().unwrap();
+
+        let actual = detect_build_command(fixture.path());
+        let expected = None;
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_task_mentions_dependency_and_build() {
+        let fixture = MigrationTask::new("tokio", "2.0.0", "cargo check");
+
+        let actual = fixture.to_string();
+
+        assert!(actual.contains("`tokio` to version `2.0.0`"));
+        assert!(actual.contains("Run `cargo check`"));
+    }
+}
@@ -1,6 +1,7 @@
 use std::sync::{Arc, Mutex};
 
 use forge_api::{Model, Workflow};
+use forge_display::Glyph;
 use strum::{EnumProperty, IntoEnumIterator};
 use strum_macros::{EnumIter, EnumProperty};
 
@@ -83,7 +84,7 @@ impl ForgeCommandManager {
 
         commands.extend(workflow.commands.clone().into_iter().map(|cmd| {
             let name = format!("/{}", cmd.name);
-            let description = format!("⚙ {}", cmd.description);
+            let description = format!("{} {}", Glyph::Gear, cmd.description);
             let value = cmd.prompt.clone();
 
             ForgeCommand { name, description, value }
@@ -181,6 +182,36 @@ impl ForgeCommandManager {
             "/help" => Ok(Command::Help),
             "/model" => Ok(Command::Model),
             "/tools" => Ok(Command::Tools),
+            "/mark" => Ok(Command::Mark(parameters.join(" "))),
+            "/jump" => match parameters.as_slice() {
+                [name] => Ok(Command::Jump(name.to_string())),
+                _ => Err(anyhow::anyhow!("Usage: /jump <mark>")),
+            },
+            "/diff" => match parameters.as_slice() {
+                [from] => Ok(Command::Diff(from.to_string(), None)),
+                [from, to] => Ok(Command::Diff(from.to_string(), Some(to.to_string()))),
+                _ => Err(anyhow::anyhow!("Usage: /diff <mark> [<mark>]")),
+            },
+            "/promote" => Ok(Command::Promote),
+            "/memory" => match parameters.as_slice() {
+                [] | ["status"] => Ok(Command::Memory),
+                _ => Err(anyhow::anyhow!("Usage: /memory status")),
+            },
+            "/debug" => match parameters.as_slice() {
+                ["last-request"] => Ok(Command::LastRequest(1)),
+                ["last-request", nth] => nth
+                    .parse()
+                    .map(Command::LastRequest)
+                    .map_err(|_| anyhow::anyhow!("Usage: /debug last-request [<n>]")),
+                _ => Err(anyhow::anyhow!("Usage: /debug last-request [<n>]")),
+            },
+            "/search" => {
+                if parameters.is_empty() {
+                    Err(anyhow::anyhow!("Usage: /search <text>"))
+                } else {
+                    Ok(Command::Search(parameters.join(" ")))
+                }
+            }
             text => {
                 let parts = text.split_ascii_whitespace().collect::<Vec<&str>>();
 
@@ -253,6 +284,39 @@ pub enum Command {
     /// This can be triggered with the '/tools' command.
     #[strum(props(usage = "List all available tools with their descriptions and schema"))]
     Tools,
+    /// Search the messages and tool outputs of the current conversation
+    /// This can be triggered with the '/search <text>' command.
+    #[strum(props(usage = "Search the current conversation (e.g. /search error)"))]
+    Search(String),
+    /// Mark the current point of the conversation, or list the marks when no
+    /// name is given. This can be triggered with the '/mark <name>' command.
+    #[strum(props(usage = "Mark this point of the conversation (e.g. /mark fix-attempt-1)"))]
+    Mark(String),
+    /// Show the messages that follow a mark
+    /// This can be triggered with the '/jump <mark>' command.
+    #[strum(props(usage = "Show the conversation from a mark (e.g. /jump fix-attempt-1)"))]
+    Jump(String),
+    /// Diff the files changed since a mark, against another mark or the
+    /// working tree. This can be triggered with the '/diff <mark> [<mark>]'
+    /// command.
+    #[strum(props(usage = "Show file changes since a mark (e.g. /diff start fix-attempt-1)"))]
+    Diff(String, Option<String>),
+    /// Validate the changes of a shadow workspace and copy them to the
+    /// workspace. This can be triggered with the '/promote' command.
+    #[strum(props(usage = "Validate the changes of the shadow workspace and apply them"))]
+    Promote,
+    /// Show what the model knows after compaction: the summaries and files
+    /// left in the context. This can be triggered with the '/memory status'
+    /// command.
+    #[strum(props(usage = "Show what the model knows of the conversation (e.g. /memory status)"))]
+    Memory,
+    /// Show the nth last request sent to the provider and its response, with
+    /// secrets masked. This can be triggered with the '/debug last-request
+    /// [<n>]' command.
+    #[strum(props(
+        usage = "Show the last request sent to the provider (e.g. /debug last-request)"
+    ))]
+    LastRequest(usize),
     /// Handles custom command defined in workflow file.
     Custom(PartialEvent),
     /// Executes a native shell command.
@@ -275,6 +339,13 @@ impl Command {
             Command::Dump(_) => "/dump",
             Command::Model => "/model",
             Command::Tools => "/tools",
+            Command::Search(_) => "/search",
+            Command::Mark(_) => "/mark",
+            Command::Jump(_) => "/jump",
+            Command::Diff(_, _) => "/diff",
+            Command::Promote => "/promote",
+            Command::Memory => "/memory",
+            Command::LastRequest(_) => "/debug",
             Command::Custom(event) => &event.name,
             Command::Shell(_) => "!shell",
         }
@@ -427,6 +498,20 @@ mod tests {
         // Verify - provided value should override default
         assert_eq!(result, Some(String::from("provided_value")));
     }
+    #[test]
+    fn test_parse_memory_status() {
+        let cmd_manager = ForgeCommandManager::default();
+
+        let actual = [
+            cmd_manager.parse("/memory status").unwrap(),
+            cmd_manager.parse("/memory").unwrap(),
+        ];
+
+        let expected = [Command::Memory, Command::Memory];
+        assert_eq!(actual, expected);
+        assert!(cmd_manager.parse("/memory clear").is_err());
+    }
+
     #[test]
     fn test_parse_shell_command() {
         // Setup
@@ -485,4 +570,64 @@ mod tests {
             "Shell command should not be in default commands"
         );
     }
+
+    #[test]
+    fn test_parse_search_command() {
+        // Setup
+        let cmd_manager = ForgeCommandManager::default();
+
+        // Execute
+        let result = cmd_manager.parse("/search mismatched types").unwrap();
+
+        // Verify
+        assert_eq!(result, Command::Search("mismatched types".to_string()));
+    }
+
+    #[test]
+    fn test_parse_search_command_without_query() {
+        // Setup
+        let cmd_manager = ForgeCommandManager::default();
+
+        // Execute
+        let result = cmd_manager.parse("/search");
+
+        // Verify
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_diff_command() {
+        // Setup
+        let cmd_manager = ForgeCommandManager::default();
+
+        // Execute
+        let result = cmd_manager.parse("/diff start end").unwrap();
+
+        // Verify
+        assert_eq!(
+            result,
+            Command::Diff("start".to_string(), Some("end".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_debug_command() {
+        // Setup
+        let cmd_manager = ForgeCommandManager::default();
+
+        // Execute
+        let actual = [
+            cmd_manager.parse("/debug last-request").ok(),
+            cmd_manager.parse("/debug last-request 3").ok(),
+            cmd_manager.parse("/debug").ok(),
+        ];
+
+        // Verify
+        let expected = [
+            Some(Command::LastRequest(1)),
+            Some(Command::LastRequest(3)),
+            None,
+        ];
+        assert_eq!(actual, expected);
+    }
 }
@@ -0,0 +1,353 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use regex::Regex;
+use serde_json::Value;
+
+/// Files that mark the root of a Bazel workspace
+const BAZEL_ROOTS: &[&str] = &["MODULE.bazel", "WORKSPACE", "WORKSPACE.bazel"];
+
+/// Files that mark a Bazel package
+const BAZEL_BUILD_FILES: &[&str] = &["BUILD", "BUILD.bazel"];
+
+/// Maximum depth searched for Bazel packages
+const MAX_BAZEL_DEPTH: usize = 8;
+
+/// Member of a monorepo: a Cargo workspace member, a pnpm, yarn or npm
+/// workspace, or a Bazel package
+#[derive(Debug, Clone, PartialEq)]
+pub struct Package {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl Package {
+    fn new(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self { name: name.into(), path: path.into() }
+    }
+}
+
+/// Finds the package named `name` in the monorepo that contains `cwd`. The
+/// name is matched against package names and against their paths relative to
+/// the root of the monorepo, eg: `forge_main` or `crates/forge_main`.
+pub fn find(cwd: &Path, name: &str) -> Result<Package> {
+    let Some((root, packages)) = cwd.ancestors().find_map(|root| {
+        let packages = detect(root);
+        (!packages.is_empty()).then(|| (root.to_path_buf(), packages))
+    }) else {
+        bail!(
+            "No workspace members found in {} or its parents",
+            cwd.display()
+        )
+    };
+
+    let name = name.trim_start_matches("//").trim_end_matches('/');
+    let found = packages.iter().find(|package| {
+        package.name.trim_start_matches("//") == name
+            || package
+                .path
+                .strip_prefix(&root)
+                .is_ok_and(|path| path == Path::new(name))
+    });
+    match found {
+        Some(package) => Ok(package.clone()),
+        None => bail!(
+            "Package '{name}' not found, available packages: {}",
+            packages
+                .iter()
+                .map(|package| package.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Packages of the monorepo rooted at `root`, sorted by name
+pub fn detect(root: &Path) -> Vec<Package> {
+    let mut packages = cargo(root);
+    packages.extend(pnpm(root));
+    packages.extend(node(root));
+    packages.extend(bazel(root));
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    packages.dedup_by(|a, b| a.path == b.path);
+    packages
+}
+
+fn cargo(root: &Path) -> Vec<Package> {
+    let Ok(manifest) = std::fs::read_to_string(root.join("Cargo.toml")) else {
+        return Vec::new();
+    };
+    let Some(workspace) = toml_section(&manifest, "workspace") else {
+        return Vec::new();
+    };
+    let members = toml_array(workspace, "members");
+    let excluded = toml_array(workspace, "exclude");
+
+    expand(root, &members)
+        .into_iter()
+        .filter(|path| !excluded.iter().any(|exclude| path.ends_with(exclude)))
+        .filter_map(|path| {
+            let manifest = std::fs::read_to_string(path.join("Cargo.toml")).ok()?;
+            let name = toml_section(&manifest, "package")
+                .and_then(|package| toml_string(package, "name"))
+                .or_else(|| file_name(&path))?;
+            Some(Package::new(name, path))
+        })
+        .collect()
+}
+
+fn pnpm(root: &Path) -> Vec<Package> {
+    let Ok(content) = std::fs::read_to_string(root.join("pnpm-workspace.yaml")) else {
+        return Vec::new();
+    };
+    let Ok(workspace) = serde_yml::from_str::<serde_yml::Value>(&content) else {
+        return Vec::new();
+    };
+    let patterns = workspace["packages"]
+        .as_sequence()
+        .map(|patterns| {
+            patterns
+                .iter()
+                .filter_map(|pattern| pattern.as_str().map(str::to_string))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    node_packages(root, &patterns)
+}
+
+/// Yarn and npm workspaces, from the `workspaces` of `package.json`
+fn node(root: &Path) -> Vec<Package> {
+    let Some(manifest) = read_json(&root.join("package.json")) else {
+        return Vec::new();
+    };
+    let workspaces = match &manifest["workspaces"] {
+        Value::Array(patterns) => patterns.clone(),
+        Value::Object(workspaces) => workspaces
+            .get("packages")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    let patterns = workspaces
+        .iter()
+        .filter_map(|pattern| pattern.as_str().map(str::to_string))
+        .collect::<Vec<_>>();
+    node_packages(root, &patterns)
+}
+
+fn node_packages(root: &Path, patterns: &[String]) -> Vec<Package> {
+    let (excluded, included): (Vec<_>, Vec<_>) = patterns
+        .iter()
+        .cloned()
+        .partition(|pattern| pattern.starts_with('!'));
+    let excluded = excluded
+        .iter()
+        .map(|pattern| pattern.trim_start_matches('!').to_string())
+        .collect::<Vec<_>>();
+    let excluded = expand(root, &excluded);
+
+    expand(root, &included)
+        .into_iter()
+        .filter(|path| !excluded.contains(path))
+        .filter_map(|path| {
+            let manifest = read_json(&path.join("package.json"))?;
+            let name = manifest["name"]
+                .as_str()
+                .map(str::to_string)
+                .or_else(|| file_name(&path))?;
+            Some(Package::new(name, path))
+        })
+        .collect()
+}
+
+fn bazel(root: &Path) -> Vec<Package> {
+    if !BAZEL_ROOTS.iter().any(|file| root.join(file).is_file()) {
+        return Vec::new();
+    }
+    let mut packages = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        if dir != root
+            && BAZEL_BUILD_FILES
+                .iter()
+                .any(|file| dir.join(file).is_file())
+        {
+            let label = dir.strip_prefix(root).unwrap_or(&dir).to_string_lossy();
+            packages.push(Package::new(
+                format!("//{}", label.replace('\\', "/")),
+                &dir,
+            ));
+        }
+        if depth >= MAX_BAZEL_DEPTH {
+            continue;
+        }
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Hidden directories and the output symlinks of Bazel are skipped
+            let skipped = name.starts_with('.') || name.starts_with("bazel-");
+            if !skipped && entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                pending.push((entry.path(), depth + 1));
+            }
+        }
+    }
+    packages
+}
+
+/// Directories matching glob patterns relative to `root`
+fn expand(root: &Path, patterns: &[String]) -> Vec<PathBuf> {
+    patterns
+        .iter()
+        .filter_map(|pattern| glob::glob(&root.join(pattern).to_string_lossy()).ok())
+        .flatten()
+        .flatten()
+        .filter(|path| path.is_dir())
+        .collect()
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+/// Content of a `[section]` of a TOML file, up to the next section
+fn toml_section<'a>(content: &'a str, section: &str) -> Option<&'a str> {
+    let header = format!("[{section}]");
+    let start = content
+        .lines()
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len() + 1;
+            Some((start, line))
+        })
+        .find(|(_, line)| line.trim() == header)
+        .map(|(start, _)| start + header.len())?;
+    let rest = content.get(start..)?;
+    let end = Regex::new(r"(?m)^\s*\[")
+        .unwrap()
+        .find(rest)
+        .map_or(rest.len(), |m| m.start());
+    Some(&rest[..end])
+}
+
+/// Strings of a TOML array, which may span multiple lines
+fn toml_array(section: &str, key: &str) -> Vec<String> {
+    let pattern = Regex::new(&format!(r#"(?ms)^\s*{key}\s*=\s*\[(.*?)\]"#)).unwrap();
+    let Some(captures) = pattern.captures(section) else {
+        return Vec::new();
+    };
+    Regex::new(r#""([^"]*)""#)
+        .unwrap()
+        .captures_iter(&captures[1])
+        .map(|captures| captures[1].to_string())
+        .collect()
+}
+
+fn toml_string(section: &str, key: &str) -> Option<String> {
+    let pattern = Regex::new(&format!(r#"(?m)^\s*{key}\s*=\s*"([^"]*)""#)).unwrap();
+    pattern
+        .captures(section)
+        .map(|captures| captures[1].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn names(packages: &[Package]) -> Vec<&str> {
+        packages
+            .iter()
+            .map(|package| package.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_detect_cargo() {
+        let fixture = tempfile::tempdir().unwrap();
+        let root = fixture.path();
+        write(
+            root,
+            "Cargo.toml",
+            "[workspace]\nmembers = [\n    \"crates/*\",\n]\nexclude = [\"crates/legacy\"]\n\n[workspace.dependencies]\nserde = \"1\"\n",
+        );
+        write(
+            root,
+            "crates/api/Cargo.toml",
+            "[package]\nname = \"forge_api\"\n",
+        );
+        write(
+            root,
+            "crates/main/Cargo.toml",
+            "[package]\nname = \"forge_main\"\n",
+        );
+        write(
+            root,
+            "crates/legacy/Cargo.toml",
+            "[package]\nname = \"legacy\"\n",
+        );
+
+        let actual = detect(root);
+
+        assert_eq!(names(&actual), vec!["forge_api", "forge_main"]);
+    }
+
+    #[test]
+    fn test_detect_node_and_pnpm() {
+        let fixture = tempfile::tempdir().unwrap();
+        let root = fixture.path();
+        write(
+            root,
+            "package.json",
+            r#"{"workspaces": {"packages": ["apps/*"]}}"#,
+        );
+        write(
+            root,
+            "pnpm-workspace.yaml",
+            "packages:\n  - 'libs/*'\n  - '!libs/old'\n",
+        );
+        write(root, "apps/web/package.json", r#"{"name": "@acme/web"}"#);
+        write(root, "libs/ui/package.json", r#"{"name": "@acme/ui"}"#);
+        write(root, "libs/old/package.json", r#"{"name": "@acme/old"}"#);
+
+        let actual = detect(root);
+
+        assert_eq!(names(&actual), vec!["@acme/ui", "@acme/web"]);
+    }
+
+    #[test]
+    fn test_find_bazel_package() {
+        let fixture = tempfile::tempdir().unwrap();
+        let root = fixture.path();
+        write(root, "MODULE.bazel", "");
+        write(root, "services/auth/BUILD.bazel", "");
+        write(root, "services/billing/BUILD", "");
+        write(root, "bazel-out/services/BUILD", "");
+
+        let actual = [
+            find(&root.join("services"), "//services/auth").unwrap(),
+            find(root, "services/billing").unwrap(),
+        ];
+
+        let expected = [
+            Package::new("//services/auth", root.join("services/auth")),
+            Package::new("//services/billing", root.join("services/billing")),
+        ];
+        assert_eq!(actual, expected);
+        assert!(find(root, "web").is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Pager used when none is configured and delta isn't installed
+const DEFAULT_PAGER: &str = "less -R";
+
+/// Number of lines above which diffs are shown in a pager by default
+pub const DEFAULT_DIFF_THRESHOLD: usize = 50;
+
+/// Shows the content through the user's pager when stdout is a terminal and
+/// the content doesn't fit on the screen. Returns false if the content wasn't
+/// paged, in which case the caller is expected to print it.
+pub fn page(content: &str) -> bool {
+    let (rows, _) = console::Term::stdout().size();
+    page_above(content, rows as usize)
+}
+
+/// Shows the content through the user's pager when it has more lines than the
+/// threshold. A threshold of 0 disables paging.
+pub fn page_above(content: &str, threshold: usize) -> bool {
+    if threshold == 0 || content.lines().count() <= threshold {
+        return false;
+    }
+    if !std::io::stdout().is_terminal() {
+        return false;
+    }
+
+    spawn(&pager(), content).is_ok()
+}
+
+/// Resolves the pager the same way git does, preferring delta over less when
+/// nothing is configured
+fn pager() -> String {
+    ["GIT_PAGER", "PAGER"]
+        .into_iter()
+        .find_map(|var| {
+            std::env::var(var)
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+        })
+        .or_else(|| is_installed("delta").then(|| "delta".to_string()))
+        .unwrap_or_else(|| DEFAULT_PAGER.to_string())
+}
+
+fn is_installed(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| is_executable(&dir.join(program)))
+    })
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.is_file() || path.with_extension("exe").is_file()
+}
+
+fn spawn(pager: &str, content: &str) -> std::io::Result<()> {
+    let mut args = pager.split_whitespace();
+    let program = args.next().unwrap_or(DEFAULT_PAGER);
+    let mut child = Command::new(program)
+        .args(args)
+        .env(
+            "LESS",
+            std::env::var("LESS").unwrap_or_else(|_| "FRX".to_string()),
+        )
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager closes its input when the user quits early, which isn't an
+        // error
+        let _ = stdin.write_all(content.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_above_disabled() {
+        let fixture = "line\n".repeat(100);
+        let actual = page_above(&fixture, 0);
+        assert!(!actual);
+    }
+
+    #[test]
+    fn test_page_above_short_content() {
+        let fixture = "line\n".repeat(10);
+        let actual = page_above(&fixture, DEFAULT_DIFF_THRESHOLD);
+        assert!(!actual);
+    }
+}
@@ -0,0 +1,540 @@
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use forge_api::{
+    AgentId, AgentMessage, ChatRequest, ChatResponse, Event, ResponseSchema, Workflow, API,
+};
+use handlebars::Handlebars;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use tokio_stream::StreamExt;
+
+use crate::report::{StepReport, StepRun, StepStatus};
+
+/// A sequence of agent runs loaded from a pipeline file, where later steps can
+/// use the structured output of earlier ones
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Pipeline {
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Step {
+    /// Name under which the output of the step is available to later steps
+    pub id: String,
+    /// Agent that runs the step, defaults to the main agent
+    #[serde(default)]
+    pub agent: Option<AgentId>,
+    /// Handlebars template of the task sent to the agent. It can refer to
+    /// `steps.<id>.output` and, when fanning out, to `item`.
+    pub prompt: String,
+    /// Schema of the step's output, which makes it available to later steps
+    #[serde(default)]
+    pub response_schema: Option<ResponseSchema>,
+    /// Path to a value that must be truthy for the step to run, eg:
+    /// `steps.check.output.missing_docs`. Prefix it with `!` to negate it.
+    #[serde(default)]
+    pub when: Option<String>,
+    /// Runs the step once per item
+    #[serde(default)]
+    pub for_each: Option<ForEach>,
+    /// Number of items processed in parallel when fanning out, each in its own
+    /// conversation
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForEach {
+    /// Every file matching the glob pattern, relative to the working directory
+    Files(String),
+    /// Every element of the array at the path, eg: `steps.find.output.files`
+    Items(String),
+}
+
+impl Pipeline {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read pipeline: {}", path.display()))?;
+        let pipeline: Pipeline = serde_yml::from_str(&content)
+            .with_context(|| format!("Failed to parse pipeline: {}", path.display()))?;
+
+        let mut ids = Vec::new();
+        for step in &pipeline.steps {
+            if ids.contains(&step.id.as_str()) {
+                bail!("Duplicate pipeline step '{}'", step.id);
+            }
+            ids.push(step.id.as_str());
+        }
+        Ok(pipeline)
+    }
+}
+
+impl Step {
+    /// Name of the event that starts the step
+    pub fn event_name(&self) -> String {
+        format!("pipeline/{}", self.id)
+    }
+
+    /// Prepares the workflow that the step runs with. The step's agent, or the
+    /// agents subscribed to `default_event` when none is configured, subscribe
+    /// to the step's event and respond with the step's schema.
+    pub fn workflow(&self, mut workflow: Workflow, default_event: &str) -> Result<Workflow> {
+        let event = self.event_name();
+        let mut found = false;
+        for agent in workflow.agents.iter_mut() {
+            let selected = match &self.agent {
+                Some(id) => &agent.id == id,
+                None => agent
+                    .subscribe
+                    .as_ref()
+                    .is_some_and(|events| events.iter().any(|name| name == default_event)),
+            };
+            if selected {
+                agent
+                    .subscribe
+                    .get_or_insert_with(Vec::new)
+                    .push(event.clone());
+                if let Some(schema) = &self.response_schema {
+                    agent.response_schema = Some(schema.clone());
+                }
+                found = true;
+            }
+        }
+
+        if !found {
+            bail!("No agent found for pipeline step '{}'", self.id);
+        }
+        Ok(workflow)
+    }
+}
+
+/// Runs a step in a new conversation and returns what the agent did
+pub async fn run_step<F: API>(
+    api: &F,
+    step: &Step,
+    workflow: Workflow,
+    default_event: &str,
+    prompt: String,
+) -> Result<StepRun> {
+    let workflow = step.workflow(workflow, default_event)?;
+    let conversation = api.init_conversation(workflow).await?;
+    let event = Event::new(step.event_name(), prompt);
+    let mut stream = api.chat(ChatRequest::new(event, conversation.id)).await?;
+    collect_run(&mut stream).await
+}
+
+/// Determines if a step failed because the provider is rate limiting requests,
+/// after the provider's own retries were exhausted
+pub fn is_rate_limited(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        let message = cause.to_string().to_lowercase();
+        message.contains("429")
+            || message.contains("too many requests")
+            || message.contains("rate limit")
+    })
+}
+
+/// Number of items of a fan-out that run in parallel. It is halved whenever
+/// the provider rate limits a request and grows back by one with every item
+/// that completes, up to the configured concurrency.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConcurrencyLimit {
+    current: usize,
+    max: usize,
+}
+
+impl ConcurrencyLimit {
+    pub fn new(max: usize) -> Self {
+        let max = max.max(1);
+        Self { current: max, max }
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn on_success(&mut self) {
+        self.current = (self.current + 1).min(self.max);
+    }
+
+    pub fn on_rate_limit(&mut self) {
+        self.current = (self.current / 2).max(1);
+    }
+}
+
+/// Outcome of a step for one item of a fan-out
+#[derive(Debug, Clone)]
+pub struct ItemResult {
+    pub item: Value,
+    pub output: Result<StepRun, String>,
+    pub duration: Duration,
+}
+
+/// Consolidated results of a step that fanned out over many items
+#[derive(Debug, Clone)]
+pub struct BatchReport {
+    pub step: String,
+    pub results: Vec<ItemResult>,
+}
+
+impl BatchReport {
+    /// Outputs in the order of the items, with `null` for the items that
+    /// failed
+    pub fn outputs(&self) -> Value {
+        Value::Array(
+            self.results
+                .iter()
+                .map(|result| match &result.output {
+                    Ok(run) => run.output.clone(),
+                    Err(_) => Value::Null,
+                })
+                .collect(),
+        )
+    }
+
+    pub fn into_step_reports(self) -> Vec<StepReport> {
+        self.results
+            .into_iter()
+            .map(|result| StepReport {
+                id: self.step.clone(),
+                item: Some(result.item),
+                status: if result.output.is_ok() {
+                    StepStatus::Completed
+                } else {
+                    StepStatus::Failed
+                },
+                error: result.output.as_ref().err().cloned(),
+                duration_secs: result.duration.as_secs_f64(),
+                run: result.output.unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failed = self
+            .results
+            .iter()
+            .filter(|result| result.output.is_err())
+            .collect::<Vec<_>>();
+        let duration: Duration = self.results.iter().map(|result| result.duration).sum();
+
+        writeln!(f, "{}", self.step)?;
+        for result in &failed {
+            if let Err(error) = &result.output {
+                writeln!(f, "  FAIL {} {}", item_label(&result.item), error)?;
+            }
+        }
+        writeln!(
+            f,
+            "  completed: {}/{}, failed: {}, duration: {:.1}s",
+            self.results.len() - failed.len(),
+            self.results.len(),
+            failed.len(),
+            duration.as_secs_f64()
+        )
+    }
+}
+
+/// Displays an item without quotes when it is a string, eg: a file path
+pub fn item_label(item: &Value) -> String {
+    match item {
+        Value::String(item) => item.clone(),
+        item => item.to_string(),
+    }
+}
+
+/// Reads the chat stream of a step until it ends
+pub async fn collect_run(
+    stream: &mut (impl StreamExt<Item = Result<AgentMessage<ChatResponse>>> + Unpin),
+) -> Result<StepRun> {
+    let mut run = StepRun::default();
+    while let Some(response) = stream.next().await {
+        run.record(response?.message);
+    }
+    Ok(run.finish())
+}
+
+/// Outputs of the steps that have run so far, which templates and paths are
+/// resolved against
+#[derive(Debug, Clone, Default)]
+pub struct PipelineState {
+    steps: Map<String, Value>,
+}
+
+impl PipelineState {
+    /// Records the output of a step. Steps that fan out record the array of
+    /// their outputs.
+    pub fn record(&mut self, step: &str, output: Value) {
+        self.steps
+            .insert(step.to_string(), json!({ "output": output }));
+    }
+
+    fn data(&self, item: Option<&Value>) -> Value {
+        let mut data = json!({ "steps": self.steps });
+        if let Some(item) = item {
+            data["item"] = item.clone();
+        }
+        data
+    }
+
+    /// Looks up a dotted path, eg: `steps.find.output.files`
+    pub fn resolve(&self, path: &str) -> Option<Value> {
+        path.split('.')
+            .try_fold(&self.data(None), |value, key| match value {
+                Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+                _ => value.get(key),
+            })
+            .cloned()
+    }
+
+    /// Evaluates the condition of a step
+    pub fn is_met(&self, condition: &str) -> bool {
+        match condition.trim().strip_prefix('!') {
+            Some(path) => !is_truthy(self.resolve(path.trim()).as_ref()),
+            None => is_truthy(self.resolve(condition.trim()).as_ref()),
+        }
+    }
+
+    /// Lists the items that a step fans out over
+    pub fn items(&self, for_each: &ForEach, cwd: &Path) -> Result<Vec<Value>> {
+        match for_each {
+            ForEach::Files(pattern) => {
+                let pattern = cwd.join(pattern);
+                let mut files = glob::glob(&pattern.to_string_lossy())?
+                    .filter_map(|path| path.ok())
+                    .filter(|path| path.is_file())
+                    .map(|path| {
+                        let path = path.strip_prefix(cwd).unwrap_or(&path);
+                        Value::String(path.display().to_string())
+                    })
+                    .collect::<Vec<_>>();
+                files.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+                Ok(files)
+            }
+            ForEach::Items(path) => match self.resolve(path) {
+                Some(Value::Array(items)) => Ok(items),
+                Some(_) => bail!("'{path}' is not an array"),
+                None => Err(anyhow!(
+                    "'{path}' doesn't refer to the output of a previous step"
+                )),
+            },
+        }
+    }
+
+    /// Renders the prompt of a step
+    pub fn render(&self, template: &str, item: Option<&Value>) -> Result<String> {
+        let mut hb = Handlebars::new();
+        hb.set_strict_mode(true);
+        hb.register_escape_fn(handlebars::no_escape);
+        hb.render_template(template, &self.data(item))
+            .context("Failed to render pipeline prompt")
+    }
+}
+
+fn is_truthy(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => false,
+        Some(Value::Bool(value)) => *value,
+        Some(Value::Number(number)) => number.as_f64().is_some_and(|number| number != 0.0),
+        Some(Value::String(value)) => !value.is_empty(),
+        Some(Value::Array(items)) => !items.is_empty(),
+        Some(Value::Object(_)) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use forge_api::Agent;
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn fixture() -> PipelineState {
+        let mut state = PipelineState::default();
+        state.record(
+            "find",
+            json!({"files": ["src/a.rs", "src/b.rs"], "missing_docs": true, "skipped": []}),
+        );
+        state
+    }
+
+    #[test]
+    fn test_parse_pipeline() {
+        let fixture = r#"
+steps:
+  - id: find
+    prompt: List the files with undocumented public functions
+    response_schema:
+      type: object
+      properties:
+        files: { type: array, items: { type: string } }
+  - id: document
+    when: steps.find.output.files
+    for_each:
+      items: steps.find.output.files
+    prompt: Add a license header and docstrings to {{item}}
+"#;
+
+        let actual: Pipeline = serde_yml::from_str(fixture).unwrap();
+
+        assert_eq!(actual.steps.len(), 2);
+        assert_eq!(
+            actual.steps[1].for_each,
+            Some(ForEach::Items("steps.find.output.files".to_string()))
+        );
+        assert!(actual.steps[0].response_schema.is_some());
+    }
+
+    #[test]
+    fn test_step_workflow_defaults_to_main_agent() {
+        let workflow = Workflow::new().agents(vec![
+            Agent::new("software-engineer").subscribe(vec!["act/user_task_init".to_string()]),
+            Agent::new("reviewer"),
+        ]);
+        let fixture: Step = serde_yml::from_str(
+            "id: find\nprompt: List the files\nresponse_schema: { type: array }",
+        )
+        .unwrap();
+
+        let actual = fixture.workflow(workflow, "act/user_task_init").unwrap();
+
+        assert_eq!(
+            actual.agents[0].subscribe,
+            Some(vec![
+                "act/user_task_init".to_string(),
+                "pipeline/find".to_string()
+            ])
+        );
+        assert_eq!(actual.agents[0].response_schema, fixture.response_schema);
+        assert_eq!(actual.agents[1].subscribe, None);
+    }
+
+    #[test]
+    fn test_step_workflow_unknown_agent() {
+        let fixture: Step =
+            serde_yml::from_str("id: review\nagent: reviewer\nprompt: Review").unwrap();
+
+        let actual = fixture.workflow(Workflow::new(), "act/user_task_init");
+
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn test_concurrency_limit() {
+        let mut limit = ConcurrencyLimit::new(8);
+
+        limit.on_rate_limit();
+        limit.on_rate_limit();
+        let throttled = limit.current();
+        limit.on_success();
+        let recovered = limit.current();
+
+        assert_eq!((throttled, recovered), (2, 3));
+    }
+
+    #[test]
+    fn test_is_rate_limited() {
+        let actual = [
+            anyhow!("Invalid status code: 429 Too Many Requests").context("Failed to chat"),
+            anyhow!("Invalid status code: 401 Unauthorized"),
+        ]
+        .map(|error| is_rate_limited(&error));
+
+        assert_eq!(actual, [true, false]);
+    }
+
+    #[test]
+    fn test_batch_report() {
+        let fixture = BatchReport {
+            step: "document".to_string(),
+            results: vec![
+                ItemResult {
+                    item: json!("src/a.rs"),
+                    output: Ok(StepRun::default().output(json!("done"))),
+                    duration: Duration::from_secs(3),
+                },
+                ItemResult {
+                    item: json!("src/b.rs"),
+                    output: Err("Timed out".to_string()),
+                    duration: Duration::from_secs(1),
+                },
+            ],
+        };
+
+        let actual = (fixture.to_string(), fixture.outputs());
+
+        let expected = (
+            "document\n  FAIL src/b.rs Timed out\n  completed: 1/2, failed: 1, duration: 4.0s\n"
+                .to_string(),
+            json!(["done", null]),
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let actual = fixture().resolve("steps.find.output.files.1");
+        assert_eq!(actual, Some(json!("src/b.rs")));
+    }
+
+    #[test]
+    fn test_is_met() {
+        let fixture = fixture();
+
+        let actual = [
+            "steps.find.output.missing_docs",
+            "!steps.find.output.missing_docs",
+            "steps.find.output.skipped",
+            "steps.unknown.output",
+        ]
+        .map(|condition| fixture.is_met(condition));
+
+        assert_eq!(actual, [true, false, false, false]);
+    }
+
+    #[test]
+    fn test_items_from_output() {
+        let actual = fixture()
+            .items(
+                &ForEach::Items("steps.find.output.files".to_string()),
+                Path::new("/"),
+            )
+            .unwrap();
+
+        assert_eq!(actual, vec![json!("src/a.rs"), json!("src/b.rs")]);
+    }
+
+    #[test]
+    fn test_items_from_files() {
+        let cwd = TempDir::new().unwrap();
+        std::fs::create_dir(cwd.path().join("src")).unwrap();
+        std::fs::write(cwd.path().join("src/b.rs"), "").unwrap();
+        std::fs::write(cwd.path().join("src/a.rs"), "").unwrap();
+        std::fs::write(cwd.path().join("README.md"), "").unwrap();
+
+        let actual = PipelineState::default()
+            .items(&ForEach::Files("src/*.rs".to_string()), cwd.path())
+            .unwrap();
+
+        assert_eq!(actual, vec![json!("src/a.rs"), json!("src/b.rs")]);
+    }
+
+    #[test]
+    fn test_render() {
+        let actual = fixture()
+            .render(
+                "Document {{item}} after {{steps.find.output.files.[1]}}",
+                Some(&json!("src/a.rs")),
+            )
+            .unwrap();
+
+        assert_eq!(actual, "Document src/a.rs after src/b.rs");
+    }
+}
@@ -5,6 +5,7 @@ use std::process::Command;
 
 use derive_setters::Setters;
 use forge_api::{ModelId, Usage};
+use forge_display::{palette, Glyph};
 use forge_tracker::VERSION;
 use nu_ansi_term::{Color, Style};
 use reedline::{Prompt, PromptHistorySearchStatus};
@@ -13,7 +14,6 @@ use crate::state::Mode;
 
 // Constants
 const MULTILINE_INDICATOR: &str = "::: ";
-const RIGHT_CHEVRON: &str = "❯";
 
 /// Very Specialized Prompt for the Agent Chat
 #[derive(Clone, Default, Setters)]
@@ -27,9 +27,10 @@ pub struct ForgePrompt {
 impl Prompt for ForgePrompt {
     fn render_prompt_left(&self) -> Cow<str> {
         // Pre-compute styles to avoid repeated style creation
-        let mode_style = Style::new().fg(Color::White).bold();
-        let folder_style = Style::new().fg(Color::Cyan);
-        let branch_style = Style::new().fg(Color::LightGreen);
+        let palette = palette();
+        let mode_style = Style::new().fg(color(palette.text)).bold();
+        let folder_style = Style::new().fg(color(palette.info));
+        let branch_style = Style::new().fg(color(palette.success));
 
         // Get current directory
         let current_dir = env::current_dir()
@@ -63,7 +64,7 @@ impl Prompt for ForgePrompt {
             }
         }
 
-        write!(result, "\n{} ", branch_style.paint(RIGHT_CHEVRON)).unwrap();
+        write!(result, "\n{} ", branch_style.paint(Glyph::Chevron.as_str())).unwrap();
 
         Cow::Owned(result)
     }
@@ -110,7 +111,7 @@ impl Prompt for ForgePrompt {
         Cow::Owned(
             Style::new()
                 .bold()
-                .fg(Color::DarkGray)
+                .fg(color(palette().muted))
                 .paint(&result)
                 .to_string(),
         )
@@ -147,7 +148,27 @@ impl Prompt for ForgePrompt {
             .unwrap();
         }
 
-        Cow::Owned(Style::new().fg(Color::White).paint(&result).to_string())
+        Cow::Owned(
+            Style::new()
+                .fg(color(palette().text))
+                .paint(&result)
+                .to_string(),
+        )
+    }
+}
+
+/// Converts a color of the display palette to the one used by the prompt
+fn color(color: console::Color) -> Color {
+    match color {
+        console::Color::Black => Color::Black,
+        console::Color::Red => Color::Red,
+        console::Color::Green => Color::Green,
+        console::Color::Yellow => Color::Yellow,
+        console::Color::Blue => Color::Blue,
+        console::Color::Magenta => Color::Magenta,
+        console::Color::Cyan => Color::Cyan,
+        console::Color::White => Color::White,
+        console::Color::Color256(value) => Color::Fixed(value),
     }
 }
 
@@ -194,7 +215,7 @@ mod tests {
 
         // Check that it has the expected format with mode and directory displayed
         assert!(actual.contains("ACT"));
-        assert!(actual.contains(RIGHT_CHEVRON));
+        assert!(actual.contains(Glyph::Chevron.as_str()));
     }
 
     #[test]
@@ -210,7 +231,7 @@ mod tests {
 
         // Verify the prompt contains expected elements regardless of $PROMPT var
         assert!(actual.contains("ACT"));
-        assert!(actual.contains(RIGHT_CHEVRON));
+        assert!(actual.contains(Glyph::Chevron.as_str()));
     }
 
     #[test]
@@ -0,0 +1,425 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use derive_setters::Setters;
+use forge_api::{ChatResponse, CodeOwners, ToolCallId, Usage};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::locale::Locale;
+use crate::pipeline::item_label;
+
+/// Tools that modify the file at their `path` argument
+const FILE_TOOLS: &[&str] = &[
+    "forge_tool_fs_create",
+    "forge_tool_fs_patch",
+    "forge_tool_fs_remove",
+    "forge_tool_fs_undo",
+];
+
+const SHELL_TOOL: &str = "forge_tool_process_shell";
+
+/// Shell command that an agent ran, eg: the project's tests
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandRun {
+    pub command: String,
+    pub success: bool,
+}
+
+/// Everything an agent did while running a step, collected from its chat
+/// stream
+#[derive(Debug, Clone, Default, Serialize, Setters)]
+#[setters(strip_option, into)]
+pub struct StepRun {
+    /// Validated output when the agent has a response schema, its final
+    /// message otherwise
+    pub output: Value,
+    pub usage: Usage,
+    pub files_changed: Vec<String>,
+    pub diffs: Vec<String>,
+    pub commands: Vec<CommandRun>,
+    #[serde(skip)]
+    #[setters(skip)]
+    message: Value,
+    #[serde(skip)]
+    #[setters(skip)]
+    pending_commands: Vec<(Option<ToolCallId>, String)>,
+}
+
+impl StepRun {
+    pub fn record(&mut self, response: ChatResponse) {
+        match response {
+            ChatResponse::Output(output) => self.output = output,
+            ChatResponse::Text { text, is_complete: true, is_summary: false, .. } => {
+                self.message = Value::String(text)
+            }
+            ChatResponse::Diff(diff) => self.diffs.push(diff),
+            ChatResponse::Usage(usage) => {
+                self.usage.prompt_tokens += usage.prompt_tokens;
+                self.usage.completion_tokens += usage.completion_tokens;
+                self.usage.total_tokens += usage.total_tokens;
+            }
+            ChatResponse::ToolCallStart(call) => {
+                let argument = |name: &str| {
+                    call.arguments
+                        .get(name)
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                };
+                if FILE_TOOLS.contains(&call.name.as_str()) {
+                    if let Some(path) = argument("path") {
+                        if !self.files_changed.contains(&path) {
+                            self.files_changed.push(path);
+                        }
+                    }
+                } else if call.name.as_str() == SHELL_TOOL {
+                    if let Some(command) = argument("command") {
+                        self.pending_commands.push((call.call_id.clone(), command));
+                    }
+                }
+            }
+            ChatResponse::ToolCallEnd(result) if result.name.as_str() == SHELL_TOOL => {
+                let position = self
+                    .pending_commands
+                    .iter()
+                    .position(|(call_id, _)| call_id == &result.call_id);
+                if let Some(position) = position {
+                    let (_, command) = self.pending_commands.remove(position);
+                    self.commands
+                        .push(CommandRun { command, success: !result.is_error });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Completes the run once the stream has ended
+    pub fn finish(mut self) -> Self {
+        if self.output.is_null() {
+            self.output = std::mem::take(&mut self.message);
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Completed,
+    Failed,
+    Skipped,
+}
+
+impl StepStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            StepStatus::Completed => "completed",
+            StepStatus::Failed => "failed",
+            StepStatus::Skipped => "skipped",
+        }
+    }
+}
+
+/// Outcome of a step, or of one item of a step that fans out
+#[derive(Debug, Clone, Serialize)]
+pub struct StepReport {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<Value>,
+    pub status: StepStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_secs: f64,
+    #[serde(flatten)]
+    pub run: StepRun,
+}
+
+impl StepReport {
+    pub fn new(
+        id: impl ToString,
+        item: Option<Value>,
+        run: &anyhow::Result<StepRun>,
+        duration: Duration,
+    ) -> Self {
+        let (status, error, run) = match run {
+            Ok(run) => (StepStatus::Completed, None, run.clone()),
+            Err(error) => (
+                StepStatus::Failed,
+                Some(format!("{error:#}")),
+                StepRun::default(),
+            ),
+        };
+        Self {
+            id: id.to_string(),
+            item,
+            status,
+            error,
+            duration_secs: duration.as_secs_f64(),
+            run,
+        }
+    }
+
+    pub fn skipped(id: impl ToString) -> Self {
+        Self {
+            id: id.to_string(),
+            item: None,
+            status: StepStatus::Skipped,
+            error: None,
+            duration_secs: 0.0,
+            run: StepRun::default(),
+        }
+    }
+
+    fn name(&self) -> String {
+        match &self.item {
+            Some(item) => format!("{} [{}]", self.id, item_label(item)),
+            None => self.id.clone(),
+        }
+    }
+}
+
+/// Report of a pipeline run, written as Markdown for people and as JSON for
+/// tools, eg: to attach both to the artifacts of a CI job
+#[derive(Debug, Clone, Serialize)]
+pub struct RunReport {
+    pub pipeline: PathBuf,
+    pub started_at: DateTime<Local>,
+    pub steps: Vec<StepReport>,
+    /// Owners of the files of the workspace, listed next to the files changed
+    #[serde(skip)]
+    code_owners: Option<CodeOwners>,
+    /// Formats of the Markdown report, the JSON report keeps the raw values
+    #[serde(skip)]
+    locale: Locale,
+}
+
+impl RunReport {
+    pub fn new(pipeline: impl Into<PathBuf>) -> Self {
+        Self {
+            pipeline: pipeline.into(),
+            started_at: Local::now(),
+            steps: Vec::new(),
+            code_owners: None,
+            locale: Locale::default(),
+        }
+    }
+
+    pub fn code_owners(mut self, code_owners: Option<CodeOwners>) -> Self {
+        self.code_owners = code_owners;
+        self
+    }
+
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    pub fn usage(&self) -> Usage {
+        let mut usage = Usage::default();
+        for step in &self.steps {
+            usage.prompt_tokens += step.run.usage.prompt_tokens;
+            usage.completion_tokens += step.run.usage.completion_tokens;
+            usage.total_tokens += step.run.usage.total_tokens;
+        }
+        usage
+    }
+
+    pub fn files_changed(&self) -> Vec<&str> {
+        let mut files = Vec::new();
+        for file in self.steps.iter().flat_map(|step| &step.run.files_changed) {
+            if !files.contains(&file.as_str()) {
+                files.push(file.as_str());
+            }
+        }
+        files
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+        let usage = self.usage();
+        let failed = self
+            .steps
+            .iter()
+            .filter(|step| step.status == StepStatus::Failed)
+            .count();
+
+        let _ = writeln!(markdown, "# Pipeline report: {}\n", self.pipeline.display());
+        let _ = writeln!(
+            markdown,
+            "Started at {}, {} steps run, {} failed, {} tokens used ({} prompt, {} completion).\n",
+            self.locale.datetime(&self.started_at),
+            self.steps.len(),
+            failed,
+            self.locale.number(usage.total_tokens),
+            self.locale.number(usage.prompt_tokens),
+            self.locale.number(usage.completion_tokens)
+        );
+
+        let _ = writeln!(markdown, "## Steps\n");
+        let _ = writeln!(markdown, "| Step | Status | Duration | Tokens |");
+        let _ = writeln!(markdown, "| ---- | ------ | -------- | ------ |");
+        for step in &self.steps {
+            let status = match (&step.status, &step.error) {
+                (StepStatus::Failed, Some(error)) => format!("failed: {error}"),
+                (status, _) => status.as_str().to_string(),
+            };
+            let _ = writeln!(
+                markdown,
+                "| {} | {} | {} | {} |",
+                step.name(),
+                status.replace('|', "\\|").replace('\n', " "),
+                self.locale
+                    .duration(Duration::from_secs_f64(step.duration_secs)),
+                self.locale.number(step.run.usage.total_tokens)
+            );
+        }
+
+        let files = self.files_changed();
+        if !files.is_empty() {
+            let _ = writeln!(markdown, "\n## Files changed\n");
+            for file in files {
+                let owners = self
+                    .code_owners
+                    .as_ref()
+                    .map(|code_owners| code_owners.owners(Path::new(file)))
+                    .unwrap_or_default();
+                if owners.is_empty() {
+                    let _ = writeln!(markdown, "- `{file}`");
+                } else {
+                    let _ = writeln!(markdown, "- `{file}` ({})", owners.join(", "));
+                }
+            }
+        }
+
+        let commands = self
+            .steps
+            .iter()
+            .flat_map(|step| step.run.commands.iter().map(move |command| (step, command)))
+            .collect::<Vec<_>>();
+        if !commands.is_empty() {
+            let _ = writeln!(markdown, "\n## Commands\n");
+            for (step, command) in commands {
+                let _ = writeln!(
+                    markdown,
+                    "- {} `{}` ({})",
+                    if command.success { "PASS" } else { "FAIL" },
+                    command.command,
+                    step.name()
+                );
+            }
+        }
+
+        let diffs = self
+            .steps
+            .iter()
+            .flat_map(|step| &step.run.diffs)
+            .collect::<Vec<_>>();
+        if !diffs.is_empty() {
+            let _ = writeln!(markdown, "\n## Diffs\n");
+            for diff in diffs {
+                let diff = console::strip_ansi_codes(diff);
+                let _ = writeln!(markdown, "```diff\n{}\n```", diff.trim_end());
+            }
+        }
+
+        markdown
+    }
+
+    /// Writes `report.md` and `report.json` to the directory, returning their
+    /// paths
+    pub fn write(&self, dir: &Path) -> Result<(PathBuf, PathBuf)> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create report directory: {}", dir.display()))?;
+
+        let markdown = dir.join("report.md");
+        std::fs::write(&markdown, self.to_markdown())
+            .with_context(|| format!("Failed to write report: {}", markdown.display()))?;
+
+        let json = dir.join("report.json");
+        std::fs::write(&json, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write report: {}", json.display()))?;
+
+        Ok((markdown, json))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use forge_api::{ToolCallFull, ToolName, ToolResult};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    fn fixture() -> StepRun {
+        let mut run = StepRun::default();
+        for response in [
+            ChatResponse::ToolCallStart(
+                ToolCallFull::new(ToolName::new("forge_tool_fs_patch"))
+                    .arguments(json!({"path": "src/lib.rs"})),
+            ),
+            ChatResponse::ToolCallStart(
+                ToolCallFull::new(ToolName::new(SHELL_TOOL))
+                    .call_id(ToolCallId::new("1"))
+                    .arguments(json!({"command": "cargo test"})),
+            ),
+            ChatResponse::ToolCallEnd(
+                ToolResult::new(ToolName::new(SHELL_TOOL))
+                    .call_id(ToolCallId::new("1"))
+                    .failure(anyhow::anyhow!("1 test failed")),
+            ),
+            ChatResponse::Usage(Usage { total_tokens: 120, ..Default::default() }),
+            ChatResponse::Text {
+                text: "Done".to_string(),
+                is_complete: true,
+                is_md: true,
+                is_summary: false,
+            },
+        ] {
+            run.record(response);
+        }
+        run.finish()
+    }
+
+    #[test]
+    fn test_step_run() {
+        let actual = fixture();
+
+        assert_eq!(actual.output, json!("Done"));
+        assert_eq!(actual.usage.total_tokens, 120);
+        assert_eq!(actual.files_changed, vec!["src/lib.rs".to_string()]);
+        assert_eq!(
+            actual.commands,
+            vec![CommandRun { command: "cargo test".to_string(), success: false }]
+        );
+    }
+
+    #[test]
+    fn test_run_report() {
+        let mut fixture_report = RunReport::new("pipeline.yaml")
+            .code_owners(Some(CodeOwners::parse("/repo", "*.rs @org/core")));
+        fixture_report.steps = vec![
+            StepReport {
+                id: "document".to_string(),
+                item: Some(json!("src/lib.rs")),
+                status: StepStatus::Completed,
+                error: None,
+                duration_secs: 2.0,
+                run: fixture(),
+            },
+            StepReport::skipped("release"),
+        ];
+
+        let markdown = fixture_report.to_markdown();
+        let json = serde_json::to_value(&fixture_report).unwrap();
+
+        assert!(markdown.contains("| document [src/lib.rs] | completed | 2.0s | 120 |"));
+        assert!(markdown.contains("- `src/lib.rs` (@org/core)"));
+        assert!(markdown.contains("- FAIL `cargo test` (document [src/lib.rs])"));
+        assert_eq!(json["steps"][0]["files_changed"], json!(["src/lib.rs"]));
+        assert_eq!(json["steps"][1]["status"], json!("skipped"));
+    }
+}
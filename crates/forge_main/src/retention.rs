@@ -0,0 +1,233 @@
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use forge_api::{Environment, RetentionPolicy};
+use forge_snaps::Snapshot;
+
+use crate::idle::sessions_path;
+
+/// Prefixes of the files that long tool outputs spill to in the temp directory
+const SPILL_PREFIXES: &[&str] = &["forge_shell_", "forge_fetch_"];
+
+/// Stores of the files that forge keeps across sessions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Store {
+    Sessions,
+    Snapshots,
+    Logs,
+    Spill,
+}
+
+impl Display for Store {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Store::Sessions => write!(f, "sessions"),
+            Store::Snapshots => write!(f, "snapshots"),
+            Store::Logs => write!(f, "logs"),
+            Store::Spill => write!(f, "spill"),
+        }
+    }
+}
+
+/// A file in one of the stores
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredFile {
+    pub store: Store,
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+/// Files of every store
+pub fn stored_files(env: &Environment) -> Vec<StoredFile> {
+    let mut files = list(Store::Sessions, &sessions_path(&env.base_path), |_| true);
+    files.extend(list(Store::Logs, &env.log_path(), |_| true));
+    files.extend(list(Store::Spill, &std::env::temp_dir(), |path| {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| SPILL_PREFIXES.iter().any(|p| name.starts_with(p)))
+    }));
+    // Snapshots are kept in a directory per file
+    for dir in subdirectories(&env.snapshot_path()) {
+        files.extend(list(Store::Snapshots, &dir, is_snapshot));
+    }
+    files
+}
+
+/// Whether the file is a snapshot, ie: of the content of a file or of its
+/// creation, unlike the source file of its directory
+fn is_snapshot(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| {
+            Snapshot::from_filename(name, String::new()).is_some()
+                || Snapshot::migrate_filename(name).is_some()
+        })
+}
+
+fn subdirectories(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect()
+}
+
+fn list(store: Store, dir: &Path, filter: impl Fn(&Path) -> bool) -> Vec<StoredFile> {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| filter(&entry.path()))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some(StoredFile {
+                store,
+                path: entry.path(),
+                size: metadata.len(),
+                modified: metadata.modified().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Files that the policy prunes: the ones older than the maximum age, then the
+/// oldest of the others until their total size fits the limit
+pub fn expired(
+    mut files: Vec<StoredFile>,
+    policy: &RetentionPolicy,
+    now: SystemTime,
+) -> Vec<StoredFile> {
+    files.sort_by_key(|file| file.modified);
+
+    let (mut expired, mut kept): (Vec<_>, Vec<_>) = files.into_iter().partition(|file| {
+        policy.max_age().is_some_and(|max_age| {
+            now.duration_since(file.modified)
+                .is_ok_and(|age| age > max_age)
+        })
+    });
+
+    if let Some(max_total_bytes) = policy.max_total_bytes() {
+        let mut total = kept.iter().map(|file| file.size).sum::<u64>();
+        let oldest = kept
+            .iter()
+            .take_while(|file| {
+                let over = total > max_total_bytes;
+                total = total.saturating_sub(file.size);
+                over
+            })
+            .count();
+        expired.extend(kept.drain(..oldest));
+    }
+    expired
+}
+
+/// Deletes the files that the policy prunes, or only lists them on a dry run
+pub fn prune(
+    env: &Environment,
+    policy: &RetentionPolicy,
+    dry_run: bool,
+) -> Result<Vec<StoredFile>> {
+    let files = expired(stored_files(env), policy, SystemTime::now());
+    if dry_run {
+        return Ok(files);
+    }
+
+    let mut snapshot_dirs = BTreeSet::new();
+    for file in &files {
+        match std::fs::remove_file(&file.path) {
+            Ok(()) => {}
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("Failed to delete {}", file.path.display()))
+            }
+        }
+        if file.store == Store::Snapshots {
+            snapshot_dirs.extend(file.path.parent().map(Path::to_path_buf));
+        }
+    }
+
+    // A directory without snapshots only holds the path of its file
+    for dir in snapshot_dirs {
+        let empty = list(Store::Snapshots, &dir, is_snapshot).is_empty();
+        if empty {
+            std::fs::remove_dir_all(&dir)
+                .with_context(|| format!("Failed to delete {}", dir.display()))?;
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn file(name: &str, size: u64, days: u64, now: SystemTime) -> StoredFile {
+        StoredFile {
+            store: Store::Sessions,
+            path: PathBuf::from(name),
+            size,
+            modified: now - Duration::from_secs(days * 86400),
+        }
+    }
+
+    #[test]
+    fn test_expired() {
+        let now = SystemTime::now();
+        let fixture = vec![
+            file("yesterday", 400, 1, now),
+            file("last_month", 100, 40, now),
+            file("last_week", 300, 10, now),
+            file("this_week", 200, 5, now),
+        ];
+        let policy = RetentionPolicy { max_age_days: Some(30), max_total_bytes: Some(600) };
+
+        let actual = expired(fixture, &policy, now)
+            .into_iter()
+            .map(|file| file.path.display().to_string())
+            .collect::<Vec<_>>();
+
+        let expected = vec!["last_month".to_string(), "last_week".to_string()];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_is_snapshot() {
+        let id = "0b8a4c1e-8c5a-4d8e-9f2a-3c1d2e4f5a6b";
+        let fixture = [
+            format!("2025-01-01_10-00-00-000000000_{id}.snap"),
+            format!("2025-01-01_10-00-00-000000000_{id}.new"),
+            "2025-01-01_10-00-00-000000000.snap".to_string(),
+            "source".to_string(),
+        ];
+
+        let actual = fixture
+            .iter()
+            .map(|name| is_snapshot(&Path::new("/snapshots/hash").join(name)))
+            .collect::<Vec<_>>();
+
+        let expected = vec![true, true, true, false];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_expired_without_limits() {
+        let now = SystemTime::now();
+        let fixture = vec![file("old", 100, 400, now)];
+        let policy = RetentionPolicy { max_age_days: Some(0), max_total_bytes: Some(0) };
+
+        let actual = expired(fixture, &policy, now);
+
+        assert_eq!(actual, vec![]);
+    }
+}
@@ -0,0 +1,208 @@
+use std::fmt::Write;
+
+use console::style;
+use forge_api::{AgentId, Context, ContextMessage, Conversation, Role};
+use forge_display::{palette, TitleFormat};
+
+/// Number of characters shown on each side of a match
+const EXCERPT_RADIUS: usize = 40;
+
+/// Maximum number of matches printed for a single search
+const MAX_MATCHES: usize = 50;
+
+/// A line of the conversation that contains the searched text
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub agent: AgentId,
+    /// 1-based position of the message in the agent's context
+    pub message: usize,
+    /// Role of the author, or the name of the tool that produced the message
+    pub source: String,
+    /// 1-based line number within the message
+    pub line: usize,
+    pub before: String,
+    pub text: String,
+    pub after: String,
+}
+
+/// Finds every line of the conversation's messages and tool outputs that
+/// contains the query, ignoring case. System prompts aren't searched.
+pub fn search(conversation: &Conversation, query: &str) -> Vec<Match> {
+    let mut contexts = conversation
+        .state
+        .iter()
+        .filter_map(|(agent, state)| state.context.as_ref().map(|context| (agent, context)))
+        .collect::<Vec<_>>();
+    contexts.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+
+    contexts
+        .into_iter()
+        .flat_map(|(agent, context)| search_context(agent, context, query))
+        .collect()
+}
+
+fn search_context(agent: &AgentId, context: &Context, query: &str) -> Vec<Match> {
+    let needle = query.to_lowercase();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches = Vec::new();
+    for (index, message) in context.messages.iter().enumerate() {
+        let (source, content) = match message {
+            ContextMessage::ContentMessage(message) if message.role != Role::System => {
+                (message.role.to_string().to_lowercase(), &message.content)
+            }
+            ContextMessage::ToolMessage(result) => {
+                (result.name.as_str().to_string(), &result.content)
+            }
+            _ => continue,
+        };
+
+        for (line_index, line) in content.lines().enumerate() {
+            if let Some((before, text, after)) = excerpt(line, &needle) {
+                matches.push(Match {
+                    agent: agent.clone(),
+                    message: index + 1,
+                    source: source.clone(),
+                    line: line_index + 1,
+                    before,
+                    text,
+                    after,
+                });
+            }
+        }
+    }
+    matches
+}
+
+/// Splits the line around the first occurrence of the lowercase needle,
+/// keeping at most `EXCERPT_RADIUS` characters on each side
+fn excerpt(line: &str, needle: &str) -> Option<(String, String, String)> {
+    let (start, _) = line
+        .char_indices()
+        .find(|(i, _)| line[*i..].to_lowercase().starts_with(needle))?;
+    let end = line[start..]
+        .char_indices()
+        .map(|(i, c)| start + i + c.len_utf8())
+        .find(|end| line[start..*end].to_lowercase().len() >= needle.len())?;
+
+    let before = line[..start].chars().collect::<Vec<_>>();
+    let after = line[end..].chars().collect::<Vec<_>>();
+
+    let mut head = before[before.len().saturating_sub(EXCERPT_RADIUS)..]
+        .iter()
+        .collect::<String>();
+    if before.len() > EXCERPT_RADIUS {
+        head.insert_str(0, "...");
+    }
+    let mut tail = after.iter().take(EXCERPT_RADIUS).collect::<String>();
+    if after.len() > EXCERPT_RADIUS {
+        tail.push_str("...");
+    }
+
+    Some((
+        head.trim_start().to_string(),
+        line[start..end].to_string(),
+        tail,
+    ))
+}
+
+/// Formats the matches with the matched text highlighted
+pub fn format(query: &str, matches: &[Match]) -> Result<String, std::fmt::Error> {
+    if matches.is_empty() {
+        return Ok(TitleFormat::info("No matches").sub_title(query).to_string());
+    }
+
+    let mut output = String::new();
+    writeln!(
+        output,
+        "{}",
+        TitleFormat::info(format!("{} matches", matches.len())).sub_title(query)
+    )?;
+    for found in matches.iter().take(MAX_MATCHES) {
+        writeln!(
+            output,
+            "  {} {}{}{}",
+            style(format!(
+                "[{} #{}] {}:{}",
+                found.agent, found.message, found.source, found.line
+            ))
+            .dim(),
+            found.before,
+            style(&found.text).fg(palette().accent).bold(),
+            found.after
+        )?;
+    }
+    if matches.len() > MAX_MATCHES {
+        writeln!(
+            output,
+            "  {}",
+            style(format!("... and {} more", matches.len() - MAX_MATCHES)).dim()
+        )?;
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use forge_api::{ToolName, ToolResult};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn fixture() -> Context {
+        Context::default()
+            .add_message(ContextMessage::system("You handle errors"))
+            .add_message(ContextMessage::user("Why does the build fail?"))
+            .add_message(ContextMessage::tool_result(
+                ToolResult::new(ToolName::new("tool_forge_process_shell"))
+                    .success("Compiling forge\nerror[E0308]: mismatched types"),
+            ))
+            .add_message(ContextMessage::assistant(
+                "The Error is a type mismatch",
+                None,
+            ))
+    }
+
+    #[test]
+    fn test_search_context() {
+        let agent = AgentId::new("software-engineer");
+
+        let actual = search_context(&agent, &fixture(), "error")
+            .into_iter()
+            .map(|found| (found.message, found.source, found.line, found.text))
+            .collect::<Vec<_>>();
+
+        let expected = vec![
+            (
+                3,
+                "tool_forge_process_shell".to_string(),
+                2,
+                "error".to_string(),
+            ),
+            (4, "assistant".to_string(), 1, "Error".to_string()),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_excerpt_truncates_long_lines() {
+        let fixture = format!("{}needle{}", "a".repeat(100), "b".repeat(100));
+
+        let actual = excerpt(&fixture, "needle").unwrap();
+
+        let expected = (
+            format!("...{}", "a".repeat(EXCERPT_RADIUS)),
+            "needle".to_string(),
+            format!("{}...", "b".repeat(EXCERPT_RADIUS)),
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_excerpt_no_match() {
+        let actual = excerpt("nothing here", "needle");
+        assert_eq!(actual, None);
+    }
+}
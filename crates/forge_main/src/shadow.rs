@@ -0,0 +1,477 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+use anyhow::{Context, Result};
+use forge_walker::FORGE_IGNORE;
+use ignore::WalkBuilder;
+use similar::{capture_diff_slices, Algorithm, DiffTag};
+use tempfile::TempDir;
+use tokio::process::Command;
+
+/// Directory of the copy that the session works in, in the temporary directory
+const WORKSPACE_DIR: &str = "workspace";
+
+/// Directory of the base that changes are merged from, in the temporary
+/// directory
+const BASE_DIR: &str = "base";
+
+/// Change of a file of the shadow workspace since it was copied or last
+/// promoted
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Change {
+    Added(PathBuf),
+    Modified(PathBuf),
+    Removed(PathBuf),
+}
+
+impl Change {
+    pub fn path(&self) -> &Path {
+        match self {
+            Change::Added(path) | Change::Modified(path) | Change::Removed(path) => path,
+        }
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Added(path) => write!(f, "added {}", path.display()),
+            Change::Modified(path) => write!(f, "modified {}", path.display()),
+            Change::Removed(path) => write!(f, "removed {}", path.display()),
+        }
+    }
+}
+
+/// Part of a file that both the workspace and the shadow workspace changed
+/// since the base, which is left for the user to resolve
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub path: PathBuf,
+    /// First line of the part in the base, starting at 1, or none when the
+    /// whole file conflicts, eg: it was removed on one side and changed on the
+    /// other
+    pub line: Option<usize>,
+    /// The part as it is in the workspace
+    pub workspace: String,
+    /// The part as it is in the shadow workspace
+    pub shadow: String,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{line}", self.path.display()),
+            None => write!(f, "{}", self.path.display()),
+        }
+    }
+}
+
+/// Outcome of a promotion: the changes that were applied to the workspace, and
+/// the conflicts of the ones that weren't
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Promotion {
+    pub promoted: Vec<Change>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Copy of the workspace that a session edits, builds and tests, so that its
+/// changes only reach the real workspace once they pass validation. Files that
+/// are ignored, eg: build outputs, aren't copied.
+pub struct ShadowWorkspace {
+    root: PathBuf,
+    dir: TempDir,
+    workspace: PathBuf,
+    /// Hashes of the files of the base, ie: the workspace as it was copied or
+    /// last promoted, by their path relative to the root
+    files: BTreeMap<PathBuf, u64>,
+}
+
+impl ShadowWorkspace {
+    /// Copies the files of the workspace at `root` to a temporary directory,
+    /// which is deleted when the shadow workspace is dropped. A second copy is
+    /// kept as the base that the changes of both sides are merged from.
+    pub fn create(root: &Path) -> Result<Self> {
+        let dir = tempfile::Builder::new().prefix("forge_shadow_").tempdir()?;
+        let workspace = dir.path().join(WORKSPACE_DIR);
+        let files = hashes(root)?;
+        for path in files.keys() {
+            let content = std::fs::read(root.join(path))
+                .with_context(|| format!("Failed to copy {}", path.display()))?;
+            write(&workspace.join(path), &content)?;
+            write(&dir.path().join(BASE_DIR).join(path), &content)?;
+        }
+        Ok(Self { root: root.to_path_buf(), dir, workspace, files })
+    }
+
+    /// Real workspace that the changes are promoted to
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Directory of the copy that the session works in
+    pub fn path(&self) -> &Path {
+        &self.workspace
+    }
+
+    fn base(&self) -> PathBuf {
+        self.dir.path().join(BASE_DIR)
+    }
+
+    /// Files that were added, modified or removed in the shadow workspace
+    pub fn changes(&self) -> Result<Vec<Change>> {
+        let current = hashes(self.path())?;
+        let mut changes = current
+            .iter()
+            .filter_map(|(path, hash)| match self.files.get(path) {
+                None => Some(Change::Added(path.clone())),
+                Some(original) if original != hash => Some(Change::Modified(path.clone())),
+                Some(_) => None,
+            })
+            .chain(
+                self.files
+                    .keys()
+                    .filter(|path| !current.contains_key(*path))
+                    .map(|path| Change::Removed(path.clone())),
+            )
+            .collect::<Vec<_>>();
+        changes.sort();
+        Ok(changes)
+    }
+
+    /// Runs the validation command, eg: the build or the tests, in the shadow
+    /// workspace
+    pub async fn validate(&self, command: &str) -> Result<Output> {
+        let (shell, flag) = if cfg!(windows) {
+            ("cmd", "/C")
+        } else {
+            ("sh", "-c")
+        };
+        Command::new(shell)
+            .args([flag, command])
+            .current_dir(self.path())
+            .output()
+            .await
+            .with_context(|| format!("Failed to run {command}"))
+    }
+
+    /// Applies the changes to the workspace. Each file is merged with the
+    /// edits made to the workspace since the base, and a file whose edits
+    /// overlap is left untouched and reported as conflicting.
+    pub fn promote(&mut self, changes: &[Change]) -> Result<Promotion> {
+        let mut promotion = Promotion::default();
+        for change in changes {
+            let path = change.path();
+            let base = read(&self.base().join(path))?;
+            let workspace = read(&self.root.join(path))?;
+            let shadow = read(&self.path().join(path))?;
+            match merge_file(path, base, workspace, shadow) {
+                Ok(merged) => {
+                    // Both copies catch up with the workspace, so that its edits
+                    // aren't reported as changes of the shadow workspace
+                    for dir in [self.root.clone(), self.path().to_path_buf(), self.base()] {
+                        let target = dir.join(path);
+                        match &merged {
+                            Some(content) => write(&target, content)?,
+                            None => remove(&target)?,
+                        }
+                    }
+                    promotion.promoted.push(change.clone());
+                }
+                Err(conflicts) => promotion.conflicts.extend(conflicts),
+            }
+        }
+        self.files = hashes(&self.base())?;
+        Ok(promotion)
+    }
+}
+
+/// Merges the changes of the shadow workspace to a file with the ones of the
+/// workspace, where `None` is a missing file
+fn merge_file(
+    path: &Path,
+    base: Option<Vec<u8>>,
+    workspace: Option<Vec<u8>>,
+    shadow: Option<Vec<u8>>,
+) -> Result<Option<Vec<u8>>, Vec<Conflict>> {
+    if workspace == base || workspace == shadow {
+        return Ok(shadow);
+    }
+    let text = |content: &Option<Vec<u8>>| {
+        content
+            .as_ref()
+            .map(|content| String::from_utf8(content.clone()).ok())
+    };
+    match (text(&base), text(&workspace), text(&shadow)) {
+        (Some(Some(base)), Some(Some(workspace)), Some(Some(shadow))) => {
+            merge3(path, &base, &workspace, &shadow).map(|merged| Some(merged.into_bytes()))
+        }
+        _ => {
+            let lossy = |content: Option<Vec<u8>>| {
+                content
+                    .map(|content| String::from_utf8_lossy(&content).into_owned())
+                    .unwrap_or_default()
+            };
+            Err(vec![Conflict {
+                path: path.to_path_buf(),
+                line: None,
+                workspace: lossy(workspace),
+                shadow: lossy(shadow),
+            }])
+        }
+    }
+}
+
+/// Lines of the base that a side replaced, by the range of its own lines
+struct Hunk {
+    base: Range<usize>,
+    lines: Range<usize>,
+}
+
+fn hunks(base: &[&str], lines: &[&str]) -> Vec<Hunk> {
+    capture_diff_slices(Algorithm::Myers, base, lines)
+        .into_iter()
+        .filter(|op| op.tag() != DiffTag::Equal)
+        .map(|op| Hunk { base: op.old_range(), lines: op.new_range() })
+        .collect()
+}
+
+/// Lines of the base from `start` to `end` with the hunks of a side applied
+fn apply(base: &[&str], start: usize, end: usize, hunks: &[Hunk], lines: &[&str]) -> String {
+    let mut applied = String::new();
+    let mut position = start;
+    for hunk in hunks {
+        applied.push_str(&base[position..hunk.base.start].concat());
+        applied.push_str(&lines[hunk.lines.clone()].concat());
+        position = hunk.base.end;
+    }
+    applied.push_str(&base[position..end].concat());
+    applied
+}
+
+/// Three-way merge of the lines of a file. The hunks of both sides that
+/// overlap or touch are grouped, and a group conflicts when both sides changed
+/// it differently.
+fn merge3(path: &Path, base: &str, workspace: &str, shadow: &str) -> Result<String, Vec<Conflict>> {
+    let base = base.split_inclusive('\n').collect::<Vec<_>>();
+    let workspace = workspace.split_inclusive('\n').collect::<Vec<_>>();
+    let shadow = shadow.split_inclusive('\n').collect::<Vec<_>>();
+    let workspace_hunks = hunks(&base, &workspace);
+    let shadow_hunks = hunks(&base, &shadow);
+
+    let mut merged = String::new();
+    let mut conflicts = Vec::new();
+    let (mut i, mut j, mut position) = (0, 0, 0);
+    while i < workspace_hunks.len() || j < shadow_hunks.len() {
+        let start = workspace_hunks
+            .get(i)
+            .into_iter()
+            .chain(shadow_hunks.get(j))
+            .map(|hunk| hunk.base.start)
+            .min()
+            .unwrap_or_default();
+        let (workspace_from, shadow_from) = (i, j);
+        let mut end = start;
+        loop {
+            if let Some(hunk) = workspace_hunks.get(i).filter(|hunk| hunk.base.start <= end) {
+                end = end.max(hunk.base.end);
+                i += 1;
+            } else if let Some(hunk) = shadow_hunks.get(j).filter(|hunk| hunk.base.start <= end) {
+                end = end.max(hunk.base.end);
+                j += 1;
+            } else {
+                break;
+            }
+        }
+
+        merged.push_str(&base[position..start].concat());
+        let ours = apply(
+            &base,
+            start,
+            end,
+            &workspace_hunks[workspace_from..i],
+            &workspace,
+        );
+        let theirs = apply(&base, start, end, &shadow_hunks[shadow_from..j], &shadow);
+        if workspace_from == i || ours == theirs {
+            merged.push_str(&theirs);
+        } else if shadow_from == j {
+            merged.push_str(&ours);
+        } else {
+            conflicts.push(Conflict {
+                path: path.to_path_buf(),
+                line: Some(start + 1),
+                workspace: ours,
+                shadow: theirs,
+            });
+        }
+        position = end;
+    }
+    merged.push_str(&base[position..].concat());
+
+    if conflicts.is_empty() {
+        Ok(merged)
+    } else {
+        Err(conflicts)
+    }
+}
+
+/// Hashes of the files of a directory that aren't ignored, by their path
+/// relative to it. The ignore files are copied along, so the same rules apply
+/// to the workspace and its copies.
+fn hashes(root: &Path) -> Result<BTreeMap<PathBuf, u64>> {
+    let walk = WalkBuilder::new(root)
+        .hidden(false)
+        .require_git(false)
+        .add_custom_ignore_filename(FORGE_IGNORE)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build();
+
+    let mut files = BTreeMap::new();
+    for entry in walk {
+        let entry = entry?;
+        if !entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+        {
+            continue;
+        }
+        let path = entry.path().strip_prefix(root)?.to_path_buf();
+        let content = std::fs::read(entry.path())
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        files.insert(path, hasher.finish());
+    }
+    Ok(files)
+}
+
+fn read(path: &Path) -> Result<Option<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+fn write(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn remove(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(error).with_context(|| format!("Failed to remove {}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_promote() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.path().join("kept.txt"), "kept").unwrap();
+        std::fs::write(root.path().join("edited.txt"), "before").unwrap();
+        std::fs::write(root.path().join("removed.txt"), "removed").unwrap();
+        let mut fixture = ShadowWorkspace::create(root.path()).unwrap();
+        std::fs::write(fixture.path().join("edited.txt"), "after").unwrap();
+        std::fs::write(fixture.path().join("added.txt"), "added").unwrap();
+        std::fs::remove_file(fixture.path().join("removed.txt")).unwrap();
+        std::fs::create_dir(fixture.path().join("target")).unwrap();
+        std::fs::write(fixture.path().join("target/build.log"), "ignored").unwrap();
+
+        let actual = fixture.changes().unwrap();
+        let expected = vec![
+            Change::Added(PathBuf::from("added.txt")),
+            Change::Modified(PathBuf::from("edited.txt")),
+            Change::Removed(PathBuf::from("removed.txt")),
+        ];
+        assert_eq!(actual, expected);
+        assert_eq!(
+            std::fs::read_to_string(root.path().join("edited.txt")).unwrap(),
+            "before"
+        );
+
+        let promotion = fixture.promote(&actual).unwrap();
+
+        assert_eq!(
+            promotion,
+            Promotion { promoted: expected, conflicts: vec![] }
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.path().join("edited.txt")).unwrap(),
+            "after"
+        );
+        assert!(root.path().join("added.txt").exists());
+        assert!(!root.path().join("removed.txt").exists());
+        assert_eq!(fixture.changes().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_promote_merges_the_edits_of_the_workspace() {
+        let root = tempfile::tempdir().unwrap();
+        let base = "fn a() {}\n\nfn b() {}\n\nfn c() {}\n";
+        std::fs::write(root.path().join("merged.rs"), base).unwrap();
+        std::fs::write(root.path().join("conflicting.rs"), base).unwrap();
+        let mut fixture = ShadowWorkspace::create(root.path()).unwrap();
+        std::fs::write(
+            fixture.path().join("merged.rs"),
+            "fn a() {}\n\nfn b() {}\n\nfn c() { shadow() }\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.path().join("merged.rs"),
+            "fn a() { workspace() }\n\nfn b() {}\n\nfn c() {}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            fixture.path().join("conflicting.rs"),
+            "fn a() {}\n\nfn b() { shadow() }\n\nfn c() {}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.path().join("conflicting.rs"),
+            "fn a() {}\n\nfn b() { workspace() }\n\nfn c() {}\n",
+        )
+        .unwrap();
+
+        let changes = fixture.changes().unwrap();
+        let actual = fixture.promote(&changes).unwrap();
+
+        let expected = Promotion {
+            promoted: vec![Change::Modified(PathBuf::from("merged.rs"))],
+            conflicts: vec![Conflict {
+                path: PathBuf::from("conflicting.rs"),
+                line: Some(3),
+                workspace: "fn b() { workspace() }\n".to_string(),
+                shadow: "fn b() { shadow() }\n".to_string(),
+            }],
+        };
+        assert_eq!(actual, expected);
+        assert_eq!(
+            std::fs::read_to_string(root.path().join("merged.rs")).unwrap(),
+            "fn a() { workspace() }\n\nfn b() {}\n\nfn c() { shadow() }\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.path().join("conflicting.rs")).unwrap(),
+            "fn a() {}\n\nfn b() { workspace() }\n\nfn c() {}\n"
+        );
+        assert_eq!(
+            fixture.changes().unwrap(),
+            vec![Change::Modified(PathBuf::from("conflicting.rs"))]
+        );
+    }
+}
@@ -1,7 +1,10 @@
 use derive_setters::Setters;
-use forge_api::{ConversationId, Model, ModelId, Provider, Usage};
+use forge_api::{
+    ConversationId, Model, ModelId, PatchConflict, Provider, ToolOverride, TurnId, Usage,
+};
 use serde::Deserialize;
 
+use crate::marks::{Marks, Turns};
 use crate::prompt::ForgePrompt;
 
 // TODO: convert to a new type
@@ -34,6 +37,15 @@ pub struct UIState {
     pub model: Option<ModelId>,
     pub cached_models: Option<Vec<Model>>,
     pub provider: Option<Provider>,
+    pub diff_pager_threshold: Option<usize>,
+    pub plan_budget: Option<f64>,
+    pub tool_overrides: Vec<ToolOverride>,
+    pub marks: Marks,
+    pub turns: Turns,
+    /// Turn in progress, or the last one
+    pub turn_id: Option<TurnId>,
+    /// Patches that didn't match during the task, resolved once it's done
+    pub conflicts: Vec<PatchConflict>,
 }
 
 impl UIState {
@@ -46,6 +58,13 @@ impl UIState {
             model: Default::default(),
             cached_models: Default::default(),
             provider: Default::default(),
+            diff_pager_threshold: Default::default(),
+            plan_budget: Default::default(),
+            tool_overrides: Default::default(),
+            marks: Default::default(),
+            turns: Default::default(),
+            turn_id: Default::default(),
+            conflicts: Default::default(),
         }
     }
 }
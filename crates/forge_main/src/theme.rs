@@ -0,0 +1,118 @@
+use anyhow::{anyhow, bail, Result};
+use console::Color;
+use forge_api::Theme;
+use forge_display::Palette;
+
+/// Colors that can be referred to by name in custom themes
+const COLORS: [(&str, Color); 8] = [
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("white", Color::White),
+];
+
+/// Resolves the palette of the configured theme
+pub fn palette(theme: &Theme) -> Result<Palette> {
+    Ok(match theme {
+        Theme::Auto => Palette::detect(),
+        Theme::Dark => Palette::DARK,
+        Theme::Light => Palette::LIGHT,
+        Theme::Solarized => Palette::SOLARIZED,
+        Theme::Custom(colors) => {
+            let mut palette = Palette::detect();
+            for (role, value) in colors {
+                let color = parse_color(value)?;
+                match role.as_str() {
+                    "text" => palette.text = color,
+                    "accent" => palette.accent = color,
+                    "info" => palette.info = color,
+                    "success" => palette.success = color,
+                    "error" => palette.error = color,
+                    "added" => palette.added = color,
+                    "removed" => palette.removed = color,
+                    "gutter" => palette.gutter = color,
+                    "muted" => palette.muted = color,
+                    _ => bail!(
+                        "Unknown theme color '{role}', expected one of: text, accent, info, success, error, added, removed, gutter, muted"
+                    ),
+                }
+            }
+            palette
+        }
+    })
+}
+
+/// Parses a color name (eg: `red`, `bright_red`) or an ANSI 256 color number
+fn parse_color(value: &str) -> Result<Color> {
+    let value = value.trim().to_lowercase();
+    if let Ok(number) = value.parse::<u8>() {
+        return Ok(Color::Color256(number));
+    }
+
+    let (name, bright) = match value.strip_prefix("bright_") {
+        Some(name) => (name, true),
+        None => (value.as_str(), false),
+    };
+    let index = COLORS
+        .iter()
+        .position(|(color, _)| *color == name)
+        .ok_or_else(|| anyhow!("Invalid color '{value}'"))?;
+
+    Ok(if bright {
+        Color::Color256(index as u8 + 8)
+    } else {
+        COLORS[index].1
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        let fixture = ["green", "Bright_Red", "208"];
+        let actual = fixture.map(|value| parse_color(value).unwrap());
+        let expected = [Color::Green, Color::Color256(9), Color::Color256(208)];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_parse_color_invalid() {
+        let actual = parse_color("mauve");
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn test_palette_custom() {
+        let fixture = Theme::Custom(BTreeMap::from([
+            ("added".to_string(), "green".to_string()),
+            ("removed".to_string(), "red".to_string()),
+        ]));
+
+        let actual = palette(&fixture).unwrap();
+
+        assert_eq!(actual.added, Color::Green);
+        assert_eq!(actual.removed, Color::Red);
+    }
+
+    #[test]
+    fn test_palette_custom_unknown_role() {
+        let fixture = Theme::Custom(BTreeMap::from([(
+            "background".to_string(),
+            "black".to_string(),
+        )]));
+
+        let actual = palette(&fixture);
+
+        assert!(actual.is_err());
+    }
+}
@@ -1,5 +1,7 @@
 use colored::Colorize;
-use forge_api::ToolDefinition;
+use forge_api::{Agent, Progress, ToolDefinition};
+use forge_display::ProgressFormat;
+use serde::Serialize;
 use serde_json::to_string_pretty;
 
 /// Formats the list of tools for display in the shell UI, following these
@@ -26,3 +28,176 @@ pub fn format_tools(tools: &[ToolDefinition]) -> String {
 
     out
 }
+
+/// Documentation of a tool, with the agents of the workflow that can use it
+#[derive(Serialize)]
+struct ToolDoc<'a> {
+    #[serde(flatten)]
+    definition: &'a ToolDefinition,
+    agents: Vec<&'a str>,
+}
+
+fn tool_docs<'a>(tools: &'a [ToolDefinition], agents: &'a [Agent]) -> Vec<ToolDoc<'a>> {
+    tools
+        .iter()
+        .map(|definition| ToolDoc {
+            definition,
+            agents: agents
+                .iter()
+                .filter(|agent| {
+                    agent
+                        .tools
+                        .as_ref()
+                        .is_some_and(|tools| tools.contains(&definition.name))
+                })
+                .map(|agent| agent.id.as_str())
+                .collect(),
+        })
+        .collect()
+}
+
+/// Renders the documentation of the tools as Markdown: a summary table of the
+/// tools and the agents that can use them, then the description and the input
+/// schema of every tool
+pub fn markdown_tools(tools: &[ToolDefinition], agents: &[Agent]) -> String {
+    let docs = tool_docs(tools, agents);
+    let agents = |doc: &ToolDoc| {
+        if doc.agents.is_empty() {
+            "none".to_string()
+        } else {
+            doc.agents.join(", ")
+        }
+    };
+
+    let mut out = String::from("# Tools\n\n| Tool | Agents |\n| ---- | ------ |\n");
+    for doc in &docs {
+        out.push_str(&format!(
+            "| `{}` | {} |\n",
+            doc.definition.name.as_str(),
+            agents(doc)
+        ));
+    }
+
+    for doc in &docs {
+        let schema =
+            to_string_pretty(&doc.definition.input_schema).unwrap_or_else(|_| "{}".to_string());
+        out.push_str(&format!(
+            "\n## {}\n\nAgents: {}\n\n{}\n\n```json\n{schema}\n```\n",
+            doc.definition.name.as_str(),
+            agents(doc),
+            doc.definition.description.trim()
+        ));
+    }
+    out
+}
+
+/// Renders the documentation of the tools as a JSON array of tool definitions,
+/// each with the agents that can use it
+pub fn json_tools(tools: &[ToolDefinition], agents: &[Agent]) -> serde_json::Result<String> {
+    to_string_pretty(&tool_docs(tools, agents))
+}
+
+/// Formats the progress of a running tool as the message of the spinner, eg:
+/// `Searching [####------] 42% (1.5 MB / 3.6 MB)`
+pub fn format_progress(progress: &Progress) -> String {
+    let mut out = progress
+        .message
+        .clone()
+        .unwrap_or_else(|| "Working".to_string());
+
+    if let Some(fraction) = progress.fraction() {
+        out.push_str(&format!(" {}", ProgressFormat::fraction(fraction)));
+    }
+
+    match (progress.bytes_processed, progress.total_bytes) {
+        (Some(processed), Some(total)) => out.push_str(&format!(
+            " ({} / {})",
+            format_bytes(processed),
+            format_bytes(total)
+        )),
+        (Some(processed), None) => out.push_str(&format!(" ({})", format_bytes(processed))),
+        _ => {}
+    }
+
+    out
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use forge_api::ToolName;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn fixture() -> (Vec<ToolDefinition>, Vec<Agent>) {
+        let tools = vec![
+            ToolDefinition::new("forge_tool_fs_read").description("Reads a file"),
+            ToolDefinition::new("forge_tool_shell").description("Runs a command"),
+        ];
+        let agents = vec![
+            Agent::new("software-engineer").tools(vec![
+                ToolName::new("forge_tool_fs_read"),
+                ToolName::new("forge_tool_shell"),
+            ]),
+            Agent::new("researcher").tools(vec![ToolName::new("forge_tool_fs_read")]),
+        ];
+        (tools, agents)
+    }
+
+    #[test]
+    fn test_markdown_tools() {
+        let (tools, agents) = fixture();
+
+        let actual = markdown_tools(&tools, &agents);
+
+        assert!(actual.starts_with(
+            "# Tools\n\n| Tool | Agents |\n| ---- | ------ |\n| `forge_tool_fs_read` | software-engineer, researcher |\n| `forge_tool_shell` | software-engineer |\n"
+        ));
+        assert!(actual.contains(
+            "\n## forge_tool_shell\n\nAgents: software-engineer\n\nRuns a command\n\n```json\n{"
+        ));
+    }
+
+    #[test]
+    fn test_json_tools() {
+        let (tools, agents) = fixture();
+
+        let actual: serde_json::Value =
+            serde_json::from_str(&json_tools(&tools, &agents).unwrap()).unwrap();
+
+        assert_eq!(actual[1]["name"], "forge_tool_shell");
+        assert_eq!(actual[1]["description"], "Runs a command");
+        assert_eq!(
+            actual[1]["agents"],
+            serde_json::json!(["software-engineer"])
+        );
+        assert!(actual[1]["input_schema"].is_object());
+    }
+
+    #[test]
+    fn test_format_progress() {
+        let fixture = Progress::new("Extracting")
+            .bytes_processed(1536u64)
+            .total_bytes(6144u64);
+
+        let actual = strip_ansi_escapes::strip_str(format_progress(&fixture));
+
+        let expected = "Extracting [#####---------------] 25% (1.5 KB / 6.0 KB)";
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,376 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use tokio::process::Command;
+
+/// Maximum number of frames kept from a trace, the innermost ones first
+const MAX_FRAMES: usize = 40;
+
+/// Maximum number of workspace files attached to the session
+const MAX_FILES: usize = 10;
+
+/// Maximum number of error messages kept from a log
+const MAX_MESSAGES: usize = 5;
+
+/// Magic bytes of ELF files, which core dumps are
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+
+/// A frame of a stack trace
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub path: String,
+    pub line: usize,
+    pub function: Option<String>,
+    /// File of the workspace the frame points to, relative to its root
+    pub file: Option<PathBuf>,
+}
+
+impl Frame {
+    fn new(path: impl Into<String>, line: usize, function: Option<String>) -> Self {
+        Self { path: path.into(), line, function, file: None }
+    }
+}
+
+/// Panics, exceptions and stack traces parsed from a log, a crash report or
+/// the backtrace of a core dump
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trace {
+    pub messages: Vec<String>,
+    pub frames: Vec<Frame>,
+}
+
+impl Trace {
+    /// Parses Rust panics and backtraces, Python tracebacks, JavaScript, Go
+    /// and Java stack traces, and gdb backtraces
+    pub fn parse(log: &str) -> Self {
+        let rust_function = Regex::new(r"^\s*\d+:\s+(?:0x[0-9a-f]+\s+-\s+)?(\S+)").unwrap();
+        let patterns = [
+            // Rust backtraces: `at ./src/main.rs:10:5`, and bare JavaScript frames
+            Regex::new(r"^\s*at\s+(?P<path>[^\s()]+?):(?P<line>\d+)(?::\d+)?\s*$").unwrap(),
+            // Rust panics: `panicked at src/main.rs:10:5`
+            Regex::new(r"panicked at (?:'.*',\s*)?(?P<path>[^\s:]+):(?P<line>\d+)(?::\d+)?").unwrap(),
+            // Python: `File "app/x.py", line 12, in handler`
+            Regex::new(r#"^\s*File "(?P<path>[^"]+)", line (?P<line>\d+)(?:, in (?P<function>\S+))?"#)
+                .unwrap(),
+            // JavaScript: `at handler (/app/src/x.js:10:5)`
+            Regex::new(r"^\s*at\s+(?:async\s+)?(?P<function>\S+)\s+\((?:file://)?(?P<path>[^\s()]+?):(?P<line>\d+)(?::\d+)?\)")
+                .unwrap(),
+            // Go: `\t/app/main.go:42 +0x1d`
+            Regex::new(r"^\s+(?P<path>\S+\.go):(?P<line>\d+)(?:\s+\+0x[0-9a-f]+)?\s*$").unwrap(),
+            // Java: `at com.acme.Service.run(Service.java:42)`
+            Regex::new(r"^\s*at\s+(?P<function>(?P<class>[\w$.]+)\.[\w$<>]+)\((?P<file>\w+\.(?:java|kt|scala)):(?P<line>\d+)\)")
+                .unwrap(),
+            // gdb: `#1  0x0000 in handler (arg=1) at src/x.c:12`
+            Regex::new(r"^#\d+\s+(?:0x[0-9a-f]+\s+in\s+)?(?P<function>\S+)\s.*\bat\s+(?P<path>\S+):(?P<line>\d+)")
+                .unwrap(),
+        ];
+        let message = Regex::new(
+            r"(?:panicked at|^panic: |^fatal error: |^Segmentation fault|^(?:[\w$]+\.)*\w*(?:Error|Exception)(?::|$))",
+        )
+        .unwrap();
+
+        let mut trace = Trace::default();
+        let mut function = None;
+        let mut lines = log.lines().peekable();
+        while let Some(line) = lines.next() {
+            if message.is_match(line.trim()) && trace.messages.len() < MAX_MESSAGES {
+                let mut text = line.trim().to_string();
+                // Recent Rust versions print the panic message on the next line
+                if text.starts_with("thread ") && text.ends_with(':') {
+                    if let Some(next) = lines.peek() {
+                        text = format!("{text} {}", next.trim());
+                    }
+                }
+                if !trace.messages.contains(&text) {
+                    trace.messages.push(text);
+                }
+            }
+
+            let frame = patterns.iter().find_map(|pattern| {
+                let captures = pattern.captures(line)?;
+                let line = captures["line"].parse().ok()?;
+                let path = match (captures.name("path"), captures.name("class")) {
+                    (Some(path), _) => path.as_str().to_string(),
+                    (None, Some(class)) => java_path(class.as_str(), &captures["file"]),
+                    (None, None) => return None,
+                };
+                let function = captures
+                    .name("function")
+                    .map(|function| function.as_str().to_string());
+                Some(Frame::new(path, line, function))
+            });
+
+            match frame {
+                Some(mut frame) => {
+                    frame.function = frame.function.or(function.take());
+                    if trace.frames.len() < MAX_FRAMES && !trace.frames.contains(&frame) {
+                        trace.frames.push(frame);
+                    }
+                }
+                None => {
+                    function = rust_function
+                        .captures(line)
+                        .map(|captures| captures[1].to_string());
+                }
+            }
+        }
+        trace
+    }
+
+    /// Maps the frames to the files of the workspace rooted at `cwd`
+    pub fn resolve(mut self, cwd: &Path) -> Self {
+        for frame in &mut self.frames {
+            frame.file = resolve_file(cwd, &frame.path);
+        }
+        self
+    }
+
+    /// Workspace files that the trace goes through, in order of appearance
+    pub fn files(&self) -> Vec<&Path> {
+        let mut files = Vec::new();
+        for file in self.frames.iter().filter_map(|frame| frame.file.as_deref()) {
+            if !files.contains(&file) && files.len() < MAX_FILES {
+                files.push(file);
+            }
+        }
+        files
+    }
+}
+
+/// Reads the trace of a log file, or the backtrace of a core dump using gdb.
+/// The binary that produced the core dump improves the backtrace, but is
+/// optional.
+pub async fn load(path: &Path, binary: Option<&Path>) -> Result<Trace> {
+    let content =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if !content.starts_with(ELF_MAGIC) {
+        return Ok(Trace::parse(&String::from_utf8_lossy(&content)));
+    }
+
+    let mut command = Command::new("gdb");
+    command.args(["--batch", "--quiet", "-ex", "thread apply all bt"]);
+    if let Some(binary) = binary {
+        command.arg(binary);
+    }
+    let output = command
+        .arg("--core")
+        .arg(path)
+        .output()
+        .await
+        .context("Failed to run gdb, which is required to read core dumps")?;
+    Ok(Trace::parse(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Path of the source file of a JVM class, eg: `com/acme/Service.java`
+fn java_path(class: &str, file: &str) -> String {
+    match class.rsplit_once('.') {
+        Some((package, _)) => format!("{}/{file}", package.replace('.', "/")),
+        None => file.to_string(),
+    }
+}
+
+/// Finds the workspace file of a frame. Traces often come from another
+/// machine, eg: a CI runner, so the leading components of the path are
+/// dropped one by one until the rest exists in the workspace.
+fn resolve_file(cwd: &Path, path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    if let Ok(relative) = path.strip_prefix(cwd) {
+        return path.is_file().then(|| relative.to_path_buf());
+    }
+
+    let components = path
+        .components()
+        .filter(|component| matches!(component, std::path::Component::Normal(_)))
+        .collect::<Vec<_>>();
+    let found = (0..components.len()).find_map(|start| {
+        let suffix = components[start..].iter().collect::<PathBuf>();
+        cwd.join(&suffix).is_file().then_some(suffix)
+    });
+    if found.is_some() || path.is_absolute() {
+        return found;
+    }
+
+    // Relative paths without a known root, eg: the package path of a Java class,
+    // are searched for in the whole workspace
+    let pattern = cwd.join("**").join(path);
+    glob::glob(&pattern.to_string_lossy())
+        .ok()?
+        .flatten()
+        .find(|file| file.is_file())
+        .and_then(|file| file.strip_prefix(cwd).ok().map(Path::to_path_buf))
+}
+
+/// A crash that is handed to the agent to find its root cause. The files the
+/// trace goes through are attached so that the agent starts with them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriageTask {
+    pub source: PathBuf,
+    pub trace: Trace,
+}
+
+impl TriageTask {
+    pub fn new(source: impl Into<PathBuf>, trace: Trace) -> Self {
+        Self { source: source.into(), trace }
+    }
+}
+
+impl fmt::Display for TriageTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let TriageTask { source, trace } = self;
+        writeln!(
+            f,
+            "Find the root cause of the crash recorded in `{}`.",
+            source.display()
+        )?;
+        writeln!(f)?;
+        writeln!(f, "<stack_trace>")?;
+        for message in &trace.messages {
+            writeln!(f, "<error>{message}</error>")?;
+        }
+        for frame in &trace.frames {
+            let function = frame
+                .function
+                .as_ref()
+                .map(|function| format!(" function=\"{function}\""))
+                .unwrap_or_default();
+            match &frame.file {
+                Some(file) => writeln!(
+                    f,
+                    "<frame path=\"{}\" line=\"{}\"{function} workspace=\"true\"/>",
+                    file.display(),
+                    frame.line
+                )?,
+                None => writeln!(
+                    f,
+                    "<frame path=\"{}\" line=\"{}\"{function}/>",
+                    frame.path, frame.line
+                )?,
+            }
+        }
+        writeln!(f, "</stack_trace>")?;
+        writeln!(f)?;
+        writeln!(f, "Follow these steps:")?;
+        writeln!(
+            f,
+            "1. Read the error and the frames of the workspace, starting from the innermost one."
+        )?;
+        writeln!(
+            f,
+            "2. Explain the sequence of events that leads to the crash and the assumption that breaks."
+        )?;
+        writeln!(
+            f,
+            "3. Propose a fix for the root cause rather than the symptom, and a test that reproduces the crash."
+        )?;
+
+        let files = trace.files();
+        if !files.is_empty() {
+            writeln!(f)?;
+            write!(f, "Files of the workspace in the trace:")?;
+            for file in files {
+                write!(f, " @[{}]", file.display())?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn frame(path: &str, line: usize, function: Option<&str>) -> Frame {
+        Frame::new(path, line, function.map(str::to_string))
+    }
+
+    #[test]
+    fn test_parse_rust_panic() {
+        let fixture = "thread 'main' panicked at src/parser.rs:42:9:\nindex out of bounds\nstack backtrace:\n   0: rust_begin_unwind\n             at /rustc/abc/library/std/src/panicking.rs:645:5\n   1: app::parser::parse\n             at ./src/parser.rs:42:9\n";
+
+        let actual = Trace::parse(fixture);
+
+        let expected = Trace {
+            messages: vec![
+                "thread 'main' panicked at src/parser.rs:42:9: index out of bounds".to_string(),
+            ],
+            frames: vec![
+                frame("src/parser.rs", 42, None),
+                frame(
+                    "/rustc/abc/library/std/src/panicking.rs",
+                    645,
+                    Some("rust_begin_unwind"),
+                ),
+                frame("./src/parser.rs", 42, Some("app::parser::parse")),
+            ],
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_parse_other_languages() {
+        let fixture = [
+            "Traceback (most recent call last):",
+            "  File \"/srv/app/handlers.py\", line 12, in handle",
+            "ValueError: invalid id",
+            "TypeError: x is undefined",
+            "    at render (/home/ci/web/src/view.js:10:5)",
+            "panic: runtime error",
+            "\t/go/src/app/main.go:42 +0x1d",
+            "java.lang.IllegalStateException: closed",
+            "\tat com.acme.Service.run(Service.java:7)",
+        ]
+        .join("\n");
+
+        let actual = Trace::parse(&fixture);
+
+        let expected = Trace {
+            messages: vec![
+                "ValueError: invalid id".to_string(),
+                "TypeError: x is undefined".to_string(),
+                "panic: runtime error".to_string(),
+                "java.lang.IllegalStateException: closed".to_string(),
+            ],
+            frames: vec![
+                frame("/srv/app/handlers.py", 12, Some("handle")),
+                frame("/home/ci/web/src/view.js", 10, Some("render")),
+                frame("/go/src/app/main.go", 42, None),
+                frame("com/acme/Service.java", 7, Some("com.acme.Service.run")),
+            ],
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_resolve_and_task() {
+        let fixture = TempDir::new().unwrap();
+        let cwd = fixture.path();
+        std::fs::create_dir_all(cwd.join("src/main/java/com/acme")).unwrap();
+        std::fs::create_dir_all(cwd.join("src")).unwrap();
+        std::fs::write(cwd.join("src/main/java/com/acme/Service.java"), "").unwrap();
+        std::fs::write(cwd.join("src/view.js"), "").unwrap();
+        let trace = Trace::parse(
+            "    at render (/home/ci/web/src/view.js:10:5)\n    at node:internal/main:1:1\n\tat com.acme.Service.run(Service.java:7)",
+        );
+
+        let trace = trace.resolve(cwd);
+        let actual = trace.files();
+
+        let expected = vec![
+            Path::new("src/view.js"),
+            Path::new("src/main/java/com/acme/Service.java"),
+        ];
+        assert_eq!(actual, expected);
+
+        let task = TriageTask::new("crash.log", trace).to_string();
+        assert!(task.contains(
+            "<frame path=\"src/view.js\" line=\"10\" function=\"render\" workspace=\"true\"/>"
+        ));
+        assert!(task.contains("@[src/view.js] @[src/main/java/com/acme/Service.java]"));
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use forge_api::Agent;
+use serde::{Deserialize, Serialize};
+
+/// Tools that agents keep in an untrusted workspace, since they can neither
+/// change files nor run commands
+const READ_ONLY_TOOLS: &[&str] = &[
+    "forge_tool_fs_read",
+    "forge_tool_fs_read_many",
+    "forge_tool_fs_search",
+    "forge_tool_fs_list",
+    "forge_tool_fs_tree",
+    "forge_tool_code_outline",
+    "forge_tool_fs_info",
+    "forge_tool_todo_scan",
+    "forge_tool_attempt_completion",
+    "forge_tool_followup",
+];
+
+/// Workspaces that the user trusts, across sessions. Like in editors, a
+/// workspace that was cloned from elsewhere may contain instructions that
+/// steer the agent, so it only gets read-only tools until it is trusted.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrustedWorkspaces {
+    workspaces: BTreeSet<PathBuf>,
+}
+
+impl TrustedWorkspaces {
+    /// Path of the trusted workspaces, shared by every workspace
+    pub fn path(base_path: &Path) -> PathBuf {
+        base_path.join("trusted_workspaces.json")
+    }
+
+    /// Loads the trusted workspaces from `path`, trusting none when it is
+    /// missing or unreadable
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Whether the workspace, or a directory that contains it, is trusted
+    pub fn is_trusted(&self, cwd: &Path) -> bool {
+        let cwd = canonical(cwd);
+        self.workspaces
+            .iter()
+            .any(|workspace| cwd.starts_with(workspace))
+    }
+
+    pub fn trust(&mut self, cwd: &Path) {
+        self.workspaces.insert(canonical(cwd));
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Restricts the agents of an untrusted workspace to the read-only tools
+pub fn restrict(agents: &mut [Agent]) {
+    for agent in agents {
+        if let Some(tools) = agent.tools.as_mut() {
+            tools.retain(|tool| READ_ONLY_TOOLS.contains(&tool.as_str()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use forge_api::ToolName;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_is_trusted() {
+        let mut fixture = TrustedWorkspaces::default();
+        fixture.trust(Path::new("/projects/forge"));
+
+        let actual = [
+            fixture.is_trusted(Path::new("/projects/forge")),
+            fixture.is_trusted(Path::new("/projects/forge/crates")),
+            fixture.is_trusted(Path::new("/projects/forge-fork")),
+            fixture.is_trusted(Path::new("/projects")),
+        ];
+
+        let expected = [true, true, false, false];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_restrict() {
+        let mut fixture = vec![Agent::new("forge").tools(vec![
+            ToolName::new("forge_tool_fs_read"),
+            ToolName::new("forge_tool_fs_patch"),
+            ToolName::new("forge_tool_process_shell"),
+            ToolName::new("forge_tool_attempt_completion"),
+        ])];
+
+        restrict(&mut fixture);
+
+        let actual = fixture[0].tools.clone().unwrap_or_default();
+        let expected = vec![
+            ToolName::new("forge_tool_fs_read"),
+            ToolName::new("forge_tool_attempt_completion"),
+        ];
+        assert_eq!(actual, expected);
+    }
+}
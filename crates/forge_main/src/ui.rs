@@ -1,34 +1,83 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::io::IsTerminal;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use anyhow::{Context, Result};
+use chrono::Utc;
 use forge_api::{
-    AgentMessage, ChatRequest, ChatResponse, Conversation, ConversationId, Event, Model, ModelId,
-    API,
+    Agent, AgentId, AgentMessage, ChatRequest, ChatResponse, CodeOwners, Conversation,
+    ConversationId, Event, FileUsage, Model, ModelId, MpscStream, ToolCallFull, ToolOverride,
+    TurnId, Usage, Workflow, API,
+};
+use forge_display::{
+    glyph, Glyph, MarkdownFormat, Palette, RendererRegistry, TitleFormat, ToolRenderer,
 };
-use forge_display::{MarkdownFormat, TitleFormat};
 use forge_fs::ForgeFS;
+use forge_snaps::SnapshotService;
 use forge_spinner::SpinnerManager;
-use forge_tracker::ToolCallPayload;
+use forge_tracker::{
+    BudgetPayload, Category, Consent, EventKind, SessionPayload, ToolCallPayload, TurnPayload,
+};
+use futures::stream::FuturesUnordered;
 use inquire::error::InquireError;
 use inquire::ui::{RenderConfig, Styled};
-use inquire::Select;
+use inquire::{Confirm, Select};
 use serde::Deserialize;
 use serde_json::Value;
 use tokio_stream::StreamExt;
 use tracing::error;
 
 use crate::auto_update::update_forge;
-use crate::cli::Cli;
+use crate::cli::{
+    ChangelogCommand, Cli, DiffCommand, DoctorCommand, EvalCommand, InitCommand, MigrateCommand,
+    PipelineCommand, PipelineRunCommand, PipelineSubcommand, SessionsCommand, SessionsSubcommand,
+    StatsCommand, StatsSubcommand, TelemetryCommand, TelemetrySubcommand, ToolsCommand,
+    ToolsSubcommand, TopLevelCommand, TriageCommand, WatchCommand,
+};
+use crate::conflicts::{self, Resolution};
+use crate::doctor::Doctor;
+use crate::estimate::{self, Estimate, TurnRecord, TurnStats};
+use crate::eval::{collect_usage, EvalReport, EvalResult, EvalTask};
+use crate::idle::IdleGuard;
 use crate::info::Info;
+use crate::init::{self, Stack};
 use crate::input::Console;
+use crate::locale::LOCALE;
+use crate::marks::{self, Mark};
+use crate::memory::MemoryStatus;
+use crate::migrate::{detect_build_command, MigrationTask};
 use crate::model::{Command, ForgeCommandManager};
+use crate::pipeline::{
+    is_rate_limited, item_label, run_step, BatchReport, ConcurrencyLimit, ItemResult, Pipeline,
+    PipelineState, Step,
+};
+use crate::report::{RunReport, StepReport};
+use crate::retention::{self, Store};
+use crate::shadow::ShadowWorkspace;
 use crate::state::{Mode, UIState};
-use crate::{banner, TRACKER};
+use crate::tools_display::{format_progress, json_tools, markdown_tools};
+use crate::triage::{self, TriageTask};
+use crate::trust::{self, TrustedWorkspaces};
+use crate::watch::{fix_task, WorkspaceWatcher};
+use crate::{banner, changelog, diff, inspect, packages, pager, search, theme, TRACKER};
 
 // Event type constants moved to UI layer
 pub const EVENT_USER_TASK_INIT: &str = "user_task_init";
 pub const EVENT_USER_TASK_UPDATE: &str = "user_task_update";
 
+/// Number of times a pipeline item that was rate limited is retried
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+
+/// Delay before retrying a rate limited pipeline item, multiplied by the
+/// attempt
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Time that the tools of a cancelled conversation get to stop their
+/// processes and finish their writes before the conversation is dropped
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Debug, Clone, Deserialize, PartialEq, Eq, Default)]
 pub struct PartialEvent {
     pub name: String,
@@ -55,11 +104,18 @@ pub struct UI<F> {
     command: Arc<ForgeCommandManager>,
     cli: Cli,
     spinner: SpinnerManager,
+    renderers: RendererRegistry,
+    /// Whether the workspace is trusted, once the user was asked
+    trusted: Option<bool>,
+    /// Time the session started at, to tell the plans written during it
+    started_at: SystemTime,
+    /// Copy of the workspace that the session works in, with `--shadow`
+    shadow: Option<ShadowWorkspace>,
     #[allow(dead_code)] // The guard is kept alive by being held in the struct
     _guard: forge_tracker::Guard,
 }
 
-impl<F: API> UI<F> {
+impl<F: API + 'static> UI<F> {
     /// Writes a line to the console output
     /// Takes anything that implements ToString trait
     fn writeln<T: ToString>(&mut self, content: T) -> anyhow::Result<()> {
@@ -89,6 +145,11 @@ impl<F: API> UI<F> {
 
     // Set the current mode and update conversation variable
     async fn handle_mode_change(&mut self, mode: Mode) -> Result<()> {
+        if matches!((&self.state.mode, &mode), (Mode::Plan, Mode::Act))
+            && !self.approve_plan().await?
+        {
+            return Ok(());
+        }
         self.handle_new().await?;
         // Set the mode variable in the conversation if a conversation exists
         let conversation_id = self.init_conversation().await?;
@@ -109,6 +170,70 @@ impl<F: API> UI<F> {
 
         Ok(())
     }
+
+    /// Shows the estimate of the plan written in this session before acting on
+    /// it, and asks for approval when it is over the budget of the workflow
+    async fn approve_plan(&mut self) -> Result<bool> {
+        let env = self.api.environment();
+        let Some(path) = estimate::latest_plan(&env.cwd, self.started_at) else {
+            return Ok(true);
+        };
+        let steps = estimate::plan_steps(&ForgeFS::read_to_string(&path).await?);
+        let stats = TurnStats::load(&TurnStats::path(&env.base_path));
+        let pricing = match self.state.model.clone() {
+            Some(model) => self
+                .get_models()
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .find(|candidate| candidate.id == model)
+                .and_then(|model| model.pricing),
+            None => None,
+        };
+        let Some(estimate) = Estimate::new(steps, &stats, pricing.as_ref()) else {
+            return Ok(true);
+        };
+
+        let mut summary = format!(
+            "{} steps, ~{} tokens, ~{}",
+            estimate.steps,
+            LOCALE.number(estimate.prompt_tokens + estimate.completion_tokens),
+            LOCALE.duration(estimate.duration)
+        );
+        if let Some(cost) = estimate.cost {
+            summary.push_str(&format!(", ~${}", LOCALE.decimal(cost, 2)));
+        }
+        self.writeln(
+            TitleFormat::info(format!("Plan estimate: {summary}"))
+                .sub_title(path.display().to_string()),
+        )?;
+
+        let Some(budget) = self
+            .state
+            .plan_budget
+            .filter(|budget| estimate.exceeds(*budget))
+        else {
+            return Ok(true);
+        };
+        let approved = std::io::stdin().is_terminal() && {
+            self.spinner.stop(None)?;
+            Confirm::new(&format!(
+                "The plan is over the budget of ${}. Act on it?",
+                LOCALE.decimal(budget, 2)
+            ))
+            .with_default(false)
+            .prompt()
+            .unwrap_or(false)
+        };
+        if !approved {
+            self.writeln(
+                TitleFormat::action("Staying in plan mode")
+                    .sub_title("the estimate is over the plan budget"),
+            )?;
+        }
+        Ok(approved)
+    }
+
     // Helper functions for creating events with the specific event names
     fn create_task_init_event<V: Into<Value>>(&self, content: V) -> Event {
         Event::new(
@@ -133,9 +258,28 @@ impl<F: API> UI<F> {
     }
 
     pub fn init(cli: Cli, api: Arc<F>) -> Result<Self> {
+        if let Some(name) = &cli.package {
+            let package = packages::find(&std::env::current_dir()?, name)?;
+            // The environment resolves the working directory on every access, so
+            // the session is scoped to the package from here on
+            std::env::set_current_dir(&package.path)?;
+        }
+
+        // Likewise, a shadow session works in the copy of the workspace
+        let shadow = if cli.shadow {
+            let shadow = ShadowWorkspace::create(&std::env::current_dir()?)?;
+            std::env::set_current_dir(shadow.path())?;
+            Some(shadow)
+        } else {
+            None
+        };
+
         // Parse CLI arguments first to get flags
         let env = api.environment();
         let command = Arc::new(ForgeCommandManager::default());
+        // Use the detected theme until the workflow has been read
+        forge_display::set_palette(Palette::detect());
+        glyph::set_ascii(cli.ascii || glyph::detect_ascii());
         Ok(Self {
             state: Default::default(),
             api,
@@ -144,11 +288,35 @@ impl<F: API> UI<F> {
             command,
             spinner: SpinnerManager::new(),
             markdown: MarkdownFormat::new(),
+            renderers: RendererRegistry::default(),
+            trusted: None,
+            started_at: SystemTime::now(),
+            shadow,
             _guard: forge_tracker::init_tracing(env.log_path())?,
         })
     }
 
+    /// Registers how the output of a tool is displayed. Tool outputs are shown
+    /// in verbose mode, and always for tools with a registered renderer.
+    pub fn register_renderer(
+        &mut self,
+        tool: impl Into<String>,
+        renderer: impl ToolRenderer + 'static,
+    ) -> &mut Self {
+        self.renderers.register(tool, renderer);
+        self
+    }
+
     async fn prompt(&self) -> Result<Command> {
+        let timeout = Some(self.cli.idle_timeout)
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs);
+        let _idle = IdleGuard::start(
+            self.api.clone(),
+            self.state.conversation_id.clone(),
+            timeout,
+        );
+
         // Prompt the user for input
         self.console.prompt(Some(self.state.clone().into())).await
     }
@@ -164,6 +332,20 @@ impl<F: API> UI<F> {
     }
 
     async fn run_inner(&mut self) -> Result<()> {
+        // Subcommands run a single task and exit
+        if let Some(subcommand) = self.cli.subcommands.clone() {
+            return self.handle_subcommand(subcommand).await;
+        }
+
+        // The retention of the stored files is enforced in the background, so
+        // that it doesn't delay the startup
+        let env = self.api.environment();
+        tokio::task::spawn_blocking(move || {
+            if let Err(err) = retention::prune(&env, &env.retention, false) {
+                error!(error = ?err, "Failed to prune the stored files");
+            }
+        });
+
         // Check for dispatch flag first
         if let Some(dispatch_json) = self.cli.event.clone() {
             return self.handle_dispatch(dispatch_json).await;
@@ -216,12 +398,27 @@ impl<F: API> UI<F> {
                     self.spinner.start(None)?;
                     let chat_result = self.chat(content.clone()).await;
                     if let Err(err) = chat_result {
-                        tokio::spawn(
-                            TRACKER.dispatch(forge_tracker::EventKind::Error(format!("{err:?}"))),
-                        );
-                        error!(error = ?err, "Chat request failed");
-
-                        self.writeln(TitleFormat::error(format!("{err:?}")))?;
+                        let max_turns = err.chain().find_map(|cause| {
+                            match cause.downcast_ref::<forge_api::Error>() {
+                                Some(forge_api::Error::MaxTurnsReached(_, limit)) => Some(*limit),
+                                _ => None,
+                            }
+                        });
+                        if let Some(limit) = max_turns {
+                            tokio::spawn(TRACKER.dispatch(EventKind::BudgetExceeded(
+                                BudgetPayload::new("max_turns", limit),
+                            )));
+                        }
+                        tokio::spawn(TRACKER.dispatch(EventKind::Error(format!("{err:?}"))));
+                        let turn_id = self.state.turn_id.as_ref().map(TurnId::into_string);
+                        error!(error = ?err, turn_id = ?turn_id, "Chat request failed");
+
+                        // The id of the turn finds its entries in the logs
+                        let mut title = TitleFormat::error(format!("{err:?}"));
+                        if let Some(turn_id) = turn_id {
+                            title = title.sub_title(format!("turn {turn_id}"));
+                        }
+                        self.writeln(title)?;
                     }
                 }
                 Command::Act => {
@@ -240,6 +437,44 @@ impl<F: API> UI<F> {
                     let output = format_tools(&tools);
                     self.writeln(output)?;
                 }
+                Command::Mark(ref name) => {
+                    self.handle_mark(name).await?;
+                }
+                Command::Jump(ref name) => {
+                    let context = self.main_context().await?;
+                    let output = marks::jump(context.as_ref(), self.state.marks.get(name)?);
+                    self.writeln(output)?;
+                }
+                Command::Diff(ref from, ref to) => {
+                    let service = SnapshotService::new(self.api.environment().snapshot_path());
+                    let from = self.state.marks.get(from)?;
+                    let to = to
+                        .as_deref()
+                        .map(|to| self.state.marks.get(to))
+                        .transpose()?;
+                    let output = marks::diff(&service, from, to).await?;
+                    if !pager::page(&output) {
+                        self.writeln(output.trim_end())?;
+                    }
+                }
+                Command::Search(ref query) => {
+                    self.handle_search(query).await?;
+                }
+                Command::Promote => {
+                    self.handle_promote().await?;
+                }
+                Command::Memory => {
+                    let context = self.main_context().await?;
+                    let status = MemoryStatus::new(context.as_ref());
+                    self.writeln(Info::from(&status))?;
+                }
+                Command::LastRequest(nth) => {
+                    let path = self.api.environment().inspection_path();
+                    let output = inspect::last_request(&path, nth)?;
+                    if !pager::page(&output) {
+                        self.writeln(output.trim_end())?;
+                    }
+                }
                 Command::Exit => {
                     update_forge().await;
 
@@ -274,6 +509,608 @@ impl<F: API> UI<F> {
         Ok(())
     }
 
+    async fn handle_subcommand(&mut self, subcommand: TopLevelCommand) -> Result<()> {
+        match subcommand {
+            TopLevelCommand::Changelog(command) => self.handle_changelog(command).await,
+            TopLevelCommand::Migrate(command) => self.handle_migrate(command).await,
+            TopLevelCommand::Watch(command) => self.handle_watch(command).await,
+            TopLevelCommand::Eval(command) => self.handle_eval(command).await,
+            TopLevelCommand::Diff(command) => self.handle_diff(command).await,
+            TopLevelCommand::Pipeline(command) => self.handle_pipeline(command).await,
+            TopLevelCommand::Triage(command) => self.handle_triage(command).await,
+            TopLevelCommand::Doctor(command) => self.handle_doctor(command).await,
+            TopLevelCommand::Init(command) => self.handle_init(command).await,
+            TopLevelCommand::Tools(command) => self.handle_tools(command).await,
+            TopLevelCommand::Stats(command) => self.handle_stats(command).await,
+            TopLevelCommand::Sessions(command) => self.handle_sessions(command).await,
+            TopLevelCommand::Telemetry(command) => self.handle_telemetry(command).await,
+        }
+    }
+
+    async fn handle_pipeline(&mut self, command: PipelineCommand) -> Result<()> {
+        let PipelineSubcommand::Run(command) = command.command;
+        let pipeline = Pipeline::load(&command.path)?;
+        let mut report = RunReport::new(&command.path)
+            .code_owners(CodeOwners::find(&self.api.environment().cwd))
+            .locale(LOCALE.clone());
+
+        // The report is written even when a step fails, since that is when it is
+        // needed the most
+        let result = self.run_pipeline(&pipeline, &command, &mut report).await;
+        if let Some(dir) = &command.report_dir {
+            let (markdown, json) = report.write(dir)?;
+            self.writeln(TitleFormat::info("Report").sub_title(format!(
+                "{} {}",
+                markdown.display(),
+                json.display()
+            )))?;
+        }
+        result
+    }
+
+    async fn run_pipeline(
+        &mut self,
+        pipeline: &Pipeline,
+        command: &PipelineRunCommand,
+        report: &mut RunReport,
+    ) -> Result<()> {
+        let mut workflow = self.api.read_workflow(self.cli.workflow.as_deref()).await?;
+        if !self.is_workspace_trusted()? {
+            trust::restrict(&mut workflow.agents);
+        }
+        self.apply_dry_run(&mut workflow.agents);
+        let cwd = self.api.environment().cwd;
+        let mut state = PipelineState::default();
+
+        for step in &pipeline.steps {
+            if let Some(condition) = &step.when {
+                if !state.is_met(condition) {
+                    self.writeln(TitleFormat::info("Skipped").sub_title(&step.id))?;
+                    report.steps.push(StepReport::skipped(&step.id));
+                    continue;
+                }
+            }
+
+            let output = match &step.for_each {
+                Some(for_each) => {
+                    let items = state.items(for_each, &cwd)?;
+                    let concurrency = step.concurrency.unwrap_or(command.concurrency);
+                    let batch = self
+                        .run_pipeline_batch(step, &workflow, &state, items, concurrency)
+                        .await?;
+                    self.writeln(&batch)?;
+                    let output = batch.outputs();
+                    report.steps.extend(batch.into_step_reports());
+                    output
+                }
+                None => {
+                    let prompt = state.render(&step.prompt, None)?;
+                    let default_event = self.create_task_init_event(Value::Null).name;
+
+                    self.spinner
+                        .start(Some(format!("Running {}", step.id).as_str()))?;
+                    let start = std::time::Instant::now();
+                    let run = run_step(
+                        self.api.as_ref(),
+                        step,
+                        workflow.clone(),
+                        &default_event,
+                        prompt,
+                    )
+                    .await;
+                    self.spinner.stop(None)?;
+
+                    let step_report = StepReport::new(&step.id, None, &run, start.elapsed());
+                    report.steps.push(step_report);
+                    let run = run.with_context(|| format!("Pipeline step '{}' failed", step.id))?;
+                    self.writeln(TitleFormat::action("Completed").sub_title(&step.id))?;
+                    run.output
+                }
+            };
+            state.record(&step.id, output);
+        }
+
+        Ok(())
+    }
+
+    /// Runs a step once per item, with up to `concurrency` items in flight.
+    /// Items that fail are reported instead of stopping the pipeline, and
+    /// items that were rate limited are retried with less concurrency.
+    async fn run_pipeline_batch(
+        &mut self,
+        step: &Step,
+        workflow: &Workflow,
+        state: &PipelineState,
+        items: Vec<Value>,
+        concurrency: usize,
+    ) -> Result<BatchReport> {
+        let default_event = self.create_task_init_event(Value::Null).name;
+        let mut pending = items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let prompt = state.render(&step.prompt, Some(&item))?;
+                Ok((index, item, prompt, 0))
+            })
+            .collect::<Result<VecDeque<_>>>()?;
+        let total = pending.len();
+        let mut results = vec![None; total];
+
+        let api = self.api.clone();
+        let run = |index: usize, item: Value, prompt: String, attempt: u32| {
+            let api = api.clone();
+            let workflow = workflow.clone();
+            let default_event = default_event.clone();
+            async move {
+                if attempt > 0 {
+                    tokio::time::sleep(RATE_LIMIT_BACKOFF * attempt).await;
+                }
+                let start = std::time::Instant::now();
+                let output =
+                    run_step(api.as_ref(), step, workflow, &default_event, prompt.clone()).await;
+                (index, item, prompt, attempt, output, start.elapsed())
+            }
+        };
+
+        let mut limit = ConcurrencyLimit::new(concurrency);
+        let mut running = FuturesUnordered::new();
+        let mut completed = 0;
+        self.spinner
+            .start(Some(format!("Running {} [0/{}]", step.id, total).as_str()))?;
+
+        loop {
+            while running.len() < limit.current() {
+                match pending.pop_front() {
+                    Some((index, item, prompt, attempt)) => {
+                        running.push(run(index, item, prompt, attempt))
+                    }
+                    None => break,
+                }
+            }
+
+            let Some((index, item, prompt, attempt, output, duration)) = running.next().await
+            else {
+                break;
+            };
+
+            match output {
+                Err(error) if is_rate_limited(&error) && attempt < MAX_RATE_LIMIT_RETRIES => {
+                    limit.on_rate_limit();
+                    pending.push_front((index, item, prompt, attempt + 1));
+                }
+                output => {
+                    completed += 1;
+                    let title = format!("{} [{}]", step.id, item_label(&item));
+                    self.spinner.stop(None)?;
+                    if output.is_ok() {
+                        limit.on_success();
+                        self.writeln(TitleFormat::action("Completed").sub_title(title))?;
+                    } else {
+                        self.writeln(TitleFormat::error("Failed").sub_title(title))?;
+                    }
+                    self.spinner.start(Some(
+                        format!("Running {} [{}/{}]", step.id, completed, total).as_str(),
+                    ))?;
+
+                    results[index] = Some(ItemResult {
+                        item,
+                        output: output.map_err(|error| format!("{error:#}")),
+                        duration,
+                    });
+                }
+            }
+        }
+        self.spinner.stop(None)?;
+
+        Ok(BatchReport {
+            step: step.id.clone(),
+            results: results.into_iter().flatten().collect(),
+        })
+    }
+
+    async fn handle_diff(&mut self, command: DiffCommand) -> Result<()> {
+        let service = SnapshotService::new(self.api.environment().snapshot_path());
+        let output = match command.snapshot {
+            Some(id) => diff::diff(&service, &id, command.against.as_deref()).await?,
+            None => diff::list(&service).await?,
+        };
+
+        if !pager::page(&output) {
+            self.writeln(output)?;
+        }
+        Ok(())
+    }
+
+    async fn handle_eval(&mut self, command: EvalCommand) -> Result<()> {
+        let tasks = EvalTask::load_all(&command.fixtures)?;
+        let mut workflow = self.api.read_workflow(self.cli.workflow.as_deref()).await?;
+        if !self.is_workspace_trusted()? {
+            trust::restrict(&mut workflow.agents);
+        }
+        let models = if command.models.is_empty() {
+            vec![workflow.model.clone().ok_or(anyhow::anyhow!(
+                "No model configured, please provide one with --model"
+            ))?]
+        } else {
+            command.models.iter().map(ModelId::new).collect()
+        };
+
+        let cwd = std::env::current_dir()?;
+        let mut report = EvalReport::default();
+
+        for model in models {
+            for task in &tasks {
+                self.spinner.start(Some(
+                    format!("Evaluating {} with {}", task.name, model).as_str(),
+                ))?;
+                let result = self
+                    .run_eval_task(task, &model, &workflow, command.timeout)
+                    .await;
+
+                // Restore the working directory changed by the task
+                std::env::set_current_dir(&cwd)?;
+                self.spinner.stop(None)?;
+
+                let result = result?;
+                let title = if result.passed {
+                    TitleFormat::action("PASS")
+                } else {
+                    TitleFormat::error("FAIL")
+                };
+                self.writeln(title.sub_title(format!("{} [{}]", result.task, result.model)))?;
+                report.results.push(result);
+            }
+        }
+
+        if let Some(path) = &command.csv {
+            std::fs::write(path, report.table().to_csv())
+                .with_context(|| format!("Failed to write results to {}", path.display()))?;
+        }
+        self.writeln(report)
+    }
+
+    async fn run_eval_task(
+        &mut self,
+        task: &EvalTask,
+        model: &ModelId,
+        workflow: &Workflow,
+        timeout: u64,
+    ) -> Result<EvalResult> {
+        let workspace = task.workspace()?;
+
+        // The environment resolves the working directory on every access, so the
+        // agent operates on the task workspace until the directory is restored
+        std::env::set_current_dir(workspace.path())?;
+
+        let workflow = workflow.clone().model(model.clone());
+        let conversation = self.api.init_conversation(workflow).await?;
+        let event = self.create_task_init_event(task.prompt.clone());
+        let api = self.api.clone();
+
+        let start = std::time::Instant::now();
+        let (usage, error) =
+            match tokio::time::timeout(tokio::time::Duration::from_secs(timeout), async move {
+                let mut stream = api.chat(ChatRequest::new(event, conversation.id)).await?;
+                anyhow::Ok(collect_usage(&mut stream).await)
+            })
+            .await
+            {
+                Ok(Ok(result)) => result,
+                Ok(Err(err)) => (Usage::default(), Some(format!("{err:?}"))),
+                Err(_) => (
+                    Usage::default(),
+                    Some(format!("Timed out after {timeout}s")),
+                ),
+            };
+        let duration = start.elapsed();
+
+        let output = self
+            .api
+            .execute_shell_command(&task.check, workspace.path().to_path_buf())
+            .await?;
+
+        Ok(EvalResult {
+            task: task.name.clone(),
+            model: model.clone(),
+            passed: error.is_none() && output.exit_code == Some(0),
+            duration,
+            usage,
+            error,
+        })
+    }
+
+    async fn handle_watch(&mut self, command: WatchCommand) -> Result<()> {
+        let cwd = self.api.environment().cwd;
+        let check = command
+            .command
+            .or_else(|| detect_build_command(&cwd).map(|command| command.to_string()))
+            .ok_or(anyhow::anyhow!(
+                "Could not detect the command to watch, please provide one with --command"
+            ))?;
+
+        self.writeln(TitleFormat::action("Watching").sub_title(&check))?;
+
+        let mut watcher = WorkspaceWatcher::new(cwd.clone());
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_secs(command.interval.max(1)));
+
+        loop {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => return Ok(()),
+                _ = interval.tick() => {}
+            }
+
+            if !watcher.poll().await? {
+                continue;
+            }
+
+            let output = self.api.execute_shell_command(&check, cwd.clone()).await?;
+            if output.exit_code == Some(0) {
+                self.writeln(TitleFormat::action("Check passed").sub_title(&check))?;
+                continue;
+            }
+
+            self.writeln(TitleFormat::error("Check failed").sub_title(&check))?;
+
+            let apply = command.auto
+                || Confirm::new("Ask forge to fix the failure?")
+                    .with_default(true)
+                    .prompt()
+                    .unwrap_or(false);
+
+            if apply {
+                self.spinner.start(None)?;
+                if let Err(err) = self.chat(fix_task(&output)).await {
+                    self.writeln(TitleFormat::error(format!("{err:?}")))?;
+                }
+            }
+
+            // Changes made by the agent are picked up by the next poll, which
+            // re-runs the check to verify the fix.
+        }
+    }
+
+    async fn handle_migrate(&mut self, command: MigrateCommand) -> Result<()> {
+        let cwd = self.api.environment().cwd;
+        let build_command = command
+            .build_command
+            .or_else(|| detect_build_command(&cwd).map(|command| command.to_string()))
+            .ok_or(anyhow::anyhow!(
+                "Could not detect the build command, please provide one with --build-command"
+            ))?;
+
+        let task = MigrationTask::new(command.dependency, command.version, build_command);
+        self.writeln(TitleFormat::action("Migrating").sub_title(format!(
+            "{} {} {}",
+            task.dependency,
+            Glyph::Arrow,
+            task.version
+        )))?;
+
+        self.spinner.start(None)?;
+        self.chat(task.to_string()).await
+    }
+
+    async fn handle_tools(&mut self, command: ToolsCommand) -> Result<()> {
+        let ToolsSubcommand::Docs(command) = command.command;
+        let tools = self.api.tools().await;
+        // Agents are resolved like in a conversation, so that the docs show what
+        // the configuration grants and not only what forge.yaml overrides
+        let workflow = self.api.read_workflow(self.cli.workflow.as_deref()).await?;
+        let agents = Conversation::new(ConversationId::generate(), workflow).agents;
+
+        let docs = if command.json {
+            json_tools(&tools, &agents)?
+        } else {
+            markdown_tools(&tools, &agents)
+        };
+        match command.output {
+            Some(path) => {
+                tokio::fs::write(&path, docs).await?;
+                self.writeln(TitleFormat::action("Documented").sub_title(format!(
+                    "{} tools in {}",
+                    tools.len(),
+                    path.display()
+                )))
+            }
+            None => self.writeln(docs),
+        }
+    }
+
+    /// Records the files that the agent reads and edits, to rank them in later
+    /// sessions. Failing to record is not worth interrupting the session.
+    fn record_file_usage(&self, call: &ToolCallFull) {
+        let Some(path) = call.arguments.get("path").and_then(Value::as_str) else {
+            return;
+        };
+        let store = self.api.environment().file_usage_path();
+        let mut usage = FileUsage::load(&store);
+        if usage.record(call.name.as_str(), Path::new(path), Utc::now()) {
+            if let Err(err) = usage.save(&store) {
+                error!(error = ?err, "Failed to record file usage");
+            }
+        }
+    }
+
+    async fn handle_stats(&mut self, command: StatsCommand) -> Result<()> {
+        let StatsSubcommand::Files(command) = command.command;
+        let env = self.api.environment();
+        let files = FileUsage::load(&env.file_usage_path()).hot_files(&env.cwd, command.limit);
+        if files.is_empty() {
+            return self.writeln(TitleFormat::info(
+                "No file usage recorded in this workspace",
+            ));
+        }
+
+        let width = files
+            .iter()
+            .map(|(_, stats)| LOCALE.number(stats.edits.max(stats.reads)).chars().count())
+            .max()
+            .unwrap_or_default()
+            .max("reads".len());
+        let used_width = "last used"
+            .len()
+            .max(LOCALE.datetime(&Utc::now()).chars().count());
+        let mut out = format!(
+            "{:>width$}  {:>width$}  {:<used_width$}  file\n",
+            "edits", "reads", "last used"
+        );
+        for (path, stats) in files {
+            let last_used = stats
+                .last_used
+                .map(|time| LOCALE.datetime(&time))
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "{:>width$}  {:>width$}  {:<used_width$}  {}\n",
+                LOCALE.number(stats.edits),
+                LOCALE.number(stats.reads),
+                last_used,
+                path.display()
+            ));
+        }
+        self.writeln(out.trim_end())
+    }
+
+    async fn handle_sessions(&mut self, command: SessionsCommand) -> Result<()> {
+        let SessionsSubcommand::Prune(command) = command.command;
+        let env = self.api.environment();
+        let mut policy = env.retention.clone();
+        policy.max_age_days = command.max_age_days.or(policy.max_age_days);
+        policy.max_total_bytes = command
+            .max_size_mb
+            .map(|mb| mb * 1024 * 1024)
+            .or(policy.max_total_bytes);
+
+        let files = retention::prune(&env, &policy, command.dry_run)?;
+        if files.is_empty() {
+            return self.writeln(TitleFormat::info("Nothing to prune"));
+        }
+
+        let mut stores = BTreeMap::<Store, (u64, u64)>::new();
+        for file in &files {
+            if command.dry_run {
+                self.writeln(format!(
+                    "{:<9}  {:>10}  {}",
+                    file.store,
+                    LOCALE.bytes(file.size),
+                    file.path.display()
+                ))?;
+            }
+            let (count, bytes) = stores.entry(file.store).or_default();
+            *count += 1;
+            *bytes += file.size;
+        }
+        for (store, (count, bytes)) in stores {
+            let title = if command.dry_run {
+                TitleFormat::info("Would prune")
+            } else {
+                TitleFormat::action("Pruned")
+            };
+            self.writeln(title.sub_title(format!(
+                "{store}: {} files, {}",
+                LOCALE.number(count),
+                LOCALE.bytes(bytes)
+            )))?;
+        }
+        Ok(())
+    }
+
+    async fn handle_telemetry(&mut self, command: TelemetryCommand) -> Result<()> {
+        let mut stored = Consent::stored();
+        match command.command {
+            TelemetrySubcommand::Status => {}
+            TelemetrySubcommand::Enable(command) => {
+                stored.set(command.category.into(), true);
+                stored.save()?;
+            }
+            TelemetrySubcommand::Disable(command) => {
+                stored.set(command.category.into(), false);
+                stored.save()?;
+            }
+        }
+
+        let consent = Consent::load();
+        for (name, category) in [("errors", Category::Errors), ("usage", Category::Usage)] {
+            let status = if consent.allows(category) {
+                "shared"
+            } else if !category.is_compiled() {
+                "not shared, not compiled in"
+            } else if stored.allows(category) {
+                "not shared, disabled by FORGE_TRACKER=false"
+            } else {
+                "not shared"
+            };
+            self.writeln(TitleFormat::info(name).sub_title(status))?;
+        }
+        self.writeln(TitleFormat::info("prompts").sub_title("never collected"))
+    }
+
+    async fn handle_init(&mut self, command: InitCommand) -> Result<()> {
+        let cwd = self.api.environment().cwd;
+        let stacks = Stack::detect(&cwd);
+        let files = init::scaffold(&cwd, &stacks, command.force)?;
+
+        let detected = if stacks.is_empty() {
+            "no stack detected".to_string()
+        } else {
+            stacks
+                .iter()
+                .map(|stack| stack.name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        self.writeln(TitleFormat::action("Initialized").sub_title(detected))?;
+        for file in files {
+            let file = file.strip_prefix(&cwd).unwrap_or(&file).to_path_buf();
+            self.writeln(TitleFormat::info("Created").sub_title(file.display().to_string()))?;
+        }
+        Ok(())
+    }
+
+    async fn handle_doctor(&mut self, command: DoctorCommand) -> Result<()> {
+        Doctor::new(self.cli.restricted, command).run().await
+    }
+
+    async fn handle_triage(&mut self, command: TriageCommand) -> Result<()> {
+        let cwd = self.api.environment().cwd;
+        let trace = triage::load(&command.path, command.binary.as_deref())
+            .await?
+            .resolve(&cwd);
+        if trace.frames.is_empty() && trace.messages.is_empty() {
+            anyhow::bail!(
+                "No panic, exception or stack trace found in {}",
+                command.path.display()
+            );
+        }
+
+        self.writeln(TitleFormat::action("Triaging").sub_title(format!(
+            "{} ({} frames, {} files)",
+            command.path.display(),
+            trace.frames.len(),
+            trace.files().len()
+        )))?;
+
+        let task = TriageTask::new(command.path, trace);
+        self.spinner.start(None)?;
+        self.chat(task.to_string()).await
+    }
+
+    async fn handle_changelog(&mut self, command: ChangelogCommand) -> Result<()> {
+        let notes = changelog::generate(&command.range, command.template.as_deref()).await?;
+
+        match command.output {
+            Some(path) => {
+                tokio::fs::write(&path, notes).await?;
+                self.writeln(
+                    TitleFormat::action("Changelog created").sub_title(path.display().to_string()),
+                )?;
+            }
+            None => self.writeln(notes)?,
+        }
+
+        Ok(())
+    }
+
     /// Select a model from the available models
     /// Returns Some(ModelId) if a model was selected, or None if selection was
     /// canceled
@@ -286,9 +1123,9 @@ impl<F: API> UI<F> {
 
         // Create a custom render config with the specified icons
         let render_config = RenderConfig::default()
-            .with_scroll_up_prefix(Styled::new("⇡"))
-            .with_scroll_down_prefix(Styled::new("⇣"))
-            .with_highlighted_option_prefix(Styled::new("➤"));
+            .with_scroll_up_prefix(Styled::new(Glyph::ScrollUp.as_str()))
+            .with_scroll_down_prefix(Styled::new(Glyph::ScrollDown.as_str()))
+            .with_highlighted_option_prefix(Styled::new(Glyph::Pointer.as_str()));
 
         // Find the index of the current model
         let starting_cursor = self
@@ -362,6 +1199,7 @@ impl<F: API> UI<F> {
 
         // Create the chat request with the event
         let chat = ChatRequest::new(event.into(), conversation_id);
+        self.state.turn_id = Some(chat.turn_id.clone());
 
         // Process the event
         let mut stream = self.api.chat(chat).await?;
@@ -386,6 +1224,21 @@ impl<F: API> UI<F> {
                     .write_workflow(self.cli.workflow.as_deref(), &workflow)
                     .await?;
 
+                // Agents of an untrusted workspace only get read-only tools
+                if !self.is_workspace_trusted()? {
+                    trust::restrict(&mut workflow.agents);
+                }
+                self.apply_dry_run(&mut workflow.agents);
+
+                // The rules of the project are added after the workflow is written, so
+                // that they stay in their own files
+                if let Some(rules) = init::project_rules(&self.api.environment().cwd) {
+                    workflow.custom_rules = Some(match workflow.custom_rules.take() {
+                        Some(custom_rules) => format!("{custom_rules}\n\n{rules}"),
+                        None => rules,
+                    });
+                }
+
                 // Get the mode from the config
                 let mode = workflow
                     .variables
@@ -395,14 +1248,39 @@ impl<F: API> UI<F> {
                     .unwrap_or(Mode::Act);
 
                 self.state = UIState::new(mode).provider(self.api.environment().provider);
+                self.state.diff_pager_threshold = workflow.diff_pager_threshold;
+                self.state.plan_budget = workflow.plan_budget;
+                self.state.tool_overrides = workflow.tool_overrides.clone().unwrap_or_default();
+                forge_display::set_palette(theme::palette(
+                    &workflow.theme.clone().unwrap_or_default(),
+                )?);
+                self.markdown = MarkdownFormat::new();
+                glyph::set_ascii(
+                    self.cli.ascii || workflow.ascii.unwrap_or_else(glyph::detect_ascii),
+                );
                 self.command.register_all(&workflow);
 
                 // We need to try and get the conversation ID first before fetching the model
                 if let Some(ref path) = self.cli.conversation {
-                    let conversation: Conversation = serde_json::from_str(
-                        ForgeFS::read_to_string(path.as_os_str()).await?.as_str(),
-                    )
-                    .context("Failed to parse Conversation")?;
+                    // The conversation may be a checkpoint, which is sealed
+                    let mut conversation: Conversation =
+                        serde_json::from_slice(&ForgeFS::read_sealed(path.as_os_str()).await?)
+                            .context("Failed to parse Conversation")?;
+                    if !self.is_workspace_trusted()? {
+                        trust::restrict(&mut conversation.agents);
+                    }
+                    self.apply_dry_run(&mut conversation.agents);
+                    let turns = conversation
+                        .state
+                        .values()
+                        .map(|state| state.turn_count)
+                        .sum();
+                    tokio::spawn(
+                        TRACKER.dispatch(EventKind::SessionResumed(SessionPayload::new(
+                            turns,
+                            conversation.events.len(),
+                        ))),
+                    );
 
                     let conversation_id = conversation.id.clone();
                     self.state.model = Some(conversation.main_model()?);
@@ -419,8 +1297,67 @@ impl<F: API> UI<F> {
         }
     }
 
+    /// Only simulates the tools that change files or run commands when the
+    /// session is a dry run
+    fn apply_dry_run(&self, agents: &mut [Agent]) {
+        if self.cli.dry_run {
+            for agent in agents {
+                agent.dry_run = Some(true);
+            }
+        }
+    }
+
+    /// Whether the workspace is trusted, asking the user the first time forge
+    /// is used in it
+    fn is_workspace_trusted(&mut self) -> Result<bool> {
+        if let Some(trusted) = self.trusted {
+            return Ok(trusted);
+        }
+
+        let env = self.api.environment();
+        // A shadow workspace is as trusted as the workspace it copies
+        let cwd = self
+            .shadow
+            .as_ref()
+            .map_or(env.cwd, |shadow| shadow.root().to_path_buf());
+        let path = TrustedWorkspaces::path(&env.base_path);
+        let mut workspaces = TrustedWorkspaces::load(&path);
+        let trusted = if self.cli.trust_workspace || workspaces.is_trusted(&cwd) {
+            true
+        } else if std::io::stdin().is_terminal() {
+            self.spinner.stop(None)?;
+            let trusted = Confirm::new(&format!("Do you trust the files in {}?", cwd.display()))
+                .with_help_message(
+                    "Untrusted workspaces only get read-only tools and can't run commands",
+                )
+                .with_default(false)
+                .prompt()
+                .unwrap_or(false);
+            if trusted {
+                workspaces.trust(&cwd);
+                workspaces.save(&path)?;
+            }
+            trusted
+        } else {
+            false
+        };
+
+        if !trusted {
+            self.writeln(TitleFormat::info("Untrusted workspace").sub_title(
+                "only read-only tools are available, use --trust-workspace to trust it",
+            ))?;
+        }
+        self.trusted = Some(trusted);
+        Ok(trusted)
+    }
+
     async fn chat(&mut self, content: String) -> Result<()> {
         let conversation_id = self.init_conversation().await?;
+        let content = self.state.marks.expand(&content)?;
+        let service = SnapshotService::new(self.api.environment().snapshot_path());
+        let content = self.state.turns.expand(&service, &content).await?;
+        let context = self.main_context().await?;
+        self.state.turns.start(context.as_ref())?;
 
         // Create a ChatRequest with the appropriate event type
         let event = if self.state.is_first {
@@ -432,24 +1369,86 @@ impl<F: API> UI<F> {
 
         // Create the chat request with the event
         let chat = ChatRequest::new(event, conversation_id);
+        self.state.turn_id = Some(chat.turn_id.clone());
 
         match self.api.chat(chat).await {
-            Ok(mut stream) => self.handle_chat_stream(&mut stream).await,
-            Err(err) => Err(err),
+            Ok(mut stream) => self.handle_chat_stream(&mut stream).await?,
+            Err(err) => return Err(err),
+        }
+
+        // Patches that didn't match are resolved once the task is done, and the
+        // ones that the user wants retried are sent back to the agent
+        match self.resolve_conflicts()? {
+            Some(task) => Box::pin(self.chat(task)).await,
+            None => Ok(()),
         }
     }
 
+    /// Asks the user how to resolve each patch that was queued because it
+    /// didn't match. Returns the task that retries the patches to retry.
+    fn resolve_conflicts(&mut self) -> Result<Option<String>> {
+        let conflicts = std::mem::take(&mut self.state.conflicts);
+        if conflicts.is_empty() {
+            return Ok(None);
+        }
+        self.spinner.stop(None)?;
+
+        let total = conflicts.len();
+        let mut retries = Vec::new();
+        for (index, conflict) in conflicts.into_iter().enumerate() {
+            self.writeln(
+                TitleFormat::info(format!("Patch conflict {} of {total}", index + 1))
+                    .sub_title(conflict.path.display().to_string()),
+            )?;
+            self.writeln(conflicts::describe(&conflict))?;
+
+            // Without a terminal the conflicts are only listed for the user
+            if !std::io::stdin().is_terminal() {
+                continue;
+            }
+            let resolution = Select::new("Resolve the patch:", Resolution::ALL.to_vec())
+                .prompt()
+                .unwrap_or(Resolution::Skip);
+            match resolution {
+                Resolution::Retry => retries.push(conflict),
+                Resolution::Manual => {
+                    if !conflicts::open_in_editor(&conflict.path)? {
+                        self.writeln(
+                            TitleFormat::action("Apply the patch manually")
+                                .sub_title("set $EDITOR to open the file"),
+                        )?;
+                    }
+                }
+                Resolution::Skip => {}
+            }
+        }
+
+        if retries.is_empty() {
+            return Ok(None);
+        }
+        self.spinner.start(None)?;
+        Ok(Some(conflicts::retry_task(&retries)))
+    }
+
     async fn handle_chat_stream(
         &mut self,
-        stream: &mut (impl StreamExt<Item = Result<AgentMessage<ChatResponse>>> + Unpin),
+        stream: &mut MpscStream<Result<AgentMessage<ChatResponse>>>,
     ) -> Result<()> {
         // Set up a tokio interval to update the spinner every second
         let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(500));
+        let start = std::time::Instant::now();
 
         loop {
             tokio::select! {
                 _ = tokio::signal::ctrl_c() => {
                     self.spinner.stop(None)?;
+                    // The tools are cancelled rather than dropped, so that they
+                    // kill the processes they started
+                    stream.cancel();
+                    let _ = tokio::time::timeout(CANCEL_TIMEOUT, async {
+                        while stream.next().await.is_some() {}
+                    })
+                    .await;
                     return Ok(());
                 }
                 _ = interval.tick() => {
@@ -467,6 +1466,7 @@ impl<F: API> UI<F> {
                         }
                         None => {
                             self.spinner.stop(None)?;
+                            self.track_turn(start.elapsed());
                             return Ok(())
                         },
                     }
@@ -475,6 +1475,139 @@ impl<F: API> UI<F> {
         }
     }
 
+    /// Tracks a turn that completed with the usage reported for it
+    fn track_turn(&self, duration: Duration) {
+        let usage = &self.state.usage;
+        let mut payload = TurnPayload::default()
+            .prompt_tokens(usage.prompt_tokens)
+            .completion_tokens(usage.completion_tokens)
+            .total_tokens(usage.total_tokens)
+            .duration_ms(duration.as_millis() as u64);
+        if let Some(model) = &self.state.model {
+            payload = payload.model(model.as_str());
+        }
+        if let Some(turn_id) = &self.state.turn_id {
+            payload = payload.turn_id(turn_id.into_string());
+        }
+        tokio::spawn(TRACKER.dispatch(EventKind::TurnCompleted(payload)));
+
+        // Kept locally to estimate the cost of plans
+        let path = TurnStats::path(&self.api.environment().base_path);
+        let mut stats = TurnStats::load(&path);
+        stats.record(TurnRecord {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            duration_ms: duration.as_millis() as u64,
+        });
+        if let Err(err) = stats.save(&path) {
+            tracing::warn!(error = ?err, "Failed to save the turn statistics");
+        }
+    }
+
+    async fn handle_mark(&mut self, name: &str) -> Result<()> {
+        if name.is_empty() {
+            return self.writeln(self.state.marks.list());
+        }
+
+        let mark = Mark::new(name, self.main_context().await?.as_ref())?;
+        self.writeln(
+            TitleFormat::action(format!("Marked {name}"))
+                .sub_title(format!("after message #{}", mark.message)),
+        )?;
+        self.state.marks.add(mark);
+        Ok(())
+    }
+
+    /// Returns the context of the main agent in the current conversation
+    async fn main_context(&self) -> Result<Option<forge_api::Context>> {
+        let Some(conversation_id) = self.state.conversation_id.clone() else {
+            return Ok(None);
+        };
+        Ok(self
+            .api
+            .conversation(&conversation_id)
+            .await?
+            .and_then(|conversation| {
+                conversation
+                    .context(&AgentId::new(Conversation::MAIN_AGENT_NAME))
+                    .cloned()
+            }))
+    }
+
+    async fn handle_search(&mut self, query: &str) -> Result<()> {
+        let conversation = match self.state.conversation_id.clone() {
+            Some(conversation_id) => self.api.conversation(&conversation_id).await?,
+            None => None,
+        };
+        let matches = conversation
+            .map(|conversation| search::search(&conversation, query))
+            .unwrap_or_default();
+        let output = search::format(query, &matches)?;
+
+        if !pager::page(&output) {
+            self.writeln(output.trim_end())?;
+        }
+        Ok(())
+    }
+
+    /// Validates the changes of the shadow workspace and promotes them to the
+    /// workspace when the validation passes
+    async fn handle_promote(&mut self) -> Result<()> {
+        let Some(shadow) = self.shadow.as_ref() else {
+            return self.writeln(
+                TitleFormat::error("Not a shadow session")
+                    .sub_title("start forge with --shadow to work in a copy of the workspace"),
+            );
+        };
+        let changes = shadow.changes()?;
+        if changes.is_empty() {
+            return self.writeln(TitleFormat::info("No changes to promote"));
+        }
+
+        let command = self
+            .cli
+            .validate
+            .clone()
+            .or_else(|| detect_build_command(shadow.path()).map(str::to_string));
+        if let Some(command) = command {
+            self.spinner.start(Some("Validating"))?;
+            let output = shadow.validate(&command).await;
+            self.spinner.stop(None)?;
+            let output = output?;
+            if !output.status.success() {
+                self.writeln(
+                    TitleFormat::error("Validation failed, nothing was promoted")
+                        .sub_title(&command),
+                )?;
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return self.writeln(format!("{stdout}{stderr}").trim_end());
+            }
+            self.writeln(TitleFormat::info("Validation passed").sub_title(&command))?;
+        }
+
+        let Some(shadow) = self.shadow.as_mut() else {
+            return Ok(());
+        };
+        let promotion = shadow.promote(&changes)?;
+        for change in promotion.promoted {
+            self.writeln(TitleFormat::info("Promoted").sub_title(change.to_string()))?;
+        }
+        // Conflicting files are left as they are in the workspace, and stay
+        // pending in the shadow workspace until they are resolved
+        for conflict in promotion.conflicts {
+            self.writeln(TitleFormat::error("Conflict").sub_title(format!(
+                "{conflict}, changed in both the workspace and the shadow workspace"
+            )))?;
+            self.writeln(format!(
+                "workspace:\n{}\nshadow workspace:\n{}",
+                conflict.workspace.trim_end(),
+                conflict.shadow.trim_end()
+            ))?;
+        }
+        Ok(())
+    }
+
     /// Modified version of handle_dump that supports HTML format
     async fn handle_dump(&mut self, format: Option<String>) -> Result<()> {
         if let Some(conversation_id) = self.state.conversation_id.clone() {
@@ -527,18 +1660,49 @@ impl<F: API> UI<F> {
                     self.writeln(text)?;
                 }
             }
-            ChatResponse::ToolCallStart(_) => {
+            ChatResponse::Diff(diff) => {
+                let threshold = self
+                    .state
+                    .diff_pager_threshold
+                    .unwrap_or(pager::DEFAULT_DIFF_THRESHOLD);
+                self.spinner.stop(None)?;
+                if !pager::page_above(&diff, threshold) {
+                    self.writeln(diff)?;
+                }
+            }
+            ChatResponse::ToolCallStart(call) => {
                 self.spinner.stop(None)?;
+                self.record_file_usage(&call);
+            }
+            ChatResponse::ToolCallProgress(progress) => {
+                self.spinner.set_message(&format_progress(&progress))?;
             }
             ChatResponse::ToolCallEnd(toolcall_result) => {
+                let name = toolcall_result.name.as_str();
+                if self.cli.verbose || self.renderers.contains(name) {
+                    let output = self.renderers.render(name, &toolcall_result.content);
+                    self.writeln(output)?;
+                }
+
+                // Track the variant of the tool's description, so that the success of
+                // the variants can be compared
+                let variant = ToolOverride::find(
+                    &self.state.tool_overrides,
+                    &toolcall_result.name,
+                    self.state.model.as_ref(),
+                )
+                .and_then(|tool_override| tool_override.variant.clone());
+
                 // Only track toolcall name in case of success else track the error.
                 let payload = if toolcall_result.is_error {
                     ToolCallPayload::new(toolcall_result.name.into_string())
                         .with_cause(toolcall_result.content)
                 } else {
                     ToolCallPayload::new(toolcall_result.name.into_string())
-                };
-                tokio::spawn(TRACKER.dispatch(forge_tracker::EventKind::ToolCall(payload)));
+                }
+                .with_variant(variant)
+                .with_turn_id(self.state.turn_id.as_ref().map(TurnId::into_string));
+                tokio::spawn(TRACKER.dispatch(EventKind::ToolExecuted(payload)));
 
                 self.spinner.start(None)?;
                 if !self.cli.verbose {
@@ -548,6 +1712,23 @@ impl<F: API> UI<F> {
             ChatResponse::Usage(usage) => {
                 self.state.usage = usage;
             }
+            ChatResponse::Output(output) => {
+                let output = serde_json::to_string_pretty(&output)?;
+                if let Some(path) = &self.cli.output {
+                    std::fs::write(path, &output)?;
+                }
+                self.writeln(output)?;
+            }
+            ChatResponse::PatchConflict(conflict) => {
+                self.writeln(
+                    TitleFormat::info("Patch queued")
+                        .sub_title(conflict.path.display().to_string()),
+                )?;
+                self.state.conflicts.push(conflict);
+            }
+            ChatResponse::Warning(warning) => {
+                self.writeln(TitleFormat::info("Degraded capability").sub_title(warning))?;
+            }
         }
         Ok(())
     }
@@ -555,6 +1736,7 @@ impl<F: API> UI<F> {
     async fn dispatch_event(&mut self, event: Event) -> Result<()> {
         let conversation_id = self.init_conversation().await?;
         let chat = ChatRequest::new(event, conversation_id);
+        self.state.turn_id = Some(chat.turn_id.clone());
         match self.api.chat(chat).await {
             Ok(mut stream) => self.handle_chat_stream(&mut stream).await,
             Err(err) => Err(err),
@@ -0,0 +1,111 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use anyhow::Result;
+use forge_api::CommandOutput;
+use forge_walker::Walker;
+
+/// Maximum number of characters of the check output forwarded to the agent
+const MAX_OUTPUT_CHARS: usize = 8_000;
+
+/// Detects changes in the workspace by comparing a fingerprint of the paths,
+/// sizes and modification times of all the files that are not ignored.
+pub struct WorkspaceWatcher {
+    cwd: PathBuf,
+    fingerprint: Option<u64>,
+}
+
+impl WorkspaceWatcher {
+    pub fn new(cwd: PathBuf) -> Self {
+        Self { cwd, fingerprint: None }
+    }
+
+    /// Returns true if the workspace changed since the last poll. The first
+    /// poll always reports a change so that the check runs on startup.
+    pub async fn poll(&mut self) -> Result<bool> {
+        let fingerprint = self.fingerprint().await?;
+        let changed = self.fingerprint != Some(fingerprint);
+        self.fingerprint = Some(fingerprint);
+        Ok(changed)
+    }
+
+    async fn fingerprint(&self) -> Result<u64> {
+        let mut files = Walker::max_all()
+            .cwd(self.cwd.clone())
+            .get()
+            .await?
+            .into_iter()
+            .filter(|file| !file.is_dir())
+            .collect::<Vec<_>>();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut hasher = DefaultHasher::new();
+        for file in files {
+            let modified = std::fs::metadata(self.cwd.join(&file.path))
+                .and_then(|meta| meta.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            file.path.hash(&mut hasher);
+            file.size.hash(&mut hasher);
+            modified.hash(&mut hasher);
+        }
+
+        Ok(hasher.finish())
+    }
+}
+
+/// Builds the task sent to the agent when the watched command fails
+pub fn fix_task(output: &CommandOutput) -> String {
+    let combined = format!("{}\n{}", output.stdout, output.stderr);
+    let combined = strip_ansi_escapes::strip_str(combined.trim());
+    let chars = combined.chars().count();
+    let combined = if chars > MAX_OUTPUT_CHARS {
+        // The end of the output usually contains the summary of the failures
+        combined.chars().skip(chars - MAX_OUTPUT_CHARS).collect()
+    } else {
+        combined
+    };
+
+    format!(
+        "The command `{}` failed while I was editing the code. Investigate the failure below and fix it with the smallest possible change. Do not modify or delete tests to make them pass.\n\n<output>\n{}\n</output>",
+        output.command, combined
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_poll_detects_changes() {
+        let fixture = tempfile::Builder::new().prefix("watch").tempdir().unwrap();
+        std::fs::write(fixture.path().join("a.rs"), "fn main() {}").unwrap();
+        let mut watcher = WorkspaceWatcher::new(fixture.path().to_path_buf());
+
+        let first = watcher.poll().await.unwrap();
+        let unchanged = watcher.poll().await.unwrap();
+        std::fs::write(fixture.path().join("b.rs"), "fn other() {}").unwrap();
+        let changed = watcher.poll().await.unwrap();
+
+        assert_eq!((first, unchanged, changed), (true, false, true));
+    }
+
+    #[test]
+    fn test_fix_task_strips_ansi() {
+        let fixture = CommandOutput {
+            command: "cargo test".to_string(),
+            stdout: "\x1b[31merror\x1b[0m: boom".to_string(),
+            stderr: String::new(),
+            exit_code: Some(101),
+            usage: None,
+        };
+
+        let actual = fix_task(&fixture);
+
+        assert!(actual.contains("`cargo test` failed"));
+        assert!(actual.contains("<output>\nerror: boom\n</output>"));
+    }
+}
@@ -14,7 +14,7 @@ use tracing::{debug, error};
 use super::request::Request;
 use super::response::{EventData, ListModelResponse};
 use crate::retry::StatusCodeRetryPolicy;
-use crate::utils::format_http_context;
+use crate::utils::{correlation_headers, format_http_context};
 
 #[derive(Clone, Builder)]
 pub struct Anthropic {
@@ -71,6 +71,7 @@ impl ProviderService for Anthropic {
         context: Context,
     ) -> ResultStream<ChatCompletionMessage, anyhow::Error> {
         let max_tokens = context.max_tokens.unwrap_or(4000);
+        let turn_id = context.turn_id.clone();
         let request = Request::try_from(context)?
             .model(model.as_str().to_string())
             .stream(true)
@@ -82,6 +83,7 @@ impl ProviderService for Anthropic {
             .client
             .post(url.clone())
             .headers(self.headers())
+            .headers(correlation_headers(turn_id.as_ref()))
             .json(&request)
             .eventsource()
             .context(format_http_context(None, "POST", &url))?;
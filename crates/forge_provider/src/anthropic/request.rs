@@ -3,6 +3,8 @@ use forge_domain::ContextMessage;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use crate::schema::{sanitize, tool_description, SchemaDialect};
+
 #[derive(Serialize, Default, Setters)]
 #[setters(into, strip_option)]
 pub struct Request {
@@ -47,6 +49,14 @@ impl TryFrom<forge_domain::Context> for Request {
             }
         });
 
+        // note: Anthropic doesn't support constraining the response to a schema, so
+        // the schema is described in the system prompt instead.
+        let system = match (system, &request.response_schema) {
+            (Some(system), Some(schema)) => Some(format!("{system}\n\n{}", schema.instructions())),
+            (None, Some(schema)) => Some(schema.instructions()),
+            (system, None) => system,
+        };
+
         Ok(Self {
             messages: request
                 .messages
@@ -304,10 +314,14 @@ impl TryFrom<forge_domain::ToolDefinition> for ToolDefinition {
     type Error = anyhow::Error;
     fn try_from(value: forge_domain::ToolDefinition) -> std::result::Result<Self, Self::Error> {
         Ok(ToolDefinition {
+            input_schema: sanitize(
+                value.name.as_str(),
+                &value.input_schema,
+                SchemaDialect::Anthropic,
+            ),
             name: value.name.into_string(),
-            description: Some(value.description),
+            description: Some(tool_description(value.description)),
             cache_control: None,
-            input_schema: serde_json::to_value(value.input_schema)?,
         })
     }
 }
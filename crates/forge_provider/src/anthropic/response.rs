@@ -23,6 +23,7 @@ impl From<Model> for forge_domain::Model {
             name: Some(value.display_name),
             description: None,
             context_length: None,
+            pricing: None,
         }
     }
 }
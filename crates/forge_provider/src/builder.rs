@@ -2,12 +2,14 @@
 
 use anyhow::{Context as _, Result};
 use forge_domain::{
-    ChatCompletionMessage, Context, Model, ModelId, Provider, ProviderService, ResultStream,
-    RetryConfig,
+    ChatCompletionMessage, Context, Model, ModelId, Parameters, Provider, ProviderService,
+    ResultStream, RetryConfig,
 };
+use tokio_stream::StreamExt;
 
 use crate::anthropic::Anthropic;
 use crate::open_router::OpenRouter;
+use crate::tool_alias::ToolAliases;
 
 pub enum Client {
     OpenAICompat(OpenRouter),
@@ -54,10 +56,20 @@ impl ProviderService for Client {
         model: &ModelId,
         context: Context,
     ) -> ResultStream<ChatCompletionMessage, anyhow::Error> {
-        match self {
-            Client::OpenAICompat(provider) => provider.chat(model, context).await,
-            Client::Anthropic(provider) => provider.chat(model, context).await,
+        // Tools are sent with names that the provider accepts, and the calls
+        // it makes are mapped back to the names of the tools
+        let aliases = ToolAliases::new(&context);
+        let context = aliases.alias_context(context);
+        let stream = match self {
+            Client::OpenAICompat(provider) => provider.chat(model, context).await?,
+            Client::Anthropic(provider) => provider.chat(model, context).await?,
+        };
+        if aliases.is_identity() {
+            return Ok(stream);
         }
+        Ok(Box::pin(stream.map(move |message| {
+            message.map(|message| aliases.resolve(message))
+        })))
     }
 
     async fn models(&self) -> anyhow::Result<Vec<Model>> {
@@ -66,4 +78,11 @@ impl ProviderService for Client {
             Client::Anthropic(provider) => provider.models().await,
         }
     }
+
+    async fn parameters(&self, model: &ModelId) -> anyhow::Result<Option<Parameters>> {
+        match self {
+            Client::OpenAICompat(provider) => provider.parameters(model).await,
+            Client::Anthropic(provider) => provider.parameters(model).await,
+        }
+    }
 }
@@ -0,0 +1,261 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{Context as _, Result};
+use forge_domain::{ChatCompletionMessage, Context, Model, ModelId, ProviderService, ResultStream};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio_stream::StreamExt;
+
+/// Placeholder that replaces secrets in recorded cassettes
+const REDACTED: &str = "[REDACTED]";
+
+/// A single request/response pair exchanged with the provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub model: ModelId,
+    pub request: Context,
+    pub response: Vec<ChatCompletionMessage>,
+}
+
+/// A recording of the interactions with a provider, stored as JSON so that it
+/// can be reviewed and committed alongside the tests that replay it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn interaction(mut self, interaction: Interaction) -> Self {
+        self.interactions.push(interaction);
+        self
+    }
+
+    /// Loads a cassette, decrypting it when it was written encrypted, eg: by
+    /// the [`crate::Inspector`]
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read(path)
+            .map_err(anyhow::Error::from)
+            .and_then(forge_fs::ForgeFS::unseal)
+            .with_context(|| format!("Failed to read cassette: {}", path.display()))?;
+        serde_json::from_slice(&content)
+            .with_context(|| format!("Failed to parse cassette: {}", path.display()))
+    }
+
+    /// Serializes the cassette replacing every secret with a placeholder
+    pub(crate) fn to_scrubbed_json(&self, secrets: &[String]) -> Result<String> {
+        let mut json = serde_json::to_string_pretty(self)?;
+        for secret in secrets.iter().filter(|secret| !secret.is_empty()) {
+            json = json.replace(secret.as_str(), REDACTED);
+        }
+        Ok(json)
+    }
+}
+
+/// Wraps a provider and appends every interaction to a cassette on disk.
+/// Responses are buffered completely before being handed back to the caller,
+/// so streaming behaviour isn't preserved while recording.
+pub struct Recorder<P> {
+    inner: P,
+    path: PathBuf,
+    secrets: Vec<String>,
+    cassette: Mutex<Cassette>,
+}
+
+impl<P> Recorder<P> {
+    /// Creates a recorder that writes to `path`. All `secrets` (eg: API keys)
+    /// are scrubbed from the cassette before it is written.
+    pub fn new(inner: P, path: impl Into<PathBuf>, secrets: Vec<String>) -> Self {
+        Self {
+            inner,
+            path: path.into(),
+            secrets,
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+
+    async fn record(&self, interaction: Interaction) -> Result<()> {
+        let mut cassette = self.cassette.lock().await;
+        cassette.interactions.push(interaction);
+        let json = cassette.to_scrubbed_json(&self.secrets)?;
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&self.path, json)
+            .await
+            .with_context(|| format!("Failed to write cassette: {}", self.path.display()))
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: ProviderService> ProviderService for Recorder<P> {
+    async fn chat(
+        &self,
+        model: &ModelId,
+        context: Context,
+    ) -> ResultStream<ChatCompletionMessage, anyhow::Error> {
+        let request = context.clone();
+        let mut stream = self.inner.chat(model, context).await?;
+
+        let mut response = Vec::new();
+        while let Some(message) = stream.next().await {
+            response.push(message?);
+        }
+
+        self.record(Interaction { model: model.clone(), request, response: response.clone() })
+            .await?;
+
+        Ok(Box::pin(tokio_stream::iter(response.into_iter().map(Ok))))
+    }
+
+    async fn models(&self) -> Result<Vec<Model>> {
+        self.inner.models().await
+    }
+}
+
+/// Serves the responses of a cassette in the order they were recorded,
+/// without contacting the provider
+pub struct Replayer {
+    cassette: Cassette,
+    cursor: AtomicUsize,
+}
+
+impl Replayer {
+    pub fn new(cassette: Cassette) -> Self {
+        Self { cassette, cursor: AtomicUsize::new(0) }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self::new(Cassette::load(path)?))
+    }
+}
+
+#[async_trait::async_trait]
+impl ProviderService for Replayer {
+    async fn chat(
+        &self,
+        model: &ModelId,
+        _context: Context,
+    ) -> ResultStream<ChatCompletionMessage, anyhow::Error> {
+        let index = self.cursor.fetch_add(1, Ordering::SeqCst);
+        let interaction = self.cassette.interactions.get(index).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Cassette exhausted: request #{} was not recorded",
+                index + 1
+            )
+        })?;
+
+        if &interaction.model != model {
+            anyhow::bail!(
+                "Cassette mismatch for request #{}: recorded model '{}' but got '{}'",
+                index + 1,
+                interaction.model,
+                model
+            );
+        }
+
+        let response = interaction.response.clone();
+        Ok(Box::pin(tokio_stream::iter(response.into_iter().map(Ok))))
+    }
+
+    async fn models(&self) -> Result<Vec<Model>> {
+        let mut seen = HashSet::new();
+        Ok(self
+            .cassette
+            .interactions
+            .iter()
+            .filter(|interaction| seen.insert(interaction.model.clone()))
+            .map(|interaction| Model {
+                id: interaction.model.clone(),
+                name: None,
+                description: None,
+                context_length: None,
+                pricing: None,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn interaction(model: &str, text: &str) -> Interaction {
+        Interaction {
+            model: ModelId::new(model),
+            request: Context::default(),
+            response: vec![ChatCompletionMessage::assistant(
+                forge_domain::Content::full(text),
+            )],
+        }
+    }
+
+    async fn collect(provider: &impl ProviderService, model: &str) -> Result<Vec<String>> {
+        let stream = provider
+            .chat(&ModelId::new(model), Context::default())
+            .await?;
+        let messages = stream.collect::<Result<Vec<_>>>().await?;
+        Ok(messages
+            .into_iter()
+            .filter_map(|message| message.content.map(|content| content.as_str().to_string()))
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn test_replay_in_order() {
+        let fixture = Replayer::new(
+            Cassette::default()
+                .interaction(interaction("model", "first"))
+                .interaction(interaction("model", "second")),
+        );
+
+        let actual = vec![
+            collect(&fixture, "model").await.unwrap(),
+            collect(&fixture, "model").await.unwrap(),
+        ];
+        let expected = vec![vec!["first".to_string()], vec!["second".to_string()]];
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_replay_exhausted() {
+        let fixture = Replayer::new(Cassette::default());
+
+        let actual = collect(&fixture, "model").await;
+
+        assert!(actual.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_replay_model_mismatch() {
+        let fixture = Replayer::new(Cassette::default().interaction(interaction("a", "text")));
+
+        let actual = collect(&fixture, "b").await;
+
+        assert!(actual.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_record_scrubs_secrets() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("cassette.json");
+        let inner = Replayer::new(
+            Cassette::default().interaction(interaction("model", "key is sk-secret")),
+        );
+        let fixture = Recorder::new(inner, &path, vec!["sk-secret".to_string()]);
+
+        let actual = collect(&fixture, "model").await.unwrap();
+        let recorded = Cassette::load(&path).unwrap();
+
+        assert_eq!(actual, vec!["key is sk-secret".to_string()]);
+        assert_eq!(
+            recorded.interactions[0].response,
+            vec![ChatCompletionMessage::assistant(
+                forge_domain::Content::full("key is [REDACTED]")
+            )]
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use forge_domain::{ChatCompletionMessage, Context, Model, ModelId, ProviderService, ResultStream};
+use forge_fs::ForgeFS;
+use tokio_stream::StreamExt;
+use tracing::warn;
+
+use crate::cassette::{Cassette, Interaction};
+
+/// Keeps the last interactions with a provider in a file, so that the requests
+/// that were sent and the responses that were received can be inspected, eg:
+/// with `/debug last-request`. Unlike the [`crate::Recorder`], responses are
+/// still streamed, and an interaction is written once its stream ends.
+pub struct Inspector<P> {
+    inner: P,
+    log: Arc<InspectionLog>,
+}
+
+impl<P> Inspector<P> {
+    /// Creates an inspector that keeps the last `limit` interactions in
+    /// `path`. All `secrets` (eg: API keys) are scrubbed from the file.
+    pub fn new(inner: P, path: impl Into<PathBuf>, secrets: Vec<String>, limit: usize) -> Self {
+        let log = InspectionLog {
+            path: path.into(),
+            secrets,
+            limit,
+            cassette: Mutex::new(Cassette::default()),
+            pending: Mutex::new(None),
+            writing: tokio::sync::Mutex::new(()),
+        };
+        Self { inner, log: Arc::new(log) }
+    }
+}
+
+struct InspectionLog {
+    path: PathBuf,
+    secrets: Vec<String>,
+    limit: usize,
+    cassette: Mutex<Cassette>,
+    /// Content of the file that is yet to be written
+    pending: Mutex<Option<String>>,
+    /// Held while the file is written, so that writes don't overlap
+    writing: tokio::sync::Mutex<()>,
+}
+
+impl InspectionLog {
+    /// Appends the interaction, forgetting the oldest ones past the limit, and
+    /// writes the file in the background, since interactions are recorded
+    /// when their stream is dropped. Failing to write the file doesn't fail
+    /// the request.
+    fn record(self: &Arc<Self>, interaction: Interaction) {
+        let json = {
+            let mut cassette = self.cassette.lock().unwrap();
+            cassette.interactions.push(interaction);
+            let excess = cassette.interactions.len().saturating_sub(self.limit);
+            cassette.interactions.drain(..excess);
+            cassette.to_scrubbed_json(&self.secrets)
+        };
+
+        let result = json.and_then(|json| Ok((json, tokio::runtime::Handle::try_current()?)));
+        match result {
+            Ok((json, runtime)) => {
+                *self.pending.lock().unwrap() = Some(json);
+                runtime.spawn(self.clone().flush());
+            }
+            Err(error) => {
+                warn!(path = %self.path.display(), error = ?error, "Failed to write the inspected requests");
+            }
+        }
+    }
+
+    /// Writes the most recent content of the file, encrypted like the other
+    /// files that forge persists. A write that finds no pending content was
+    /// preceded by one that wrote its content already.
+    async fn flush(self: Arc<Self>) {
+        let _writing = self.writing.lock().await;
+        let Some(json) = self.pending.lock().unwrap().take() else {
+            return;
+        };
+
+        let result = async {
+            if let Some(parent) = self.path.parent() {
+                ForgeFS::create_dir_all(parent).await?;
+            }
+            ForgeFS::write_sealed(&self.path, json).await
+        }
+        .await;
+        if let Err(error) = result {
+            warn!(path = %self.path.display(), error = ?error, "Failed to write the inspected requests");
+        }
+    }
+}
+
+/// Interaction whose response is being streamed. It is recorded when dropped,
+/// ie: once the stream ended or was abandoned.
+struct PendingInteraction {
+    log: Arc<InspectionLog>,
+    interaction: Option<Interaction>,
+}
+
+impl Drop for PendingInteraction {
+    fn drop(&mut self) {
+        if let Some(interaction) = self.interaction.take() {
+            self.log.record(interaction);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: ProviderService> ProviderService for Inspector<P> {
+    async fn chat(
+        &self,
+        model: &ModelId,
+        context: Context,
+    ) -> ResultStream<ChatCompletionMessage, anyhow::Error> {
+        // A request that fails before responding is recorded too, without
+        // response
+        let mut pending = PendingInteraction {
+            log: self.log.clone(),
+            interaction: Some(Interaction {
+                model: model.clone(),
+                request: context.clone(),
+                response: Vec::new(),
+            }),
+        };
+        let stream = self.inner.chat(model, context).await?;
+
+        Ok(Box::pin(stream.map(move |message| {
+            if let (Ok(message), Some(interaction)) = (&message, pending.interaction.as_mut()) {
+                interaction.response.push(message.clone());
+            }
+            message
+        })))
+    }
+
+    async fn models(&self) -> Result<Vec<Model>> {
+        self.inner.models().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::Replayer;
+
+    fn interaction(text: &str) -> Interaction {
+        Interaction {
+            model: ModelId::new("model"),
+            request: Context::default(),
+            response: vec![ChatCompletionMessage::assistant(
+                forge_domain::Content::full(text),
+            )],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keeps_the_last_interactions() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("last_requests.json");
+        let inner = Replayer::new(
+            Cassette::default()
+                .interaction(interaction("first"))
+                .interaction(interaction("second with sk-secret"))
+                .interaction(interaction("third")),
+        );
+        let fixture = Inspector::new(inner, &path, vec!["sk-secret".to_string()], 2);
+
+        for _ in 0..3 {
+            let stream = fixture
+                .chat(&ModelId::new("model"), Context::default())
+                .await
+                .unwrap();
+            stream.collect::<Result<Vec<_>>>().await.unwrap();
+        }
+
+        // The file is written in the background
+        while fixture.log.pending.lock().unwrap().is_some() {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        drop(fixture.log.writing.lock().await);
+
+        let actual = Cassette::load(&path).unwrap().interactions;
+        let expected = vec![interaction("second with [REDACTED]"), interaction("third")];
+        assert_eq!(actual, expected);
+    }
+}
@@ -1,8 +1,14 @@
 mod anthropic;
 mod builder;
+mod cassette;
+mod inspector;
 mod open_router;
 mod retry;
+mod schema;
+mod tool_alias;
 mod utils;
 
 // Re-export from builder.rs
 pub use builder::Client;
+pub use cassette::*;
+pub use inspector::Inspector;
@@ -3,6 +3,7 @@ mod model;
 mod parameters;
 mod request;
 mod response;
+mod server;
 mod tool_choice;
 mod transformers;
 
@@ -29,6 +29,17 @@ pub struct Pricing {
     pub request: Option<String>,
 }
 
+impl From<Pricing> for forge_domain::Pricing {
+    fn from(value: Pricing) -> Self {
+        // Prices are sent as strings of USD per token, eg: "0.000003"
+        let parse = |price: Option<String>| price.and_then(|price| price.parse().ok());
+        Self {
+            prompt: parse(value.prompt),
+            completion: parse(value.completion),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TopProvider {
     pub context_length: Option<u64>,
@@ -3,8 +3,8 @@ use std::time::Duration;
 use anyhow::{Context as _, Result};
 use derive_builder::Builder;
 use forge_domain::{
-    self, ChatCompletionMessage, Context as ChatContext, Model, ModelId, Provider, ProviderService,
-    ResultStream, RetryConfig,
+    self, ChatCompletionMessage, Context as ChatContext, Model, ModelId, Parameters, Provider,
+    ProviderService, ResultStream, RetryConfig,
 };
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
 use reqwest::{Client, Url};
@@ -15,9 +15,10 @@ use tracing::debug;
 use super::model::{ListModelResponse, OpenRouterModel};
 use super::request::OpenRouterRequest;
 use super::response::OpenRouterResponse;
+use super::server::{ListServerModelResponse, LlamaCppProps, Server};
 use crate::open_router::transformers::{ProviderPipeline, Transformer};
 use crate::retry::StatusCodeRetryPolicy;
-use crate::utils::format_http_context;
+use crate::utils::{correlation_headers, format_http_context};
 
 #[derive(Clone, Builder)]
 pub struct OpenRouter {
@@ -75,6 +76,7 @@ impl OpenRouter {
         model: &ModelId,
         context: ChatContext,
     ) -> ResultStream<ChatCompletionMessage, anyhow::Error> {
+        let turn_id = context.turn_id.clone();
         let mut request = OpenRouterRequest::from(context)
             .model(model.clone())
             .stream(true);
@@ -94,6 +96,7 @@ impl OpenRouter {
             .client
             .post(url.clone())
             .headers(self.headers())
+            .headers(correlation_headers(turn_id.as_ref()))
             .json(&request)
             .eventsource()
             .context(format_http_context(None, "POST", &url))?;
@@ -181,6 +184,103 @@ impl OpenRouter {
         }
     }
 
+    /// Probes the capabilities of self-hosted vLLM and llama.cpp servers.
+    /// Hosted providers are left to the configuration.
+    async fn inner_parameters(&self, model: &ModelId) -> Result<Option<Parameters>> {
+        if self.provider.is_antinomy()
+            || self.provider.is_open_router()
+            || self.provider.is_open_ai()
+        {
+            return Ok(None);
+        }
+
+        let url = self.url("models")?;
+        let response = self.fetch_models(url.clone()).await?;
+        let data: ListServerModelResponse = serde_json::from_str(&response)
+            .context(format_http_context(None, "GET", &url))
+            .context("Failed to deserialize models response")?;
+        let Some(server_model) = data.data.into_iter().find(|entry| &entry.id == model) else {
+            return Ok(None);
+        };
+
+        let parameters = match Server::detect(&server_model) {
+            Some(Server::VLlm) => {
+                let mut parameters = Parameters::new(self.probe_tool_calls(model).await?);
+                parameters.context_length = server_model.max_model_len;
+                parameters
+            }
+            Some(Server::LlamaCpp) => self.fetch_props().await?.parameters(&server_model),
+            None => return Ok(None),
+        };
+        debug!(model = %model, parameters = ?parameters, "Probed server capabilities");
+        Ok(Some(parameters))
+    }
+
+    /// Sends a minimal request with a tool definition. vLLM rejects it unless
+    /// it was started with tool calling enabled.
+    async fn probe_tool_calls(&self, model: &ModelId) -> Result<bool> {
+        let url = self.url("chat/completions")?;
+        let request = serde_json::json!({
+            "model": model,
+            "messages": [{ "role": "user", "content": "ping" }],
+            "tools": [{
+                "type": "function",
+                "function": {
+                    "name": "ping",
+                    "description": "Replies to a ping",
+                    "parameters": { "type": "object", "properties": {} }
+                }
+            }],
+            "tool_choice": "auto",
+            "max_tokens": 1
+        });
+        let response = self
+            .client
+            .post(url.clone())
+            .headers(self.headers())
+            .json(&request)
+            .send()
+            .await
+            .context(format_http_context(None, "POST", &url))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(true);
+        }
+        if status == reqwest::StatusCode::BAD_REQUEST {
+            debug!(body = ?response.text().await.ok(), "Tool calling is not enabled on the server");
+            return Ok(false);
+        }
+        Err(
+            anyhow::anyhow!("Failed to probe tool calling support").context(format_http_context(
+                Some(status),
+                "POST",
+                &url,
+            )),
+        )
+    }
+
+    /// Fetches the server properties of llama.cpp, which are served outside
+    /// of the OpenAI compatible API
+    async fn fetch_props(&self) -> Result<LlamaCppProps> {
+        let url = self.provider.to_base_url().join("/props")?;
+        let response = self
+            .client
+            .get(url.clone())
+            .headers(self.headers())
+            .send()
+            .await
+            .context(format_http_context(None, "GET", &url))?;
+        let ctx_message = format_http_context(Some(response.status()), "GET", &url);
+        response
+            .error_for_status()
+            .context(ctx_message.clone())?
+            .json()
+            .await
+            .context(ctx_message)
+            .context("Failed to deserialize server properties")
+    }
+
     async fn fetch_models(&self, url: Url) -> Result<String, anyhow::Error> {
         match self
             .client
@@ -225,6 +325,10 @@ impl ProviderService for OpenRouter {
     async fn models(&self) -> Result<Vec<Model>> {
         self.inner_models().await
     }
+
+    async fn parameters(&self, model: &ModelId) -> Result<Option<Parameters>> {
+        self.inner_parameters(model).await
+    }
 }
 
 impl From<OpenRouterModel> for Model {
@@ -234,6 +338,7 @@ impl From<OpenRouterModel> for Model {
             name: value.name,
             description: value.description,
             context_length: value.context_length,
+            pricing: value.pricing.map(Into::into),
         }
     }
 }
@@ -1,12 +1,14 @@
 use derive_more::derive::Display;
 use derive_setters::Setters;
 use forge_domain::{
-    Context, ContextMessage, ModelId, Role, ToolCallFull, ToolCallId, ToolDefinition, ToolName,
+    Context, ContextMessage, ModelId, ResponseSchema, Role, ToolCallFull, ToolCallId,
+    ToolDefinition, ToolName,
 };
 use serde::{Deserialize, Serialize};
 
 use super::response::{FunctionCall, OpenRouterToolCall};
 use super::tool_choice::{FunctionType, ToolChoice};
+use crate::schema::{sanitize, tool_description, SchemaDialect};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TextContent {
@@ -116,6 +118,26 @@ pub struct OpenRouterTool {
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ResponseFormat {
     pub r#type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<JsonSchemaFormat>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JsonSchemaFormat {
+    pub name: String,
+    pub schema: serde_json::Value,
+}
+
+impl From<ResponseSchema> for ResponseFormat {
+    fn from(value: ResponseSchema) -> Self {
+        ResponseFormat {
+            r#type: "json_schema".to_string(),
+            json_schema: Some(JsonSchemaFormat {
+                name: "response".to_string(),
+                schema: value.as_value().clone(),
+            }),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -219,9 +241,13 @@ impl From<ToolDefinition> for OpenRouterTool {
         OpenRouterTool {
             r#type: FunctionType,
             function: FunctionDescription {
-                description: Some(value.description),
+                parameters: sanitize(
+                    value.name.as_str(),
+                    &value.input_schema,
+                    SchemaDialect::OpenAi,
+                ),
+                description: Some(tool_description(value.description)),
                 name: value.name.into_string(),
-                parameters: serde_json::to_value(value.input_schema).unwrap(),
             },
         }
     }
@@ -253,7 +279,7 @@ impl From<Context> for OpenRouterRequest {
             },
             model: None,
             prompt: Default::default(),
-            response_format: Default::default(),
+            response_format: request.response_schema.map(ResponseFormat::from),
             stop: Default::default(),
             stream: Default::default(),
             max_tokens: request.max_tokens.map(|t| t as u32),
@@ -455,6 +481,21 @@ mod tests {
         assert_json_snapshot!(router_message);
     }
 
+    #[test]
+    fn test_response_schema_conversion() {
+        let schema = json!({"type": "object", "required": ["summary"]});
+        let context = Context::default().response_schema(ResponseSchema::new(schema.clone()));
+
+        let request = OpenRouterRequest::from(context);
+
+        let actual = serde_json::to_value(request.response_format).unwrap();
+        let expected = json!({
+            "type": "json_schema",
+            "json_schema": {"name": "response", "schema": schema}
+        });
+        assert_eq!(actual, expected);
+    }
+
     #[test]
     fn test_transform_display() {
         assert_eq!(
@@ -0,0 +1,139 @@
+use forge_domain::{ModelId, Parameters};
+use serde::Deserialize;
+
+/// Inference servers that are commonly self-hosted behind an OpenAI
+/// compatible API
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Server {
+    VLlm,
+    LlamaCpp,
+}
+
+impl Server {
+    /// Identifies the server from the owner it reports for its models
+    pub fn detect(model: &ServerModel) -> Option<Self> {
+        match model.owned_by.as_deref()? {
+            "vllm" => Some(Server::VLlm),
+            "llamacpp" => Some(Server::LlamaCpp),
+            _ => None,
+        }
+    }
+}
+
+/// Model as listed by a self-hosted server, including the server specific
+/// fields that aren't part of the OpenAI API
+#[derive(Debug, Deserialize)]
+pub struct ServerModel {
+    pub id: ModelId,
+    pub owned_by: Option<String>,
+    /// Context length the model was loaded with, reported by vLLM
+    pub max_model_len: Option<u64>,
+    /// Model metadata, reported by llama.cpp
+    pub meta: Option<LlamaCppMeta>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LlamaCppMeta {
+    pub n_ctx_train: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListServerModelResponse {
+    pub data: Vec<ServerModel>,
+}
+
+/// Response of llama.cpp's `/props` endpoint
+#[derive(Debug, Default, Deserialize)]
+pub struct LlamaCppProps {
+    pub default_generation_settings: Option<GenerationSettings>,
+    /// Capabilities of the chat template, only reported when the server runs
+    /// with `--jinja`
+    pub chat_template_caps: Option<ChatTemplateCaps>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GenerationSettings {
+    pub n_ctx: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatTemplateCaps {
+    pub supports_tool_calls: Option<bool>,
+}
+
+impl LlamaCppProps {
+    /// Builds the parameters of the model, falling back to the context length
+    /// the model was trained with when the server doesn't report its own
+    pub fn parameters(&self, model: &ServerModel) -> Parameters {
+        let tool_supported = self
+            .chat_template_caps
+            .as_ref()
+            .and_then(|caps| caps.supports_tool_calls)
+            .unwrap_or(false);
+        let context_length = self
+            .default_generation_settings
+            .as_ref()
+            .and_then(|settings| settings.n_ctx)
+            .or(model.meta.as_ref().and_then(|meta| meta.n_ctx_train));
+
+        Parameters { tool_supported, context_length }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    fn fixture_models(owned_by: &str) -> ListServerModelResponse {
+        serde_json::from_value(json!({
+            "object": "list",
+            "data": [{
+                "id": "qwen2.5-coder",
+                "object": "model",
+                "owned_by": owned_by,
+                "max_model_len": 32768,
+                "meta": { "n_ctx_train": 131072 }
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_detect_server() {
+        let actual = ["vllm", "llamacpp", "openai"]
+            .map(|owner| Server::detect(&fixture_models(owner).data[0]));
+
+        let expected = [Some(Server::VLlm), Some(Server::LlamaCpp), None];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_llama_cpp_parameters() {
+        let models = fixture_models("llamacpp");
+        let fixture: LlamaCppProps = serde_json::from_value(json!({
+            "default_generation_settings": { "n_ctx": 8192 },
+            "chat_template": "{% for message in messages %}{% endfor %}",
+            "chat_template_caps": { "supports_tool_calls": true }
+        }))
+        .unwrap();
+
+        let actual = fixture.parameters(&models.data[0]);
+
+        let expected = Parameters::new(true).context_length(8192u64);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_llama_cpp_parameters_without_jinja() {
+        let models = fixture_models("llamacpp");
+        let fixture = LlamaCppProps::default();
+
+        let actual = fixture.parameters(&models.data[0]);
+
+        let expected = Parameters::new(false).context_length(131072u64);
+        assert_eq!(actual, expected);
+    }
+}
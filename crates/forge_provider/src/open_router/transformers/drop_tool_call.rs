@@ -61,6 +61,8 @@ mod tests {
             tool_choice: None,
             max_tokens: None,
             temperature: None,
+            response_schema: None,
+            turn_id: None,
         };
 
         let request = OpenRouterRequest::from(context);
@@ -0,0 +1,245 @@
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use tracing::warn;
+
+/// Maximum number of characters of a description within a schema
+const MAX_DESCRIPTION_CHARS: usize = 1024;
+
+/// Maximum number of characters of the description of a tool
+const MAX_TOOL_DESCRIPTION_CHARS: usize = 16 * 1024;
+
+/// Maximum size of a schema once sanitized, past which the tool is sent with
+/// a schema that accepts any object
+const MAX_SCHEMA_BYTES: usize = 64 * 1024;
+
+/// Number of nested references that are inlined, so that recursive schemas
+/// stay finite. Deeper references accept any value.
+const MAX_REF_DEPTH: usize = 4;
+
+/// JSON schema dialect of a provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SchemaDialect {
+    OpenAi,
+    Anthropic,
+}
+
+impl SchemaDialect {
+    /// Keywords that the provider rejects, or that only make schemas larger
+    fn unsupported_keywords(&self) -> &'static [&'static str] {
+        match self {
+            SchemaDialect::OpenAi => &["$schema", "$id", "$comment", "examples"],
+            SchemaDialect::Anthropic => &["$schema", "$id", "$comment"],
+        }
+    }
+}
+
+/// Caps the description of a tool
+pub(crate) fn tool_description(description: String) -> String {
+    truncate(description, MAX_TOOL_DESCRIPTION_CHARS)
+}
+
+/// Converts the input schema of a tool to one that the provider accepts:
+/// references are inlined, unsupported keywords removed and descriptions
+/// capped. A schema that can't be converted, or that remains too large, is
+/// replaced with one that accepts any object, so that a single bad tool
+/// doesn't fail the whole request.
+pub(crate) fn sanitize(tool: &str, schema: &impl Serialize, dialect: SchemaDialect) -> Value {
+    let value = match serde_json::to_value(schema) {
+        Ok(value) => value,
+        Err(error) => {
+            warn!(tool = tool, error = ?error, "Failed to convert the schema of the tool");
+            return any_object();
+        }
+    };
+
+    let definitions = ["definitions", "$defs"]
+        .iter()
+        .filter_map(|key| value.get(key)?.as_object())
+        .flatten()
+        .map(|(name, schema)| (name.clone(), schema.clone()))
+        .collect::<Map<_, _>>();
+    let sanitized = Sanitizer { definitions, dialect }.schema(value, 0);
+
+    let size = sanitized.to_string().len();
+    if size > MAX_SCHEMA_BYTES {
+        warn!(
+            tool = tool,
+            size = size,
+            "The schema of the tool is too large, sending it without"
+        );
+        return any_object();
+    }
+    sanitized
+}
+
+fn any_object() -> Value {
+    json!({ "type": "object" })
+}
+
+fn truncate(text: String, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+struct Sanitizer {
+    definitions: Map<String, Value>,
+    dialect: SchemaDialect,
+}
+
+impl Sanitizer {
+    fn schema(&self, schema: Value, mut depth: usize) -> Value {
+        // Boolean schemas have nothing to sanitize
+        let Value::Object(mut object) = schema else {
+            return schema;
+        };
+
+        // The keywords next to a reference take precedence over the ones of the
+        // schema it points at. A reference that can't be resolved is dropped,
+        // leaving the value unconstrained.
+        if let Some(Value::String(reference)) = object.remove("$ref") {
+            let name = reference.rsplit('/').next().unwrap_or_default();
+            if let Some(Value::Object(target)) = self.definitions.get(name) {
+                if depth < MAX_REF_DEPTH {
+                    for (key, value) in target {
+                        object.entry(key.clone()).or_insert_with(|| value.clone());
+                    }
+                    depth += 1;
+                }
+            }
+        }
+
+        let unsupported = self.dialect.unsupported_keywords();
+        object
+            .into_iter()
+            .filter(|(key, _)| {
+                !unsupported.contains(&key.as_str()) && key != "definitions" && key != "$defs"
+            })
+            .map(|(key, value)| {
+                let value = match (key.as_str(), value) {
+                    ("description", Value::String(description)) => {
+                        Value::String(truncate(description, MAX_DESCRIPTION_CHARS))
+                    }
+                    // Maps of schemas, whose keys are names rather than keywords
+                    ("properties" | "patternProperties", Value::Object(properties)) => {
+                        Value::Object(
+                            properties
+                                .into_iter()
+                                .map(|(name, schema)| (name, self.schema(schema, depth)))
+                                .collect(),
+                        )
+                    }
+                    (
+                        "items"
+                        | "additionalProperties"
+                        | "additionalItems"
+                        | "contains"
+                        | "propertyNames"
+                        | "not"
+                        | "if"
+                        | "then"
+                        | "else"
+                        | "anyOf"
+                        | "allOf"
+                        | "oneOf"
+                        | "prefixItems",
+                        value,
+                    ) => self.schemas(value, depth),
+                    // Other keywords hold data, eg: `enum` or `required`
+                    (_, value) => value,
+                };
+                (key, value)
+            })
+            .collect::<Map<_, _>>()
+            .into()
+    }
+
+    /// Sanitizes a schema, or each schema of a list
+    fn schemas(&self, value: Value, depth: usize) -> Value {
+        match value {
+            Value::Array(schemas) => schemas
+                .into_iter()
+                .map(|schema| self.schema(schema, depth))
+                .collect(),
+            schema => self.schema(schema, depth),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_sanitize_inlines_references() {
+        let fixture = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "Node",
+            "type": "object",
+            "properties": {
+                "name": { "description": "Name of the node", "type": "string" },
+                "children": { "type": "array", "items": { "$ref": "#/definitions/Node" } },
+                "kind": { "$ref": "#/definitions/Kind" }
+            },
+            "definitions": {
+                "Kind": { "type": "string", "enum": ["File", "Directory"] },
+                "Node": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "description": "Name of the node", "type": "string" },
+                        "children": { "type": "array", "items": { "$ref": "#/definitions/Node" } }
+                    }
+                }
+            }
+        });
+
+        let actual = sanitize("tree", &fixture, SchemaDialect::Anthropic);
+
+        assert_eq!(actual.get("$schema"), None);
+        assert_eq!(actual.get("definitions"), None);
+        assert_eq!(
+            actual["properties"]["kind"],
+            json!({ "type": "string", "enum": ["File", "Directory"] })
+        );
+        assert_eq!(
+            actual["properties"]["children"]["items"]["properties"]["name"]["description"],
+            json!("Name of the node")
+        );
+        assert!(!actual.to_string().contains("$ref"));
+    }
+
+    #[test]
+    fn test_sanitize_caps_descriptions() {
+        let fixture = json!({
+            "type": "object",
+            "properties": {
+                "examples": { "type": "string", "description": "a".repeat(2000), "examples": ["x"] }
+            }
+        });
+
+        let actual = sanitize("tool", &fixture, SchemaDialect::OpenAi);
+
+        let expected = json!({
+            "type": "object",
+            "properties": {
+                "examples": { "type": "string", "description": format!("{}…", "a".repeat(MAX_DESCRIPTION_CHARS)) }
+            }
+        });
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_sanitize_replaces_large_schemas() {
+        let properties = (0..5000)
+            .map(|i| (format!("property_{i}"), json!({ "type": "string" })))
+            .collect::<Map<_, _>>();
+        let fixture = json!({ "type": "object", "properties": properties });
+
+        let actual = sanitize("tool", &fixture, SchemaDialect::OpenAi);
+
+        assert_eq!(actual, any_object());
+    }
+}
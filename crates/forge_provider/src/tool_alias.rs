@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+use forge_domain::{
+    ChatCompletionMessage, Context, ContextMessage, ToolCall, ToolChoice, ToolName,
+};
+
+/// Maximum length of a tool name that providers accept
+const MAX_TOOL_NAME_CHARS: usize = 64;
+
+/// Names that tools are sent to the provider with. Providers only accept
+/// names of at most 64 letters, digits, `_` and `-`, so namespaced names such
+/// as `mcp:github:create_issue` are sent as aliases, eg:
+/// `mcp_github_create_issue`, and the calls in the response are mapped back.
+#[derive(Debug, Default)]
+pub(crate) struct ToolAliases {
+    aliases: HashMap<ToolName, ToolName>,
+    names: HashMap<ToolName, ToolName>,
+}
+
+impl ToolAliases {
+    /// Gives each tool of the context a distinct alias. Tools whose names are
+    /// valid keep them, and an alias that is already taken gets a numbered
+    /// suffix.
+    pub(crate) fn new(context: &Context) -> Self {
+        let (valid, invalid): (Vec<_>, Vec<_>) = context
+            .tools
+            .iter()
+            .map(|tool| &tool.name)
+            .partition(|name| sanitize(name.as_str()) == name.as_str());
+
+        let mut aliases = Self::default();
+        for name in valid.into_iter().chain(invalid) {
+            let base = sanitize(name.as_str());
+            let mut alias = ToolName::new(&base);
+            let mut n = 1;
+            while aliases.names.contains_key(&alias) {
+                n += 1;
+                let suffix = format!("_{n}");
+                let prefix = base
+                    .chars()
+                    .take(MAX_TOOL_NAME_CHARS - suffix.len())
+                    .collect::<String>();
+                alias = ToolName::new(prefix + &suffix);
+            }
+            aliases.names.insert(alias.clone(), name.clone());
+            aliases.aliases.insert(name.clone(), alias);
+        }
+        aliases
+    }
+
+    /// Whether every tool is sent with its own name
+    pub(crate) fn is_identity(&self) -> bool {
+        self.aliases.iter().all(|(name, alias)| name == alias)
+    }
+
+    /// Alias of a tool. Tools that aren't part of the context anymore, eg:
+    /// in earlier messages, are sent with a valid name too.
+    fn alias(&self, name: &ToolName) -> ToolName {
+        self.aliases
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| ToolName::new(sanitize(name.as_str())))
+    }
+
+    /// Name of the tool that an alias was given to
+    fn name(&self, alias: &ToolName) -> ToolName {
+        self.names
+            .get(alias)
+            .cloned()
+            .unwrap_or_else(|| alias.clone())
+    }
+
+    /// Replaces the names of the tools, and of their calls and results, with
+    /// their aliases
+    pub(crate) fn alias_context(&self, mut context: Context) -> Context {
+        for tool in &mut context.tools {
+            tool.name = self.alias(&tool.name);
+        }
+        if let Some(ToolChoice::Call(name)) = &mut context.tool_choice {
+            *name = self.alias(name);
+        }
+        for message in &mut context.messages {
+            match message {
+                ContextMessage::ContentMessage(message) => {
+                    for call in message.tool_calls.iter_mut().flatten() {
+                        call.name = self.alias(&call.name);
+                    }
+                }
+                ContextMessage::ToolMessage(result) => result.name = self.alias(&result.name),
+                ContextMessage::Image(_) => {}
+            }
+        }
+        context
+    }
+
+    /// Replaces the aliases of the tools that the provider called with their
+    /// names
+    pub(crate) fn resolve(&self, mut message: ChatCompletionMessage) -> ChatCompletionMessage {
+        for call in &mut message.tool_calls {
+            match call {
+                ToolCall::Full(call) => call.name = self.name(&call.name),
+                ToolCall::Part(part) => part.name = part.name.as_ref().map(|name| self.name(name)),
+            }
+        }
+        message
+    }
+}
+
+/// Replaces the characters that providers reject with `_`, and caps the length
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(MAX_TOOL_NAME_CHARS)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use forge_domain::{ToolCallFull, ToolDefinition, ToolResult};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_aliases() {
+        let long = format!("mcp:server:{}", "a".repeat(80));
+        let fixture = Context::default()
+            .add_tool(ToolDefinition::new("forge_tool_fs_read"))
+            .add_tool(ToolDefinition::new("mcp:github:create_issue"))
+            .add_tool(ToolDefinition::new("mcp_github_create_issue"))
+            .add_tool(ToolDefinition::new(&long));
+
+        let aliases = ToolAliases::new(&fixture);
+        let actual = aliases
+            .alias_context(fixture)
+            .tools
+            .into_iter()
+            .map(|tool| tool.name.into_string())
+            .collect::<Vec<_>>();
+
+        let expected = vec![
+            "forge_tool_fs_read".to_string(),
+            "mcp_github_create_issue_2".to_string(),
+            "mcp_github_create_issue".to_string(),
+            format!("mcp_server_{}", "a".repeat(53)),
+        ];
+        assert_eq!(actual, expected);
+        assert!(!aliases.is_identity());
+    }
+
+    #[test]
+    fn test_alias_history_and_resolve_calls() {
+        let name = ToolName::new("plugin:lint:check");
+        let fixture = Context::default()
+            .add_tool(ToolDefinition::new(name.as_str()))
+            .add_message(ContextMessage::assistant(
+                "",
+                Some(vec![ToolCallFull::new(name.clone())]),
+            ))
+            .add_message(ContextMessage::ToolMessage(ToolResult::new(name.clone())));
+        let aliases = ToolAliases::new(&fixture);
+
+        let context = aliases.alias_context(fixture);
+        let actual = aliases.resolve(
+            ChatCompletionMessage::default()
+                .add_tool_call(ToolCallFull::new(ToolName::new("plugin_lint_check"))),
+        );
+
+        let expected = ChatCompletionMessage::default().add_tool_call(ToolCallFull::new(name));
+        assert_eq!(actual, expected);
+        assert!(!serde_json::to_string(&context)
+            .unwrap()
+            .contains("plugin:lint"));
+    }
+}
@@ -1,5 +1,11 @@
+use forge_domain::TurnId;
+use reqwest::header::{HeaderMap, HeaderValue};
 use reqwest::StatusCode;
 
+/// Header that carries the id of the turn a request is made for. Providers
+/// that log request headers can then be correlated with the logs of forge.
+const CORRELATION_HEADER: &str = "x-correlation-id";
+
 /// Helper function to format HTTP request/response context for logging and
 /// error reporting
 pub(crate) fn format_http_context<U: AsRef<str>>(
@@ -13,3 +19,30 @@ pub(crate) fn format_http_context<U: AsRef<str>>(
         format!("{} {}", method, url.as_ref())
     }
 }
+
+/// Headers that correlate a request with the turn it is made for, if any
+pub(crate) fn correlation_headers(turn_id: Option<&TurnId>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Some(value) = turn_id.and_then(|id| HeaderValue::from_str(&id.into_string()).ok()) {
+        headers.insert(CORRELATION_HEADER, value);
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_correlation_headers() {
+        let fixture = TurnId::generate();
+
+        let actual = correlation_headers(Some(&fixture));
+
+        let expected = fixture.into_string();
+        assert_eq!(actual[CORRELATION_HEADER].to_str().unwrap(), expected);
+        assert!(correlation_headers(None).is_empty());
+    }
+}
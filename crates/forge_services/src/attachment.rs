@@ -6,6 +6,7 @@ use std::sync::Arc;
 use base64::Engine;
 use forge_domain::{Attachment, AttachmentService, ContentType, EnvironmentService};
 
+use crate::tools::utils::assert_not_ignored;
 use crate::{FsReadService, Infrastructure};
 
 #[derive(Clone)]
@@ -77,6 +78,8 @@ impl<F: Infrastructure> ForgeChatRequest<F> {
                 .join(path);
         }
 
+        assert_not_ignored(&path)?;
+
         // Determine file type (text or image with format)
         let img_format = extension.and_then(|ext| match ext.as_str() {
             "jpeg" | "jpg" => Some("jpeg".to_string()),
@@ -146,6 +149,12 @@ pub mod tests {
                 base_path: PathBuf::from("/base"),
                 provider: Provider::open_router("test-key"),
                 retry_config: Default::default(),
+                resource_limits: Default::default(),
+                retention: Default::default(),
+                code_owner_teams: vec![],
+                cassette: None,
+                inspected_requests: 0,
+                clock: Default::default(),
             }
         }
     }
@@ -281,6 +290,11 @@ pub mod tests {
             Ok(())
         }
 
+        // Files are kept in memory, so there is nothing to sync
+        fn durable(&self) -> bool {
+            false
+        }
+
         async fn write_temp(&self, _: &str, _: &str, content: &str) -> anyhow::Result<PathBuf> {
             let temp_dir = TempDir::new().unwrap();
             let path = temp_dir.path();
@@ -289,6 +303,10 @@ pub mod tests {
 
             Ok(path)
         }
+
+        async fn replace(&self, _: &Path, _: &[u64], _: usize, _: &str) -> anyhow::Result<()> {
+            unimplemented!()
+        }
     }
 
     #[derive(Debug)]
@@ -300,9 +318,17 @@ pub mod tests {
             unimplemented!()
         }
 
+        async fn record_creation(&self, _: &Path) -> anyhow::Result<Snapshot> {
+            unimplemented!()
+        }
+
         async fn undo_snapshot(&self, _: &Path) -> anyhow::Result<()> {
             unimplemented!()
         }
+
+        async fn restore_session(&self) -> anyhow::Result<Vec<PathBuf>> {
+            unimplemented!()
+        }
     }
 
     #[async_trait::async_trait]
@@ -339,6 +365,7 @@ pub mod tests {
                     stderr: "".to_string(),
                     command,
                     exit_code: Some(0),
+                    usage: None,
                 });
             } else if command.contains("echo") {
                 if command.contains(">") && command.contains(">&2") {
@@ -358,6 +385,7 @@ pub mod tests {
                         stderr: stderr.to_string(),
                         command,
                         exit_code: Some(0),
+                        usage: None,
                     });
                 } else if command.contains(">&2") {
                     // Command with only stderr
@@ -368,6 +396,7 @@ pub mod tests {
                         stderr: format!("{content}\n"),
                         command,
                         exit_code: Some(0),
+                        usage: None,
                     });
                 } else {
                     // Standard echo command
@@ -396,6 +425,7 @@ pub mod tests {
                         stderr: "".to_string(),
                         command,
                         exit_code: Some(0),
+                        usage: None,
                     });
                 }
             } else if command == "pwd" || command == "cd" {
@@ -405,6 +435,7 @@ pub mod tests {
                     stderr: "".to_string(),
                     command,
                     exit_code: Some(0),
+                    usage: None,
                 });
             } else if command == "true" {
                 // true command returns success with no output
@@ -413,6 +444,7 @@ pub mod tests {
                     stderr: "".to_string(),
                     command,
                     exit_code: Some(0),
+                    usage: None,
                 });
             } else if command.starts_with("/bin/ls") || command.contains("whoami") {
                 // Full path commands
@@ -421,6 +453,7 @@ pub mod tests {
                     stderr: "".to_string(),
                     command,
                     exit_code: Some(0),
+                    usage: None,
                 });
             } else if command == "non_existent_command" {
                 // Command not found
@@ -429,6 +462,7 @@ pub mod tests {
                     stderr: "command not found: non_existent_command\n".to_string(),
                     command,
                     exit_code: Some(-1),
+                    usage: None,
                 });
             }
 
@@ -438,6 +472,7 @@ pub mod tests {
                 stderr: "".to_string(),
                 command,
                 exit_code: Some(0),
+                usage: None,
             })
         }
     }
@@ -2,8 +2,9 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use forge_domain::{
-    extract_tag_content, Agent, ChatCompletionMessage, Compact, CompactionService, Context,
-    ContextMessage, ProviderService, Role, TemplateService,
+    extract_tag_content, salient_turns, Agent, ChatCompletionMessage, Compact, CompactionService,
+    Context, ContextMessage, HeuristicScorer, ProviderService, Role, SalienceScorer,
+    TemplateService,
 };
 use futures::StreamExt;
 use tracing::{debug, info};
@@ -13,12 +14,19 @@ use tracing::{debug, info};
 pub struct ForgeCompactionService<T, P> {
     template: Arc<T>,
     provider: Arc<P>,
+    scorer: Arc<dyn SalienceScorer>,
 }
 
 impl<T: TemplateService, P: ProviderService> ForgeCompactionService<T, P> {
     /// Creates a new ContextCompactor instance
     pub fn new(template: Arc<T>, provider: Arc<P>) -> Self {
-        Self { template, provider }
+        Self { template, provider, scorer: Arc::new(HeuristicScorer) }
+    }
+
+    /// Replaces the scorer that chooses the turns kept verbatim
+    pub fn scorer(mut self, scorer: Arc<dyn SalienceScorer>) -> Self {
+        self.scorer = scorer;
+        self
     }
 
     /// Apply compaction to the context if requested
@@ -57,12 +65,32 @@ impl<T: TemplateService, P: ProviderService> ForgeCompactionService<T, P> {
     ) -> Result<Context> {
         let (start, end) = sequence;
 
-        // Extract the sequence to summarize
-        let sequence_messages = &context.messages[start..=end];
+        // The most salient turns of the sequence are kept verbatim, and the
+        // others summarized
+        let retained = salient_turns(
+            self.scorer.as_ref(),
+            &context.messages[start..=end],
+            &context.messages[end + 1..],
+            compact.retained_turns.unwrap_or_default(),
+        );
+        let (retained_messages, sequence_messages): (Vec<_>, Vec<_>) = context.messages
+            [start..=end]
+            .iter()
+            .cloned()
+            .enumerate()
+            .partition(|(index, _)| retained.iter().any(|turn| turn.contains(index)));
+        if sequence_messages.is_empty() {
+            debug!("Every turn of the sequence is retained, nothing to summarize");
+            return Ok(context);
+        }
+        let sequence_messages = sequence_messages
+            .into_iter()
+            .map(|(_, message)| message)
+            .collect::<Vec<_>>();
 
         // Generate summary for this sequence
         let summary = self
-            .generate_summary_for_sequence(compact, sequence_messages)
+            .generate_summary_for_sequence(compact, &sequence_messages)
             .await?;
 
         // Log the summary for debugging
@@ -70,6 +98,7 @@ impl<T: TemplateService, P: ProviderService> ForgeCompactionService<T, P> {
             summary = %summary,
             sequence_start = sequence.0,
             sequence_end = sequence.1,
+            retained_messages = retained_messages.len(),
             "Created context compaction summary"
         );
 
@@ -78,11 +107,12 @@ impl<T: TemplateService, P: ProviderService> ForgeCompactionService<T, P> {
         "#
         );
 
-        // Replace the sequence with a single summary message using splice
-        // This removes the sequence and inserts the summary message in-place
+        // Replace the sequence with a single summary message using splice,
+        // followed by the retained messages
         context.messages.splice(
             start..=end,
-            std::iter::once(ContextMessage::assistant(summary, None)),
+            std::iter::once(ContextMessage::assistant(summary, None))
+                .chain(retained_messages.into_iter().map(|(_, message)| message)),
         );
 
         Ok(context)
@@ -36,10 +36,10 @@ pub struct ForgeServices<F> {
 }
 
 impl<F: Infrastructure> ForgeServices<F> {
-    pub fn new(infra: Arc<F>) -> Self {
+    pub fn new(infra: Arc<F>) -> anyhow::Result<Self> {
         let tool_service = Arc::new(ForgeToolService::new(infra.clone()));
         let template_service = Arc::new(ForgeTemplateService::new());
-        let provider_service = Arc::new(ForgeProviderService::new(infra.clone()));
+        let provider_service = Arc::new(ForgeProviderService::new(infra.clone())?);
         let attachment_service = Arc::new(ForgeChatRequest::new(infra.clone()));
         let compaction_service = Arc::new(ForgeCompactionService::new(
             template_service.clone(),
@@ -51,7 +51,7 @@ impl<F: Infrastructure> ForgeServices<F> {
 
         let workflow_service = Arc::new(ForgeWorkflowService::new(infra.clone()));
         let suggestion_service = Arc::new(ForgeSuggestionService::new(infra.clone()));
-        Self {
+        Ok(Self {
             infra,
             conversation_service,
             tool_service,
@@ -61,7 +61,7 @@ impl<F: Infrastructure> ForgeServices<F> {
             template_service,
             workflow_service,
             suggestion_service,
-        }
+        })
     }
 }
 
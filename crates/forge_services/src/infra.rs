@@ -1,9 +1,15 @@
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::Result;
 use bytes::Bytes;
-use forge_domain::{CommandOutput, EnvironmentService};
+use forge_domain::{
+    CommandEnv, CommandOutput, EnvironmentService, JobOutput, ResourceUsage, TerminalSize,
+};
+use forge_fs::{FileLock, ForgeFS};
 use forge_snaps::Snapshot;
+use tokio::io::AsyncRead;
+use tokio_util::sync::CancellationToken;
 
 /// Repository for accessing system environment information
 /// This uses the EnvironmentService trait from forge_domain
@@ -42,13 +48,64 @@ pub trait FsReadService: Send + Sync {
         start_char: u64,
         end_char: u64,
     ) -> anyhow::Result<(String, forge_fs::FileInfo)>;
+
+    /// Returns the size of a file in bytes
+    async fn size(&self, path: &Path) -> anyhow::Result<u64> {
+        Ok(self.read(path).await?.len() as u64)
+    }
+
+    /// Opens a file to be read in chunks. Implementations may stream the file
+    /// as it is stored instead of holding it in memory, eg: to patch large
+    /// files.
+    async fn open(&self, path: &Path) -> anyhow::Result<Box<dyn AsyncRead + Send + Unpin>> {
+        Ok(Box::new(std::io::Cursor::new(self.read(path).await?)))
+    }
 }
 
 #[async_trait::async_trait]
 pub trait FsWriteService: Send + Sync {
-    /// Writes the content of a file at the specified path.
+    /// Writes the content of a file at the specified path. The file is
+    /// replaced atomically, so that a crash never leaves it half-written.
     async fn write(&self, path: &Path, contents: Bytes) -> anyhow::Result<()>;
 
+    /// Writes a file like `write`, unless the tool call that writes it was
+    /// cancelled, eg: while the user was asked to confirm the change
+    async fn write_unless_cancelled(
+        &self,
+        path: &Path,
+        contents: Bytes,
+        cancellation: &CancellationToken,
+    ) -> anyhow::Result<()> {
+        if cancellation.is_cancelled() {
+            anyhow::bail!(
+                "Cancelled by the user before {} was written",
+                path.display()
+            )
+        }
+        self.write(path, contents).await
+    }
+
+    /// Writes a file like `write` while holding its lock, so that the write
+    /// doesn't race with a tool that is changing the same file. Tools that
+    /// aren't built in, eg: of plugins, should write files through it.
+    async fn write_atomic(&self, path: &Path, contents: Bytes) -> anyhow::Result<()> {
+        let _lock = self.lock(path).await;
+        self.write(path, contents).await
+    }
+
+    /// Takes the lock of a file, for tools that read, change and write it, so
+    /// that tools running in parallel don't overwrite each other's changes
+    async fn lock(&self, path: &Path) -> FileLock {
+        ForgeFS::lock(path).await
+    }
+
+    /// Whether writes are synced to the disk before they complete, so that
+    /// they also survive a crash of the system. Tests can turn it off to skip
+    /// the fsync.
+    fn durable(&self) -> bool {
+        true
+    }
+
     /// Writes content to a temporary file with the given prefix and extension,
     /// and returns its path. The file will be kept (not deleted) after
     /// creation.
@@ -58,6 +115,17 @@ pub trait FsWriteService: Send + Sync {
     /// * `ext` - File extension (e.g. ".txt", ".md")
     /// * `content` - Content to write to the file
     async fn write_temp(&self, prefix: &str, ext: &str, content: &str) -> anyhow::Result<PathBuf>;
+
+    /// Replaces the `length` bytes at each of the given offsets of a file,
+    /// which are sorted and don't overlap. Implementations may stream the file
+    /// instead of holding it in memory, eg: to patch large files.
+    async fn replace(
+        &self,
+        path: &Path,
+        offsets: &[u64],
+        length: usize,
+        replacement: &str,
+    ) -> anyhow::Result<()>;
 }
 
 #[async_trait::async_trait]
@@ -83,8 +151,18 @@ pub trait FsSnapshotService: Send + Sync {
     // Creation
     async fn create_snapshot(&self, file_path: &Path) -> Result<Snapshot>;
 
-    /// Restores the most recent snapshot for the given file path
+    /// Records that the given file is about to be created, so that undoing or
+    /// restoring it deletes the file
+    async fn record_creation(&self, file_path: &Path) -> Result<Snapshot>;
+
+    /// Restores the most recent snapshot for the given file path, whose lock
+    /// the caller holds
     async fn undo_snapshot(&self, file_path: &Path) -> Result<()>;
+
+    /// Restores every file that was changed in the session to its content
+    /// before the first change, returning the paths of the restored files.
+    /// Each file is locked while it is restored.
+    async fn restore_session(&self) -> Result<Vec<PathBuf>>;
 }
 
 /// Service for executing shell commands
@@ -96,6 +174,76 @@ pub trait CommandExecutorService: Send + Sync {
         command: String,
         working_dir: PathBuf,
     ) -> anyhow::Result<CommandOutput>;
+
+    /// Executes a shell command like `execute_command`, with the given
+    /// environment, and stops it once it runs longer than `timeout` or the
+    /// user cancels it
+    async fn execute_command_with(
+        &self,
+        command: String,
+        working_dir: PathBuf,
+        env: CommandEnv,
+        timeout: Option<Duration>,
+        cancellation: CancellationToken,
+    ) -> anyhow::Result<CommandOutput> {
+        if !env.is_inherited() {
+            anyhow::bail!("Commands can't be executed with their own environment")
+        }
+        let output = async {
+            let Some(timeout) = timeout else {
+                return self.execute_command(command, working_dir).await;
+            };
+            match tokio::time::timeout(timeout, self.execute_command(command.clone(), working_dir))
+                .await
+            {
+                Ok(output) => output,
+                Err(_) => Ok(CommandOutput {
+                    command,
+                    stdout: String::new(),
+                    stderr: String::new(),
+                    exit_code: None,
+                    usage: Some(ResourceUsage::timed_out(timeout)),
+                }),
+            }
+        };
+        tokio::select! {
+            output = output => output,
+            _ = cancellation.cancelled() => anyhow::bail!("Cancelled by the user"),
+        }
+    }
+
+    /// Executes a shell command attached to a pseudo-terminal of the given
+    /// size, for commands that behave differently when they don't write to a
+    /// terminal. The command's stdout and stderr are both returned as stdout.
+    async fn execute_command_in_pty(
+        &self,
+        _command: String,
+        _working_dir: PathBuf,
+        _env: CommandEnv,
+        _size: TerminalSize,
+        _timeout: Option<Duration>,
+        _cancellation: CancellationToken,
+    ) -> anyhow::Result<CommandOutput> {
+        anyhow::bail!("Commands can't be executed in a terminal")
+    }
+
+    /// Starts a shell command in the background, eg: a dev server, and returns
+    /// the id of its job
+    async fn spawn_command(&self, _command: String, _working_dir: PathBuf) -> anyhow::Result<u64> {
+        anyhow::bail!("Background commands are not supported")
+    }
+
+    /// Returns the output of a background command since it was last polled.
+    /// The job is forgotten once its command exited and this returned.
+    async fn poll_command(&self, id: u64) -> anyhow::Result<JobOutput> {
+        anyhow::bail!("No background command with job id {id}")
+    }
+
+    /// Kills a background command and the processes it spawned, returning
+    /// its output since it was last polled
+    async fn kill_command(&self, id: u64) -> anyhow::Result<JobOutput> {
+        anyhow::bail!("No background command with job id {id}")
+    }
 }
 
 #[async_trait::async_trait]
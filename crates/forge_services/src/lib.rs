@@ -8,6 +8,8 @@ mod metadata;
 mod provider;
 mod suggestion;
 mod template;
+#[cfg(any(test, feature = "test-util"))]
+mod test_infra;
 mod tool_service;
 mod tools;
 mod workflow;
@@ -16,5 +18,7 @@ pub use clipper::*;
 pub use forge_services::*;
 pub use infra::*;
 pub use suggestion::*;
+#[cfg(any(test, feature = "test-util"))]
+pub use test_infra::*;
 #[cfg(test)]
 pub use tools::TempDir;
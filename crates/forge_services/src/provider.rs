@@ -1,29 +1,55 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
 use forge_domain::{
-    ChatCompletionMessage, Context as ChatContext, EnvironmentService, Model, ModelId,
-    ProviderService, ResultStream,
+    CassetteMode, ChatCompletionMessage, Context as ChatContext, EnvironmentService, Model,
+    ModelId, Parameters, ProviderService, ResultStream,
 };
-use forge_provider::Client;
+use forge_provider::{Client, Inspector, Recorder, Replayer};
+use tokio::sync::Mutex;
+use tracing::warn;
 
 use crate::Infrastructure;
 
 #[derive(Clone)]
 pub struct ForgeProviderService {
     // The provider service implementation
-    client: Arc<Client>,
+    client: Arc<dyn ProviderService>,
+    // Capabilities probed for each model, so that the server is only probed once
+    parameters: Arc<Mutex<HashMap<ModelId, Option<Parameters>>>>,
 }
 
 impl ForgeProviderService {
-    pub fn new<F: Infrastructure>(infra: Arc<F>) -> Self {
+    /// Creates the service with the provider of the environment. Fails when
+    /// the client can't be built or the cassette to replay can't be loaded.
+    pub fn new<F: Infrastructure>(infra: Arc<F>) -> Result<Self> {
         let infra = infra.clone();
         let env = infra.environment_service().get_environment();
         let provider = env.provider.clone();
         let retry_config = env.retry_config;
-        Self {
-            client: Arc::new(Client::new(provider, retry_config).unwrap()),
-        }
+        let inspection_path = env.inspection_path();
+        let secrets = provider
+            .key()
+            .map(|key| key.to_string())
+            .into_iter()
+            .collect();
+        let client: Arc<dyn ProviderService> = match env.cassette {
+            Some(CassetteMode::Replay(path)) => Arc::new(Replayer::load(&path)?),
+            Some(CassetteMode::Record(path)) => Arc::new(Recorder::new(
+                Client::new(provider, retry_config)?,
+                path,
+                secrets,
+            )),
+            None if env.inspected_requests > 0 => Arc::new(Inspector::new(
+                Client::new(provider, retry_config)?,
+                inspection_path,
+                secrets,
+                env.inspected_requests,
+            )),
+            None => Arc::new(Client::new(provider, retry_config)?),
+        };
+        Ok(Self { client, parameters: Default::default() })
     }
 }
 
@@ -43,4 +69,20 @@ impl ProviderService for ForgeProviderService {
     async fn models(&self) -> Result<Vec<Model>> {
         self.client.models().await
     }
+
+    async fn parameters(&self, model: &ModelId) -> Result<Option<Parameters>> {
+        let mut cache = self.parameters.lock().await;
+        if let Some(parameters) = cache.get(model) {
+            return Ok(parameters.clone());
+        }
+
+        // A failed probe shouldn't prevent using the model with its configured
+        // capabilities
+        let parameters = self.client.parameters(model).await.unwrap_or_else(|error| {
+            warn!(model = %model, error = ?error, "Failed to probe model capabilities");
+            None
+        });
+        cache.insert(model.clone(), parameters.clone());
+        Ok(parameters)
+    }
 }
@@ -0,0 +1,695 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use bytes::Bytes;
+use forge_domain::{
+    CommandEnv, CommandOutput, Environment, EnvironmentService, JobOutput, Provider, TerminalSize,
+};
+use forge_snaps::{Snapshot, SnapshotId};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    CommandExecutorService, FileRemoveService, FsCreateDirsService, FsMetaService, FsReadService,
+    FsSnapshotService, FsWriteService, Infrastructure, InquireService,
+};
+
+/// A deterministic [`Infrastructure`] for testing tools without touching the
+/// real filesystem, clock or shell.
+///
+/// Files live in memory, snapshots are timestamped with a [`TestClock`] that
+/// only moves when told to, and shell commands return the outputs they were
+/// scripted with.
+///
+/// ```ignore
+/// let infra = Arc::new(
+///     TestInfrastructure::new()
+///         .file("/test/main.rs", "fn main() {}")
+///         .command("cargo check", CommandOutput { .. }),
+/// );
+/// let tool = FSRead::new(infra.clone());
+/// ```
+#[derive(Clone)]
+pub struct TestInfrastructure {
+    env: Arc<TestEnvironment>,
+    fs: Arc<InMemoryFs>,
+    shell: Arc<ScriptedShell>,
+    inquire: Arc<ScriptedInquire>,
+}
+
+impl Default for TestInfrastructure {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestInfrastructure {
+    /// Creates an empty infrastructure with `/test` as the working directory
+    pub fn new() -> Self {
+        let clock = TestClock::default();
+        Self {
+            env: Arc::new(TestEnvironment(Mutex::new(Self::default_environment()))),
+            fs: Arc::new(InMemoryFs::new(clock)),
+            shell: Arc::new(ScriptedShell::default()),
+            inquire: Arc::new(ScriptedInquire::default()),
+        }
+    }
+
+    fn default_environment() -> Environment {
+        Environment {
+            os: "test".to_string(),
+            pid: 12345,
+            cwd: PathBuf::from("/test"),
+            home: Some(PathBuf::from("/home/test")),
+            shell: "bash".to_string(),
+            base_path: PathBuf::from("/base"),
+            provider: Provider::open_router("test-key"),
+            retry_config: Default::default(),
+            resource_limits: Default::default(),
+            retention: Default::default(),
+            code_owner_teams: vec![],
+            cassette: None,
+            inspected_requests: 0,
+            clock: Default::default(),
+        }
+    }
+
+    /// Replaces the environment returned by the environment service
+    pub fn environment(self, env: Environment) -> Self {
+        *self.env.0.lock().unwrap() = env;
+        self
+    }
+
+    /// Adds a file, creating its parent directories
+    pub fn file(self, path: impl Into<PathBuf>, content: impl Into<Bytes>) -> Self {
+        self.fs.insert(path.into(), content.into());
+        self
+    }
+
+    /// Adds an empty directory
+    pub fn dir(self, path: impl Into<PathBuf>) -> Self {
+        self.fs.insert_dir(path.into());
+        self
+    }
+
+    /// Scripts the output returned when `command` is executed. Outputs scripted
+    /// for the same command are returned in order, the last one is repeated.
+    pub fn command(self, command: impl ToString, output: CommandOutput) -> Self {
+        self.shell.script(command.to_string(), output);
+        self
+    }
+
+    /// Queues the answer returned by the next prompt. `None` simulates the
+    /// user interrupting the prompt.
+    pub fn answer(self, answer: Option<&str>) -> Self {
+        self.inquire
+            .answers
+            .lock()
+            .unwrap()
+            .push_back(answer.map(str::to_string));
+        self
+    }
+
+    /// Returns the content of a file as UTF-8, if it exists
+    pub fn read_file(&self, path: impl AsRef<Path>) -> Option<String> {
+        self.fs
+            .files
+            .lock()
+            .unwrap()
+            .get(path.as_ref())
+            .map(|content| String::from_utf8_lossy(content).to_string())
+    }
+
+    /// Returns the paths of all the files, sorted
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.fs.files.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Returns the commands executed so far together with their working
+    /// directories, in order of execution
+    pub fn executed_commands(&self) -> Vec<(String, PathBuf)> {
+        self.shell.history.lock().unwrap().clone()
+    }
+
+    /// The clock used to timestamp snapshots
+    pub fn clock(&self) -> &TestClock {
+        &self.fs.clock
+    }
+}
+
+impl Infrastructure for TestInfrastructure {
+    type EnvironmentService = TestEnvironment;
+    type FsMetaService = InMemoryFs;
+    type FsReadService = InMemoryFs;
+    type FsRemoveService = InMemoryFs;
+    type FsSnapshotService = InMemoryFs;
+    type FsWriteService = InMemoryFs;
+    type FsCreateDirsService = InMemoryFs;
+    type CommandExecutorService = ScriptedShell;
+    type InquireService = ScriptedInquire;
+
+    fn environment_service(&self) -> &Self::EnvironmentService {
+        &self.env
+    }
+
+    fn file_meta_service(&self) -> &Self::FsMetaService {
+        &self.fs
+    }
+
+    fn file_read_service(&self) -> &Self::FsReadService {
+        &self.fs
+    }
+
+    fn file_remove_service(&self) -> &Self::FsRemoveService {
+        &self.fs
+    }
+
+    fn file_snapshot_service(&self) -> &Self::FsSnapshotService {
+        &self.fs
+    }
+
+    fn file_write_service(&self) -> &Self::FsWriteService {
+        &self.fs
+    }
+
+    fn create_dirs_service(&self) -> &Self::FsCreateDirsService {
+        &self.fs
+    }
+
+    fn command_executor_service(&self) -> &Self::CommandExecutorService {
+        &self.shell
+    }
+
+    fn inquire_service(&self) -> &Self::InquireService {
+        &self.inquire
+    }
+}
+
+/// A clock that only moves forward when advanced explicitly
+#[derive(Debug, Clone, Default)]
+pub struct TestClock(Arc<Mutex<Duration>>);
+
+impl TestClock {
+    /// Time elapsed since the unix epoch
+    pub fn now(&self) -> Duration {
+        *self.0.lock().unwrap()
+    }
+
+    pub fn set(&self, now: Duration) {
+        *self.0.lock().unwrap() = now;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.0.lock().unwrap() += duration;
+    }
+}
+
+pub struct TestEnvironment(Mutex<Environment>);
+
+impl EnvironmentService for TestEnvironment {
+    fn get_environment(&self) -> Environment {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// An in-memory filesystem that also keeps snapshots of modified files
+pub struct InMemoryFs {
+    files: Mutex<BTreeMap<PathBuf, Bytes>>,
+    dirs: Mutex<BTreeSet<PathBuf>>,
+    snapshots: Mutex<HashMap<PathBuf, Vec<(Snapshot, Bytes)>>>,
+    temp_files: AtomicUsize,
+    clock: TestClock,
+}
+
+impl InMemoryFs {
+    fn new(clock: TestClock) -> Self {
+        Self {
+            files: Default::default(),
+            dirs: Default::default(),
+            snapshots: Default::default(),
+            temp_files: AtomicUsize::new(0),
+            clock,
+        }
+    }
+
+    fn insert(&self, path: PathBuf, content: Bytes) {
+        if let Some(parent) = path.parent() {
+            self.insert_dir(parent.to_path_buf());
+        }
+        self.files.lock().unwrap().insert(path, content);
+    }
+
+    fn insert_dir(&self, path: PathBuf) {
+        let mut dirs = self.dirs.lock().unwrap();
+        for ancestor in path.ancestors() {
+            dirs.insert(ancestor.to_path_buf());
+        }
+    }
+
+    fn get(&self, path: &Path) -> Result<Bytes> {
+        self.files
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| anyhow!("File not found: {}", path.display()))
+    }
+
+    /// Reverts a file to a snapshot, removing it if the snapshot records its
+    /// creation
+    fn revert(&self, path: PathBuf, snapshot: &Snapshot, content: Bytes) {
+        if snapshot.created {
+            self.files.lock().unwrap().remove(&path);
+        } else {
+            self.insert(path, content);
+        }
+    }
+
+    fn push_snapshot(&self, file_path: &Path, created: bool, content: Bytes) -> Snapshot {
+        let snapshot = Snapshot {
+            id: SnapshotId::new(),
+            timestamp: self.clock.now(),
+            path: file_path.display().to_string(),
+            created,
+        };
+        self.snapshots
+            .lock()
+            .unwrap()
+            .entry(file_path.to_path_buf())
+            .or_default()
+            .push((snapshot.clone(), content));
+        snapshot
+    }
+}
+
+#[async_trait::async_trait]
+impl FsReadService for InMemoryFs {
+    async fn read_utf8(&self, path: &Path) -> Result<String> {
+        String::from_utf8(self.get(path)?.to_vec())
+            .map_err(|e| anyhow!("Invalid UTF-8 in file {}: {}", path.display(), e))
+    }
+
+    async fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        Ok(self.get(path)?.to_vec())
+    }
+
+    async fn range_read_utf8(
+        &self,
+        path: &Path,
+        start_char: u64,
+        end_char: u64,
+    ) -> Result<(String, forge_fs::FileInfo)> {
+        if let Some(file_type) = forge_fs::ForgeFS::binary_type(&self.get(path)?) {
+            return Err(forge_fs::Error::BinaryFileNotSupported(file_type).into());
+        }
+        let content = self.read_utf8(path).await?;
+        let total_chars = content.chars().count() as u64;
+        if start_char > total_chars {
+            bail!(
+                "Start position {start_char} is beyond the file size of {total_chars} characters"
+            );
+        }
+        let end_char = end_char.min(total_chars);
+        if start_char > end_char {
+            bail!("Start position {start_char} is greater than end position {end_char}");
+        }
+
+        let content = content
+            .chars()
+            .skip(start_char as usize)
+            .take((end_char - start_char) as usize)
+            .collect();
+        Ok((
+            content,
+            forge_fs::FileInfo::new(start_char, end_char, total_chars),
+        ))
+    }
+}
+
+#[async_trait::async_trait]
+impl FsWriteService for InMemoryFs {
+    async fn write(&self, path: &Path, contents: Bytes) -> Result<()> {
+        if self.is_file(path).await? {
+            self.create_snapshot(path).await?;
+        } else {
+            self.record_creation(path).await?;
+        }
+        self.insert(path.to_path_buf(), contents);
+        Ok(())
+    }
+
+    // Files are kept in memory, so there is nothing to sync
+    fn durable(&self) -> bool {
+        false
+    }
+
+    async fn write_temp(&self, prefix: &str, ext: &str, content: &str) -> Result<PathBuf> {
+        let index = self.temp_files.fetch_add(1, Ordering::SeqCst);
+        let path = PathBuf::from(format!("/tmp/{prefix}{index}{ext}"));
+        self.insert(path.clone(), Bytes::from(content.to_string()));
+        Ok(path)
+    }
+
+    async fn replace(
+        &self,
+        path: &Path,
+        offsets: &[u64],
+        length: usize,
+        replacement: &str,
+    ) -> Result<()> {
+        let source = self.get(path)?;
+        let mut contents = Vec::with_capacity(source.len());
+        let mut position = 0;
+        for &offset in offsets {
+            let offset = offset as usize;
+            contents.extend_from_slice(&source[position..offset]);
+            contents.extend_from_slice(replacement.as_bytes());
+            position = offset + length;
+        }
+        contents.extend_from_slice(&source[position..]);
+        self.write(path, Bytes::from(contents)).await
+    }
+}
+
+#[async_trait::async_trait]
+impl FileRemoveService for InMemoryFs {
+    async fn remove(&self, path: &Path) -> Result<()> {
+        self.files
+            .lock()
+            .unwrap()
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| anyhow!("File not found: {}", path.display()))
+    }
+}
+
+#[async_trait::async_trait]
+impl FsMetaService for InMemoryFs {
+    async fn is_file(&self, path: &Path) -> Result<bool> {
+        Ok(self.files.lock().unwrap().contains_key(path))
+    }
+
+    async fn exists(&self, path: &Path) -> Result<bool> {
+        Ok(self.is_file(path).await? || self.dirs.lock().unwrap().contains(path))
+    }
+}
+
+#[async_trait::async_trait]
+impl FsCreateDirsService for InMemoryFs {
+    async fn create_dirs(&self, path: &Path) -> Result<()> {
+        self.insert_dir(path.to_path_buf());
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl FsSnapshotService for InMemoryFs {
+    async fn create_snapshot(&self, file_path: &Path) -> Result<Snapshot> {
+        let content = self.get(file_path)?;
+        Ok(self.push_snapshot(file_path, false, content))
+    }
+
+    async fn record_creation(&self, file_path: &Path) -> Result<Snapshot> {
+        Ok(self.push_snapshot(file_path, true, Bytes::new()))
+    }
+
+    async fn undo_snapshot(&self, file_path: &Path) -> Result<()> {
+        let (snapshot, content) = self
+            .snapshots
+            .lock()
+            .unwrap()
+            .get_mut(file_path)
+            .and_then(|snapshots| snapshots.pop())
+            .ok_or_else(|| anyhow!("No snapshots found for {}", file_path.display()))?;
+        self.revert(file_path.to_path_buf(), &snapshot, content);
+        Ok(())
+    }
+
+    async fn restore_session(&self) -> Result<Vec<PathBuf>> {
+        let snapshots = std::mem::take(&mut *self.snapshots.lock().unwrap());
+        let mut paths = Vec::new();
+        for (path, snapshots) in snapshots {
+            if let Some((snapshot, content)) = snapshots.into_iter().next() {
+                self.revert(path.clone(), &snapshot, content);
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        Ok(paths)
+    }
+}
+
+/// Returns scripted outputs for shell commands and records every execution.
+/// Executing a command that wasn't scripted is an error. A command spawned in
+/// the background exits with its scripted output, which its first poll
+/// returns. A command executed in a terminal or with its own environment
+/// returns its scripted output too.
+#[derive(Default)]
+pub struct ScriptedShell {
+    outputs: Mutex<HashMap<String, VecDeque<CommandOutput>>>,
+    history: Mutex<Vec<(String, PathBuf)>>,
+    jobs: Mutex<HashMap<u64, JobOutput>>,
+}
+
+impl ScriptedShell {
+    fn script(&self, command: String, output: CommandOutput) {
+        self.outputs
+            .lock()
+            .unwrap()
+            .entry(command)
+            .or_default()
+            .push_back(output);
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandExecutorService for ScriptedShell {
+    async fn execute_command(
+        &self,
+        command: String,
+        working_dir: PathBuf,
+    ) -> Result<CommandOutput> {
+        self.history
+            .lock()
+            .unwrap()
+            .push((command.clone(), working_dir));
+
+        let mut outputs = self.outputs.lock().unwrap();
+        let queue = outputs
+            .get_mut(&command)
+            .ok_or_else(|| anyhow!("No output scripted for command: {command}"))?;
+        let output = if queue.len() > 1 {
+            queue.pop_front()
+        } else {
+            queue.front().cloned()
+        };
+
+        output
+            .map(|output| CommandOutput { command, ..output })
+            .ok_or_else(|| anyhow!("No output scripted for command"))
+    }
+
+    async fn execute_command_with(
+        &self,
+        command: String,
+        working_dir: PathBuf,
+        _env: CommandEnv,
+        _timeout: Option<Duration>,
+        _cancellation: CancellationToken,
+    ) -> Result<CommandOutput> {
+        self.execute_command(command, working_dir).await
+    }
+
+    async fn execute_command_in_pty(
+        &self,
+        command: String,
+        working_dir: PathBuf,
+        _env: CommandEnv,
+        _size: TerminalSize,
+        _timeout: Option<Duration>,
+        _cancellation: CancellationToken,
+    ) -> Result<CommandOutput> {
+        self.execute_command(command, working_dir).await
+    }
+
+    async fn spawn_command(&self, command: String, working_dir: PathBuf) -> Result<u64> {
+        let output = self.execute_command(command, working_dir).await?;
+        let mut jobs = self.jobs.lock().unwrap();
+        let id = self.history.lock().unwrap().len() as u64;
+        jobs.insert(
+            id,
+            JobOutput {
+                id,
+                command: output.command,
+                stdout: output.stdout,
+                stderr: output.stderr,
+                running: false,
+                exit_code: output.exit_code,
+            },
+        );
+        Ok(id)
+    }
+
+    async fn poll_command(&self, id: u64) -> Result<JobOutput> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .ok_or_else(|| anyhow!("No background command with job id {id}"))
+    }
+
+    async fn kill_command(&self, id: u64) -> Result<JobOutput> {
+        self.poll_command(id).await
+    }
+}
+
+/// Answers prompts with queued answers, in order
+#[derive(Default)]
+pub struct ScriptedInquire {
+    answers: Mutex<VecDeque<Option<String>>>,
+}
+
+impl ScriptedInquire {
+    fn next(&self, question: &str) -> Result<Option<String>> {
+        self.answers
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| anyhow!("No answer queued for prompt: {question}"))
+    }
+}
+
+#[async_trait::async_trait]
+impl InquireService for ScriptedInquire {
+    async fn prompt_question(&self, question: &str) -> Result<Option<String>> {
+        self.next(question)
+    }
+
+    async fn select_one(&self, message: &str, _: Vec<String>) -> Result<Option<String>> {
+        self.next(message)
+    }
+
+    async fn select_many(&self, message: &str, _: Vec<String>) -> Result<Option<Vec<String>>> {
+        Ok(self.next(message)?.map(|answer| {
+            answer
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .collect()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn output(stdout: &str) -> CommandOutput {
+        CommandOutput {
+            command: String::new(),
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+            exit_code: Some(0),
+            usage: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scripted_outputs_in_order() {
+        let fixture = TestInfrastructure::new()
+            .command("cargo test", output("first"))
+            .command("cargo test", output("second"));
+        let shell = fixture.command_executor_service();
+
+        let mut actual = Vec::new();
+        for _ in 0..3 {
+            let output = shell
+                .execute_command("cargo test".to_string(), PathBuf::from("/test"))
+                .await
+                .unwrap();
+            actual.push(output.stdout);
+        }
+
+        let expected = vec!["first", "second", "second"];
+        assert_eq!(actual, expected);
+        assert_eq!(fixture.executed_commands().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_unscripted_command_fails() {
+        let fixture = TestInfrastructure::new();
+
+        let actual = fixture
+            .command_executor_service()
+            .execute_command("rm -rf /".to_string(), PathBuf::from("/test"))
+            .await;
+
+        assert!(actual.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_uses_clock_and_undo_restores() {
+        let fixture = TestInfrastructure::new().file("/test/a.txt", "before");
+        fixture.clock().set(Duration::from_secs(100));
+        fixture.clock().advance(Duration::from_secs(5));
+
+        let snapshot = fixture
+            .file_snapshot_service()
+            .create_snapshot(Path::new("/test/a.txt"))
+            .await
+            .unwrap();
+        fixture
+            .file_write_service()
+            .write(Path::new("/test/a.txt"), Bytes::from("after"))
+            .await
+            .unwrap();
+        fixture
+            .file_snapshot_service()
+            .undo_snapshot(Path::new("/test/a.txt"))
+            .await
+            .unwrap();
+
+        assert_eq!(snapshot.timestamp, Duration::from_secs(105));
+        assert_eq!(fixture.read_file("/test/a.txt"), Some("before".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_write_snapshots_and_restore_session_reverts() {
+        let fixture = TestInfrastructure::new().file("/test/a.txt", "before");
+        for path in ["/test/a.txt", "/test/b.txt"] {
+            fixture
+                .file_write_service()
+                .write(Path::new(path), Bytes::from("after"))
+                .await
+                .unwrap();
+        }
+
+        let actual = fixture
+            .file_snapshot_service()
+            .restore_session()
+            .await
+            .unwrap();
+
+        let expected = vec![PathBuf::from("/test/a.txt"), PathBuf::from("/test/b.txt")];
+        assert_eq!(actual, expected);
+        assert_eq!(fixture.read_file("/test/a.txt"), Some("before".to_string()));
+        assert_eq!(fixture.read_file("/test/b.txt"), None);
+    }
+
+    #[tokio::test]
+    async fn test_exists_includes_parent_dirs() {
+        let fixture = TestInfrastructure::new().file("/test/src/lib.rs", "");
+
+        let actual = fixture
+            .file_meta_service()
+            .exists(Path::new("/test/src"))
+            .await
+            .unwrap();
+
+        assert!(actual);
+    }
+}
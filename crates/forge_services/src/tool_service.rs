@@ -1,11 +1,13 @@
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::sync::Arc;
 
 use forge_domain::{
-    Tool, ToolCallContext, ToolCallFull, ToolDefinition, ToolName, ToolResult, ToolService,
+    FileChange, Tool, ToolCallContext, ToolCallFull, ToolDefinition, ToolName, ToolResult,
+    ToolService,
 };
 use tokio::time::{timeout, Duration};
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 use crate::tools::ToolRegistry;
 use crate::Infrastructure;
@@ -25,12 +27,35 @@ impl ForgeToolService {
     }
 }
 
+impl ForgeToolService {
+    /// Finds a tool by name, accepting the explicit `builtin:` namespace of
+    /// built-in tools
+    fn find(&self, name: &ToolName) -> Option<&Tool> {
+        self.tools.get(name).or_else(|| {
+            name.without_builtin_namespace()
+                .and_then(|name| self.tools.get(&name))
+        })
+    }
+}
+
 impl FromIterator<Tool> for ForgeToolService {
+    /// Registers the tools in order. A tool whose name is already taken isn't
+    /// registered, so that a tool of an MCP server or a plugin can't replace
+    /// a built-in one.
     fn from_iter<T: IntoIterator<Item = Tool>>(iter: T) -> Self {
-        let tools: HashMap<ToolName, Tool> = iter
-            .into_iter()
-            .map(|tool| (tool.definition.name.clone(), tool))
-            .collect::<HashMap<_, _>>();
+        let mut tools: HashMap<ToolName, Tool> = HashMap::new();
+        for tool in iter {
+            match tools.entry(tool.definition.name.clone()) {
+                Entry::Occupied(entry) => warn!(
+                    tool_name = entry.key().as_str(),
+                    namespace = %entry.key().namespace(),
+                    "A tool with the same name is already registered, skipping it"
+                ),
+                Entry::Vacant(entry) => {
+                    entry.insert(tool);
+                }
+            }
+        }
 
         Self { tools: Arc::new(tools) }
     }
@@ -51,15 +76,25 @@ impl ToolService for ForgeToolService {
 
         available_tools.sort();
 
-        let output = match self.tools.get(&name) {
+        let output = match self.find(&name) {
             Some(tool) => {
-                // Wrap tool call with timeout
-                match timeout(TOOL_CALL_TIMEOUT, tool.executable.call(context, input)).await {
-                    Ok(result) => result,
-                    Err(_) => Err(anyhow::anyhow!(
-                        "Tool '{}' timed out after {} minutes",
-                        name.as_str(),
-                        TOOL_CALL_TIMEOUT.as_secs() / 60
+                let cancellation = context.cancellation.clone();
+                // Wrap tool call with timeout, and drop it when cancelled so that the processes
+                // and requests it started are stopped
+                tokio::select! {
+                    result = timeout(TOOL_CALL_TIMEOUT, tool.executable.call(context, input)) => {
+                        match result {
+                            Ok(result) => result,
+                            Err(_) => Err(anyhow::anyhow!(
+                                "Tool '{}' timed out after {} minutes",
+                                name.as_str(),
+                                TOOL_CALL_TIMEOUT.as_secs() / 60
+                            )),
+                        }
+                    }
+                    _ = cancellation.cancelled() => Err(anyhow::anyhow!(
+                        "Tool '{}' was cancelled by the user",
+                        name.as_str()
                     )),
                 }
             }
@@ -94,12 +129,20 @@ impl ToolService for ForgeToolService {
 
         tools
     }
+
+    async fn preview(&self, call: &ToolCallFull) -> anyhow::Result<Option<FileChange>> {
+        match self.find(&call.name) {
+            Some(tool) => tool.executable.preview(call.arguments.clone()).await,
+            None => Ok(None),
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use anyhow::bail;
     use forge_domain::{Tool, ToolCallContext, ToolCallId, ToolDefinition};
+    use pretty_assertions::assert_eq;
     use serde_json::{json, Value};
     use tokio::time;
 
@@ -198,6 +241,40 @@ mod test {
         insta::assert_snapshot!(result);
     }
 
+    #[tokio::test]
+    async fn test_tool_name_collision() {
+        type Executable = Box<dyn forge_domain::ExecutableTool<Input = Value> + Send + Sync>;
+        let tool = |description: &str, executable: Executable| Tool {
+            definition: ToolDefinition {
+                name: ToolName::new("success_tool"),
+                description: description.to_string(),
+                input_schema: schemars::schema_for!(serde_json::Value),
+                output_schema: Some(schemars::schema_for!(String)),
+            },
+            executable,
+        };
+        let service = ForgeToolService::from_iter(vec![
+            tool("Registered first", Box::new(SuccessTool)),
+            tool("Registered second", Box::new(FailureTool)),
+        ]);
+        let call = ToolCallFull {
+            name: ToolName::new("builtin:success_tool"),
+            arguments: json!("test input"),
+            call_id: Some(ToolCallId::new("test")),
+        };
+
+        let actual = service
+            .list()
+            .into_iter()
+            .map(|tool| tool.description)
+            .collect::<Vec<_>>();
+        let result = service.call(ToolCallContext::default(), call).await;
+
+        let expected = vec!["Registered first".to_string()];
+        assert_eq!(actual, expected);
+        assert!(!result.is_error);
+    }
+
     // Mock tool that simulates a long-running task
     struct SlowTool;
     #[async_trait::async_trait]
@@ -249,4 +326,37 @@ mod test {
         );
         assert!(result.is_error, "Expected error result for timeout");
     }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_tool_cancelled() {
+        test::time::pause();
+
+        let slow_tool = Tool {
+            definition: ToolDefinition {
+                name: ToolName::new("slow_tool"),
+                description: "A test tool that takes too long".to_string(),
+                input_schema: schemars::schema_for!(serde_json::Value),
+                output_schema: Some(schemars::schema_for!(String)),
+            },
+            executable: Box::new(SlowTool),
+        };
+
+        let service = ForgeToolService::from_iter(vec![slow_tool]);
+        let call = ToolCallFull {
+            name: ToolName::new("slow_tool"),
+            arguments: json!("test input"),
+            call_id: Some(ToolCallId::new("test")),
+        };
+        let context = ToolCallContext::default();
+        let cancellation = context.cancellation.clone();
+
+        let result = tokio::join!(service.call(context, call), async move {
+            time::sleep(Duration::from_secs(1)).await;
+            cancellation.cancel();
+        })
+        .0;
+
+        assert!(result.content.contains("was cancelled by the user"));
+        assert!(result.is_error);
+    }
 }
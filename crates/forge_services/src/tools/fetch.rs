@@ -95,13 +95,14 @@ impl<F: Infrastructure> Fetch<F> {
         context: &ToolCallContext,
         force_raw: bool,
     ) -> Result<(String, String)> {
-        self.check_robots_txt(url).await?;
+        // The requests are dropped as soon as the user cancels the call
+        context
+            .until_cancelled(self.check_robots_txt(url))
+            .await??;
 
-        let response = self
-            .client
-            .get(url.as_str())
-            .send()
-            .await
+        let response = context
+            .until_cancelled(self.client.get(url.as_str()).send())
+            .await?
             .map_err(|e| anyhow!("Failed to fetch URL {}: {}", url, e))?;
 
         context
@@ -125,9 +126,9 @@ impl<F: Infrastructure> Fetch<F> {
             .unwrap_or("")
             .to_string();
 
-        let page_raw = response
-            .text()
-            .await
+        let page_raw = context
+            .until_cancelled(response.text())
+            .await?
             .map_err(|e| anyhow!("Failed to read response content from {}: {}", url, e))?;
 
         let is_page_html = page_raw[..100.min(page_raw.len())].contains("<html")
@@ -1,11 +1,12 @@
-use std::collections::HashSet;
+use std::collections::BTreeSet;
 use std::path::Path;
 use std::sync::Arc;
 
 use anyhow::Context;
 use forge_display::{GrepFormat, TitleFormat};
 use forge_domain::{
-    EnvironmentService, ExecutableTool, NamedTool, ToolCallContext, ToolDescription, ToolName,
+    EnvironmentService, ExecutableTool, NamedTool, Progress, ToolCallContext, ToolDescription,
+    ToolName,
 };
 use forge_tool_macros::ToolDescription;
 use forge_walker::Walker;
@@ -16,6 +17,15 @@ use serde::Deserialize;
 use crate::tools::utils::{assert_absolute_path, format_display_path};
 use crate::Infrastructure;
 
+/// Number of files above which a content search reports its progress
+const PROGRESS_MIN_FILES: usize = 500;
+
+/// Results returned when no maximum is given
+const DEFAULT_MAX_RESULTS: u64 = 200;
+
+/// Maximum number of lines shown around each match
+const MAX_CONTEXT_LINES: u64 = 10;
+
 #[derive(Deserialize, JsonSchema)]
 pub struct FSFindInput {
     /// The absolute path of the directory or file to search in. If it's a
@@ -31,6 +41,18 @@ pub struct FSFindInput {
     /// Glob pattern to filter files (e.g., '*.ts' for TypeScript files). If not
     /// provided, it will search all files (*).
     pub file_pattern: Option<String>,
+
+    /// Whether the regex is a literal text rather than a pattern, eg: to
+    /// search for `foo(` without escaping it.
+    pub literal: Option<bool>,
+
+    /// Number of lines shown before and after each content match, 0 by
+    /// default and at most 10. Context lines are formatted as
+    /// `filepath-line_num-content`, and groups of lines are separated by `--`.
+    pub context_lines: Option<u64>,
+
+    /// Maximum number of matches returned, 200 by default.
+    pub max_results: Option<u64>,
 }
 
 impl FSFindInput {
@@ -70,7 +92,10 @@ impl FSFindInput {
 /// (when regex omitted). Uses case-insensitive Rust regex syntax. Requires
 /// absolute paths. Avoids binary files and excluded directories. Best for code
 /// exploration, API usage discovery, configuration settings, or finding
-/// patterns across projects.
+/// patterns across projects. Content matches are returned one per line as
+/// `filepath:line_num:content`, optionally with context lines around them.
+/// Use a literal search for text with regex characters, and narrow the path or
+/// the file pattern when the results are capped.
 #[derive(ToolDescription)]
 pub struct FSFind<F>(Arc<F>);
 
@@ -120,7 +145,11 @@ impl<F: Infrastructure> FSFind<F> {
         // Create content regex pattern if provided
         let regex = match &input.regex {
             Some(regex) => {
-                let pattern = format!("(?i){regex}"); // Case-insensitive by default
+                let pattern = if input.literal.unwrap_or_default() {
+                    format!("(?i){}", regex::escape(regex))
+                } else {
+                    format!("(?i){regex}") // Case-insensitive by default
+                };
                 Some(
                     Regex::new(&pattern)
                         .with_context(|| format!("Invalid regex pattern: {regex}"))?,
@@ -130,10 +159,40 @@ impl<F: Infrastructure> FSFind<F> {
         };
 
         let paths = retrieve_file_paths(path).await?;
+        let total = paths.len();
+        let mut reported = 0;
+        let mut bytes_processed = 0;
+        let max_results = input.max_results.unwrap_or(DEFAULT_MAX_RESULTS).max(1) as usize;
+        let context_lines = input
+            .context_lines
+            .unwrap_or_default()
+            .min(MAX_CONTEXT_LINES) as usize;
 
         let mut matches = Vec::new();
+        let mut results = 0;
+        let mut capped = false;
+
+        for (index, path) in paths.into_iter().enumerate() {
+            if results >= max_results {
+                capped = true;
+                break;
+            }
+
+            // Searching contents can take a while in large workspaces
+            if regex.is_some() && total >= PROGRESS_MIN_FILES {
+                let percentage = index * 100 / total;
+                if percentage > reported {
+                    reported = percentage;
+                    context
+                        .send_progress(
+                            Progress::new(format!("Searched {index} of {total} files"))
+                                .percentage(percentage as f64)
+                                .bytes_processed(bytes_processed as u64),
+                        )
+                        .await?;
+                }
+            }
 
-        for path in paths {
             if !input.match_file_path(path.as_path())? {
                 continue;
             }
@@ -141,6 +200,7 @@ impl<F: Infrastructure> FSFind<F> {
             // File name only search mode
             if regex.is_none() {
                 matches.push((self.format_display_path(&path)?).to_string());
+                results += 1;
                 continue;
             }
 
@@ -160,27 +220,51 @@ impl<F: Infrastructure> FSFind<F> {
                 }
             };
 
+            bytes_processed += content.len();
+
             // Process the file line by line to find content matches
             if let Some(regex) = &regex {
-                let mut found_match = false;
-
-                for (line_num, line) in content.lines().enumerate() {
-                    if regex.is_match(line) {
-                        found_match = true;
-                        // Format match in ripgrep style: filepath:line_num:content
-                        matches.push(format!(
-                            "{}:{}:{}",
-                            self.format_display_path(&path)?,
-                            line_num + 1,
-                            line
-                        ));
-                    }
+                let lines = content.lines().collect::<Vec<_>>();
+                let mut found = lines
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, line)| regex.is_match(line))
+                    .map(|(line_num, _)| line_num)
+                    .collect::<Vec<_>>();
+                if found.len() > max_results - results {
+                    found.truncate(max_results - results);
+                    capped = true;
                 }
-
-                // If no matches found in content but we're looking for content,
-                // don't add this file to matches
-                if !found_match && input.regex.is_some() {
-                    continue;
+                results += found.len();
+
+                let display_path = self.format_display_path(&path)?;
+                let context_line =
+                    |num: usize| format!("{display_path}-{}-{}", num + 1, lines[num]);
+                // Last line added for this file
+                let mut last: Option<usize> = None;
+                for (position, &line_num) in found.iter().enumerate() {
+                    let start = line_num
+                        .saturating_sub(context_lines)
+                        .max(last.map_or(0, |last| last + 1));
+                    if context_lines > 0
+                        && !matches.is_empty()
+                        && last.is_none_or(|last| start > last + 1)
+                    {
+                        matches.push("--".to_string());
+                    }
+                    // Format match in ripgrep style: filepath:line_num:content, and
+                    // context lines as filepath-line_num-content
+                    matches.extend((start..line_num).map(&context_line));
+                    matches.push(format!(
+                        "{display_path}:{}:{}",
+                        line_num + 1,
+                        lines[line_num]
+                    ));
+                    let end = (line_num + context_lines)
+                        .min(lines.len() - 1)
+                        .min(found.get(position + 1).map_or(usize::MAX, |next| next - 1));
+                    matches.extend((line_num + 1..=end).map(&context_line));
+                    last = Some(end.max(line_num));
                 }
             }
         }
@@ -198,11 +282,16 @@ impl<F: Infrastructure> FSFind<F> {
         }
 
         context.send_text(formatted_output.format()).await?;
+        if capped {
+            matches.push(format!(
+                "Stopped after {max_results} results, there may be more. Narrow the search or raise max_results."
+            ));
+        }
         Ok(matches.join("\n"))
     }
 }
 
-async fn retrieve_file_paths(dir: &Path) -> anyhow::Result<HashSet<std::path::PathBuf>> {
+async fn retrieve_file_paths(dir: &Path) -> anyhow::Result<BTreeSet<std::path::PathBuf>> {
     if dir.is_dir() {
         Ok(Walker::max_all()
             .cwd(dir.to_path_buf())
@@ -211,9 +300,9 @@ async fn retrieve_file_paths(dir: &Path) -> anyhow::Result<HashSet<std::path::Pa
             .with_context(|| format!("Failed to walk directory '{}'", dir.display()))?
             .into_iter()
             .map(|file| dir.join(file.path))
-            .collect::<HashSet<_>>())
+            .collect::<BTreeSet<_>>())
     } else {
-        Ok(HashSet::from_iter([dir.to_path_buf()]))
+        Ok(BTreeSet::from_iter([dir.to_path_buf()]))
     }
 }
 
@@ -264,6 +353,9 @@ mod test {
                     path: temp_dir.path().to_string_lossy().to_string(),
                     regex: Some("test".to_string()),
                     file_pattern: None,
+                    literal: None,
+                    context_lines: None,
+                    max_results: None,
                 },
             )
             .await
@@ -295,6 +387,9 @@ mod test {
                     path: temp_dir.path().to_string_lossy().to_string(),
                     regex: Some("test".to_string()),
                     file_pattern: Some("*.rs".to_string()),
+                    literal: None,
+                    context_lines: None,
+                    max_results: None,
                 },
             )
             .await
@@ -328,6 +423,9 @@ mod test {
                     path: temp_dir.path().to_string_lossy().to_string(),
                     regex: None,
                     file_pattern: Some("test*.txt".to_string()),
+                    literal: None,
+                    context_lines: None,
+                    max_results: None,
                 },
             )
             .await
@@ -358,6 +456,9 @@ mod test {
                     path: temp_dir.path().to_string_lossy().to_string(),
                     regex: Some("test".to_string()),
                     file_pattern: None,
+                    literal: None,
+                    context_lines: None,
+                    max_results: None,
                 },
             )
             .await
@@ -394,6 +495,9 @@ mod test {
                     path: temp_dir.path().to_string_lossy().to_string(),
                     regex: Some("test".to_string()),
                     file_pattern: None,
+                    literal: None,
+                    context_lines: None,
+                    max_results: None,
                 },
             )
             .await
@@ -406,6 +510,78 @@ mod test {
         assert!(result.contains("best.txt"));
     }
 
+    #[tokio::test]
+    async fn test_fs_search_literal_with_context() {
+        let temp_dir = TempDir::new().unwrap();
+        let content = "fn a() {}\nlet b = a();\nlet c = 1;\nlet d = 2;\nlet e = 3;\na();";
+
+        fs::write(temp_dir.path().join("test.rs"), content)
+            .await
+            .unwrap();
+
+        let infra = Arc::new(MockInfrastructure::new());
+        let fs_search = FSFind::new(infra);
+        let result = fs_search
+            .call(
+                ToolCallContext::default(),
+                FSFindInput {
+                    path: temp_dir.path().to_string_lossy().to_string(),
+                    regex: Some("a()".to_string()),
+                    file_pattern: None,
+                    literal: Some(true),
+                    context_lines: Some(1),
+                    max_results: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let path = temp_dir.path().join("test.rs").display().to_string();
+        let actual = result.replace(&path, "test.rs");
+        let expected = "test.rs:1:fn a() {}\n\
+                        test.rs:2:let b = a();\n\
+                        test.rs-3-let c = 1;\n\
+                        --\n\
+                        test.rs-5-let e = 3;\n\
+                        test.rs:6:a();";
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_fs_search_max_results() {
+        let temp_dir = TempDir::new().unwrap();
+
+        fs::write(temp_dir.path().join("test1.txt"), "test\ntest")
+            .await
+            .unwrap();
+        fs::write(temp_dir.path().join("test2.txt"), "test")
+            .await
+            .unwrap();
+
+        let infra = Arc::new(MockInfrastructure::new());
+        let fs_search = FSFind::new(infra);
+        let result = fs_search
+            .call(
+                ToolCallContext::default(),
+                FSFindInput {
+                    path: temp_dir.path().to_string_lossy().to_string(),
+                    regex: Some("test".to_string()),
+                    file_pattern: None,
+                    literal: None,
+                    context_lines: None,
+                    max_results: Some(2),
+                },
+            )
+            .await
+            .unwrap();
+
+        let lines: Vec<_> = result.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("test1.txt:1:"));
+        assert!(lines[1].contains("test1.txt:2:"));
+        assert!(lines[2].starts_with("Stopped after 2 results"));
+    }
+
     #[tokio::test]
     async fn test_fs_search_case_insensitive() {
         let temp_dir = TempDir::new().unwrap();
@@ -426,6 +602,9 @@ mod test {
                     path: temp_dir.path().to_string_lossy().to_string(),
                     regex: Some("test".to_string()),
                     file_pattern: None,
+                    literal: None,
+                    context_lines: None,
+                    max_results: None,
                 },
             )
             .await
@@ -454,6 +633,9 @@ mod test {
                     path: temp_dir.path().to_string_lossy().to_string(),
                     regex: Some("nonexistent".to_string()),
                     file_pattern: None,
+                    literal: None,
+                    context_lines: None,
+                    max_results: None,
                 },
             )
             .await
@@ -482,6 +664,9 @@ mod test {
                     path: temp_dir.path().to_string_lossy().to_string(),
                     regex: None,
                     file_pattern: None,
+                    literal: None,
+                    context_lines: None,
+                    max_results: None,
                 },
             )
             .await
@@ -506,6 +691,9 @@ mod test {
                     path: temp_dir.path().to_string_lossy().to_string(),
                     regex: Some("[invalid".to_string()),
                     file_pattern: None,
+                    literal: None,
+                    context_lines: None,
+                    max_results: None,
                 },
             )
             .await;
@@ -528,6 +716,9 @@ mod test {
                     path: "relative/path".to_string(),
                     regex: Some("test".to_string()),
                     file_pattern: None,
+                    literal: None,
+                    context_lines: None,
+                    max_results: None,
                 },
             )
             .await;
@@ -584,6 +775,9 @@ mod test {
                     path: temp_dir.path().join("best.txt").display().to_string(),
                     regex: Some("nice".to_string()),
                     file_pattern: None,
+                    literal: None,
+                    context_lines: None,
+                    max_results: None,
                 },
             )
             .await
@@ -604,6 +798,9 @@ mod test {
                     path: temp_dir.path().join("best.txt").display().to_string(),
                     regex: None,
                     file_pattern: None,
+                    literal: None,
+                    context_lines: None,
+                    max_results: None,
                 },
             )
             .await
@@ -3,16 +3,19 @@ use std::fmt::Write;
 use std::path::Path;
 use std::sync::Arc;
 
-use anyhow::{bail, Context};
+use anyhow::bail;
 use forge_display::TitleFormat;
 use forge_domain::{
     EnvironmentService, ExecutableTool, NamedTool, ToolCallContext, ToolDescription, ToolName,
 };
+use forge_fs::{ForgeFS, TextFile};
 use forge_tool_macros::ToolDescription;
 use schemars::JsonSchema;
 use serde::Deserialize;
 
-use crate::tools::utils::{assert_absolute_path, format_display_path};
+use crate::tools::utils::{
+    assert_absolute_path, assert_not_ignored, content_hash, format_display_path,
+};
 use crate::{FsReadService, Infrastructure};
 
 // Define maximum character limits
@@ -55,19 +58,31 @@ pub struct FSReadInput {
     /// Optional end position in characters (inclusive). If provided, reading
     /// will end at this character position.
     pub end_char: Option<u64>,
+
+    /// Optional first line to read (1-based). Can't be combined with
+    /// start_char and end_char.
+    pub start_line: Option<u64>,
+
+    /// Optional last line to read (1-based, inclusive). Reads to the end of
+    /// the file when omitted.
+    pub end_line: Option<u64>,
 }
 
 /// Reads file contents at specified path. Use for analyzing code, config files,
 /// documentation or text data. Extracts text from PDF/DOCX files and preserves
 /// original formatting. Returns content as string. Always use absolute paths.
-/// Read-only with no file modifications.
+/// Read-only with no file modifications. Binary files, eg: images or compiled
+/// programs, are described by their mime type and size instead.
 ///
 /// Files larger than 40,000 characters will automatically be read using range
 /// functionality, returning only the first 40,000 characters by default. For
 /// large files, you can specify custom ranges using start_char and end_char
 /// parameters. The total range must not exceed 40,000 characters (an error will
-/// be thrown if (end_char - start_char) > 40,000). Binary files are
-/// automatically detected and rejected.
+/// be thrown if (end_char - start_char) > 40,000). The hash of the file is
+/// returned, to be sent as expected_hash when changing it. To page through a
+/// large file by lines, use start_line and end_line instead: the total_lines of
+/// the file and the next_start_line to read are returned when more lines
+/// remain.
 #[derive(ToolDescription)]
 pub struct FSRead<F>(Arc<F>);
 
@@ -155,10 +170,132 @@ impl<F: Infrastructure> FSRead<F> {
         Ok(())
     }
 
+    /// Describes a binary file by its type and size instead of returning its
+    /// content, which would only fill the context with noise
+    async fn describe_binary(
+        &self,
+        context: &ToolCallContext,
+        path: &Path,
+        mime_type: &str,
+    ) -> anyhow::Result<String> {
+        let bytes = self.0.file_read_service().read(path).await?;
+        context
+            .send_text(
+                TitleFormat::debug("Read (Binary)")
+                    .sub_title(format!("{} ({mime_type})", self.format_display_path(path)?)),
+            )
+            .await?;
+
+        let mut response = String::new();
+        writeln!(response, "---")?;
+        writeln!(response, "path: {}", path.display())?;
+        writeln!(response, "hash: {}", content_hash(&bytes))?;
+        writeln!(response, "binary: true")?;
+        writeln!(response, "mime_type: {mime_type}")?;
+        writeln!(response, "total_bytes: {}", bytes.len())?;
+        writeln!(response, "---")?;
+        if mime_type.starts_with("image/") {
+            writeln!(
+                response,
+                "The file is an image, its content can't be read as text. Ask the user to attach it with @[{}] to look at it.",
+                path.display()
+            )?;
+        } else {
+            writeln!(
+                response,
+                "The file is binary, its content can't be read as text."
+            )?;
+        }
+        Ok(response)
+    }
+
+    /// Reads a range of lines of a file, cutting it on a line boundary when
+    /// it's longer than [`MAX_RANGE_SIZE`] characters
+    async fn read_lines(
+        &self,
+        context: &ToolCallContext,
+        input: &FSReadInput,
+        path: &Path,
+    ) -> anyhow::Result<String> {
+        if input.start_char.is_some() || input.end_char.is_some() {
+            bail!("Provide either a line range or a character range, not both")
+        }
+        let start_line = input.start_line.unwrap_or(1).max(1);
+        if let Some(end_line) = input.end_line {
+            if end_line < start_line {
+                bail!("Invalid range: end line ({end_line}) must not be less than start line ({start_line})")
+            }
+        }
+
+        let bytes = self.0.file_read_service().read(path).await?;
+        if let Some(mime_type) = ForgeFS::binary_type(&bytes) {
+            return self.describe_binary(context, path, &mime_type).await;
+        }
+        let hash = content_hash(&bytes);
+        let content = TextFile::decode(&bytes).content;
+
+        let lines = content.lines().collect::<Vec<_>>();
+        let total_lines = lines.len() as u64;
+        if start_line > total_lines.max(1) {
+            bail!("Start line {start_line} is past the end of the file, which has {total_lines} lines")
+        }
+        let requested_end = input.end_line.unwrap_or(total_lines).min(total_lines);
+
+        // Whole lines are returned until the range or the size limit is reached,
+        // but always at least one line
+        let mut end_line = start_line.saturating_sub(1);
+        let mut chars = 0;
+        for line in &lines[end_line as usize..requested_end as usize] {
+            chars += line.chars().count() as u64 + 1;
+            if chars > MAX_RANGE_SIZE && end_line >= start_line {
+                break;
+            }
+            end_line += 1;
+        }
+        let mut range = lines[(start_line - 1) as usize..end_line as usize].join("\n");
+
+        // A single line longer than the limit, eg: in a minified file, is cut
+        let cut = range.chars().count() as u64 > MAX_RANGE_SIZE;
+        if cut {
+            range = range.chars().take(MAX_RANGE_SIZE as usize).collect();
+        }
+        let truncated = cut || end_line < requested_end;
+
+        let display_path = self.format_display_path(path)?;
+        context
+            .send_text(TitleFormat::debug("Read (Lines)").sub_title(format!(
+                "{display_path} (line range: {start_line}-{end_line}, total lines: {total_lines})"
+            )))
+            .await?;
+
+        let mut response = String::new();
+        writeln!(response, "---")?;
+        writeln!(response, "path: {}", path.display())?;
+        writeln!(response, "hash: {hash}")?;
+        writeln!(response, "start_line: {start_line}")?;
+        writeln!(response, "end_line: {end_line}")?;
+        writeln!(response, "total_lines: {total_lines}")?;
+        if truncated {
+            writeln!(response, "truncated: true")?;
+        }
+        if end_line < total_lines {
+            writeln!(response, "next_start_line: {}", end_line + 1)?;
+        }
+        writeln!(response, "---")?;
+        writeln!(response, "{range}")?;
+
+        Ok(response)
+    }
+
     /// Helper function to read a file with range constraints
     async fn call(&self, context: ToolCallContext, input: FSReadInput) -> anyhow::Result<String> {
         let path = Path::new(&input.path);
         assert_absolute_path(path)?;
+        assert_not_ignored(path)?;
+
+        if input.start_line.is_some() || input.end_line.is_some() {
+            return self.read_lines(&context, &input, path).await;
+        }
 
         let start_char = input.start_char.unwrap_or(0);
         let end_char = input.end_char.unwrap_or(MAX_RANGE_SIZE.saturating_sub(1));
@@ -166,12 +303,28 @@ impl<F: Infrastructure> FSRead<F> {
         // Validate the range size using the module-level assertion function
         assert_valid_range(start_char, end_char)?;
 
-        let (content, file_info) = self
+        let (content, file_info) = match self
             .0
             .file_read_service()
             .range_read_utf8(path, start_char, end_char)
             .await
-            .with_context(|| format!("Failed to read file content from {}", input.path))?;
+        {
+            Ok(read) => read,
+            Err(error) => {
+                if let Some(forge_fs::Error::BinaryFileNotSupported(mime_type)) =
+                    error.downcast_ref::<forge_fs::Error>()
+                {
+                    return self.describe_binary(&context, path, mime_type).await;
+                }
+                return Err(
+                    error.context(format!("Failed to read file content from {}", input.path))
+                );
+            }
+        };
+
+        // The hash of the whole file lets the agent change it only if nobody
+        // else changed it in the meantime
+        let hash = content_hash(&self.0.file_read_service().read(path).await?);
 
         // Create and send the title using the extracted method
         self.create_and_send_title(&context, &input, path, start_char, end_char, &file_info)
@@ -192,6 +345,7 @@ impl<F: Infrastructure> FSRead<F> {
 
         writeln!(response, "---")?;
         writeln!(response, "path: {}", path.display())?;
+        writeln!(response, "hash: {hash}")?;
         if is_range_relevant {
             writeln!(response, "start_char: {}", file_info.start_char)?;
             writeln!(response, "end_char: {}", file_info.end_char)?;
@@ -240,7 +394,13 @@ mod test {
         fs_read
             .call(
                 ToolCallContext::default(),
-                FSReadInput { path: path.to_string(), start_char: None, end_char: None },
+                FSReadInput {
+                    path: path.to_string(),
+                    start_char: None,
+                    end_char: None,
+                    start_line: None,
+                    end_line: None,
+                },
             )
             .await
     }
@@ -290,6 +450,8 @@ mod test {
                     path: file_path.to_string_lossy().to_string(),
                     start_char: Some(10),
                     end_char: Some(20),
+                    start_line: None,
+                    end_line: None,
                 },
             )
             .await;
@@ -320,6 +482,8 @@ mod test {
                     path: file_path.to_string_lossy().to_string(),
                     start_char: Some(20),
                     end_char: Some(10),
+                    start_line: None,
+                    end_line: None,
                 },
             )
             .await;
@@ -502,6 +666,8 @@ mod test {
                     path: "/test/large_file.txt".to_string(),
                     start_char: None,
                     end_char: None,
+                    start_line: None,
+                    end_line: None,
                 },
             )
             .await;
@@ -563,4 +729,162 @@ mod test {
         assert!(display_path.is_ok());
         assert_eq!(display_path.unwrap(), file_path.display().to_string());
     }
+
+    #[tokio::test]
+    async fn test_fs_read_in_memory() {
+        let infra =
+            Arc::new(crate::TestInfrastructure::new().file("/test/main.rs", "fn main() {}"));
+        let fs_read = FSRead::new(infra);
+
+        let actual = fs_read
+            .call(
+                ToolCallContext::default(),
+                FSReadInput {
+                    path: "/test/main.rs".to_string(),
+                    start_char: Some(3),
+                    end_char: None,
+                    start_line: None,
+                    end_line: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        assert!(actual.ends_with("main() {}\n"));
+    }
+
+    #[tokio::test]
+    async fn test_fs_read_lines() {
+        let fixture = "line 1\nline 2\nline 3\nline 4\n";
+        let infra = Arc::new(crate::TestInfrastructure::new().file("/test/lines.txt", fixture));
+        let fs_read = FSRead::new(infra);
+
+        let actual = fs_read
+            .call(
+                ToolCallContext::default(),
+                FSReadInput {
+                    path: "/test/lines.txt".to_string(),
+                    start_char: None,
+                    end_char: None,
+                    start_line: Some(2),
+                    end_line: Some(3),
+                },
+            )
+            .await
+            .unwrap();
+
+        let expected = format!(
+            "---\npath: /test/lines.txt\nhash: {}\nstart_line: 2\nend_line: 3\ntotal_lines: 4\nnext_start_line: 4\n---\nline 2\nline 3\n",
+            content_hash(fixture.as_bytes())
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_fs_read_lines_truncated() {
+        let line = "a".repeat(30_000);
+        let fixture = format!("{line}\n{line}\n{line}\n");
+        let infra =
+            Arc::new(crate::TestInfrastructure::new().file("/test/large.txt", fixture.clone()));
+        let fs_read = FSRead::new(infra);
+
+        let actual = fs_read
+            .call(
+                ToolCallContext::default(),
+                FSReadInput {
+                    path: "/test/large.txt".to_string(),
+                    start_char: None,
+                    end_char: None,
+                    start_line: Some(1),
+                    end_line: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let expected = format!(
+            "---\npath: /test/large.txt\nhash: {}\nstart_line: 1\nend_line: 1\ntotal_lines: 3\ntruncated: true\nnext_start_line: 2\n---\n{line}\n",
+            content_hash(fixture.as_bytes())
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_fs_read_lines_cuts_a_long_line() {
+        let fixture = "a".repeat(50_000);
+        let infra =
+            Arc::new(crate::TestInfrastructure::new().file("/test/min.js", fixture.clone()));
+        let fs_read = FSRead::new(infra);
+
+        let actual = fs_read
+            .call(
+                ToolCallContext::default(),
+                FSReadInput {
+                    path: "/test/min.js".to_string(),
+                    start_char: None,
+                    end_char: None,
+                    start_line: Some(1),
+                    end_line: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let expected = format!(
+            "---\npath: /test/min.js\nhash: {}\nstart_line: 1\nend_line: 1\ntotal_lines: 1\ntruncated: true\n---\n{}\n",
+            content_hash(fixture.as_bytes()),
+            "a".repeat(40_000)
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_fs_read_lines_with_chars() {
+        let infra =
+            Arc::new(crate::TestInfrastructure::new().file("/test/main.rs", "fn main() {}"));
+        let fs_read = FSRead::new(infra);
+
+        let actual = fs_read
+            .call(
+                ToolCallContext::default(),
+                FSReadInput {
+                    path: "/test/main.rs".to_string(),
+                    start_char: Some(0),
+                    end_char: None,
+                    start_line: Some(1),
+                    end_line: None,
+                },
+            )
+            .await;
+
+        assert!(actual.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fs_read_binary() {
+        let png = vec![0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00];
+        let infra = Arc::new(crate::TestInfrastructure::new().file("/test/logo.png", png.clone()));
+        let fs_read = FSRead::new(infra);
+
+        let actual = fs_read
+            .call(
+                ToolCallContext::default(),
+                FSReadInput {
+                    path: "/test/logo.png".to_string(),
+                    start_char: None,
+                    end_char: None,
+                    start_line: None,
+                    end_line: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let expected = format!(
+            "---\npath: /test/logo.png\nhash: {}\nbinary: true\nmime_type: image/png\ntotal_bytes: 9\n---\n\
+             The file is an image, its content can't be read as text. Ask the user to attach it with @[/test/logo.png] to look at it.\n",
+            content_hash(&png)
+        );
+        assert_eq!(actual, expected);
+    }
 }
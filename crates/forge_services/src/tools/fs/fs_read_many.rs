@@ -0,0 +1,307 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use forge_display::TitleFormat;
+use forge_domain::{
+    EnvironmentService, ExecutableTool, NamedTool, ToolCallContext, ToolDescription, ToolName,
+};
+use forge_tool_macros::ToolDescription;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::tools::utils::{
+    assert_absolute_path, assert_not_ignored, content_hash, format_display_path,
+};
+use crate::{FsReadService, Infrastructure};
+
+/// Maximum number of files read in one call
+const MAX_FILES: usize = 20;
+
+/// Characters read from each file when no cap is given
+const DEFAULT_CHARS_PER_FILE: u64 = 10_000;
+
+/// Maximum number of characters read from each file, as for a single read
+const MAX_CHARS_PER_FILE: u64 = 40_000;
+
+/// Characters read from all of the files when no budget is given
+const DEFAULT_TOTAL_CHARS: u64 = 100_000;
+
+/// Maximum number of characters read from all of the files
+const MAX_TOTAL_CHARS: u64 = 200_000;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct FSReadManyInput {
+    /// The paths of the files to read, always provide absolute paths
+    #[serde(default)]
+    pub paths: Vec<String>,
+
+    /// A glob matching the files to read, eg: `src/**/*.rs`, relative to the
+    /// working directory unless absolute. The matches are read after the
+    /// paths.
+    pub glob: Option<String>,
+
+    /// Maximum number of characters read from each file, 10,000 by default
+    /// and at most 40,000. Longer files are truncated.
+    pub max_chars_per_file: Option<u64>,
+
+    /// Maximum number of characters read from all of the files, 100,000 by
+    /// default and at most 200,000. The files that don't fit are omitted.
+    pub max_total_chars: Option<u64>,
+}
+
+/// Reads several small files in one call, eg: a module and its tests, given
+/// their absolute paths or a glob. Returns the content of each file after a
+/// header with its path, its hash to be sent as expected_hash when changing
+/// it, and its size. Each file is read up to max_chars_per_file characters,
+/// all of them up to max_total_chars, and at most 20 files are read. Files that
+/// can't be read are reported with an error instead of failing the call. Use
+/// forge_tool_fs_read to read a large file or a range of it. Read-only with no
+/// file modifications.
+#[derive(ToolDescription)]
+pub struct FSReadMany<F>(Arc<F>);
+
+impl<F: Infrastructure> FSReadMany<F> {
+    pub fn new(f: Arc<F>) -> Self {
+        Self(f)
+    }
+
+    /// Reads a file up to `max_chars` characters, and formats it with its
+    /// header. Returns the number of characters read along with it.
+    async fn read(&self, path: &Path, max_chars: u64) -> anyhow::Result<(String, u64)> {
+        assert_not_ignored(path)?;
+        let (content, info) = self
+            .0
+            .file_read_service()
+            .range_read_utf8(path, 0, max_chars)
+            .await?;
+        let hash = content_hash(&self.0.file_read_service().read(path).await?);
+
+        let mut output = String::new();
+        writeln!(output, "---")?;
+        writeln!(output, "path: {}", path.display())?;
+        writeln!(output, "hash: {hash}")?;
+        writeln!(output, "total_chars: {}", info.total_chars)?;
+        if info.total_chars > info.end_char {
+            writeln!(output, "truncated_at: {}", info.end_char)?;
+        }
+        writeln!(output, "---")?;
+        writeln!(output, "{content}")?;
+        Ok((output, info.end_char - info.start_char))
+    }
+}
+
+/// Files that a glob matches, sorted, skipping directories and the files
+/// ignored by `.forgeignore`
+pub fn expand_glob(pattern: &str) -> anyhow::Result<Vec<PathBuf>> {
+    assert_absolute_path(Path::new(pattern))?;
+    Ok(glob::glob(pattern)
+        .with_context(|| format!("Invalid glob: {pattern}"))?
+        .filter_map(Result::ok)
+        .filter(|path| path.is_file() && assert_not_ignored(path).is_ok())
+        .collect())
+}
+
+impl<F> NamedTool for FSReadMany<F> {
+    fn tool_name() -> ToolName {
+        ToolName::new("forge_tool_fs_read_many")
+    }
+}
+
+#[async_trait::async_trait]
+impl<F: Infrastructure> ExecutableTool for FSReadMany<F> {
+    type Input = FSReadManyInput;
+
+    async fn call(&self, context: ToolCallContext, input: Self::Input) -> anyhow::Result<String> {
+        let env = self.0.environment_service().get_environment();
+        let mut paths = input.paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+        for path in &paths {
+            assert_absolute_path(path)?;
+        }
+        if let Some(pattern) = &input.glob {
+            let pattern = if Path::new(pattern).is_absolute() {
+                pattern.clone()
+            } else {
+                env.cwd.join(pattern).display().to_string()
+            };
+            for path in expand_glob(&pattern)? {
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        if paths.is_empty() {
+            bail!("No files to read, provide paths or a glob that matches files");
+        }
+        let mut omitted = paths.len().saturating_sub(MAX_FILES);
+        paths.truncate(MAX_FILES);
+
+        let max_chars = input
+            .max_chars_per_file
+            .unwrap_or(DEFAULT_CHARS_PER_FILE)
+            .clamp(1, MAX_CHARS_PER_FILE);
+        let mut budget = input
+            .max_total_chars
+            .unwrap_or(DEFAULT_TOTAL_CHARS)
+            .clamp(1, MAX_TOTAL_CHARS);
+
+        let display_paths = paths
+            .iter()
+            .map(|path| format_display_path(path, &env.cwd))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        context
+            .send_text(TitleFormat::debug("Read").sub_title(display_paths.join(", ")))
+            .await?;
+
+        let mut response = String::new();
+        let mut failed = 0;
+        let mut total_chars = 0;
+        let mut files = 0;
+        for path in &paths {
+            // The files that don't fit in the budget are omitted
+            if budget == 0 {
+                omitted += paths.len() - files;
+                break;
+            }
+            files += 1;
+            match self.read(path, max_chars.min(budget)).await {
+                Ok((file, chars)) => {
+                    response.push_str(&file);
+                    total_chars += chars;
+                    budget = budget.saturating_sub(chars);
+                }
+                Err(error) => {
+                    failed += 1;
+                    writeln!(response, "---")?;
+                    writeln!(response, "path: {}", path.display())?;
+                    writeln!(response, "error: {error:#}")?;
+                    writeln!(response, "---")?;
+                }
+            }
+        }
+
+        let mut summary = String::new();
+        writeln!(summary, "---")?;
+        writeln!(summary, "files: {files}")?;
+        writeln!(summary, "total_chars: {total_chars}")?;
+        if failed > 0 {
+            writeln!(summary, "failed: {failed}")?;
+        }
+        if omitted > 0 {
+            writeln!(summary, "omitted: {omitted}")?;
+        }
+        writeln!(summary, "---")?;
+        Ok(summary + &response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::tools::utils::TempDir;
+    use crate::TestInfrastructure;
+
+    #[tokio::test]
+    async fn test_fs_read_many() {
+        let infra = Arc::new(
+            TestInfrastructure::new()
+                .file("/test/a.rs", "fn a() {}")
+                .file("/test/b.rs", "fn b() {}\nfn c() {}"),
+        );
+        let fixture = FSReadManyInput {
+            paths: vec![
+                "/test/a.rs".to_string(),
+                "/test/b.rs".to_string(),
+                "/test/missing.rs".to_string(),
+            ],
+            glob: None,
+            max_chars_per_file: Some(9),
+            max_total_chars: None,
+        };
+
+        let actual = FSReadMany::new(infra)
+            .call(ToolCallContext::default(), fixture)
+            .await
+            .unwrap();
+
+        let expected = format!(
+            "---\nfiles: 3\ntotal_chars: 18\nfailed: 1\n---\n\
+             ---\npath: /test/a.rs\nhash: {}\ntotal_chars: 9\n---\nfn a() {{}}\n\
+             ---\npath: /test/b.rs\nhash: {}\ntotal_chars: 19\ntruncated_at: 9\n---\nfn b() {{}}\n\
+             ---\npath: /test/missing.rs\nerror: ",
+            content_hash(b"fn a() {}"),
+            content_hash(b"fn b() {}\nfn c() {}"),
+        );
+        assert!(actual.starts_with(&expected), "{actual}");
+    }
+
+    #[tokio::test]
+    async fn test_fs_read_many_total_budget() {
+        let infra = Arc::new(
+            TestInfrastructure::new()
+                .file("/test/a.rs", "fn a() {}")
+                .file("/test/b.rs", "fn b() {}\nfn c() {}")
+                .file("/test/c.rs", "fn d() {}"),
+        );
+        let fixture = FSReadManyInput {
+            paths: vec![
+                "/test/a.rs".to_string(),
+                "/test/b.rs".to_string(),
+                "/test/c.rs".to_string(),
+            ],
+            glob: None,
+            max_chars_per_file: None,
+            max_total_chars: Some(12),
+        };
+
+        let actual = FSReadMany::new(infra)
+            .call(ToolCallContext::default(), fixture)
+            .await
+            .unwrap();
+
+        let expected = format!(
+            "---\nfiles: 2\ntotal_chars: 12\nomitted: 1\n---\n\
+             ---\npath: /test/a.rs\nhash: {}\ntotal_chars: 9\n---\nfn a() {{}}\n\
+             ---\npath: /test/b.rs\nhash: {}\ntotal_chars: 19\ntruncated_at: 3\n---\nfn \n",
+            content_hash(b"fn a() {}"),
+            content_hash(b"fn b() {}\nfn c() {}"),
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_fs_read_many_requires_files() {
+        let infra = Arc::new(TestInfrastructure::new());
+        let fixture = FSReadManyInput {
+            paths: vec![],
+            glob: None,
+            max_chars_per_file: None,
+            max_total_chars: None,
+        };
+
+        let actual = FSReadMany::new(infra)
+            .call(ToolCallContext::default(), fixture)
+            .await;
+
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn test_expand_glob() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::create_dir(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/b.rs"), "").unwrap();
+        std::fs::write(dir.join("src/a.rs"), "").unwrap();
+        std::fs::write(dir.join("src/c.txt"), "").unwrap();
+        std::fs::create_dir(dir.join("src/d.rs")).unwrap();
+
+        let actual = expand_glob(&format!("{}/src/*.rs", dir.display())).unwrap();
+
+        let expected = vec![dir.join("src/a.rs"), dir.join("src/b.rs")];
+        assert_eq!(actual, expected);
+    }
+}
@@ -1,13 +1,15 @@
 use std::path::Path;
 use std::sync::Arc;
 
-use forge_domain::{ExecutableTool, NamedTool, ToolCallContext, ToolDescription, ToolName};
+use forge_domain::{
+    ExecutableTool, FileChange, NamedTool, ToolCallContext, ToolDescription, ToolName,
+};
 use forge_tool_macros::ToolDescription;
 use schemars::JsonSchema;
 use serde::Deserialize;
 
-use crate::tools::utils::assert_absolute_path;
-use crate::{FileRemoveService, FsMetaService, Infrastructure};
+use crate::tools::utils::{assert_absolute_path, confirm_owners};
+use crate::{FileRemoveService, FsMetaService, FsReadService, FsWriteService, Infrastructure};
 
 #[derive(Deserialize, JsonSchema)]
 pub struct FSRemoveInput {
@@ -37,10 +39,14 @@ impl<T> NamedTool for FSRemove<T> {
 impl<T: Infrastructure> ExecutableTool for FSRemove<T> {
     type Input = FSRemoveInput;
 
-    async fn call(&self, _context: ToolCallContext, input: Self::Input) -> anyhow::Result<String> {
+    async fn call(&self, context: ToolCallContext, input: Self::Input) -> anyhow::Result<String> {
         let path = Path::new(&input.path);
         assert_absolute_path(path)?;
 
+        // The file is locked from its checks to its removal, so that tools
+        // running in parallel can't change it in between
+        let _lock = self.0.file_write_service().lock(path).await;
+
         // Check if the file exists
         if !self.0.file_meta_service().exists(path).await? {
             return Err(anyhow::anyhow!("File not found: {}", input.path));
@@ -51,11 +57,31 @@ impl<T: Infrastructure> ExecutableTool for FSRemove<T> {
             return Err(anyhow::anyhow!("Path is not a file: {}", input.path));
         }
 
+        if context.dry_run {
+            return Ok(format!(
+                "File not removed, since the session is a dry run: {}",
+                input.path
+            ));
+        }
+
         // Remove the file
+        confirm_owners(self.0.as_ref(), path).await?;
         self.0.file_remove_service().remove(path).await?;
 
         Ok(format!("Successfully removed file: {}", input.path))
     }
+
+    async fn preview(&self, input: Self::Input) -> anyhow::Result<Option<FileChange>> {
+        let path = Path::new(&input.path);
+        assert_absolute_path(path)?;
+        if !self.0.file_meta_service().is_file(path).await? {
+            return Ok(None);
+        }
+
+        // Binary files can't be compared line by line
+        let content = self.0.file_read_service().read_utf8(path).await.ok();
+        Ok(content.map(|content| FileChange::new(path, content, "")))
+    }
 }
 
 #[cfg(test)]
@@ -116,6 +142,36 @@ mod test {
         assert!(result.unwrap_err().to_string().contains("File not found"));
     }
 
+    #[tokio::test]
+    async fn test_fs_remove_waits_for_lock() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("locked.txt");
+        let infra = Arc::new(MockInfrastructure::new());
+        infra
+            .file_write_service()
+            .write(file_path.as_path(), Bytes::from("test content"))
+            .await
+            .unwrap();
+
+        let lock = infra.file_write_service().lock(&file_path).await;
+        let fs_remove = FSRemove::new(infra.clone());
+        let input = || FSRemoveInput { path: file_path.to_string_lossy().to_string() };
+        let blocked = tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            fs_remove.call(ToolCallContext::default(), input()),
+        )
+        .await;
+        assert!(blocked.is_err());
+        assert!(infra.file_meta_service().exists(&file_path).await.unwrap());
+
+        drop(lock);
+        fs_remove
+            .call(ToolCallContext::default(), input())
+            .await
+            .unwrap();
+        assert!(!infra.file_meta_service().exists(&file_path).await.unwrap());
+    }
+
     #[tokio::test]
     async fn test_fs_remove_directory() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,381 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use forge_display::TitleFormat;
+use forge_domain::{
+    EnvironmentService, ExecutableTool, NamedTool, ToolCallContext, ToolDescription, ToolName,
+};
+use forge_tool_macros::ToolDescription;
+use forge_walker::Walker;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::tools::utils::{assert_absolute_path, format_display_path};
+use crate::Infrastructure;
+
+/// Levels of directories shown when no depth is given
+const DEFAULT_DEPTH: u64 = 3;
+
+/// Maximum levels of directories shown
+const MAX_DEPTH: u64 = 10;
+
+/// Maximum number of entries shown in the tree
+const MAX_ENTRIES: usize = 500;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct FSTreeInput {
+    /// The absolute path of the directory to show the tree of
+    pub path: String,
+
+    /// Number of levels of directories shown, 3 by default and at most 10.
+    /// Deeper directories are summarized with their number of files and
+    /// their size.
+    pub depth: Option<u64>,
+
+    /// Globs of the files to show, eg: `*.rs` or `src/**/*.ts`, matched
+    /// against the file names and their paths relative to `path`.
+    /// Directories without matching files are hidden. Every file is shown
+    /// when omitted.
+    #[serde(default)]
+    pub include: Vec<String>,
+
+    /// Globs of the files and directories to hide, eg: `target` or `*.lock`,
+    /// matched like include.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// Shows the tree of a directory, with the size of each file and the number
+/// of files and size of each directory, to get oriented in a project without
+/// listing it in the shell. Skips hidden files and the files ignored by
+/// `.gitignore` and `.forgeignore`. Directories deeper than depth are only
+/// summarized, and files can be filtered with include and exclude globs. The
+/// totals are reported in the front matter of the result. Requires an
+/// absolute path. Read-only with no file modifications.
+#[derive(ToolDescription)]
+pub struct FSTree<F>(Arc<F>);
+
+impl<F: Infrastructure> FSTree<F> {
+    pub fn new(f: Arc<F>) -> Self {
+        Self(f)
+    }
+}
+
+impl<F> NamedTool for FSTree<F> {
+    fn tool_name() -> ToolName {
+        ToolName::new("forge_tool_fs_tree")
+    }
+}
+
+/// A directory of the tree, with the number of files and the size of all of
+/// its subdirectories
+#[derive(Debug, Default)]
+struct Dir {
+    dirs: BTreeMap<String, Dir>,
+    /// Size of the files, by name
+    files: BTreeMap<String, u64>,
+    file_count: usize,
+    size: u64,
+}
+
+impl Dir {
+    fn insert_file(&mut self, components: &[&str], size: u64) {
+        self.file_count += 1;
+        self.size += size;
+        match components {
+            [name] => {
+                self.files.insert(name.to_string(), size);
+            }
+            [dir, rest @ ..] => self
+                .dirs
+                .entry(dir.to_string())
+                .or_default()
+                .insert_file(rest, size),
+            [] => {}
+        }
+    }
+
+    fn insert_dir(&mut self, components: &[&str]) {
+        if let [dir, rest @ ..] = components {
+            self.dirs
+                .entry(dir.to_string())
+                .or_default()
+                .insert_dir(rest);
+        }
+    }
+
+    fn dir_count(&self) -> usize {
+        self.dirs.values().map(|dir| 1 + dir.dir_count()).sum()
+    }
+
+    fn summary(&self) -> String {
+        match self.file_count {
+            0 => "empty".to_string(),
+            1 => format!("1 file, {}", format_size(self.size)),
+            count => format!("{count} files, {}", format_size(self.size)),
+        }
+    }
+
+    /// Writes the entries of the directory as a tree, directories first, up
+    /// to `depth` levels. Returns false when the tree was cut at
+    /// [`MAX_ENTRIES`].
+    fn write(&self, output: &mut String, prefix: &str, depth: usize, entries: &mut usize) -> bool {
+        let count = self.dirs.len() + self.files.len();
+        let children = self
+            .dirs
+            .iter()
+            .map(|(name, dir)| (name, Some(dir), dir.size))
+            .chain(self.files.iter().map(|(name, size)| (name, None, *size)));
+        for (index, (name, dir, size)) in children.enumerate() {
+            if *entries >= MAX_ENTRIES {
+                return false;
+            }
+            *entries += 1;
+
+            let (branch, indent) = if index + 1 == count {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            match dir {
+                Some(dir) => {
+                    output.push_str(&format!("{prefix}{branch}{name}/ ({})\n", dir.summary()));
+                    if depth > 1
+                        && !dir.write(output, &format!("{prefix}{indent}"), depth - 1, entries)
+                    {
+                        return false;
+                    }
+                }
+                None => {
+                    output.push_str(&format!("{prefix}{branch}{name} ({})\n", format_size(size)))
+                }
+            }
+        }
+        true
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+fn patterns(globs: &[String]) -> anyhow::Result<Vec<glob::Pattern>> {
+    globs
+        .iter()
+        .map(|glob| {
+            glob::Pattern::new(glob).with_context(|| format!("Invalid glob pattern: {glob}"))
+        })
+        .collect()
+}
+
+/// Whether a pattern matches a path relative to the root of the tree, or its
+/// name
+fn matches(pattern: &glob::Pattern, path: &str) -> bool {
+    pattern.matches(path)
+        || Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| pattern.matches(name))
+}
+
+#[async_trait::async_trait]
+impl<F: Infrastructure> ExecutableTool for FSTree<F> {
+    type Input = FSTreeInput;
+
+    async fn call(&self, context: ToolCallContext, input: Self::Input) -> anyhow::Result<String> {
+        let dir = Path::new(&input.path);
+        assert_absolute_path(dir)?;
+        if !dir.is_dir() {
+            bail!("Directory '{}' does not exist", input.path);
+        }
+        let depth = input.depth.unwrap_or(DEFAULT_DEPTH).clamp(1, MAX_DEPTH) as usize;
+        let include = patterns(&input.include)?;
+        let exclude = patterns(&input.exclude)?;
+
+        let env = self.0.environment_service().get_environment();
+        let display_path = format_display_path(dir, &env.cwd)?;
+        context
+            .send_text(TitleFormat::debug("Tree").sub_title(&display_path))
+            .await?;
+
+        let files = Walker::max_all()
+            .cwd(dir.to_path_buf())
+            .get()
+            .await
+            .with_context(|| format!("Failed to walk directory '{}'", input.path))?;
+
+        let mut root = Dir::default();
+        for file in files {
+            let path = file.path.trim_end_matches('/');
+            if path.is_empty() {
+                continue;
+            }
+            let components = path.split('/').collect::<Vec<_>>();
+            // A directory that is excluded hides all of its files
+            let excluded = (1..=components.len()).any(|length| {
+                let ancestor = components[..length].join("/");
+                exclude.iter().any(|pattern| matches(pattern, &ancestor))
+            });
+            if excluded {
+                continue;
+            }
+
+            if file.is_dir() {
+                // Directories are only shown for their matching files when
+                // filtering
+                if include.is_empty() {
+                    root.insert_dir(&components);
+                }
+            } else if include.is_empty() || include.iter().any(|pattern| matches(pattern, path)) {
+                root.insert_file(&components, file.size);
+            }
+        }
+
+        let mut tree = format!("{display_path}/ ({})\n", root.summary());
+        let complete = root.write(&mut tree, "", depth, &mut 0);
+
+        let mut result = String::new();
+        writeln!(result, "---")?;
+        writeln!(result, "path: {}", dir.display())?;
+        writeln!(result, "depth: {depth}")?;
+        writeln!(result, "directories: {}", root.dir_count())?;
+        writeln!(result, "files: {}", root.file_count)?;
+        writeln!(result, "total_bytes: {}", root.size)?;
+        if !complete {
+            writeln!(result, "truncated_at: {MAX_ENTRIES}")?;
+        }
+        writeln!(result, "---")?;
+        result.push_str(&tree);
+        if !complete {
+            writeln!(
+                result,
+                "The tree was cut at {MAX_ENTRIES} entries, lower the depth or filter it with include and exclude."
+            )?;
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+    use tokio::fs;
+
+    use super::*;
+    use crate::attachment::tests::MockInfrastructure;
+    use crate::tools::utils::TempDir;
+
+    async fn fixture() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        fs::create_dir_all(dir.join("src/tools/fs")).await.unwrap();
+        fs::create_dir_all(dir.join("target/debug")).await.unwrap();
+        fs::create_dir(dir.join("empty")).await.unwrap();
+        fs::write(dir.join("README.md"), "# Readme").await.unwrap();
+        fs::write(dir.join("src/lib.rs"), "mod tools;")
+            .await
+            .unwrap();
+        fs::write(dir.join("src/tools/mod.rs"), "mod fs;")
+            .await
+            .unwrap();
+        fs::write(dir.join("src/tools/fs/read.rs"), "fn read() {}")
+            .await
+            .unwrap();
+        fs::write(dir.join("target/debug/forge"), "binary")
+            .await
+            .unwrap();
+        temp_dir
+    }
+
+    #[tokio::test]
+    async fn test_fs_tree() {
+        let temp_dir = fixture().await;
+        let path = temp_dir.path().display().to_string();
+
+        let actual = FSTree::new(Arc::new(MockInfrastructure::new()))
+            .call(
+                ToolCallContext::default(),
+                FSTreeInput {
+                    path: path.clone(),
+                    depth: Some(2),
+                    include: vec![],
+                    exclude: vec!["target".to_string()],
+                },
+            )
+            .await
+            .unwrap();
+
+        let expected = format!(
+            "---\npath: {path}\ndepth: 2\ndirectories: 4\nfiles: 4\ntotal_bytes: 37\n---\n\
+             {path}/ (4 files, 37 B)\n\
+             ├── empty/ (empty)\n\
+             ├── src/ (3 files, 29 B)\n\
+             │   ├── tools/ (2 files, 19 B)\n\
+             │   └── lib.rs (10 B)\n\
+             └── README.md (8 B)\n"
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_fs_tree_include() {
+        let temp_dir = fixture().await;
+        let path = temp_dir.path().display().to_string();
+
+        let actual = FSTree::new(Arc::new(MockInfrastructure::new()))
+            .call(
+                ToolCallContext::default(),
+                FSTreeInput {
+                    path: path.clone(),
+                    depth: None,
+                    include: vec!["src/tools/**/*.rs".to_string()],
+                    exclude: vec![],
+                },
+            )
+            .await
+            .unwrap();
+
+        let expected = format!(
+            "---\npath: {path}\ndepth: 3\ndirectories: 3\nfiles: 2\ntotal_bytes: 19\n---\n\
+             {path}/ (2 files, 19 B)\n\
+             └── src/ (2 files, 19 B)\n    \
+             └── tools/ (2 files, 19 B)\n        \
+             ├── fs/ (1 file, 12 B)\n        \
+             └── mod.rs (7 B)\n"
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_fs_tree_requires_directory() {
+        let temp_dir = fixture().await;
+
+        let actual = FSTree::new(Arc::new(MockInfrastructure::new()))
+            .call(
+                ToolCallContext::default(),
+                FSTreeInput {
+                    path: temp_dir.path().join("README.md").display().to_string(),
+                    depth: None,
+                    include: vec![],
+                    exclude: vec![],
+                },
+            )
+            .await;
+
+        assert!(actual.is_err());
+    }
+}
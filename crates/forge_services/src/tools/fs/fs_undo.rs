@@ -9,13 +9,14 @@ use forge_tool_macros::ToolDescription;
 use schemars::JsonSchema;
 use serde::Deserialize;
 
-use crate::infra::FsSnapshotService;
+use crate::infra::{FsSnapshotService, FsWriteService};
 use crate::tools::utils::{assert_absolute_path, format_display_path};
 use crate::Infrastructure;
 
-/// Reverts the most recent file operation (create/modify/delete) on a specific
-/// file. Use this tool when you need to recover from incorrect file changes or
-/// if a revert is requested by the user.
+/// Reverts the most recent file operations (create/modify/delete) on a specific
+/// file, or every change of the session when no path is given. Use this tool
+/// when you need to recover from incorrect file changes or if a revert is
+/// requested by the user.
 #[derive(Default, ToolDescription)]
 pub struct FsUndo<F>(Arc<F>);
 
@@ -53,18 +54,49 @@ pub struct UndoInput {
     /// the exact path that was previously modified, created, or deleted by
     /// a Forge file operation. If the file was deleted, provide the
     /// original path it had before deletion. The system requires a prior
-    /// snapshot for this path.
-    pub path: String,
+    /// snapshot for this path. Omit it to restore every file changed in the
+    /// session to its state before the session.
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// Number of the last operations on the file to revert, 1 by default.
+    /// Ignored when the whole session is restored.
+    #[serde(default)]
+    pub count: Option<usize>,
 }
 
 #[async_trait::async_trait]
 impl<F: Infrastructure> ExecutableTool for FsUndo<F> {
     type Input = UndoInput;
     async fn call(&self, context: ToolCallContext, input: Self::Input) -> anyhow::Result<String> {
-        let path = Path::new(&input.path);
+        let Some(path) = input.path else {
+            return self.restore_session(context).await;
+        };
+        let path = Path::new(&path);
         assert_absolute_path(path)?;
 
-        self.0.file_snapshot_service().undo_snapshot(path).await?;
+        if context.dry_run {
+            return Ok(format!(
+                "Last operation not undone, since the session is a dry run: {}",
+                path.display()
+            ));
+        }
+
+        // The file is locked across the operations, so that tools running in
+        // parallel can't change it while it is reverted
+        let _lock = self.0.file_write_service().lock(path).await;
+
+        // Revert the operations one by one, stopping when the file runs out of
+        // snapshots after at least one was reverted
+        let count = input.count.unwrap_or(1).max(1);
+        let mut undone = 0;
+        while undone < count {
+            match self.0.file_snapshot_service().undo_snapshot(path).await {
+                Ok(()) => undone += 1,
+                Err(error) if undone == 0 => return Err(error),
+                Err(_) => break,
+            }
+        }
 
         // Format the path for display
         let display_path = self.format_display_path(path)?;
@@ -73,8 +105,41 @@ impl<F: Infrastructure> ExecutableTool for FsUndo<F> {
         let message = TitleFormat::debug("Undo").sub_title(display_path.clone());
         context.send_text(message).await?;
 
+        if undone == 1 {
+            Ok(format!(
+                "Successfully undid last operation on path: {display_path}"
+            ))
+        } else {
+            Ok(format!(
+                "Successfully undid last {undone} operations on path: {display_path}"
+            ))
+        }
+    }
+}
+
+impl<F: Infrastructure> FsUndo<F> {
+    /// Restores every file changed in the session to its state before the
+    /// session
+    async fn restore_session(&self, context: ToolCallContext) -> anyhow::Result<String> {
+        if context.dry_run {
+            return Ok("Session changes not restored, since the session is a dry run".to_string());
+        }
+
+        let paths = self.0.file_snapshot_service().restore_session().await?;
+        if paths.is_empty() {
+            return Ok("No file was changed in the session".to_string());
+        }
+
+        let paths = paths
+            .iter()
+            .map(|path| self.format_display_path(path))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let message = TitleFormat::debug("Undo").sub_title(format!("{} files", paths.len()));
+        context.send_text(message).await?;
+
         Ok(format!(
-            "Successfully undid last operation on path: {display_path}"
+            "Successfully restored the files changed in the session:\n{}",
+            paths.join("\n")
         ))
     }
 }
@@ -83,11 +148,14 @@ impl<F: Infrastructure> ExecutableTool for FsUndo<F> {
 mod tests {
     use std::sync::Arc;
 
+    use bytes::Bytes;
     use tempfile::TempDir;
 
     use super::*;
     use crate::attachment::tests::MockInfrastructure;
+    use crate::infra::FsWriteService;
     use crate::tools::registry::tests::Stub;
+    use crate::TestInfrastructure;
 
     #[tokio::test]
     async fn test_successful_undo() {
@@ -101,7 +169,10 @@ mod tests {
         let result = undo
             .call(
                 ToolCallContext::default(),
-                UndoInput { path: test_path.to_string_lossy().to_string() },
+                UndoInput {
+                    path: Some(test_path.to_string_lossy().to_string()),
+                    count: None,
+                },
             )
             .await;
 
@@ -117,6 +188,69 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_undo_count() {
+        let infra = Arc::new(TestInfrastructure::new().file("/test/a.txt", "first"));
+        for content in ["second", "third"] {
+            infra
+                .file_write_service()
+                .write(Path::new("/test/a.txt"), Bytes::from(content))
+                .await
+                .unwrap();
+        }
+        let undo = FsUndo::new(infra.clone());
+
+        let actual = undo
+            .call(
+                ToolCallContext::default(),
+                UndoInput { path: Some("/test/a.txt".to_string()), count: Some(5) },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            actual,
+            "Successfully undid last 2 operations on path: a.txt"
+        );
+        assert_eq!(infra.read_file("/test/a.txt"), Some("first".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_restore_session() {
+        let infra = Arc::new(
+            TestInfrastructure::new()
+                .file("/test/a.txt", "a")
+                .file("/test/b.txt", "b"),
+        );
+        for (path, content) in [
+            ("/test/a.txt", "a2"),
+            ("/test/a.txt", "a3"),
+            ("/test/b.txt", "b2"),
+        ] {
+            infra
+                .file_write_service()
+                .write(Path::new(path), Bytes::from(content))
+                .await
+                .unwrap();
+        }
+        let undo = FsUndo::new(infra.clone());
+
+        let actual = undo
+            .call(
+                ToolCallContext::default(),
+                UndoInput { path: None, count: None },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            actual,
+            "Successfully restored the files changed in the session:\na.txt\nb.txt"
+        );
+        assert_eq!(infra.read_file("/test/a.txt"), Some("a".to_string()));
+        assert_eq!(infra.read_file("/test/b.txt"), Some("b".to_string()));
+    }
+
     #[tokio::test]
     async fn test_tool_name() {
         assert_eq!(
@@ -7,14 +7,19 @@ use bytes::Bytes;
 use console::strip_ansi_codes;
 use forge_display::{DiffFormat, TitleFormat};
 use forge_domain::{
-    EnvironmentService, ExecutableTool, NamedTool, ToolCallContext, ToolDescription, ToolName,
+    EnvironmentService, ExecutableTool, FileChange, NamedTool, ToolCallContext, ToolDescription,
+    ToolName,
 };
+use forge_fs::TextFile;
 use forge_tool_macros::ToolDescription;
 use schemars::JsonSchema;
 use serde::Deserialize;
 
 use crate::tools::syn;
-use crate::tools::utils::{assert_absolute_path, format_display_path};
+use crate::tools::utils::{
+    assert_absolute_path, assert_content_hash, assert_not_ignored, confirm_owners, content_hash,
+    format_display_path, moderate,
+};
 use crate::{FsMetaService, FsReadService, FsWriteService, Infrastructure};
 
 #[derive(Deserialize, JsonSchema)]
@@ -30,6 +35,15 @@ pub struct FSWriteInput {
     /// existing file.
     #[serde(default)]
     pub overwrite: bool,
+    /// If set to true, the diff is returned without writing the file, eg: to
+    /// preview a large change
+    #[serde(default)]
+    pub dry_run: bool,
+    /// The hash of the file when it was last read, as reported by the read,
+    /// create and patch tools. If set, the file is only overwritten when its
+    /// content didn't change since, eg: because the user edited it.
+    #[serde(default)]
+    pub expected_hash: Option<String>,
 }
 
 /// Use it to create a new file at a specified path with the provided content.
@@ -67,25 +81,52 @@ impl<F> NamedTool for FSWrite<F> {
     }
 }
 
+/// Unix permissions of the file, eg: `755`
+#[cfg(unix)]
+async fn file_mode(path: &Path) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let metadata = tokio::fs::metadata(path).await.ok()?;
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+async fn file_mode(_: &Path) -> Option<u32> {
+    None
+}
+
 #[async_trait::async_trait]
 impl<F: Infrastructure> ExecutableTool for FSWrite<F> {
     type Input = FSWriteInput;
 
     async fn call(&self, context: ToolCallContext, input: Self::Input) -> anyhow::Result<String> {
+        // Every write of a dry run session is only previewed
+        let input = FSWriteInput { dry_run: input.dry_run || context.dry_run, ..input };
+
         // Validate absolute path requirement
         let path = Path::new(&input.path);
         assert_absolute_path(path)?;
+        assert_not_ignored(path)?;
+        moderate(&context, &input.content).await?;
 
         // Validate file content if it's a supported language file
         let syntax_warning = syn::validate(&input.path, &input.content);
 
-        // Create parent directories if they don't exist
-        if let Some(parent) = Path::new(&input.path).parent() {
-            tokio::fs::create_dir_all(parent)
-                .await
-                .with_context(|| format!("Failed to create directories: {}", input.path))?;
+        // Create parent directories if they don't exist, unless the write is only
+        // previewed
+        match Path::new(&input.path).parent() {
+            Some(parent) if !input.dry_run => {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .with_context(|| format!("Failed to create directories: {}", input.path))?;
+            }
+            _ => {}
         }
 
+        // The file is locked from the check of its content to its write, so that
+        // tools running in parallel can't change it in between
+        let _lock = self.0.file_write_service().lock(path).await;
+
         // Check if the file exists
         let file_exists = self.0.file_meta_service().is_file(path).await?;
 
@@ -100,20 +141,51 @@ impl<F: Infrastructure> ExecutableTool for FSWrite<F> {
             ));
         }
 
-        // record the file content before they're modified
-        let old_content = if file_exists {
+        // record the file content before they're modified. An overwritten file
+        // keeps its encoding and line endings.
+        let existing_bytes = if file_exists {
             // if file already exists, we should be able to read it.
-            self.0.file_read_service().read_utf8(path).await?
+            Some(self.0.file_read_service().read(path).await?)
+        } else {
+            None
+        };
+        assert_content_hash(
+            path,
+            existing_bytes.as_deref(),
+            input.expected_hash.as_deref(),
+        )?;
+        // if file doesn't exist, we should record it as an empty string.
+        let existing = existing_bytes
+            .as_deref()
+            .map(TextFile::decode)
+            .unwrap_or_default();
+        let old_content = existing.content.clone();
+
+        // Overwritten files keep their permissions, which are reported
+        let mode = if file_exists {
+            file_mode(path).await
         } else {
-            // if file doesn't exist, we should record it as an empty string.
-            "".to_string()
+            None
         };
 
-        // Write file only after validation passes and directories are created
-        self.0
-            .file_write_service()
-            .write(Path::new(&input.path), Bytes::from(input.content.clone()))
-            .await?;
+        // A dry run only previews the change, so nothing is confirmed or written
+        let bytes = existing.encode(&input.content);
+        let owners = if input.dry_run {
+            Vec::new()
+        } else {
+            let owners = confirm_owners(self.0.as_ref(), path).await?;
+
+            // Write file only after validation passes and directories are created
+            self.0
+                .file_write_service()
+                .write_unless_cancelled(
+                    Path::new(&input.path),
+                    Bytes::from(bytes.clone()),
+                    &context.cancellation,
+                )
+                .await?;
+            owners
+        };
 
         let mut result = String::new();
 
@@ -125,19 +197,56 @@ impl<F: Infrastructure> ExecutableTool for FSWrite<F> {
             writeln!(result, "operation: CREATE")?;
         }
         writeln!(result, "total_chars: {}", input.content.len())?;
-        if let Some(warning) = syntax_warning {
+        // A dry run leaves the file, and so its hash, unchanged
+        let hash = if input.dry_run {
+            existing_bytes.as_deref().map(content_hash)
+        } else {
+            Some(content_hash(&bytes))
+        };
+        if let Some(hash) = hash {
+            writeln!(result, "hash: {hash}")?;
+        }
+        if let Some(mode) = mode {
+            writeln!(result, "mode: {mode:o}")?;
+        }
+        if !existing.is_plain() {
+            writeln!(result, "encoding: {}", existing.encoding)?;
+            writeln!(result, "line_ending: {}", existing.line_ending)?;
+        }
+        if input.dry_run {
+            writeln!(result, "dry_run: true")?;
+        }
+        if !owners.is_empty() {
+            writeln!(result, "owners: {}", owners.join(" "))?;
+        }
+        if let Some(warning) = &syntax_warning {
             writeln!(result, "Warning: {}", &warning.to_string())?;
+            if let Some((line, column)) = warning.location() {
+                writeln!(result, "warning_line: {line}")?;
+                writeln!(result, "warning_column: {column}")?;
+            }
         }
+        syn::write_diagnostics(&mut result, &syn::diagnostics(&input.path, &input.content))?;
         writeln!(result, "---")?;
 
         // record the file content after they're modified
-        let new_content = self.0.file_read_service().read_utf8(path).await?;
-        let diff = DiffFormat::format(&old_content, &new_content);
-        let title = if file_exists {
-            writeln!(result, "{}", strip_ansi_codes(&diff))?;
-            "Overwrite"
+        let new_content = if input.dry_run {
+            input.content.clone()
         } else {
-            "Create"
+            TextFile::decode(&self.0.file_read_service().read(path).await?).content
+        };
+        let diff = DiffFormat::format(&old_content, &new_content);
+        let title = match (file_exists, input.dry_run) {
+            (true, dry_run) => {
+                writeln!(result, "{}", strip_ansi_codes(&diff))?;
+                if dry_run {
+                    "Overwrite (dry run)"
+                } else {
+                    "Overwrite"
+                }
+            }
+            (false, true) => "Create (dry run)",
+            (false, false) => "Create",
         };
 
         // Use the formatted path for display
@@ -150,10 +259,31 @@ impl<F: Infrastructure> ExecutableTool for FSWrite<F> {
             ))
             .await?;
 
-        context.send_text(diff).await?;
+        context.send_diff(diff).await?;
+
+        if let Some(frame) = syntax_warning.and_then(|e| e.code_frame(&input.content)) {
+            context.send_text(frame).await?;
+        }
 
         Ok(result)
     }
+
+    async fn preview(&self, input: Self::Input) -> anyhow::Result<Option<FileChange>> {
+        let path = Path::new(&input.path);
+        assert_absolute_path(path)?;
+        assert_not_ignored(path)?;
+
+        let old_content = if self.0.file_meta_service().is_file(path).await? {
+            // Existing files are only changed when overwriting
+            if !input.overwrite {
+                return Ok(None);
+            }
+            TextFile::decode(&self.0.file_read_service().read(path).await?).content
+        } else {
+            String::new()
+        };
+        Ok(Some(FileChange::new(path, old_content, input.content)))
+    }
 }
 
 #[cfg(test)]
@@ -196,6 +326,8 @@ mod test {
                     path: file_path.to_string_lossy().to_string(),
                     content: content.to_string(),
                     overwrite: false,
+                    dry_run: false,
+                    expected_hash: None,
                 },
             )
             .await
@@ -214,6 +346,32 @@ mod test {
         assert_eq!(content, "Hello, World!")
     }
 
+    #[tokio::test]
+    async fn test_fs_write_dry_run() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("test.txt");
+
+        let infra = Arc::new(MockInfrastructure::new());
+        let fs_write = FSWrite::new(infra.clone());
+        let output = fs_write
+            .call(
+                ToolCallContext::default(),
+                FSWriteInput {
+                    path: file_path.to_string_lossy().to_string(),
+                    content: "Hello, World!".to_string(),
+                    overwrite: false,
+                    dry_run: true,
+                    expected_hash: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let actual = infra.file_meta_service().is_file(&file_path).await.unwrap();
+        assert!(!actual);
+        assert!(output.contains("dry_run: true"));
+    }
+
     #[tokio::test]
     async fn test_fs_write_invalid_rust() {
         let temp_dir = TempDir::new().unwrap();
@@ -228,6 +386,8 @@ mod test {
                     path: file_path.to_string_lossy().to_string(),
                     content: "fn main() { let x = ".to_string(),
                     overwrite: false,
+                    dry_run: false,
+                    expected_hash: None,
                 },
             )
             .await;
@@ -253,6 +413,8 @@ mod test {
                     path: file_path.to_string_lossy().to_string(),
                     content: content.to_string(),
                     overwrite: false,
+                    dry_run: false,
+                    expected_hash: None,
                 },
             )
             .await;
@@ -288,6 +450,8 @@ mod test {
                     path: nested_path.to_string_lossy().to_string(),
                     content: content.to_string(),
                     overwrite: false,
+                    dry_run: false,
+                    expected_hash: None,
                 },
             )
             .await
@@ -330,6 +494,8 @@ mod test {
                     path: deep_path.to_string_lossy().to_string(),
                     content: content.to_string(),
                     overwrite: false,
+                    dry_run: false,
+                    expected_hash: None,
                 },
             )
             .await
@@ -373,6 +539,8 @@ mod test {
                     path: path_str,
                     content: content.to_string(),
                     overwrite: false,
+                    dry_run: false,
+                    expected_hash: None,
                 },
             )
             .await
@@ -410,6 +578,8 @@ mod test {
                     path: "relative/path/file.txt".to_string(),
                     content: "test content".to_string(),
                     overwrite: false,
+                    dry_run: false,
+                    expected_hash: None,
                 },
             )
             .await;
@@ -444,6 +614,8 @@ mod test {
                     path: file_path.to_string_lossy().to_string(),
                     content: "New content".to_string(),
                     overwrite: false,
+                    dry_run: false,
+                    expected_hash: None,
                 },
             )
             .await;
@@ -509,6 +681,8 @@ mod test {
                     path: file_path.to_string_lossy().to_string(),
                     content: new_content.to_string(),
                     overwrite: true,
+                    dry_run: false,
+                    expected_hash: None,
                 },
             )
             .await;
@@ -529,4 +703,82 @@ mod test {
             .unwrap();
         assert_eq!(content, new_content);
     }
+
+    #[tokio::test]
+    async fn test_fs_write_refuses_changed_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("test_conflict.txt");
+        let infra = Arc::new(MockInfrastructure::new());
+        infra
+            .file_write_service()
+            .write(&file_path, Bytes::from("Edited by the user"))
+            .await
+            .unwrap();
+        let fixture = |expected_hash: String| FSWriteInput {
+            path: file_path.to_string_lossy().to_string(),
+            content: "New content".to_string(),
+            overwrite: true,
+            dry_run: false,
+            expected_hash: Some(expected_hash),
+        };
+
+        let fs_write = FSWrite::new(infra.clone());
+        let stale = fs_write
+            .call(
+                ToolCallContext::default(),
+                fixture(content_hash(b"Original content")),
+            )
+            .await;
+        let current = fs_write
+            .call(
+                ToolCallContext::default(),
+                fixture(content_hash(b"Edited by the user")),
+            )
+            .await
+            .unwrap();
+
+        assert!(stale
+            .unwrap_err()
+            .to_string()
+            .contains("was changed since it was read"));
+        assert!(current.contains(&format!("hash: {}", content_hash(b"New content"))));
+        let actual = infra
+            .file_read_service()
+            .read_utf8(&file_path)
+            .await
+            .unwrap();
+        assert_eq!(actual, "New content");
+    }
+
+    #[tokio::test]
+    async fn test_fs_write_preview_with_overwrite() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("test_preview.txt");
+
+        let infra = Arc::new(MockInfrastructure::new());
+        infra
+            .file_write_service()
+            .write(&file_path, Bytes::from("Original content"))
+            .await
+            .unwrap();
+
+        let fs_write = FSWrite::new(infra.clone());
+        let actual = fs_write
+            .preview(FSWriteInput {
+                path: file_path.to_string_lossy().to_string(),
+                content: "New content".to_string(),
+                overwrite: true,
+                dry_run: false,
+                expected_hash: None,
+            })
+            .await
+            .unwrap();
+
+        let expected = Some(FileChange::new(
+            &file_path,
+            "Original content",
+            "New content",
+        ));
+        assert_eq!(actual, expected);
+    }
 }
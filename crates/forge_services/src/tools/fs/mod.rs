@@ -2,7 +2,9 @@ mod file_info;
 mod fs_find;
 mod fs_list;
 mod fs_read;
+mod fs_read_many;
 mod fs_remove;
+mod fs_tree;
 mod fs_undo;
 mod fs_write;
 
@@ -10,6 +12,8 @@ pub use file_info::*;
 pub use fs_find::*;
 pub use fs_list::*;
 pub use fs_read::*;
+pub use fs_read_many::*;
 pub use fs_remove::*;
+pub use fs_tree::*;
 pub use fs_undo::*;
 pub use fs_write::*;
@@ -2,11 +2,16 @@ mod completion;
 mod fetch;
 mod followup;
 mod fs;
+mod outline;
 mod patch;
+mod profile;
 mod registry;
 mod shell;
+mod shell_job;
 mod syn;
-mod utils;
+mod task_run;
+mod todo_scan;
+pub(crate) mod utils;
 
 pub use registry::ToolRegistry;
 #[cfg(test)]
@@ -0,0 +1,133 @@
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::bail;
+use forge_display::TitleFormat;
+use forge_domain::{
+    EnvironmentService, ExecutableTool, NamedTool, ToolCallContext, ToolDescription, ToolName,
+};
+use forge_tool_macros::ToolDescription;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::tools::syn;
+use crate::tools::utils::{assert_absolute_path, assert_not_ignored, format_display_path};
+use crate::{FsReadService, Infrastructure};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CodeOutlineInput {
+    /// The absolute path of the source file to outline
+    pub path: String,
+}
+
+/// Lists the definitions of a source file, ie: its functions, structs,
+/// enums, traits, impls, classes and methods, with their first and last
+/// lines. Nested definitions are indented under the ones that contain them,
+/// eg: methods under their impl or class. Use it to get the structure of a
+/// file without reading it whole, then read or patch a definition by its
+/// lines with forge_tool_fs_read and forge_tool_fs_patch instead of guessing
+/// text anchors. Supports Rust, Python, JavaScript/TypeScript, Go and Java.
+/// Requires an absolute path. Read-only with no file modifications.
+#[derive(ToolDescription)]
+pub struct CodeOutline<F>(Arc<F>);
+
+impl<F: Infrastructure> CodeOutline<F> {
+    pub fn new(infra: Arc<F>) -> Self {
+        Self(infra)
+    }
+}
+
+impl<F> NamedTool for CodeOutline<F> {
+    fn tool_name() -> ToolName {
+        ToolName::new("forge_tool_code_outline")
+    }
+}
+
+#[async_trait::async_trait]
+impl<F: Infrastructure> ExecutableTool for CodeOutline<F> {
+    type Input = CodeOutlineInput;
+
+    async fn call(&self, context: ToolCallContext, input: Self::Input) -> anyhow::Result<String> {
+        let path = Path::new(&input.path);
+        assert_absolute_path(path)?;
+        assert_not_ignored(path)?;
+
+        let cwd = self.0.environment_service().get_environment().cwd;
+        context
+            .send_text(TitleFormat::debug("Outline").sub_title(format_display_path(path, &cwd)?))
+            .await?;
+
+        let content = self.0.file_read_service().read_utf8(path).await?;
+        let Some(symbols) = syn::outline(path, &content) else {
+            bail!(
+                "Can't outline {}, only Rust, Python, JavaScript/TypeScript, Go and Java files are supported",
+                input.path
+            );
+        };
+
+        let mut result = String::new();
+        writeln!(result, "---")?;
+        writeln!(result, "path: {}", path.display())?;
+        writeln!(result, "total_lines: {}", content.lines().count())?;
+        writeln!(result, "symbols: {}", symbols.len())?;
+        writeln!(result, "---")?;
+        for symbol in symbols {
+            writeln!(
+                result,
+                "{}{}-{} {} {}",
+                "  ".repeat(symbol.depth),
+                symbol.start_line,
+                symbol.end_line,
+                symbol.kind,
+                symbol.name
+            )?;
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::TestInfrastructure;
+
+    #[tokio::test]
+    async fn test_code_outline() {
+        let infra = Arc::new(TestInfrastructure::new().file(
+            "/test/server.py",
+            "import os\n\nclass Server:\n    def start(self):\n        pass\n\n    def stop(self):\n        pass\n\ndef main():\n    Server().start()\n",
+        ));
+
+        let actual = CodeOutline::new(infra)
+            .call(
+                ToolCallContext::default(),
+                CodeOutlineInput { path: "/test/server.py".to_string() },
+            )
+            .await
+            .unwrap();
+
+        let expected = "---\npath: /test/server.py\ntotal_lines: 11\nsymbols: 4\n---\n\
+                        3-8 class Server\n  \
+                        4-5 def start\n  \
+                        7-8 def stop\n\
+                        10-11 def main\n";
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_code_outline_unsupported_language() {
+        let infra = Arc::new(TestInfrastructure::new().file("/test/notes.txt", "notes"));
+
+        let actual = CodeOutline::new(infra)
+            .call(
+                ToolCallContext::default(),
+                CodeOutlineInput { path: "/test/notes.txt".to_string() },
+            )
+            .await;
+
+        assert!(actual.is_err());
+    }
+}
@@ -1,25 +1,53 @@
+use std::collections::HashSet;
 use std::fmt::Write;
 use std::path::Path;
 use std::sync::Arc;
 
+use anyhow::{anyhow, bail};
 use bytes::Bytes;
+use dissimilar::Chunk;
 use forge_display::{DiffFormat, TitleFormat};
 use forge_domain::{
-    EnvironmentService, ExecutableTool, NamedTool, ToolCallContext, ToolDescription, ToolName,
+    EnvironmentService, ExecutableTool, FileChange, NamedTool, PatchConflict, ToolCallContext,
+    ToolDescription, ToolName,
 };
+use forge_fs::{ForgeFS, TextFile};
 use forge_tool_macros::ToolDescription;
+use regex::{Regex, RegexBuilder};
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use strum_macros::AsRefStr;
 use thiserror::Error;
 use tokio::fs;
 
-// No longer using dissimilar for fuzzy matching
 use crate::tools::syn;
-use crate::tools::utils::{assert_absolute_path, format_display_path};
-use crate::{FsWriteService, Infrastructure};
+use crate::tools::utils::{
+    assert_absolute_path, assert_content_hash, assert_hash, assert_not_ignored, confirm_owners,
+    content_hash, format_display_path, moderate, stream_hash,
+};
+use crate::{FsReadService, FsWriteService, Infrastructure};
+
+/// Minimum similarity, between 0 and 1, of the text that a fuzzy search
+/// matches
+const FUZZY_THRESHOLD: f64 = 0.85;
+
+/// Number of windows of lines that a fuzzy search diffs with the search text,
+/// out of the ones that share the most words with it, so that the search
+/// stays fast in large files
+const MAX_FUZZY_WINDOWS: usize = 32;
+
+/// Maximum size of a compiled search regex. The regex engine matches in linear
+/// time, so there is no catastrophic backtracking, but a pattern with large
+/// repetitions could still take long to compile and match.
+const MAX_REGEX_SIZE: usize = 1 << 20;
 
-// Removed fuzzy matching threshold as we only use exact matching now
+/// Size of a file, in bytes, above which a patch that only changes an exact
+/// text is streamed through the file instead of applied in memory
+const STREAMING_PATCH_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Told to the agent when none of its patches matched and all of them were
+/// queued for the user
+const DEFERRED_MESSAGE: &str = "The search text didn't match, so the patch was queued for the user to resolve at the end of the task. Continue with the other changes and don't retry it.";
 
 /// A match found in the source text. Represents a range in the source text that
 /// can be used for extraction or replacement operations. Stores the position
@@ -50,7 +78,169 @@ impl Range {
             .map(|start| Self::new(start, search.len()))
     }
 
-    // Fuzzy matching removed - we only use exact matching
+    /// Finds the lines that are the most similar to the search text, ignoring
+    /// the whitespace around each line, with their similarity when it reaches
+    /// the threshold. Only the windows of lines that share the most words with
+    /// the search text are compared with it.
+    fn find_fuzzy(source: &str, search: &str) -> Option<(Self, f64)> {
+        let lines = source.split_inclusive('\n').collect::<Vec<_>>();
+        let count = search.lines().count().max(1);
+        if lines.len() < count {
+            return None;
+        }
+
+        let search = normalize(search);
+        let starts = lines
+            .iter()
+            .scan(0, |offset, line| {
+                let start = *offset;
+                *offset += line.len();
+                Some(start)
+            })
+            .collect::<Vec<_>>();
+
+        // Ranking is stable, so that the first windows are kept on a tie
+        let words = search.split_whitespace().collect::<HashSet<_>>();
+        let shared = lines
+            .iter()
+            .map(|line| {
+                line.split_whitespace()
+                    .filter(|word| words.contains(word))
+                    .count()
+            })
+            .collect::<Vec<_>>();
+        let mut windows = shared
+            .windows(count)
+            .map(|window| window.iter().sum::<usize>())
+            .enumerate()
+            .collect::<Vec<_>>();
+        windows.sort_by_key(|(_, shared)| std::cmp::Reverse(*shared));
+        windows.truncate(MAX_FUZZY_WINDOWS);
+        windows.sort_by_key(|(i, _)| *i);
+
+        // The first of the most similar windows wins, as for an exact search
+        windows
+            .into_iter()
+            .map(|(i, _)| {
+                let text = lines[i..i + count].concat();
+                let text = text.trim_end_matches(['\r', '\n']);
+                let similarity = similarity(&normalize(text), &search);
+                (Self::new(starts[i], text.len()), similarity)
+            })
+            .rev()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .filter(|(_, similarity)| *similarity >= FUZZY_THRESHOLD)
+    }
+}
+
+/// Regex that matches a search text with the whitespace differences that a
+/// match mode allows, or None when it must match exactly
+fn whitespace_pattern(search: &str, match_mode: &MatchMode) -> Option<String> {
+    let pattern = match match_mode {
+        MatchMode::Exact => return None,
+        MatchMode::IgnoreIndentation => search
+            .split('\n')
+            .enumerate()
+            .map(|(index, line)| {
+                let text = regex::escape(line.trim_start().trim_end_matches('\r'));
+                // The first line may start in the middle of a line of the file
+                if index == 0 && !line.starts_with([' ', '\t']) {
+                    text
+                } else {
+                    format!(r"[ \t]*{text}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(r"\r?\n"),
+        MatchMode::NormalizeWhitespace => search
+            .split_whitespace()
+            .map(regex::escape)
+            .collect::<Vec<_>>()
+            .join(r"\s+"),
+    };
+    Some(pattern).filter(|pattern| !pattern.is_empty())
+}
+
+/// Matches of an exact search text. When it isn't found as is, it's matched
+/// with the whitespace differences that the match mode allows.
+fn find_exact_matches(
+    source: &str,
+    search: &str,
+    match_mode: &MatchMode,
+) -> Result<Vec<Range>, Error> {
+    let matches = source
+        .match_indices(search)
+        .map(|(start, matched)| Range::new(start, matched.len()))
+        .collect::<Vec<_>>();
+    let Some(pattern) = whitespace_pattern(search, match_mode).filter(|_| matches.is_empty())
+    else {
+        return Ok(matches);
+    };
+    Ok(compile_regex(&pattern)?
+        .find_iter(source)
+        .map(|matched| Range::new(matched.start(), matched.len()))
+        .collect())
+}
+
+/// Leading spaces and tabs of a text
+fn indentation(text: &str) -> &str {
+    &text[..text.len() - text.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Content that replaces a match of the search text with different
+/// whitespace, indented like the file instead of like the search text: the
+/// indentation of the first line of the search text is replaced with the one
+/// of the line of the match.
+fn reindent(content: &str, search: &str, source: &str, patch: Range) -> String {
+    let matched = &source[std::ops::Range::from(patch)];
+    if matched == search {
+        return content.to_string();
+    }
+
+    let line_start = source[..patch.start].rfind('\n').map_or(0, |i| i + 1);
+    let search_indentation = indentation(search);
+    let file_indentation = indentation(&source[line_start..]);
+    content
+        .split('\n')
+        .enumerate()
+        .map(|(index, line)| {
+            if index == 0 {
+                // The match only includes the indentation that it starts with
+                let text = line.strip_prefix(search_indentation).unwrap_or(line);
+                format!("{}{text}", indentation(matched))
+            } else if line.trim().is_empty() {
+                line.to_string()
+            } else {
+                match line.strip_prefix(search_indentation) {
+                    Some(text) => format!("{file_indentation}{text}"),
+                    None => line.to_string(),
+                }
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Text without the whitespace around each of its lines
+fn normalize(text: &str) -> String {
+    text.lines().map(str::trim).collect::<Vec<_>>().join("\n")
+}
+
+/// Similarity of two texts between 0 and 1, ie: the share of their characters
+/// that they have in common
+fn similarity(a: &str, b: &str) -> f64 {
+    let total = a.chars().count() + b.chars().count();
+    if total == 0 {
+        return 1.0;
+    }
+    let common = dissimilar::diff(a, b)
+        .iter()
+        .map(|chunk| match chunk {
+            Chunk::Equal(text) => text.chars().count(),
+            Chunk::Delete(_) | Chunk::Insert(_) => 0,
+        })
+        .sum::<usize>();
+    (2 * common) as f64 / total as f64
 }
 
 impl From<Range> for std::ops::Range<usize> {
@@ -69,57 +259,146 @@ enum Error {
     NoMatch(String),
     #[error("Could not find swap target text: {0}")]
     NoSwapTarget(String),
+    #[error("Invalid search regex: {0}")]
+    InvalidRegex(#[from] regex::Error),
+    #[error("Lines {0} to {1} are out of range, the file has {2} lines")]
+    InvalidLineRange(usize, usize, usize),
+    #[error("Operation '{0}' can't be used with a line range")]
+    UnsupportedLineOperation(String),
+    #[error("Operation '{0}' can't be used with a target, use 'prepend' or 'append'")]
+    UnsupportedTargetOperation(String),
+    #[error(
+        "Found several matches for search text on lines {1}, set occurrence to choose one: {0}"
+    )]
+    AmbiguousMatch(String, String),
+    #[error("Occurrence {0} not found, the search text has {1} matches")]
+    InvalidOccurrence(isize, usize),
 }
 
-fn apply_replacement(
-    source: String,
-    search: &str,
-    operation: &Operation,
-    content: &str,
-) -> Result<String, Error> {
+fn compile_regex(search: &str) -> Result<Regex, Error> {
+    Ok(RegexBuilder::new(search)
+        .size_limit(MAX_REGEX_SIZE)
+        .dfa_size_limit(MAX_REGEX_SIZE)
+        .build()?)
+}
+
+/// Applies a patch to the text that it searches, which is either its search
+/// text or the text that its fuzzy search matched
+fn apply_replacement(mut source: String, search: &str, patch: &Patch) -> Result<String, Error> {
+    let &Patch {
+        ref search_kind,
+        ref match_mode,
+        ref operation,
+        ref content,
+        delete_line,
+        occurrence,
+        ..
+    } = patch;
+
     // Handle empty search string - only certain operations make sense here
     if search.is_empty() {
         return match operation {
             // Append to the end of the file
-            Operation::Append => Ok(format!("{source}{content}")),
+            Operation::Append => {
+                source.push_str(content);
+                Ok(source)
+            }
             // Prepend to the beginning of the file
-            Operation::Prepend => Ok(format!("{content}{source}")),
+            Operation::Prepend => {
+                source.insert_str(0, content);
+                Ok(source)
+            }
             // Replace is equivalent to completely replacing the file
-            Operation::Replace => Ok(content.to_string()),
-            // Swap doesn't make sense with empty search - keep source unchanged
-            Operation::Swap => Ok(source),
+            Operation::Replace | Operation::ReplaceAll => Ok(content.to_string()),
+            // Swap and delete don't make sense with empty search - keep source
+            // unchanged
+            Operation::Swap | Operation::Delete => Ok(source),
+        };
+    }
+
+    // Every occurrence is replaced at once
+    if *operation == Operation::ReplaceAll {
+        return match search_kind {
+            SearchKind::Exact if source.contains(search) => Ok(source.replace(search, content)),
+            SearchKind::Exact => {
+                let matches = find_exact_matches(&source, search, match_mode)?;
+                if matches.is_empty() {
+                    return Err(Error::NoMatch(search.to_string()));
+                }
+                // The later matches are replaced first, so that the position of
+                // the earlier ones doesn't move
+                for patch in matches.into_iter().rev() {
+                    let replacement = reindent(content, search, &source, patch);
+                    source.replace_range(std::ops::Range::from(patch), &replacement);
+                }
+                Ok(source)
+            }
+            SearchKind::Regex => {
+                let regex = compile_regex(search)?;
+                if !regex.is_match(&source) {
+                    return Err(Error::NoMatch(search.to_string()));
+                }
+                Ok(regex.replace_all(&source, content).into_owned())
+            }
         };
     }
 
-    // Find the exact match to operate on
-    let patch =
-        Range::find_exact(&source, search).ok_or_else(|| Error::NoMatch(search.to_string()))?;
+    // Find the match to operate on. The capture groups of a regex are
+    // substituted in the new content, eg: `$1`.
+    let (patch, replacement) = match search_kind {
+        SearchKind::Exact => {
+            let matches = find_exact_matches(&source, search, match_mode)?;
+            let index = select_occurrence(
+                search,
+                matches.len(),
+                |index| line_of(&source, &matches[index]),
+                occurrence,
+            )?;
+            let replacement = reindent(content, search, &source, matches[index]);
+            (matches[index], replacement)
+        }
+        SearchKind::Regex => {
+            let regex = compile_regex(search)?;
+            let captures = regex.captures_iter(&source).collect::<Vec<_>>();
+            let matches = captures
+                .iter()
+                .map(|captures| {
+                    let matched = captures.get(0).expect("group 0 is the whole match");
+                    Range::new(matched.start(), matched.len())
+                })
+                .collect::<Vec<_>>();
+            let index = select_occurrence(
+                search,
+                matches.len(),
+                |index| line_of(&source, &matches[index]),
+                occurrence,
+            )?;
+            let mut replacement = String::new();
+            captures[index].expand(content, &mut replacement);
+            (matches[index], replacement)
+        }
+    };
 
-    // Apply the operation based on its type
+    // Apply the operation in place, so that the file isn't copied again
     match operation {
         // Prepend content before the matched text
-        Operation::Prepend => Ok(format!(
-            "{}{}{}",
-            &source[..patch.start],
-            content,
-            &source[patch.start..]
-        )),
+        Operation::Prepend => source.insert_str(patch.start, &replacement),
 
         // Append content after the matched text
-        Operation::Append => Ok(format!(
-            "{}{}{}",
-            &source[..patch.end()],
-            content,
-            &source[patch.end()..]
-        )),
+        Operation::Append => source.insert_str(patch.end(), &replacement),
 
         // Replace matched text with new content
-        Operation::Replace => Ok(format!(
-            "{}{}{}",
-            &source[..patch.start],
-            content,
-            &source[patch.end()..]
-        )),
+        Operation::Replace | Operation::ReplaceAll => {
+            source.replace_range(std::ops::Range::from(patch), &replacement)
+        }
+
+        // Remove the matched text, and its line if nothing else is left on it
+        Operation::Delete => {
+            source.replace_range(std::ops::Range::from(patch), "");
+            if delete_line {
+                remove_blank_line(&mut source, patch.start);
+            }
+        }
 
         // Swap with another text in the source
         Operation::Swap => {
@@ -132,43 +411,481 @@ fn apply_replacement(
                 || (target_patch.start <= patch.start && target_patch.end() > patch.start)
             {
                 // For overlapping ranges, we just do an ordinary replacement
-                return Ok(format!(
-                    "{}{}{}",
-                    &source[..patch.start],
-                    content,
-                    &source[patch.end()..]
-                ));
+                source.replace_range(std::ops::Range::from(patch), content);
+                return Ok(source);
             }
 
-            // We need to handle different ordering of patches
+            // The later text is replaced first, so that the position of the
+            // earlier one doesn't move
+            let matched = source[std::ops::Range::from(patch)].to_string();
             if patch.start < target_patch.start {
-                // Original text comes first
-                Ok(format!(
-                    "{}{}{}{}{}",
-                    &source[..patch.start],
-                    content,
-                    &source[patch.end()..target_patch.start],
-                    &source[patch.start..patch.end()],
-                    &source[target_patch.end()..]
-                ))
+                source.replace_range(std::ops::Range::from(target_patch), &matched);
+                source.replace_range(std::ops::Range::from(patch), content);
             } else {
-                // Target text comes first
-                Ok(format!(
-                    "{}{}{}{}{}",
-                    &source[..target_patch.start],
-                    &source[patch.start..patch.end()],
-                    &source[target_patch.end()..patch.start],
-                    content,
-                    &source[patch.end()..]
-                ))
+                source.replace_range(std::ops::Range::from(patch), content);
+                source.replace_range(std::ops::Range::from(target_patch), &matched);
+            }
+        }
+    }
+    Ok(source)
+}
+
+/// Index of the match to operate on, among `count` matches: the given
+/// occurrence, starting at 1 or at -1 for the last one, or the only match when
+/// no occurrence is given
+fn select_occurrence(
+    search: &str,
+    count: usize,
+    line: impl Fn(usize) -> usize,
+    occurrence: Option<isize>,
+) -> Result<usize, Error> {
+    if count == 0 {
+        return Err(Error::NoMatch(search.to_string()));
+    }
+    let index = match occurrence {
+        None if count == 1 => Some(0),
+        None => {
+            let lines = (0..count)
+                .map(|index| line(index).to_string())
+                .collect::<Vec<_>>();
+            return Err(Error::AmbiguousMatch(search.to_string(), lines.join(", ")));
+        }
+        Some(occurrence) if occurrence > 0 => Some(occurrence.unsigned_abs() - 1),
+        Some(occurrence) => count.checked_sub(occurrence.unsigned_abs()),
+    };
+    index
+        .filter(|index| *index < count)
+        .ok_or_else(|| Error::InvalidOccurrence(occurrence.unwrap_or_default(), count))
+}
+
+/// Line of a match, starting at 1
+fn line_of(source: &str, patch: &Range) -> usize {
+    source[..patch.start].matches('\n').count() + 1
+}
+
+/// Removes the line at `position` if it only holds whitespace, along with its
+/// line break
+fn remove_blank_line(source: &mut String, position: usize) {
+    let mut start = source[..position].rfind('\n').map_or(0, |i| i + 1);
+    let end = source[position..]
+        .find('\n')
+        .map_or(source.len(), |i| position + i + 1);
+    if !source[start..end].trim().is_empty() {
+        return;
+    }
+    // The last line has no line break of its own, so the previous one is removed
+    if !source[start..end].ends_with('\n') && start > 0 {
+        start -= 1;
+    }
+    source.replace_range(start..end, "");
+}
+
+/// Applies an operation to the lines from `start_line` to `end_line`, both
+/// inclusive and starting at 1
+fn apply_lines(
+    mut source: String,
+    start_line: usize,
+    end_line: usize,
+    operation: &Operation,
+    content: &str,
+) -> Result<String, Error> {
+    let lines = source.split_inclusive('\n').collect::<Vec<_>>();
+    if start_line == 0 || start_line > end_line || end_line > lines.len() {
+        return Err(Error::InvalidLineRange(start_line, end_line, lines.len()));
+    }
+
+    let start = lines[..start_line - 1]
+        .iter()
+        .map(|line| line.len())
+        .sum::<usize>();
+    let end = start
+        + lines[start_line - 1..end_line]
+            .iter()
+            .map(|line| line.len())
+            .sum::<usize>();
+
+    // The content takes the place of whole lines, so it ends with a line break
+    // unless it is added at the end of a file without one
+    let ends_with_newline = source[..end].ends_with('\n');
+    let line = |content: &str| {
+        if content.is_empty() || content.ends_with('\n') || !ends_with_newline {
+            content.to_string()
+        } else {
+            format!("{content}\n")
+        }
+    };
+
+    match operation {
+        Operation::Prepend => source.insert_str(start, &line(content)),
+        Operation::Append if ends_with_newline => source.insert_str(end, &line(content)),
+        Operation::Append => {
+            source.insert(end, '\n');
+            source.insert_str(end + 1, content);
+        }
+        Operation::Replace => source.replace_range(start..end, &line(content)),
+        Operation::Delete => source.replace_range(start..end, ""),
+        Operation::ReplaceAll | Operation::Swap => {
+            return Err(Error::UnsupportedLineOperation(
+                operation.as_ref().to_string(),
+            ))
+        }
+    }
+    Ok(source)
+}
+
+/// Adds content at the start or the end of a file. The content is added as
+/// whole lines, so a line break separates it from the rest of the file.
+fn apply_target(
+    mut source: String,
+    target: &Target,
+    operation: &Operation,
+    content: &str,
+) -> Result<String, Error> {
+    if !matches!(operation, Operation::Prepend | Operation::Append) {
+        return Err(Error::UnsupportedTargetOperation(
+            operation.as_ref().to_string(),
+        ));
+    }
+    let separated = source.is_empty() || content.is_empty();
+    match target {
+        Target::FileStart => {
+            if !separated && !content.ends_with('\n') {
+                source.insert(0, '\n');
             }
+            source.insert_str(0, content);
+        }
+        Target::FileEnd => {
+            if !separated && !source.ends_with('\n') {
+                source.push('\n');
+            }
+            source.push_str(content);
+        }
+    }
+    Ok(source)
+}
+
+/// Line of the occurrence of an anchor regex to operate on, starting at 1,
+/// among the lines that it matches
+fn anchor_line(source: &str, anchor: &str, occurrence: Option<isize>) -> Result<usize, Error> {
+    let regex = compile_regex(anchor)?;
+    let lines = source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| regex.is_match(line))
+        .map(|(index, _)| index + 1)
+        .collect::<Vec<_>>();
+    let index = select_occurrence(anchor, lines.len(), |index| lines[index], occurrence)?;
+    Ok(lines[index])
+}
+
+/// Applies a patch to its line range or anchor line if it has one, else to its
+/// search text
+fn apply_patch(patched: Patched, index: usize, patch: &Patch) -> Result<Patched, Error> {
+    let Patched { content: source, mut occurrences, mut fuzzy_matches } = patched;
+    if let Some(target) = &patch.target {
+        let content = apply_target(source, target, &patch.operation, &patch.content)?;
+        return Ok(Patched { content, occurrences: occurrences + 1, fuzzy_matches });
+    }
+    let lines = match (patch.start_line, &patch.anchor_regex) {
+        (Some(start_line), _) => Some((start_line, patch.end_line.unwrap_or(start_line))),
+        (None, Some(anchor)) => {
+            let line = anchor_line(&source, anchor, patch.occurrence)?;
+            Some((line, line))
+        }
+        (None, None) => None,
+    };
+    if let Some((start_line, end_line)) = lines {
+        let content = apply_lines(
+            source,
+            start_line,
+            end_line,
+            &patch.operation,
+            &patch.content,
+        )?;
+        return Ok(Patched { content, occurrences: occurrences + 1, fuzzy_matches });
+    }
+
+    // A fuzzy match is operated on like the exact text it matched
+    let fuzzy_match = find_fuzzy_match(&source, index, patch);
+    let search = fuzzy_match
+        .as_ref()
+        .map_or(patch.search.as_str(), |fuzzy_match| {
+            fuzzy_match.matched.as_str()
+        });
+    occurrences += count_occurrences(&source, search, patch);
+    let content = apply_replacement(source, search, patch)?;
+    fuzzy_matches.extend(fuzzy_match);
+    Ok(Patched { content, occurrences, fuzzy_matches })
+}
+
+/// Text that a fuzzy patch matches, when its search text isn't found exactly
+fn find_fuzzy_match(source: &str, index: usize, patch: &Patch) -> Option<FuzzyMatch> {
+    if !patch.fuzzy
+        || patch.search_kind != SearchKind::Exact
+        || patch.search.is_empty()
+        || source.contains(&patch.search)
+    {
+        return None;
+    }
+    let (range, confidence) = Range::find_fuzzy(source, &patch.search)?;
+    Some(FuzzyMatch {
+        patch: index + 1,
+        confidence,
+        matched: source[std::ops::Range::from(range)].to_string(),
+    })
+}
+
+/// Number of occurrences of the search text that a patch changes
+fn count_occurrences(source: &str, search: &str, patch: &Patch) -> usize {
+    if patch.operation != Operation::ReplaceAll || search.is_empty() {
+        return 1;
+    }
+    match patch.search_kind {
+        SearchKind::Exact => find_exact_matches(source, search, &patch.match_mode)
+            .map(|matches| matches.len())
+            .unwrap_or_default(),
+        SearchKind::Regex => compile_regex(search)
+            .map(|regex| regex.find_iter(source).count())
+            .unwrap_or_default(),
+    }
+}
+
+/// Text that replaces the search text of a patch that can be applied while
+/// streaming the file, ie: a patch of an exact text that doesn't depend on the
+/// rest of the file
+fn streamed_replacement(patch: &Patch) -> Option<String> {
+    if patch.search.is_empty()
+        || patch.search_kind != SearchKind::Exact
+        || patch.fuzzy
+        || patch.match_mode != MatchMode::Exact
+        || patch.start_line.is_some()
+        || patch.anchor_regex.is_some()
+        || patch.target.is_some()
+        || patch.ensure_trailing_newline
+    {
+        return None;
+    }
+    match patch.operation {
+        Operation::Replace | Operation::ReplaceAll => Some(patch.content.clone()),
+        Operation::Prepend => Some(format!("{}{}", patch.content, patch.search)),
+        Operation::Append => Some(format!("{}{}", patch.search, patch.content)),
+        Operation::Delete if !patch.delete_line => Some(String::new()),
+        Operation::Delete | Operation::Swap => None,
+    }
+}
+
+/// Text that a fuzzy search matched, reported so that the model can verify
+/// that the right text was changed
+#[derive(Debug, Clone, PartialEq)]
+struct FuzzyMatch {
+    /// Position of the patch, starting at 1
+    patch: usize,
+    confidence: f64,
+    matched: String,
+}
+
+/// Maximum number of files next to a patched one whose imports are looked up
+const MAX_SIBLINGS: usize = 50;
+
+/// Maximum size of a file next to a patched one whose imports are looked up
+const MAX_SIBLING_BYTES: u64 = 256 * 1024;
+
+/// Contents of the files next to the given one with the same extension,
+/// whose imports are reused to fix the imports of a patched file. Files that
+/// can't be read are skipped.
+async fn sibling_sources(path: &Path) -> Vec<String> {
+    let (Some(dir), Some(extension)) = (path.parent(), path.extension()) else {
+        return Vec::new();
+    };
+    let Ok(mut entries) = fs::read_dir(dir).await else {
+        return Vec::new();
+    };
+
+    let mut sources = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if sources.len() >= MAX_SIBLINGS {
+            break;
+        }
+        let sibling = entry.path();
+        if sibling == path || sibling.extension() != Some(extension) {
+            continue;
+        }
+        let small = entry
+            .metadata()
+            .await
+            .is_ok_and(|metadata| metadata.is_file() && metadata.len() <= MAX_SIBLING_BYTES);
+        if !small {
+            continue;
+        }
+        if let Ok(source) = fs::read_to_string(&sibling).await {
+            sources.push(source);
+        }
+    }
+    sources
+}
+
+/// Content of a file after patches are applied
+#[derive(Debug, Clone, PartialEq)]
+struct Patched {
+    content: String,
+    /// Number of occurrences of the search texts that were changed
+    occurrences: usize,
+    fuzzy_matches: Vec<FuzzyMatch>,
+}
+
+impl Patched {
+    /// Ends the content with a line break when the patch asks for it
+    fn ensure_trailing_newline(mut self, patch: &Patch) -> Self {
+        if patch.ensure_trailing_newline
+            && !self.content.is_empty()
+            && !self.content.ends_with('\n')
+        {
+            self.content.push('\n');
+        }
+        self
+    }
+}
+
+/// Applies the patches in order, so that each one sees the changes of the
+/// previous ones. Nothing is changed when any of them fails to apply.
+fn apply_patches(source: String, patches: &[Patch]) -> anyhow::Result<Patched> {
+    if patches.is_empty() {
+        bail!("No patches to apply, provide at least one");
+    }
+    let patched = Patched { content: source, occurrences: 0, fuzzy_matches: Vec::new() };
+    if let [patch] = patches {
+        return Ok(apply_patch(patched, 0, patch)?.ensure_trailing_newline(patch));
+    }
+
+    patches
+        .iter()
+        .enumerate()
+        .try_fold(patched, |patched, (i, patch)| {
+            apply_patch(patched, i, patch)
+                .map(|patched| patched.ensure_trailing_newline(patch))
+                .map_err(|err| {
+                    anyhow!(
+                        "Patch {} of {} failed, the file was not changed: {err}",
+                        i + 1,
+                        patches.len()
+                    )
+                })
+        })
+}
+
+/// Applies the patches in order like [`apply_patches`], except that the
+/// patches whose search text doesn't match are skipped so that they can be
+/// resolved later. Returns the indexes of the skipped patches with the text
+/// that didn't match.
+fn apply_patches_deferring(
+    source: String,
+    patches: &[Patch],
+) -> anyhow::Result<(Patched, Vec<(usize, String)>)> {
+    if patches.is_empty() {
+        bail!("No patches to apply, provide at least one");
+    }
+    let mut patched = Patched { content: source, occurrences: 0, fuzzy_matches: Vec::new() };
+    let mut deferred = Vec::new();
+    for (i, patch) in patches.iter().enumerate() {
+        match apply_patch(patched.clone(), i, patch) {
+            Ok(result) => patched = result.ensure_trailing_newline(patch),
+            Err(Error::NoMatch(search)) => deferred.push((i, search)),
+            Err(err) if patches.len() == 1 => return Err(err.into()),
+            Err(err) => bail!(
+                "Patch {} of {} failed, the file was not changed: {err}",
+                i + 1,
+                patches.len()
+            ),
         }
     }
+    Ok((patched, deferred))
+}
+
+/// Queues the patches that didn't match for the user to resolve at the end of
+/// the task
+async fn queue_conflicts(
+    context: &ToolCallContext,
+    path: &Path,
+    patches: &[Patch],
+    deferred: &[(usize, String)],
+) -> anyhow::Result<()> {
+    for (index, search) in deferred {
+        let patch = &patches[*index];
+        context
+            .send_patch_conflict(PatchConflict {
+                path: path.to_path_buf(),
+                operation: patch.operation.as_ref().to_string(),
+                search: search.clone(),
+                content: patch.content.clone(),
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+/// Text of a range of lines of the content, with their line breaks
+fn lines_text(content: &str, lines: &std::ops::Range<usize>) -> String {
+    content
+        .split_inclusive('\n')
+        .skip(lines.start)
+        .take(lines.len())
+        .collect()
+}
+
+/// Numbers of the deferred patches, starting at 1
+fn deferred_numbers(deferred: &[(usize, String)]) -> String {
+    deferred
+        .iter()
+        .map(|(index, _)| (index + 1).to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Where content is added in a file, regardless of the search text
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    /// Before the first line of the file
+    FileStart,
+
+    /// After the last line of the file
+    FileEnd,
+}
+
+/// How the search text is matched
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchKind {
+    /// The search text must appear exactly, including its whitespace
+    #[default]
+    Exact,
+
+    /// The search text is a regex, eg: `fn\s+main\(\)`. Its capture groups
+    /// can be used in the content as `$1` or `${name}`, and `$$` is a literal
+    /// `$`.
+    Regex,
+}
+
+/// Whitespace differences tolerated when an exact search text isn't found as
+/// is. The content keeps the indentation of the file.
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    /// The search text must appear exactly
+    #[default]
+    Exact,
+
+    /// The indentation of each line may differ, eg: tabs instead of spaces
+    IgnoreIndentation,
+
+    /// Every run of whitespace, including line breaks, matches any other
+    NormalizeWhitespace,
 }
 
 /// Operation types that can be performed on matched text
-#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq, AsRefStr)]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default, PartialEq, AsRefStr)]
 #[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
 pub enum Operation {
     /// Prepend content before the matched text
     Prepend,
@@ -177,39 +894,170 @@ pub enum Operation {
     Append,
 
     /// Replace the matched text with new content
+    #[default]
     Replace,
 
+    /// Replace every occurrence of the matched text with new content
+    ReplaceAll,
+
     /// Swap the matched text with another text (search for the second text and
     /// swap them)
     Swap,
+
+    /// Delete the matched text. The content is ignored.
+    Delete,
 }
 
-#[derive(Deserialize, JsonSchema)]
+/// A text operation on an occurrence of a pattern
+#[derive(Deserialize, JsonSchema, Debug, Clone, Default, PartialEq)]
 #[serde(rename_all = "snake_case")]
-pub struct Input {
-    /// The path to the file to modify
-    pub path: String,
-
+pub struct Patch {
     /// The text to search for in the source. If empty, operation applies to the
-    /// end of the file.
+    /// end of the file, and 'replace' replaces the whole file. Not needed when
+    /// start_line, anchor_regex or target is set.
+    #[serde(default)]
     pub search: String,
 
+    /// How the search text is matched, 'exact' by default or 'regex'
+    #[serde(default)]
+    pub search_kind: SearchKind,
+
+    /// Whitespace differences tolerated by an exact search: 'exact' by
+    /// default, 'ignore_indentation' or 'normalize_whitespace'. The content
+    /// is reindented like the matched lines.
+    #[serde(default)]
+    pub match_mode: MatchMode,
+
+    /// If set to true and the search text isn't found exactly, the lines that
+    /// are the most similar to it are matched instead, ignoring indentation
+    /// and trailing whitespace. The matched text and the confidence of the
+    /// match are reported.
+    #[serde(default)]
+    pub fuzzy: bool,
+
+    /// Which occurrence of the search text to operate on, starting at 1, or
+    /// -1 for the last one. Required when the search text appears more than
+    /// once, in which case the lines of the matches are reported.
+    #[serde(default)]
+    pub occurrence: Option<isize>,
+
+    /// First line to operate on, starting at 1. When set, the operation applies
+    /// to the lines from start_line to end_line instead of the search text:
+    /// 'prepend' adds the content before them, 'append' after them,
+    /// 'replace' replaces them and 'delete' removes them.
+    #[serde(default)]
+    pub start_line: Option<usize>,
+
+    /// Last line to operate on, inclusive. Defaults to start_line.
+    #[serde(default)]
+    pub end_line: Option<usize>,
+
+    /// A regex matching the line to operate on, eg: `^\s*}$`, with occurrence
+    /// choosing among several matching lines. The operation applies to that
+    /// line as for start_line: 'prepend' inserts the content before it and
+    /// 'append' after it. Ignored when start_line is set.
+    #[serde(default)]
+    pub anchor_regex: Option<String>,
+
+    /// Adds the content at 'file_start' or 'file_end' with a 'prepend' or
+    /// 'append' operation, on lines of its own. Clearer than an empty search
+    /// text, and takes precedence over the search text and lines.
+    #[serde(default)]
+    pub target: Option<Target>,
+
     /// The operation to perform on the matched text. Possible options are only
-    /// 'prepend', 'append', 'replace', and 'swap'.
+    /// 'prepend', 'append', 'replace', 'replace_all', 'swap' and 'delete'.
     pub operation: Operation,
 
+    /// If set to true, a 'delete' operation also removes the line of the
+    /// matched text when nothing but whitespace is left on it
+    #[serde(default)]
+    pub delete_line: bool,
+
+    /// If set to true, a line break is added at the end of the file after the
+    /// patch if it doesn't end with one
+    #[serde(default)]
+    pub ensure_trailing_newline: bool,
+
     /// The content to use for the operation (replacement text, text to
-    /// prepend/append, or target text for swap operations)
+    /// prepend/append, or target text for swap operations). Ignored by
+    /// delete operations.
+    #[serde(default)]
     pub content: String,
 }
 
-/// Modifies files with targeted text operations on matched patterns. Supports
-/// prepend, append, replace, swap, delete operations on first pattern
-/// occurrence. Ideal for precise changes to configs, code, or docs while
-/// preserving context. Not suitable for complex refactoring or modifying all
-/// pattern occurrences - use forge_tool_fs_create instead for complete
-/// rewrites and forge_tool_fs_undo for undoing the last operation. Fails if
-/// search pattern isn't found.
+#[derive(JsonSchema, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct Input {
+    /// The path to the file to modify
+    pub path: String,
+
+    /// The patches to apply to the file, in order. Each patch sees the
+    /// changes of the previous ones, and the file is only changed when all of
+    /// them apply.
+    pub patches: Vec<Patch>,
+
+    /// If set to true, the diff is returned without changing the file, eg: to
+    /// preview a large edit
+    pub dry_run: bool,
+
+    /// The hash of the file when it was last read, as reported by the read,
+    /// create and patch tools. If set, the file is only patched when its
+    /// content didn't change since, eg: because the user edited it.
+    pub expected_hash: Option<String>,
+}
+
+/// Input as sent by the model, which may also be a single patch without the
+/// `patches` array
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawInput {
+    Patches {
+        path: String,
+        patches: Vec<Patch>,
+        #[serde(default)]
+        dry_run: bool,
+        #[serde(default)]
+        expected_hash: Option<String>,
+    },
+    Patch {
+        path: String,
+        #[serde(flatten)]
+        patch: Patch,
+        #[serde(default)]
+        dry_run: bool,
+        #[serde(default)]
+        expected_hash: Option<String>,
+    },
+}
+
+impl<'de> Deserialize<'de> for Input {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match RawInput::deserialize(deserializer)? {
+            RawInput::Patches { path, patches, dry_run, expected_hash } => {
+                Input { path, patches, dry_run, expected_hash }
+            }
+            RawInput::Patch { path, patch, dry_run, expected_hash } => {
+                Input { path, patches: vec![patch], dry_run, expected_hash }
+            }
+        })
+    }
+}
+
+/// Modifies a file with targeted text operations: prepend, append, replace,
+/// swap or delete a matched pattern. The search text is matched exactly, or as
+/// a regex when search_kind is 'regex', with capture groups used in the content
+/// as $1 or ${name}. Set occurrence to pick one of several matches, replace_all
+/// to change every match, fuzzy to tolerate differences in whitespace and
+/// verify the matched text that is reported, or start_line and end_line, or an
+/// anchor_regex matching a line, to address lines directly. Several patches can
+/// be sent in one call; they apply in order and the file is left unchanged if
+/// any fails. Set dry_run to only get the diff. Fails if the pattern isn't
+/// found. Use forge_tool_fs_create for complete rewrites and forge_tool_fs_undo
+/// to undo the last change. A single exact patch of a large file is streamed
+/// through it. In Rust files, the imports that a patch needs are added from the
+/// files next to it, and the ones it leaves unused are removed; both are
+/// reported.
 #[derive(ToolDescription)]
 pub struct ApplyPatchJson<F>(Arc<F>);
 
@@ -237,6 +1085,113 @@ impl<F: Infrastructure> ApplyPatchJson<F> {
         // Use the shared utility function
         format_display_path(path, cwd)
     }
+
+    /// Applies a patch to a file larger than `limit` bytes by streaming the
+    /// file, so that it is never loaded in memory. Returns None when the patch
+    /// or the file needs the patch to be applied in memory.
+    async fn call_streaming(
+        &self,
+        context: &ToolCallContext,
+        input: &Input,
+        limit: u64,
+    ) -> anyhow::Result<Option<String>> {
+        let [patch] = input.patches.as_slice() else {
+            return Ok(None);
+        };
+        let Some(replacement) = streamed_replacement(patch) else {
+            return Ok(None);
+        };
+        let path = Path::new(&input.path);
+        let reader = self.0.file_read_service();
+        let size = reader.size(path).await?;
+        if size <= limit || !ForgeFS::is_streamable(reader.open(path).await?).await? {
+            return Ok(None);
+        }
+        assert_hash(
+            path,
+            Some(&stream_hash(reader.open(path).await?).await?),
+            input.expected_hash.as_deref(),
+        )?;
+
+        let matches = ForgeFS::find_streaming(reader.open(path).await?, &patch.search).await?;
+        let offsets = if patch.operation == Operation::ReplaceAll {
+            if matches.is_empty() {
+                return Err(Error::NoMatch(patch.search.clone()).into());
+            }
+            matches.iter().map(|m| m.offset).collect::<Vec<_>>()
+        } else {
+            let index = select_occurrence(
+                &patch.search,
+                matches.len(),
+                |index| matches[index].line,
+                patch.occurrence,
+            )?;
+            vec![matches[index].offset]
+        };
+
+        let owners = if input.dry_run {
+            Vec::new()
+        } else {
+            let owners = confirm_owners(self.0.as_ref(), path).await?;
+            if context.is_cancelled() {
+                bail!(
+                    "Cancelled by the user before {} was written",
+                    path.display()
+                );
+            }
+            self.0
+                .file_write_service()
+                .replace(path, &offsets, patch.search.len(), &replacement)
+                .await?;
+            owners
+        };
+        let count = offsets.len() as u64;
+        let total_bytes = (size + count * replacement.len() as u64)
+            .saturating_sub(count * patch.search.len() as u64);
+
+        let mut result = String::new();
+        writeln!(result, "---")?;
+        writeln!(result, "path: {}", path.display())?;
+        writeln!(result, "total_bytes: {total_bytes}")?;
+        writeln!(result, "occurrences: {count}")?;
+        writeln!(
+            result,
+            "hash: {}",
+            stream_hash(reader.open(path).await?).await?
+        )?;
+        writeln!(result, "streamed: true")?;
+        if input.dry_run {
+            writeln!(result, "dry_run: true")?;
+        }
+        if !owners.is_empty() {
+            writeln!(result, "owners: {}", owners.join(" "))?;
+        }
+        writeln!(result, "---")?;
+
+        // The file is too large to diff, so only the changed text is shown
+        let diff = DiffFormat::format(&patch.search, &replacement);
+        writeln!(result, "{}", console::strip_ansi_codes(&diff).as_ref())?;
+
+        let display_path = self.format_display_path(path)?;
+        let title = match count {
+            1 => display_path,
+            count => format!("{display_path} ({count} occurrences)"),
+        };
+        let operation = if input.dry_run {
+            "Patch (dry run, streamed)"
+        } else {
+            "Patch (streamed)"
+        };
+        context
+            .send_text(format!(
+                "{}",
+                TitleFormat::debug(operation).sub_title(title)
+            ))
+            .await?;
+        context.send_diff(diff).await?;
+
+        Ok(Some(result))
+    }
 }
 
 #[async_trait::async_trait]
@@ -244,83 +1199,286 @@ impl<F: Infrastructure> ExecutableTool for ApplyPatchJson<F> {
     type Input = Input;
 
     async fn call(&self, context: ToolCallContext, patch: Self::Input) -> anyhow::Result<String> {
+        // Every patch of a dry run session is only previewed
+        let patch = Input { dry_run: patch.dry_run || context.dry_run, ..patch };
         let path = Path::new(&patch.path);
         assert_absolute_path(path)?;
+        assert_not_ignored(path)?;
+        for patch in &patch.patches {
+            moderate(&context, &patch.content).await?;
+        }
 
-        // Read the original content once
-        let mut current_content = fs::read_to_string(path)
-            .await
-            .map_err(Error::FileOperation)?;
+        // The file is locked from its read to its write, so that patches of the
+        // same file made in parallel don't overwrite each other
+        let _lock = self.0.file_write_service().lock(path).await;
+
+        // Large files are streamed when the patch allows it, so that they aren't
+        // loaded in memory
+        let limit = self
+            .0
+            .environment_service()
+            .get_environment()
+            .resource_limits
+            .streaming_patch_bytes
+            .unwrap_or(STREAMING_PATCH_BYTES);
+        let defer = context.defer_patch_conflicts && !patch.dry_run;
+        match self.call_streaming(&context, &patch, limit).await {
+            Ok(Some(result)) => return Ok(result),
+            Ok(None) => {}
+            Err(err) if defer => match err.downcast::<Error>() {
+                Ok(Error::NoMatch(search)) => {
+                    let deferred = [(0, search)];
+                    queue_conflicts(&context, path, &patch.patches, &deferred).await?;
+                    return Ok(format!(
+                        "---\npath: {}\ndeferred_patches: 1\n---\n{DEFERRED_MESSAGE}\n",
+                        path.display()
+                    ));
+                }
+                Ok(err) => return Err(err.into()),
+                Err(err) => return Err(err),
+            },
+            Err(err) => return Err(err),
+        }
+
+        // Read the original content once, decoded to UTF-8 so that files in
+        // other encodings or with CRLF line endings can be patched too
+        let bytes = self.0.file_read_service().read(path).await?;
+        assert_content_hash(path, Some(&bytes), patch.expected_hash.as_deref())?;
+        let mut file = TextFile::decode(&bytes);
 
         // Save the old content before modification for diff generation
-        let old_content = current_content.clone();
+        let old_content = std::mem::take(&mut file.content);
+
+        // Apply the patches in memory, so that the file is only written when all
+        // of them apply. When the agent defers conflicts, the patches that don't
+        // match are queued for the user instead and the others are still applied.
+        let (Patched { content: current_content, occurrences, fuzzy_matches }, deferred) = if defer
+        {
+            apply_patches_deferring(old_content.clone(), &patch.patches)?
+        } else {
+            (
+                apply_patches(old_content.clone(), &patch.patches)?,
+                Vec::new(),
+            )
+        };
+        if deferred.len() == patch.patches.len() {
+            queue_conflicts(&context, path, &patch.patches, &deferred).await?;
+            return Ok(format!(
+                "---\npath: {}\ndeferred_patches: {}\n---\n{DEFERRED_MESSAGE}\n",
+                path.display(),
+                deferred_numbers(&deferred)
+            ));
+        }
 
-        // Apply the replacement
-        current_content = apply_replacement(
-            current_content,
-            &patch.search,
-            &patch.operation,
-            &patch.content,
-        )?;
+        // Names that the patches started to use are imported like the files next
+        // to this one import them, and the imports that they left unused are
+        // removed
+        let imports = if syn::supports_imports(path) {
+            syn::fix_imports(
+                path,
+                &old_content,
+                &current_content,
+                &sibling_sources(path).await,
+            )
+        } else {
+            None
+        };
+        let current_content = match &imports {
+            Some(imports) => imports.content.clone(),
+            None => current_content,
+        };
 
         // Format the display path for output
         let display_path = self.format_display_path(path)?;
 
-        // Generate diff between old and new content
-        let diff = DiffFormat::format(&old_content, &current_content);
-
-        // Write final content to file after all patches are applied
-        self.0
-            .file_write_service()
-            .write(path, Bytes::from(current_content.clone()))
-            .await?;
+        // Generate diff between old and new content, with each hunk titled with
+        // the definitions that it changes, eg: a function and its impl
+        let format = DiffFormat::default();
+        let hunks = format.hunks(&old_content, &current_content);
+        let lines = hunks.iter().map(|hunk| hunk.changed).collect::<Vec<_>>();
+        let symbols = syn::enclosing_symbols(path, &current_content, &lines);
+        let diff = format
+            .titles(symbols.clone())
+            .render(&old_content, &current_content);
+
+        // A dry run only previews the change, so nothing is confirmed or written
+        // and the file keeps its hash
+        let (owners, hash) = if patch.dry_run {
+            (Vec::new(), content_hash(&bytes))
+        } else {
+            let owners = confirm_owners(self.0.as_ref(), path).await?;
+
+            // Write final content to file after all patches are applied
+            let bytes = file.encode(&current_content);
+            let hash = content_hash(&bytes);
+            self.0
+                .file_write_service()
+                .write_unless_cancelled(path, Bytes::from(bytes), &context.cancellation)
+                .await?;
+            (owners, hash)
+        };
 
         let mut result = String::new();
 
         writeln!(result, "---")?;
         writeln!(result, "path: {}", path.display())?;
         writeln!(result, "total_chars: {}", current_content.len())?;
+        writeln!(result, "occurrences: {occurrences}")?;
+        writeln!(result, "hash: {hash}")?;
+        if !file.is_plain() {
+            writeln!(result, "encoding: {}", file.encoding)?;
+            writeln!(result, "line_ending: {}", file.line_ending)?;
+        }
+        if patch.dry_run {
+            writeln!(result, "dry_run: true")?;
+        }
+        if !fuzzy_matches.is_empty() {
+            writeln!(result, "fuzzy_matches:")?;
+            for fuzzy_match in &fuzzy_matches {
+                writeln!(result, "  - patch: {}", fuzzy_match.patch)?;
+                writeln!(result, "    confidence: {:.2}", fuzzy_match.confidence)?;
+            }
+        }
+        if !owners.is_empty() {
+            writeln!(result, "owners: {}", owners.join(" "))?;
+        }
+        if !deferred.is_empty() {
+            writeln!(result, "deferred_patches: {}", deferred_numbers(&deferred))?;
+        }
+        if !hunks.is_empty() {
+            writeln!(result, "hunks:")?;
+            for (hunk, symbol) in hunks.iter().zip(&symbols) {
+                writeln!(result, "  - line: {}", hunk.changed + 1)?;
+                if let Some(symbol) = symbol {
+                    writeln!(result, "    symbol: {symbol}")?;
+                }
+                // The lines of the hunk are reported like in a unified diff, with
+                // their text as JSON strings, so that editors don't have to parse
+                // the diff
+                writeln!(result, "    old_start: {}", hunk.old.start + 1)?;
+                writeln!(result, "    old_count: {}", hunk.old.len())?;
+                writeln!(result, "    new_start: {}", hunk.new.start + 1)?;
+                writeln!(result, "    new_count: {}", hunk.new.len())?;
+                writeln!(
+                    result,
+                    "    old_text: {}",
+                    serde_json::to_string(&lines_text(&old_content, &hunk.old))?
+                )?;
+                writeln!(
+                    result,
+                    "    new_text: {}",
+                    serde_json::to_string(&lines_text(&current_content, &hunk.new))?
+                )?;
+            }
+        }
+        if let Some(imports) = &imports {
+            for (key, paths) in [
+                ("imports_added", &imports.added),
+                ("imports_removed", &imports.removed),
+            ] {
+                if !paths.is_empty() {
+                    writeln!(result, "{key}:")?;
+                    for path in paths {
+                        writeln!(result, "  - {path}")?;
+                    }
+                }
+            }
+        }
 
         // Check for syntax errors
-        if let Some(warning) = syn::validate(path, &current_content).map(|e| e.to_string()) {
+        let syntax_warning = syn::validate(path, &current_content);
+        if let Some(warning) = &syntax_warning {
             writeln!(result, "warning:{warning}")?;
+            if let Some((line, column)) = warning.location() {
+                writeln!(result, "warning_line: {line}")?;
+                writeln!(result, "warning_column: {column}")?;
+            }
         }
+        syn::write_diagnostics(&mut result, &syn::diagnostics(path, &current_content))?;
 
         writeln!(result, "---")?;
 
+        // The text that a fuzzy search matched may not be the intended one
+        for fuzzy_match in &fuzzy_matches {
+            writeln!(
+                result,
+                "Patch {} matched this text, verify that it is the intended one:\n{}\n",
+                fuzzy_match.patch, fuzzy_match.matched
+            )?;
+        }
+
+        if !deferred.is_empty() {
+            writeln!(
+                result,
+                "Patches {} didn't match and were queued for the user to resolve at the end of the task, don't retry them.\n",
+                deferred_numbers(&deferred)
+            )?;
+        }
+
         writeln!(result, "{}", console::strip_ansi_codes(&diff).as_ref())?;
 
+        // Replacing every occurrence may change more than the diff shows at a glance
+        let title = match occurrences {
+            1 => display_path,
+            count => format!("{display_path} ({count} occurrences)"),
+        };
+        let operation = if patch.dry_run {
+            "Patch (dry run)"
+        } else {
+            "Patch"
+        };
         context
             .send_text(format!(
                 "{}",
-                TitleFormat::debug("Patch").sub_title(display_path)
+                TitleFormat::debug(operation).sub_title(title)
             ))
             .await?;
 
         // Output diff either to sender or println
-        context.send_text(diff).await?;
+        context.send_diff(diff).await?;
+        queue_conflicts(&context, path, &patch.patches, &deferred).await?;
+
+        // Point at the syntax error so that it can be spotted without reading
+        // the whole file
+        if let Some(frame) = syntax_warning.and_then(|e| e.code_frame(&current_content)) {
+            context.send_text(frame).await?;
+        }
 
         // Return the final result
         Ok(result)
     }
+
+    async fn preview(&self, patch: Self::Input) -> anyhow::Result<Option<FileChange>> {
+        let path = Path::new(&patch.path);
+        assert_absolute_path(path)?;
+        assert_not_ignored(path)?;
+
+        let bytes = self.0.file_read_service().read(path).await?;
+        let old_content = TextFile::decode(&bytes).content;
+        let patched = apply_patches(old_content.clone(), &patch.patches)?;
+        Ok(Some(FileChange::new(path, old_content, patched.content)))
+    }
 }
 
 #[cfg(test)]
 mod test {
 
+    use pretty_assertions::assert_eq;
+
     use super::*;
     use crate::tools::utils::TempDir;
+    use crate::TestInfrastructure;
 
     // Enhanced test helper for running multiple operations
     #[derive(Debug)]
     struct PatchTest {
         initial: String,
-        patches: Vec<Patch>,
+        patches: Vec<PatchStep>,
     }
 
     // Single operation with its result
     #[derive(Debug)]
-    struct Patch {
+    struct PatchStep {
         operation: PatchOperation,
         result: Result<String, String>,
     }
@@ -331,6 +1489,7 @@ mod test {
         search: String,
         operation: Operation,
         content: String,
+        delete_line: bool,
     }
 
     // fmt::Display implementation removed in favor of using assert_debug_snapshot!
@@ -346,8 +1505,9 @@ mod test {
                 search: search.to_string(),
                 operation: Operation::Replace,
                 content: content.to_string(),
+                delete_line: false,
             };
-            self.patches.push(Patch {
+            self.patches.push(PatchStep {
                 operation,
                 result: Err("Not executed yet".to_string()), // Placeholder
             });
@@ -360,8 +1520,9 @@ mod test {
                 search: search.to_string(),
                 operation: Operation::Prepend,
                 content: content.to_string(),
+                delete_line: false,
             };
-            self.patches.push(Patch {
+            self.patches.push(PatchStep {
                 operation,
                 result: Err("Not executed yet".to_string()), // Placeholder
             });
@@ -374,8 +1535,9 @@ mod test {
                 search: search.to_string(),
                 operation: Operation::Append,
                 content: content.to_string(),
+                delete_line: false,
             };
-            self.patches.push(Patch {
+            self.patches.push(PatchStep {
                 operation,
                 result: Err("Not executed yet".to_string()), // Placeholder
             });
@@ -388,8 +1550,25 @@ mod test {
                 search: search.to_string(),
                 operation: Operation::Swap,
                 content: target.to_string(),
+                delete_line: false,
+            };
+            self.patches.push(PatchStep {
+                operation,
+                result: Err("Not executed yet".to_string()), // Placeholder
+            });
+            self
+        }
+
+        /// Delete matched text, and its line if `delete_line` is set and
+        /// nothing else is left on it
+        fn delete(mut self, search: impl ToString, delete_line: bool) -> Self {
+            let operation = PatchOperation {
+                search: search.to_string(),
+                operation: Operation::Delete,
+                content: String::new(),
+                delete_line,
             };
-            self.patches.push(Patch {
+            self.patches.push(PatchStep {
                 operation,
                 result: Err("Not executed yet".to_string()), // Placeholder
             });
@@ -402,11 +1581,17 @@ mod test {
 
             for op_result in &mut self.patches {
                 // Apply the operation
+                let patch = Patch {
+                    search: op_result.operation.search.clone(),
+                    operation: op_result.operation.operation.clone(),
+                    delete_line: op_result.operation.delete_line,
+                    content: op_result.operation.content.clone(),
+                    ..Default::default()
+                };
                 let result = match apply_replacement(
                     current_content.clone(),
                     &op_result.operation.search,
-                    &op_result.operation.operation,
-                    &op_result.operation.content,
+                    &patch,
                 ) {
                     Ok(content) => {
                         // Update the current content for the next operation
@@ -470,6 +1655,712 @@ mod test {
         insta::assert_debug_snapshot!(test);
     }
 
+    #[test]
+    fn comprehensive_delete_tests() {
+        // Create a test specifically for delete operations
+        let test = PatchTest::new(
+            "fn main() {\n    let unused = 1;\n    println!(\"Hello World\");\n}\n// TODO",
+        )
+        // Delete part of a line
+        .delete(" World", false)
+        // Delete a whole line, leaving it empty
+        .delete("let unused = 1;", false)
+        // Delete the now-empty line as well
+        .delete("println!(\"Hello\");", true)
+        // The line is kept when text is left on it
+        .delete("main", true)
+        // The last line has no line break of its own
+        .delete("// TODO", true)
+        // Empty search keeps the source unchanged
+        .delete("", true)
+        // Missing text
+        .delete("nonexistent", false)
+        .execute_all();
+
+        // Snapshot the delete test results using Debug representation
+        insta::assert_debug_snapshot!(test);
+    }
+
+    #[test]
+    fn test_apply_patches() {
+        let fixture = vec![
+            Patch {
+                search: "foo".to_string(),
+                operation: Operation::Replace,
+                content: "qux".to_string(),
+                ..Default::default()
+            },
+            Patch {
+                search: "qux bar".to_string(),
+                operation: Operation::Append,
+                content: "!".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let actual = apply_patches("foo bar baz".to_string(), &fixture).unwrap();
+
+        let expected = Patched {
+            content: "qux bar! baz".to_string(),
+            occurrences: 2,
+            fuzzy_matches: vec![],
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_apply_patches_replace_all() {
+        let fixture = vec![Patch {
+            search: "foo".to_string(),
+            operation: Operation::ReplaceAll,
+            content: "qux".to_string(),
+            ..Default::default()
+        }];
+
+        let actual = apply_patches("foo bar foo baz foo".to_string(), &fixture).unwrap();
+
+        let expected = Patched {
+            content: "qux bar qux baz qux".to_string(),
+            occurrences: 3,
+            fuzzy_matches: vec![],
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_apply_patches_occurrence() {
+        let patch = |occurrence| Patch {
+            search: "foo".to_string(),
+            occurrence,
+            operation: Operation::Replace,
+            content: "qux".to_string(),
+            ..Default::default()
+        };
+        let source = "foo\nbar foo\nfoo";
+
+        let actual = [Some(2), Some(-1), None, Some(0), Some(4)].map(|occurrence| {
+            apply_patches(source.to_string(), &[patch(occurrence)])
+                .map(|patched| patched.content)
+                .map_err(|err| err.to_string())
+        });
+
+        let expected = [
+            Ok("foo\nbar qux\nfoo".to_string()),
+            Ok("foo\nbar foo\nqux".to_string()),
+            Err("Found several matches for search text on lines 1, 2, 3, set occurrence to choose one: foo".to_string()),
+            Err("Occurrence 0 not found, the search text has 3 matches".to_string()),
+            Err("Occurrence 4 not found, the search text has 3 matches".to_string()),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_apply_patches_fails_atomically() {
+        let fixture = vec![
+            Patch {
+                search: "foo".to_string(),
+                operation: Operation::Replace,
+                content: "qux".to_string(),
+                ..Default::default()
+            },
+            Patch {
+                search: "foo".to_string(),
+                operation: Operation::Replace,
+                content: "quux".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let actual = apply_patches("foo bar".to_string(), &fixture)
+            .unwrap_err()
+            .to_string();
+
+        let expected =
+            "Patch 2 of 2 failed, the file was not changed: Could not find match for search text: foo"
+                .to_string();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_apply_patches_regex() {
+        let fixture = vec![
+            Patch {
+                search: r"fn (\w+)\(\)".to_string(),
+                search_kind: SearchKind::Regex,
+                operation: Operation::Replace,
+                content: "fn ${1}_v2()".to_string(),
+                ..Default::default()
+            },
+            Patch {
+                search: r"(\d+)\.(\d+)".to_string(),
+                search_kind: SearchKind::Regex,
+                operation: Operation::ReplaceAll,
+                content: "$2.$1".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let actual = apply_patches("fn main() { 1.2 + 3.4 }".to_string(), &fixture).unwrap();
+
+        let expected = Patched {
+            content: "fn main_v2() { 2.1 + 4.3 }".to_string(),
+            occurrences: 3,
+            fuzzy_matches: vec![],
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_apply_patches_invalid_regex() {
+        let fixture = vec![Patch {
+            search: "(foo".to_string(),
+            search_kind: SearchKind::Regex,
+            operation: Operation::Replace,
+            content: "bar".to_string(),
+            ..Default::default()
+        }];
+
+        let actual = apply_patches("foo".to_string(), &fixture).is_err();
+
+        assert!(actual);
+    }
+
+    #[test]
+    fn test_apply_patches_line_range() {
+        let fixture = vec![
+            Patch {
+                start_line: Some(2),
+                end_line: Some(3),
+                operation: Operation::Replace,
+                content: "two and three".to_string(),
+                ..Default::default()
+            },
+            Patch {
+                start_line: Some(3),
+                operation: Operation::Append,
+                content: "five".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let actual = apply_patches("one\ntwo\nthree\nfour".to_string(), &fixture).unwrap();
+
+        let expected = Patched {
+            content: "one\ntwo and three\nfour\nfive".to_string(),
+            occurrences: 2,
+            fuzzy_matches: vec![],
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_apply_patches_line_range_out_of_range() {
+        let fixture = vec![Patch {
+            start_line: Some(2),
+            end_line: Some(4),
+            operation: Operation::Replace,
+            content: "two".to_string(),
+            ..Default::default()
+        }];
+
+        let actual = apply_patches("one\ntwo\n".to_string(), &fixture)
+            .unwrap_err()
+            .to_string();
+
+        let expected = "Lines 2 to 4 are out of range, the file has 2 lines".to_string();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_apply_patches_fuzzy() {
+        let fixture = vec![Patch {
+            search: "fn main() {\n  println!(\"hello\");  \n}".to_string(),
+            fuzzy: true,
+            operation: Operation::Replace,
+            content: "fn main() {}".to_string(),
+            ..Default::default()
+        }];
+
+        let actual = apply_patches(
+            "use std::io;\n\nfn main() {\n    println!(\"hello\");\n}\n".to_string(),
+            &fixture,
+        )
+        .unwrap();
+
+        let expected = Patched {
+            content: "use std::io;\n\nfn main() {}\n".to_string(),
+            occurrences: 1,
+            fuzzy_matches: vec![FuzzyMatch {
+                patch: 1,
+                confidence: 1.0,
+                matched: "fn main() {\n    println!(\"hello\");\n}".to_string(),
+            }],
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_find_fuzzy_large_file() {
+        let filler = (0..10_000)
+            .map(|i| format!("let value_{i} = compute({i});\n"))
+            .collect::<String>();
+        let fixture = format!("{filler}fn main() {{\n    println!(\"hello\");\n}}\n");
+
+        let (actual, confidence) =
+            Range::find_fuzzy(&fixture, "fn main() {\n  println!(\"hello\");  \n}").unwrap();
+
+        assert_eq!(actual, Range::new(filler.len(), 36));
+        assert_eq!(confidence, 1.0);
+    }
+
+    #[test]
+    fn test_apply_patches_fuzzy_below_threshold() {
+        let fixture = vec![Patch {
+            search: "fn other() {}".to_string(),
+            fuzzy: true,
+            operation: Operation::Replace,
+            content: "fn main() {}".to_string(),
+            ..Default::default()
+        }];
+
+        let actual = apply_patches("fn main() {\n}\n".to_string(), &fixture).is_err();
+
+        assert!(actual);
+    }
+
+    #[test]
+    fn test_apply_patches_ignore_indentation() {
+        let fixture = vec![Patch {
+            search: "fn a() {\n    1\n}".to_string(),
+            match_mode: MatchMode::IgnoreIndentation,
+            operation: Operation::Replace,
+            content: "fn a() {\n    2\n}".to_string(),
+            ..Default::default()
+        }];
+
+        let actual = apply_patches(
+            "impl A {\n    fn a() {\n        1\n    }\n}\n".to_string(),
+            &fixture,
+        )
+        .unwrap();
+
+        let expected = Patched {
+            content: "impl A {\n    fn a() {\n        2\n    }\n}\n".to_string(),
+            occurrences: 1,
+            fuzzy_matches: vec![],
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_apply_patches_normalize_whitespace() {
+        let fixture = vec![Patch {
+            search: "foo(a, b)".to_string(),
+            match_mode: MatchMode::NormalizeWhitespace,
+            operation: Operation::Replace,
+            content: "bar(a, b)".to_string(),
+            ..Default::default()
+        }];
+
+        let actual = apply_patches("let x = foo(a,\n    b);\n".to_string(), &fixture).unwrap();
+
+        let expected = Patched {
+            content: "let x = bar(a, b);\n".to_string(),
+            occurrences: 1,
+            fuzzy_matches: vec![],
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_apply_patches_anchor_regex() {
+        let fixture = |occurrence| {
+            vec![Patch {
+                occurrence,
+                anchor_regex: Some("^}$".to_string()),
+                operation: Operation::Append,
+                content: "fn c() {}".to_string(),
+                ..Default::default()
+            }]
+        };
+        let source = "fn a() {\n}\nfn b() {\n}\n".to_string();
+
+        let actual = apply_patches(source.clone(), &fixture(Some(-1)))
+            .unwrap()
+            .content;
+        let expected = "fn a() {\n}\nfn b() {\n}\nfn c() {}\n".to_string();
+        assert_eq!(actual, expected);
+
+        let actual = apply_patches(source, &fixture(None))
+            .unwrap_err()
+            .to_string();
+        let expected =
+            "Found several matches for search text on lines 2, 4, set occurrence to choose one: ^}$"
+                .to_string();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_apply_patches_target() {
+        let patch = |target, operation, content: &str| Patch {
+            target: Some(target),
+            operation,
+            ensure_trailing_newline: true,
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let fixture = vec![
+            patch(Target::FileStart, Operation::Prepend, "//! Server"),
+            patch(Target::FileEnd, Operation::Append, "fn b() {}"),
+        ];
+
+        let actual = apply_patches("fn a() {}".to_string(), &fixture)
+            .unwrap()
+            .content;
+
+        let expected = "//! Server\nfn a() {}\nfn b() {}\n".to_string();
+        assert_eq!(actual, expected);
+
+        let fixture = vec![patch(Target::FileEnd, Operation::Replace, "")];
+        let actual = apply_patches("fn a() {}".to_string(), &fixture)
+            .unwrap_err()
+            .to_string();
+        let expected = "Operation 'replace' can't be used with a target, use 'prepend' or 'append'"
+            .to_string();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_apply_patches_deferring() {
+        let patch = |search: &str, content: &str| Patch {
+            search: search.to_string(),
+            operation: Operation::Replace,
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let fixture = vec![
+            patch("fn a()", "fn b()"),
+            patch("fn missing()", "fn c()"),
+            patch("{}", "{ 1 }"),
+        ];
+
+        let (patched, actual) = apply_patches_deferring("fn a() {}".to_string(), &fixture).unwrap();
+
+        let expected = vec![(1, "fn missing()".to_string())];
+        assert_eq!(actual, expected);
+        assert_eq!(patched.content, "fn b() { 1 }".to_string());
+    }
+
+    #[test]
+    fn test_input_accepts_a_single_patch() {
+        let fixture = serde_json::json!({
+            "path": "/test/file.txt",
+            "search": "foo",
+            "operation": "replace",
+            "content": "bar"
+        });
+
+        let actual: Input = serde_json::from_value(fixture).unwrap();
+
+        let expected = Input {
+            path: "/test/file.txt".to_string(),
+            patches: vec![Patch {
+                search: "foo".to_string(),
+                operation: Operation::Replace,
+                content: "bar".to_string(),
+                ..Default::default()
+            }],
+            dry_run: false,
+            expected_hash: None,
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_leaves_the_file_unchanged() {
+        let infra = Arc::new(TestInfrastructure::new().file("/test/test.txt", "foo bar"));
+        let fixture = Input {
+            path: "/test/test.txt".to_string(),
+            patches: vec![Patch {
+                search: "foo".to_string(),
+                operation: Operation::Replace,
+                content: "baz".to_string(),
+                ..Default::default()
+            }],
+            dry_run: true,
+            expected_hash: None,
+        };
+
+        let output = ApplyPatchJson::new(infra.clone())
+            .call(ToolCallContext::default(), fixture)
+            .await
+            .unwrap();
+
+        let actual = infra.read_file("/test/test.txt");
+        let expected = Some("foo bar".to_string());
+        assert_eq!(actual, expected);
+        assert!(output.contains("dry_run: true"));
+    }
+
+    #[tokio::test]
+    async fn test_patch_defers_conflicts() {
+        let infra = Arc::new(TestInfrastructure::new().file("/test/test.txt", "foo bar"));
+        let patch = |search: &str| Patch {
+            search: search.to_string(),
+            operation: Operation::Replace,
+            content: "baz".to_string(),
+            ..Default::default()
+        };
+        let fixture = Input {
+            path: "/test/test.txt".to_string(),
+            patches: vec![patch("foo"), patch("qux")],
+            dry_run: false,
+            expected_hash: None,
+        };
+
+        let output = ApplyPatchJson::new(infra.clone())
+            .call(
+                ToolCallContext::default().defer_patch_conflicts(true),
+                fixture,
+            )
+            .await
+            .unwrap();
+
+        let actual = infra.read_file("/test/test.txt");
+        let expected = Some("baz bar".to_string());
+        assert_eq!(actual, expected);
+        assert!(output.contains("deferred_patches: 2\n"));
+    }
+
+    #[tokio::test]
+    async fn test_patch_keeps_the_encoding() {
+        let infra = Arc::new(
+            TestInfrastructure::new().file("/test/test.txt", &b"caf\xE9 foo\r\nbar\r\n"[..]),
+        );
+        let fixture = Input {
+            path: "/test/test.txt".to_string(),
+            patches: vec![Patch {
+                search: "foo\nbar".to_string(),
+                operation: Operation::Replace,
+                content: "baz\nqux".to_string(),
+                ..Default::default()
+            }],
+            dry_run: false,
+            expected_hash: None,
+        };
+
+        let output = ApplyPatchJson::new(infra.clone())
+            .call(ToolCallContext::default(), fixture)
+            .await
+            .unwrap();
+
+        let actual = infra
+            .file_read_service()
+            .read(Path::new("/test/test.txt"))
+            .await
+            .unwrap();
+        let expected = b"caf\xE9 baz\r\nqux\r\n".to_vec();
+        assert_eq!(actual, expected);
+        assert!(output.contains("encoding: latin-1\nline_ending: crlf"));
+    }
+
+    #[tokio::test]
+    async fn test_patch_refuses_changed_file() {
+        let infra = Arc::new(TestInfrastructure::new().file("/test/test.txt", "foo bar"));
+        let fixture = |expected_hash: &str| Input {
+            path: "/test/test.txt".to_string(),
+            patches: vec![Patch {
+                search: "foo".to_string(),
+                operation: Operation::Replace,
+                content: "baz".to_string(),
+                ..Default::default()
+            }],
+            dry_run: false,
+            expected_hash: Some(expected_hash.to_string()),
+        };
+        let tool = ApplyPatchJson::new(infra.clone());
+
+        let stale = tool
+            .call(ToolCallContext::default(), fixture(&content_hash(b"foo")))
+            .await;
+        let current = tool
+            .call(
+                ToolCallContext::default(),
+                fixture(&content_hash(b"foo bar")),
+            )
+            .await
+            .unwrap();
+
+        let actual = infra.read_file("/test/test.txt");
+        let expected = Some("baz bar".to_string());
+        assert_eq!(actual, expected);
+        assert!(stale
+            .unwrap_err()
+            .to_string()
+            .contains("was changed since it was read"));
+        assert!(current.contains(&format!("hash: {}", content_hash(b"baz bar"))));
+    }
+
+    #[tokio::test]
+    async fn test_patch_streams_large_files() {
+        let infra = Arc::new(TestInfrastructure::new().file("/test/test.txt", "foo\nbar\nfoo\n"));
+        let fixture = |occurrence: Option<isize>| Input {
+            path: "/test/test.txt".to_string(),
+            patches: vec![Patch {
+                search: "foo".to_string(),
+                occurrence,
+                operation: Operation::Append,
+                content: "d".to_string(),
+                ..Default::default()
+            }],
+            dry_run: false,
+            expected_hash: None,
+        };
+        let tool = ApplyPatchJson::new(infra.clone());
+
+        let ambiguous = tool
+            .call_streaming(&ToolCallContext::default(), &fixture(None), 0)
+            .await;
+        let output = tool
+            .call_streaming(&ToolCallContext::default(), &fixture(Some(-1)), 0)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let actual = infra.read_file("/test/test.txt");
+        let expected = Some("foo\nbar\nfood\n".to_string());
+        assert_eq!(actual, expected);
+        assert!(ambiguous.unwrap_err().to_string().contains("on lines 1, 3"));
+        assert!(output.contains("total_bytes: 13\noccurrences: 1"));
+        assert!(output.contains("streamed: true"));
+    }
+
+    #[tokio::test]
+    async fn test_patch_streams_only_exact_text() {
+        let infra = Arc::new(TestInfrastructure::new().file("/test/test.txt", "foo bar"));
+        let fixture = |search_kind: SearchKind| Input {
+            path: "/test/test.txt".to_string(),
+            patches: vec![Patch {
+                search: "foo".to_string(),
+                search_kind,
+                operation: Operation::Replace,
+                content: "baz".to_string(),
+                ..Default::default()
+            }],
+            dry_run: true,
+            expected_hash: None,
+        };
+        let tool = ApplyPatchJson::new(infra);
+
+        let mut actual = Vec::new();
+        for (search_kind, limit) in [
+            (SearchKind::Exact, 0),
+            (SearchKind::Exact, 1024),
+            (SearchKind::Regex, 0),
+        ] {
+            let output = tool
+                .call_streaming(&ToolCallContext::default(), &fixture(search_kind), limit)
+                .await
+                .unwrap();
+            actual.push(output.is_some());
+        }
+
+        let expected = vec![true, false, false];
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_patch_fixes_imports() {
+        // The files next to the patched one are listed from the disk
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("a.rs");
+        let infra = Arc::new(TestInfrastructure::new().file(
+            file_path.clone(),
+            "use std::fs;\n\nfn a() {\n    fs::remove_file(\"a\");\n}\n",
+        ));
+        fs::write(
+            temp_dir.path().join("b.rs"),
+            "use std::collections::HashMap;\n",
+        )
+        .await
+        .unwrap();
+        let fixture = Input {
+            path: file_path.display().to_string(),
+            patches: vec![Patch {
+                search: "fs::remove_file(\"a\");".to_string(),
+                operation: Operation::Replace,
+                content: "HashMap::<u8, u8>::new();".to_string(),
+                ..Default::default()
+            }],
+            dry_run: true,
+            expected_hash: None,
+        };
+
+        let actual = ApplyPatchJson::new(infra)
+            .call(ToolCallContext::default(), fixture)
+            .await
+            .unwrap();
+
+        assert!(actual.contains("imports_added:\n  - std::collections::HashMap\n"));
+        assert!(actual.contains("imports_removed:\n  - std::fs\n"));
+        assert!(actual.contains("+use std::collections::HashMap;"));
+    }
+
+    #[tokio::test]
+    async fn test_patch_reports_hunk_symbols() {
+        let content = "struct Server;\n\nimpl Server {\n    fn handle_request(&self) -> u8 {\n        1\n    }\n}\n";
+        let infra = Arc::new(TestInfrastructure::new().file("/test/server.rs", content));
+        let fixture = Input {
+            path: "/test/server.rs".to_string(),
+            patches: vec![Patch {
+                search: "        1".to_string(),
+                operation: Operation::Replace,
+                content: "        2".to_string(),
+                ..Default::default()
+            }],
+            dry_run: true,
+            expected_hash: None,
+        };
+
+        let actual = ApplyPatchJson::new(infra)
+            .call(ToolCallContext::default(), fixture)
+            .await
+            .unwrap();
+
+        assert!(
+            actual.contains("hunks:\n  - line: 5\n    symbol: impl Server > fn handle_request\n")
+        );
+        assert!(actual.contains("@@ impl Server > fn handle_request\n"));
+    }
+
+    #[tokio::test]
+    async fn test_patch_reports_hunk_lines() {
+        let infra = Arc::new(TestInfrastructure::new().file("/test/test.txt", "a\nb\nc\n"));
+        let fixture = Input {
+            path: "/test/test.txt".to_string(),
+            patches: vec![Patch {
+                search: "b".to_string(),
+                operation: Operation::Replace,
+                content: "x\ny".to_string(),
+                ..Default::default()
+            }],
+            dry_run: true,
+            expected_hash: None,
+        };
+
+        let actual = ApplyPatchJson::new(infra)
+            .call(ToolCallContext::default(), fixture)
+            .await
+            .unwrap();
+
+        let expected = "hunks:\n  - line: 2\n    \
+                        old_start: 1\n    old_count: 3\n    \
+                        new_start: 1\n    new_count: 4\n    \
+                        old_text: \"a\\nb\\nc\\n\"\n    \
+                        new_text: \"a\\nx\\ny\\nc\\n\"\n";
+        assert!(actual.contains(expected));
+    }
+
     // The previous individual tests are removed since they're now consolidated
 
     #[tokio::test]
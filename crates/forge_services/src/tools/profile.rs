@@ -0,0 +1,391 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use forge_display::TitleFormat;
+use forge_domain::{
+    EnvironmentService, ExecutableTool, NamedTool, ToolCallContext, ToolDescription, ToolName,
+};
+use forge_tool_macros::ToolDescription;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::metadata::Metadata;
+use crate::tools::utils::assert_absolute_path;
+use crate::{CommandExecutorService, FsCreateDirsService, FsReadService, Infrastructure};
+
+/// Frames listed in each table when not specified
+const DEFAULT_TOP: usize = 15;
+
+/// Sampling frequency of perf, in Hz
+const PERF_FREQUENCY: usize = 999;
+
+/// Profiler used to sample a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Profiler {
+    /// `perf record`, for native programs on Linux
+    Perf,
+    /// `cargo flamegraph`, for Rust binaries, tests and benchmarks
+    Flamegraph,
+    /// `py-spy record`, for Python programs
+    PySpy,
+}
+
+impl fmt::Display for Profiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Profiler::Perf => write!(f, "perf"),
+            Profiler::Flamegraph => write!(f, "cargo flamegraph"),
+            Profiler::PySpy => write!(f, "py-spy"),
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ProfileInput {
+    /// The command to profile, eg: `./target/release/app input.txt` or
+    /// `python main.py`. With `flamegraph`, the arguments passed to `cargo
+    /// flamegraph` instead, eg: `--bench parse` or `--bin app -- input.txt`.
+    pub command: String,
+    /// The absolute path of the directory in which the command runs.
+    pub cwd: String,
+    /// The profiler to use: `perf`, `flamegraph` or `py_spy`.
+    pub profiler: Profiler,
+    /// Number of frames listed in each table. Defaults to 15.
+    pub top: Option<usize>,
+}
+
+/// Samples of a frame, in the stacks where it's the leaf (self) and in all
+/// the stacks that contain it (total)
+#[derive(Debug, Clone, PartialEq)]
+struct Frame {
+    name: String,
+    self_samples: u64,
+    total_samples: u64,
+}
+
+/// Summary of the samples of a profile
+#[derive(Debug, Clone, PartialEq)]
+struct Summary {
+    samples: u64,
+    frames: Vec<Frame>,
+    /// The stack sampled the most, from the root to the leaf
+    hottest_stack: Vec<String>,
+}
+
+/// Profiles a command with perf, cargo flamegraph or py-spy and summarizes
+/// where the time goes: the frames with the most self and total time, and
+/// the hottest stack. Use it before optimizing code, so that changes target
+/// measured hot paths instead of guesses. The profiler must be installed, and
+/// the command should run long enough to collect samples (at least a second).
+#[derive(ToolDescription)]
+pub struct Profile<F>(Arc<F>);
+
+impl<F: Infrastructure> Profile<F> {
+    pub fn new(infra: Arc<F>) -> Self {
+        Self(infra)
+    }
+
+    async fn call(&self, context: ToolCallContext, input: ProfileInput) -> anyhow::Result<String> {
+        let cwd = PathBuf::from(&input.cwd);
+        assert_absolute_path(&cwd)?;
+
+        let dir = self
+            .0
+            .environment_service()
+            .get_environment()
+            .base_path
+            .join("profiles");
+        self.0.create_dirs_service().create_dirs(&dir).await?;
+        let id = uuid::Uuid::new_v4();
+        let stacks_path = dir.join(format!("{id}.stacks"));
+        let command = profile_command(input.profiler, &input.command, &dir, &id, &stacks_path);
+
+        context
+            .send_text(
+                TitleFormat::debug(format!("Profile with {}", input.profiler))
+                    .sub_title(&input.command),
+            )
+            .await?;
+        let output = self
+            .0
+            .command_executor_service()
+            .execute_command(command, cwd)
+            .await?;
+
+        let content = self
+            .0
+            .file_read_service()
+            .read_utf8(&stacks_path)
+            .await
+            .ok()
+            .filter(|content| !content.trim().is_empty());
+        let Some(content) = content else {
+            bail!(
+                "{} collected no samples, check that it's installed and that the command runs.\n<stderr>\n{}\n</stderr>",
+                input.profiler,
+                output.stderr.trim()
+            );
+        };
+
+        let stacks = match input.profiler {
+            Profiler::Perf | Profiler::Flamegraph => collapse_perf(&content),
+            Profiler::PySpy => parse_folded(&content),
+        };
+        let summary = summarize(&stacks).context("The profile contains no samples")?;
+        let top = input.top.unwrap_or(DEFAULT_TOP);
+
+        let metadata = Metadata::default()
+            .add("profiler", input.profiler)
+            .add("command", &input.command)
+            .add_optional("exit_code", output.exit_code)
+            .add("samples", summary.samples)
+            .add("stacks", stacks_path.display());
+        Ok(format!("{metadata}{}", summary.render(top)))
+    }
+}
+
+/// Shell command that profiles `command` and writes its stacks to
+/// `stacks_path`
+fn profile_command(
+    profiler: Profiler,
+    command: &str,
+    dir: &Path,
+    id: &uuid::Uuid,
+    stacks_path: &Path,
+) -> String {
+    let data = dir.join(format!("{id}.data"));
+    match profiler {
+        Profiler::Perf => format!(
+            "perf record -F {PERF_FREQUENCY} -g -o '{}' -- {command}; perf script -i '{}' > '{}'",
+            data.display(),
+            data.display(),
+            stacks_path.display()
+        ),
+        // cargo flamegraph leaves the perf data in the working directory
+        Profiler::Flamegraph => format!(
+            "cargo flamegraph -o '{}' {command}; perf script -i perf.data > '{}'",
+            dir.join(format!("{id}.svg")).display(),
+            stacks_path.display()
+        ),
+        Profiler::PySpy => format!(
+            "py-spy record --format raw -o '{}' -- {command}",
+            stacks_path.display()
+        ),
+    }
+}
+
+/// Stacks of the output of `perf script`, from the root to the leaf, with
+/// their number of samples
+fn collapse_perf(content: &str) -> Vec<(Vec<String>, u64)> {
+    let mut counts = HashMap::<Vec<String>, u64>::new();
+    for sample in content.split("\n\n") {
+        let mut stack = sample
+            .lines()
+            .skip_while(|line| !line.starts_with(char::is_whitespace))
+            .filter(|line| !line.trim().is_empty())
+            .map(perf_symbol)
+            .collect::<Vec<_>>();
+        if stack.is_empty() {
+            continue;
+        }
+        // perf lists the leaf first
+        stack.reverse();
+        *counts.entry(stack).or_default() += 1;
+    }
+    counts.into_iter().collect()
+}
+
+/// Symbol of a frame of `perf script`, eg: `7f3a2b1c parse::line+0x1f
+/// (/usr/bin/app)` is `parse::line`
+fn perf_symbol(line: &str) -> String {
+    let line = line.trim();
+    let line = line.split_once(' ').map_or(line, |(_, symbol)| symbol);
+    let symbol = match line.rsplit_once(" (") {
+        Some((symbol, _)) => symbol,
+        None => line,
+    };
+    let symbol = match symbol.rsplit_once("+0x") {
+        Some((symbol, _)) => symbol,
+        None => symbol,
+    };
+    symbol.trim().to_string()
+}
+
+/// Stacks in the folded format, one `root;...;leaf count` per line
+fn parse_folded(content: &str) -> Vec<(Vec<String>, u64)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (stack, count) = line.trim().rsplit_once(' ')?;
+            let count = count.parse().ok()?;
+            Some((stack.split(';').map(str::to_string).collect(), count))
+        })
+        .collect()
+}
+
+fn summarize(stacks: &[(Vec<String>, u64)]) -> Option<Summary> {
+    let samples = stacks.iter().map(|(_, count)| count).sum::<u64>();
+    if samples == 0 {
+        return None;
+    }
+
+    let mut frames = HashMap::<&str, Frame>::new();
+    for (stack, count) in stacks {
+        let mut seen = Vec::new();
+        for (index, name) in stack.iter().enumerate() {
+            let frame = frames.entry(name.as_str()).or_insert_with(|| Frame {
+                name: name.clone(),
+                self_samples: 0,
+                total_samples: 0,
+            });
+            // Recursive frames count once towards the total of a stack
+            if !seen.contains(&name) {
+                frame.total_samples += count;
+                seen.push(name);
+            }
+            if index + 1 == stack.len() {
+                frame.self_samples += count;
+            }
+        }
+    }
+
+    let mut frames = frames.into_values().collect::<Vec<_>>();
+    frames.sort_by(|a, b| {
+        b.self_samples
+            .cmp(&a.self_samples)
+            .then(a.name.cmp(&b.name))
+    });
+    let hottest_stack = stacks
+        .iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(stack, _)| stack.clone())
+        .unwrap_or_default();
+    Some(Summary { samples, frames, hottest_stack })
+}
+
+impl Summary {
+    fn percent(&self, samples: u64) -> f64 {
+        samples as f64 * 100.0 / self.samples as f64
+    }
+
+    fn table(&self, frames: &[&Frame]) -> String {
+        let mut table = String::from("  self   total  frame\n");
+        for frame in frames {
+            let _ = writeln!(
+                table,
+                "{:>5.1}%  {:>5.1}%  {}",
+                self.percent(frame.self_samples),
+                self.percent(frame.total_samples),
+                frame.name
+            );
+        }
+        table
+    }
+
+    fn render(&self, top: usize) -> String {
+        let by_self = self.frames.iter().take(top).collect::<Vec<_>>();
+        let mut by_total = self.frames.iter().collect::<Vec<_>>();
+        by_total.sort_by(|a, b| {
+            b.total_samples
+                .cmp(&a.total_samples)
+                .then(a.name.cmp(&b.name))
+        });
+        by_total.truncate(top);
+
+        format!(
+            "<hot_frames by=\"self\">\n{}</hot_frames>\n<hot_frames by=\"total\">\n{}</hot_frames>\n<hottest_stack>\n{}\n</hottest_stack>\n",
+            self.table(&by_self),
+            self.table(&by_total),
+            self.hottest_stack.join("\n")
+        )
+    }
+}
+
+impl<F> NamedTool for Profile<F> {
+    fn tool_name() -> ToolName {
+        ToolName::new("forge_tool_profile")
+    }
+}
+
+#[async_trait::async_trait]
+impl<F: Infrastructure> ExecutableTool for Profile<F> {
+    type Input = ProfileInput;
+
+    async fn call(&self, context: ToolCallContext, input: Self::Input) -> anyhow::Result<String> {
+        self.call(context, input).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn stack(frames: &str) -> Vec<String> {
+        frames.split(';').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_collapse_perf() {
+        let fixture = "app 1234 5678.9: 1001001 cycles:\n\t    55d1c8e2 parse::line+0x1f (/usr/bin/app)\n\t    55d1c000 main+0x10 (/usr/bin/app)\n\napp 1234 5679.0: 1001001 cycles:\n\t    55d1c8e2 parse::line+0x2a (/usr/bin/app)\n\t    55d1c000 main+0x10 (/usr/bin/app)\n\n";
+
+        let actual = collapse_perf(fixture);
+
+        let expected = vec![(stack("main;parse::line"), 2)];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_parse_folded() {
+        let fixture = "main (app.py:10);load (app.py:3) 12\nmain (app.py:10) 3\n";
+
+        let actual = parse_folded(fixture);
+
+        let expected = vec![
+            (stack("main (app.py:10);load (app.py:3)"), 12),
+            (stack("main (app.py:10)"), 3),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let fixture = vec![
+            (stack("main;parse;tokenize"), 6),
+            (stack("main;parse"), 2),
+            (stack("main;render"), 2),
+        ];
+
+        let actual = summarize(&fixture).unwrap();
+
+        assert_eq!(actual.samples, 10);
+        assert_eq!(actual.hottest_stack, stack("main;parse;tokenize"));
+        assert_eq!(
+            actual.render(2),
+            [
+                "<hot_frames by=\"self\">",
+                "  self   total  frame",
+                " 60.0%   60.0%  tokenize",
+                " 20.0%   80.0%  parse",
+                "</hot_frames>",
+                "<hot_frames by=\"total\">",
+                "  self   total  frame",
+                "  0.0%  100.0%  main",
+                " 20.0%   80.0%  parse",
+                "</hot_frames>",
+                "<hottest_stack>",
+                "main",
+                "parse",
+                "tokenize",
+                "</hottest_stack>",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
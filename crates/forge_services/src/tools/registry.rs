@@ -5,8 +5,13 @@ use forge_domain::Tool;
 use super::completion::Completion;
 use super::fetch::Fetch;
 use super::fs::*;
+use super::outline::CodeOutline;
 use super::patch::*;
+use super::profile::Profile;
 use super::shell::Shell;
+use super::shell_job::{ShellKill, ShellPoll, ShellSpawn};
+use super::task_run::TaskRun;
+use super::todo_scan::TodoScan;
 use crate::tools::followup::Followup;
 use crate::Infrastructure;
 
@@ -23,17 +28,26 @@ impl<F: Infrastructure> ToolRegistry<F> {
     pub fn tools(&self) -> Vec<Tool> {
         vec![
             FSRead::new(self.infra.clone()).into(),
+            FSReadMany::new(self.infra.clone()).into(),
             FSWrite::new(self.infra.clone()).into(),
             FSRemove::new(self.infra.clone()).into(),
             FSList::default().into(),
             FSFind::new(self.infra.clone()).into(),
+            FSTree::new(self.infra.clone()).into(),
+            CodeOutline::new(self.infra.clone()).into(),
             FSFileInfo::new(self.infra.clone()).into(),
             FsUndo::new(self.infra.clone()).into(),
             ApplyPatchJson::new(self.infra.clone()).into(),
             Shell::new(self.infra.clone()).into(),
+            ShellSpawn::new(self.infra.clone()).into(),
+            ShellPoll::new(self.infra.clone()).into(),
+            ShellKill::new(self.infra.clone()).into(),
             Completion.into(),
             Followup::new(self.infra.clone()).into(),
             Fetch::new(self.infra.clone()).into(),
+            TodoScan::new(self.infra.clone()).into(),
+            TaskRun::new(self.infra.clone()).into(),
+            Profile::new(self.infra.clone()).into(),
         ]
     }
 }
@@ -68,6 +82,12 @@ pub mod tests {
                 pid: std::process::id(),
                 provider: Provider::anthropic("test-key"),
                 retry_config: Default::default(),
+                resource_limits: Default::default(),
+                retention: Default::default(),
+                code_owner_teams: vec![],
+                cassette: None,
+                inspected_requests: 0,
+                clock: Default::default(),
             },
         }
     }
@@ -119,6 +139,10 @@ pub mod tests {
         async fn write_temp(&self, _: &str, _: &str, _: &str) -> anyhow::Result<PathBuf> {
             unimplemented!()
         }
+
+        async fn replace(&self, _: &Path, _: &[u64], _: usize, _: &str) -> anyhow::Result<()> {
+            unimplemented!()
+        }
     }
 
     #[async_trait::async_trait]
@@ -127,9 +151,17 @@ pub mod tests {
             unimplemented!()
         }
 
+        async fn record_creation(&self, _: &Path) -> anyhow::Result<Snapshot> {
+            unimplemented!()
+        }
+
         async fn undo_snapshot(&self, _: &Path) -> anyhow::Result<()> {
             Ok(())
         }
+
+        async fn restore_session(&self) -> anyhow::Result<Vec<PathBuf>> {
+            Ok(Vec::new())
+        }
     }
 
     #[async_trait::async_trait]
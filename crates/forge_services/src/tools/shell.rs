@@ -1,11 +1,13 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::bail;
 use forge_display::TitleFormat;
 use forge_domain::{
-    CommandOutput, Environment, EnvironmentService, ExecutableTool, NamedTool, ToolCallContext,
-    ToolDescription, ToolName,
+    CommandEnv, CommandOutput, Environment, EnvironmentService, ExecutableTool, NamedTool,
+    ResourceUsage, TerminalSize, ToolCallContext, ToolDescription, ToolName,
 };
 use forge_tool_macros::ToolDescription;
 use schemars::JsonSchema;
@@ -13,25 +15,63 @@ use serde::{Deserialize, Serialize};
 use strip_ansi_escapes::strip;
 
 use crate::metadata::Metadata;
+use crate::tools::utils::{moderate, simulate_command};
 use crate::{Clipper, ClipperResult, CommandExecutorService, FsWriteService, Infrastructure};
 
 /// Number of characters to keep at the start of truncated output
-const PREFIX_CHARS: usize = 10_000;
+pub(crate) const PREFIX_CHARS: usize = 10_000;
 
 /// Number of characters to keep at the end of truncated output
-const SUFFIX_CHARS: usize = 10_000;
+pub(crate) const SUFFIX_CHARS: usize = 10_000;
+
+/// Maximum output that a command can ask to keep of each stream
+const MAX_OUTPUT_BYTES: usize = 100_000;
+
+/// Maximum timeout of a command, below the timeout of tool calls so that the
+/// output of a command that timed out is still returned
+const MAX_TIMEOUT_SECS: u64 = 270;
 
 #[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct ShellInput {
     /// The shell command to execute.
     pub command: String,
-    /// The working directory where the command should be executed.
+    /// The working directory where the command should be executed, instead
+    /// of `cd dir && ...`. A relative path is resolved against the current
+    /// working directory.
     pub cwd: PathBuf,
+    /// Environment variables set for the command, eg: `{"RUST_LOG": "debug"}`,
+    /// instead of exporting them in the command.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Whether the command only inherits the environment variables that a
+    /// shell needs, eg: PATH and HOME, besides the ones in env.
+    #[serde(default)]
+    pub clean_env: bool,
     /// Whether to preserve ANSI escape codes in the output.
     /// If true, ANSI escape codes will be preserved in the output.
     /// If false (default), ANSI escape codes will be stripped from the output.
     #[serde(default)]
     pub keep_ansi: bool,
+    /// Seconds after which the command and the processes it started are
+    /// killed, eg: for tests that may hang. At most 270. The output written
+    /// until then is returned with `timed_out: true`.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Maximum bytes of stdout and of stderr that are returned, half from
+    /// their start and half from their end. Defaults to 20000, at most 100000.
+    #[serde(default)]
+    pub max_output_bytes: Option<usize>,
+    /// Whether to run the command in a pseudo-terminal, for commands that
+    /// change their output or refuse to run when they don't write to a
+    /// terminal. stderr is then returned within stdout.
+    #[serde(default)]
+    pub pty: bool,
+    /// Rows of the pseudo-terminal, defaults to 24.
+    #[serde(default)]
+    pub pty_rows: Option<u16>,
+    /// Columns of the pseudo-terminal, defaults to 80.
+    #[serde(default)]
+    pub pty_cols: Option<u16>,
 }
 
 // Strips out the ansi codes from content.
@@ -43,9 +83,10 @@ fn strip_ansi(content: String) -> String {
 /// stderr is commonly used for warnings and progress info, so success is
 /// determined by exit status, not stderr presence. Returns Ok(output) on
 /// success or Err(output) on failure, with a status message if both streams are
-/// empty.
-async fn format_output<F: Infrastructure>(
+/// empty. The output follows the given metadata.
+pub(crate) async fn format_output_with<F: Infrastructure>(
     infra: &Arc<F>,
+    metadata: Metadata,
     mut output: CommandOutput,
     keep_ansi: bool,
     prefix_chars: usize,
@@ -59,10 +100,20 @@ async fn format_output<F: Infrastructure>(
     }
 
     // Create metadata
-    let mut metadata = Metadata::default()
+    let mut metadata = metadata
         .add("command", &output.command)
         .add_optional("exit_code", output.exit_code);
 
+    if let Some(usage) = &output.usage {
+        metadata = metadata
+            .add("peak_memory_mb", usage.peak_memory_bytes / 1024 / 1024)
+            .add("peak_cpu_percent", format!("{:.0}", usage.peak_cpu_percent))
+            .add_optional("killed", usage.killed.as_ref());
+        if usage.timed_out {
+            metadata = metadata.add("timed_out", true);
+        }
+    }
+
     let mut is_truncated = false;
 
     // Format stdout if not empty
@@ -164,7 +215,10 @@ fn tag_output(result: ClipperResult, tag: &str, content: &str) -> String {
 /// installing packages, or executing build commands. For operations requiring
 /// unrestricted access, advise users to run forge CLI with '-u' flag. Returns
 /// complete output including stdout, stderr, and exit code for diagnostic
-/// purposes.
+/// purposes. Set timeout_secs for commands that may hang, eg: tests, and
+/// max_output_bytes to keep less of a verbose output. Set pty for commands
+/// that need a terminal. Pass the directory and environment variables of a
+/// command with cwd and env rather than `cd` and `export` in the command.
 #[derive(ToolDescription)]
 pub struct Shell<I> {
     env: Environment,
@@ -194,23 +248,81 @@ impl<I: Infrastructure> ExecutableTool for Shell<I> {
         if input.command.trim().is_empty() {
             bail!("Command string is empty or contains only whitespace".to_string());
         }
+        if let Some(name) = input
+            .env
+            .keys()
+            .find(|name| name.is_empty() || name.contains(['=', '\0']))
+        {
+            bail!("Invalid environment variable name: {name:?}");
+        }
+
+        // Commit messages and branch names are conformed to the naming policy
+        let command = match &context.git_policy {
+            Some(policy) => policy.conform_command(&input.command)?,
+            None => input.command,
+        };
+        moderate(&context, &command).await?;
         let title_format = TitleFormat::debug(format!("Execute [{}]", self.env.shell.as_str()))
-            .sub_title(&input.command);
+            .sub_title(&command);
 
         context.send_text(title_format).await?;
 
-        let output = self
-            .infra
-            .command_executor_service()
-            .execute_command(input.command, input.cwd)
-            .await?;
+        let cwd = self.env.cwd.join(&input.cwd);
+        if context.dry_run {
+            return Ok(simulate_command(&command, &cwd));
+        }
+
+        // The working directory is reported when it was resolved, and only the
+        // names of the variables since their values may be secrets
+        let mut metadata = Metadata::default();
+        if input.cwd.is_relative() {
+            metadata = metadata.add("cwd", cwd.display());
+        }
+        if !input.env.is_empty() {
+            let names = input.env.keys().cloned().collect::<Vec<_>>().join(" ");
+            metadata = metadata.add("env", names);
+        }
+        if input.clean_env {
+            metadata = metadata.add("clean_env", true);
+        }
+        let env = CommandEnv { vars: input.env, clean: input.clean_env };
+
+        let executor = self.infra.command_executor_service();
+        let timeout = input
+            .timeout_secs
+            .map(|secs| Duration::from_secs(secs.clamp(1, MAX_TIMEOUT_SECS)));
+        let output = if input.pty {
+            let default = TerminalSize::default();
+            let size = TerminalSize {
+                rows: input.pty_rows.unwrap_or(default.rows).max(1),
+                cols: input.pty_cols.unwrap_or(default.cols).max(1),
+            };
+            metadata = metadata.add("pty", format!("{}x{}", size.cols, size.rows));
+            executor
+                .execute_command_in_pty(command, cwd, env, size, timeout, context.cancellation)
+                .await?
+        } else {
+            executor
+                .execute_command_with(command, cwd, env, timeout, context.cancellation)
+                .await?
+        };
 
-        format_output(
+        // The output is kept from the start and the end of each stream, where
+        // commands usually print what they do and their summary
+        let (prefix_chars, suffix_chars) = match input.max_output_bytes {
+            Some(max) => {
+                let max = max.clamp(2, MAX_OUTPUT_BYTES);
+                (max / 2, max - max / 2)
+            }
+            None => (PREFIX_CHARS, SUFFIX_CHARS),
+        };
+        format_output_with(
             &self.infra,
+            metadata,
             output,
             input.keep_ansi,
-            PREFIX_CHARS,
-            SUFFIX_CHARS,
+            prefix_chars,
+            suffix_chars,
         )
         .await
     }
@@ -228,10 +340,12 @@ mod tests {
             stderr: "".to_string(),
             command: "echo".into(),
             exit_code: Some(0),
+            usage: None,
         };
-        let small_result = format_output(&infra, small_output, false, 5, 5)
-            .await
-            .unwrap();
+        let small_result =
+            format_output_with(&infra, Metadata::default(), small_output, false, 5, 5)
+                .await
+                .unwrap();
         insta::assert_snapshot!(
             "format_output_small_truncation",
             TempDir::normalize(&small_result)
@@ -243,10 +357,12 @@ mod tests {
             stderr: "".to_string(),
             command: "echo".into(),
             exit_code: Some(0),
+            usage: None,
         };
-        let large_result = format_output(&infra, large_output, false, 100, 100)
-            .await
-            .unwrap();
+        let large_result =
+            format_output_with(&infra, Metadata::default(), large_output, false, 100, 100)
+                .await
+                .unwrap();
         insta::assert_snapshot!(
             "format_output_no_truncation",
             TempDir::normalize(&large_result)
@@ -255,6 +371,7 @@ mod tests {
     use std::env;
     use std::sync::Arc;
 
+    use forge_domain::{GitPolicy, Moderation};
     use pretty_assertions::assert_eq;
 
     use super::*;
@@ -285,7 +402,14 @@ mod tests {
                 ShellInput {
                     command: "echo 'Hello, World!'".to_string(),
                     cwd: env::current_dir().unwrap(),
+                    env: BTreeMap::new(),
+                    clean_env: false,
                     keep_ansi: true,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
                 },
             )
             .await
@@ -308,7 +432,14 @@ mod tests {
                         "echo 'to stderr' >&2; echo 'to stdout'".to_string()
                     },
                     cwd: env::current_dir().unwrap(),
+                    env: BTreeMap::new(),
+                    clean_env: false,
                     keep_ansi: true,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
                 },
             )
             .await
@@ -326,7 +457,14 @@ mod tests {
                 ShellInput {
                     command: "echo 'to stdout' && echo 'to stderr' >&2".to_string(),
                     cwd: env::current_dir().unwrap(),
+                    env: BTreeMap::new(),
+                    clean_env: false,
                     keep_ansi: true,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
                 },
             )
             .await
@@ -350,7 +488,14 @@ mod tests {
                         "pwd".to_string()
                     },
                     cwd: temp_dir.clone(),
+                    env: BTreeMap::new(),
+                    clean_env: false,
                     keep_ansi: true,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
                 },
             )
             .await
@@ -370,7 +515,14 @@ mod tests {
                 ShellInput {
                     command: "non_existent_command".to_string(),
                     cwd: env::current_dir().unwrap(),
+                    env: BTreeMap::new(),
+                    clean_env: false,
                     keep_ansi: true,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
                 },
             )
             .await;
@@ -399,7 +551,14 @@ mod tests {
                 ShellInput {
                     command: "".to_string(),
                     cwd: env::current_dir().unwrap(),
+                    env: BTreeMap::new(),
+                    clean_env: false,
                     keep_ansi: true,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
                 },
             )
             .await;
@@ -434,7 +593,14 @@ mod tests {
                         "pwd".to_string()
                     },
                     cwd: current_dir.clone(),
+                    env: BTreeMap::new(),
+                    clean_env: false,
                     keep_ansi: true,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
                 },
             )
             .await
@@ -469,7 +635,14 @@ mod tests {
                 ShellInput {
                     command: "echo 'first' && echo 'second'".to_string(),
                     cwd: env::current_dir().unwrap(),
+                    env: BTreeMap::new(),
+                    clean_env: false,
                     keep_ansi: true,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
                 },
             )
             .await
@@ -486,7 +659,14 @@ mod tests {
                 ShellInput {
                     command: "true".to_string(),
                     cwd: env::current_dir().unwrap(),
+                    env: BTreeMap::new(),
+                    clean_env: false,
                     keep_ansi: true,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
                 },
             )
             .await
@@ -505,7 +685,14 @@ mod tests {
                 ShellInput {
                     command: "echo ''".to_string(),
                     cwd: env::current_dir().unwrap(),
+                    env: BTreeMap::new(),
+                    clean_env: false,
                     keep_ansi: true,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
                 },
             )
             .await
@@ -524,7 +711,14 @@ mod tests {
                 ShellInput {
                     command: "echo $PATH".to_string(),
                     cwd: env::current_dir().unwrap(),
+                    env: BTreeMap::new(),
+                    clean_env: false,
                     keep_ansi: true,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
                 },
             )
             .await
@@ -550,7 +744,14 @@ mod tests {
                 ShellInput {
                     command: cmd.to_string(),
                     cwd: env::current_dir().unwrap(),
+                    env: BTreeMap::new(),
+                    clean_env: false,
                     keep_ansi: true,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
                 },
             )
             .await;
@@ -572,10 +773,18 @@ mod tests {
             stderr: "\x1b[31mWarning\x1b[0m".to_string(),
             command: "ls -la".into(),
             exit_code: Some(0),
+            usage: None,
         };
-        let preserved = format_output(&infra, ansi_output, true, PREFIX_CHARS, SUFFIX_CHARS)
-            .await
-            .unwrap();
+        let preserved = format_output_with(
+            &infra,
+            Metadata::default(),
+            ansi_output,
+            true,
+            PREFIX_CHARS,
+            SUFFIX_CHARS,
+        )
+        .await
+        .unwrap();
         insta::assert_snapshot!("format_output_ansi_preserved", preserved);
 
         // Test with keep_ansi = false (should strip ANSI codes)
@@ -584,10 +793,18 @@ mod tests {
             stderr: "\x1b[31mWarning\x1b[0m".to_string(),
             command: "ls -la".into(),
             exit_code: Some(0),
+            usage: None,
         };
-        let stripped = format_output(&infra, ansi_output, false, PREFIX_CHARS, SUFFIX_CHARS)
-            .await
-            .unwrap();
+        let stripped = format_output_with(
+            &infra,
+            Metadata::default(),
+            ansi_output,
+            false,
+            PREFIX_CHARS,
+            SUFFIX_CHARS,
+        )
+        .await
+        .unwrap();
         insta::assert_snapshot!("format_output_ansi_stripped", stripped);
     }
 
@@ -609,15 +826,383 @@ mod tests {
             stderr: test_string,
             command: "ls -la".into(),
             exit_code: Some(0),
+            usage: None,
         };
 
-        let preserved = format_output(&infra, ansi_output, false, TINY_PREFIX, TINY_SUFFIX)
-            .await
-            .unwrap();
+        let preserved = format_output_with(
+            &infra,
+            Metadata::default(),
+            ansi_output,
+            false,
+            TINY_PREFIX,
+            TINY_SUFFIX,
+        )
+        .await
+        .unwrap();
         // Use a specific name for the snapshot instead of auto-generated name
         insta::assert_snapshot!(
             "format_output_large_command",
             TempDir::normalize(&preserved)
         );
     }
+
+    #[tokio::test]
+    async fn test_shell_scripted_failure() {
+        let infra = Arc::new(crate::TestInfrastructure::new().command(
+            "cargo test",
+            CommandOutput {
+                command: String::new(),
+                stdout: String::new(),
+                stderr: "test failed".to_string(),
+                exit_code: Some(-1),
+                usage: None,
+            },
+        ));
+        let shell = Shell::new(infra.clone());
+
+        let actual = shell
+            .call(
+                ToolCallContext::default(),
+                ShellInput {
+                    command: "cargo test".to_string(),
+                    cwd: PathBuf::from("/test"),
+                    env: BTreeMap::new(),
+                    clean_env: false,
+                    keep_ansi: false,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
+                },
+            )
+            .await;
+
+        assert!(actual.unwrap_err().to_string().contains("test failed"));
+        assert_eq!(
+            infra.executed_commands(),
+            vec![("cargo test".to_string(), PathBuf::from("/test"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shell_conforms_git_policy() {
+        let infra = Arc::new(crate::TestInfrastructure::new().command(
+            "git commit -m 'fix: handle empty input'",
+            CommandOutput {
+                command: String::new(),
+                stdout: String::new(),
+                stderr: String::new(),
+                exit_code: Some(0),
+                usage: None,
+            },
+        ));
+        let shell = Shell::new(infra.clone());
+        let context = ToolCallContext::default()
+            .git_policy(GitPolicy::default().commit_pattern(r"^(feat|fix): [a-z].+$"));
+
+        shell
+            .call(
+                context,
+                ShellInput {
+                    command: "git commit -m 'Fix: Handle empty input.'".to_string(),
+                    cwd: PathBuf::from("/test"),
+                    env: BTreeMap::new(),
+                    clean_env: false,
+                    keep_ansi: false,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            infra.executed_commands(),
+            vec![(
+                "git commit -m 'fix: handle empty input'".to_string(),
+                PathBuf::from("/test")
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shell_refuses_moderated_command() {
+        let infra = Arc::new(crate::TestInfrastructure::new());
+        let shell = Shell::new(infra.clone());
+        let context = ToolCallContext::default().moderation(Some(Moderation::default().rules(
+            std::collections::BTreeMap::from([(
+                "destructive".to_string(),
+                r"rm\s+-rf\s+/".to_string(),
+            )]),
+        )));
+
+        let actual = shell
+            .call(
+                context,
+                ShellInput {
+                    command: "rm -rf /".to_string(),
+                    cwd: PathBuf::from("/test"),
+                    env: BTreeMap::new(),
+                    clean_env: false,
+                    keep_ansi: false,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
+                },
+            )
+            .await
+            .unwrap_err()
+            .to_string();
+
+        assert_eq!(
+            actual,
+            "Content refused by the moderation rule 'destructive'"
+        );
+        assert_eq!(infra.executed_commands(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_shell_dry_run() {
+        let infra = Arc::new(crate::TestInfrastructure::new());
+        let shell = Shell::new(infra.clone());
+        let context = ToolCallContext::default().dry_run(true);
+
+        let actual = shell
+            .call(
+                context,
+                ShellInput {
+                    command: "git push".to_string(),
+                    cwd: PathBuf::from("/test"),
+                    env: BTreeMap::new(),
+                    clean_env: false,
+                    keep_ansi: false,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let expected = "---\ncommand: git push\ncwd: /test\ndry_run: true\n---\nThe command was not run, since the session is a dry run.";
+        assert_eq!(actual, expected);
+        assert_eq!(infra.executed_commands(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_shell_max_output_bytes() {
+        let infra = Arc::new(crate::TestInfrastructure::new().command(
+            "cargo test",
+            CommandOutput {
+                command: String::new(),
+                stdout: format!("{}{}", "a".repeat(50), "b".repeat(50)),
+                stderr: String::new(),
+                exit_code: Some(0),
+                usage: None,
+            },
+        ));
+
+        let actual = Shell::new(infra)
+            .call(
+                ToolCallContext::default(),
+                ShellInput {
+                    command: "cargo test".to_string(),
+                    cwd: PathBuf::from("/test"),
+                    env: BTreeMap::new(),
+                    clean_env: false,
+                    keep_ansi: false,
+                    timeout_secs: Some(60),
+                    max_output_bytes: Some(10),
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        assert!(actual.contains("total_stdout_chars: 100\n"));
+        assert!(actual.contains("<stdout chars=\"0-5\">\naaaaa\n</stdout>"));
+        assert!(actual.contains("<stdout chars=\"95-100\">\nbbbbb\n</stdout>"));
+    }
+
+    #[tokio::test]
+    async fn test_shell_pty() {
+        let infra = Arc::new(crate::TestInfrastructure::new().command(
+            "cargo build",
+            CommandOutput {
+                command: String::new(),
+                stdout: "\x1b[1m\x1b[32m   Compiling\x1b[0m forge v0.1.0".to_string(),
+                stderr: String::new(),
+                exit_code: Some(0),
+                usage: None,
+            },
+        ));
+
+        let actual = Shell::new(infra)
+            .call(
+                ToolCallContext::default(),
+                ShellInput {
+                    command: "cargo build".to_string(),
+                    cwd: PathBuf::from("/test"),
+                    env: BTreeMap::new(),
+                    clean_env: false,
+                    keep_ansi: false,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: true,
+                    pty_rows: Some(40),
+                    pty_cols: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let expected = "---\npty: 80x40\ncommand: cargo build\nexit_code: 0\n---\n\
+                        <stdout>\n   Compiling forge v0.1.0\n</stdout>";
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_shell_env_and_relative_cwd() {
+        let infra = Arc::new(crate::TestInfrastructure::new().command(
+            "cargo test",
+            CommandOutput {
+                command: String::new(),
+                stdout: "test result: ok".to_string(),
+                stderr: String::new(),
+                exit_code: Some(0),
+                usage: None,
+            },
+        ));
+
+        let actual = Shell::new(infra.clone())
+            .call(
+                ToolCallContext::default(),
+                ShellInput {
+                    command: "cargo test".to_string(),
+                    cwd: PathBuf::from("crates/forge"),
+                    env: BTreeMap::from([
+                        ("RUST_LOG".to_string(), "debug".to_string()),
+                        ("API_KEY".to_string(), "secret".to_string()),
+                    ]),
+                    clean_env: true,
+                    keep_ansi: false,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let expected = "---\ncwd: /test/crates/forge\nenv: API_KEY RUST_LOG\nclean_env: true\n\
+                        command: cargo test\nexit_code: 0\n---\n\
+                        <stdout>\ntest result: ok\n</stdout>";
+        assert_eq!(actual, expected);
+        assert_eq!(
+            infra.executed_commands(),
+            vec![(
+                "cargo test".to_string(),
+                PathBuf::from("/test/crates/forge")
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shell_invalid_env_name() {
+        let infra = Arc::new(crate::TestInfrastructure::new());
+
+        let actual = Shell::new(infra.clone())
+            .call(
+                ToolCallContext::default(),
+                ShellInput {
+                    command: "cargo test".to_string(),
+                    cwd: PathBuf::from("/test"),
+                    env: BTreeMap::from([("A=B".to_string(), "debug".to_string())]),
+                    clean_env: false,
+                    keep_ansi: false,
+                    timeout_secs: None,
+                    max_output_bytes: None,
+                    pty: false,
+                    pty_rows: None,
+                    pty_cols: None,
+                },
+            )
+            .await;
+
+        assert!(actual.is_err());
+        assert_eq!(infra.executed_commands(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_format_output_timed_out_command() {
+        let infra = Arc::new(MockInfrastructure::new());
+        let fixture = CommandOutput {
+            stdout: "running 12 tests".to_string(),
+            stderr: "".to_string(),
+            command: "cargo test".into(),
+            exit_code: None,
+            usage: Some(ResourceUsage::timed_out(Duration::from_secs(60))),
+        };
+
+        let actual = format_output_with(
+            &infra,
+            Metadata::default(),
+            fixture,
+            false,
+            PREFIX_CHARS,
+            SUFFIX_CHARS,
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+
+        assert!(
+            actual.contains("killed: ran longer than the timeout of 60 seconds\ntimed_out: true\n")
+        );
+        assert!(actual.contains("<stdout>\nrunning 12 tests\n</stdout>"));
+    }
+
+    #[tokio::test]
+    async fn test_format_output_killed_command() {
+        let infra = Arc::new(MockInfrastructure::new());
+        let fixture = CommandOutput {
+            stdout: "added 1200 packages".to_string(),
+            stderr: "".to_string(),
+            command: "npm install".into(),
+            exit_code: None,
+            usage: Some(ResourceUsage {
+                peak_memory_bytes: 600 * 1024 * 1024,
+                peak_cpu_percent: 180.4,
+                killed: Some("used 600 MB of memory, over the limit of 512 MB".to_string()),
+                timed_out: false,
+            }),
+        };
+
+        let actual = format_output_with(
+            &infra,
+            Metadata::default(),
+            fixture,
+            false,
+            PREFIX_CHARS,
+            SUFFIX_CHARS,
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+
+        assert!(actual.contains("peak_memory_mb: 600\npeak_cpu_percent: 180\n"));
+        assert!(actual.contains("killed: used 600 MB of memory, over the limit of 512 MB"));
+    }
 }
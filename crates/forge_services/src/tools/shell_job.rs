@@ -0,0 +1,281 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::bail;
+use forge_display::TitleFormat;
+use forge_domain::{
+    CommandOutput, EnvironmentService, ExecutableTool, JobOutput, NamedTool, ToolCallContext,
+    ToolDescription, ToolName,
+};
+use forge_tool_macros::ToolDescription;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::metadata::Metadata;
+use crate::tools::shell::{format_output_with, PREFIX_CHARS, SUFFIX_CHARS};
+use crate::tools::utils::{moderate, simulate_command};
+use crate::{CommandExecutorService, Infrastructure};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ShellSpawnInput {
+    /// The shell command to start in the background, eg: `npm run dev`.
+    pub command: String,
+    /// The working directory where the command should be executed. A
+    /// relative path is resolved against the current working directory.
+    pub cwd: PathBuf,
+}
+
+/// Starts a shell command in the background and returns its job id, for
+/// commands that never exit on their own like dev servers and watch tasks.
+/// Poll the job with forge_tool_shell_poll to read its output and see whether
+/// it exited, and stop it with forge_tool_shell_kill when it's no longer
+/// needed. Use forge_tool_process_shell for commands that finish.
+#[derive(ToolDescription)]
+pub struct ShellSpawn<F>(Arc<F>);
+
+impl<F: Infrastructure> ShellSpawn<F> {
+    pub fn new(infra: Arc<F>) -> Self {
+        Self(infra)
+    }
+}
+
+impl<F> NamedTool for ShellSpawn<F> {
+    fn tool_name() -> ToolName {
+        ToolName::new("forge_tool_shell_spawn")
+    }
+}
+
+#[async_trait::async_trait]
+impl<F: Infrastructure> ExecutableTool for ShellSpawn<F> {
+    type Input = ShellSpawnInput;
+
+    async fn call(&self, context: ToolCallContext, input: Self::Input) -> anyhow::Result<String> {
+        if input.command.trim().is_empty() {
+            bail!("Command string is empty or contains only whitespace");
+        }
+
+        // Commit messages and branch names are conformed to the naming policy
+        let command = match &context.git_policy {
+            Some(policy) => policy.conform_command(&input.command)?,
+            None => input.command,
+        };
+        moderate(&context, &command).await?;
+        let env = self.0.environment_service().get_environment();
+        context
+            .send_text(TitleFormat::debug(format!("Spawn [{}]", env.shell)).sub_title(&command))
+            .await?;
+
+        let cwd = env.cwd.join(&input.cwd);
+        if context.dry_run {
+            return Ok(simulate_command(&command, &cwd));
+        }
+
+        let id = self
+            .0
+            .command_executor_service()
+            .spawn_command(command.clone(), cwd)
+            .await?;
+
+        let metadata = Metadata::default()
+            .add("job_id", id)
+            .add("command", &command);
+        Ok(format!(
+            "{metadata}The command runs in the background. Read its output with forge_tool_shell_poll and stop it with forge_tool_shell_kill."
+        ))
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ShellJobInput {
+    /// The id of the job, as returned by forge_tool_shell_spawn.
+    pub job_id: u64,
+}
+
+/// Returns the output that a background command started with
+/// forge_tool_shell_spawn wrote since it was last polled, and whether it is
+/// still running. Once the command exited its exit code is reported and the
+/// job is forgotten.
+#[derive(ToolDescription)]
+pub struct ShellPoll<F>(Arc<F>);
+
+impl<F: Infrastructure> ShellPoll<F> {
+    pub fn new(infra: Arc<F>) -> Self {
+        Self(infra)
+    }
+}
+
+impl<F> NamedTool for ShellPoll<F> {
+    fn tool_name() -> ToolName {
+        ToolName::new("forge_tool_shell_poll")
+    }
+}
+
+#[async_trait::async_trait]
+impl<F: Infrastructure> ExecutableTool for ShellPoll<F> {
+    type Input = ShellJobInput;
+
+    async fn call(&self, context: ToolCallContext, input: Self::Input) -> anyhow::Result<String> {
+        context
+            .send_text(TitleFormat::debug("Poll").sub_title(format!("job {}", input.job_id)))
+            .await?;
+        let output = self
+            .0
+            .command_executor_service()
+            .poll_command(input.job_id)
+            .await?;
+        format_job_output(&self.0, output).await
+    }
+}
+
+/// Stops a background command started with forge_tool_shell_spawn, along
+/// with the processes that it spawned, and returns the output that it wrote
+/// since it was last polled.
+#[derive(ToolDescription)]
+pub struct ShellKill<F>(Arc<F>);
+
+impl<F: Infrastructure> ShellKill<F> {
+    pub fn new(infra: Arc<F>) -> Self {
+        Self(infra)
+    }
+}
+
+impl<F> NamedTool for ShellKill<F> {
+    fn tool_name() -> ToolName {
+        ToolName::new("forge_tool_shell_kill")
+    }
+}
+
+#[async_trait::async_trait]
+impl<F: Infrastructure> ExecutableTool for ShellKill<F> {
+    type Input = ShellJobInput;
+
+    async fn call(&self, context: ToolCallContext, input: Self::Input) -> anyhow::Result<String> {
+        context
+            .send_text(TitleFormat::debug("Kill").sub_title(format!("job {}", input.job_id)))
+            .await?;
+        if context.dry_run {
+            return Ok(format!(
+                "Dry run: job {} was not killed. The session only previews changes.",
+                input.job_id
+            ));
+        }
+        let output = self
+            .0
+            .command_executor_service()
+            .kill_command(input.job_id)
+            .await?;
+        format_job_output(&self.0, output).await
+    }
+}
+
+/// Formats the output of a background command like the output of a command
+/// that finished, with the id of the job and whether it's still running
+async fn format_job_output<F: Infrastructure>(
+    infra: &Arc<F>,
+    output: JobOutput,
+) -> anyhow::Result<String> {
+    let metadata = Metadata::default()
+        .add("job_id", output.id)
+        .add("running", output.running);
+    if output.running && output.stdout.trim().is_empty() && output.stderr.trim().is_empty() {
+        let metadata = metadata.add("command", &output.command);
+        return Ok(format!("{metadata}No new output since the last poll."));
+    }
+
+    let output = CommandOutput {
+        command: output.command,
+        stdout: output.stdout,
+        stderr: output.stderr,
+        exit_code: output.exit_code,
+        usage: None,
+    };
+    format_output_with(infra, metadata, output, false, PREFIX_CHARS, SUFFIX_CHARS).await
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::TestInfrastructure;
+
+    #[tokio::test]
+    async fn test_shell_spawn_and_poll() {
+        let infra = Arc::new(TestInfrastructure::new().command(
+            "npm run dev",
+            CommandOutput {
+                command: String::new(),
+                stdout: "Listening on 3000".to_string(),
+                stderr: String::new(),
+                exit_code: Some(0),
+                usage: None,
+            },
+        ));
+
+        let spawned = ShellSpawn::new(infra.clone())
+            .call(
+                ToolCallContext::default(),
+                ShellSpawnInput {
+                    command: "npm run dev".to_string(),
+                    cwd: PathBuf::from("/test"),
+                },
+            )
+            .await
+            .unwrap();
+        let actual = ShellPoll::new(infra.clone())
+            .call(ToolCallContext::default(), ShellJobInput { job_id: 1 })
+            .await
+            .unwrap();
+
+        assert!(spawned.starts_with("---\njob_id: 1\ncommand: npm run dev\n---\n"));
+        let expected = "---\njob_id: 1\nrunning: false\ncommand: npm run dev\nexit_code: 0\n---\n\
+                        <stdout>\nListening on 3000\n</stdout>";
+        assert_eq!(actual, expected);
+        assert_eq!(
+            infra.executed_commands(),
+            vec![("npm run dev".to_string(), PathBuf::from("/test"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shell_spawn_relative_cwd() {
+        let infra = Arc::new(TestInfrastructure::new().command(
+            "npm run dev",
+            CommandOutput {
+                command: String::new(),
+                stdout: String::new(),
+                stderr: String::new(),
+                exit_code: Some(0),
+                usage: None,
+            },
+        ));
+        let cwd = infra.environment_service().get_environment().cwd;
+
+        ShellSpawn::new(infra.clone())
+            .call(
+                ToolCallContext::default(),
+                ShellSpawnInput {
+                    command: "npm run dev".to_string(),
+                    cwd: PathBuf::from("web"),
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            infra.executed_commands(),
+            vec![("npm run dev".to_string(), cwd.join("web"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shell_kill_unknown_job() {
+        let infra = Arc::new(TestInfrastructure::new());
+
+        let actual = ShellKill::new(infra)
+            .call(ToolCallContext::default(), ShellJobInput { job_id: 7 })
+            .await;
+
+        assert!(actual.is_err());
+    }
+}
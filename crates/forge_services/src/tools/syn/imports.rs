@@ -0,0 +1,567 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::Range;
+use std::path::Path;
+
+use tree_sitter::{Node, Parser, Tree};
+
+/// Names that are in scope in every Rust module, so they are never imported
+const PRELUDE: &[&str] = &[
+    "Self",
+    "String",
+    "Vec",
+    "Option",
+    "Some",
+    "None",
+    "Result",
+    "Ok",
+    "Err",
+    "Box",
+    "ToString",
+    "ToOwned",
+    "Into",
+    "From",
+    "TryFrom",
+    "TryInto",
+    "AsRef",
+    "AsMut",
+    "Iterator",
+    "IntoIterator",
+    "DoubleEndedIterator",
+    "ExactSizeIterator",
+    "Extend",
+    "FromIterator",
+    "Default",
+    "Clone",
+    "Copy",
+    "Send",
+    "Sync",
+    "Sized",
+    "Unpin",
+    "Drop",
+    "Fn",
+    "FnMut",
+    "FnOnce",
+    "PartialEq",
+    "Eq",
+    "PartialOrd",
+    "Ord",
+    "std",
+    "core",
+    "alloc",
+    "bool",
+    "char",
+    "str",
+    "u8",
+    "u16",
+    "u32",
+    "u64",
+    "u128",
+    "usize",
+    "i8",
+    "i16",
+    "i32",
+    "i64",
+    "i128",
+    "isize",
+    "f32",
+    "f64",
+];
+
+/// Imports that were added to or removed from a file after it was patched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportFix {
+    /// Content of the file with its imports fixed
+    pub content: String,
+    /// Paths of the imports that were added, eg: `std::collections::HashMap`
+    pub added: Vec<String>,
+    /// Paths of the imports that were removed
+    pub removed: Vec<String>,
+}
+
+/// An item imported by a `use` declaration
+struct Import {
+    /// Name that the item is imported as
+    name: String,
+    /// Path of the item, eg: `std::collections::HashMap`
+    path: String,
+    /// Bytes to remove to drop the item from its `use` list, with the comma
+    /// that separates it from the others, if it's in one
+    item: Option<Range<usize>>,
+}
+
+/// A `use` declaration and the items it imports
+struct UseDeclaration {
+    range: Range<usize>,
+    /// Whether the declaration is at the root of the file rather than in a
+    /// module or a function
+    top_level: bool,
+    /// Whether the declaration may be removed, ie: it isn't a re-export and
+    /// has no attributes
+    removable: bool,
+    glob: bool,
+    imports: Vec<Import>,
+}
+
+/// Whether the imports of a file can be fixed after a patch, which is only
+/// supported for Rust
+pub fn supports_imports(path: impl AsRef<Path>) -> bool {
+    path.as_ref().extension().is_some_and(|ext| ext == "rs")
+}
+
+/// Fixes the imports of a file after a patch changed its content from
+/// `before` to `after`: the names that the patch started to use without
+/// importing them are imported like the `siblings`, ie: the contents of the
+/// files next to it, import them, and the imports that the patch left unused
+/// are removed. Returns `None` when nothing was changed, or when either
+/// content has syntax errors.
+pub fn fix_imports(
+    path: impl AsRef<Path>,
+    before: &str,
+    after: &str,
+    siblings: &[String],
+) -> Option<ImportFix> {
+    if !supports_imports(path) {
+        return None;
+    }
+    let before_tree = parse(before)?;
+    let tree = parse(after)?;
+    let used_before = references(before_tree.root_node(), before);
+    let used = references(tree.root_node(), after);
+    let declarations = declarations(tree.root_node(), after);
+
+    // Imports that were used before the patch and aren't anymore. Imports that
+    // were already unused may be traits, whose use can't be told from the
+    // syntax alone.
+    let mut removals = Vec::new();
+    let mut removed = Vec::new();
+    for declaration in declarations
+        .iter()
+        .filter(|declaration| declaration.removable)
+    {
+        let unused = declaration
+            .imports
+            .iter()
+            .filter(|import| used_before.contains(&import.name) && !used.contains(&import.name))
+            .collect::<Vec<_>>();
+        if unused.is_empty() {
+            continue;
+        }
+        if unused.len() == declaration.imports.len() && !declaration.glob {
+            removals.push(line_range(after, declaration.range.clone()));
+            removed.extend(unused.iter().map(|import| import.path.clone()));
+            continue;
+        }
+        for import in unused {
+            if let Some(item) = &import.item {
+                removals.push(item.clone());
+                removed.push(import.path.clone());
+            }
+        }
+    }
+
+    // Names that the patch started to use and that aren't defined or imported
+    let mut added = Vec::new();
+    if !declarations
+        .iter()
+        .any(|declaration| declaration.top_level && declaration.glob)
+    {
+        let mut defined = definitions(tree.root_node(), after);
+        defined.extend(
+            declarations
+                .iter()
+                .flat_map(|declaration| &declaration.imports)
+                .map(|import| import.name.clone()),
+        );
+        let candidates = candidates(siblings);
+        for name in path_heads(tree.root_node(), after) {
+            if used_before.contains(&name)
+                || defined.contains(&name)
+                || PRELUDE.contains(&name.as_str())
+            {
+                continue;
+            }
+            if let Some(path) = candidates.get(&name).and_then(most_common) {
+                added.push(path);
+            }
+        }
+    }
+
+    if removed.is_empty() && added.is_empty() {
+        return None;
+    }
+
+    added.sort();
+    removed.sort();
+    let mut content = remove(after, removals);
+    if !added.is_empty() {
+        let lines = added
+            .iter()
+            .map(|path| format!("use {path};"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let tree = parse(&content)?;
+        match insertion_point(tree.root_node(), &content) {
+            (position, true) => content.insert_str(position, &format!("\n{lines}")),
+            (position, false) => content.insert_str(position, &format!("{lines}\n\n")),
+        }
+    }
+    Some(ImportFix { content, added, removed })
+}
+
+/// Parses Rust code, if it has no syntax errors
+fn parse(content: &str) -> Option<Tree> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_rust::LANGUAGE.into())
+        .ok()?;
+    parser
+        .parse(content, None)
+        .filter(|tree| !tree.root_node().has_error())
+}
+
+fn text<'a>(node: Node, source: &'a str) -> &'a str {
+    &source[node.byte_range()]
+}
+
+/// Visits the nodes of a tree in order, skipping the children of the nodes
+/// for which `visit` returns false
+fn walk<'a, F: FnMut(Node<'a>) -> bool>(node: Node<'a>, visit: &mut F) {
+    if !visit(node) {
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        walk(child, visit);
+    }
+}
+
+/// Whether `node` is the given field of its parent
+fn is_field(node: Node, field: &str) -> bool {
+    node.parent()
+        .and_then(|parent| parent.child_by_field_name(field))
+        .is_some_and(|child| child == node)
+}
+
+/// The `use` declarations of a file, at any depth
+fn declarations(root: Node, source: &str) -> Vec<UseDeclaration> {
+    let mut declarations = Vec::new();
+    walk(root, &mut |node| {
+        if node.kind() != "use_declaration" {
+            return true;
+        }
+        let mut cursor = node.walk();
+        let public = node
+            .children(&mut cursor)
+            .any(|child| child.kind() == "visibility_modifier");
+        let attributed = node
+            .prev_named_sibling()
+            .is_some_and(|sibling| sibling.kind() == "attribute_item");
+        let mut declaration = UseDeclaration {
+            range: node.byte_range(),
+            top_level: node
+                .parent()
+                .is_some_and(|parent| parent.kind() == "source_file"),
+            removable: !public && !attributed,
+            glob: false,
+            imports: Vec::new(),
+        };
+        if let Some(argument) = node.child_by_field_name("argument") {
+            collect_imports(argument, source, "", None, &mut declaration);
+        }
+        declarations.push(declaration);
+        false
+    });
+    declarations
+}
+
+/// Collects the items imported by the argument of a `use` declaration, whose
+/// path starts with `prefix`
+fn collect_imports(
+    node: Node,
+    source: &str,
+    prefix: &str,
+    item: Option<Range<usize>>,
+    declaration: &mut UseDeclaration,
+) {
+    let path = match prefix {
+        "" => text(node, source).to_string(),
+        prefix => format!("{prefix}::{}", text(node, source)),
+    };
+    let name = match node.kind() {
+        "identifier" => Some(node),
+        "scoped_identifier" => node.child_by_field_name("name"),
+        "use_as_clause" => node.child_by_field_name("alias"),
+        "scoped_use_list" => {
+            let prefix = match node.child_by_field_name("path") {
+                Some(path) if prefix.is_empty() => text(path, source).to_string(),
+                Some(path) => format!("{prefix}::{}", text(path, source)),
+                None => prefix.to_string(),
+            };
+            if let Some(list) = node.child_by_field_name("list") {
+                collect_imports(list, source, &prefix, None, declaration);
+            }
+            None
+        }
+        "use_list" => {
+            let mut cursor = node.walk();
+            let items = node
+                .named_children(&mut cursor)
+                .filter(|child| !child.is_extra())
+                .collect::<Vec<_>>();
+            for (index, child) in items.iter().enumerate() {
+                // The comma after the item is removed with it, or the one
+                // before it when it's the last one
+                let range = match (index.checked_sub(1), items.get(index + 1)) {
+                    (_, Some(next)) => child.start_byte()..next.start_byte(),
+                    (Some(previous), None) => items[previous].end_byte()..child.end_byte(),
+                    (None, None) => child.byte_range(),
+                };
+                collect_imports(*child, source, prefix, Some(range), declaration);
+            }
+            None
+        }
+        "use_wildcard" => {
+            declaration.glob = true;
+            None
+        }
+        _ => None,
+    };
+    if let Some(name) = name {
+        declaration
+            .imports
+            .push(Import { name: text(name, source).to_string(), path, item });
+    }
+}
+
+/// Names that the code of a file refers to, outside of its `use`
+/// declarations
+fn references(root: Node, source: &str) -> HashSet<String> {
+    let mut names = HashSet::new();
+    walk(root, &mut |node| match node.kind() {
+        "use_declaration" => false,
+        "identifier" | "type_identifier" => {
+            names.insert(text(node, source).to_string());
+            true
+        }
+        _ => true,
+    });
+    names
+}
+
+/// Names that a file refers to as types, macros or at the start of paths,
+/// which must be in scope, eg: `HashMap` in `HashMap::new()`
+fn path_heads(root: Node, source: &str) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    walk(root, &mut |node| {
+        let head = match node.kind() {
+            "use_declaration" => return false,
+            "type_identifier" => node
+                .parent()
+                .is_none_or(|parent| parent.kind() != "scoped_type_identifier"),
+            "identifier" => node.parent().is_some_and(|parent| match parent.kind() {
+                "scoped_identifier" | "scoped_type_identifier" => is_field(node, "path"),
+                "macro_invocation" => is_field(node, "macro"),
+                _ => false,
+            }),
+            _ => false,
+        };
+        if head {
+            names.insert(text(node, source).to_string());
+        }
+        true
+    });
+    names
+}
+
+/// Names of the items and type parameters that a file defines
+fn definitions(root: Node, source: &str) -> HashSet<String> {
+    let mut names = HashSet::new();
+    walk(root, &mut |node| {
+        let name = match node.kind() {
+            "struct_item"
+            | "enum_item"
+            | "union_item"
+            | "trait_item"
+            | "type_item"
+            | "function_item"
+            | "function_signature_item"
+            | "mod_item"
+            | "const_item"
+            | "static_item"
+            | "macro_definition"
+            | "associated_type" => node.child_by_field_name("name"),
+            "type_identifier" => node
+                .parent()
+                .is_some_and(|parent| match parent.kind() {
+                    "type_parameters" => true,
+                    "constrained_type_parameter" => is_field(node, "left"),
+                    "optional_type_parameter" => is_field(node, "name"),
+                    _ => false,
+                })
+                .then_some(node),
+            _ => None,
+        };
+        if let Some(name) = name {
+            names.insert(text(name, source).to_string());
+        }
+        true
+    });
+    names
+}
+
+/// Paths that the given files import each name from, with the number of
+/// files that do. Paths relative to the files themselves are skipped.
+fn candidates(files: &[String]) -> HashMap<String, BTreeMap<String, usize>> {
+    let mut candidates: HashMap<String, BTreeMap<String, usize>> = HashMap::new();
+    for file in files {
+        let Some(tree) = parse(file) else {
+            continue;
+        };
+        let declarations = declarations(tree.root_node(), file);
+        let imports = declarations
+            .iter()
+            .filter(|declaration| declaration.top_level)
+            .flat_map(|declaration| &declaration.imports)
+            .filter(|import| {
+                !import.path.starts_with("self::") && !import.path.starts_with("super::")
+            });
+        for import in imports {
+            *candidates
+                .entry(import.name.clone())
+                .or_default()
+                .entry(import.path.clone())
+                .or_default() += 1;
+        }
+    }
+    candidates
+}
+
+/// The path imported by the most files, or the first one in order when
+/// several are
+fn most_common(paths: &BTreeMap<String, usize>) -> Option<String> {
+    paths
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+        .map(|(path, _)| path.clone())
+}
+
+/// Extends the range of a declaration to its whole line, when nothing else is
+/// on it, so that removing it doesn't leave an empty line
+fn line_range(source: &str, range: Range<usize>) -> Range<usize> {
+    let line_start = source[..range.start]
+        .rfind('\n')
+        .map_or(0, |index| index + 1);
+    let start = if source[line_start..range.start].trim().is_empty() {
+        line_start
+    } else {
+        range.start
+    };
+    let mut end = range.end;
+    if start == line_start && source[end..].starts_with('\n') {
+        end += 1;
+        // A blank line is left between the lines around the declaration, but
+        // not at the start of the file
+        let blank = start == 0 || source[..start].ends_with("\n\n");
+        if blank && source[end..].starts_with('\n') {
+            end += 1;
+        }
+    }
+    start..end
+}
+
+/// Removes the given ranges from the source, merging the ones that overlap
+fn remove(source: &str, mut ranges: Vec<Range<usize>>) -> String {
+    ranges.sort_by_key(|range| range.start);
+    let mut content = String::with_capacity(source.len());
+    let mut position = 0;
+    for range in ranges {
+        if range.start > position {
+            content.push_str(&source[position..range.start]);
+        }
+        position = position.max(range.end);
+    }
+    content.push_str(&source[position..]);
+    content
+}
+
+/// Where imports are added to a file: after its last top level `use`
+/// declaration, which is reported with `true`, or else before its first item
+fn insertion_point(root: Node, source: &str) -> (usize, bool) {
+    let mut cursor = root.walk();
+    let children = root.named_children(&mut cursor).collect::<Vec<_>>();
+    if let Some(last) = children
+        .iter()
+        .rev()
+        .find(|child| child.kind() == "use_declaration")
+    {
+        return (last.end_byte(), true);
+    }
+
+    // Inner attributes and doc comments belong to the file, and outer ones to
+    // the item that follows them
+    let first = children.iter().find(|child| {
+        let text = text(**child, source);
+        child.kind() != "inner_attribute_item"
+            && !text.starts_with("//!")
+            && !text.starts_with("/*!")
+    });
+    (
+        first.map_or(source.len(), |child| child.start_byte()),
+        false,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_fix_imports() {
+        let before = "use std::collections::{BTreeMap, HashSet};\nuse std::fs;\n\nfn a() -> HashSet<u8> {\n    let _ = fs::read(\"a\");\n    HashSet::new()\n}\n";
+        let after = "use std::collections::{BTreeMap, HashSet};\nuse std::fs;\n\nfn a() -> HashMap<u8, u8> {\n    bail!(\"a\");\n    HashMap::new()\n}\n";
+        let siblings = vec![
+            "use std::collections::{HashMap, HashSet};\nuse anyhow::bail;\n".to_string(),
+            "use std::collections::HashMap;\n".to_string(),
+            "use foo::HashMap;\n".to_string(),
+        ];
+
+        let actual = fix_imports("lib.rs", before, after, &siblings).unwrap();
+
+        let expected = ImportFix {
+            content: "use std::collections::{BTreeMap};\nuse anyhow::bail;\nuse std::collections::HashMap;\n\nfn a() -> HashMap<u8, u8> {\n    bail!(\"a\");\n    HashMap::new()\n}\n".to_string(),
+            added: vec![
+                "anyhow::bail".to_string(),
+                "std::collections::HashMap".to_string(),
+            ],
+            removed: vec!["std::collections::HashSet".to_string(), "std::fs".to_string()],
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_fix_imports_keeps_unrelated_imports() {
+        // The trait is unused as far as the syntax tells, and the definition
+        // of the struct isn't imported
+        let before = "use std::io::Write;\n\nfn a() {}\n";
+        let after = "use std::io::Write;\n\nstruct Config;\n\nfn a() -> Config {\n    Config\n}\n";
+        let siblings = vec!["use crate::Config;\n".to_string()];
+
+        let actual = fix_imports("lib.rs", before, after, &siblings);
+
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn test_fix_imports_without_imports() {
+        let before = "//! Module docs\n\n/// Docs\nfn a() {}\n";
+        let after = "//! Module docs\n\n/// Docs\nfn a() -> Value {\n    json!(1)\n}\n";
+        let siblings = vec!["use serde_json::{json, Value};\n".to_string()];
+
+        let actual = fix_imports("lib.rs", before, after, &siblings).unwrap();
+
+        let expected = "//! Module docs\n\nuse serde_json::Value;\nuse serde_json::json;\n\n/// Docs\nfn a() -> Value {\n    json!(1)\n}\n";
+        assert_eq!(actual.content, expected);
+    }
+}
@@ -1,3 +1,7 @@
+mod imports;
+mod symbols;
 mod validate;
 
-pub use validate::validate;
+pub use imports::{fix_imports, supports_imports, ImportFix};
+pub use symbols::{enclosing_symbols, outline, Symbol};
+pub use validate::{diagnostics, validate, write_diagnostics};
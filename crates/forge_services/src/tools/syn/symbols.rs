@@ -0,0 +1,211 @@
+use std::path::Path;
+
+use tree_sitter::{Node, Parser, Point, Tree};
+
+use super::validate::extension;
+
+/// Kinds of the nodes that define a symbol, with the keyword that they are
+/// shown with and the field of their name
+const DEFINITIONS: &[(&str, &str, &str)] = &[
+    // Rust
+    ("function_item", "fn", "name"),
+    ("function_signature_item", "fn", "name"),
+    ("struct_item", "struct", "name"),
+    ("enum_item", "enum", "name"),
+    ("union_item", "union", "name"),
+    ("trait_item", "trait", "name"),
+    ("impl_item", "impl", "type"),
+    ("mod_item", "mod", "name"),
+    ("macro_definition", "macro_rules!", "name"),
+    // Python
+    ("function_definition", "def", "name"),
+    ("class_definition", "class", "name"),
+    // JavaScript and TypeScript
+    ("function_declaration", "function", "name"),
+    ("method_definition", "method", "name"),
+    ("class_declaration", "class", "name"),
+    ("interface_declaration", "interface", "name"),
+    // Go and Java
+    ("method_declaration", "method", "name"),
+    ("type_spec", "type", "name"),
+    ("constructor_declaration", "constructor", "name"),
+    ("enum_declaration", "enum", "name"),
+];
+
+/// A definition of a file, as listed in its outline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// Keyword of the definition, eg: `fn` or `class`
+    pub kind: &'static str,
+    pub name: String,
+    /// First line of the definition, starting at 1
+    pub start_line: usize,
+    /// Last line of the definition, starting at 1
+    pub end_line: usize,
+    /// Number of definitions that enclose this one, eg: 1 for a method
+    pub depth: usize,
+}
+
+impl Symbol {
+    fn new(node: Node, content: &str, depth: usize) -> Option<Self> {
+        let &(_, kind, field) = DEFINITIONS
+            .iter()
+            .find(|(kind, _, _)| *kind == node.kind())?;
+        let name = node.child_by_field_name(field)?;
+        Some(Self {
+            kind,
+            name: content[name.byte_range()].to_string(),
+            start_line: node.start_position().row + 1,
+            end_line: node.end_position().row + 1,
+            depth,
+        })
+    }
+}
+
+/// Parses a file with the parser of its language, if it's supported
+fn parse(path: &Path, content: &str) -> Option<Tree> {
+    let language = extension(path.extension()?.to_str()?)?;
+    let mut parser = Parser::new();
+    parser.set_language(&language).ok()?;
+    parser.parse(content, None)
+}
+
+/// The definitions of a file in order, eg: the functions of a module and the
+/// methods of its classes. Returns `None` for files of unsupported languages.
+pub fn outline(path: impl AsRef<Path>, content: &str) -> Option<Vec<Symbol>> {
+    let tree = parse(path.as_ref(), content)?;
+    let mut symbols = Vec::new();
+    collect_symbols(tree.root_node(), content, 0, &mut symbols);
+    Some(symbols)
+}
+
+fn collect_symbols(node: Node, content: &str, depth: usize, symbols: &mut Vec<Symbol>) {
+    let symbol = Symbol::new(node, content, depth);
+    let depth = depth + usize::from(symbol.is_some());
+    symbols.extend(symbol);
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_symbols(child, content, depth, symbols);
+    }
+}
+
+/// The definitions that enclose each of the given lines of a file, from the
+/// outermost to the innermost, eg: `impl Server > fn handle_request`. Lines
+/// are 0-based. A line outside of any definition has no symbol, and neither
+/// do the lines of files in unsupported languages.
+pub fn enclosing_symbols(
+    path: impl AsRef<Path>,
+    content: &str,
+    lines: &[usize],
+) -> Vec<Option<String>> {
+    let Some(tree) = parse(path.as_ref(), content) else {
+        return vec![None; lines.len()];
+    };
+
+    let texts = content.lines().collect::<Vec<_>>();
+    lines
+        .iter()
+        .map(|&line| {
+            // The first character of the line that isn't indentation
+            let column = texts
+                .get(line)
+                .map_or(0, |text| text.len() - text.trim_start().len());
+            let point = Point::new(line, column);
+            let node = tree.root_node().descendant_for_point_range(point, point)?;
+
+            let mut symbols = Vec::new();
+            let mut ancestor = Some(node);
+            while let Some(node) = ancestor {
+                symbols.extend(
+                    Symbol::new(node, content, 0)
+                        .map(|symbol| format!("{} {}", symbol.kind, symbol.name)),
+                );
+                ancestor = node.parent();
+            }
+            if symbols.is_empty() {
+                return None;
+            }
+            symbols.reverse();
+            Some(symbols.join(" > "))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_enclosing_symbols() {
+        let fixture = "use std::fmt;\n\nstruct Server;\n\nimpl Server {\n    fn handle_request(&self) {\n        let a = 1;\n    }\n}\n";
+
+        let actual = enclosing_symbols("server.rs", fixture, &[0, 2, 5, 6, 7]);
+
+        let expected = vec![
+            None,
+            Some("struct Server".to_string()),
+            Some("impl Server > fn handle_request".to_string()),
+            Some("impl Server > fn handle_request".to_string()),
+            Some("impl Server > fn handle_request".to_string()),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_enclosing_symbols_python() {
+        let fixture = "class Server:\n    def handle(self):\n        return 1\n";
+
+        let actual = enclosing_symbols("server.py", fixture, &[2]);
+
+        let expected = vec![Some("class Server > def handle".to_string())];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_outline() {
+        let fixture = "struct Server;\n\nimpl Server {\n    fn new() -> Self {\n        Server\n    }\n\n    fn handle(&self) {}\n}\n";
+
+        let actual = outline("server.rs", fixture).unwrap();
+
+        let expected = vec![
+            Symbol {
+                kind: "struct",
+                name: "Server".to_string(),
+                start_line: 1,
+                end_line: 1,
+                depth: 0,
+            },
+            Symbol {
+                kind: "impl",
+                name: "Server".to_string(),
+                start_line: 3,
+                end_line: 9,
+                depth: 0,
+            },
+            Symbol {
+                kind: "fn",
+                name: "new".to_string(),
+                start_line: 4,
+                end_line: 6,
+                depth: 1,
+            },
+            Symbol {
+                kind: "fn",
+                name: "handle".to_string(),
+                start_line: 8,
+                end_line: 8,
+                depth: 1,
+            },
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_outline_unsupported_language() {
+        let actual = outline("notes.txt", "struct Server;");
+        assert_eq!(actual, None);
+    }
+}
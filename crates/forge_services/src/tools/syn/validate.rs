@@ -1,7 +1,9 @@
+use std::fmt::{self, Display, Write};
 use std::path::Path;
 
+use forge_display::CodeFrame;
 use thiserror::Error;
-use tree_sitter::{Language, LanguageError, Parser};
+use tree_sitter::{Language, LanguageError, Node, Parser};
 
 /// Represents possible errors that can occur during syntax validation
 #[derive(Debug, Error, PartialEq)]
@@ -19,9 +21,186 @@ pub enum Error {
     Parse {
         file_path: String,
         extension: String,
+        /// 1-based line of the first syntax error
+        line: usize,
+        /// 1-based column, in characters, of the first syntax error
+        column: usize,
     },
 }
 
+impl Error {
+    /// Returns the line and column of the syntax error, if known
+    pub fn location(&self) -> Option<(usize, usize)> {
+        match self {
+            Error::Parse { line, column, .. } => Some((*line, *column)),
+            _ => None,
+        }
+    }
+
+    /// Renders the lines around the syntax error for display in the terminal
+    pub fn code_frame(&self, content: &str) -> Option<CodeFrame> {
+        match self {
+            Error::Parse { file_path, line, column, .. } => {
+                Some(CodeFrame::new(content, *line, *column).path(file_path.as_str()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Maximum number of diagnostics reported for a file, since a single mistake
+/// can make the parser report errors until the end of the file
+const MAX_DIAGNOSTICS: usize = 10;
+
+/// Maximum number of characters of the unexpected text quoted in a diagnostic
+const MAX_QUOTED_CHARS: usize = 40;
+
+/// How certain a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Text that the parser couldn't make sense of
+    Error,
+    /// Token that the parser assumed to be missing to recover from an error,
+    /// which may be the mistake itself or a consequence of an earlier one
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A syntax error found in a file, so that the agent can fix it precisely
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// 1-based line
+    pub line: usize,
+    /// 1-based column, in characters
+    pub column: usize,
+    pub message: String,
+    pub severity: Severity,
+}
+
+impl Diagnostic {
+    fn new(line: usize, column: usize, message: impl ToString, severity: Severity) -> Self {
+        Self { line, column, message: message.to_string(), severity }
+    }
+}
+
+/// Writes the diagnostics as a list of the front matter of a tool result
+pub fn write_diagnostics(result: &mut String, diagnostics: &[Diagnostic]) -> fmt::Result {
+    if diagnostics.is_empty() {
+        return Ok(());
+    }
+    writeln!(result, "diagnostics:")?;
+    for diagnostic in diagnostics {
+        writeln!(result, "  - line: {}", diagnostic.line)?;
+        writeln!(result, "    column: {}", diagnostic.column)?;
+        writeln!(result, "    severity: {}", diagnostic.severity)?;
+        writeln!(result, "    message: {:?}", diagnostic.message)?;
+    }
+    Ok(())
+}
+
+/// Collects the nodes of the tree that are errors or were inserted by the
+/// parser to recover from one, in order
+fn collect_errors<'a>(node: Node<'a>, errors: &mut Vec<Node<'a>>) {
+    if errors.len() >= MAX_DIAGNOSTICS || !node.has_error() {
+        return;
+    }
+    if node.is_error() || node.is_missing() {
+        errors.push(node);
+        return;
+    }
+
+    let mut cursor = node.walk();
+    let children = node.children(&mut cursor).collect::<Vec<_>>();
+    for child in children {
+        collect_errors(child, errors);
+    }
+}
+
+/// Diagnostics of the errors of a parsed tree
+fn tree_diagnostics(content: &str, root: Node) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+    collect_errors(root, &mut errors);
+    // The tree may have an error without a node that shows where
+    if errors.is_empty() && root.has_error() {
+        errors.push(root);
+    }
+
+    errors
+        .into_iter()
+        .map(|node| {
+            let position = node.start_position();
+            let (line, column) = location(content, position.row, position.column);
+            if node.is_missing() {
+                Diagnostic::new(
+                    line,
+                    column,
+                    format!("Missing `{}`", node.kind()),
+                    Severity::Warning,
+                )
+            } else {
+                let text = content[node.byte_range()].trim();
+                let quoted = text
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .chars()
+                    .take(MAX_QUOTED_CHARS)
+                    .collect::<String>();
+                Diagnostic::new(
+                    line,
+                    column,
+                    format!("Unexpected `{quoted}`"),
+                    Severity::Error,
+                )
+            }
+        })
+        .collect()
+}
+
+/// Diagnostic of a JSON document, which can only have one since parsing stops
+/// at the first error
+fn json_diagnostics(content: &str) -> Vec<Diagnostic> {
+    let Err(error) = serde_json::from_str::<serde::de::IgnoredAny>(content) else {
+        return Vec::new();
+    };
+    // The message of serde_json ends with the location, which is reported apart
+    let message = error.to_string();
+    let message = message
+        .rsplit_once(" at line ")
+        .map_or(message.as_str(), |(message, _)| message);
+    vec![Diagnostic::new(
+        error.line(),
+        error.column(),
+        message,
+        Severity::Error,
+    )]
+}
+
+/// Converts the zero-based byte position reported by tree-sitter into a
+/// one-based line and character column
+fn location(content: &str, row: usize, byte_column: usize) -> (usize, usize) {
+    let column = content
+        .lines()
+        .nth(row)
+        .map(|line| {
+            let end = (0..=byte_column.min(line.len()))
+                .rev()
+                .find(|index| line.is_char_boundary(*index))
+                .unwrap_or_default();
+            line[..end].chars().count()
+        })
+        .unwrap_or_default();
+    (row + 1, column + 1)
+}
+
 /// Maps file extensions to their corresponding Tree-sitter language parsers.
 ///
 /// This function takes a file extension as input and returns the appropriate
@@ -38,6 +217,8 @@ pub enum Error {
 /// * Rust (.rs)
 /// * JavaScript/TypeScript (.js, .jsx, .ts, .tsx)
 /// * Python (.py)
+///
+/// JSON is validated without Tree-sitter, see [`diagnostics`].
 pub fn extension(ext: &str) -> Option<Language> {
     match ext.to_lowercase().as_str() {
         "rs" => Some(tree_sitter_rust::LANGUAGE.into()),
@@ -54,6 +235,39 @@ pub fn extension(ext: &str) -> Option<Language> {
     }
 }
 
+/// Parses the content of a file with the given extension, returning its syntax
+/// errors. Files of unsupported languages have none.
+fn parse(ext: &str, content: &str) -> Result<Vec<Diagnostic>, Error> {
+    if ext.eq_ignore_ascii_case("json") {
+        return Ok(json_diagnostics(content));
+    }
+    let Some(language) = extension(ext) else {
+        return Ok(Vec::new());
+    };
+
+    let mut parser = Parser::new();
+    parser.set_language(&language)?;
+    match parser.parse(content, None) {
+        Some(tree) => Ok(tree_diagnostics(content, tree.root_node())),
+        None => Ok(vec![Diagnostic::new(
+            1,
+            1,
+            "The file could not be parsed",
+            Severity::Error,
+        )]),
+    }
+}
+
+/// Syntax errors of a file, for Rust, Python, JavaScript/TypeScript, JSON and
+/// the other languages of [`extension`]
+pub fn diagnostics(path: impl AsRef<Path>, content: &str) -> Vec<Diagnostic> {
+    path.as_ref()
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(|ext| parse(ext, content).ok())
+        .unwrap_or_default()
+}
+
 /// Validates source code content using Tree-sitter parsers.
 ///
 /// This function attempts to parse the provided content using a Tree-sitter
@@ -80,29 +294,17 @@ pub fn validate(path: impl AsRef<Path>, content: &str) -> Option<Error> {
         None => return Some(Error::Extension),
     };
 
-    // Get language for the extension
-    // If we don't support the language, consider it valid
-    let language = extension(ext)?;
-
-    // Initialize parser
-    let mut parser = Parser::new();
-    if let Err(e) = parser.set_language(&language) {
-        return Some(Error::Language(e));
-    }
-
-    // Try parsing the content
-    let Some(tree) = parser.parse(content, None) else {
-        return Some(Error::Parse {
-            file_path: path.display().to_string(),
-            extension: ext.to_string(),
-        });
+    // The first syntax error is reported. Languages that aren't supported are
+    // considered valid.
+    let diagnostic = match parse(ext, content) {
+        Ok(diagnostics) => diagnostics.into_iter().next()?,
+        Err(error) => return Some(error),
     };
-
-    // Find syntax errors in the tree
-    let root_node = tree.root_node();
-    (root_node.has_error() || root_node.is_error()).then(|| Error::Parse {
+    Some(Error::Parse {
         file_path: path.display().to_string(),
         extension: ext.to_string(),
+        line: diagnostic.line,
+        column: diagnostic.column,
     })
 }
 
@@ -174,6 +376,40 @@ mod tests {
         assert!(matches!(result, Some(Error::Extension)));
     }
 
+    #[test]
+    fn test_error_location() {
+        let path = PathBuf::from("test.rs");
+        let content = "fn main() {\n    let x = 1\n    let y = 2;\n}\n";
+        let actual = validate(&path, content).and_then(|error| error.location());
+        assert!(matches!(actual, Some((2 | 3, _))));
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let actual = diagnostics("test.rs", "fn main() {\n    let x = 1\n    let y = 2;\n}\n");
+        assert!(!actual.is_empty());
+        assert!(actual
+            .iter()
+            .all(|diagnostic| matches!(diagnostic.line, 2 | 3)));
+
+        assert_eq!(diagnostics("test.rs", RUST_VALID), vec![]);
+        assert_eq!(diagnostics("test.txt", "fn main() {"), vec![]);
+    }
+
+    #[test]
+    fn test_diagnostics_json() {
+        let actual = diagnostics("package.json", "{\n  \"name\": \"forge\",\n}\n");
+        let expected = vec![Diagnostic::new(3, 1, "trailing comma", Severity::Error)];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_location_counts_characters() {
+        let actual = location("let é = ;", 0, 9);
+        let expected = (1, 9);
+        assert_eq!(actual, expected);
+    }
+
     #[test]
     fn test_error_messages() {
         let path = PathBuf::from("test");
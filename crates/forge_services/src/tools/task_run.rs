@@ -0,0 +1,514 @@
+use std::fmt::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::bail;
+use forge_display::TitleFormat;
+use forge_domain::{
+    CommandOutput, ExecutableTool, NamedTool, ToolCallContext, ToolDescription, ToolName,
+};
+use forge_tool_macros::ToolDescription;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::metadata::Metadata;
+use crate::tools::shell::{format_output_with, PREFIX_CHARS, SUFFIX_CHARS};
+use crate::tools::utils::{assert_absolute_path, simulate_command};
+use crate::{CommandExecutorService, FsReadService, Infrastructure};
+
+const MAKEFILES: &[&str] = &["GNUmakefile", "makefile", "Makefile"];
+const JUSTFILES: &[&str] = &["justfile", "Justfile", ".justfile"];
+const CARGO_CONFIGS: &[&str] = &[".cargo/config.toml", ".cargo/config"];
+
+/// Reminder appended to the output of flaky failures, so that nondeterminism
+/// isn't "fixed" by weakening the tests
+const FLAKY_NOTE: &str = "The failure is flaky: the task passed on some re-runs without any change. Fix the source of nondeterminism (timing, ordering, shared state, randomness) instead of removing or loosening assertions.";
+
+/// Where a task is defined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskSource {
+    Just,
+    Make,
+    Npm,
+    Cargo,
+}
+
+impl fmt::Display for TaskSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskSource::Just => write!(f, "just recipe"),
+            TaskSource::Make => write!(f, "make target"),
+            TaskSource::Npm => write!(f, "package.json script"),
+            TaskSource::Cargo => write!(f, "cargo alias"),
+        }
+    }
+}
+
+/// A task of the project, eg: a package.json script or a Makefile target
+#[derive(Debug, Clone, PartialEq)]
+struct Task {
+    name: String,
+    source: TaskSource,
+    /// What the task runs, or its doc comment when it has one
+    description: Option<String>,
+}
+
+impl Task {
+    fn new(name: impl Into<String>, source: TaskSource, description: Option<String>) -> Self {
+        Self { name: name.into(), source, description }
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct TaskRunInput {
+    /// The absolute path of the project directory whose tasks are listed or
+    /// run.
+    pub cwd: String,
+    /// Name of the task to run, eg: `test` or `lint`. When not provided, the
+    /// tasks of the project are listed instead.
+    pub task: Option<String>,
+    /// Where the task is defined, when several sources define a task with the
+    /// same name: just, make, npm or cargo. Defaults to the first of them that
+    /// defines the task, in that order.
+    pub source: Option<TaskSource>,
+    /// Extra arguments passed to the task, eg: `--verbose`
+    pub args: Option<String>,
+    /// Number of times a failing task is re-run to tell flaky failures from
+    /// deterministic ones, eg: 3 when running tests. Defaults to 0.
+    pub reruns: Option<usize>,
+}
+
+/// Lists and runs the canonical tasks of a project: justfile recipes,
+/// Makefile targets, package.json scripts and cargo aliases. Call it without
+/// a task first to discover the tasks, then prefer running them over guessing
+/// the equivalent shell commands, since they carry the project's flags and
+/// setup. Returns the command that was run with its exit code, stdout and
+/// stderr. Set `reruns` when running tests to classify a failure as flaky or
+/// deterministic before changing any code.
+#[derive(ToolDescription)]
+pub struct TaskRun<F>(Arc<F>);
+
+impl<F: Infrastructure> TaskRun<F> {
+    pub fn new(infra: Arc<F>) -> Self {
+        Self(infra)
+    }
+
+    async fn call(&self, context: ToolCallContext, input: TaskRunInput) -> anyhow::Result<String> {
+        let cwd = PathBuf::from(&input.cwd);
+        assert_absolute_path(&cwd)?;
+        let tasks = self.discover(&cwd).await;
+
+        let Some(name) = input.task else {
+            return Ok(list(&cwd, &tasks));
+        };
+        let Some(task) = tasks.iter().find(|task| {
+            task.name == name && input.source.is_none_or(|source| task.source == source)
+        }) else {
+            bail!(
+                "Task '{name}' not found in {}. Call the tool without a task to list the available tasks.",
+                cwd.display()
+            );
+        };
+
+        let command = self.command(&cwd, task, input.args.as_deref()).await;
+        context
+            .send_text(TitleFormat::debug(format!("Run {}", task.source)).sub_title(&command))
+            .await?;
+        if context.dry_run {
+            return Ok(simulate_command(&command, &cwd));
+        }
+        let executor = self.0.command_executor_service();
+        let output = executor
+            .execute_command(command.clone(), cwd.clone())
+            .await?;
+
+        let mut metadata = Metadata::default()
+            .add("task", &task.name)
+            .add("source", task.source);
+        let reruns = input.reruns.unwrap_or_default();
+        let mut flaky = false;
+        if !passed(&output) && reruns > 0 {
+            let mut passes = 0;
+            for _ in 0..reruns {
+                let rerun = executor
+                    .execute_command(command.clone(), cwd.clone())
+                    .await?;
+                if passed(&rerun) {
+                    passes += 1;
+                }
+            }
+            flaky = passes > 0;
+            metadata = metadata
+                .add("reruns", reruns)
+                .add("reruns_passed", passes)
+                .add("failure", if flaky { "flaky" } else { "deterministic" });
+        }
+
+        // The output of the first run is reported, since it shows the failure
+        let result =
+            format_output_with(&self.0, metadata, output, false, PREFIX_CHARS, SUFFIX_CHARS).await;
+        match result {
+            Ok(output) if flaky => Ok(format!("{output}\n{FLAKY_NOTE}")),
+            Err(err) if flaky => bail!("{err}\n{FLAKY_NOTE}"),
+            result => result,
+        }
+    }
+
+    async fn read(&self, path: PathBuf) -> Option<String> {
+        self.0.file_read_service().read_utf8(&path).await.ok()
+    }
+
+    async fn read_first(&self, cwd: &Path, files: &[&str]) -> Option<String> {
+        for file in files {
+            if let Some(content) = self.read(cwd.join(file)).await {
+                return Some(content);
+            }
+        }
+        None
+    }
+
+    async fn discover(&self, cwd: &Path) -> Vec<Task> {
+        let mut tasks = Vec::new();
+        if let Some(content) = self.read_first(cwd, JUSTFILES).await {
+            tasks.extend(just_recipes(&content));
+        }
+        if let Some(content) = self.read_first(cwd, MAKEFILES).await {
+            tasks.extend(make_targets(&content));
+        }
+        if let Some(content) = self.read(cwd.join("package.json")).await {
+            tasks.extend(npm_scripts(&content));
+        }
+        if let Some(content) = self.read_first(cwd, CARGO_CONFIGS).await {
+            tasks.extend(cargo_aliases(&content));
+        }
+        tasks
+    }
+
+    /// Command that runs a task, with the package manager of the project for
+    /// package.json scripts
+    async fn command(&self, cwd: &Path, task: &Task, args: Option<&str>) -> String {
+        let command = match task.source {
+            TaskSource::Just => format!("just {}", task.name),
+            TaskSource::Make => format!("make {}", task.name),
+            TaskSource::Cargo => format!("cargo {}", task.name),
+            TaskSource::Npm => {
+                let manager = if self.read(cwd.join("pnpm-lock.yaml")).await.is_some() {
+                    "pnpm"
+                } else if self.read(cwd.join("yarn.lock")).await.is_some() {
+                    "yarn"
+                } else {
+                    "npm"
+                };
+                match args {
+                    // npm needs a separator to pass arguments to the script
+                    Some(args) if manager == "npm" => {
+                        return format!("npm run {} -- {args}", task.name)
+                    }
+                    _ => format!("{manager} run {}", task.name),
+                }
+            }
+        };
+        match args {
+            Some(args) => format!("{command} {args}"),
+            None => command,
+        }
+    }
+}
+
+/// Whether a task passed. Unlike [`CommandOutput::success`], which only fails
+/// commands that didn't exit, a non-zero exit code fails the task.
+fn passed(output: &CommandOutput) -> bool {
+    output.success() && output.exit_code == Some(0)
+}
+
+fn list(cwd: &Path, tasks: &[Task]) -> String {
+    if tasks.is_empty() {
+        return format!(
+            "No justfile, Makefile, package.json scripts or cargo aliases found in {}",
+            cwd.display()
+        );
+    }
+
+    let mut output = Metadata::default()
+        .add("cwd", cwd.display())
+        .add("total_tasks", tasks.len())
+        .to_string();
+    for task in tasks {
+        let _ = write!(
+            output,
+            "<task name=\"{}\" source=\"{}\">",
+            task.name, task.source
+        );
+        if let Some(description) = &task.description {
+            output.push_str(description);
+        }
+        output.push_str("</task>\n");
+    }
+    output
+}
+
+/// Recipes of a justfile, with the comment above each as its description
+fn just_recipes(content: &str) -> Vec<Task> {
+    let recipe = Regex::new(r"^@?([A-Za-z_][A-Za-z0-9_-]*)[^:=]*:([^=]|$)").unwrap();
+    let mut comment = None;
+    let mut tasks = Vec::new();
+    for line in content.lines() {
+        if let Some(text) = line.strip_prefix('#') {
+            comment = Some(text.trim().to_string());
+            continue;
+        }
+        if let Some(captures) = recipe.captures(line) {
+            tasks.push(Task::new(&captures[1], TaskSource::Just, comment.take()));
+        }
+        comment = None;
+    }
+    tasks
+}
+
+/// Targets of a Makefile, with their `## description` when they have one.
+/// Special targets such as `.PHONY` and pattern rules are skipped.
+fn make_targets(content: &str) -> Vec<Task> {
+    let target = Regex::new(r"^([A-Za-z0-9_][A-Za-z0-9_./-]*)\s*:([^=]|$)").unwrap();
+    let mut tasks: Vec<Task> = Vec::new();
+    for line in content.lines() {
+        let Some(captures) = target.captures(line) else {
+            continue;
+        };
+        let name = &captures[1];
+        if tasks.iter().any(|task| task.name == name) {
+            continue;
+        }
+        let description = line
+            .split_once("##")
+            .map(|(_, description)| description.trim().to_string())
+            .filter(|description| !description.is_empty());
+        tasks.push(Task::new(name, TaskSource::Make, description));
+    }
+    tasks
+}
+
+fn npm_scripts(content: &str) -> Vec<Task> {
+    let Ok(manifest) = serde_json::from_str::<Value>(content) else {
+        return Vec::new();
+    };
+    manifest["scripts"]
+        .as_object()
+        .map(|scripts| {
+            scripts
+                .iter()
+                .map(|(name, script)| {
+                    Task::new(name, TaskSource::Npm, script.as_str().map(str::to_string))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Aliases of the `[alias]` section of a cargo config
+fn cargo_aliases(content: &str) -> Vec<Task> {
+    let alias = Regex::new(r#"^\s*([A-Za-z0-9_-]+)\s*=\s*(.+?)\s*$"#).unwrap();
+    content
+        .lines()
+        .skip_while(|line| line.trim() != "[alias]")
+        .skip(1)
+        .take_while(|line| !line.trim_start().starts_with('['))
+        .filter_map(|line| alias.captures(line))
+        .map(|captures| {
+            let command = captures[2].trim_matches('"').to_string();
+            Task::new(&captures[1], TaskSource::Cargo, Some(command))
+        })
+        .collect()
+}
+
+impl<F> NamedTool for TaskRun<F> {
+    fn tool_name() -> ToolName {
+        ToolName::new("forge_tool_task_run")
+    }
+}
+
+#[async_trait::async_trait]
+impl<F: Infrastructure> ExecutableTool for TaskRun<F> {
+    type Input = TaskRunInput;
+
+    async fn call(&self, context: ToolCallContext, input: Self::Input) -> anyhow::Result<String> {
+        self.call(context, input).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::TestInfrastructure;
+
+    fn names(tasks: &[Task]) -> Vec<(&str, Option<&str>)> {
+        tasks
+            .iter()
+            .map(|task| (task.name.as_str(), task.description.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn test_just_recipes() {
+        let fixture = "set shell := [\"bash\", \"-c\"]\nalias t := test\n\n# Run the tests\ntest *args:\n    cargo test {{args}}\n\n@lint: fmt\n    cargo clippy\n";
+
+        let actual = just_recipes(fixture);
+
+        assert_eq!(
+            names(&actual),
+            vec![("test", Some("Run the tests")), ("lint", None)]
+        );
+    }
+
+    #[test]
+    fn test_make_targets() {
+        let fixture = ".PHONY: build test\nVERSION := 1.0\nbuild: ## Build the binary\n\tgo build ./...\ntest: build\n\tgo test ./...\n%.o: %.c\n\tcc -c $<\n";
+
+        let actual = make_targets(fixture);
+
+        assert_eq!(
+            names(&actual),
+            vec![("build", Some("Build the binary")), ("test", None)]
+        );
+    }
+
+    #[test]
+    fn test_cargo_aliases() {
+        let fixture = "[build]\njobs = 4\n\n[alias]\nxtask = \"run --package xtask --\"\nci = [\"test\", \"--all\"]\n\n[net]\nretry = 2\n";
+
+        let actual = cargo_aliases(fixture);
+
+        assert_eq!(
+            names(&actual),
+            vec![
+                ("xtask", Some("run --package xtask --")),
+                ("ci", Some("[\"test\", \"--all\"]"))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_task_run() {
+        let infra = Arc::new(
+            TestInfrastructure::new()
+                .file(
+                    "/test/package.json",
+                    r#"{"scripts": {"test": "vitest run", "lint": "eslint ."}}"#,
+                )
+                .file("/test/pnpm-lock.yaml", "")
+                .command(
+                    "pnpm run test --watch=false",
+                    CommandOutput {
+                        command: String::new(),
+                        stdout: "2 passed".to_string(),
+                        stderr: String::new(),
+                        exit_code: Some(0),
+                        usage: None,
+                    },
+                ),
+        );
+
+        let actual = TaskRun::new(infra.clone())
+            .call(
+                ToolCallContext::default(),
+                TaskRunInput {
+                    cwd: "/test".to_string(),
+                    task: Some("test".to_string()),
+                    source: None,
+                    args: Some("--watch=false".to_string()),
+                    reruns: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        assert!(actual.starts_with(
+            "---\ntask: test\nsource: package.json script\ncommand: pnpm run test --watch=false\n"
+        ));
+        assert!(actual.contains("2 passed"));
+    }
+
+    #[tokio::test]
+    async fn test_task_list() {
+        let infra = Arc::new(
+            TestInfrastructure::new()
+                .file("/test/Makefile", "build:\n\tgo build\n")
+                .file("/test/package.json", r#"{"scripts": {"lint": "eslint ."}}"#),
+        );
+
+        let actual = TaskRun::new(infra)
+            .call(
+                ToolCallContext::default(),
+                TaskRunInput {
+                    cwd: "/test".to_string(),
+                    task: None,
+                    source: None,
+                    args: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let expected = "---\ncwd: /test\ntotal_tasks: 2\n---\n<task name=\"build\" source=\"make target\"></task>\n<task name=\"lint\" source=\"package.json script\">eslint .</task>\n";
+        assert_eq!(actual, expected);
+    }
+
+    fn output(exit_code: i32) -> CommandOutput {
+        CommandOutput {
+            command: String::new(),
+            stdout: String::new(),
+            stderr: "test_retry failed".to_string(),
+            exit_code: Some(exit_code),
+            usage: None,
+        }
+    }
+
+    fn rerun_fixture(outputs: &[i32]) -> Arc<TestInfrastructure> {
+        let infra = outputs.iter().fold(
+            TestInfrastructure::new().file("/test/Makefile", "test:\n\tgo test ./...\n"),
+            |infra, exit_code| infra.command("make test", output(*exit_code)),
+        );
+        Arc::new(infra)
+    }
+
+    fn rerun_input() -> TaskRunInput {
+        TaskRunInput {
+            cwd: "/test".to_string(),
+            task: Some("test".to_string()),
+            source: None,
+            args: None,
+            reruns: Some(3),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_task_run_flaky_failure() {
+        let infra = rerun_fixture(&[1, 0, 1, 0]);
+
+        let actual = TaskRun::new(infra.clone())
+            .call(ToolCallContext::default(), rerun_input())
+            .await
+            .unwrap();
+
+        assert!(actual.contains("reruns: 3\nreruns_passed: 2\nfailure: flaky\n"));
+        assert!(actual.ends_with(FLAKY_NOTE));
+        assert_eq!(infra.executed_commands().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_task_run_deterministic_failure() {
+        let infra = rerun_fixture(&[1]);
+
+        let actual = TaskRun::new(infra)
+            .call(ToolCallContext::default(), rerun_input())
+            .await
+            .unwrap();
+
+        assert!(actual.contains("reruns: 3\nreruns_passed: 0\nfailure: deterministic\n"));
+        assert!(!actual.contains(FLAKY_NOTE));
+    }
+}
@@ -0,0 +1,334 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use forge_display::TitleFormat;
+use forge_domain::{
+    EnvironmentService, ExecutableTool, NamedTool, ToolCallContext, ToolDescription, ToolName,
+};
+use forge_tool_macros::ToolDescription;
+use forge_walker::Walker;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::metadata::Metadata;
+use crate::tools::utils::{assert_absolute_path, format_display_path};
+use crate::Infrastructure;
+
+/// Tags collected when none are given
+const DEFAULT_TAGS: &[&str] = &["TODO", "FIXME", "HACK"];
+
+/// Lines of context shown before and after a comment when not specified
+const DEFAULT_CONTEXT_LINES: usize = 2;
+
+/// Maximum number of comments returned, so that a large workspace doesn't
+/// flood the context
+const MAX_RESULTS: usize = 200;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct TodoScanInput {
+    /// The absolute path of the directory or file to scan. Directories are
+    /// scanned recursively, skipping ignored files.
+    pub path: String,
+
+    /// Tags to collect, eg: ["TODO", "FIXME"]. Defaults to TODO, FIXME and
+    /// HACK. Tags are matched case-sensitively, and only in comments.
+    pub tags: Option<Vec<String>>,
+
+    /// Glob pattern to filter files (e.g., '*.rs'). If not provided, all files
+    /// are scanned.
+    pub file_pattern: Option<String>,
+
+    /// Number of lines of context shown before and after each comment.
+    /// Defaults to 2.
+    pub context_lines: Option<usize>,
+}
+
+/// A tagged comment found in a file
+#[derive(Debug, Clone, PartialEq)]
+struct TodoComment {
+    tag: String,
+    /// Author or issue in parentheses after the tag, eg: `TODO(alice)`
+    owner: Option<String>,
+    message: String,
+    /// Line of the comment, starting at 1
+    line: usize,
+    context: String,
+}
+
+/// Collects TODO, FIXME and HACK comments across a directory, with their
+/// location, owner and surrounding code. Use it instead of a content search
+/// when cleaning up or triaging the TODOs of a module, since it only matches
+/// tags in comments and groups the results by tag. Requires absolute paths
+/// and skips binary and ignored files. Returns at most 200 comments.
+#[derive(ToolDescription)]
+pub struct TodoScan<F>(Arc<F>);
+
+impl<F: Infrastructure> TodoScan<F> {
+    pub fn new(infra: Arc<F>) -> Self {
+        Self(infra)
+    }
+
+    async fn call(&self, context: ToolCallContext, input: TodoScanInput) -> anyhow::Result<String> {
+        let path = Path::new(&input.path);
+        assert_absolute_path(path)?;
+
+        let cwd = self.0.environment_service().get_environment().cwd;
+        let display_path = format_display_path(path, &cwd)?;
+        context
+            .send_text(TitleFormat::debug(format!("Scan TODOs at {display_path}")))
+            .await?;
+
+        let tags = input
+            .tags
+            .clone()
+            .filter(|tags| !tags.is_empty())
+            .unwrap_or_else(|| DEFAULT_TAGS.iter().map(|tag| tag.to_string()).collect());
+        let pattern = comment_pattern(&tags)?;
+        let file_pattern = input
+            .file_pattern
+            .as_deref()
+            .map(|pattern| {
+                glob::Pattern::new(pattern)
+                    .with_context(|| format!("Invalid glob pattern: {pattern}"))
+            })
+            .transpose()?;
+        let context_lines = input.context_lines.unwrap_or(DEFAULT_CONTEXT_LINES);
+
+        let mut files = retrieve_files(path).await?;
+        files.sort();
+
+        let mut results = Vec::new();
+        let mut total = 0;
+        for file in files {
+            let matches_pattern = file_pattern.as_ref().is_none_or(|pattern| {
+                file.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| pattern.matches(name))
+            });
+            if !matches_pattern || file.is_dir() {
+                continue;
+            }
+
+            // Binary and unreadable files are skipped
+            let Ok(content) = tokio::fs::read_to_string(&file).await else {
+                continue;
+            };
+            for comment in scan(&pattern, &content, context_lines) {
+                total += 1;
+                if results.len() < MAX_RESULTS {
+                    results.push((format_display_path(&file, &cwd)?, comment));
+                }
+            }
+        }
+
+        if results.is_empty() {
+            return Ok(format!("No {} comments found.", tags.join("/")));
+        }
+
+        let mut counts = BTreeMap::<&str, usize>::new();
+        for (_, comment) in &results {
+            *counts.entry(comment.tag.as_str()).or_default() += 1;
+        }
+        let counts = counts
+            .iter()
+            .map(|(tag, count)| format!("{tag}={count}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let metadata = Metadata::default()
+            .add("path", &display_path)
+            .add("total", total)
+            .add("counts", counts)
+            .add_optional(
+                "truncated",
+                (total > results.len()).then(|| format!("showing the first {MAX_RESULTS}")),
+            );
+
+        let mut output = metadata.to_string();
+        for (file, comment) in results {
+            write!(
+                output,
+                "<todo tag=\"{}\" location=\"{}:{}\"",
+                comment.tag, file, comment.line
+            )?;
+            if let Some(owner) = &comment.owner {
+                write!(output, " owner=\"{owner}\"")?;
+            }
+            writeln!(
+                output,
+                ">\n{}\n<context>\n{}</context>\n</todo>",
+                comment.message, comment.context
+            )?;
+        }
+        Ok(output)
+    }
+}
+
+/// Pattern of a tagged comment: a comment marker, the tag, an optional owner
+/// in parentheses and the message
+fn comment_pattern(tags: &[String]) -> anyhow::Result<Regex> {
+    let tags = tags
+        .iter()
+        .map(|tag| regex::escape(tag))
+        .collect::<Vec<_>>()
+        .join("|");
+    Regex::new(&format!(
+        r"(?://+|#+|/\*+|^\s*\*|--|<!--|;+)\s*(?:.*?\s)?\b({tags})\b(?:\(([^)]*)\))?:?\s*(.*?)\s*(?:\*/|-->)?\s*$"
+    ))
+    .context("Invalid TODO tags")
+}
+
+fn scan(pattern: &Regex, content: &str, context_lines: usize) -> Vec<TodoComment> {
+    let lines = content.lines().collect::<Vec<_>>();
+    let width = lines.len().to_string().len();
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let captures = pattern.captures(line)?;
+            let start = index.saturating_sub(context_lines);
+            let end = (index + context_lines + 1).min(lines.len());
+            let context = (start..end)
+                .map(|number| {
+                    let marker = if number == index { ">" } else { " " };
+                    format!("{marker}{:>width$} | {}\n", number + 1, lines[number])
+                })
+                .collect::<String>();
+
+            Some(TodoComment {
+                tag: captures[1].to_string(),
+                owner: captures
+                    .get(2)
+                    .map(|owner| owner.as_str().trim().to_string())
+                    .filter(|owner| !owner.is_empty()),
+                message: captures[3].to_string(),
+                line: index + 1,
+                context,
+            })
+        })
+        .collect()
+}
+
+async fn retrieve_files(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if path.is_dir() {
+        Ok(Walker::max_all()
+            .cwd(path.to_path_buf())
+            .get()
+            .await
+            .with_context(|| format!("Failed to walk directory '{}'", path.display()))?
+            .into_iter()
+            .map(|file| path.join(file.path))
+            .collect())
+    } else {
+        Ok(vec![path.to_path_buf()])
+    }
+}
+
+impl<F> NamedTool for TodoScan<F> {
+    fn tool_name() -> ToolName {
+        ToolName::new("forge_tool_todo_scan")
+    }
+}
+
+#[async_trait::async_trait]
+impl<F: Infrastructure> ExecutableTool for TodoScan<F> {
+    type Input = TodoScanInput;
+
+    async fn call(&self, context: ToolCallContext, input: Self::Input) -> anyhow::Result<String> {
+        self.call(context, input).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tokio::fs;
+
+    use super::*;
+    use crate::attachment::tests::MockInfrastructure;
+    use crate::tools::utils::TempDir;
+
+    fn tags() -> Vec<String> {
+        DEFAULT_TAGS.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn test_scan_comments() {
+        let fixture = [
+            "fn main() {",
+            "    // TODO(alice): handle the error",
+            "    let todo = \"TODO: not a comment\";",
+            "    # FIXME remove this hack",
+            "    /* HACK: works around a bug */",
+            "}",
+        ]
+        .join("\n");
+
+        let actual = scan(&comment_pattern(&tags()).unwrap(), &fixture, 0)
+            .into_iter()
+            .map(|comment| (comment.tag, comment.owner, comment.message, comment.line))
+            .collect::<Vec<_>>();
+
+        let expected = vec![
+            (
+                "TODO".to_string(),
+                Some("alice".to_string()),
+                "handle the error".to_string(),
+                2,
+            ),
+            ("FIXME".to_string(), None, "remove this hack".to_string(), 4),
+            (
+                "HACK".to_string(),
+                None,
+                "works around a bug".to_string(),
+                5,
+            ),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_scan_context() {
+        let fixture = "let a = 1;\n// TODO: rename\nlet b = 2;\nlet c = 3;";
+
+        let actual = scan(&comment_pattern(&tags()).unwrap(), fixture, 1);
+
+        let expected = " 1 | let a = 1;\n>2 | // TODO: rename\n 3 | let b = 2;\n";
+        assert_eq!(actual[0].context, expected);
+    }
+
+    #[tokio::test]
+    async fn test_todo_scan() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("lib.rs"),
+            "// TODO: document\nfn a() {}\n",
+        )
+        .await
+        .unwrap();
+        fs::write(temp_dir.path().join("notes.md"), "TODO: not a comment\n")
+            .await
+            .unwrap();
+
+        let actual = TodoScan::new(Arc::new(MockInfrastructure::new()))
+            .call(
+                ToolCallContext::default(),
+                TodoScanInput {
+                    path: temp_dir.path().to_string_lossy().to_string(),
+                    tags: None,
+                    file_pattern: None,
+                    context_lines: Some(0),
+                },
+            )
+            .await
+            .unwrap();
+
+        assert!(actual.contains("total: 1\ncounts: TODO=1\n"));
+        assert!(
+            actual.contains("lib.rs:1\">\ndocument\n<context>\n>1 | // TODO: document\n</context>")
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use std::hash::Hasher;
+use std::path::Path;
+
+use anyhow::bail;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Hash of the content of a file, as reported by the tools that read or
+/// change it, eg: `hash: 9f1c0b2e4d6a8c31`
+pub fn content_hash(bytes: &[u8]) -> String {
+    let mut hasher = fnv_rs::Fnv64::default();
+    hasher.write(bytes);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Hash of the content of a file that is read in chunks, eg: to patch large
+/// files without loading them in memory
+pub async fn stream_hash<R: AsyncRead + Unpin>(mut reader: R) -> anyhow::Result<String> {
+    let mut hasher = fnv_rs::Fnv64::default();
+    let mut chunk = vec![0; 64 * 1024];
+    loop {
+        match reader.read(&mut chunk).await? {
+            0 => return Ok(format!("{:016x}", hasher.finish())),
+            read => hasher.write(&chunk[..read]),
+        }
+    }
+}
+
+/// Fails when the content of a file no longer has the hash that the agent
+/// last saw, ie: when the file was changed by someone else since it was read,
+/// so that their changes aren't overwritten.
+pub fn assert_content_hash(
+    path: &Path,
+    bytes: Option<&[u8]>,
+    expected: Option<&str>,
+) -> anyhow::Result<()> {
+    if expected.is_none() {
+        return Ok(());
+    }
+    assert_hash(path, bytes.map(content_hash).as_deref(), expected)
+}
+
+/// Like [`assert_content_hash`], given the hash of the content, or None when
+/// the file doesn't exist
+pub fn assert_hash(path: &Path, hash: Option<&str>, expected: Option<&str>) -> anyhow::Result<()> {
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+    match hash {
+        None => bail!(
+            "{} was removed since it was read, read it again before changing it",
+            path.display()
+        ),
+        Some(hash) if hash != expected => bail!(
+            "{} was changed since it was read (hash {hash}, expected {expected}), read it again before changing it",
+            path.display()
+        ),
+        Some(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_assert_content_hash() {
+        let path = Path::new("/test/file.txt");
+        let hash = content_hash(b"foo");
+
+        let actual = [
+            assert_content_hash(path, Some(b"foo"), None).is_ok(),
+            assert_content_hash(path, Some(b"foo"), Some(&hash)).is_ok(),
+            assert_content_hash(path, Some(b"bar"), Some(&hash)).is_ok(),
+            assert_content_hash(path, None, Some(&hash)).is_ok(),
+            assert_content_hash(path, None, None).is_ok(),
+        ];
+
+        let expected = [true, true, false, false, true];
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_stream_hash() {
+        let content = "fn main() {}\n".repeat(10_000);
+
+        let actual = stream_hash(std::io::Cursor::new(content.as_bytes()))
+            .await
+            .unwrap();
+
+        let expected = content_hash(content.as_bytes());
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,16 @@
+use std::path::Path;
+
+use tracing::info;
+
+use crate::metadata::Metadata;
+
+/// Result of a command that isn't run, since the session is a dry run. The
+/// command is logged so that the intended commands can be reviewed afterwards.
+pub fn simulate_command(command: &str, cwd: &Path) -> String {
+    info!(command, cwd = %cwd.display(), "Dry run, command not executed");
+    let metadata = Metadata::default()
+        .add("command", command)
+        .add("cwd", cwd.display())
+        .add("dry_run", true);
+    format!("{metadata}The command was not run, since the session is a dry run.")
+}
@@ -1,7 +1,15 @@
+mod content_hash;
+mod dry_run;
+mod moderation;
+mod owners;
 mod path;
 #[cfg(test)]
 mod temp_dir;
 
+pub use content_hash::*;
+pub use dry_run::*;
+pub use moderation::*;
+pub use owners::*;
 pub use path::*;
 #[cfg(test)]
 pub use temp_dir::*;
@@ -0,0 +1,32 @@
+use anyhow::Context;
+use forge_domain::{Moderation, ToolCallContext};
+use reqwest::Client;
+use serde_json::{json, Value};
+
+/// Refuses content that the moderation of the agent blocks, before it is
+/// written to a file or executed
+pub async fn moderate(context: &ToolCallContext, content: &str) -> anyhow::Result<()> {
+    let Some(moderation) = &context.moderation else {
+        return Ok(());
+    };
+    moderation.check(content)?;
+
+    if let Some(endpoint) = &moderation.endpoint {
+        let mut request = Client::new()
+            .post(endpoint)
+            .json(&json!({ "input": content }));
+        if let Ok(key) = std::env::var("FORGE_MODERATION_API_KEY") {
+            request = request.bearer_auth(key);
+        }
+        let response: Value = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Failed to reach the moderation endpoint {endpoint}"))?
+            .json()
+            .await
+            .context("Failed to parse the response of the moderation endpoint")?;
+        Moderation::check_response(&response)?;
+    }
+    Ok(())
+}
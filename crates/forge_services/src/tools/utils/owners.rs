@@ -0,0 +1,94 @@
+use std::path::Path;
+
+use anyhow::bail;
+use forge_domain::{CodeOwners, EnvironmentService};
+
+use crate::{Infrastructure, InquireService};
+
+const YES: &str = "Yes";
+const NO: &str = "No";
+
+/// Returns the owners of a file from the CODEOWNERS file of the workspace.
+/// When the user's teams are configured and none of them owns the file, asks
+/// the user to confirm the change first and fails when they decline.
+pub async fn confirm_owners<F: Infrastructure>(
+    infra: &F,
+    path: &Path,
+) -> anyhow::Result<Vec<String>> {
+    let env = infra.environment_service().get_environment();
+    let Some(code_owners) = CodeOwners::find(&env.cwd) else {
+        return Ok(Vec::new());
+    };
+
+    let owners = code_owners.owners(path).to_vec();
+    if needs_confirmation(&owners, &env.code_owner_teams) {
+        let message = format!(
+            "{} is owned by {}. Change it anyway?",
+            path.display(),
+            owners.join(", ")
+        );
+        let answer = infra
+            .inquire_service()
+            .select_one(&message, vec![YES.to_string(), NO.to_string()])
+            .await?;
+        if answer.as_deref() != Some(YES) {
+            bail!(
+                "The user declined the change to {}, which is owned by {}",
+                path.display(),
+                owners.join(", ")
+            );
+        }
+    }
+    Ok(owners)
+}
+
+/// Whether a file is owned only by teams that the user doesn't belong to
+fn needs_confirmation(owners: &[String], teams: &[String]) -> bool {
+    !teams.is_empty() && !owners.is_empty() && !owners.iter().any(|owner| teams.contains(owner))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::tools::utils::TempDir;
+    use crate::TestInfrastructure;
+
+    fn teams(teams: &[&str]) -> Vec<String> {
+        teams.iter().map(|team| team.to_string()).collect()
+    }
+
+    #[test]
+    fn test_needs_confirmation() {
+        let owners = teams(&["@org/payments", "@alice"]);
+
+        let actual = [
+            needs_confirmation(&owners, &[]),
+            needs_confirmation(&[], &teams(&["@org/core"])),
+            needs_confirmation(&owners, &teams(&["@org/core", "@alice"])),
+            needs_confirmation(&owners, &teams(&["@org/core"])),
+        ];
+
+        let expected = [false, false, false, true];
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_confirm_owners_declined() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("CODEOWNERS"), "*.rs @org/payments\n").unwrap();
+        let mut env = TestInfrastructure::new()
+            .environment_service()
+            .get_environment();
+        env.cwd = temp_dir.path();
+        env.code_owner_teams = teams(&["@org/core"]);
+        let fixture = TestInfrastructure::new().environment(env).answer(Some(NO));
+
+        let actual = confirm_owners(&fixture, &temp_dir.path().join("lib.rs")).await;
+
+        assert!(actual.is_err());
+        let actual = confirm_owners(&fixture, &temp_dir.path().join("README.md")).await;
+        assert_eq!(actual.unwrap(), Vec::<String>::new());
+    }
+}
@@ -1,6 +1,7 @@
 use std::path::Path;
 
 use anyhow::bail;
+use forge_walker::{ForgeIgnore, FORGE_IGNORE};
 
 /// Ensures that the given path is absolute
 ///
@@ -18,6 +19,19 @@ pub fn assert_absolute_path(path: &Path) -> anyhow::Result<()> {
     }
 }
 
+/// Ensures that the given path is not excluded from the agent by the
+/// `.forgeignore` files of its parent directories
+pub fn assert_not_ignored(path: &Path) -> anyhow::Result<()> {
+    let dir = path.parent().unwrap_or(path);
+    if ForgeIgnore::find(dir).is_ignored(path) {
+        bail!(
+            "Access to {} is denied by {FORGE_IGNORE}, do not try to read or change it",
+            path.display()
+        )
+    }
+    Ok(())
+}
+
 /// Formats a path for display, converting absolute paths to relative when
 /// possible
 ///
@@ -103,6 +117,15 @@ mod tests {
         assert!(assert_absolute_path(path).is_err());
     }
 
+    #[test]
+    fn test_not_ignored() {
+        let fixture = tempfile::tempdir().unwrap();
+        std::fs::write(fixture.path().join(FORGE_IGNORE), "contracts/\n").unwrap();
+
+        assert!(assert_not_ignored(&fixture.path().join("contracts/acme.md")).is_err());
+        assert!(assert_not_ignored(&fixture.path().join("src/main.rs")).is_ok());
+    }
+
     #[test]
     fn test_cwd() {
         let cwd = Path::new("/home/user/projects");
@@ -1,10 +1,16 @@
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use forge_fs::ForgeFS;
 
 use crate::snapshot::Snapshot;
 
+/// Name of the file that records the original path of the snapshots stored in
+/// a directory
+const SOURCE_FILE: &str = "source";
+
 /// Implementation of the SnapshotService
 #[derive(Debug)]
 pub struct SnapshotService {
@@ -21,8 +27,17 @@ impl SnapshotService {
 
 impl SnapshotService {
     pub async fn create_snapshot(&self, path: PathBuf) -> Result<Snapshot> {
-        let snapshot = Snapshot::create(path).await?;
+        self.save(Snapshot::create(path).await?).await
+    }
+
+    /// Records that the file at `path`, which doesn't exist yet, is about to
+    /// be created, so that undoing or restoring it deletes the file
+    pub async fn record_creation(&self, path: PathBuf) -> Result<Snapshot> {
+        let snapshot = Snapshot { created: true, ..Snapshot::create(path).await? };
+        self.save(snapshot).await
+    }
 
+    async fn save(&self, snapshot: Snapshot) -> Result<Snapshot> {
         // Create intermediary directories if they don't exist
         let snapshot_path = snapshot.snapshot_path(Some(self.snapshots_directory.clone()));
         if let Some(parent) = PathBuf::from(&snapshot_path).parent() {
@@ -33,29 +48,123 @@ impl SnapshotService {
             .save(Some(self.snapshots_directory.clone()))
             .await?;
 
+        // Record the original path so that snapshots can be listed later
+        self.save_source(&snapshot).await?;
+
         Ok(snapshot)
     }
 
+    /// Lists all the snapshots, most recent first
+    pub async fn list(&self) -> Result<Vec<Snapshot>> {
+        let mut snapshots = Vec::new();
+        if !ForgeFS::exists(&self.snapshots_directory) {
+            return Ok(snapshots);
+        }
+
+        let mut dirs = ForgeFS::read_dir(&self.snapshots_directory).await?;
+        while let Some(dir) = dirs.next_entry().await? {
+            // The path of the snapshots taken before it was recorded is only
+            // known once the file is snapshotted or undone again
+            let source = dir.path().join(SOURCE_FILE);
+            if !ForgeFS::is_file(&source) {
+                continue;
+            }
+            Self::migrate(&dir.path()).await?;
+            let path = String::from_utf8(ForgeFS::read_sealed(&source).await?)?;
+
+            let mut files = ForgeFS::read_dir(dir.path()).await?;
+            while let Some(file) = files.next_entry().await? {
+                let filename = file.file_name().to_string_lossy().to_string();
+                snapshots.extend(Snapshot::from_filename(&filename, path.clone()));
+            }
+        }
+
+        snapshots.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        Ok(snapshots)
+    }
+
+    /// Finds a snapshot by its ID or by a unique prefix of it
+    pub async fn find(&self, id: &str) -> Result<Snapshot> {
+        let mut matches = self
+            .list()
+            .await?
+            .into_iter()
+            .filter(|snapshot| snapshot.id.to_string().starts_with(id));
+
+        match (matches.next(), matches.next()) {
+            (Some(snapshot), None) => Ok(snapshot),
+            (Some(_), Some(_)) => bail!("Snapshot ID '{id}' is ambiguous"),
+            (None, _) => bail!("No snapshot found with ID '{id}'"),
+        }
+    }
+
+    /// Reads the content stored in a snapshot
+    pub async fn read(&self, snapshot: &Snapshot) -> Result<Vec<u8>> {
+        ForgeFS::read_sealed(snapshot.snapshot_path(Some(self.snapshots_directory.clone()))).await
+    }
+
+    /// Renames the snapshots of a directory that were named after their
+    /// timestamp only, before snapshots had an ID
+    async fn migrate(dir: &Path) -> Result<()> {
+        let mut files = ForgeFS::read_dir(dir).await?;
+        while let Some(file) = files.next_entry().await? {
+            let filename = file.file_name().to_string_lossy().to_string();
+            if let Some(migrated) = Snapshot::migrate_filename(&filename) {
+                tokio::fs::rename(file.path(), dir.join(migrated))
+                    .await
+                    .with_context(|| {
+                        format!("Failed to migrate snapshot {}", file.path().display())
+                    })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the file that records the original path of the snapshots of a
+    /// directory
+    async fn save_source(&self, snapshot: &Snapshot) -> Result<()> {
+        let source = self
+            .snapshots_directory
+            .join(snapshot.path_hash())
+            .join(SOURCE_FILE);
+        ForgeFS::write_sealed(source, snapshot.path.as_bytes()).await
+    }
+
     /// Find the most recent snapshot for a given path based on filename
     /// timestamp
-    async fn find_recent_snapshot(snapshot_dir: &PathBuf) -> Result<Option<PathBuf>> {
+    async fn find_recent_snapshot(snapshot_dir: &PathBuf) -> Result<Option<(PathBuf, bool)>> {
         let mut latest_path = None;
         let mut latest_filename = None;
         let mut dir = ForgeFS::read_dir(&snapshot_dir).await?;
 
         while let Some(entry) = dir.next_entry().await? {
             let filename = entry.file_name().to_string_lossy().to_string();
-            if filename.ends_with(".snap")
-                && (latest_filename.is_none() || filename > latest_filename.clone().unwrap())
-            {
+            let Some(snapshot) = Snapshot::from_filename(&filename, String::new()) else {
+                continue;
+            };
+            if latest_filename.is_none() || filename > latest_filename.clone().unwrap() {
                 latest_filename = Some(filename);
-                latest_path = Some(entry.path());
+                latest_path = Some((entry.path(), snapshot.created));
             }
         }
 
         Ok(latest_path)
     }
 
+    /// Reverts the file at `path` to a snapshot, deleting it if the snapshot
+    /// records its creation
+    async fn revert(path: &str, snapshot_path: &Path, created: bool) -> Result<()> {
+        if !created {
+            let content = ForgeFS::read_sealed(snapshot_path).await?;
+            ForgeFS::write_atomic(path, content).await?;
+        } else if ForgeFS::exists(path) {
+            ForgeFS::remove_file(path).await?;
+        }
+        Ok(())
+    }
+
+    /// Reverts the file at `path` to its most recent snapshot. The caller
+    /// holds the lock of the file, eg: across the undo of several operations.
     pub async fn undo_snapshot(&self, path: PathBuf) -> Result<()> {
         let snapshot = Snapshot::create(path.clone()).await?;
 
@@ -67,20 +176,58 @@ impl SnapshotService {
             return Err(anyhow::anyhow!("No snapshots found for {:?}", path));
         }
 
+        // Snapshots taken before they had an ID or their path was recorded are
+        // migrated, since the path is known here
+        Self::migrate(&snapshot_dir).await?;
+        self.save_source(&snapshot).await?;
+
         // Retrieve the latest snapshot path
-        let snapshot_path = Self::find_recent_snapshot(&snapshot_dir)
+        let (snapshot_path, created) = Self::find_recent_snapshot(&snapshot_dir)
             .await?
             .context(format!("No valid snapshots found for {path:?}"))?;
 
         // Restore the content
-        let content = ForgeFS::read(&snapshot_path).await?;
-        ForgeFS::write(&path, content).await?;
+        Self::revert(&snapshot.path, &snapshot_path, created).await?;
 
         // Remove the used snapshot
         ForgeFS::remove_file(&snapshot_path).await?;
 
         Ok(())
     }
+
+    /// Restores every file that was changed since `since` to its content
+    /// before the first of these changes, deleting the files that were created
+    /// since then, and removes the snapshots that were taken since then.
+    /// Returns the paths of the restored files.
+    pub async fn restore_since(&self, since: Duration) -> Result<Vec<String>> {
+        // Snapshots are listed most recent first, so the last one of each path
+        // holds its content before the first change
+        let mut changes = BTreeMap::<String, Vec<Snapshot>>::new();
+        for snapshot in self.list().await? {
+            if snapshot.timestamp >= since {
+                changes
+                    .entry(snapshot.path.clone())
+                    .or_default()
+                    .push(snapshot);
+            }
+        }
+
+        for (path, snapshots) in &changes {
+            if let Some(first) = snapshots.last() {
+                let snapshot_path = first.snapshot_path(Some(self.snapshots_directory.clone()));
+                let _lock = ForgeFS::lock(path).await;
+                Self::revert(path, &snapshot_path, first.created).await?;
+            }
+            for snapshot in snapshots {
+                ForgeFS::remove_file(
+                    snapshot.snapshot_path(Some(self.snapshots_directory.clone())),
+                )
+                .await?;
+            }
+        }
+
+        Ok(changes.into_keys().collect())
+    }
 }
 
 #[cfg(test)]
@@ -92,7 +239,7 @@ mod tests {
     // Test helpers
     struct TestContext {
         _temp_dir: TempDir,
-        _snapshots_dir: PathBuf,
+        snapshots_dir: PathBuf,
         test_file: PathBuf,
         service: SnapshotService,
     }
@@ -106,7 +253,7 @@ mod tests {
 
             Ok(Self {
                 _temp_dir: temp_dir,
-                _snapshots_dir: snapshots_dir,
+                snapshots_dir,
                 test_file,
                 service,
             })
@@ -206,6 +353,128 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_list_and_find_snapshots() -> Result<()> {
+        // Arrange
+        let ctx = TestContext::new().await?;
+
+        // Act
+        ctx.write_content("Initial content").await?;
+        let first = ctx.create_snapshot().await?;
+        ctx.write_content("Second content").await?;
+        let second = ctx.create_snapshot().await?;
+
+        // Assert
+        let actual = ctx
+            .service
+            .list()
+            .await?
+            .into_iter()
+            .map(|snapshot| snapshot.id)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, vec![second.id.clone(), first.id.clone()]);
+
+        let found = ctx.service.find(&first.id.to_string()[..8]).await?;
+        let content = ctx.service.read(&found).await?;
+        assert_eq!(String::from_utf8(content)?, "Initial content");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore_since() -> Result<()> {
+        // Arrange
+        let ctx = TestContext::new().await?;
+        ctx.write_content("Before the session").await?;
+        ctx.create_snapshot().await?;
+        ctx.write_content("Initial content").await?;
+        let first = ctx.create_snapshot().await?;
+        ctx.write_content("Second content").await?;
+        ctx.create_snapshot().await?;
+        ctx.write_content("Final content").await?;
+
+        // Act
+        let actual = ctx.service.restore_since(first.timestamp).await?;
+
+        // Assert
+        assert_eq!(actual, vec![first.path.clone()]);
+        assert_eq!(ctx.read_content().await?, "Initial content");
+        assert_eq!(ctx.service.list().await?.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_legacy_snapshots() -> Result<()> {
+        // Arrange
+        let ctx = TestContext::new().await?;
+        ctx.write_content("Legacy content").await?;
+        let dir = ctx
+            .snapshots_dir
+            .join(Snapshot::create(ctx.test_file.clone()).await?.path_hash());
+        ForgeFS::create_dir_all(&dir).await?;
+        ForgeFS::write(
+            dir.join("2020-01-01_00-00-00-000000000.snap"),
+            "Initial content",
+        )
+        .await?;
+        ForgeFS::write(
+            dir.join("2020-01-02_00-00-00-000000000.snap"),
+            "Second content",
+        )
+        .await?;
+
+        // Act
+        ctx.undo_snapshot().await?;
+        let actual = ctx.service.list().await?;
+
+        // Assert
+        assert_eq!(ctx.read_content().await?, "Second content");
+        assert_eq!(actual.len(), 1);
+        assert_eq!(
+            String::from_utf8(ctx.service.read(&actual[0]).await?)?,
+            "Initial content"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_undo_creation() -> Result<()> {
+        // Arrange
+        let ctx = TestContext::new().await?;
+        ctx.service.record_creation(ctx.test_file.clone()).await?;
+        ctx.write_content("New content").await?;
+
+        // Act
+        ctx.undo_snapshot().await?;
+
+        // Assert
+        assert!(!ForgeFS::exists(&ctx.test_file));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore_since_deletes_created_files() -> Result<()> {
+        // Arrange
+        let ctx = TestContext::new().await?;
+        let created = ctx.service.record_creation(ctx.test_file.clone()).await?;
+        ctx.write_content("Initial content").await?;
+        ctx.create_snapshot().await?;
+        ctx.write_content("Final content").await?;
+
+        // Act
+        let actual = ctx.service.restore_since(created.timestamp).await?;
+
+        // Assert
+        assert_eq!(actual, vec![created.path.clone()]);
+        assert!(!ForgeFS::exists(&ctx.test_file));
+        assert!(ctx.service.list().await?.is_empty());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_multiple_snapshots_undo_twice() -> Result<()> {
         // Arrange
@@ -1,6 +1,6 @@
 use std::fmt::{Display, Formatter};
 use std::hash::Hasher;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use forge_fs::ForgeFS;
@@ -46,6 +46,16 @@ impl From<Uuid> for SnapshotId {
     }
 }
 
+/// Format of the timestamp at the start of a snapshot filename
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d_%H-%M-%S-%9f";
+
+/// Extension of the snapshots that hold the content of a file
+const CONTENT_EXTENSION: &str = "snap";
+
+/// Extension of the snapshots that record the creation of a file, which are
+/// empty
+const CREATION_EXTENSION: &str = "new";
+
 /// Represents information about a file snapshot
 ///
 /// Contains details about when the snapshot was created,
@@ -60,17 +70,23 @@ pub struct Snapshot {
 
     /// Original file path that is being processed
     pub path: String,
+
+    /// Whether the snapshot records the creation of the file, which didn't
+    /// exist before, instead of its content
+    #[serde(default)]
+    pub created: bool,
 }
 
 impl Snapshot {
     pub async fn create(path: PathBuf) -> anyhow::Result<Self> {
-        let path = path.canonicalize()?;
+        let path = canonicalize(&path)?;
         let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?;
 
         Ok(Self {
             id: SnapshotId::new(),
             timestamp,
             path: path.display().to_string(),
+            created: false,
         })
     }
 
@@ -87,10 +103,15 @@ impl Snapshot {
         let datetime = UNIX_EPOCH + self.timestamp;
         // Format: YYYY-MM-DD_HH-MM-SS-nnnnnnnnn (including nanoseconds)
         let formatted_time = chrono::DateTime::<chrono::Utc>::from(datetime)
-            .format("%Y-%m-%d_%H-%M-%S-%9f")
+            .format(TIMESTAMP_FORMAT)
             .to_string();
 
-        let filename = format!("{formatted_time}.snap");
+        let extension = if self.created {
+            CREATION_EXTENSION
+        } else {
+            CONTENT_EXTENSION
+        };
+        let filename = format!("{formatted_time}_{}.{extension}", self.id);
         let path = PathBuf::from(self.path_hash()).join(PathBuf::from(filename));
         if let Some(cwd) = cwd {
             cwd.join(path)
@@ -99,10 +120,118 @@ impl Snapshot {
         }
     }
 
+    /// Parses a snapshot from its filename, eg:
+    /// `2025-01-01_10-00-00-000000000_<id>.snap`, or `.new` for the creation
+    /// of a file. Returns `None` for files that don't follow this format.
+    pub fn from_filename(filename: &str, path: impl Into<String>) -> Option<Self> {
+        let (name, extension) = filename.rsplit_once('.')?;
+        let created = match extension {
+            CONTENT_EXTENSION => false,
+            CREATION_EXTENSION => true,
+            _ => return None,
+        };
+        let (time, id) = name.rsplit_once('_')?;
+        let id = SnapshotId::parse(id)?;
+        let datetime = chrono::NaiveDateTime::parse_from_str(time, TIMESTAMP_FORMAT)
+            .ok()?
+            .and_utc();
+        let timestamp = Duration::new(
+            u64::try_from(datetime.timestamp()).ok()?,
+            datetime.timestamp_subsec_nanos(),
+        );
+
+        Some(Self { id, timestamp, path: path.into(), created })
+    }
+
+    /// Names a snapshot that was named after its timestamp only, eg:
+    /// `2025-01-01_10-00-00-000000000.snap`, like the current snapshots, with
+    /// a new ID. Returns `None` for files that don't follow this format.
+    pub fn migrate_filename(filename: &str) -> Option<String> {
+        let time = filename.strip_suffix(&format!(".{CONTENT_EXTENSION}"))?;
+        chrono::NaiveDateTime::parse_from_str(time, TIMESTAMP_FORMAT).ok()?;
+        Some(format!("{time}_{}.{CONTENT_EXTENSION}", SnapshotId::new()))
+    }
+
     pub async fn save(&self, path: Option<PathBuf>) -> anyhow::Result<()> {
-        let content = ForgeFS::read(&self.path).await?;
+        let content = if self.created {
+            Vec::new()
+        } else {
+            ForgeFS::read(&self.path).await?
+        };
         let path = self.snapshot_path(path);
-        ForgeFS::write(path, content).await?;
+        ForgeFS::write_sealed(path, content).await?;
         Ok(())
     }
 }
+
+/// Canonicalizes the path of a file that may not exist, eg: that is about to
+/// be created or was deleted, through its directory
+fn canonicalize(path: &Path) -> anyhow::Result<PathBuf> {
+    match (path.canonicalize(), path.parent(), path.file_name()) {
+        (Ok(path), _, _) => Ok(path),
+        (Err(_), Some(parent), Some(name)) if !parent.as_os_str().is_empty() => {
+            Ok(parent.canonicalize()?.join(name))
+        }
+        (Err(err), _, _) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_from_filename_roundtrip() {
+        let fixture = Snapshot {
+            id: SnapshotId::new(),
+            timestamp: Duration::new(1_700_000_000, 123_456_789),
+            path: "/test/main.rs".to_string(),
+            created: false,
+        };
+        let filename = fixture.snapshot_path(None);
+        let filename = filename.file_name().unwrap().to_string_lossy();
+
+        let actual = Snapshot::from_filename(&filename, "/test/main.rs").unwrap();
+
+        assert_eq!(
+            (actual.id, actual.timestamp, actual.path),
+            (fixture.id, fixture.timestamp, fixture.path)
+        );
+    }
+
+    #[test]
+    fn test_from_filename_creation() {
+        let fixture = Snapshot {
+            id: SnapshotId::new(),
+            timestamp: Duration::new(1_700_000_000, 0),
+            path: "/test/new.rs".to_string(),
+            created: true,
+        };
+        let filename = fixture.snapshot_path(None);
+        let filename = filename.file_name().unwrap().to_string_lossy();
+
+        let actual = Snapshot::from_filename(&filename, "/test/new.rs").unwrap();
+
+        assert!(filename.ends_with(".new"));
+        assert!(actual.created);
+    }
+
+    #[test]
+    fn test_migrate_filename() {
+        let legacy = "2025-01-01_10-00-00-000000000.snap";
+
+        let actual = Snapshot::migrate_filename(legacy).unwrap();
+        let snapshot = Snapshot::from_filename(&actual, "/a").unwrap();
+
+        assert_eq!(snapshot.timestamp, Duration::new(1_735_725_600, 0));
+        assert_eq!(Snapshot::migrate_filename(&actual), None);
+    }
+
+    #[test]
+    fn test_from_filename_invalid() {
+        let actual = Snapshot::from_filename("2025-01-01_10-00-00-000000000.snap", "/a");
+        assert!(actual.is_none());
+    }
+}
@@ -2,6 +2,7 @@ use std::time::Instant;
 
 use anyhow::Result;
 use colored::Colorize;
+use forge_display::glyph::{self, Glyph};
 use indicatif::{ProgressBar, ProgressStyle};
 use rand::seq::SliceRandom;
 
@@ -52,7 +53,7 @@ impl SpinnerManager {
         // The {spinner} will show a visual spinner animation
         pb.set_style(
             ProgressStyle::default_spinner()
-                .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"])
+                .tick_strings(glyph::spinner_frames())
                 .template("{spinner:.green} {msg}")
                 .unwrap(),
         );
@@ -63,8 +64,9 @@ impl SpinnerManager {
 
         // Set the initial message
         let message = format!(
-            "{} 0s · {}",
+            "{} 0s {} {}",
             word.green().bold(),
+            Glyph::Dot,
             "Ctrl+C to interrupt".white().dimmed()
         );
         pb.set_message(message);
@@ -84,9 +86,10 @@ impl SpinnerManager {
 
             // Create a new message with the elapsed time
             let updated_message = format!(
-                "{} {}s · {}",
+                "{} {}s {} {}",
                 message.green().bold(),
                 seconds,
+                Glyph::Dot,
                 "Ctrl+C to interrupt".white().dimmed()
             );
 
@@ -98,6 +101,16 @@ impl SpinnerManager {
         Ok(())
     }
 
+    /// Replaces the message of the running spinner without resetting its
+    /// timer, starting a spinner if none is running
+    pub fn set_message(&mut self, message: &str) -> Result<()> {
+        if self.spinner.is_none() {
+            return self.start(Some(message));
+        }
+        self.message = Some(message.to_string());
+        self.update_time()
+    }
+
     /// Stop the active spinner if any
     pub fn stop(&mut self, message: Option<String>) -> Result<()> {
         if let Some(spinner) = self.spinner.take() {
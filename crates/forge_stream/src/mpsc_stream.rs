@@ -3,10 +3,12 @@ use std::future::Future;
 use futures::Stream;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 pub struct MpscStream<T> {
     join_handle: JoinHandle<()>,
     receiver: Receiver<T>,
+    cancellation: CancellationToken,
 }
 
 impl<T> MpscStream<T> {
@@ -14,9 +16,31 @@ impl<T> MpscStream<T> {
     where
         F: (FnOnce(Sender<T>) -> S) + Send + 'static,
         S: Future<Output = ()> + Send + 'static,
+    {
+        Self::spawn_with_cancellation(|tx, _| f(tx))
+    }
+
+    /// Spawns the task with a token that is cancelled when the stream is
+    /// cancelled or dropped, so that work the task hands off, eg: to other
+    /// tasks or processes, can stop too
+    pub fn spawn_with_cancellation<F, S>(f: F) -> MpscStream<T>
+    where
+        F: (FnOnce(Sender<T>, CancellationToken) -> S) + Send + 'static,
+        S: Future<Output = ()> + Send + 'static,
     {
         let (tx, rx) = tokio::sync::mpsc::channel(1);
-        MpscStream { join_handle: tokio::spawn(f(tx)), receiver: rx }
+        let cancellation = CancellationToken::new();
+        MpscStream {
+            join_handle: tokio::spawn(f(tx, cancellation.clone())),
+            receiver: rx,
+            cancellation,
+        }
+    }
+
+    /// Asks the task to stop, while still receiving the messages it sends
+    /// until it does
+    pub fn cancel(&self) {
+        self.cancellation.cancel();
     }
 }
 
@@ -35,6 +59,7 @@ impl<T> Drop for MpscStream<T> {
     fn drop(&mut self) {
         // Close the receiver to prevent any new messages
         self.receiver.close();
+        self.cancellation.cancel();
         self.join_handle.abort();
     }
 }
@@ -98,4 +123,31 @@ mod test {
             "Task should have been aborted"
         );
     }
+
+    #[tokio::test]
+    async fn test_drop_cancels_token() {
+        let (token_tx, token_rx) = tokio::sync::oneshot::channel();
+        let stream = MpscStream::<()>::spawn_with_cancellation(|_, token| async move {
+            let _ = token_tx.send(token.clone());
+            token.cancelled().await;
+        });
+        let token = token_rx.await.unwrap();
+
+        drop(stream);
+
+        assert!(token.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_lets_task_finish() {
+        let mut stream = MpscStream::spawn_with_cancellation(|tx, token| async move {
+            token.cancelled().await;
+            tx.send("cancelled").await.unwrap();
+        });
+
+        stream.cancel();
+
+        let actual = stream.next().await;
+        assert_eq!(actual, Some("cancelled"));
+    }
 }
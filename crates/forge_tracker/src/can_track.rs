@@ -17,6 +17,12 @@ pub fn can_track() -> bool {
     can_track_inner(is_prod, usage_enabled)
 }
 
+/// Checks if tracking is disabled with `FORGE_TRACKER=false`, which overrides
+/// the stored consent
+pub fn is_disabled_by_env() -> bool {
+    env::var(LONG_ENV_FILTER_VAR_NAME).is_ok_and(|v| v.eq_ignore_ascii_case("false"))
+}
+
 fn can_track_inner(is_prod_build: bool, usage_enabled: Option<bool>) -> bool {
     if let Some(usage_enabled) = usage_enabled {
         usage_enabled
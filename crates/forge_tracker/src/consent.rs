@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::Result;
+use crate::can_track::{can_track, is_disabled_by_env};
+
+/// Categories of events that can be shared. Prompts and their metadata are
+/// never collected, so they have no category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    /// Errors and diagnostics, eg: failed requests or `forge doctor --share`
+    Errors,
+    /// Usage metrics, eg: sessions and the names of the tools called
+    Usage,
+}
+
+impl Category {
+    /// Whether the category is compiled in, with the feature of the same name
+    pub const fn is_compiled(&self) -> bool {
+        match self {
+            Category::Errors => cfg!(feature = "errors"),
+            Category::Usage => cfg!(feature = "usage"),
+        }
+    }
+}
+
+/// Categories of events that the user consents to share, stored in
+/// `telemetry.json` of the forge directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Consent {
+    #[serde(default)]
+    pub errors: bool,
+    #[serde(default)]
+    pub usage: bool,
+}
+
+impl Consent {
+    /// Path of the stored consent, in the same directory as the other files
+    /// of forge
+    pub fn path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join("forge").join("telemetry.json"))
+    }
+
+    /// Consent in effect, ie: the stored one unless `FORGE_TRACKER=false`
+    /// disables every category
+    pub fn load() -> Self {
+        if is_disabled_by_env() {
+            Self::default()
+        } else {
+            Self::stored()
+        }
+    }
+
+    /// Consent stored in the config, defaulting to every category in release
+    /// builds
+    pub fn stored() -> Self {
+        let tracking = can_track();
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or(Self { errors: tracking, usage: tracking })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(path) = Self::path() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        }
+        Ok(())
+    }
+
+    pub fn set(&mut self, category: Category, enabled: bool) {
+        match category {
+            Category::Errors => self.errors = enabled,
+            Category::Usage => self.usage = enabled,
+        }
+    }
+
+    /// Whether events of the category are shared. Categories that aren't
+    /// compiled in are never shared.
+    pub fn allows(&self, category: Category) -> bool {
+        category.is_compiled()
+            && match category {
+                Category::Errors => self.errors,
+                Category::Usage => self.usage,
+            }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_allows() {
+        let mut fixture = Consent { errors: true, usage: true };
+        fixture.set(Category::Usage, false);
+
+        let actual = [
+            fixture.allows(Category::Errors),
+            fixture.allows(Category::Usage),
+        ];
+
+        let expected = [cfg!(feature = "errors"), false];
+        assert_eq!(actual, expected);
+    }
+}
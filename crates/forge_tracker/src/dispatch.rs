@@ -9,9 +9,8 @@ use tokio::sync::Mutex;
 use tokio::time::Duration;
 
 use super::Result;
-use crate::can_track::can_track;
 use crate::collect::{posthog, Collect};
-use crate::{Event, EventKind};
+use crate::{Consent, Event, EventKind};
 
 const POSTHOG_API_SECRET: &str = match option_env!("POSTHOG_API_SECRET") {
     Some(val) => val,
@@ -29,7 +28,7 @@ const DEFAULT_CLIENT_ID: &str = "<anonymous>";
 
 pub struct Tracker {
     collectors: Vec<Box<dyn Collect>>,
-    can_track: bool,
+    consent: Consent,
     start_time: DateTime<Utc>,
     email: Mutex<Option<Vec<String>>>,
 }
@@ -38,10 +37,9 @@ impl Default for Tracker {
     fn default() -> Self {
         let posthog_tracker = Box::new(posthog::Tracker::new(POSTHOG_API_SECRET));
         let start_time = Utc::now();
-        let can_track = can_track();
         Self {
             collectors: vec![posthog_tracker],
-            can_track,
+            consent: Consent::load(),
             start_time,
             email: Mutex::new(None),
         }
@@ -60,7 +58,8 @@ impl Tracker {
     }
 
     pub async fn dispatch(&'static self, event_kind: EventKind) -> Result<()> {
-        if self.can_track {
+        // Nothing about the event is collected unless its category is allowed
+        if self.consent.allows(event_kind.category()) {
             // Create a new event
             let event = Event {
                 event_name: event_kind.name(),
@@ -221,10 +220,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_tracker() {
-        if let Err(e) = TRACKER
-            .dispatch(EventKind::Prompt("ping".to_string()))
-            .await
-        {
+        if let Err(e) = TRACKER.dispatch(EventKind::Ping).await {
             panic!("Tracker dispatch error: {e:?}");
         }
     }
@@ -26,3 +26,11 @@ pub enum Error {
 }
 
 pub type Result<A> = std::result::Result<A, Error>;
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl std::error::Error for Error {}
@@ -2,8 +2,11 @@ use std::ops::Deref;
 
 use chrono::{DateTime, Utc};
 use convert_case::{Case, Casing};
+use derive_setters::Setters;
 use serde::{Deserialize, Serialize};
 
+use crate::Category;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Event {
     pub event_name: Name,
@@ -42,31 +45,124 @@ impl From<Name> for String {
     }
 }
 
+/// Version of the payload schemas, bumped when a field of a payload is
+/// removed or changes meaning so that consumers can tell the shapes apart
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Payload serialized with the version of its schema
+#[derive(Serialize)]
+struct Versioned<'a, T> {
+    schema_version: u32,
+    #[serde(flatten)]
+    payload: &'a T,
+}
+
+fn versioned<T: Serialize>(payload: &T) -> String {
+    serde_json::to_string(&Versioned { schema_version: SCHEMA_VERSION, payload })
+        .unwrap_or_default()
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct ToolCallPayload {
     tool_name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     cause: Option<String>,
+    /// Variant of the tool's description, when it is overridden
+    #[serde(skip_serializing_if = "Option::is_none")]
+    variant: Option<String>,
+    /// Turn that the tool was called in, to correlate the event with the logs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    turn_id: Option<String>,
 }
 
 impl ToolCallPayload {
     pub fn new(tool_name: String) -> Self {
-        Self { tool_name, cause: None }
+        Self { tool_name, cause: None, variant: None, turn_id: None }
     }
 
     pub fn with_cause(mut self, cause: String) -> Self {
         self.cause = Some(cause);
         self
     }
+
+    pub fn with_variant(mut self, variant: Option<String>) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    pub fn with_turn_id(mut self, turn_id: Option<String>) -> Self {
+        self.turn_id = turn_id;
+        self
+    }
+}
+
+/// A turn of the conversation that completed, ie: the agent answered the
+/// message of the user
+#[derive(Debug, Clone, Default, Serialize, Setters)]
+#[setters(strip_option, into)]
+pub struct TurnPayload {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    turn_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    total_tokens: u64,
+    duration_ms: u64,
+}
+
+/// An update of forge that succeeded
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdatePayload {
+    /// Version that performed the update
+    from_version: String,
+}
+
+impl UpdatePayload {
+    pub fn new(from_version: impl Into<String>) -> Self {
+        Self { from_version: from_version.into() }
+    }
+}
+
+/// A budget of the conversation that was used up, eg: the maximum turns of an
+/// agent
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetPayload {
+    budget: String,
+    limit: u64,
+}
+
+impl BudgetPayload {
+    pub fn new(budget: impl Into<String>, limit: u64) -> Self {
+        Self { budget: budget.into(), limit }
+    }
+}
+
+/// A conversation that was resumed from its checkpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionPayload {
+    /// Turns completed by the agents before the conversation was resumed
+    turns: u64,
+    events: usize,
+}
+
+impl SessionPayload {
+    pub fn new(turns: u64, events: usize) -> Self {
+        Self { turns, events }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum EventKind {
     Start,
     Ping,
-    ToolCall(ToolCallPayload),
-    Prompt(String),
+    ToolExecuted(ToolCallPayload),
+    TurnCompleted(TurnPayload),
+    UpdatePerformed(UpdatePayload),
+    BudgetExceeded(BudgetPayload),
+    SessionResumed(SessionPayload),
     Error(String),
+    Doctor(String),
 }
 
 impl EventKind {
@@ -74,18 +170,70 @@ impl EventKind {
         match self {
             Self::Start => Name::from("start".to_string()),
             Self::Ping => Name::from("ping".to_string()),
-            Self::Prompt(_) => Name::from("prompt".to_string()),
             Self::Error(_) => Name::from("error".to_string()),
-            Self::ToolCall(_) => Name::from("tool_call".to_string()),
+            Self::ToolExecuted(_) => Name::from("tool_executed".to_string()),
+            Self::TurnCompleted(_) => Name::from("turn_completed".to_string()),
+            Self::UpdatePerformed(_) => Name::from("update_performed".to_string()),
+            Self::BudgetExceeded(_) => Name::from("budget_exceeded".to_string()),
+            Self::SessionResumed(_) => Name::from("session_resumed".to_string()),
+            Self::Doctor(_) => Name::from("doctor".to_string()),
         }
     }
     pub fn value(&self) -> String {
         match self {
             Self::Start => "".to_string(),
             Self::Ping => "".to_string(),
-            Self::Prompt(content) => content.to_string(),
             Self::Error(content) => content.to_string(),
-            Self::ToolCall(payload) => serde_json::to_string(&payload).unwrap_or_default(),
+            Self::ToolExecuted(payload) => versioned(payload),
+            Self::TurnCompleted(payload) => versioned(payload),
+            Self::UpdatePerformed(payload) => versioned(payload),
+            Self::BudgetExceeded(payload) => versioned(payload),
+            Self::SessionResumed(payload) => versioned(payload),
+            Self::Doctor(report) => report.to_string(),
         }
     }
+
+    /// Category that the user must consent to for the event to be shared
+    pub fn category(&self) -> Category {
+        match self {
+            Self::Start
+            | Self::Ping
+            | Self::ToolExecuted(_)
+            | Self::TurnCompleted(_)
+            | Self::UpdatePerformed(_)
+            | Self::BudgetExceeded(_)
+            | Self::SessionResumed(_) => Category::Usage,
+            Self::Error(_) | Self::Doctor(_) => Category::Errors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_value_is_versioned() {
+        let fixture = EventKind::TurnCompleted(
+            TurnPayload::default()
+                .model("gpt-4.1")
+                .total_tokens(120u64)
+                .duration_ms(800u64),
+        );
+
+        let actual: serde_json::Value = serde_json::from_str(&fixture.value()).unwrap();
+
+        let expected = json!({
+            "schema_version": SCHEMA_VERSION,
+            "model": "gpt-4.1",
+            "prompt_tokens": 0,
+            "completion_tokens": 0,
+            "total_tokens": 120,
+            "duration_ms": 800
+        });
+        assert_eq!(actual, expected);
+    }
 }
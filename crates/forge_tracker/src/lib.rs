@@ -1,11 +1,16 @@
 mod can_track;
 mod collect;
+mod consent;
 mod dispatch;
 mod error;
 mod event;
 mod log;
 pub use can_track::VERSION;
+pub use consent::{Category, Consent};
 pub use dispatch::Tracker;
 use error::Result;
-pub use event::{Event, EventKind, ToolCallPayload};
+pub use event::{
+    BudgetPayload, Event, EventKind, SessionPayload, ToolCallPayload, TurnPayload, UpdatePayload,
+    SCHEMA_VERSION,
+};
 pub use log::{init_tracing, Guard};
@@ -0,0 +1,77 @@
+use std::path::Path;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// Name of the files that exclude paths from the agent, with the syntax of
+/// `.gitignore`. Unlike `.gitignore`, they also apply to committed files, eg:
+/// contracts that must never be sent to a provider.
+pub const FORGE_IGNORE: &str = ".forgeignore";
+
+/// Rules of the `.forgeignore` files of a workspace and its parents
+#[derive(Debug, Clone, Default)]
+pub struct ForgeIgnore {
+    matchers: Vec<Gitignore>,
+}
+
+impl ForgeIgnore {
+    /// Loads the `.forgeignore` files of `cwd` and its parents. Malformed
+    /// lines are skipped.
+    pub fn find(cwd: &Path) -> Self {
+        let matchers = cwd
+            .ancestors()
+            .map(|dir| dir.join(FORGE_IGNORE))
+            .filter(|file| file.is_file())
+            .filter_map(|file| {
+                let mut builder = GitignoreBuilder::new(file.parent()?);
+                builder.add(&file);
+                builder.build().ok()
+            })
+            .collect();
+        Self { matchers }
+    }
+
+    /// Whether an absolute path, or one of its parents, is excluded
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        self.matchers.iter().any(|matcher| {
+            path.starts_with(matcher.path())
+                && matcher
+                    .matched_path_or_any_parents(path, path.is_dir())
+                    .is_ignore()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_is_ignored() {
+        let fixture = tempfile::tempdir().unwrap();
+        let root = fixture.path();
+        std::fs::create_dir_all(root.join("docs/contracts")).unwrap();
+        std::fs::create_dir_all(root.join("app")).unwrap();
+        std::fs::write(
+            root.join(FORGE_IGNORE),
+            "docs/contracts/\n*.pem\n!public.pem\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("app").join(FORGE_IGNORE), "local.env\n").unwrap();
+
+        let ignore = ForgeIgnore::find(&root.join("app"));
+        let actual = [
+            "docs/contracts/acme.pdf",
+            "docs/guide.md",
+            "app/key.pem",
+            "app/public.pem",
+            "app/local.env",
+            "local.env",
+        ]
+        .map(|path| ignore.is_ignored(&root.join(path)));
+
+        let expected = [true, false, true, false, true, false];
+        assert_eq!(actual, expected);
+    }
+}
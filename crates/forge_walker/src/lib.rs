@@ -1,3 +1,5 @@
+mod forge_ignore;
 mod walker;
 
+pub use forge_ignore::{ForgeIgnore, FORGE_IGNORE};
 pub use walker::{File, Walker};
@@ -6,6 +6,8 @@ use derive_setters::Setters;
 use ignore::WalkBuilder;
 use tokio::task::spawn_blocking;
 
+use crate::FORGE_IGNORE;
+
 #[derive(Clone, Debug)]
 pub struct File {
     pub path: String,
@@ -118,6 +120,7 @@ impl Walker {
             .git_global(true) // Use global gitignore
             .git_ignore(true) // Use local .gitignore
             .ignore(true) // Use .ignore files
+            .add_custom_ignore_filename(FORGE_IGNORE) // Use .forgeignore files
             .max_depth(Some(self.max_depth))
             // TODO: use build_parallel() for better performance
             .build();
@@ -310,6 +313,28 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_walker_respects_forge_ignore() {
+        let fixture =
+            fixtures::create_sized_files(&[("text.txt".into(), 10), ("secret.txt".into(), 10)])
+                .unwrap();
+        fs::write(fixture.path().join(FORGE_IGNORE), "secret.txt\n").unwrap();
+
+        let actual = Walker::min_all()
+            .cwd(fixture.path().to_path_buf())
+            .get()
+            .await
+            .unwrap();
+
+        let expected = vec!["text.txt"];
+        let actual_files: Vec<_> = actual
+            .iter()
+            .filter(|f| !f.is_dir())
+            .map(|f| f.path.as_str())
+            .collect();
+        assert_eq!(actual_files, expected);
+    }
+
     #[tokio::test]
     async fn test_walker_enforces_directory_breadth_limit() {
         let (fixture, _) =